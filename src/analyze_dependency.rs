@@ -1,37 +1,57 @@
 use crate::analyze_utils::{
     calculate_table_column_widths, collect_matching_spans, draw_clickable_right_aligned_text_cell,
-    draw_left_aligned_text_cell, process_spans_for_analysis, span_search_ui,
-    span_selection_list_ui, Statistics,
+    draw_left_aligned_text_cell, is_span_name_pattern, process_spans_for_analysis,
+    retain_spans_in_time_range, selected_time_range_ui, span_search_ui, span_selection_list_ui,
+    time_range_to_display_string, ModalFocus, SpanNameMatcher, Statistics,
+};
+use crate::background_task::BackgroundTask;
+use crate::charts::{
+    cumulative_distribution, draw_chart, draw_histogram, histogram, ChartAxis, ChartSeries,
+};
+use crate::clipboard::copy_button;
+use crate::colors::{node_color, theme};
+use crate::dependency_link_table::{DependencyLinkTable, LinkTableAction};
+use crate::node_filter::NodeFilter;
+use crate::relation::{
+    ArrowStyle, AttributeRelation, AttributeRelationOp, MatchType, Relation, RelationNodesConfig,
+};
+use crate::report::{ReportBlock, ReportChart, ReportSection, ReportSeries};
+use crate::span_id::SpanId;
+use crate::structured_modes::{MatchCondition, MatchOperator, SpanSelector};
+use crate::types::{format_ms_value, MILLISECONDS_PER_SECOND};
+use crate::types::{
+    time_point_to_utc_string, value_to_text, Span, SpanData, TimeFormat, TimePoint,
 };
-use crate::colors;
-use crate::types::Span;
-use crate::types::MILLISECONDS_PER_SECOND;
 use eframe::egui::{
-    self, Button, ComboBox, Grid, Id, Layout, Modal, RichText, ScrollArea, TextEdit, Ui, Vec2,
+    self, Button, CollapsingHeader, ComboBox, Grid, Id, Layout, Modal, ProgressBar, RichText,
+    ScrollArea, TextEdit, Ui, Vec2,
 };
 use opentelemetry_proto::tonic::common::v1::any_value::Value;
 use regex;
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Deref;
 use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Instant;
+use uuid::Uuid;
 
 /// Structure to represent a dependency link between spans.
 #[derive(Clone)]
-pub struct DependencyLink {
-    pub source_spans: Vec<Rc<Span>>,
-    pub target_spans: Vec<Rc<Span>>,
+pub struct DependencyLink<S = Rc<Span>> {
+    pub source_spans: Vec<S>,
+    pub target_spans: Vec<S>,
     pub delay_seconds: f64,
 }
 
 /// Holds statistics and a list of identified dependency links where the target span resides on a specific node.
-pub struct NodeDependencyMetrics {
+pub struct NodeDependencyMetrics<S = Rc<Span>> {
     pub link_delay_statistics: Statistics,
-    pub links: Vec<DependencyLink>,
-    pub min_delay_link: Option<DependencyLink>,
-    pub max_delay_link: Option<DependencyLink>,
+    pub links: Vec<DependencyLink<S>>,
+    pub min_delay_link: Option<DependencyLink<S>>,
+    pub max_delay_link: Option<DependencyLink<S>>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[derive(Debug, PartialEq, Eq, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub enum SourceScope {
     #[default]
     SameNode,
@@ -48,7 +68,7 @@ impl std::fmt::Display for SourceScope {
 }
 
 /// Defines the strategy for selecting source spans when multiple are available.
-#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[derive(Debug, PartialEq, Eq, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub enum SourceTimingStrategy {
     #[default]
     EarliestFirst,
@@ -65,7 +85,7 @@ impl std::fmt::Display for SourceTimingStrategy {
 }
 
 /// Defines how the link delay is calculated when source spans are grouped.
-#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[derive(Debug, PartialEq, Eq, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub enum GroupAggregationStrategy {
     /// Link delay is based on the latest end time among all selected source spans from all groups.
     WaitForLastGroup,
@@ -83,8 +103,42 @@ impl std::fmt::Display for GroupAggregationStrategy {
     }
 }
 
+/// Links spans by the causality recorded in the span context, in addition to the linking attributes.
+#[derive(Debug, PartialEq, Eq, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub enum ContextLinking {
+    #[default]
+    None,
+    /// Source and target spans must have the same trace_id.
+    SameTrace,
+    /// One of the spans must be the parent of the other.
+    ParentChild,
+}
+
+impl ContextLinking {
+    fn matches(&self, source_span: &SpanData, target_span: &SpanData) -> bool {
+        match self {
+            ContextLinking::None => true,
+            ContextLinking::SameTrace => source_span.trace_id == target_span.trace_id,
+            ContextLinking::ParentChild => {
+                target_span.parent_span_id == source_span.span_id
+                    || source_span.parent_span_id == target_span.span_id
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ContextLinking {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContextLinking::None => write!(f, "none"),
+            ContextLinking::SameTrace => write!(f, "same trace"),
+            ContextLinking::ParentChild => write!(f, "parent/child"),
+        }
+    }
+}
+
 /// Defines the analysis cardinality mode.
-#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[derive(Debug, PartialEq, Eq, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub enum AnalysisCardinality {
     /// N-to-1: Find N source spans for each target span (existing mode).
     #[default]
@@ -107,6 +161,7 @@ pub struct DependencyAnalysisResult {
     pub target_span_name: String,
     pub threshold: usize,
     pub linking_attribute: String,
+    pub context_linking: ContextLinking,
     pub source_scope: SourceScope,
     pub source_timing_strategy: SourceTimingStrategy,
     pub group_by_attribute: String,
@@ -114,11 +169,442 @@ pub struct DependencyAnalysisResult {
     pub analysis_cardinality: AnalysisCardinality,
     pub per_node_results: HashMap<String, NodeDependencyMetrics>,
     pub analysis_duration_ms: u128,
+    /// The time range the analysis was restricted to, `None` when the whole trace was analyzed.
+    pub time_range: Option<(TimePoint, TimePoint)>,
     pub overall_stats: Statistics,
     pub overall_min_delay_link: Option<DependencyLink>,
     pub overall_max_delay_link: Option<DependencyLink>,
 }
 
+/// Links whose target span starts in `start..end`, part of the delay trend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelayTrendBucket {
+    pub start: TimePoint,
+    pub end: TimePoint,
+    pub count: usize,
+    pub mean_seconds: f64,
+    pub p99_seconds: f64,
+}
+
+impl DependencyAnalysisResult {
+    /// Splits the time between the first and last target span start into `bucket_count` buckets
+    /// and computes the delay statistics of the links in each. Buckets without links are skipped.
+    pub fn delay_trend(
+        &self,
+        node_name: Option<&str>,
+        bucket_count: usize,
+    ) -> Vec<DelayTrendBucket> {
+        let links: Vec<&DependencyLink> = self
+            .per_node_results
+            .iter()
+            .filter(|(name, _)| node_name.is_none_or(|node_name| node_name == name.as_str()))
+            .flat_map(|(_, metrics)| &metrics.links)
+            .filter(|link| !link.target_spans.is_empty())
+            .collect();
+        let link_time = |link: &DependencyLink| link.target_spans[0].start_time;
+        let Some(first) = links.iter().map(|link| link_time(link)).reduce(f64::min) else {
+            return Vec::new();
+        };
+        let last = links
+            .iter()
+            .map(|link| link_time(link))
+            .fold(first, f64::max);
+        let bucket_count = bucket_count.max(1);
+        let width = (last - first) / bucket_count as f64;
+
+        let mut stats: Vec<Statistics> = (0..bucket_count).map(|_| Statistics::new()).collect();
+        for link in &links {
+            let index = if width > 0.0 {
+                (((link_time(link) - first) / width) as usize).min(bucket_count - 1)
+            } else {
+                0
+            };
+            stats[index].add_value(link.delay_seconds);
+        }
+        stats
+            .into_iter()
+            .enumerate()
+            .filter(|(_, stats)| stats.count > 0)
+            .map(|(i, stats)| DelayTrendBucket {
+                start: first + i as f64 * width,
+                end: first + (i + 1) as f64 * width,
+                count: stats.count,
+                mean_seconds: stats.mean(),
+                p99_seconds: stats.percentile(99.0),
+            })
+            .collect()
+    }
+
+    /// Delays of all links on the node, or on all nodes when `node_name` is `None`.
+    pub fn delays_seconds(&self, node_name: Option<&str>) -> &[f64] {
+        match node_name {
+            None => &self.overall_stats.data_points,
+            Some(node_name) => self
+                .per_node_results
+                .get(node_name)
+                .map(|metrics| metrics.link_delay_statistics.data_points.as_slice())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Describes the analysis parameters, the description can be parsed back by the quick setup.
+    pub fn description(&self) -> String {
+        let context_linking = match self.context_linking {
+            ContextLinking::None => String::new(),
+            ref context_linking => format!(", context linking: {context_linking}"),
+        };
+        format!(
+            "Analysis of dependency: '{}' -> '{}' (cardinality: {}, threshold: {}, linking by: {}, group by: {}, scope: {}, timing: {}, group aggregation: {}{})",
+            self.source_span_name,
+            self.target_span_name,
+            self.analysis_cardinality,
+            self.threshold,
+            if self.linking_attribute.is_empty() { "none" } else { &self.linking_attribute },
+            if self.group_by_attribute.is_empty() { "none" } else { &self.group_by_attribute },
+            self.source_scope,
+            self.source_timing_strategy,
+            self.group_aggregation_strategy,
+            context_linking
+        )
+    }
+
+    /// The results as a report section: the per node delay statistics, the distribution of the
+    /// delays and their trend over time.
+    pub fn report_section(&self, time_format: &TimeFormat) -> ReportSection {
+        let row = |label: &str, stats: &Statistics| {
+            if stats.count == 0 {
+                return vec![label.to_string(), "0".to_string()];
+            }
+            vec![
+                label.to_string(),
+                stats.count.to_string(),
+                format_ms_value(stats.min),
+                format_ms_value(stats.max),
+                format_ms_value(stats.mean()),
+                format_ms_value(stats.median()),
+                format_ms_value(stats.percentile(99.0)),
+            ]
+        };
+        let mut node_names: Vec<&String> = self.per_node_results.keys().collect();
+        node_names.sort();
+        let mut rows: Vec<Vec<String>> = node_names
+            .into_iter()
+            .map(|node_name| {
+                row(
+                    node_name,
+                    &self.per_node_results[node_name].link_delay_statistics,
+                )
+            })
+            .collect();
+        rows.push(row("All Nodes", &self.overall_stats));
+
+        let mut text = self.description();
+        if let Some(time_range) = self.time_range {
+            text.push_str(&format!(
+                " (Time range: {})",
+                time_range_to_display_string(time_range, time_format)
+            ));
+        }
+        let mut blocks = vec![
+            ReportBlock::Text(text),
+            ReportBlock::Table {
+                headers: [
+                    "Node",
+                    "Links",
+                    "Min (ms)",
+                    "Max (ms)",
+                    "Mean (ms)",
+                    "Median (ms)",
+                    "P99 (ms)",
+                ]
+                .map(String::from)
+                .to_vec(),
+                rows,
+            },
+        ];
+
+        let delays_ms: Vec<f64> = self
+            .overall_stats
+            .data_points
+            .iter()
+            .map(|delay| delay * MILLISECONDS_PER_SECOND)
+            .collect();
+        if !delays_ms.is_empty() {
+            blocks.push(ReportBlock::Chart {
+                title: "Distribution of the link delays".to_string(),
+                chart: ReportChart::Histogram {
+                    x_label: "Delay (ms)".to_string(),
+                    buckets: histogram(&delays_ms, DELAY_HISTOGRAM_BUCKETS),
+                },
+            });
+        }
+        let trend = self.delay_trend(None, DELAY_TREND_BUCKETS);
+        if let Some(first) = trend.first() {
+            let series = |name: &str, value: fn(&DelayTrendBucket) -> f64| ReportSeries {
+                name: name.to_string(),
+                points: trend
+                    .iter()
+                    .map(|bucket| {
+                        (
+                            bucket.start - first.start,
+                            value(bucket) * MILLISECONDS_PER_SECOND,
+                        )
+                    })
+                    .collect(),
+            };
+            blocks.push(ReportBlock::Chart {
+                title: "Link delays over time".to_string(),
+                chart: ReportChart::Lines {
+                    x_label: format!("Seconds since {}", time_point_to_utc_string(first.start)),
+                    y_label: "Delay (ms)".to_string(),
+                    series: vec![
+                        series("mean", |bucket| bucket.mean_seconds),
+                        series("p99", |bucket| bucket.p99_seconds),
+                    ],
+                },
+            });
+        }
+
+        ReportSection {
+            title: format!(
+                "Dependency analysis: {} -> {}",
+                self.source_span_name, self.target_span_name
+            ),
+            blocks,
+        }
+    }
+}
+
+/// A named analysis configuration, saved in the persistent data so that it doesn't have to be
+/// entered again.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DependencyPreset {
+    pub name: String,
+    pub source_span_name: String,
+    pub target_span_name: String,
+    pub threshold: usize,
+    pub linking_attribute: String,
+    #[serde(default)]
+    pub context_linking: ContextLinking,
+    pub group_by_attribute: String,
+    pub source_scope: SourceScope,
+    pub source_timing_strategy: SourceTimingStrategy,
+    pub group_aggregation_strategy: GroupAggregationStrategy,
+    pub analysis_cardinality: AnalysisCardinality,
+}
+
+/// Selector of the spans which the analysis selects by `span_name`, a name or a pattern.
+fn span_name_selector(span_name: &str) -> Result<SpanSelector, String> {
+    if !is_span_name_pattern(span_name) {
+        return Ok(SpanSelector::new_equal_name(span_name));
+    }
+    let matcher = SpanNameMatcher::parse(span_name)
+        .map_err(|e| format!("invalid span name pattern '{span_name}': {e}"))?;
+    Ok(SpanSelector {
+        span_name_condition: MatchCondition {
+            operator: MatchOperator::Regex,
+            value: matcher.to_regex_string(),
+        },
+        ..SpanSelector::new_equal_name(span_name)
+    })
+}
+
+/// Converts an analysis configuration to a [Relation] which draws arrows between the source and
+/// target spans. The dependency analysis and relations overlap: both link spans by name, attributes
+/// and nodes, but the analysis computes statistics once, while relations are drawn in the view.
+/// Not everything can be converted:
+/// * Span name patterns (globs and regexes) become regex conditions on the span name
+/// * Linking attributes become attribute relations: `attr` and `attr=+0` match equal values,
+///   `attr=+1` matches a target value one greater than the source value. Other offsets,
+///   tolerances and case-insensitive patterns fail
+/// * The threshold of N-to-1 analyses becomes the number of required "from" spans (fan-in),
+///   1-to-N analyses with a threshold above 1 fail
+/// * Linking by the span context fails, relations only look at the attributes
+/// * Group by attribute and the timing strategies don't have a counterpart and are ignored
+pub fn dependency_to_relation(preset: &DependencyPreset) -> Result<Relation, String> {
+    if preset.context_linking != ContextLinking::None {
+        return Err(format!(
+            "context linking '{}' can't be a relation",
+            preset.context_linking
+        ));
+    }
+    let mut attribute_relations = Vec::new();
+    for pattern in parse_linking_patterns(&preset.linking_attribute) {
+        if pattern.tolerance.is_some() || pattern.case_insensitive {
+            return Err(format!(
+                "linking attribute '{}': tolerance and case-insensitive matching are not supported",
+                pattern.attribute
+            ));
+        }
+        let op = match pattern.offset {
+            None | Some(0.0) => AttributeRelationOp::Equal,
+            Some(1.0) => AttributeRelationOp::OneGreater,
+            Some(_) => {
+                return Err(format!(
+                    "linking attribute '{}': only offsets +0 and +1 are supported",
+                    pattern.attribute
+                ))
+            }
+        };
+        attribute_relations.push(AttributeRelation {
+            from_attribute: pattern.attribute.clone(),
+            to_attribute: pattern.attribute,
+            relation: op,
+        });
+    }
+
+    let (required_sources, match_type) = match preset.analysis_cardinality {
+        AnalysisCardinality::NToOne => (preset.threshold.max(1), MatchType::MatchAll),
+        AnalysisCardinality::OneToN if preset.threshold <= 1 => (1, MatchType::MatchClosest),
+        AnalysisCardinality::OneToN => {
+            return Err("1-to-N analyses with a threshold above 1 can't be a relation".to_string())
+        }
+    };
+
+    Ok(Relation {
+        id: Uuid::new_v4(),
+        name: preset.name.clone(),
+        description: format!(
+            "Made from a dependency analysis of '{}' -> '{}'",
+            preset.source_span_name, preset.target_span_name
+        ),
+        from_span_selector: span_name_selector(&preset.source_span_name)?,
+        to_span_selector: span_name_selector(&preset.target_span_name)?,
+        attribute_relations,
+        max_time_diff: Some(10.0),
+        nodes_config: match preset.source_scope {
+            SourceScope::SameNode => RelationNodesConfig::SameNode,
+            SourceScope::AllNodes => RelationNodesConfig::AllNodes,
+        },
+        match_type,
+        min_time_diff: 0.0,
+        required_sources,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
+        is_builtin: false,
+    })
+}
+
+/// One comma-separated part of the linking attribute, which says how the values of an attribute
+/// must relate for a source and target span to be linked. Supported forms:
+/// * `height` - the values are equal
+/// * `height=+1`, `height+1`, `height-2` - the target value is the source value plus the offset
+/// * `height~0.5`, `height+1~0.5` - numeric comparison, the values may differ by the tolerance
+/// * `chain_id/i` - the values are equal when compared as case-insensitive text
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkingPattern {
+    pub attribute: String,
+    /// Offset added to the source value before comparing it with the target value.
+    pub offset: Option<f64>,
+    /// Maximal difference of numeric values which are still considered equal.
+    pub tolerance: Option<f64>,
+    pub case_insensitive: bool,
+}
+
+/// Tolerance of numeric comparisons for which no tolerance was specified.
+const DEFAULT_NUMERIC_TOLERANCE: f64 = 0.0001;
+
+impl LinkingPattern {
+    pub fn parse(pattern: &str) -> LinkingPattern {
+        let mut rest = pattern.trim();
+        let mut case_insensitive = false;
+        if let Some(stripped) = rest.strip_suffix("/i") {
+            rest = stripped.trim_end();
+            case_insensitive = true;
+        }
+        let mut tolerance = None;
+        if let Some((before, tolerance_str)) = rest.rsplit_once('~') {
+            if let Ok(value) = tolerance_str.trim().parse::<f64>() {
+                rest = before.trim_end();
+                tolerance = Some(value.abs());
+            }
+        }
+        let (attribute, offset) = match rest.split_once('=') {
+            // An invalid offset is ignored, the values are compared as they are.
+            Some((attribute, offset_str)) => (attribute, parse_offset(offset_str.trim())),
+            None => match rest.rfind(['+', '-']) {
+                Some(sign_pos) if sign_pos > 0 => match parse_offset(&rest[sign_pos..]) {
+                    Some(offset) => (&rest[..sign_pos], Some(offset)),
+                    None => (rest, None),
+                },
+                _ => (rest, None),
+            },
+        };
+        LinkingPattern {
+            attribute: attribute.trim().to_string(),
+            offset,
+            tolerance,
+            case_insensitive,
+        }
+    }
+
+    /// Checks whether the attribute values of a source and target span satisfy the pattern.
+    /// Both spans must have the attribute.
+    pub fn matches(
+        &self,
+        source_attributes: &BTreeMap<String, Option<Value>>,
+        target_attributes: &BTreeMap<String, Option<Value>>,
+    ) -> bool {
+        let (Some(source_value), Some(target_value)) = (
+            source_attributes.get(&self.attribute),
+            target_attributes.get(&self.attribute),
+        ) else {
+            return false;
+        };
+
+        if self.offset.is_some() || self.tolerance.is_some() {
+            let (Some(source_num), Some(target_num)) = (
+                extract_numeric_value(source_value),
+                extract_numeric_value(target_value),
+            ) else {
+                return false;
+            };
+            let expected_target = source_num + self.offset.unwrap_or(0.0);
+            let tolerance = self.tolerance.unwrap_or(DEFAULT_NUMERIC_TOLERANCE);
+            return (target_num - expected_target).abs() <= tolerance;
+        }
+
+        if self.case_insensitive {
+            return value_to_text(source_value).to_lowercase()
+                == value_to_text(target_value).to_lowercase();
+        }
+
+        source_value == target_value
+    }
+}
+
+/// Parses the comma-separated linking attribute, empty parts are skipped.
+pub fn parse_linking_patterns(linking_attribute: &str) -> Vec<LinkingPattern> {
+    linking_attribute
+        .split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(LinkingPattern::parse)
+        .collect()
+}
+
+/// Parses a relative offset string like "+1", "-2", "+0"
+fn parse_offset(offset_str: &str) -> Option<f64> {
+    if let Some(stripped) = offset_str.strip_prefix('+') {
+        stripped.parse::<f64>().ok()
+    } else if let Some(stripped) = offset_str.strip_prefix('-') {
+        stripped.parse::<f64>().ok().map(|v| -v)
+    } else {
+        // Allow plain numbers (treat as positive offset)
+        offset_str.parse::<f64>().ok()
+    }
+}
+
+/// Extracts a numeric value from an OpenTelemetry attribute value
+fn extract_numeric_value(value: &Option<Value>) -> Option<f64> {
+    match value {
+        Some(Value::IntValue(i)) => Some(*i as f64),
+        Some(Value::DoubleValue(d)) => Some(*d),
+        Some(Value::StringValue(s)) => s.parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
 /// Information needed to display the dependency link details popup.
 pub struct LinkDetailsPopupInfo {
     pub link: DependencyLink,
@@ -147,6 +633,8 @@ pub struct AnalyzeDependencyModal {
     threshold_edit_str: String,
     /// Optional attribute name used to match source and target spans for linking.
     linking_attribute: String,
+    /// Linking by the trace_id or parent span id of the spans.
+    context_linking: ContextLinking,
     /// Scope for selecting source spans: "self" (same node as target) or "all nodes".
     source_scope: SourceScope,
     /// Strategy for selecting source spans (earliest or latest).
@@ -169,12 +657,46 @@ pub struct AnalyzeDependencyModal {
     all_spans_for_analysis: Vec<Rc<Span>>,
     /// If set, indicates a specific node to focus on in the trace view after closing the modal.
     pub focus_node: Option<String>,
+    pub focus: ModalFocus,
     /// If set, shows a popup with details of a specific dependency link.
     show_link_details_popup: Option<LinkDetailsPopupInfo>,
     /// Input for parsing analysis descriptions.
     description_input: String,
+    /// Analysis which is still running in the background.
+    running_analysis: Option<RunningAnalysis>,
+    /// Description of the last analysis that finished, taken by the app to record macros.
+    pub completed_analysis: Option<String>,
+    /// Relation made from the current configuration by "Save as relation", the app should add it
+    /// to the defined relations.
+    pub saved_relation: Option<Relation>,
+    /// Set when "Export links" was clicked, the app should save the links of `analysis_result` and
+    /// reset it.
+    pub export_links_requested: bool,
+    /// Results which should be added to the report, taken by the app.
+    pub report_section: Option<ReportSection>,
+    /// Set when a preset was saved or deleted, the app should save its presets and reset it.
+    pub presets_changed: bool,
+    /// Name entered for a new preset.
+    preset_name_input: String,
+    /// Sort order of the per-node tables listing every formed link.
+    link_table: DependencyLinkTable,
+    /// Node whose delays are shown in the delay distribution, `None` shows all nodes.
+    distribution_node: Option<String>,
+    /// Whether the cumulative distribution of the delays is shown below the histogram.
+    show_delay_cdf: bool,
+    /// Node whose delays are shown in the delay trend, `None` shows all nodes.
+    trend_node: Option<String>,
+    /// Whether only the spans intersecting `selected_time_range` are analyzed.
+    only_selected_time_range: bool,
+    /// The time range selected on the timeline, set by the app before showing the modal.
+    pub selected_time_range: (TimePoint, TimePoint),
 }
 
+/// Number of buckets in the histogram of link delays.
+const DELAY_HISTOGRAM_BUCKETS: usize = 30;
+/// Number of time buckets in the trend of link delays.
+const DELAY_TREND_BUCKETS: usize = 20;
+
 type PreparedAnalysisInput = (
     String,
     String,
@@ -183,7 +705,7 @@ type PreparedAnalysisInput = (
     Option<HashSet<String>>,
 );
 
-type NodeSpanMap = HashMap<String, Vec<Rc<Span>>>;
+type NodeSpanMap = HashMap<String, Vec<WorkSpan>>;
 
 impl AnalyzeDependencyModal {
     pub fn new() -> Self {
@@ -205,8 +727,103 @@ impl AnalyzeDependencyModal {
         self.spans_processed = true;
     }
 
+    /// Opens the modal with `span_name` chosen as the source span, only the target has to be picked.
+    /// Only the spans of nodes shown by `node_filter` are analyzed.
+    pub fn open_for_source_span(
+        &mut self,
+        spans_for_analysis: &[Rc<Span>],
+        span_name: &str,
+        node_filter: Option<&NodeFilter>,
+    ) {
+        match node_filter {
+            Some(node_filter) => {
+                let shown_spans: Vec<Rc<Span>> = spans_for_analysis
+                    .iter()
+                    .filter(|span| node_filter.should_show_span(&span.node.name))
+                    .cloned()
+                    .collect();
+                self.open(&shown_spans);
+            }
+            None => self.open(spans_for_analysis),
+        }
+        self.source_span_name = Some(span_name.to_string());
+        self.source_search_text = span_name.to_string();
+    }
+
+    /// The current configuration as a preset, `None` when the source or target span isn't chosen.
+    pub fn current_preset(&self, name: &str) -> Option<DependencyPreset> {
+        Some(DependencyPreset {
+            name: name.to_string(),
+            source_span_name: self.source_span_name.clone()?,
+            target_span_name: self.target_span_name.clone()?,
+            threshold: self.threshold,
+            linking_attribute: self.linking_attribute.clone(),
+            context_linking: self.context_linking.clone(),
+            group_by_attribute: self.group_by_attribute.clone(),
+            source_scope: self.source_scope.clone(),
+            source_timing_strategy: self.source_timing_strategy.clone(),
+            group_aggregation_strategy: self.group_aggregation_strategy.clone(),
+            analysis_cardinality: self.analysis_cardinality.clone(),
+        })
+    }
+
+    /// Fills all fields from the preset.
+    pub fn apply_preset(&mut self, preset: &DependencyPreset) {
+        self.source_span_name = Some(preset.source_span_name.clone());
+        self.source_search_text = preset.source_span_name.clone();
+        self.target_span_name = Some(preset.target_span_name.clone());
+        self.target_search_text = preset.target_span_name.clone();
+        self.set_threshold(preset.threshold);
+        self.linking_attribute = preset.linking_attribute.clone();
+        self.context_linking = preset.context_linking.clone();
+        self.group_by_attribute = preset.group_by_attribute.clone();
+        self.source_scope = preset.source_scope.clone();
+        self.source_timing_strategy = preset.source_timing_strategy.clone();
+        self.group_aggregation_strategy = preset.group_aggregation_strategy.clone();
+        self.analysis_cardinality = preset.analysis_cardinality.clone();
+    }
+
+    /// Saves the current configuration as a preset, replacing a preset with the same name.
+    pub fn save_preset(
+        &mut self,
+        presets: &mut Vec<DependencyPreset>,
+        name: &str,
+    ) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("The preset needs a name".to_string());
+        }
+        let preset = self
+            .current_preset(name)
+            .ok_or("Choose the source and target span first")?;
+        match presets.iter_mut().find(|p| p.name == name) {
+            Some(existing) => *existing = preset,
+            None => presets.push(preset),
+        }
+        self.presets_changed = true;
+        Ok(())
+    }
+
+    fn save_as_relation(&mut self) {
+        let name = match (&self.source_span_name, &self.target_span_name) {
+            (Some(source), Some(target)) => format!("{source} -> {target}"),
+            _ => return,
+        };
+        let Some(preset) = self.current_preset(&name) else {
+            return;
+        };
+        match dependency_to_relation(&preset) {
+            Ok(relation) => {
+                self.saved_relation = Some(relation);
+                self.error_message = None;
+            }
+            Err(err) => self.error_message = Some(format!("Can't save as relation: {err}")),
+        }
+    }
+
     pub fn clear_focus(&mut self) {
         self.focus_node = None;
+        self.focus.clear();
     }
 
     pub fn get_links_for_node(&self, node_name: &str) -> Option<&Vec<DependencyLink>> {
@@ -255,6 +872,11 @@ impl AnalyzeDependencyModal {
         self.linking_attribute = attribute;
     }
 
+    /// Test function: Sets the context linking for testing purposes.
+    pub fn set_context_linking(&mut self, context_linking: ContextLinking) {
+        self.context_linking = context_linking;
+    }
+
     /// Test function: Sets the group by attribute for testing purposes.
     pub fn set_group_by_attribute(&mut self, attribute: String) {
         self.group_by_attribute = attribute;
@@ -270,6 +892,11 @@ impl AnalyzeDependencyModal {
         self.group_aggregation_strategy = strategy;
     }
 
+    /// Test function: Restricts the analysis to `selected_time_range` for testing purposes.
+    pub fn set_only_selected_time_range(&mut self, only_selected_time_range: bool) {
+        self.only_selected_time_range = only_selected_time_range;
+    }
+
     /// Test function: Gets the error message for testing purposes.
     pub fn get_error_message(&self) -> Option<&String> {
         self.error_message.as_ref()
@@ -300,6 +927,11 @@ impl AnalyzeDependencyModal {
         &self.linking_attribute
     }
 
+    /// Test function: Gets the context linking for testing purposes.
+    pub fn get_context_linking(&self) -> &ContextLinking {
+        &self.context_linking
+    }
+
     /// Test function: Gets the group by attribute for testing purposes.
     pub fn get_group_by_attribute(&self) -> &String {
         &self.group_by_attribute
@@ -330,204 +962,20 @@ impl AnalyzeDependencyModal {
         &self.target_search_text
     }
 
-    /// Selects a subset of source spans based on the configured timing strategy and threshold.
-    fn select_spans_for_link_formation(&self, available_spans: &[Rc<Span>]) -> Vec<Rc<Span>> {
-        assert!(self.threshold >= 1);
-        let num_to_take = self.threshold;
-
-        match self.source_timing_strategy {
-            SourceTimingStrategy::EarliestFirst => {
-                available_spans.iter().take(num_to_take).cloned().collect()
-            }
-            SourceTimingStrategy::LatestFirst => {
-                let skip_count = available_spans.len().saturating_sub(num_to_take);
-                available_spans.iter().skip(skip_count).cloned().collect()
-            }
-        }
-    }
-
-    /// Records a successfully formed dependency link, updates statistics, and tracks min/max delay links.
-    #[allow(clippy::too_many_arguments)]
-    fn record_formed_link(
-        &self,
-        stats: &mut Statistics,
-        node_links: &mut Vec<DependencyLink>,
-        min_link_for_node: &mut Option<DependencyLink>,
-        max_link_for_node: &mut Option<DependencyLink>,
-        formed_link: &DependencyLink,
-        link_delay: f64,
-        used_spans: &mut HashSet<Vec<u8>>,
-        span_id: &[u8],
-    ) {
-        stats.add_value(link_delay);
-        node_links.push(formed_link.clone());
-        used_spans.insert(span_id.to_vec());
-
-        // Update min/max links
-        // stats.count, stats.min, stats.max are updated by stats.add_value()
-        if stats.count == 1 {
-            // This means it's the first link added to these stats
-            *min_link_for_node = Some(formed_link.clone());
-            *max_link_for_node = Some(formed_link.clone());
-        } else {
-            if link_delay == stats.min {
-                *min_link_for_node = Some(formed_link.clone());
-            }
-            // A link can be both min and max if it's the only one, or if multiple links share the same min/max delay.
-            if link_delay == stats.max {
-                *max_link_for_node = Some(formed_link.clone());
-            }
-        }
-    }
-
-    /// Marks the source spans of a formed link as used according to the source scope.
-    fn mark_source_spans_used(
-        &self,
-        source_spans_in_link: &[Rc<Span>],
-        global_used_source_span_ids_for_self_mode: &mut HashSet<Vec<u8>>,
-        used_source_ids_for_current_node_all_scope: &mut HashSet<Vec<u8>>,
-    ) {
-        for linked_s_span in source_spans_in_link {
-            if self.source_scope == SourceScope::SameNode {
-                global_used_source_span_ids_for_self_mode.insert(linked_s_span.span_id.clone());
-            } else {
-                used_source_ids_for_current_node_all_scope.insert(linked_s_span.span_id.clone());
-            }
-        }
-    }
-
-    /// Checks if two spans have matching values for all specified linking attributes.
-    /// Supports both exact matching and relative matching patterns:
-    /// - "height" or "height=+0" - exact match
-    /// - "height=+1" - target height = source height + 1
-    /// - "height=-2" - target height = source height - 2
-    fn spans_match_linking_attributes(
-        &self,
-        source_span: &Rc<Span>,
-        target_span: &Rc<Span>,
-    ) -> bool {
-        if self.linking_attribute.is_empty() {
-            return true;
-        }
-
-        let attribute_patterns: Vec<&str> = self
-            .linking_attribute
-            .split(',')
-            .map(|s| s.trim())
-            .collect();
-
-        for pattern in attribute_patterns {
-            if pattern.is_empty() {
-                continue;
-            }
-
-            if !self.check_attribute_pattern(pattern, source_span, target_span) {
-                return false;
-            }
-        }
-
-        true
-    }
-
-    /// Checks if a single attribute pattern matches between source and target spans.
-    fn check_attribute_pattern(
-        &self,
-        pattern: &str,
-        source_span: &Rc<Span>,
-        target_span: &Rc<Span>,
-    ) -> bool {
-        // Check if pattern contains a relative offset (e.g., "height=+1")
-        if let Some(equals_pos) = pattern.find('=') {
-            let attr_name = pattern[..equals_pos].trim();
-            let offset_str = pattern[equals_pos + 1..].trim();
-
-            // Both spans must have the attribute
-            if !source_span.attributes.contains_key(attr_name)
-                || !target_span.attributes.contains_key(attr_name)
-            {
-                return false;
-            }
-
-            // Try to parse as relative offset
-            if let Ok(offset) = self.parse_relative_offset(offset_str) {
-                return self.check_numeric_attribute_with_offset(
-                    attr_name,
-                    source_span,
-                    target_span,
-                    offset,
-                );
-            }
-
-            // If not a valid offset, fall back to exact string matching
-            let source_value = &source_span.attributes[attr_name];
-            let target_value = &target_span.attributes[attr_name];
-            source_value == target_value
-        } else {
-            // No equals sign, treat as exact match attribute name
-            let attr_name = pattern.trim();
-
-            // Both spans must have the attribute
-            if !source_span.attributes.contains_key(attr_name)
-                || !target_span.attributes.contains_key(attr_name)
-            {
-                return false;
-            }
-
-            // Values must match exactly
-            let source_value = &source_span.attributes[attr_name];
-            let target_value = &target_span.attributes[attr_name];
-            source_value == target_value
-        }
-    }
-
-    /// Parses a relative offset string like "+1", "-2", "+0"
-    fn parse_relative_offset(&self, offset_str: &str) -> Result<f64, ()> {
-        if let Some(stripped) = offset_str.strip_prefix('+') {
-            stripped.parse::<f64>().map_err(|_| ())
-        } else if let Some(stripped) = offset_str.strip_prefix('-') {
-            stripped.parse::<f64>().map(|v| -v).map_err(|_| ())
-        } else {
-            // Allow plain numbers (treat as positive offset)
-            offset_str.parse::<f64>().map_err(|_| ())
-        }
-    }
-
-    /// Checks if target attribute value equals source attribute value plus offset
-    fn check_numeric_attribute_with_offset(
-        &self,
-        attr_name: &str,
-        source_span: &Rc<Span>,
-        target_span: &Rc<Span>,
-        offset: f64,
-    ) -> bool {
-        let source_value = &source_span.attributes[attr_name];
-        let target_value = &target_span.attributes[attr_name];
-
-        // Extract numeric values from the attributes
-        let source_num = self.extract_numeric_value(source_value);
-        let target_num = self.extract_numeric_value(target_value);
-
-        match (source_num, target_num) {
-            (Some(src), Some(tgt)) => {
-                let expected_target = src + offset;
-                (tgt - expected_target).abs() < 0.0001 // Allow for floating point precision
-            }
-            _ => false, // If either value is not numeric, no match
-        }
-    }
-
-    /// Extracts a numeric value from an OpenTelemetry attribute value
-    fn extract_numeric_value(&self, value: &Option<Value>) -> Option<f64> {
-        match value {
-            Some(Value::IntValue(i)) => Some(*i as f64),
-            Some(Value::DoubleValue(d)) => Some(*d),
-            Some(Value::StringValue(s)) => s.parse::<f64>().ok(),
-            _ => None,
+    /// Runs the analysis and blocks until it's finished.
+    pub fn analyze_dependencies(&mut self) {
+        self.start_analysis();
+        if let Some(running) = &mut self.running_analysis {
+            let node_results = running.task.wait();
+            self.add_node_results(node_results);
+            self.finish_analysis();
         }
     }
 
-    pub fn analyze_dependencies(&mut self) {
+    /// Starts the analysis on background threads, results are collected in [Self::poll_analysis].
+    pub fn start_analysis(&mut self) {
         self.analysis_result = None;
+        self.running_analysis = None;
 
         let analysis_start = Instant::now();
 
@@ -540,21 +988,28 @@ impl AnalyzeDependencyModal {
                 }
             };
 
+        let work_spans = |spans: &[Rc<Span>]| -> Vec<WorkSpan> {
+            spans
+                .iter()
+                .enumerate()
+                .map(|(index, span)| WorkSpan {
+                    index,
+                    data: span.data.clone(),
+                })
+                .collect()
+        };
         let (source_spans_by_node, target_spans_by_node) =
-            group_spans_by_node(&source_spans, &target_spans);
+            group_spans_by_node(&work_spans(&source_spans), &work_spans(&target_spans));
 
-        // Per-node dependency analysis
-        let mut per_node_results = HashMap::new();
-
-        match self.analysis_cardinality {
+        let node_names: Vec<String> = match self.analysis_cardinality {
             AnalysisCardinality::NToOne => {
-                let node_names = if self.source_scope == SourceScope::SameNode {
+                if self.source_scope == SourceScope::SameNode {
                     // Only analyze nodes that have both source and target spans
                     source_spans_by_node
                         .keys()
                         .filter(|node_name| target_spans_by_node.contains_key(*node_name))
                         .cloned()
-                        .collect::<Vec<String>>()
+                        .collect()
                 } else {
                     // "all nodes"
                     // Use source nodes and target nodes
@@ -562,776 +1017,363 @@ impl AnalyzeDependencyModal {
                     all_nodes.extend(source_spans_by_node.keys().cloned());
                     all_nodes.extend(target_spans_by_node.keys().cloned());
                     all_nodes.into_iter().collect()
-                };
-
-                // This set is for 'self' mode: if a source span is a potential candidate for any target, it's marked used globally
-                let mut global_used_source_span_ids_for_self_mode: HashSet<Vec<u8>> =
-                    HashSet::new();
-
-                for node_name_str in node_names {
-                    let current_target_spans_for_node = target_spans_by_node
-                        .get(&node_name_str)
-                        .map(|v| v.as_slice())
-                        .unwrap_or(&[]);
-
-                    if current_target_spans_for_node.is_empty() {
-                        continue;
-                    }
-
-                    if let Some(metrics) = self.analyze_dependencies_for_single_node_n_to_one(
-                        &node_name_str,
-                        &source_spans_by_node,
-                        current_target_spans_for_node,
-                        &mut global_used_source_span_ids_for_self_mode,
-                        &expected_group_keys_set,
-                    ) {
-                        per_node_results.insert(node_name_str.clone(), metrics);
-                    }
                 }
             }
             AnalysisCardinality::OneToN => {
-                let node_names = if self.source_scope == SourceScope::SameNode {
+                if self.source_scope == SourceScope::SameNode {
                     // Only analyze nodes that have both source and target spans
                     source_spans_by_node
                         .keys()
                         .filter(|node_name| target_spans_by_node.contains_key(*node_name))
                         .cloned()
-                        .collect::<Vec<String>>()
+                        .collect()
                 } else {
                     // "all nodes" - analyze all source nodes
                     source_spans_by_node.keys().cloned().collect()
-                };
-
-                // For 1-to-N: each source span can link to multiple targets
-                let mut global_used_target_span_ids_for_self_mode: HashSet<Vec<u8>> =
-                    HashSet::new();
-
-                for node_name_str in node_names {
-                    let current_source_spans_for_node = source_spans_by_node
-                        .get(&node_name_str)
-                        .map(|v| v.as_slice())
-                        .unwrap_or(&[]);
-
-                    if current_source_spans_for_node.is_empty() {
-                        continue;
-                    }
-
-                    if let Some(metrics) = self.analyze_dependencies_for_single_node_one_to_n(
-                        &node_name_str,
-                        current_source_spans_for_node,
-                        &target_spans_by_node,
-                        &mut global_used_target_span_ids_for_self_mode,
-                        &expected_group_keys_set,
-                    ) {
-                        per_node_results.insert(node_name_str.clone(), metrics);
-                    }
                 }
             }
-        }
-
-        // Measure analysis duration
-        let analysis_duration = analysis_start.elapsed().as_millis();
+        };
 
-        // Store the results
-        self.analysis_result = Some(DependencyAnalysisResult {
-            source_span_name: source_name,
-            target_span_name: target_name,
+        let settings = AnalysisSettings {
             threshold: self.threshold,
-            linking_attribute: self.linking_attribute.clone(),
+            linking_patterns: parse_linking_patterns(&self.linking_attribute),
+            context_linking: self.context_linking.clone(),
             source_scope: self.source_scope.clone(),
             source_timing_strategy: self.source_timing_strategy.clone(),
             group_by_attribute: self.group_by_attribute.clone(),
             group_aggregation_strategy: self.group_aggregation_strategy.clone(),
-            analysis_cardinality: self.analysis_cardinality.clone(),
-            per_node_results,
-            analysis_duration_ms: analysis_duration,
-            overall_stats: Statistics::new(),
-            overall_min_delay_link: None,
-            overall_max_delay_link: None,
+        };
+        let cardinality = self.analysis_cardinality.clone();
+        // Nodes are weighted by the number of spans processed in the per node loop.
+        let weighted_node_names: Vec<(String, usize)> = node_names
+            .into_iter()
+            .map(|node_name| {
+                let spans_by_node = match cardinality {
+                    AnalysisCardinality::NToOne => &target_spans_by_node,
+                    AnalysisCardinality::OneToN => &source_spans_by_node,
+                };
+                let weight = spans_by_node.get(&node_name).map_or(0, |spans| spans.len());
+                (node_name, weight)
+            })
+            .collect();
+
+        // Nodes are analyzed independently. In the "same node" scope each node uses only its
+        // own spans, so the sets of used spans don't have to be shared between nodes.
+        let task = BackgroundTask::spawn_parallel(
+            weighted_node_names,
+            |(_, weight)| *weight,
+            move |(node_name, _)| {
+                let metrics = match cardinality {
+                    AnalysisCardinality::NToOne => {
+                        let current_target_spans_for_node = target_spans_by_node
+                            .get(node_name)
+                            .map(|v| v.as_slice())
+                            .unwrap_or(&[]);
+                        if current_target_spans_for_node.is_empty() {
+                            return None;
+                        }
+                        settings.analyze_dependencies_for_single_node_n_to_one(
+                            node_name,
+                            &source_spans_by_node,
+                            current_target_spans_for_node,
+                            &mut HashSet::new(),
+                            &expected_group_keys_set,
+                        )
+                    }
+                    AnalysisCardinality::OneToN => {
+                        let current_source_spans_for_node = source_spans_by_node
+                            .get(node_name)
+                            .map(|v| v.as_slice())
+                            .unwrap_or(&[]);
+                        if current_source_spans_for_node.is_empty() {
+                            return None;
+                        }
+                        settings.analyze_dependencies_for_single_node_one_to_n(
+                            node_name,
+                            current_source_spans_for_node,
+                            &target_spans_by_node,
+                            &mut HashSet::new(),
+                            &expected_group_keys_set,
+                        )
+                    }
+                };
+                metrics.map(|metrics| (node_name.clone(), metrics))
+            },
+        );
+
+        self.running_analysis = Some(RunningAnalysis {
+            result: DependencyAnalysisResult {
+                source_span_name: source_name,
+                target_span_name: target_name,
+                threshold: self.threshold,
+                linking_attribute: self.linking_attribute.clone(),
+                context_linking: self.context_linking.clone(),
+                source_scope: self.source_scope.clone(),
+                source_timing_strategy: self.source_timing_strategy.clone(),
+                group_by_attribute: self.group_by_attribute.clone(),
+                group_aggregation_strategy: self.group_aggregation_strategy.clone(),
+                analysis_cardinality: self.analysis_cardinality.clone(),
+                per_node_results: HashMap::new(),
+                analysis_duration_ms: 0,
+                time_range: self
+                    .only_selected_time_range
+                    .then_some(self.selected_time_range),
+                overall_stats: Statistics::new(),
+                overall_min_delay_link: None,
+                overall_max_delay_link: None,
+            },
+            source_spans,
+            target_spans,
+            analysis_start,
+            task,
         });
+        self.error_message = None;
+    }
 
-        // Calculate overall statistics if there are results
-        if let Some(res) = &mut self.analysis_result {
-            if !res.per_node_results.is_empty() {
-                let mut temp_overall_stats = Statistics::new();
-                let mut temp_overall_min_link: Option<DependencyLink> = None;
-                let mut temp_overall_max_link: Option<DependencyLink> = None;
+    /// Collects results of the background analysis, should be called every frame.
+    pub fn poll_analysis(&mut self) {
+        let Some(running) = &mut self.running_analysis else {
+            return;
+        };
+        let node_results = running.task.poll();
+        let finished = running.task.is_finished();
+        self.add_node_results(node_results);
+        if finished {
+            self.finish_analysis();
+        }
+    }
+
+    pub fn is_analysis_running(&self) -> bool {
+        self.running_analysis.is_some()
+    }
+
+    /// Stops the running analysis, partial results are discarded.
+    pub fn cancel_analysis(&mut self) {
+        if let Some(running) = self.running_analysis.take() {
+            running.task.cancel();
+            self.error_message = Some("Analysis cancelled".to_string());
+        }
+    }
+
+    fn add_node_results(&mut self, node_results: Vec<Option<WorkNodeResult>>) {
+        let Some(running) = &mut self.running_analysis else {
+            return;
+        };
+        for (node_name, metrics) in node_results.into_iter().flatten() {
+            let to_displayed_link = |link: DependencyLink<WorkSpan>| DependencyLink {
+                source_spans: link
+                    .source_spans
+                    .iter()
+                    .map(|s| running.source_spans[s.index].clone())
+                    .collect(),
+                target_spans: link
+                    .target_spans
+                    .iter()
+                    .map(|s| running.target_spans[s.index].clone())
+                    .collect(),
+                delay_seconds: link.delay_seconds,
+            };
+            let metrics = NodeDependencyMetrics {
+                link_delay_statistics: metrics.link_delay_statistics,
+                links: metrics.links.into_iter().map(to_displayed_link).collect(),
+                min_delay_link: metrics.min_delay_link.map(to_displayed_link),
+                max_delay_link: metrics.max_delay_link.map(to_displayed_link),
+            };
+            running.result.per_node_results.insert(node_name, metrics);
+        }
+    }
 
-                for node_metrics in res.per_node_results.values() {
-                    for link in &node_metrics.links {
-                        let current_delay = link.delay_seconds;
-                        let is_first_overall_value = temp_overall_stats.count == 0;
+    fn finish_analysis(&mut self) {
+        let Some(running) = self.running_analysis.take() else {
+            return;
+        };
+        let mut res = running.result;
 
-                        // Update .min, .max, .count
-                        temp_overall_stats.add_value(current_delay);
+        // Measure analysis duration
+        res.analysis_duration_ms = running.analysis_start.elapsed().as_millis();
 
-                        if is_first_overall_value {
+        // Calculate overall statistics if there are results
+        if !res.per_node_results.is_empty() {
+            let mut temp_overall_stats = Statistics::new();
+            let mut temp_overall_min_link: Option<DependencyLink> = None;
+            let mut temp_overall_max_link: Option<DependencyLink> = None;
+
+            for node_metrics in res.per_node_results.values() {
+                for link in &node_metrics.links {
+                    let current_delay = link.delay_seconds;
+                    let is_first_overall_value = temp_overall_stats.count == 0;
+
+                    // Update .min, .max, .count
+                    temp_overall_stats.add_value(current_delay);
+
+                    if is_first_overall_value {
+                        temp_overall_min_link = Some(link.clone());
+                        temp_overall_max_link = Some(link.clone());
+                    } else {
+                        if current_delay == temp_overall_stats.min {
                             temp_overall_min_link = Some(link.clone());
+                        }
+                        if current_delay == temp_overall_stats.max {
                             temp_overall_max_link = Some(link.clone());
-                        } else {
-                            if current_delay == temp_overall_stats.min {
-                                temp_overall_min_link = Some(link.clone());
-                            }
-                            if current_delay == temp_overall_stats.max {
-                                temp_overall_max_link = Some(link.clone());
-                            }
                         }
                     }
                 }
-                res.overall_stats = temp_overall_stats;
-                res.overall_min_delay_link = temp_overall_min_link;
-                res.overall_max_delay_link = temp_overall_max_link;
             }
+            res.overall_stats = temp_overall_stats;
+            res.overall_min_delay_link = temp_overall_min_link;
+            res.overall_max_delay_link = temp_overall_max_link;
         }
 
+        // Store the results
+        self.completed_analysis = Some(res.description());
+        self.analysis_result = Some(res);
+        self.link_table.clear();
         self.error_message = None;
     }
 
-    /// Analyzes dependencies for a single node.
-    fn analyze_dependencies_for_single_node_n_to_one(
-        &mut self,
-        node_name: &str,
-        source_spans_by_node: &HashMap<String, Vec<Rc<Span>>>,
-        current_target_node_spans: &[Rc<Span>],
-        global_used_source_span_ids_for_self_mode: &mut HashSet<Vec<u8>>,
-        expected_group_keys_set: &Option<HashSet<String>>,
-    ) -> Option<NodeDependencyMetrics> {
-        let current_source_node_spans = if self.source_scope == SourceScope::SameNode {
-            // Only use spans from this node
-            source_spans_by_node
-                .get(node_name)
-                .cloned()
-                .unwrap_or_default()
-        } else {
-            // Use spans from all nodes, sorted by time
-            let mut all_s_spans: Vec<Rc<Span>> =
-                source_spans_by_node.values().flatten().cloned().collect();
-            all_s_spans.sort_by(|a, b| {
-                a.start_time
-                    .partial_cmp(&b.start_time)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            });
-            all_s_spans
+    /// Validates inputs and prepares initial span lists for dependency analysis.
+    /// Returns a tuple of (source_name, target_name, source_spans, target_spans, expected_group_keys_set)
+    /// or an error message string if validation fails.
+    fn prepare_analysis_inputs(&mut self) -> Result<PreparedAnalysisInput, String> {
+        // Validate source and target span names
+        let source_name = match &self.source_span_name {
+            Some(name) => name.clone(),
+            None => return Err("Source span not selected".to_string()),
+        };
+        let target_name = match &self.target_span_name {
+            Some(name) => name.clone(),
+            None => return Err("Target span not selected".to_string()),
         };
 
-        // Skip if no source spans for this node/scope
-        if current_source_node_spans.is_empty() {
-            return None;
-        }
-
-        // Find valid links for the current node
-        let mut node_links_for_current_node = Vec::new();
-        let mut stats_for_current_node = Statistics::new();
-        let mut min_link_for_current_node: Option<DependencyLink> = None;
-        let mut max_link_for_current_node: Option<DependencyLink> = None;
+        // Collect all source and target spans
+        let mut source_spans = Vec::new();
+        let mut target_spans = Vec::new();
 
-        let mut used_target_spans: HashSet<Vec<u8>> = HashSet::new();
-        // This is specific to the current node when in "all nodes" scope, ensuring sources are not reused for different targets *on this same node* within this call.
-        let mut used_source_ids_for_current_node_all_scope: HashSet<Vec<u8>> = HashSet::new();
+        collect_matching_spans(
+            &self.all_spans_for_analysis,
+            &source_name,
+            &mut source_spans,
+        );
+        collect_matching_spans(
+            &self.all_spans_for_analysis,
+            &target_name,
+            &mut target_spans,
+        );
 
-        for target_span_rc in current_target_node_spans.iter() {
-            if used_target_spans.contains(&target_span_rc.span_id) {
-                // This target has already been linked by a source group
-                continue;
-            }
+        let range_suffix = if self.only_selected_time_range {
+            retain_spans_in_time_range(&mut source_spans, self.selected_time_range);
+            retain_spans_in_time_range(&mut target_spans, self.selected_time_range);
+            " in the selected time range"
+        } else {
+            ""
+        };
 
-            self.process_target_span_for_links(
-                target_span_rc, // Pass as &Rc<Span>
-                &current_source_node_spans,
-                expected_group_keys_set,
-                global_used_source_span_ids_for_self_mode,
-                &mut used_source_ids_for_current_node_all_scope, // Pass as mutable ref
-                &mut node_links_for_current_node,
-                &mut stats_for_current_node,
-                &mut min_link_for_current_node,
-                &mut max_link_for_current_node,
-                &mut used_target_spans,
-            );
+        if source_spans.is_empty() {
+            return Err(format!(
+                "No spans found with name \'{source_name}\'{range_suffix}"
+            ));
         }
 
-        // Add result for this node if any links were formed
-        if !node_links_for_current_node.is_empty() || stats_for_current_node.count > 0 {
-            Some(NodeDependencyMetrics {
-                link_delay_statistics: stats_for_current_node,
-                links: node_links_for_current_node,
-                min_delay_link: min_link_for_current_node,
-                max_delay_link: max_link_for_current_node,
-            })
-        } else {
-            None
+        if target_spans.is_empty() {
+            return Err(format!(
+                "No spans found with name \'{target_name}\'{range_suffix}"
+            ));
         }
-    }
 
-    /// Processes a single target span to find and record dependency links.
-    #[allow(clippy::too_many_arguments)]
-    fn process_target_span_for_links(
-        &self,
-        target_span: &Rc<Span>,
-        current_source_node_spans: &[Rc<Span>],
-        expected_group_keys_set: &Option<HashSet<String>>,
-        global_used_source_span_ids_for_self_mode: &mut HashSet<Vec<u8>>,
-        used_source_ids_for_current_node_all_scope: &mut HashSet<Vec<u8>>,
-        node_links_for_current_node: &mut Vec<DependencyLink>,
-        stats_for_current_node: &mut Statistics,
-        min_link_for_current_node: &mut Option<DependencyLink>,
-        max_link_for_current_node: &mut Option<DependencyLink>,
-        used_target_spans: &mut HashSet<Vec<u8>>,
-    ) {
-        // Common logic to find all temporally valid and not-yet-used source spans
-        let mut eligible_sources_before_target: Vec<Rc<Span>> = Vec::new();
-        for s_span in current_source_node_spans.iter() {
-            let mut skip_source = false;
-            if self.source_scope == SourceScope::SameNode {
-                if global_used_source_span_ids_for_self_mode.contains(&s_span.span_id) {
-                    skip_source = true;
-                }
-            } else {
-                // "all nodes" mode
-                if used_source_ids_for_current_node_all_scope.contains(&s_span.span_id) {
-                    skip_source = true;
-                }
-            }
-            if skip_source {
-                continue;
-            }
+        // Determine expected_group_keys_set if grouping is active
+        let mut expected_group_keys_set: Option<HashSet<String>> = None;
+        if !self.group_by_attribute.is_empty() {
+            let mut keys_found = HashSet::new();
 
-            // Basic time validity
-            if s_span.end_time <= target_span.start_time {
-                // For grouping mode, defer linking attribute check to after grouping
-                // For non-grouping mode, check linking attribute compatibility here
-                if self.group_by_attribute.is_empty() {
-                    // Check linking attribute compatibility using the new multi-attribute function
-                    if self.spans_match_linking_attributes(s_span, target_span) {
-                        eligible_sources_before_target.push(s_span.clone());
-                    }
-                } else {
-                    // In grouping mode, add all temporally valid sources and check linking later
-                    eligible_sources_before_target.push(s_span.clone());
-                }
-            }
-        }
+            // Choose which spans to check based on cardinality mode
+            let (spans_to_check, span_type_name) = match self.analysis_cardinality {
+                AnalysisCardinality::NToOne => (&source_spans, &source_name),
+                AnalysisCardinality::OneToN => (&target_spans, &target_name),
+            };
 
-        // Branch based on grouping
-        if !self.group_by_attribute.is_empty() && expected_group_keys_set.is_some() {
-            // GROUPING LOGIC
-            let mut grouped_potential_sources: HashMap<String, Vec<Rc<Span>>> = HashMap::new();
-            for s_span in &eligible_sources_before_target {
+            for span in spans_to_check {
                 if let Some(Some(Value::StringValue(s_val))) =
-                    s_span.attributes.get(&self.group_by_attribute)
+                    span.attributes.get(&self.group_by_attribute)
                 {
-                    grouped_potential_sources
-                        .entry(s_val.clone())
-                        .or_default()
-                        .push(s_span.clone());
+                    keys_found.insert(s_val.clone());
                 }
             }
 
-            let mut all_valid_groups_meet_threshold = true;
-            let mut spans_for_this_grouped_link: Vec<Rc<Span>> = Vec::new();
-            let mut valid_groups_count = 0;
-
-            // Check each group - only count groups where individually matching sources meet threshold
-            for group_spans in grouped_potential_sources.values() {
-                // Filter to only sources that individually match the linking attribute
-                let matching_sources_in_group: Vec<Rc<Span>> = if !self.linking_attribute.is_empty()
-                {
-                    group_spans
-                        .iter()
-                        .filter(|s_span| self.spans_match_linking_attributes(s_span, target_span))
-                        .cloned()
-                        .collect()
-                } else {
-                    group_spans.clone()
+            if keys_found.is_empty() {
+                let error_message = match self.analysis_cardinality {
+                    AnalysisCardinality::NToOne => {
+                        format!(
+                            "The \'Group By Attribute\' (\'{}\') was not found in any source spans named \'{}\', or no such source spans have this attribute.",
+                            self.group_by_attribute, span_type_name
+                        )
+                    }
+                    AnalysisCardinality::OneToN => {
+                        format!(
+                            "The \'Group By Attribute\' (\'{}\') was not found in any target spans named \'{}\', or no such target spans have this attribute.",
+                            self.group_by_attribute, span_type_name
+                        )
+                    }
                 };
-
-                if matching_sources_in_group.len() >= self.threshold && self.threshold > 0 {
-                    let selected_from_group =
-                        self.select_spans_for_link_formation(&matching_sources_in_group);
-                    spans_for_this_grouped_link.extend(selected_from_group);
-                    valid_groups_count += 1;
-                } else if !matching_sources_in_group.is_empty() {
-                    // This group has matching sources but not enough to meet threshold
-                    all_valid_groups_meet_threshold = false;
-                    break;
-                }
-            }
-
-            // Only proceed if we have at least some valid groups and all valid groups meet threshold
-            if all_valid_groups_meet_threshold
-                && valid_groups_count > 0
-                && !spans_for_this_grouped_link.is_empty()
-            {
-                // Calculate link delay based on aggregation strategy for groups
-                let link_delay = match self.group_aggregation_strategy {
-                    GroupAggregationStrategy::WaitForLastGroup => {
-                        spans_for_this_grouped_link
-                            .iter()
-                            .map(|s| s.end_time)
-                            .fold(f64::NEG_INFINITY, f64::max)
-                            - target_span.start_time
-                    }
-                    GroupAggregationStrategy::FirstCompletedGroup => {
-                        let mut latest_end_time_per_group: HashMap<String, f64> = HashMap::new();
-                        for s_span in &spans_for_this_grouped_link {
-                            if let Some(Some(Value::StringValue(group_key))) =
-                                s_span.attributes.get(&self.group_by_attribute)
-                            {
-                                latest_end_time_per_group
-                                    .entry(group_key.clone())
-                                    .and_modify(|e| *e = e.max(s_span.end_time))
-                                    .or_insert(s_span.end_time);
-                            }
-                        }
-                        latest_end_time_per_group
-                            .values()
-                            .fold(f64::INFINITY, |a, &b| a.min(b))
-                            - target_span.start_time
-                    }
-                }
-                .abs();
-
-                let new_formed_link = DependencyLink {
-                    source_spans: spans_for_this_grouped_link.clone(),
-                    target_spans: vec![target_span.clone()],
-                    delay_seconds: link_delay,
-                };
-
-                self.record_formed_link(
-                    stats_for_current_node,
-                    node_links_for_current_node,
-                    min_link_for_current_node,
-                    max_link_for_current_node,
-                    &new_formed_link,
-                    link_delay,
-                    used_target_spans,
-                    &target_span.span_id,
-                );
-
-                self.mark_source_spans_used(
-                    &spans_for_this_grouped_link,
-                    global_used_source_span_ids_for_self_mode,
-                    used_source_ids_for_current_node_all_scope,
-                );
-            }
-        } else {
-            // NON-GROUPING LOGIC
-            if eligible_sources_before_target.len() >= self.threshold && self.threshold > 0 {
-                let selected_source_spans_group =
-                    self.select_spans_for_link_formation(&eligible_sources_before_target);
-
-                if !selected_source_spans_group.is_empty() {
-                    let latest_end_time_of_selected_sources = selected_source_spans_group
-                        .iter()
-                        .map(|s| s.end_time)
-                        .fold(f64::NEG_INFINITY, f64::max);
-
-                    let link_distance =
-                        (target_span.start_time - latest_end_time_of_selected_sources).abs();
-
-                    let new_formed_link = DependencyLink {
-                        source_spans: selected_source_spans_group.clone(),
-                        target_spans: vec![target_span.clone()],
-                        delay_seconds: link_distance,
-                    };
-
-                    self.record_formed_link(
-                        stats_for_current_node,
-                        node_links_for_current_node,
-                        min_link_for_current_node,
-                        max_link_for_current_node,
-                        &new_formed_link,
-                        link_distance,
-                        used_target_spans,
-                        &target_span.span_id,
-                    );
-
-                    self.mark_source_spans_used(
-                        &selected_source_spans_group,
-                        global_used_source_span_ids_for_self_mode,
-                        used_source_ids_for_current_node_all_scope,
-                    );
-                }
+                return Err(error_message);
             }
+            expected_group_keys_set = Some(keys_found);
         }
+
+        // Sort spans by start time
+        source_spans.sort_by(|a, b| {
+            a.start_time
+                .partial_cmp(&b.start_time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        target_spans.sort_by(|a, b| {
+            a.start_time
+                .partial_cmp(&b.start_time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok((
+            source_name,
+            target_name,
+            source_spans,
+            target_spans,
+            expected_group_keys_set,
+        ))
     }
 
-    /// Analyzes dependencies for a single node in 1-to-N mode.
-    fn analyze_dependencies_for_single_node_one_to_n(
+    // Show the modal
+    pub fn show_modal(
         &mut self,
-        node_name: &str,
-        current_source_node_spans: &[Rc<Span>],
-        target_spans_by_node: &HashMap<String, Vec<Rc<Span>>>,
-        global_used_target_span_ids_for_self_mode: &mut HashSet<Vec<u8>>,
-        expected_group_keys_set: &Option<HashSet<String>>,
-    ) -> Option<NodeDependencyMetrics> {
-        // Get all potential target spans based on source scope
-        let current_target_spans = if self.source_scope == SourceScope::SameNode {
-            // Only use targets from this same node
-            target_spans_by_node
-                .get(node_name)
-                .cloned()
-                .unwrap_or_default()
-        } else {
-            // Use targets from all nodes, sorted by time
-            let mut all_t_spans: Vec<Rc<Span>> =
-                target_spans_by_node.values().flatten().cloned().collect();
-            all_t_spans.sort_by(|a, b| {
-                a.start_time
-                    .partial_cmp(&b.start_time)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            });
-            all_t_spans
-        };
-
-        // Skip if no target spans for this scope
-        if current_target_spans.is_empty() {
-            return None;
+        ctx: &egui::Context,
+        time_format: &TimeFormat,
+        presets: &mut Vec<DependencyPreset>,
+        max_width: f32,
+        max_height: f32,
+    ) {
+        if !self.show {
+            return;
         }
 
-        // Find valid links for the current source node
-        let mut node_links_for_current_node = Vec::new();
-        let mut stats_for_current_node = Statistics::new();
-        let mut min_link_for_current_node: Option<DependencyLink> = None;
-        let mut max_link_for_current_node: Option<DependencyLink> = None;
-
-        let mut used_source_spans: HashSet<Vec<u8>> = HashSet::new();
-        // This is specific to the current node when in "all nodes" scope
-        let mut used_target_ids_for_current_node_all_scope: HashSet<Vec<u8>> = HashSet::new();
-
-        for source_span_rc in current_source_node_spans.iter() {
-            if used_source_spans.contains(&source_span_rc.span_id) {
-                // This source has already been processed
-                continue;
-            }
-
-            self.process_source_span_for_one_to_n_links(
-                source_span_rc,
-                &current_target_spans,
-                expected_group_keys_set,
-                global_used_target_span_ids_for_self_mode,
-                &mut used_target_ids_for_current_node_all_scope,
-                &mut node_links_for_current_node,
-                &mut stats_for_current_node,
-                &mut min_link_for_current_node,
-                &mut max_link_for_current_node,
-                &mut used_source_spans,
-            );
+        self.poll_analysis();
+        if self.is_analysis_running() {
+            // Keep polling even when there is no input.
+            ctx.request_repaint();
         }
 
-        // Add result for this node if any links were formed
-        if !node_links_for_current_node.is_empty() || stats_for_current_node.count > 0 {
-            Some(NodeDependencyMetrics {
-                link_delay_statistics: stats_for_current_node,
-                links: node_links_for_current_node,
-                min_delay_link: min_link_for_current_node,
-                max_delay_link: max_link_for_current_node,
-            })
-        } else {
-            None
-        }
-    }
+        let mut modal_closed = false;
 
-    /// Processes a single source span to find and record dependency links in 1-to-N mode.
-    #[allow(clippy::too_many_arguments)]
-    fn process_source_span_for_one_to_n_links(
-        &self,
-        source_span: &Rc<Span>,
-        current_target_spans: &[Rc<Span>],
-        expected_group_keys_set: &Option<HashSet<String>>,
-        global_used_target_span_ids_for_self_mode: &mut HashSet<Vec<u8>>,
-        used_target_ids_for_current_node_all_scope: &mut HashSet<Vec<u8>>,
-        node_links_for_current_node: &mut Vec<DependencyLink>,
-        stats_for_current_node: &mut Statistics,
-        min_link_for_current_node: &mut Option<DependencyLink>,
-        max_link_for_current_node: &mut Option<DependencyLink>,
-        used_source_spans: &mut HashSet<Vec<u8>>,
-    ) {
-        // Common logic to find all temporally valid, attribute-matching, and not-yet-used target spans
-        let mut eligible_targets_after_source: Vec<Rc<Span>> = Vec::new();
-        for t_span in current_target_spans.iter() {
-            let mut skip_target = false;
-            if self.source_scope == SourceScope::SameNode {
-                if global_used_target_span_ids_for_self_mode.contains(&t_span.span_id) {
-                    skip_target = true;
-                }
-            } else {
-                // "all nodes" mode
-                if used_target_ids_for_current_node_all_scope.contains(&t_span.span_id) {
-                    skip_target = true;
-                }
-            }
-            if skip_target {
-                continue;
-            }
+        Modal::new("analyze dependency".into()).show(ctx, |ui_modal_area| {
+            ui_modal_area.vertical(|ui_main_column| {
+                ui_main_column.set_max_width(max_width);
+                ui_main_column.set_max_height(max_height);
 
-            // Basic time validity: target must start after source ends
-            if t_span.start_time >= source_span.end_time
-                && self.spans_match_linking_attributes(source_span, t_span)
-            {
-                eligible_targets_after_source.push(t_span.clone());
-            }
-        }
+                ui_main_column.heading("Analyze Dependency");
+                ui_main_column.add_space(10.0);
 
-        // Branch based on grouping
-        if !self.group_by_attribute.is_empty() && expected_group_keys_set.is_some() {
-            // GROUPING LOGIC
-            let mut grouped_potential_targets: HashMap<String, Vec<Rc<Span>>> = HashMap::new();
-            for t_span in &eligible_targets_after_source {
-                if let Some(Some(Value::StringValue(t_val))) =
-                    t_span.attributes.get(&self.group_by_attribute)
-                {
-                    grouped_potential_targets
-                        .entry(t_val.clone())
-                        .or_default()
-                        .push(t_span.clone());
-                }
-            }
+                self.show_presets_ui(ui_main_column, presets);
 
-            let mut all_groups_meet_threshold = true;
-            let mut targets_for_this_grouped_link: Vec<Rc<Span>> = Vec::new();
+                // Quick setup section for parsing analysis descriptions
+                self.show_quick_setup_parsing_ui(ui_main_column);
 
-            // Check if each group that exists in eligible targets meets threshold
-            // (Don't require all originally expected groups - some may be filtered out by linking attributes)
-            for group_targets in grouped_potential_targets.values() {
-                if group_targets.len() >= self.threshold && self.threshold > 0 {
-                    let selected_from_group =
-                        self.select_targets_for_one_to_n_link_formation(group_targets);
-                    targets_for_this_grouped_link.extend(selected_from_group);
-                } else {
-                    all_groups_meet_threshold = false;
-                    break;
-                }
-            }
-
-            // Only proceed if we have at least some groups and all present groups meet threshold
-            if all_groups_meet_threshold
-                && !grouped_potential_targets.is_empty()
-                && !targets_for_this_grouped_link.is_empty()
-            {
-                // Create a single link with one source and multiple targets
-                // For consistency with N-to-1 mode, always use the LATEST target start time
-                // (representing "how long until ALL targets have started")
-                let link_delay = (targets_for_this_grouped_link
-                    .iter()
-                    .map(|t| t.start_time)
-                    .fold(f64::NEG_INFINITY, f64::max)
-                    - source_span.end_time)
-                    .abs();
-
-                let new_formed_link = DependencyLink {
-                    source_spans: vec![source_span.clone()],
-                    target_spans: targets_for_this_grouped_link.clone(),
-                    delay_seconds: link_delay,
-                };
-
-                self.record_formed_link(
-                    stats_for_current_node,
-                    node_links_for_current_node,
-                    min_link_for_current_node,
-                    max_link_for_current_node,
-                    &new_formed_link,
-                    link_delay,
-                    used_source_spans,
-                    &source_span.span_id,
-                );
-
-                // Mark all targets as used
-                for target_span in &targets_for_this_grouped_link {
-                    if self.source_scope == SourceScope::SameNode {
-                        global_used_target_span_ids_for_self_mode
-                            .insert(target_span.span_id.clone());
-                    } else {
-                        used_target_ids_for_current_node_all_scope
-                            .insert(target_span.span_id.clone());
-                    }
-                }
-            }
-        } else {
-            // NON-GROUPING LOGIC
-            if eligible_targets_after_source.len() >= self.threshold && self.threshold > 0 {
-                let selected_target_spans_group =
-                    self.select_targets_for_one_to_n_link_formation(&eligible_targets_after_source);
-
-                if !selected_target_spans_group.is_empty() {
-                    // Create a single link with one source and multiple targets
-                    // For consistency with N-to-1 mode, always use the LATEST target start time
-                    // (representing "how long until ALL targets have started")
-                    let link_delay = (selected_target_spans_group
-                        .iter()
-                        .map(|t| t.start_time)
-                        .fold(f64::NEG_INFINITY, f64::max)
-                        - source_span.end_time)
-                        .abs();
-
-                    let new_formed_link = DependencyLink {
-                        source_spans: vec![source_span.clone()],
-                        target_spans: selected_target_spans_group.clone(),
-                        delay_seconds: link_delay,
-                    };
-
-                    self.record_formed_link(
-                        stats_for_current_node,
-                        node_links_for_current_node,
-                        min_link_for_current_node,
-                        max_link_for_current_node,
-                        &new_formed_link,
-                        link_delay,
-                        used_source_spans,
-                        &source_span.span_id,
-                    );
-
-                    // Mark all targets as used
-                    for target_span in &selected_target_spans_group {
-                        if self.source_scope == SourceScope::SameNode {
-                            global_used_target_span_ids_for_self_mode
-                                .insert(target_span.span_id.clone());
-                        } else {
-                            used_target_ids_for_current_node_all_scope
-                                .insert(target_span.span_id.clone());
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    /// Selects a subset of target spans based on the configured timing strategy and threshold for 1-to-N analysis.
-    fn select_targets_for_one_to_n_link_formation(
-        &self,
-        available_targets: &[Rc<Span>],
-    ) -> Vec<Rc<Span>> {
-        assert!(self.threshold >= 1);
-        let num_to_take = self.threshold;
-
-        match self.source_timing_strategy {
-            SourceTimingStrategy::EarliestFirst => available_targets
-                .iter()
-                .take(num_to_take)
-                .cloned()
-                .collect(),
-            SourceTimingStrategy::LatestFirst => {
-                let skip_count = available_targets.len().saturating_sub(num_to_take);
-                available_targets.iter().skip(skip_count).cloned().collect()
-            }
-        }
-    }
-
-    /// Validates inputs and prepares initial span lists for dependency analysis.
-    /// Returns a tuple of (source_name, target_name, source_spans, target_spans, expected_group_keys_set)
-    /// or an error message string if validation fails.
-    fn prepare_analysis_inputs(&mut self) -> Result<PreparedAnalysisInput, String> {
-        // Validate source and target span names
-        let source_name = match &self.source_span_name {
-            Some(name) => name.clone(),
-            None => return Err("Source span not selected".to_string()),
-        };
-        let target_name = match &self.target_span_name {
-            Some(name) => name.clone(),
-            None => return Err("Target span not selected".to_string()),
-        };
-
-        // Collect all source and target spans
-        let mut source_spans = Vec::new();
-        let mut target_spans = Vec::new();
-
-        collect_matching_spans(
-            &self.all_spans_for_analysis,
-            &source_name,
-            &mut source_spans,
-        );
-        collect_matching_spans(
-            &self.all_spans_for_analysis,
-            &target_name,
-            &mut target_spans,
-        );
-
-        if source_spans.is_empty() {
-            return Err(format!("No spans found with name \'{source_name}\'"));
-        }
-
-        if target_spans.is_empty() {
-            return Err(format!("No spans found with name \'{target_name}\'"));
-        }
-
-        // Determine expected_group_keys_set if grouping is active
-        let mut expected_group_keys_set: Option<HashSet<String>> = None;
-        if !self.group_by_attribute.is_empty() {
-            let mut keys_found = HashSet::new();
-
-            // Choose which spans to check based on cardinality mode
-            let (spans_to_check, span_type_name) = match self.analysis_cardinality {
-                AnalysisCardinality::NToOne => (&source_spans, &source_name),
-                AnalysisCardinality::OneToN => (&target_spans, &target_name),
-            };
-
-            for span in spans_to_check {
-                if let Some(Some(Value::StringValue(s_val))) =
-                    span.attributes.get(&self.group_by_attribute)
-                {
-                    keys_found.insert(s_val.clone());
-                }
-            }
-
-            if keys_found.is_empty() {
-                let error_message = match self.analysis_cardinality {
-                    AnalysisCardinality::NToOne => {
-                        format!(
-                            "The \'Group By Attribute\' (\'{}\') was not found in any source spans named \'{}\', or no such source spans have this attribute.",
-                            self.group_by_attribute, span_type_name
-                        )
-                    }
-                    AnalysisCardinality::OneToN => {
-                        format!(
-                            "The \'Group By Attribute\' (\'{}\') was not found in any target spans named \'{}\', or no such target spans have this attribute.",
-                            self.group_by_attribute, span_type_name
-                        )
-                    }
-                };
-                return Err(error_message);
-            }
-            expected_group_keys_set = Some(keys_found);
-        }
-
-        // Sort spans by start time
-        source_spans.sort_by(|a, b| {
-            a.start_time
-                .partial_cmp(&b.start_time)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        target_spans.sort_by(|a, b| {
-            a.start_time
-                .partial_cmp(&b.start_time)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        Ok((
-            source_name,
-            target_name,
-            source_spans,
-            target_spans,
-            expected_group_keys_set,
-        ))
-    }
-
-    // Show the modal
-    pub fn show_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
-        if !self.show {
-            return;
-        }
-
-        let mut modal_closed = false;
-
-        Modal::new("analyze dependency".into()).show(ctx, |ui_modal_area| {
-            ui_modal_area.vertical(|ui_main_column| {
-                ui_main_column.set_max_width(max_width);
-                ui_main_column.set_max_height(max_height);
-
-                ui_main_column.heading("Analyze Dependency");
-                ui_main_column.add_space(10.0);
-
-                // Quick setup section for parsing analysis descriptions
-                self.show_quick_setup_parsing_ui(ui_main_column);
-
-                ui_main_column.add_space(10.0);
+                ui_main_column.add_space(10.0);
 
                 Grid::new("source_target_grid")
                     .num_columns(2)
@@ -1447,13 +1489,34 @@ impl AnalyzeDependencyModal {
                                     response.on_hover_text(concat!(
                                         "Optional. If provided, only spans with matching values for this ",
                                         "attribute field can form links. Leave empty to ignore attribute matching. ",
-                                        "Supports exact matching (e.g., 'height') and relative matching (e.g., 'height=+1', 'height=-2'). ",
+                                        "Supports exact matching (e.g., 'height') and relative matching (e.g., 'height=+1', 'height-2'). ",
+                                        "Add '~0.5' to compare numbers with a tolerance and '/i' to compare text ignoring case. ",
                                         "Use comma-separated patterns (e.g., 'height=+1,shard_id') to match multiple attributes."
                                     ));
                                 }
                             });
                         });
                         ui_row1.add_space(10.0);
+                        ui_row1.vertical(|ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Link by Context:");
+                                let resp = ComboBox::new(ui.id().with("context_linking"), "")
+                                    .selected_text(self.context_linking.to_string())
+                                    .width(100.0)
+                                    .show_ui(ui, |ui_combo_context| {
+                                        for context_linking in [ContextLinking::None, ContextLinking::SameTrace, ContextLinking::ParentChild] {
+                                            let text = context_linking.to_string();
+                                            ui_combo_context.selectable_value(&mut self.context_linking, context_linking, text);
+                                        }
+                                    });
+                                resp.response.on_hover_text(concat!(
+                                    "Optional. 'same trace' only links spans with the same trace_id, ",
+                                    "'parent/child' only links spans where one is the parent of the other. ",
+                                    "Combined with the linking attribute if both are set."
+                                ));
+                            });
+                        });
+                        ui_row1.add_space(10.0);
                         ui_row1.vertical(|ui| {
                             ui.horizontal(|ui| {
                                 ui.label("Group By Attribute:");
@@ -1540,10 +1603,25 @@ impl AnalyzeDependencyModal {
                                 });
                             });
                         });
+                        ui_row2.add_space(10.0);
+                        selected_time_range_ui(ui_row2, &mut self.only_selected_time_range, self.selected_time_range, time_format);
                         ui_row2.add_space(20.0);
                         ui_row2.with_layout(Layout::right_to_left(eframe::emath::Align::Center), |ui_analyze_button_area| {
-                            if ui_analyze_button_area.add_enabled(self.source_span_name.is_some() && self.target_span_name.is_some(), Button::new("Analyze").min_size(Vec2::new(100.0, 30.0))).clicked() {
-                                self.analyze_dependencies();
+                            if let Some(running) = &self.running_analysis {
+                                if ui_analyze_button_area.add(Button::new("Cancel").min_size(Vec2::new(100.0, 30.0))).clicked() {
+                                    self.cancel_analysis();
+                                } else {
+                                    ui_analyze_button_area.add(ProgressBar::new(running.task.progress()).desired_width(150.0).text("Analyzing"));
+                                }
+                            } else if ui_analyze_button_area.add_enabled(self.source_span_name.is_some() && self.target_span_name.is_some(), Button::new("Analyze").min_size(Vec2::new(100.0, 30.0))).clicked() {
+                                self.start_analysis();
+                            }
+                            if ui_analyze_button_area
+                                .add_enabled(self.source_span_name.is_some() && self.target_span_name.is_some(), Button::new("Save as relation").min_size(Vec2::new(100.0, 30.0)))
+                                .on_hover_text("Add a relation which draws arrows between the source and target spans")
+                                .clicked()
+                            {
+                                self.save_as_relation();
                             }
                         });
                     });
@@ -1552,7 +1630,7 @@ impl AnalyzeDependencyModal {
                 ui_main_column.add_space(10.0);
                 if let Some(error) = &self.error_message {
                     ui_main_column.horizontal(|ui_err_msg| {
-                        ui_err_msg.colored_label(colors::MILD_RED, error);
+                        ui_err_msg.colored_label(theme().error, error);
                     });
                 }
                 ui_main_column.separator();
@@ -1563,14 +1641,27 @@ impl AnalyzeDependencyModal {
 
                 if let Some(result) = &self.analysis_result {
                     ui_main_column.horizontal_wrapped(|ui_summary_wrap| {
-                        ui_summary_wrap.label(format!(
-                            "Analysis of dependency: '{}' -> '{}' (cardinality: {}, threshold: {}, linking by: {}, group by: {}, scope: {}, timing: {}, group aggregation: {})",
-                            result.source_span_name, result.target_span_name, result.analysis_cardinality, result.threshold,
-                            if result.linking_attribute.is_empty() { "none" } else { &result.linking_attribute },
-                            if result.group_by_attribute.is_empty() { "none" } else { &result.group_by_attribute },
-                            result.source_scope, result.source_timing_strategy, result.group_aggregation_strategy
-                        ));
+                        ui_summary_wrap.label(result.description());
+                        copy_button(ui_summary_wrap, "Copy the description, it can be pasted into the quick setup", || result.description());
+                        if let Some(time_range) = result.time_range {
+                            ui_summary_wrap.label(format!("(Time range: {})", time_range_to_display_string(time_range, time_format)));
+                        }
                         ui_summary_wrap.label(format!("(Analysis took {} ms)", result.analysis_duration_ms));
+                        if ui_summary_wrap
+                            .add_enabled(result.overall_stats.count > 0, Button::new("Export links"))
+                            .on_hover_text("Save all formed links with their spans to a JSON file")
+                            .clicked()
+                        {
+                            self.export_links_requested = true;
+                        }
+                        copy_button(ui_summary_wrap, "Copy the statistics table as tab separated values, it can be pasted into a spreadsheet", || result.report_section(time_format).tables_to_tsv());
+                        if ui_summary_wrap
+                            .button("Add to report")
+                            .on_hover_text("Add the statistics and charts to the report, see Report in the top bar")
+                            .clicked()
+                        {
+                            self.report_section = Some(result.report_section(time_format));
+                        }
                     });
                 }
 
@@ -1621,7 +1712,7 @@ impl AnalyzeDependencyModal {
                                             ui_data_grid.scope(|ui_cell| {
                                                 ui_cell.set_min_width(col_widths[0]);
                                                 ui_cell.horizontal(|ui_horiz| {
-                                                    ui_horiz.label(RichText::new(&node_name).monospace());
+                                                    ui_horiz.label(RichText::new(&node_name).monospace().color(node_color(&node_name)));
                                                     ui_horiz.add_space(5.0);
                                                     let focus_response = ui_horiz.button("🔍");
                                                     if focus_response.clicked() {
@@ -1635,8 +1726,8 @@ impl AnalyzeDependencyModal {
                                             });
                                             if stats.count > 0 {
                                                 draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[1], &format!("{}", stats.count), false, None, false);
-                                                let min_val_str = format!("{:.3}", stats.min * MILLISECONDS_PER_SECOND);
-                                                if let Some(resp) = draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[2], &min_val_str, false, Some(colors::MILD_BLUE2), node_result.min_delay_link.is_some()) {
+                                                let min_val_str = format_ms_value(stats.min);
+                                                if let Some(resp) = draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[2], &min_val_str, false, Some(theme().link), node_result.min_delay_link.is_some()) {
                                                     if resp.clicked() {
                                                         if let Some(link) = &node_result.min_delay_link {
                                                             self.show_link_details_popup = Some(LinkDetailsPopupInfo {
@@ -1650,8 +1741,8 @@ impl AnalyzeDependencyModal {
                                                         }
                                                     }
                                                 }
-                                                let max_val_str = format!("{:.3}", stats.max * MILLISECONDS_PER_SECOND);
-                                                if let Some(resp) = draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[3], &max_val_str, false, Some(colors::MILD_BLUE2), node_result.max_delay_link.is_some()) {
+                                                let max_val_str = format_ms_value(stats.max);
+                                                if let Some(resp) = draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[3], &max_val_str, false, Some(theme().link), node_result.max_delay_link.is_some()) {
                                                     if resp.clicked() {
                                                         if let Some(link) = &node_result.max_delay_link {
                                                             self.show_link_details_popup = Some(LinkDetailsPopupInfo {
@@ -1665,9 +1756,9 @@ impl AnalyzeDependencyModal {
                                                         }
                                                     }
                                                 }
-                                                draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[4], &format!("{:.3}", stats.mean() * MILLISECONDS_PER_SECOND), false, None, false);
-                                                draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[5], &format!("{:.3}", stats.median() * MILLISECONDS_PER_SECOND), false, None, false);
-                                                draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[6], &format!("{:.3}", stats.std_dev() * MILLISECONDS_PER_SECOND), false, None, false);
+                                                draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[4], &format_ms_value(stats.mean()), false, None, false);
+                                                draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[5], &format_ms_value(stats.median()), false, None, false);
+                                                draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[6], &format_ms_value(stats.std_dev()), false, None, false);
                                             } else {
                                                 for &col_width_val in col_widths.iter().skip(1) {
                                                     draw_clickable_right_aligned_text_cell(ui_data_grid, col_width_val, "-", false, None, false);
@@ -1694,8 +1785,8 @@ impl AnalyzeDependencyModal {
 
                                         draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[1], &format!("{}", result.overall_stats.count), true, None, false);
 
-                                        let min_val_str = format!("{:.3}", result.overall_stats.min * MILLISECONDS_PER_SECOND);
-                                        if let Some(resp) = draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[2], &min_val_str, true, Some(colors::MILD_BLUE2), result.overall_min_delay_link.is_some()) {
+                                        let min_val_str = format_ms_value(result.overall_stats.min);
+                                        if let Some(resp) = draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[2], &min_val_str, true, Some(theme().link), result.overall_min_delay_link.is_some()) {
                                             if resp.clicked() {
                                                 if let Some(link) = &result.overall_min_delay_link {
                                                     self.show_link_details_popup = Some(LinkDetailsPopupInfo {
@@ -1710,8 +1801,8 @@ impl AnalyzeDependencyModal {
                                             }
                                         }
 
-                                        let max_val_str = format!("{:.3}", result.overall_stats.max * MILLISECONDS_PER_SECOND);
-                                        if let Some(resp) = draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[3], &max_val_str, true, Some(colors::MILD_BLUE2), result.overall_max_delay_link.is_some()) {
+                                        let max_val_str = format_ms_value(result.overall_stats.max);
+                                        if let Some(resp) = draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[3], &max_val_str, true, Some(theme().link), result.overall_max_delay_link.is_some()) {
                                             if resp.clicked() {
                                                 if let Some(link) = &result.overall_max_delay_link {
                                                     self.show_link_details_popup = Some(LinkDetailsPopupInfo {
@@ -1725,331 +1816,1189 @@ impl AnalyzeDependencyModal {
                                                 }
                                             }
                                         }
-                                        draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[4], &format!("{:.3}", result.overall_stats.mean() * MILLISECONDS_PER_SECOND), true, None, false);
-                                        draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[5], &format!("{:.3}", result.overall_stats.median() * MILLISECONDS_PER_SECOND), true, None, false);
-                                        draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[6], &format!("{:.3}", result.overall_stats.std_dev() * MILLISECONDS_PER_SECOND), true, None, false);
+                                        draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[4], &format_ms_value(result.overall_stats.mean()), true, None, false);
+                                        draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[5], &format_ms_value(result.overall_stats.median()), true, None, false);
+                                        draw_clickable_right_aligned_text_cell(ui_data_grid, col_widths[6], &format_ms_value(result.overall_stats.std_dev()), true, None, false);
                                         ui_data_grid.end_row();
                                     }
                                 });
+
+                            let mut node_names: Vec<&String> = result.per_node_results.keys().collect();
+                            node_names.sort();
+
+                            if result.overall_stats.count > 0 {
+                                ui_scroll_content.add_space(10.0);
+                                CollapsingHeader::new("Delay distribution")
+                                    .id_salt("dependency delay distribution")
+                                    .show(ui_scroll_content, |ui_distribution| {
+                                        ui_distribution.horizontal(|ui| {
+                                            node_choice_ui(ui, "distribution_node", &mut self.distribution_node, &node_names);
+                                            ui.checkbox(&mut self.show_delay_cdf, "Show CDF");
+                                        });
+                                        let delays_ms: Vec<f64> = result
+                                            .delays_seconds(self.distribution_node.as_deref())
+                                            .iter()
+                                            .map(|delay| delay * MILLISECONDS_PER_SECOND)
+                                            .collect();
+                                        let x_axis = ChartAxis { label: "Delay (ms)", format: &|ms| format!("{ms:.3}") };
+                                        let chart_size = Vec2::new(ui_distribution.available_width(), 160.0);
+                                        draw_histogram(
+                                            ui_distribution,
+                                            chart_size,
+                                            &histogram(&delays_ms, DELAY_HISTOGRAM_BUCKETS),
+                                            theme().chart_line,
+                                            &x_axis,
+                                        );
+                                        if self.show_delay_cdf {
+                                            let cdf = cumulative_distribution(&delays_ms);
+                                            draw_chart(
+                                                ui_distribution,
+                                                chart_size,
+                                                &[ChartSeries { name: "CDF", color: theme().chart_line, points: &cdf, draw_lines: true }],
+                                                &x_axis,
+                                                &ChartAxis { label: "Fraction of links", format: &|fraction| format!("{fraction:.2}") },
+                                            );
+                                        }
+                                    });
+                                CollapsingHeader::new("Delay trend")
+                                    .id_salt("dependency delay trend")
+                                    .show(ui_scroll_content, |ui_trend| {
+                                        ui_trend.horizontal(|ui| {
+                                            node_choice_ui(ui, "trend_node", &mut self.trend_node, &node_names);
+                                        });
+                                        let trend = result.delay_trend(self.trend_node.as_deref(), DELAY_TREND_BUCKETS);
+                                        let mean_points: Vec<(f64, f64)> = trend.iter().map(|b| (b.start, b.mean_seconds * MILLISECONDS_PER_SECOND)).collect();
+                                        let p99_points: Vec<(f64, f64)> = trend.iter().map(|b| (b.start, b.p99_seconds * MILLISECONDS_PER_SECOND)).collect();
+                                        let chart_size = Vec2::new(ui_trend.available_width(), 200.0);
+                                        let clicked = draw_chart(
+                                            ui_trend,
+                                            chart_size,
+                                            &[
+                                                ChartSeries { name: "mean", color: theme().chart_line, points: &mean_points, draw_lines: true },
+                                                ChartSeries { name: "p99", color: theme().chart_alert, points: &p99_points, draw_lines: true },
+                                            ],
+                                            &ChartAxis { label: "target start", format: &|time| time_format.time_string(time) },
+                                            &ChartAxis { label: "Delay (ms)", format: &|ms| format!("{ms:.3}") },
+                                        );
+                                        if let Some((_, bucket_index)) = clicked {
+                                            let bucket = &trend[bucket_index];
+                                            ui_trend.label(format!(
+                                                "{} - {}: {} links, mean {:.3} ms, p99 {:.3} ms",
+                                                time_format.time_string(bucket.start),
+                                                time_format.time_string(bucket.end),
+                                                bucket.count,
+                                                bucket.mean_seconds * MILLISECONDS_PER_SECOND,
+                                                bucket.p99_seconds * MILLISECONDS_PER_SECOND
+                                            ));
+                                        }
+                                    });
+                            }
+
+                            for node_name in node_names {
+                                let node_result = &result.per_node_results[node_name];
+                                if node_result.links.is_empty() {
+                                    continue;
+                                }
+                                let header = RichText::new(format!("Links on {} ({})", node_name, node_result.links.len()))
+                                    .color(node_color(node_name));
+                                CollapsingHeader::new(header)
+                                    .id_salt(("dependency links", node_name))
+                                    .show(ui_scroll_content, |ui_links| {
+                                        let action = self.link_table.draw(
+                                            ui_links,
+                                            node_name,
+                                            &node_result.links,
+                                            &result.linking_attribute,
+                                            &result.group_by_attribute,
+                                            time_format,
+                                        );
+                                        match action {
+                                            Some(LinkTableAction::ShowDetails(index)) => {
+                                                let link = &node_result.links[index];
+                                                self.show_link_details_popup = Some(LinkDetailsPopupInfo {
+                                                    link: link.clone(),
+                                                    title: format!("Link Details ({node_name})"),
+                                                    node_name: node_name.clone(),
+                                                    group_by_attribute_name: result.group_by_attribute.clone(),
+                                                    linking_attribute_name: result.linking_attribute.clone(),
+                                                    delay_ms: link.delay_seconds * MILLISECONDS_PER_SECOND,
+                                                });
+                                            }
+                                            Some(LinkTableAction::Jump(index)) => {
+                                                if let Some(span) = node_result.links[index].target_spans.first() {
+                                                    self.focus.jump_to(span.clone());
+                                                }
+                                                modal_closed = true;
+                                            }
+                                            None => {}
+                                        }
+                                    });
+                            }
                         } else {
                             ui_scroll_content.label("Select source and target spans, then click 'Analyze' to see dependency statistics.");
                         }
-                    });
-
-                ui_main_column.separator();
-                ui_main_column.add_space(10.0);
-                ui_main_column.horizontal(|ui_close_button_row| {
-                    if ui_close_button_row.button("Close").clicked() {
-                        modal_closed = true;
+                    });
+
+                ui_main_column.separator();
+                ui_main_column.add_space(10.0);
+                ui_main_column.horizontal(|ui_close_button_row| {
+                    if ui_close_button_row.button("Close").clicked() {
+                        modal_closed = true;
+                    }
+                });
+            });
+        });
+
+        // Reset fields if modal got closed
+        if modal_closed {
+            self.show = false;
+            self.spans_processed = false;
+            self.source_span_name = None;
+            self.target_span_name = None;
+            self.source_search_text = String::new();
+            self.target_search_text = String::new();
+            self.threshold = 1;
+            self.threshold_edit_str = self.threshold.to_string();
+            self.linking_attribute = String::new();
+            self.context_linking = ContextLinking::default();
+            self.group_by_attribute = String::new();
+            self.source_scope = SourceScope::default();
+            self.source_timing_strategy = SourceTimingStrategy::default();
+            self.group_aggregation_strategy = GroupAggregationStrategy::default();
+            self.analysis_cardinality = AnalysisCardinality::default();
+            self.error_message = None;
+            self.description_input = String::new();
+        }
+
+        // Show the link details popup if requested
+        self.show_dependency_link_details_modal_ui(
+            ctx,
+            time_format,
+            max_width * 0.92,
+            max_height * 0.8,
+        );
+    }
+
+    // Method to show the dependency link details modal
+    fn show_dependency_link_details_modal_ui(
+        &mut self,
+        ctx: &egui::Context,
+        time_format: &TimeFormat,
+        max_width: f32,
+        max_height: f32,
+    ) {
+        let mut close_requested = false;
+        let mut jump_to_span = None;
+
+        if let Some(details_info) = &self.show_link_details_popup {
+            let popup_id = Id::new(&details_info.title);
+
+            Modal::new(popup_id).show(ctx, |ui| {
+                ui.set_max_width(max_width);
+                ui.set_max_height(max_height);
+                ui.set_min_size(Vec2::new(max_width * 0.5, max_height * 0.5));
+
+                ui.heading(&details_info.title);
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    ui.label("Node:");
+                    ui.label(
+                        RichText::new(&details_info.node_name)
+                            .color(node_color(&details_info.node_name)),
+                    );
+                });
+                ui.label(format!("Delay: {:.3} ms", details_info.delay_ms));
+                if !details_info.linking_attribute_name.is_empty() {
+                    ui.label(format!(
+                        "Linked by attribute: {}",
+                        details_info.linking_attribute_name
+                    ));
+                }
+                if !details_info.group_by_attribute_name.is_empty() {
+                    ui.label(format!(
+                        "Grouped by attribute: {}",
+                        details_info.group_by_attribute_name
+                    ));
+                }
+                ui.separator();
+
+                if let Some(span) = draw_link_visualization_ui_impl(ui, details_info, time_format) {
+                    jump_to_span = Some(span);
+                }
+
+                ui.add_space(10.0);
+                ui.separator();
+                ui.add_space(10.0);
+                if ui.button("Close").clicked() {
+                    close_requested = true;
+                }
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close_requested = true;
+                }
+            });
+        }
+
+        if close_requested {
+            self.show_link_details_popup = None;
+        }
+
+        if let Some(span) = jump_to_span {
+            // Close everything so that the span is visible in the timeline
+            self.show_link_details_popup = None;
+            self.focus.jump_to(span);
+            self.show = false;
+        }
+    }
+
+    /// EXPERIMENTAL FEATURE: Quick setup from analysis description parsing
+    ///
+    /// Parse an analysis description string and populate the modal fields
+    pub fn parse_and_fill_from_description(&mut self, description: &str) -> Result<(), String> {
+        // Clear any previous parse error
+        self.error_message = None;
+
+        // Trim whitespace
+        let desc = description.trim();
+
+        // Check if it starts with the expected prefix
+        if !desc.starts_with("Analysis of dependency:") {
+            return Err("Description must start with 'Analysis of dependency:'".to_string());
+        }
+
+        // Parse source and target span names
+        let after_prefix = desc.strip_prefix("Analysis of dependency:").unwrap().trim();
+
+        // Find the arrow pattern 'source' -> 'target'
+        let arrow_regex = regex::Regex::new(r"'([^']+)'\s*->\s*'([^']+)'").unwrap();
+        let arrow_captures = arrow_regex
+            .captures(after_prefix)
+            .ok_or("Could not find 'source' -> 'target' pattern in quotes")?;
+        let source_name = arrow_captures.get(1).unwrap().as_str().to_string();
+        let target_name = arrow_captures.get(2).unwrap().as_str().to_string();
+
+        // Extract the parameters part (everything in parentheses)
+        let params_start = after_prefix
+            .find('(')
+            .ok_or("Could not find opening parenthesis for parameters")?;
+        let params_end = after_prefix
+            .rfind(')')
+            .ok_or("Could not find closing parenthesis for parameters")?;
+        let params_str = &after_prefix[params_start + 1..params_end];
+
+        // Parse individual parameters
+        let mut cardinality = None;
+        let mut threshold = None;
+        let mut linking_by = None;
+        let mut group_by = None;
+        let mut scope = None;
+        let mut timing = None;
+        let mut group_aggregation = None;
+        let mut context_linking = None;
+
+        // Define the expected parameter names in order
+        let param_names = [
+            "cardinality:",
+            "threshold:",
+            "linking by:",
+            "group by:",
+            "scope:",
+            "timing:",
+            "group aggregation:",
+            "context linking:",
+        ];
+
+        // Parse parameters by finding each parameter name and taking everything until the next parameter name
+        let mut remaining = params_str;
+
+        for (i, &param_name) in param_names.iter().enumerate() {
+            if let Some(start_pos) = remaining.find(param_name) {
+                // Move past the parameter name
+                let value_start = start_pos + param_name.len();
+                let value_part = &remaining[value_start..];
+
+                // Find the next parameter name to determine where this value ends
+                let mut value_end = value_part.len();
+                for &next_param_name in &param_names[i + 1..] {
+                    if let Some(next_pos) = value_part.find(next_param_name) {
+                        value_end = value_end.min(next_pos);
+                    }
+                }
+
+                // Extract and trim the value
+                let value = value_part[..value_end].trim().trim_end_matches(',').trim();
+
+                // Parse the specific parameter
+                match param_name {
+                    "cardinality:" => {
+                        cardinality = Some(match value {
+                            "N-to-1" => AnalysisCardinality::NToOne,
+                            "1-to-N" => AnalysisCardinality::OneToN,
+                            _ => return Err(format!("Unknown cardinality: {value}")),
+                        });
+                    }
+                    "threshold:" => {
+                        threshold = Some(
+                            value
+                                .parse::<usize>()
+                                .map_err(|_| format!("Invalid threshold: {value}"))?,
+                        );
+                    }
+                    "linking by:" => {
+                        linking_by = Some(if value == "none" {
+                            String::new()
+                        } else {
+                            value.to_string()
+                        });
+                    }
+                    "group by:" => {
+                        group_by = Some(if value == "none" {
+                            String::new()
+                        } else {
+                            value.to_string()
+                        });
+                    }
+                    "scope:" => {
+                        scope = Some(match value {
+                            "self" => SourceScope::SameNode,
+                            "all nodes" => SourceScope::AllNodes,
+                            _ => return Err(format!("Unknown scope: {value}")),
+                        });
+                    }
+                    "timing:" => {
+                        timing = Some(match value {
+                            "Earliest First" => SourceTimingStrategy::EarliestFirst,
+                            "Latest First" => SourceTimingStrategy::LatestFirst,
+                            _ => return Err(format!("Unknown timing strategy: {value}")),
+                        });
+                    }
+                    "group aggregation:" => {
+                        group_aggregation = Some(match value {
+                            "Wait For Last Group" => GroupAggregationStrategy::WaitForLastGroup,
+                            "First Completed Group" => {
+                                GroupAggregationStrategy::FirstCompletedGroup
+                            }
+                            _ => {
+                                return Err(format!("Unknown group aggregation strategy: {value}"))
+                            }
+                        });
+                    }
+                    "context linking:" => {
+                        context_linking = Some(match value {
+                            "none" => ContextLinking::None,
+                            "same trace" => ContextLinking::SameTrace,
+                            "parent/child" => ContextLinking::ParentChild,
+                            _ => return Err(format!("Unknown context linking: {value}")),
+                        });
+                    }
+                    _ => {}
+                }
+
+                // Move the remaining string forward to avoid processing the same parameter again
+                remaining = &remaining[value_start + value_end..];
+            }
+        }
+
+        // Apply the parsed values
+        self.source_span_name = Some(source_name);
+        self.target_span_name = Some(target_name);
+
+        if let Some(card) = cardinality {
+            self.analysis_cardinality = card;
+        }
+
+        if let Some(thresh) = threshold {
+            self.threshold = thresh.max(1);
+            self.threshold_edit_str = self.threshold.to_string();
+        }
+
+        if let Some(linking) = linking_by {
+            self.linking_attribute = linking;
+        }
+
+        if let Some(grouping) = group_by {
+            self.group_by_attribute = grouping;
+        }
+
+        if let Some(sc) = scope {
+            self.source_scope = sc;
+        }
+
+        if let Some(tim) = timing {
+            self.source_timing_strategy = tim;
+        }
+
+        if let Some(agg) = group_aggregation {
+            self.group_aggregation_strategy = agg;
+        }
+
+        // Context linking is omitted from the description when it isn't used.
+        self.context_linking = context_linking.unwrap_or_default();
+
+        // Update search text to match the selected spans
+        if let Some(ref source) = self.source_span_name {
+            self.source_search_text = source.clone();
+        }
+        if let Some(ref target) = self.target_span_name {
+            self.target_search_text = target.clone();
+        }
+
+        Ok(())
+    }
+
+    fn show_presets_ui(&mut self, ui: &mut Ui, presets: &mut Vec<DependencyPreset>) {
+        ui.horizontal(|ui| {
+            let mut chosen = None;
+            ComboBox::from_id_salt("dependency presets")
+                .selected_text("Load preset")
+                .show_ui(ui, |ui| {
+                    if presets.is_empty() {
+                        ui.label("No saved presets");
+                    }
+                    for (i, preset) in presets.iter().enumerate() {
+                        if ui
+                            .selectable_label(false, &preset.name)
+                            .on_hover_text(format!(
+                                "'{}' -> '{}'",
+                                preset.source_span_name, preset.target_span_name
+                            ))
+                            .clicked()
+                        {
+                            chosen = Some(i);
+                        }
+                    }
+                });
+            if let Some(i) = chosen {
+                let preset = presets[i].clone();
+                self.apply_preset(&preset);
+                self.preset_name_input = preset.name;
+            }
+
+            ui.separator();
+            ui.add(
+                TextEdit::singleline(&mut self.preset_name_input)
+                    .hint_text("Preset name")
+                    .desired_width(200.0),
+            );
+            if ui
+                .button("Save preset")
+                .on_hover_text("Save the current configuration under this name")
+                .clicked()
+            {
+                match self.save_preset(presets, &self.preset_name_input.clone()) {
+                    Ok(()) => self.error_message = None,
+                    Err(err) => self.error_message = Some(err),
+                }
+            }
+            let existing = presets
+                .iter()
+                .position(|p| p.name == self.preset_name_input.trim());
+            if ui
+                .add_enabled(existing.is_some(), Button::new("Delete preset"))
+                .clicked()
+            {
+                presets.remove(existing.unwrap());
+                self.presets_changed = true;
+            }
+        });
+    }
+
+    /// EXPERIMENTAL FEATURE: Quick setup from analysis description parsing
+    fn show_quick_setup_parsing_ui(&mut self, ui_main_column: &mut Ui) {
+        ui_main_column.collapsing("Quick Setup from Analysis Description", |ui_quick_setup| {
+            ui_quick_setup.label("Paste an analysis description to automatically fill all fields:");
+            ui_quick_setup.add_space(5.0);
+
+            ui_quick_setup.horizontal(|ui_input_row| {
+                ui_input_row.add(
+                    TextEdit::multiline(&mut self.description_input)
+                        .desired_width(ui_input_row.available_width() - 120.0)
+                        .desired_rows(3)
+                        .hint_text("Analysis of dependency: 'source_span' -> 'target_span' (cardinality: 1-to-N, threshold: 1, linking by: none, group by: none, scope: all nodes, timing: Earliest First, group aggregation: First Completed Group)")
+                );
+
+                ui_input_row.vertical(|ui_button_col| {
+                    if ui_button_col.button("Parse, Fill and Analyze").clicked() {
+                        if let Err(err) = self.parse_and_fill_from_description(&self.description_input.clone()) {
+                            self.error_message = Some(format!("Parse error: {err}"));
+                        } else {
+                            // Clear parse errors but keep other error messages
+                            if let Some(ref msg) = self.error_message {
+                                if msg.starts_with("Parse error:") {
+                                    self.error_message = None;
+                                }
+                            }
+                            // After successful parsing, run the analysis
+                            self.start_analysis();
+                        }
                     }
                 });
             });
+
+            if let Some(ref error) = self.error_message {
+                if error.starts_with("Parse error:") {
+                    ui_quick_setup.add_space(5.0);
+                    ui_quick_setup.colored_label(theme().error, error);
+                }
+            }
         });
+    }
+}
 
-        // Reset fields if modal got closed
-        if modal_closed {
-            self.show = false;
-            self.spans_processed = false;
-            self.source_span_name = None;
-            self.target_span_name = None;
-            self.source_search_text = String::new();
-            self.target_search_text = String::new();
-            self.threshold = 1;
-            self.threshold_edit_str = self.threshold.to_string();
-            self.linking_attribute = String::new();
-            self.group_by_attribute = String::new();
-            self.source_scope = SourceScope::default();
-            self.source_timing_strategy = SourceTimingStrategy::default();
-            self.group_aggregation_strategy = GroupAggregationStrategy::default();
-            self.analysis_cardinality = AnalysisCardinality::default();
-            self.error_message = None;
-            self.description_input = String::new();
+/// Span as seen by the analysis workers: the thread safe span data and the position of the span
+/// in the sorted list of source or target spans, used to find the displayed span afterwards.
+#[derive(Clone)]
+struct WorkSpan {
+    index: usize,
+    data: Arc<SpanData>,
+}
+
+impl Deref for WorkSpan {
+    type Target = SpanData;
+
+    fn deref(&self) -> &SpanData {
+        &self.data
+    }
+}
+
+type WorkNodeResult = (String, NodeDependencyMetrics<WorkSpan>);
+
+/// Analysis running in the background.
+struct RunningAnalysis {
+    /// Parameters of the analysis and results of the nodes that are already done.
+    result: DependencyAnalysisResult,
+    source_spans: Vec<Rc<Span>>,
+    target_spans: Vec<Rc<Span>>,
+    analysis_start: Instant,
+    task: BackgroundTask<Option<WorkNodeResult>>,
+}
+
+/// Parameters of the analysis which are needed on the worker threads.
+struct AnalysisSettings {
+    threshold: usize,
+    linking_patterns: Vec<LinkingPattern>,
+    context_linking: ContextLinking,
+    source_scope: SourceScope,
+    source_timing_strategy: SourceTimingStrategy,
+    group_by_attribute: String,
+    group_aggregation_strategy: GroupAggregationStrategy,
+}
+
+impl AnalysisSettings {
+    /// Selects a subset of source spans based on the configured timing strategy and threshold.
+    fn select_spans_for_link_formation(&self, available_spans: &[WorkSpan]) -> Vec<WorkSpan> {
+        assert!(self.threshold >= 1);
+        let num_to_take = self.threshold;
+
+        match self.source_timing_strategy {
+            SourceTimingStrategy::EarliestFirst => {
+                available_spans.iter().take(num_to_take).cloned().collect()
+            }
+            SourceTimingStrategy::LatestFirst => {
+                let skip_count = available_spans.len().saturating_sub(num_to_take);
+                available_spans.iter().skip(skip_count).cloned().collect()
+            }
         }
+    }
 
-        // Show the link details popup if requested
-        self.show_dependency_link_details_modal_ui(ctx, max_width * 0.92, max_height * 0.8);
+    /// Records a successfully formed dependency link, updates statistics, and tracks min/max delay links.
+    #[allow(clippy::too_many_arguments)]
+    fn record_formed_link(
+        &self,
+        stats: &mut Statistics,
+        node_links: &mut Vec<DependencyLink<WorkSpan>>,
+        min_link_for_node: &mut Option<DependencyLink<WorkSpan>>,
+        max_link_for_node: &mut Option<DependencyLink<WorkSpan>>,
+        formed_link: &DependencyLink<WorkSpan>,
+        link_delay: f64,
+        used_spans: &mut HashSet<SpanId>,
+        span_id: SpanId,
+    ) {
+        stats.add_value(link_delay);
+        node_links.push(formed_link.clone());
+        used_spans.insert(span_id);
+
+        // Update min/max links
+        // stats.count, stats.min, stats.max are updated by stats.add_value()
+        if stats.count == 1 {
+            // This means it's the first link added to these stats
+            *min_link_for_node = Some(formed_link.clone());
+            *max_link_for_node = Some(formed_link.clone());
+        } else {
+            if link_delay == stats.min {
+                *min_link_for_node = Some(formed_link.clone());
+            }
+            // A link can be both min and max if it's the only one, or if multiple links share the same min/max delay.
+            if link_delay == stats.max {
+                *max_link_for_node = Some(formed_link.clone());
+            }
+        }
     }
 
-    // Method to show the dependency link details modal
-    fn show_dependency_link_details_modal_ui(
-        &mut self,
-        ctx: &egui::Context,
-        max_width: f32,
-        max_height: f32,
+    /// Marks the source spans of a formed link as used according to the source scope.
+    fn mark_source_spans_used(
+        &self,
+        source_spans_in_link: &[WorkSpan],
+        global_used_source_span_ids_for_self_mode: &mut HashSet<SpanId>,
+        used_source_ids_for_current_node_all_scope: &mut HashSet<SpanId>,
     ) {
-        let mut close_requested = false;
+        for linked_s_span in source_spans_in_link {
+            if self.source_scope == SourceScope::SameNode {
+                global_used_source_span_ids_for_self_mode.insert(linked_s_span.span_id);
+            } else {
+                used_source_ids_for_current_node_all_scope.insert(linked_s_span.span_id);
+            }
+        }
+    }
 
-        if let Some(details_info) = &self.show_link_details_popup {
-            let popup_id = Id::new(&details_info.title);
+    /// Checks if two spans have matching values for all linking patterns and satisfy the context
+    /// linking.
+    fn spans_match_linking_attributes(
+        &self,
+        source_span: &WorkSpan,
+        target_span: &WorkSpan,
+    ) -> bool {
+        self.context_linking.matches(source_span, target_span)
+            && self
+                .linking_patterns
+                .iter()
+                .all(|pattern| pattern.matches(&source_span.attributes, &target_span.attributes))
+    }
 
-            Modal::new(popup_id).show(ctx, |ui| {
-                ui.set_max_width(max_width);
-                ui.set_max_height(max_height);
-                ui.set_min_size(Vec2::new(max_width * 0.5, max_height * 0.5));
+    /// Analyzes dependencies for a single node.
+    fn analyze_dependencies_for_single_node_n_to_one(
+        &self,
+        node_name: &str,
+        source_spans_by_node: &HashMap<String, Vec<WorkSpan>>,
+        current_target_node_spans: &[WorkSpan],
+        global_used_source_span_ids_for_self_mode: &mut HashSet<SpanId>,
+        expected_group_keys_set: &Option<HashSet<String>>,
+    ) -> Option<NodeDependencyMetrics<WorkSpan>> {
+        let current_source_node_spans = if self.source_scope == SourceScope::SameNode {
+            // Only use spans from this node
+            source_spans_by_node
+                .get(node_name)
+                .cloned()
+                .unwrap_or_default()
+        } else {
+            // Use spans from all nodes, sorted by time
+            let mut all_s_spans: Vec<WorkSpan> =
+                source_spans_by_node.values().flatten().cloned().collect();
+            all_s_spans.sort_by(|a, b| {
+                a.start_time
+                    .partial_cmp(&b.start_time)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            all_s_spans
+        };
 
-                ui.heading(&details_info.title);
-                ui.add_space(5.0);
-                ui.label(format!("Node: {}", details_info.node_name));
-                ui.label(format!("Delay: {:.3} ms", details_info.delay_ms));
-                if !details_info.linking_attribute_name.is_empty() {
-                    ui.label(format!(
-                        "Linked by attribute: {}",
-                        details_info.linking_attribute_name
-                    ));
-                }
-                if !details_info.group_by_attribute_name.is_empty() {
-                    ui.label(format!(
-                        "Grouped by attribute: {}",
-                        details_info.group_by_attribute_name
-                    ));
-                }
-                ui.separator();
+        // Skip if no source spans for this node/scope
+        if current_source_node_spans.is_empty() {
+            return None;
+        }
 
-                draw_link_visualization_ui_impl(ui, details_info);
+        // Find valid links for the current node
+        let mut node_links_for_current_node = Vec::new();
+        let mut stats_for_current_node = Statistics::new();
+        let mut min_link_for_current_node: Option<DependencyLink<WorkSpan>> = None;
+        let mut max_link_for_current_node: Option<DependencyLink<WorkSpan>> = None;
 
-                ui.add_space(10.0);
-                ui.separator();
-                ui.add_space(10.0);
-                if ui.button("Close").clicked() {
-                    close_requested = true;
+        let mut used_target_spans: HashSet<SpanId> = HashSet::new();
+        // This is specific to the current node when in "all nodes" scope, ensuring sources are not reused for different targets *on this same node* within this call.
+        let mut used_source_ids_for_current_node_all_scope: HashSet<SpanId> = HashSet::new();
+
+        for target_span_rc in current_target_node_spans.iter() {
+            if used_target_spans.contains(&target_span_rc.span_id) {
+                // This target has already been linked by a source group
+                continue;
+            }
+
+            self.process_target_span_for_links(
+                target_span_rc, // Pass as &WorkSpan
+                &current_source_node_spans,
+                expected_group_keys_set,
+                global_used_source_span_ids_for_self_mode,
+                &mut used_source_ids_for_current_node_all_scope, // Pass as mutable ref
+                &mut node_links_for_current_node,
+                &mut stats_for_current_node,
+                &mut min_link_for_current_node,
+                &mut max_link_for_current_node,
+                &mut used_target_spans,
+            );
+        }
+
+        // Add result for this node if any links were formed
+        if !node_links_for_current_node.is_empty() || stats_for_current_node.count > 0 {
+            Some(NodeDependencyMetrics {
+                link_delay_statistics: stats_for_current_node,
+                links: node_links_for_current_node,
+                min_delay_link: min_link_for_current_node,
+                max_delay_link: max_link_for_current_node,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Processes a single target span to find and record dependency links.
+    #[allow(clippy::too_many_arguments)]
+    fn process_target_span_for_links(
+        &self,
+        target_span: &WorkSpan,
+        current_source_node_spans: &[WorkSpan],
+        expected_group_keys_set: &Option<HashSet<String>>,
+        global_used_source_span_ids_for_self_mode: &mut HashSet<SpanId>,
+        used_source_ids_for_current_node_all_scope: &mut HashSet<SpanId>,
+        node_links_for_current_node: &mut Vec<DependencyLink<WorkSpan>>,
+        stats_for_current_node: &mut Statistics,
+        min_link_for_current_node: &mut Option<DependencyLink<WorkSpan>>,
+        max_link_for_current_node: &mut Option<DependencyLink<WorkSpan>>,
+        used_target_spans: &mut HashSet<SpanId>,
+    ) {
+        // Common logic to find all temporally valid and not-yet-used source spans
+        let mut eligible_sources_before_target: Vec<WorkSpan> = Vec::new();
+        for s_span in current_source_node_spans.iter() {
+            let mut skip_source = false;
+            if self.source_scope == SourceScope::SameNode {
+                if global_used_source_span_ids_for_self_mode.contains(&s_span.span_id) {
+                    skip_source = true;
                 }
-                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
-                    close_requested = true;
+            } else {
+                // "all nodes" mode
+                if used_source_ids_for_current_node_all_scope.contains(&s_span.span_id) {
+                    skip_source = true;
                 }
-            });
+            }
+            if skip_source {
+                continue;
+            }
+
+            // Basic time validity
+            if s_span.end_time <= target_span.start_time {
+                // For grouping mode, defer linking attribute check to after grouping
+                // For non-grouping mode, check linking attribute compatibility here
+                if self.group_by_attribute.is_empty() {
+                    // Check linking attribute compatibility using the new multi-attribute function
+                    if self.spans_match_linking_attributes(s_span, target_span) {
+                        eligible_sources_before_target.push(s_span.clone());
+                    }
+                } else {
+                    // In grouping mode, add all temporally valid sources and check linking later
+                    eligible_sources_before_target.push(s_span.clone());
+                }
+            }
         }
 
-        if close_requested {
-            self.show_link_details_popup = None;
-        }
-    }
+        // Branch based on grouping
+        if !self.group_by_attribute.is_empty() && expected_group_keys_set.is_some() {
+            // GROUPING LOGIC
+            let mut grouped_potential_sources: HashMap<String, Vec<WorkSpan>> = HashMap::new();
+            for s_span in &eligible_sources_before_target {
+                if let Some(Some(Value::StringValue(s_val))) =
+                    s_span.attributes.get(&self.group_by_attribute)
+                {
+                    grouped_potential_sources
+                        .entry(s_val.clone())
+                        .or_default()
+                        .push(s_span.clone());
+                }
+            }
+
+            let mut all_valid_groups_meet_threshold = true;
+            let mut spans_for_this_grouped_link: Vec<WorkSpan> = Vec::new();
+            let mut valid_groups_count = 0;
 
-    /// EXPERIMENTAL FEATURE: Quick setup from analysis description parsing
-    ///
-    /// Parse an analysis description string and populate the modal fields
-    pub fn parse_and_fill_from_description(&mut self, description: &str) -> Result<(), String> {
-        // Clear any previous parse error
-        self.error_message = None;
+            // Check each group - only count groups where individually matching sources meet threshold
+            for group_spans in grouped_potential_sources.values() {
+                // Filter to only sources that individually match the linking attribute
+                let matching_sources_in_group: Vec<WorkSpan> = if !self.linking_patterns.is_empty()
+                    || self.context_linking != ContextLinking::None
+                {
+                    group_spans
+                        .iter()
+                        .filter(|s_span| self.spans_match_linking_attributes(s_span, target_span))
+                        .cloned()
+                        .collect()
+                } else {
+                    group_spans.clone()
+                };
 
-        // Trim whitespace
-        let desc = description.trim();
+                if matching_sources_in_group.len() >= self.threshold && self.threshold > 0 {
+                    let selected_from_group =
+                        self.select_spans_for_link_formation(&matching_sources_in_group);
+                    spans_for_this_grouped_link.extend(selected_from_group);
+                    valid_groups_count += 1;
+                } else if !matching_sources_in_group.is_empty() {
+                    // This group has matching sources but not enough to meet threshold
+                    all_valid_groups_meet_threshold = false;
+                    break;
+                }
+            }
 
-        // Check if it starts with the expected prefix
-        if !desc.starts_with("Analysis of dependency:") {
-            return Err("Description must start with 'Analysis of dependency:'".to_string());
-        }
+            // Only proceed if we have at least some valid groups and all valid groups meet threshold
+            if all_valid_groups_meet_threshold
+                && valid_groups_count > 0
+                && !spans_for_this_grouped_link.is_empty()
+            {
+                // Calculate link delay based on aggregation strategy for groups
+                let link_delay = match self.group_aggregation_strategy {
+                    GroupAggregationStrategy::WaitForLastGroup => {
+                        spans_for_this_grouped_link
+                            .iter()
+                            .map(|s| s.end_time)
+                            .fold(f64::NEG_INFINITY, f64::max)
+                            - target_span.start_time
+                    }
+                    GroupAggregationStrategy::FirstCompletedGroup => {
+                        let mut latest_end_time_per_group: HashMap<String, f64> = HashMap::new();
+                        for s_span in &spans_for_this_grouped_link {
+                            if let Some(Some(Value::StringValue(group_key))) =
+                                s_span.attributes.get(&self.group_by_attribute)
+                            {
+                                latest_end_time_per_group
+                                    .entry(group_key.clone())
+                                    .and_modify(|e| *e = e.max(s_span.end_time))
+                                    .or_insert(s_span.end_time);
+                            }
+                        }
+                        latest_end_time_per_group
+                            .values()
+                            .fold(f64::INFINITY, |a, &b| a.min(b))
+                            - target_span.start_time
+                    }
+                }
+                .abs();
 
-        // Parse source and target span names
-        let after_prefix = desc.strip_prefix("Analysis of dependency:").unwrap().trim();
+                let new_formed_link = DependencyLink {
+                    source_spans: spans_for_this_grouped_link.clone(),
+                    target_spans: vec![target_span.clone()],
+                    delay_seconds: link_delay,
+                };
 
-        // Find the arrow pattern 'source' -> 'target'
-        let arrow_regex = regex::Regex::new(r"'([^']+)'\s*->\s*'([^']+)'").unwrap();
-        let arrow_captures = arrow_regex
-            .captures(after_prefix)
-            .ok_or("Could not find 'source' -> 'target' pattern in quotes")?;
-        let source_name = arrow_captures.get(1).unwrap().as_str().to_string();
-        let target_name = arrow_captures.get(2).unwrap().as_str().to_string();
+                self.record_formed_link(
+                    stats_for_current_node,
+                    node_links_for_current_node,
+                    min_link_for_current_node,
+                    max_link_for_current_node,
+                    &new_formed_link,
+                    link_delay,
+                    used_target_spans,
+                    target_span.span_id,
+                );
 
-        // Extract the parameters part (everything in parentheses)
-        let params_start = after_prefix
-            .find('(')
-            .ok_or("Could not find opening parenthesis for parameters")?;
-        let params_end = after_prefix
-            .rfind(')')
-            .ok_or("Could not find closing parenthesis for parameters")?;
-        let params_str = &after_prefix[params_start + 1..params_end];
+                self.mark_source_spans_used(
+                    &spans_for_this_grouped_link,
+                    global_used_source_span_ids_for_self_mode,
+                    used_source_ids_for_current_node_all_scope,
+                );
+            }
+        } else {
+            // NON-GROUPING LOGIC
+            if eligible_sources_before_target.len() >= self.threshold && self.threshold > 0 {
+                let selected_source_spans_group =
+                    self.select_spans_for_link_formation(&eligible_sources_before_target);
 
-        // Parse individual parameters
-        let mut cardinality = None;
-        let mut threshold = None;
-        let mut linking_by = None;
-        let mut group_by = None;
-        let mut scope = None;
-        let mut timing = None;
-        let mut group_aggregation = None;
+                if !selected_source_spans_group.is_empty() {
+                    let latest_end_time_of_selected_sources = selected_source_spans_group
+                        .iter()
+                        .map(|s| s.end_time)
+                        .fold(f64::NEG_INFINITY, f64::max);
 
-        // Define the expected parameter names in order
-        let param_names = [
-            "cardinality:",
-            "threshold:",
-            "linking by:",
-            "group by:",
-            "scope:",
-            "timing:",
-            "group aggregation:",
-        ];
+                    let link_distance =
+                        (target_span.start_time - latest_end_time_of_selected_sources).abs();
 
-        // Parse parameters by finding each parameter name and taking everything until the next parameter name
-        let mut remaining = params_str;
+                    let new_formed_link = DependencyLink {
+                        source_spans: selected_source_spans_group.clone(),
+                        target_spans: vec![target_span.clone()],
+                        delay_seconds: link_distance,
+                    };
 
-        for (i, &param_name) in param_names.iter().enumerate() {
-            if let Some(start_pos) = remaining.find(param_name) {
-                // Move past the parameter name
-                let value_start = start_pos + param_name.len();
-                let value_part = &remaining[value_start..];
+                    self.record_formed_link(
+                        stats_for_current_node,
+                        node_links_for_current_node,
+                        min_link_for_current_node,
+                        max_link_for_current_node,
+                        &new_formed_link,
+                        link_distance,
+                        used_target_spans,
+                        target_span.span_id,
+                    );
 
-                // Find the next parameter name to determine where this value ends
-                let mut value_end = value_part.len();
-                for &next_param_name in &param_names[i + 1..] {
-                    if let Some(next_pos) = value_part.find(next_param_name) {
-                        value_end = value_end.min(next_pos);
-                    }
+                    self.mark_source_spans_used(
+                        &selected_source_spans_group,
+                        global_used_source_span_ids_for_self_mode,
+                        used_source_ids_for_current_node_all_scope,
+                    );
                 }
+            }
+        }
+    }
 
-                // Extract and trim the value
-                let value = value_part[..value_end].trim().trim_end_matches(',').trim();
-
-                // Parse the specific parameter
-                match param_name {
-                    "cardinality:" => {
-                        cardinality = Some(match value {
-                            "N-to-1" => AnalysisCardinality::NToOne,
-                            "1-to-N" => AnalysisCardinality::OneToN,
-                            _ => return Err(format!("Unknown cardinality: {value}")),
-                        });
-                    }
-                    "threshold:" => {
-                        threshold = Some(
-                            value
-                                .parse::<usize>()
-                                .map_err(|_| format!("Invalid threshold: {value}"))?,
-                        );
-                    }
-                    "linking by:" => {
-                        linking_by = Some(if value == "none" {
-                            String::new()
-                        } else {
-                            value.to_string()
-                        });
-                    }
-                    "group by:" => {
-                        group_by = Some(if value == "none" {
-                            String::new()
-                        } else {
-                            value.to_string()
-                        });
-                    }
-                    "scope:" => {
-                        scope = Some(match value {
-                            "self" => SourceScope::SameNode,
-                            "all nodes" => SourceScope::AllNodes,
-                            _ => return Err(format!("Unknown scope: {value}")),
-                        });
-                    }
-                    "timing:" => {
-                        timing = Some(match value {
-                            "Earliest First" => SourceTimingStrategy::EarliestFirst,
-                            "Latest First" => SourceTimingStrategy::LatestFirst,
-                            _ => return Err(format!("Unknown timing strategy: {value}")),
-                        });
-                    }
-                    "group aggregation:" => {
-                        group_aggregation = Some(match value {
-                            "Wait For Last Group" => GroupAggregationStrategy::WaitForLastGroup,
-                            "First Completed Group" => {
-                                GroupAggregationStrategy::FirstCompletedGroup
-                            }
-                            _ => {
-                                return Err(format!("Unknown group aggregation strategy: {value}"))
-                            }
-                        });
-                    }
-                    _ => {}
-                }
+    /// Analyzes dependencies for a single node in 1-to-N mode.
+    fn analyze_dependencies_for_single_node_one_to_n(
+        &self,
+        node_name: &str,
+        current_source_node_spans: &[WorkSpan],
+        target_spans_by_node: &HashMap<String, Vec<WorkSpan>>,
+        global_used_target_span_ids_for_self_mode: &mut HashSet<SpanId>,
+        expected_group_keys_set: &Option<HashSet<String>>,
+    ) -> Option<NodeDependencyMetrics<WorkSpan>> {
+        // Get all potential target spans based on source scope
+        let current_target_spans = if self.source_scope == SourceScope::SameNode {
+            // Only use targets from this same node
+            target_spans_by_node
+                .get(node_name)
+                .cloned()
+                .unwrap_or_default()
+        } else {
+            // Use targets from all nodes, sorted by time
+            let mut all_t_spans: Vec<WorkSpan> =
+                target_spans_by_node.values().flatten().cloned().collect();
+            all_t_spans.sort_by(|a, b| {
+                a.start_time
+                    .partial_cmp(&b.start_time)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            all_t_spans
+        };
 
-                // Move the remaining string forward to avoid processing the same parameter again
-                remaining = &remaining[value_start + value_end..];
-            }
+        // Skip if no target spans for this scope
+        if current_target_spans.is_empty() {
+            return None;
         }
 
-        // Apply the parsed values
-        self.source_span_name = Some(source_name);
-        self.target_span_name = Some(target_name);
+        // Find valid links for the current source node
+        let mut node_links_for_current_node = Vec::new();
+        let mut stats_for_current_node = Statistics::new();
+        let mut min_link_for_current_node: Option<DependencyLink<WorkSpan>> = None;
+        let mut max_link_for_current_node: Option<DependencyLink<WorkSpan>> = None;
 
-        if let Some(card) = cardinality {
-            self.analysis_cardinality = card;
-        }
+        let mut used_source_spans: HashSet<SpanId> = HashSet::new();
+        // This is specific to the current node when in "all nodes" scope
+        let mut used_target_ids_for_current_node_all_scope: HashSet<SpanId> = HashSet::new();
 
-        if let Some(thresh) = threshold {
-            self.threshold = thresh.max(1);
-            self.threshold_edit_str = self.threshold.to_string();
-        }
+        for source_span_rc in current_source_node_spans.iter() {
+            if used_source_spans.contains(&source_span_rc.span_id) {
+                // This source has already been processed
+                continue;
+            }
 
-        if let Some(linking) = linking_by {
-            self.linking_attribute = linking;
+            self.process_source_span_for_one_to_n_links(
+                source_span_rc,
+                &current_target_spans,
+                expected_group_keys_set,
+                global_used_target_span_ids_for_self_mode,
+                &mut used_target_ids_for_current_node_all_scope,
+                &mut node_links_for_current_node,
+                &mut stats_for_current_node,
+                &mut min_link_for_current_node,
+                &mut max_link_for_current_node,
+                &mut used_source_spans,
+            );
         }
 
-        if let Some(grouping) = group_by {
-            self.group_by_attribute = grouping;
+        // Add result for this node if any links were formed
+        if !node_links_for_current_node.is_empty() || stats_for_current_node.count > 0 {
+            Some(NodeDependencyMetrics {
+                link_delay_statistics: stats_for_current_node,
+                links: node_links_for_current_node,
+                min_delay_link: min_link_for_current_node,
+                max_delay_link: max_link_for_current_node,
+            })
+        } else {
+            None
         }
+    }
 
-        if let Some(sc) = scope {
-            self.source_scope = sc;
-        }
+    /// Processes a single source span to find and record dependency links in 1-to-N mode.
+    #[allow(clippy::too_many_arguments)]
+    fn process_source_span_for_one_to_n_links(
+        &self,
+        source_span: &WorkSpan,
+        current_target_spans: &[WorkSpan],
+        expected_group_keys_set: &Option<HashSet<String>>,
+        global_used_target_span_ids_for_self_mode: &mut HashSet<SpanId>,
+        used_target_ids_for_current_node_all_scope: &mut HashSet<SpanId>,
+        node_links_for_current_node: &mut Vec<DependencyLink<WorkSpan>>,
+        stats_for_current_node: &mut Statistics,
+        min_link_for_current_node: &mut Option<DependencyLink<WorkSpan>>,
+        max_link_for_current_node: &mut Option<DependencyLink<WorkSpan>>,
+        used_source_spans: &mut HashSet<SpanId>,
+    ) {
+        // Common logic to find all temporally valid, attribute-matching, and not-yet-used target spans
+        let mut eligible_targets_after_source: Vec<WorkSpan> = Vec::new();
+        for t_span in current_target_spans.iter() {
+            let mut skip_target = false;
+            if self.source_scope == SourceScope::SameNode {
+                if global_used_target_span_ids_for_self_mode.contains(&t_span.span_id) {
+                    skip_target = true;
+                }
+            } else {
+                // "all nodes" mode
+                if used_target_ids_for_current_node_all_scope.contains(&t_span.span_id) {
+                    skip_target = true;
+                }
+            }
+            if skip_target {
+                continue;
+            }
 
-        if let Some(tim) = timing {
-            self.source_timing_strategy = tim;
+            // Basic time validity: target must start after source ends
+            if t_span.start_time >= source_span.end_time
+                && self.spans_match_linking_attributes(source_span, t_span)
+            {
+                eligible_targets_after_source.push(t_span.clone());
+            }
         }
 
-        if let Some(agg) = group_aggregation {
-            self.group_aggregation_strategy = agg;
-        }
+        // Branch based on grouping
+        if !self.group_by_attribute.is_empty() && expected_group_keys_set.is_some() {
+            // GROUPING LOGIC
+            let mut grouped_potential_targets: HashMap<String, Vec<WorkSpan>> = HashMap::new();
+            for t_span in &eligible_targets_after_source {
+                if let Some(Some(Value::StringValue(t_val))) =
+                    t_span.attributes.get(&self.group_by_attribute)
+                {
+                    grouped_potential_targets
+                        .entry(t_val.clone())
+                        .or_default()
+                        .push(t_span.clone());
+                }
+            }
 
-        // Update search text to match the selected spans
-        if let Some(ref source) = self.source_span_name {
-            self.source_search_text = source.clone();
-        }
-        if let Some(ref target) = self.target_span_name {
-            self.target_search_text = target.clone();
-        }
+            let mut all_groups_meet_threshold = true;
+            let mut targets_for_this_grouped_link: Vec<WorkSpan> = Vec::new();
 
-        Ok(())
-    }
+            // Check if each group that exists in eligible targets meets threshold
+            // (Don't require all originally expected groups - some may be filtered out by linking attributes)
+            for group_targets in grouped_potential_targets.values() {
+                if group_targets.len() >= self.threshold && self.threshold > 0 {
+                    let selected_from_group =
+                        self.select_targets_for_one_to_n_link_formation(group_targets);
+                    targets_for_this_grouped_link.extend(selected_from_group);
+                } else {
+                    all_groups_meet_threshold = false;
+                    break;
+                }
+            }
 
-    /// EXPERIMENTAL FEATURE: Quick setup from analysis description parsing
-    fn show_quick_setup_parsing_ui(&mut self, ui_main_column: &mut Ui) {
-        ui_main_column.collapsing("Quick Setup from Analysis Description", |ui_quick_setup| {
-            ui_quick_setup.label("Paste an analysis description to automatically fill all fields:");
-            ui_quick_setup.add_space(5.0);
+            // Only proceed if we have at least some groups and all present groups meet threshold
+            if all_groups_meet_threshold
+                && !grouped_potential_targets.is_empty()
+                && !targets_for_this_grouped_link.is_empty()
+            {
+                // Create a single link with one source and multiple targets
+                // For consistency with N-to-1 mode, always use the LATEST target start time
+                // (representing "how long until ALL targets have started")
+                let link_delay = (targets_for_this_grouped_link
+                    .iter()
+                    .map(|t| t.start_time)
+                    .fold(f64::NEG_INFINITY, f64::max)
+                    - source_span.end_time)
+                    .abs();
 
-            ui_quick_setup.horizontal(|ui_input_row| {
-                ui_input_row.add(
-                    TextEdit::multiline(&mut self.description_input)
-                        .desired_width(ui_input_row.available_width() - 120.0)
-                        .desired_rows(3)
-                        .hint_text("Analysis of dependency: 'source_span' -> 'target_span' (cardinality: 1-to-N, threshold: 1, linking by: none, group by: none, scope: all nodes, timing: Earliest First, group aggregation: First Completed Group)")
+                let new_formed_link = DependencyLink {
+                    source_spans: vec![source_span.clone()],
+                    target_spans: targets_for_this_grouped_link.clone(),
+                    delay_seconds: link_delay,
+                };
+
+                self.record_formed_link(
+                    stats_for_current_node,
+                    node_links_for_current_node,
+                    min_link_for_current_node,
+                    max_link_for_current_node,
+                    &new_formed_link,
+                    link_delay,
+                    used_source_spans,
+                    source_span.span_id,
                 );
 
-                ui_input_row.vertical(|ui_button_col| {
-                    if ui_button_col.button("Parse, Fill and Analyze").clicked() {
-                        if let Err(err) = self.parse_and_fill_from_description(&self.description_input.clone()) {
-                            self.error_message = Some(format!("Parse error: {err}"));
+                // Mark all targets as used
+                for target_span in &targets_for_this_grouped_link {
+                    if self.source_scope == SourceScope::SameNode {
+                        global_used_target_span_ids_for_self_mode.insert(target_span.span_id);
+                    } else {
+                        used_target_ids_for_current_node_all_scope.insert(target_span.span_id);
+                    }
+                }
+            }
+        } else {
+            // NON-GROUPING LOGIC
+            if eligible_targets_after_source.len() >= self.threshold && self.threshold > 0 {
+                let selected_target_spans_group =
+                    self.select_targets_for_one_to_n_link_formation(&eligible_targets_after_source);
+
+                if !selected_target_spans_group.is_empty() {
+                    // Create a single link with one source and multiple targets
+                    // For consistency with N-to-1 mode, always use the LATEST target start time
+                    // (representing "how long until ALL targets have started")
+                    let link_delay = (selected_target_spans_group
+                        .iter()
+                        .map(|t| t.start_time)
+                        .fold(f64::NEG_INFINITY, f64::max)
+                        - source_span.end_time)
+                        .abs();
+
+                    let new_formed_link = DependencyLink {
+                        source_spans: vec![source_span.clone()],
+                        target_spans: selected_target_spans_group.clone(),
+                        delay_seconds: link_delay,
+                    };
+
+                    self.record_formed_link(
+                        stats_for_current_node,
+                        node_links_for_current_node,
+                        min_link_for_current_node,
+                        max_link_for_current_node,
+                        &new_formed_link,
+                        link_delay,
+                        used_source_spans,
+                        source_span.span_id,
+                    );
+
+                    // Mark all targets as used
+                    for target_span in &selected_target_spans_group {
+                        if self.source_scope == SourceScope::SameNode {
+                            global_used_target_span_ids_for_self_mode.insert(target_span.span_id);
                         } else {
-                            // Clear parse errors but keep other error messages
-                            if let Some(ref msg) = self.error_message {
-                                if msg.starts_with("Parse error:") {
-                                    self.error_message = None;
-                                }
-                            }
-                            // After successful parsing, run the analysis
-                            self.analyze_dependencies();
+                            used_target_ids_for_current_node_all_scope.insert(target_span.span_id);
                         }
                     }
-                });
-            });
-
-            if let Some(ref error) = self.error_message {
-                if error.starts_with("Parse error:") {
-                    ui_quick_setup.add_space(5.0);
-                    ui_quick_setup.colored_label(colors::MILD_RED, error);
                 }
             }
-        });
+        }
+    }
+
+    /// Selects a subset of target spans based on the configured timing strategy and threshold for 1-to-N analysis.
+    fn select_targets_for_one_to_n_link_formation(
+        &self,
+        available_targets: &[WorkSpan],
+    ) -> Vec<WorkSpan> {
+        assert!(self.threshold >= 1);
+        let num_to_take = self.threshold;
+
+        match self.source_timing_strategy {
+            SourceTimingStrategy::EarliestFirst => available_targets
+                .iter()
+                .take(num_to_take)
+                .cloned()
+                .collect(),
+            SourceTimingStrategy::LatestFirst => {
+                let skip_count = available_targets.len().saturating_sub(num_to_take);
+                available_targets.iter().skip(skip_count).cloned().collect()
+            }
+        }
     }
 }
 
 /// Draws the visualization for a dependency link, including source and target spans.
-fn draw_link_visualization_ui_impl(ui: &mut Ui, details: &LinkDetailsPopupInfo) {
+/// Returns the span that the user wants to jump to, if any.
+fn draw_link_visualization_ui_impl(
+    ui: &mut Ui,
+    details: &LinkDetailsPopupInfo,
+    time_format: &TimeFormat,
+) -> Option<Rc<Span>> {
+    let mut jump_to_span = None;
     let mut sorted_source_spans = details.link.source_spans.clone();
     // Sort by end time primarily, then by name as a secondary criterion for stable sort
     sorted_source_spans.sort_by(|a, b| {
@@ -2131,20 +3080,14 @@ fn draw_link_visualization_ui_impl(ui: &mut Ui, details: &LinkDetailsPopupInfo)
                                     * MILLISECONDS_PER_SECOND;
                                 let time_to_target_display =
                                     format!("{:.3}ms", distance_to_target_ms.max(0.0));
-                                let end_timestamp_str =
-                                    crate::types::time_point_to_utc_string(s_span.end_time);
+                                let end_timestamp_str = time_format.display_string(s_span.end_time);
 
                                 ui.strong("Time to Target: ");
                                 let ttt_label_response = ui.monospace(time_to_target_display);
                                 ttt_label_response
                                     .on_hover_text(format!("End: {end_timestamp_str}"));
 
-                                ui.strong(" Node: ");
-                                ui.monospace(&s_span.node.name);
-                                ui.strong(" Name: ");
-                                ui.monospace(&s_span.name);
-                                ui.strong(" ID: ");
-                                ui.monospace(hex::encode(&s_span.span_id));
+                                draw_span_identity(ui, &s_span, &mut jump_to_span);
                             });
                         });
                         ui.add_space(1.0);
@@ -2160,19 +3103,13 @@ fn draw_link_visualization_ui_impl(ui: &mut Ui, details: &LinkDetailsPopupInfo)
                             * MILLISECONDS_PER_SECOND;
                         let time_to_target_display =
                             format!("{:.3}ms", distance_to_target_ms.max(0.0));
-                        let end_timestamp_str =
-                            crate::types::time_point_to_utc_string(s_span.end_time);
+                        let end_timestamp_str = time_format.display_string(s_span.end_time);
 
                         ui.strong("Time to Target: ");
                         let ttt_label_response = ui.monospace(time_to_target_display);
                         ttt_label_response.on_hover_text(format!("End: {end_timestamp_str}"));
 
-                        ui.strong(" Node: ");
-                        ui.monospace(&s_span.node.name);
-                        ui.strong(" Name: ");
-                        ui.monospace(&s_span.name);
-                        ui.strong(" ID: ");
-                        ui.monospace(hex::encode(&s_span.span_id));
+                        draw_span_identity(ui, s_span, &mut jump_to_span);
                     });
                     ui.add_space(1.0);
                 }
@@ -2186,22 +3123,54 @@ fn draw_link_visualization_ui_impl(ui: &mut Ui, details: &LinkDetailsPopupInfo)
                 ui.horizontal(|ui| {
                     ui.label(format!("{}. ", idx + 1));
                     ui.strong("Start: ");
-                    ui.monospace(crate::types::time_point_to_utc_string(t_span.start_time));
-                    ui.strong(" Node: ");
-                    ui.monospace(&t_span.node.name);
-                    ui.strong(" Name: ");
-                    ui.monospace(&t_span.name);
-                    ui.strong(" ID: ");
-                    ui.monospace(hex::encode(&t_span.span_id));
+                    ui.monospace(time_format.display_string(t_span.start_time));
+                    draw_span_identity(ui, t_span, &mut jump_to_span);
                 });
                 ui.add_space(1.0);
             }
         });
+
+    jump_to_span
+}
+
+/// Combo box choosing one of the nodes, or all nodes when `None` is chosen.
+fn node_choice_ui(ui: &mut Ui, id: &str, node: &mut Option<String>, node_names: &[&String]) {
+    ui.label("Node:");
+    ComboBox::new(ui.id().with(id), "")
+        .selected_text(node.as_deref().unwrap_or("All Nodes"))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(node, None, "All Nodes");
+            for node_name in node_names {
+                ui.selectable_value(node, Some(node_name.to_string()), node_name.as_str());
+            }
+        });
+}
+
+/// Draws the node (in the node's color), name and id of a span, followed by a button which
+/// jumps to the span in the timeline.
+fn draw_span_identity(ui: &mut Ui, span: &Rc<Span>, jump_to_span: &mut Option<Rc<Span>>) {
+    ui.strong(" Node: ");
+    ui.label(
+        RichText::new(&span.node.name)
+            .monospace()
+            .color(node_color(&span.node.name)),
+    );
+    ui.strong(" Name: ");
+    ui.monospace(&span.name);
+    ui.strong(" ID: ");
+    ui.monospace(span.span_id.to_hex());
+    if ui
+        .small_button("Jump")
+        .on_hover_text("Show this span in the timeline")
+        .clicked()
+    {
+        *jump_to_span = Some(span.clone());
+    }
 }
 
 fn group_spans_by_node(
-    source_spans_list: &[Rc<Span>],
-    target_spans_list: &[Rc<Span>],
+    source_spans_list: &[WorkSpan],
+    target_spans_list: &[WorkSpan],
 ) -> (NodeSpanMap, NodeSpanMap) {
     let mut source_spans_by_node: NodeSpanMap = HashMap::new();
     let mut target_spans_by_node: NodeSpanMap = HashMap::new();
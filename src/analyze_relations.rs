@@ -0,0 +1,346 @@
+//! Analysis of the relation instances found for the active relation view - how close the matched
+//! instances get to the `max_time_diff` of their relation. Timeouts are tuned from these numbers,
+//! an instance close to the limit is a near miss which would be lost with a tighter limit.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use eframe::egui::{self, DragValue, Grid, Modal, RichText, ScrollArea};
+
+use crate::analyze_utils::ModalFocus;
+use crate::clipboard::copy_button;
+use crate::colors::theme;
+use crate::relation::{Relation, RelationInstance};
+use crate::report::{ReportBlock, ReportSection};
+use crate::types::{format_ms, format_ms_value, TimeFormat, MILLISECONDS_PER_SECOND};
+
+/// Statistics of the instances of one relation.
+#[derive(Debug, Clone)]
+pub struct RelationLimitStats {
+    pub relation: Rc<Relation>,
+    pub instances: usize,
+    /// Mean time between the end of the "from" span and the start of the "to" span, in seconds.
+    pub mean_delay: f64,
+    pub max_delay: f64,
+    /// The largest part of `max_time_diff` used by an instance, see
+    /// [RelationInstance::time_limit_usage].
+    pub max_limit_usage: Option<f64>,
+    /// Instances within the near miss percentage of the limit, sorted by the limit usage, the
+    /// closest to the limit first.
+    pub near_misses: Vec<RelationInstance>,
+}
+
+/// Groups the instances by relation, in the order of the first instance of every relation.
+pub fn relation_limit_stats(
+    instances: &[RelationInstance],
+    near_miss_percent: f64,
+) -> Vec<RelationLimitStats> {
+    let mut stats: Vec<RelationLimitStats> = Vec::new();
+    let mut index_by_id = HashMap::new();
+    let mut delay_sums: Vec<f64> = Vec::new();
+    for instance in instances {
+        let (Some(from_span), Some(to_span)) =
+            (instance.from_span.upgrade(), instance.to_span.upgrade())
+        else {
+            continue;
+        };
+        let index = *index_by_id.entry(instance.relation.id).or_insert_with(|| {
+            stats.push(RelationLimitStats {
+                relation: instance.relation.clone(),
+                instances: 0,
+                mean_delay: 0.0,
+                max_delay: f64::MIN,
+                max_limit_usage: None,
+                near_misses: Vec::new(),
+            });
+            delay_sums.push(0.0);
+            stats.len() - 1
+        });
+        let entry = &mut stats[index];
+        let delay = to_span.start_time - from_span.end_time;
+        entry.instances += 1;
+        delay_sums[index] += delay;
+        entry.max_delay = entry.max_delay.max(delay);
+        if let Some(usage) = instance.time_limit_usage() {
+            entry.max_limit_usage = Some(entry.max_limit_usage.map_or(usage, |m| m.max(usage)));
+        }
+        if instance.is_near_miss(near_miss_percent) {
+            entry.near_misses.push(instance.clone());
+        }
+    }
+    for (entry, delay_sum) in stats.iter_mut().zip(delay_sums) {
+        entry.mean_delay = delay_sum / entry.instances as f64;
+        entry.near_misses.sort_by(|a, b| {
+            let usage = |i: &RelationInstance| i.time_limit_usage().unwrap_or(0.0);
+            usage(b).total_cmp(&usage(a))
+        });
+    }
+    stats
+}
+
+/// The statistics of the relations as a report section.
+pub fn relation_stats_report_section(
+    view_name: &str,
+    stats: &[RelationLimitStats],
+) -> ReportSection {
+    let rows = stats
+        .iter()
+        .map(|stats| {
+            vec![
+                stats.relation.name.clone(),
+                stats.instances.to_string(),
+                format_ms_value(stats.mean_delay),
+                format_ms_value(stats.max_delay),
+                stats
+                    .relation
+                    .max_time_diff
+                    .map_or("-".to_string(), format_ms_value),
+                stats
+                    .max_limit_usage
+                    .map_or("-".to_string(), |usage| format!("{:.1} %", usage * 100.0)),
+                stats.near_misses.len().to_string(),
+            ]
+        })
+        .collect();
+    ReportSection {
+        title: format!("Relations: {view_name}"),
+        blocks: vec![ReportBlock::Table {
+            headers: [
+                "Relation",
+                "Instances",
+                "Mean delay (ms)",
+                "Max delay (ms)",
+                "Max time diff (ms)",
+                "Max limit usage",
+                "Near misses",
+            ]
+            .map(String::from)
+            .to_vec(),
+            rows,
+        }],
+    }
+}
+
+pub struct AnalyzeRelationsModal {
+    pub show: bool,
+    view_name: String,
+    instances: Vec<RelationInstance>,
+    /// Instances within this percentage of `max_time_diff` are near misses. The app keeps it in sync
+    /// with the arrow highlighting setting.
+    pub near_miss_percent: f64,
+    stats: Vec<RelationLimitStats>,
+    selected_relation: Option<usize>,
+    pub focus: ModalFocus,
+    /// Statistics which should be added to the report, taken by the app.
+    pub report_section: Option<ReportSection>,
+}
+
+impl Default for AnalyzeRelationsModal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnalyzeRelationsModal {
+    pub fn new() -> AnalyzeRelationsModal {
+        AnalyzeRelationsModal {
+            show: false,
+            view_name: String::new(),
+            instances: Vec::new(),
+            near_miss_percent: 10.0,
+            stats: Vec::new(),
+            selected_relation: None,
+            focus: ModalFocus::default(),
+            report_section: None,
+        }
+    }
+
+    pub fn open(
+        &mut self,
+        view_name: &str,
+        instances: &[RelationInstance],
+        near_miss_percent: f64,
+    ) {
+        self.view_name = view_name.to_string();
+        self.instances = instances.to_vec();
+        self.near_miss_percent = near_miss_percent;
+        self.selected_relation = None;
+        self.compute();
+        self.show = true;
+    }
+
+    fn compute(&mut self) {
+        self.stats = relation_limit_stats(&self.instances, self.near_miss_percent);
+        if self.selected_relation.is_none_or(|i| i >= self.stats.len()) {
+            self.selected_relation = self.stats.iter().position(|s| !s.near_misses.is_empty());
+        }
+    }
+
+    pub fn show_modal(
+        &mut self,
+        ctx: &egui::Context,
+        time_format: &TimeFormat,
+        max_width: f32,
+        max_height: f32,
+    ) {
+        if !self.show {
+            return;
+        }
+
+        let mut compute = false;
+        let mut jump_to_span = None;
+        Modal::new("analyze relations".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading(format!("Analyze relations: {}", self.view_name));
+            ui.label(
+                "Delays of the relations found in the view. Time limit usage is the time between \
+                 the start of the \"from\" span and the start of the \"to\" span, as a part of \
+                 the max time diff of the relation.",
+            );
+            ui.horizontal(|ui| {
+                ui.label("Near miss: within");
+                compute |= ui
+                    .add(
+                        DragValue::new(&mut self.near_miss_percent)
+                            .range(0.0..=100.0)
+                            .suffix(" %"),
+                    )
+                    .changed();
+                ui.label("of the max time diff");
+                if ui
+                    .add_enabled(!self.stats.is_empty(), egui::Button::new("Add to report"))
+                    .on_hover_text("Add the statistics to the report, see Report in the top bar")
+                    .clicked()
+                {
+                    self.report_section =
+                        Some(relation_stats_report_section(&self.view_name, &self.stats));
+                }
+                ui.add_enabled_ui(!self.stats.is_empty(), |ui| {
+                    copy_button(
+                        ui,
+                        "Copy the statistics as tab separated values, they can be pasted into a \
+                         spreadsheet",
+                        || {
+                            relation_stats_report_section(&self.view_name, &self.stats)
+                                .tables_to_tsv()
+                        },
+                    );
+                });
+            });
+            ui.separator();
+
+            Grid::new("analyze relations stats")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Relation");
+                    ui.strong("Instances");
+                    ui.strong("Mean delay");
+                    ui.strong("Max delay");
+                    ui.strong("Max time diff");
+                    ui.strong("Max limit usage");
+                    ui.strong("Near misses");
+                    ui.end_row();
+                    for (i, stats) in self.stats.iter().enumerate() {
+                        if ui
+                            .selectable_label(
+                                self.selected_relation == Some(i),
+                                &stats.relation.name,
+                            )
+                            .clicked()
+                        {
+                            self.selected_relation = Some(i);
+                        }
+                        ui.label(stats.instances.to_string());
+                        ui.label(format!(
+                            "{:.3} ms",
+                            stats.mean_delay * MILLISECONDS_PER_SECOND
+                        ));
+                        ui.label(format!(
+                            "{:.3} ms",
+                            stats.max_delay * MILLISECONDS_PER_SECOND
+                        ));
+                        match stats.relation.max_time_diff {
+                            Some(max) => ui.label(format_ms(max)),
+                            None => ui.label("-"),
+                        };
+                        match stats.max_limit_usage {
+                            Some(usage) => ui.label(format!("{:.1} %", usage * 100.0)),
+                            None => ui.label("-"),
+                        };
+                        let near_misses = format!(
+                            "{} ({:.1} %)",
+                            stats.near_misses.len(),
+                            stats.near_misses.len() as f64 / stats.instances as f64 * 100.0
+                        );
+                        if stats.near_misses.is_empty() {
+                            ui.label(near_misses);
+                        } else {
+                            ui.label(RichText::new(near_misses).color(theme().warning));
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            if let Some(stats) = self.selected_relation.and_then(|i| self.stats.get(i)) {
+                ui.separator();
+                ui.label(format!("Near misses of {}", stats.relation.name));
+                let row_height = ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+                ScrollArea::vertical()
+                    .max_height((max_height - 350.0).max(100.0))
+                    .auto_shrink([false, true])
+                    .show_rows(ui, row_height, stats.near_misses.len(), |ui, row_range| {
+                        Grid::new("analyze relations near misses")
+                            .striped(true)
+                            .start_row(row_range.start)
+                            .show(ui, |ui| {
+                                for instance in &stats.near_misses[row_range] {
+                                    let (Some(from_span), Some(to_span)) =
+                                        (instance.from_span.upgrade(), instance.to_span.upgrade())
+                                    else {
+                                        continue;
+                                    };
+                                    ui.monospace(time_format.display_string(from_span.start_time));
+                                    if ui
+                                        .link(format!(
+                                            "{} @ {}",
+                                            from_span.name, from_span.node.name
+                                        ))
+                                        .on_hover_text("Click to show the span")
+                                        .clicked()
+                                    {
+                                        jump_to_span = Some(from_span.clone());
+                                    }
+                                    if ui
+                                        .link(format!("{} @ {}", to_span.name, to_span.node.name))
+                                        .on_hover_text("Click to show the span")
+                                        .clicked()
+                                    {
+                                        jump_to_span = Some(to_span.clone());
+                                    }
+                                    ui.label(format!(
+                                        "{:.1} %",
+                                        instance.time_limit_usage().unwrap_or(0.0) * 100.0
+                                    ));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            }
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.show = false;
+            }
+        });
+
+        if compute {
+            self.compute();
+        }
+        if let Some(span) = jump_to_span {
+            self.focus.jump_to(span);
+            self.show = false;
+        }
+    }
+}
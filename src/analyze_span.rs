@@ -1,16 +1,53 @@
 use crate::analyze_utils::{
     calculate_table_column_widths, collect_matching_spans, draw_clickable_right_aligned_text_cell,
-    draw_left_aligned_text_cell, process_spans_for_analysis, show_span_details, span_search_ui,
-    span_selection_list_ui, Statistics,
+    draw_left_aligned_text_cell, process_spans_for_analysis, retain_spans_in_time_range,
+    selected_time_range_ui, show_span_details, span_search_ui, span_selection_list_ui,
+    time_range_to_display_string, Statistics,
 };
-use crate::colors;
-use crate::types::{value_to_text, NodeIdentifier, Span, MILLISECONDS_PER_SECOND};
+use crate::clipboard::copy_button;
+use crate::colors::theme;
+use crate::edit_modes::EditDisplayModes;
+use crate::report::{ReportBlock, ReportSection};
+use crate::structured_modes::{is_unfilled_attribute_name, MatchCondition};
+use crate::types::{format_ms_value, value_to_text, NodeIdentifier, Span, TimeFormat, TimePoint};
 use eframe::egui::{
-    Align, Button, Context, Grid, Label, Layout, Modal, RichText, ScrollArea, Sense, TextEdit, Ui,
-    Vec2,
+    Align, Button, CollapsingHeader, Context, Grid, Label, Layout, Modal, RichText, ScrollArea,
+    Sense, TextEdit, Ui, Vec2,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
+use std::sync::Arc;
+
+/// Conditions which were filled in, conditions with an empty or default attribute name were
+/// probably added accidentally and are ignored, like in span selectors.
+fn active_attribute_conditions(
+    conditions: &[(String, MatchCondition)],
+) -> impl Iterator<Item = &(String, MatchCondition)> {
+    conditions
+        .iter()
+        .filter(|(attr_name, _)| !is_unfilled_attribute_name(attr_name))
+}
+
+/// Describes the conditions, e.g. "shard_id Equal To 2, height Greater Than 1000".
+fn attribute_conditions_to_display_string(conditions: &[(String, MatchCondition)]) -> String {
+    active_attribute_conditions(conditions)
+        .map(|(attr_name, condition)| {
+            if condition.operator.uses_value() {
+                format!(
+                    "{attr_name} {} {}",
+                    condition.operator.display_name(),
+                    condition.value
+                )
+            } else {
+                format!("{attr_name} {}", condition.operator.display_name())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Breakdown value of the spans which don't have the breakdown attribute.
+const MISSING_GROUP: &str = "(missing)";
 
 #[derive(Default)]
 pub struct AnalyzeSpanModal {
@@ -34,14 +71,30 @@ pub struct AnalyzeSpanModal {
     detailed_span_analysis: Option<SpanAnalysisResult>,
     /// Filter string for attributes in format: "attr1,attr2=value"
     attribute_filter: String,
+    /// Conditions on attribute values, only spans satisfying all of them are analyzed.
+    attribute_conditions: Vec<(String, MatchCondition)>,
     /// Group by attributes: comma-separated list of attribute names to group spans by
     group_by_attributes: String,
+    /// Attribute by which the spans on each node are broken down, e.g. "shard_id". Statistics are
+    /// shown for every (node, attribute value) pair in addition to the per node ones.
+    breakdown_attribute: String,
+    /// Node of the span from which the analysis was started, its row in the results is shown in bold.
+    scope_node: Option<String>,
+    /// Whether only the spans intersecting `selected_time_range` are analyzed.
+    only_selected_time_range: bool,
+    /// The time range selected on the timeline, set by the app before showing the modal.
+    pub selected_time_range: (TimePoint, TimePoint),
+    /// Spans of a results row which should be highlighted in the trace view, taken by the app.
+    pub highlight_spans: Option<Vec<Rc<Span>>>,
+    /// Results which should be added to the report, taken by the app.
+    pub report_section: Option<ReportSection>,
 }
 
 /// Struct to hold duration statistics for spans.
-/// Also stores references to the spans with the min duration and max duration.
+/// Also stores references to all the spans, and to the spans with the min and max duration.
 struct SpanStatistics {
     duration_stats: Statistics,
+    spans: Vec<Rc<Span>>,
     min_span: Option<Rc<Span>>,
     max_span: Option<Rc<Span>>,
 }
@@ -50,6 +103,7 @@ impl SpanStatistics {
     fn new() -> Self {
         Self {
             duration_stats: Statistics::new(),
+            spans: Vec::new(),
             min_span: None,
             max_span: None,
         }
@@ -58,6 +112,7 @@ impl SpanStatistics {
     fn add_span(&mut self, span: &Rc<Span>) {
         let duration = span.end_time - span.start_time;
         self.duration_stats.add_value(duration);
+        self.spans.push(span.clone());
 
         // Update max duration and store the span if it's the new max
         if self
@@ -93,11 +148,108 @@ impl SpanStatistics {
 struct SpanAnalysisResult {
     span_name: String,
     attribute_filter: String,
+    /// The attribute conditions in a readable form, empty when there are none.
+    attribute_conditions: String,
     group_by_attributes: String,
+    breakdown_attribute: String,
+    time_range: Option<(TimePoint, TimePoint)>,
     per_node_stats: HashMap<String, SpanStatistics>,
+    /// Statistics for every (node, value of the breakdown attribute) pair, empty when there is no
+    /// breakdown attribute.
+    per_group_stats: BTreeMap<(String, String), SpanStatistics>,
     overall_stats: SpanStatistics,
 }
 
+impl SpanAnalysisResult {
+    /// Description of the analysis settings, shown above the results.
+    fn summary_parts(&self, time_format: &TimeFormat) -> Vec<String> {
+        let filter_text = if self.attribute_filter.is_empty() {
+            "none"
+        } else {
+            &self.attribute_filter
+        };
+        let grouping_text = if self.group_by_attributes.is_empty() {
+            "none"
+        } else {
+            &self.group_by_attributes
+        };
+        let mut parts = vec![format!(
+            "Analysis of span: '{}' (attribute filter: {}, group by: {})",
+            self.span_name, filter_text, grouping_text
+        )];
+        if !self.attribute_conditions.is_empty() {
+            parts.push(format!("(Conditions: {})", self.attribute_conditions));
+        }
+        if !self.breakdown_attribute.is_empty() {
+            parts.push(format!("(Broken down by: {})", self.breakdown_attribute));
+        }
+        if let Some(time_range) = self.time_range {
+            parts.push(format!(
+                "(Time range: {})",
+                time_range_to_display_string(time_range, time_format)
+            ));
+        }
+        parts
+    }
+
+    /// The results as a report section, with the rows of the results table.
+    fn report_section(&self, time_format: &TimeFormat) -> ReportSection {
+        let row = |label: &str, stats: &SpanStatistics| {
+            let duration_stats = &stats.duration_stats;
+            vec![
+                label.to_string(),
+                duration_stats.count.to_string(),
+                format_ms_value(duration_stats.min),
+                format_ms_value(duration_stats.max),
+                format_ms_value(duration_stats.mean()),
+                format_ms_value(duration_stats.median()),
+                format_ms_value(duration_stats.std_dev()),
+            ]
+        };
+        let mut node_names: Vec<&String> = self.per_node_stats.keys().collect();
+        node_names.sort();
+        let mut rows = Vec::new();
+        for node_name in node_names {
+            rows.push(row(node_name, &self.per_node_stats[node_name]));
+            for ((_, group), stats) in self
+                .per_group_stats
+                .range((node_name.clone(), String::new())..)
+                .take_while(|((node, _), _)| node == node_name)
+            {
+                rows.push(row(
+                    &format!("{node_name} {}={group}", self.breakdown_attribute),
+                    stats,
+                ));
+            }
+        }
+        rows.push(row(
+            &NodeIdentifier::AllNodes.to_string(),
+            &self.overall_stats,
+        ));
+
+        ReportSection {
+            title: format!("Span analysis: {}", self.span_name),
+            blocks: vec![
+                ReportBlock::Text(self.summary_parts(time_format).join(" ")),
+                ReportBlock::Table {
+                    headers: [
+                        "Node",
+                        "Count",
+                        "Min (ms)",
+                        "Max (ms)",
+                        "Mean (ms)",
+                        "Median (ms)",
+                        "Std Dev (ms)",
+                    ]
+                    .map(String::from)
+                    .to_vec(),
+                    rows,
+                },
+            ],
+        }
+    }
+}
+
 /// Enum to specify the type of statistic (Min or Max).
 #[derive(Clone, Copy, Debug)]
 enum StatType {
@@ -105,59 +257,196 @@ enum StatType {
     Max,
 }
 
-impl AnalyzeSpanModal {
-    /// Helper method to draw a right-aligned, clickable statistics cell (for Min/Max).
-    #[allow(clippy::too_many_arguments)]
-    fn draw_clickable_stat_cell(
-        &self,
-        ui: &mut Ui,
-        width: f32,
-        value_str: &str,
-        is_strong: bool,
-        node_identifier: NodeIdentifier,
-        stat_type: StatType,
-        span_to_view: &mut Option<Rc<Span>>,
-    ) {
-        ui.scope(|cell_ui| {
-            cell_ui.set_min_width(width);
-            cell_ui.with_layout(Layout::right_to_left(Align::Center), |inner_ui| {
-                let mut rich_text = RichText::new(value_str)
-                    .monospace()
-                    .color(colors::MILD_BLUE2);
-                if is_strong {
-                    rich_text = rich_text.strong();
-                }
-                let response = inner_ui.add(Label::new(rich_text).sense(Sense::click()));
-
-                if response.clicked() {
-                    let found_span = match stat_type {
-                        StatType::Min => self.find_min_span_for_node(&node_identifier),
-                        StatType::Max => self.find_max_span_for_node(&node_identifier),
-                    };
-                    if let Some(s) = found_span {
-                        *span_to_view = Some(s);
-                    }
-                }
-                let hover_text = match stat_type {
-                    StatType::Min => "Click to see details of span with minimum duration",
-                    StatType::Max => "Click to see details of span with maximum duration",
-                };
-                if response.hovered() {
-                    response.on_hover_text(hover_text);
+/// Draws a right-aligned, clickable statistics cell (for Min/Max), clicking it shows the details
+/// of `span`.
+fn draw_clickable_stat_cell(
+    ui: &mut Ui,
+    width: f32,
+    value_str: &str,
+    is_strong: bool,
+    span: Option<Rc<Span>>,
+    stat_type: StatType,
+    span_to_view: &mut Option<Rc<Span>>,
+) {
+    ui.scope(|cell_ui| {
+        cell_ui.set_min_width(width);
+        cell_ui.with_layout(Layout::right_to_left(Align::Center), |inner_ui| {
+            let mut rich_text = RichText::new(value_str).monospace().color(theme().link);
+            if is_strong {
+                rich_text = rich_text.strong();
+            }
+            let response = inner_ui.add(Label::new(rich_text).sense(Sense::click()));
+
+            if response.clicked() {
+                if let Some(s) = span {
+                    *span_to_view = Some(s);
                 }
-            });
+            }
+            let hover_text = match stat_type {
+                StatType::Min => "Click to see details of span with minimum duration",
+                StatType::Max => "Click to see details of span with maximum duration",
+            };
+            if response.hovered() {
+                response.on_hover_text(hover_text);
+            }
+        });
+    });
+}
+
+/// Draws one row of the results table with the statistics of the spans described by `label`.
+/// The focus button next to the label puts the row's spans in `spans_to_highlight`.
+fn draw_stats_row(
+    ui: &mut Ui,
+    col_widths: &[f32],
+    label: &str,
+    stats: &SpanStatistics,
+    is_strong: bool,
+    span_to_view: &mut Option<Rc<Span>>,
+    spans_to_highlight: &mut Option<Vec<Rc<Span>>>,
+) {
+    let duration_stats = &stats.duration_stats;
+
+    ui.scope(|cell_ui| {
+        cell_ui.set_min_width(col_widths[0]);
+        cell_ui.horizontal(|ui_horiz| {
+            let rich_text = RichText::new(label).monospace();
+            if is_strong {
+                ui_horiz.strong(rich_text);
+            } else {
+                ui_horiz.label(rich_text);
+            }
+            ui_horiz.add_space(5.0);
+            let focus_response = ui_horiz.button("🔍");
+            if focus_response.clicked() {
+                *spans_to_highlight = Some(stats.spans.clone());
+            }
+            if focus_response.hovered() {
+                focus_response.on_hover_text("Highlight these spans in the trace view");
+            }
         });
+    });
+    draw_clickable_right_aligned_text_cell(
+        ui,
+        col_widths[1],
+        &format!("{}", duration_stats.count),
+        is_strong,
+        None,
+        false,
+    );
+    draw_clickable_stat_cell(
+        ui,
+        col_widths[2],
+        &format_ms_value(duration_stats.min),
+        is_strong,
+        stats.get_min_span(),
+        StatType::Min,
+        span_to_view,
+    );
+    draw_clickable_stat_cell(
+        ui,
+        col_widths[3],
+        &format_ms_value(duration_stats.max),
+        is_strong,
+        stats.get_max_span(),
+        StatType::Max,
+        span_to_view,
+    );
+    for (width, value) in [
+        (col_widths[4], duration_stats.mean()),
+        (col_widths[5], duration_stats.median()),
+        (col_widths[6], duration_stats.std_dev()),
+    ] {
+        draw_clickable_right_aligned_text_cell(
+            ui,
+            width,
+            &format_ms_value(value),
+            is_strong,
+            None,
+            false,
+        );
     }
+    ui.end_row();
+}
 
+impl AnalyzeSpanModal {
     pub fn open(&mut self, spans_for_analysis: &[Rc<Span>]) {
         self.show = true;
         self.search_text = String::new();
         self.attribute_filter = String::new();
         self.group_by_attributes = String::new();
+        self.scope_node = None;
         self.update_span_list(spans_for_analysis);
         self.spans_processed = true;
     }
 
+    /// Opens the modal with the analysis of spans named `span_name` already done, skipping the
+    /// search in the span list. The row of `node_name` is emphasized in the results.
+    pub fn open_for_span(
+        &mut self,
+        spans_for_analysis: &[Rc<Span>],
+        span_name: &str,
+        node_name: &str,
+    ) {
+        self.open_for_span_name(spans_for_analysis, span_name);
+        self.scope_node = Some(node_name.to_string());
+    }
+
+    /// Opens the modal with the analysis of spans named `span_name` already done.
+    pub fn open_for_span_name(&mut self, spans_for_analysis: &[Rc<Span>], span_name: &str) {
+        self.open(spans_for_analysis);
+        self.search_text = span_name.to_string();
+        self.selected_span_name = Some(span_name.to_string());
+        self.perform_span_analysis(span_name);
+    }
+
+    /// The name selected in the span list.
+    pub fn get_selected_span_name(&self) -> Option<&String> {
+        self.selected_span_name.as_ref()
+    }
+
+    /// The results of the last analysis as a report section.
+    pub fn get_report_section(&self, time_format: &TimeFormat) -> Option<ReportSection> {
+        self.detailed_span_analysis
+            .as_ref()
+            .map(|result| result.report_section(time_format))
+    }
+
+    /// Nodes in the results of the last analysis, with the number of analyzed spans on each node.
+    pub fn get_analyzed_span_counts(&self) -> Vec<(String, usize)> {
+        let Some(result) = &self.detailed_span_analysis else {
+            return Vec::new();
+        };
+        let mut counts: Vec<(String, usize)> = result
+            .per_node_stats
+            .iter()
+            .map(|(node, stats)| (node.clone(), stats.duration_stats.count))
+            .collect();
+        counts.sort();
+        counts
+    }
+
+    /// Spans in the node's row of the results, they're highlighted by the row's focus button.
+    pub fn get_node_spans(&self, node_name: &str) -> Vec<Rc<Span>> {
+        self.detailed_span_analysis
+            .as_ref()
+            .and_then(|result| result.per_node_stats.get(node_name))
+            .map(|stats| stats.spans.clone())
+            .unwrap_or_default()
+    }
+
+    /// (node, value of the breakdown attribute) pairs in the results of the last analysis, with
+    /// the number of analyzed spans in each.
+    pub fn get_analyzed_group_counts(&self) -> Vec<(String, String, usize)> {
+        let Some(result) = &self.detailed_span_analysis else {
+            return Vec::new();
+        };
+        result
+            .per_group_stats
+            .iter()
+            .map(|((node, group), stats)| (node.clone(), group.clone(), stats.duration_stats.count))
+            .collect()
+    }
+
     pub fn update_span_list(&mut self, spans: &[Rc<Span>]) {
         let (all_spans, unique_names) = process_spans_for_analysis(spans);
         self.all_spans_for_analysis = all_spans;
@@ -168,10 +457,22 @@ impl AnalyzeSpanModal {
         self.attribute_filter = filter;
     }
 
+    pub fn set_attribute_conditions(&mut self, conditions: Vec<(String, MatchCondition)>) {
+        self.attribute_conditions = conditions;
+    }
+
     pub fn set_group_by_attributes(&mut self, group_by: String) {
         self.group_by_attributes = group_by;
     }
 
+    pub fn set_breakdown_attribute(&mut self, attribute: String) {
+        self.breakdown_attribute = attribute;
+    }
+
+    pub fn set_only_selected_time_range(&mut self, only_selected_time_range: bool) {
+        self.only_selected_time_range = only_selected_time_range;
+    }
+
     /// Extracts a grouping key from a span based on the group_by_attributes setting.
     /// Returns None if grouping is disabled or if any required attribute is missing.
     pub fn get_grouping_key(&self, span: &Rc<Span>) -> Option<Vec<String>> {
@@ -232,13 +533,25 @@ impl AnalyzeSpanModal {
         // Clone the first span as a template and modify only time-related fields
         let template = &*spans[0];
         let mut grouped_span = template.clone();
-        grouped_span.start_time = min_start;
-        grouped_span.end_time = max_end;
+        let data = Arc::make_mut(&mut grouped_span.data);
+        data.start_time = min_start;
+        data.end_time = max_end;
         grouped_span.min_start_time.set(min_start);
         grouped_span.max_end_time.set(max_end);
         Rc::new(grouped_span)
     }
 
+    /// Checks if a span satisfies all attribute conditions. A missing attribute only satisfies
+    /// the "Doesn't Exist" condition.
+    pub fn span_matches_attribute_conditions(&self, span: &Rc<Span>) -> bool {
+        active_attribute_conditions(&self.attribute_conditions).all(|(attr_name, condition)| {
+            match span.attributes.get(attr_name) {
+                Some(value) => condition.matches(&value_to_text(value)),
+                None => condition.matches_missing(),
+            }
+        })
+    }
+
     /// Checks if a span matches the attribute filter criteria.
     /// Format: "attr1,attr2=value" where attr1 must exist and attr2 must equal "value"
     pub fn span_matches_attribute_filter(&self, span: &Rc<Span>) -> bool {
@@ -293,10 +606,24 @@ impl AnalyzeSpanModal {
         if !self.attribute_filter.is_empty() {
             matching_spans.retain(|span| self.span_matches_attribute_filter(span));
         }
+        matching_spans.retain(|span| self.span_matches_attribute_conditions(span));
+
+        let time_range = self
+            .only_selected_time_range
+            .then_some(self.selected_time_range);
+        if let Some(time_range) = time_range {
+            retain_spans_in_time_range(&mut matching_spans, time_range);
+        }
 
         if matching_spans.is_empty() {
-            self.analysis_summary_message =
-                Some(format!("No spans found with name '{target_name}'"));
+            let range_suffix = if time_range.is_some() {
+                " in the selected time range"
+            } else {
+                ""
+            };
+            self.analysis_summary_message = Some(format!(
+                "No spans found with name '{target_name}'{range_suffix}"
+            ));
             // Clear previous results
             self.detailed_span_analysis = None;
             return;
@@ -330,13 +657,25 @@ impl AnalyzeSpanModal {
             return;
         }
 
-        // Group spans by node
+        // Group spans by node, and by (node, breakdown value) if there is a breakdown attribute
+        let breakdown_attribute = self.breakdown_attribute.trim();
         let mut per_node_stats: HashMap<String, SpanStatistics> = HashMap::new();
+        let mut per_group_stats: BTreeMap<(String, String), SpanStatistics> = BTreeMap::new();
         let mut overall_stats = SpanStatistics::new();
 
         for span in spans_to_analyze {
             overall_stats.add_span(&span);
             let node_name = span.node.name.clone();
+            if !breakdown_attribute.is_empty() {
+                let group = span
+                    .attributes
+                    .get(breakdown_attribute)
+                    .map_or_else(|| MISSING_GROUP.to_string(), value_to_text);
+                per_group_stats
+                    .entry((node_name.clone(), group))
+                    .or_insert_with(SpanStatistics::new)
+                    .add_span(&span);
+            }
             per_node_stats
                 .entry(node_name)
                 .or_insert_with(SpanStatistics::new)
@@ -347,46 +686,35 @@ impl AnalyzeSpanModal {
         self.detailed_span_analysis = Some(SpanAnalysisResult {
             span_name: target_name,
             attribute_filter: self.attribute_filter.clone(),
+            attribute_conditions: attribute_conditions_to_display_string(
+                &self.attribute_conditions,
+            ),
             group_by_attributes: self.group_by_attributes.clone(),
+            breakdown_attribute: breakdown_attribute.to_string(),
+            time_range,
             per_node_stats,
+            per_group_stats,
             overall_stats,
         });
         self.analysis_summary_message = None;
     }
 
-    /// Returns the span with the minimum duration.
-    fn find_min_span_for_node(&self, node_identifier: &NodeIdentifier) -> Option<Rc<Span>> {
-        self.detailed_span_analysis
-            .as_ref()
-            .and_then(|result| match node_identifier {
-                NodeIdentifier::AllNodes => result.overall_stats.get_min_span(),
-                NodeIdentifier::Node(node_name) => result
-                    .per_node_stats
-                    .get(node_name)
-                    .and_then(|stats| stats.get_min_span()),
-            })
-    }
-
-    /// Returns the span with the maximum duration.
-    fn find_max_span_for_node(&self, node_identifier: &NodeIdentifier) -> Option<Rc<Span>> {
-        self.detailed_span_analysis
-            .as_ref()
-            .and_then(|result| match node_identifier {
-                NodeIdentifier::AllNodes => result.overall_stats.get_max_span(),
-                NodeIdentifier::Node(node_name) => result
-                    .per_node_stats
-                    .get(node_name)
-                    .and_then(|stats| stats.get_max_span()),
-            })
-    }
-
-    pub fn show_modal(&mut self, ctx: &Context, max_width: f32, max_height: f32) {
+    pub fn show_modal(
+        &mut self,
+        ctx: &Context,
+        time_format: &TimeFormat,
+        max_width: f32,
+        max_height: f32,
+    ) {
         if !self.show {
             return;
         }
 
         // Track if we need to view a span's details after modal closes
         let mut span_to_view: Option<Rc<Span>> = None;
+        // Spans of the row whose focus button was clicked
+        let mut spans_to_highlight: Option<Vec<Rc<Span>>> = None;
+        let mut report_section = None;
 
         let mut modal_closed = false;
 
@@ -472,6 +800,21 @@ impl AnalyzeSpanModal {
                     }
                 });
 
+                ui.add_space(2.5);
+                // Attribute condition rows
+                CollapsingHeader::new(format!(
+                    "Attribute conditions ({})",
+                    active_attribute_conditions(&self.attribute_conditions).count()
+                ))
+                .id_salt("analyze span attribute conditions")
+                .show(ui, |ui| {
+                    EditDisplayModes::draw_edit_attribute_conditions(
+                        ui,
+                        &mut self.attribute_conditions,
+                        "analyze span",
+                    );
+                });
+
                 ui.add_space(2.5);
                 // Group by attributes row
                 ui.horizontal(|ui| {
@@ -488,6 +831,30 @@ impl AnalyzeSpanModal {
                     }
                 });
 
+                ui.add_space(2.5);
+                // Breakdown attribute row
+                ui.horizontal(|ui| {
+                    ui.label("Break down by attribute:");
+                    let response = ui.add(
+                        TextEdit::singleline(&mut self.breakdown_attribute)
+                            .desired_width(max_width * 0.6)
+                            .hint_text("shard_id")
+                    );
+                    if response.hovered() {
+                        response.on_hover_text(
+                            "Show statistics for every value of this attribute on each node, e.g. per shard. Leave empty to show only the per node statistics."
+                        );
+                    }
+                });
+
+                ui.add_space(2.5);
+                selected_time_range_ui(
+                    ui,
+                    &mut self.only_selected_time_range,
+                    self.selected_time_range,
+                    time_format,
+                );
+
                 ui.separator();
 
                 // Results area
@@ -495,26 +862,29 @@ impl AnalyzeSpanModal {
 
                 if let Some(result) = &self.detailed_span_analysis {
                     ui.horizontal_wrapped(|ui_summary_wrap| {
-                        let filter_text = if result.attribute_filter.is_empty() {
-                            "none"
-                        } else {
-                            &result.attribute_filter
-                        };
-                        let grouping_text = if result.group_by_attributes.is_empty() {
-                            "none"
-                        } else {
-                            &result.group_by_attributes
-                        };
-                        ui_summary_wrap.label(format!(
-                            "Analysis of span: '{}' (attribute filter: {}, group by: {})",
-                            result.span_name,
-                            filter_text,
-                            grouping_text
-                        ));
+                        for part in result.summary_parts(time_format) {
+                            ui_summary_wrap.label(part);
+                        }
+                        copy_button(ui_summary_wrap, "Copy the summary", || {
+                            result.summary_parts(time_format).join(" ")
+                        });
+                        copy_button(
+                            ui_summary_wrap,
+                            "Copy the statistics table as tab separated values, it can be pasted \
+                             into a spreadsheet",
+                            || result.report_section(time_format).tables_to_tsv(),
+                        );
+                        if ui_summary_wrap
+                            .button("Add to report")
+                            .on_hover_text("Add the statistics to the report, see Report in the top bar")
+                            .clicked()
+                        {
+                            report_section = Some(result.report_section(time_format));
+                        }
                     });
                 }
                 if let Some(message) = &self.analysis_summary_message {
-                    ui.colored_label(colors::MILD_RED, message);
+                    ui.colored_label(theme().error, message);
                 }
 
                 // Analysis results table header
@@ -609,186 +979,51 @@ impl AnalyzeSpanModal {
                                 .min_col_width(0.0)
                                 .show(ui, |ui_grid| {
                                     // Get nodes and sort them alphabetically
-                                    let mut node_names: Vec<String> =
-                                        result.per_node_stats.keys().cloned().collect();
+                                    let mut node_names: Vec<&String> =
+                                        result.per_node_stats.keys().collect();
                                     node_names.sort();
 
-                                    // Rows for each node
+                                    // Rows for each node, followed by the rows of its groups
                                     for node_name in node_names {
-                                        if let Some(stats) = result.per_node_stats.get(&node_name) {
-                                            draw_left_aligned_text_cell(
-                                                ui_grid,
-                                                col_widths[0],
-                                                &node_name,
-                                                false,
-                                            );
-                                            draw_clickable_right_aligned_text_cell(
-                                                ui_grid,
-                                                col_widths[1],
-                                                &format!("{}", stats.duration_stats.count),
-                                                false,
-                                                None,
-                                                false,
-                                            );
-
-                                            let min_text = format!(
-                                                "{:.3}",
-                                                stats.duration_stats.min * MILLISECONDS_PER_SECOND
-                                            );
-                                            self.draw_clickable_stat_cell(
+                                        draw_stats_row(
+                                            ui_grid,
+                                            &col_widths,
+                                            node_name,
+                                            &result.per_node_stats[node_name],
+                                            self.scope_node.as_ref() == Some(node_name),
+                                            &mut span_to_view,
+                                            &mut spans_to_highlight,
+                                        );
+                                        for ((_, group), stats) in result
+                                            .per_group_stats
+                                            .range((node_name.clone(), String::new())..)
+                                            .take_while(|((node, _), _)| node == node_name)
+                                        {
+                                            draw_stats_row(
                                                 ui_grid,
-                                                col_widths[2],
-                                                &min_text,
-                                                false,
-                                                NodeIdentifier::Node(node_name.clone()),
-                                                StatType::Min,
-                                                &mut span_to_view,
-                                            );
-
-                                            let max_text = format!(
-                                                "{:.3}",
-                                                stats.duration_stats.max * MILLISECONDS_PER_SECOND
-                                            );
-                                            self.draw_clickable_stat_cell(
-                                                ui_grid,
-                                                col_widths[3],
-                                                &max_text,
-                                                false,
-                                                NodeIdentifier::Node(node_name.clone()),
-                                                StatType::Max,
-                                                &mut span_to_view,
-                                            );
-
-                                            draw_clickable_right_aligned_text_cell(
-                                                ui_grid,
-                                                col_widths[4],
-                                                &format!(
-                                                    "{:.3}",
-                                                    stats.duration_stats.mean()
-                                                        * MILLISECONDS_PER_SECOND
-                                                ),
-                                                false,
-                                                None,
-                                                false,
-                                            );
-                                            draw_clickable_right_aligned_text_cell(
-                                                ui_grid,
-                                                col_widths[5],
-                                                &format!(
-                                                    "{:.3}",
-                                                    stats.duration_stats.median()
-                                                        * MILLISECONDS_PER_SECOND
-                                                ),
-                                                false,
-                                                None,
-                                                false,
-                                            );
-                                            draw_clickable_right_aligned_text_cell(
-                                                ui_grid,
-                                                col_widths[6],
+                                                &col_widths,
                                                 &format!(
-                                                    "{:.3}",
-                                                    stats.duration_stats.std_dev()
-                                                        * MILLISECONDS_PER_SECOND
+                                                    "    {}={}",
+                                                    result.breakdown_attribute, group
                                                 ),
+                                                stats,
                                                 false,
-                                                None,
-                                                false,
+                                                &mut span_to_view,
+                                                &mut spans_to_highlight,
                                             );
-
-                                            ui_grid.end_row();
-                                        } else {
-                                            // If no stats (e.g., node had no matching spans), display "-"
-                                            for &width in col_widths.iter().skip(1) {
-                                                draw_clickable_right_aligned_text_cell(
-                                                    ui_grid, width, "-", false, None, false,
-                                                );
-                                            }
                                         }
                                     }
 
                                     // Overall statistics row
-                                    let overall = &result.overall_stats;
-                                    draw_left_aligned_text_cell(
+                                    draw_stats_row(
                                         ui_grid,
-                                        col_widths[0],
+                                        &col_widths,
                                         &NodeIdentifier::AllNodes.to_string(),
+                                        &result.overall_stats,
                                         true,
-                                    );
-                                    draw_clickable_right_aligned_text_cell(
-                                        ui_grid,
-                                        col_widths[1],
-                                        &format!("{}", overall.duration_stats.count),
-                                        true,
-                                        None,
-                                        false,
-                                    );
-
-                                    let min_text_overall = format!(
-                                        "{:.3}",
-                                        overall.duration_stats.min * MILLISECONDS_PER_SECOND
-                                    );
-                                    self.draw_clickable_stat_cell(
-                                        ui_grid,
-                                        col_widths[2],
-                                        &min_text_overall,
-                                        true,
-                                        NodeIdentifier::AllNodes,
-                                        StatType::Min,
                                         &mut span_to_view,
+                                        &mut spans_to_highlight,
                                     );
-
-                                    let max_text_overall = format!(
-                                        "{:.3}",
-                                        overall.duration_stats.max * MILLISECONDS_PER_SECOND
-                                    );
-                                    self.draw_clickable_stat_cell(
-                                        ui_grid,
-                                        col_widths[3],
-                                        &max_text_overall,
-                                        true,
-                                        NodeIdentifier::AllNodes,
-                                        StatType::Max,
-                                        &mut span_to_view,
-                                    );
-
-                                    draw_clickable_right_aligned_text_cell(
-                                        ui_grid,
-                                        col_widths[4],
-                                        &format!(
-                                            "{:.3}",
-                                            overall.duration_stats.mean() * MILLISECONDS_PER_SECOND
-                                        ),
-                                        true,
-                                        None,
-                                        false,
-                                    );
-                                    draw_clickable_right_aligned_text_cell(
-                                        ui_grid,
-                                        col_widths[5],
-                                        &format!(
-                                            "{:.3}",
-                                            overall.duration_stats.median()
-                                                * MILLISECONDS_PER_SECOND
-                                        ),
-                                        true,
-                                        None,
-                                        false,
-                                    );
-                                    draw_clickable_right_aligned_text_cell(
-                                        ui_grid,
-                                        col_widths[6],
-                                        &format!(
-                                            "{:.3}",
-                                            overall.duration_stats.std_dev()
-                                                * MILLISECONDS_PER_SECOND
-                                        ),
-                                        true,
-                                        None,
-                                        false,
-                                    );
-
-                                    ui_grid.end_row();
                                 });
                         } else if self.selected_span_name.is_some() {
                             ui.label("Click 'Analyze' to see statistics for the selected span.");
@@ -806,21 +1041,32 @@ impl AnalyzeSpanModal {
             });
         });
 
+        if report_section.is_some() {
+            self.report_section = report_section;
+        }
+        if spans_to_highlight.is_some() {
+            self.highlight_spans = spans_to_highlight;
+            modal_closed = true;
+        }
+
         // Reset fields if modal got closed
         if modal_closed {
             self.show = false;
             self.selected_span_name = None;
             self.search_text = String::new();
             self.attribute_filter = String::new();
+            self.attribute_conditions = Vec::new();
             self.group_by_attributes = String::new();
+            self.breakdown_attribute = String::new();
             self.detailed_span_analysis = None;
             self.analysis_summary_message = None;
+            self.scope_node = None;
         }
 
         // If a specific span was clicked for detailed view (e.g., min/max duration span),
         // or if a detail view was already open, show/keep it open.
         if let Some(span_rc) = span_to_view.or_else(|| self.span_details.take()) {
-            if show_span_details(ctx, &span_rc, max_width, max_height) {
+            if show_span_details(ctx, &span_rc, time_format, max_width, max_height) {
                 self.span_details = None;
             } else {
                 self.span_details = Some(span_rc);
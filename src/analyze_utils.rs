@@ -1,51 +1,183 @@
-use crate::colors;
-use crate::types::Span;
-use crate::types::MILLISECONDS_PER_SECOND;
+use crate::colors::theme;
+use crate::span_id::SpanId;
+use crate::types::{Span, TimeFormat, TimePoint};
 use eframe::egui::{
     self, Align, Align2, Color32, Context, Grid, Key, Layout, Order, RichText, ScrollArea,
     TextEdit, Ui,
 };
+use regex::Regex;
 use std::collections::HashSet;
 use std::rc::Rc;
 
+/// Span which the trace view should jump to after an analysis modal is closed, set when the user
+/// clicks on a span in the modal. The app polls all modals for it once per frame.
+#[derive(Debug, Clone, Default)]
+pub struct ModalFocus {
+    span: Option<Rc<Span>>,
+}
+
+impl ModalFocus {
+    /// Asks the app to jump to the span, the modal should close itself.
+    pub fn jump_to(&mut self, span: Rc<Span>) {
+        self.span = Some(span);
+    }
+
+    pub fn clear(&mut self) {
+        self.span = None;
+    }
+
+    /// The requested span, taken by the app when it jumps to it.
+    pub fn take(&mut self) -> Option<Rc<Span>> {
+        self.span.take()
+    }
+}
+
 /// Helper function to collect all spans in a span tree with deduplication (the same span won't appear twice).
 pub fn collect_span_tree_with_deduplication(
     root_span: &Rc<Span>,
     collected_spans: &mut Vec<Rc<Span>>,
 ) {
-    let mut seen_span_ids: HashSet<Vec<u8>> = HashSet::new();
+    let mut seen_span_ids: HashSet<SpanId> = HashSet::new();
     collect_descendant_spans_with_deduplication(root_span, collected_spans, &mut seen_span_ids);
 }
 
 fn collect_descendant_spans_with_deduplication(
     current_span: &Rc<Span>,
     collected_spans: &mut Vec<Rc<Span>>,
-    seen_span_ids: &mut HashSet<Vec<u8>>,
+    seen_span_ids: &mut HashSet<SpanId>,
 ) {
     // Only add this span if we haven't seen its ID before
     if !seen_span_ids.contains(&current_span.span_id) {
-        seen_span_ids.insert(current_span.span_id.clone());
+        seen_span_ids.insert(current_span.span_id);
         collected_spans.push(current_span.clone());
         // Recursively add all children
-        for child_span in current_span.children.borrow().iter() {
+        for child_span in current_span.children().iter() {
             collect_descendant_spans_with_deduplication(child_span, collected_spans, seen_span_ids);
         }
     }
 }
 
-/// Helper function to collect spans with specific name.
+/// Selects spans by name. The selection is either an exact name, a glob with `*` and `?`
+/// (e.g. `apply_chunk*`) or a regex between slashes (e.g. `/apply_chunk.*/`). Patterns aggregate
+/// all names that match them, which is useful when span names have variant suffixes.
+pub enum SpanNameMatcher {
+    Exact(String),
+    Pattern(Regex),
+}
+
+impl SpanNameMatcher {
+    pub fn parse(selection: &str) -> Result<SpanNameMatcher, String> {
+        if let Some(regex) = selection
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            return Regex::new(regex)
+                .map(SpanNameMatcher::Pattern)
+                .map_err(|e| e.to_string());
+        }
+        if selection.contains(['*', '?']) {
+            let mut regex = String::from("^");
+            for c in selection.chars() {
+                match c {
+                    '*' => regex.push_str(".*"),
+                    '?' => regex.push('.'),
+                    _ => regex.push_str(&regex::escape(&c.to_string())),
+                }
+            }
+            regex.push('$');
+            return Regex::new(&regex)
+                .map(SpanNameMatcher::Pattern)
+                .map_err(|e| e.to_string());
+        }
+        Ok(SpanNameMatcher::Exact(selection.to_string()))
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            SpanNameMatcher::Exact(exact) => name == exact,
+            SpanNameMatcher::Pattern(regex) => regex.is_match(name),
+        }
+    }
+
+    /// Regex matching the same names, e.g. for a span selector.
+    pub fn to_regex_string(&self) -> String {
+        match self {
+            SpanNameMatcher::Exact(exact) => format!("^{}$", regex::escape(exact)),
+            SpanNameMatcher::Pattern(regex) => regex.as_str().to_string(),
+        }
+    }
+}
+
+/// Whether the selection is a glob or regex pattern rather than a single span name.
+pub fn is_span_name_pattern(selection: &str) -> bool {
+    matches!(
+        SpanNameMatcher::parse(selection),
+        Ok(SpanNameMatcher::Pattern(_)) | Err(_)
+    )
+}
+
+/// Names selected by `selection`, in the order of `unique_span_names`.
+pub fn matching_span_names<'a>(
+    selection: &str,
+    unique_span_names: &'a [String],
+) -> Vec<&'a String> {
+    match SpanNameMatcher::parse(selection) {
+        Ok(matcher) => unique_span_names
+            .iter()
+            .filter(|name| matcher.matches(name))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Helper function to collect spans with specific name, or with names matching a pattern (see
+/// [SpanNameMatcher]).
 pub fn collect_matching_spans(
     spans: &[Rc<Span>],
     target_name: &str,
     matching_spans: &mut Vec<Rc<Span>>,
 ) {
+    let Ok(matcher) = SpanNameMatcher::parse(target_name) else {
+        return;
+    };
     for span in spans {
-        if span.original_name == target_name {
+        if matcher.matches(&span.original_name) {
             matching_spans.push(span.clone());
         }
     }
 }
 
+/// Keeps only the spans which intersect the time range `start..=end`.
+pub fn retain_spans_in_time_range(spans: &mut Vec<Rc<Span>>, (start, end): (TimePoint, TimePoint)) {
+    spans.retain(|span| span.end_time >= start && span.start_time <= end);
+}
+
+/// Formats a time range for the analysis summaries.
+pub fn time_range_to_display_string(
+    (start, end): (TimePoint, TimePoint),
+    time_format: &TimeFormat,
+) -> String {
+    format!(
+        "{} - {}",
+        time_format.display_string(start),
+        time_format.display_string(end)
+    )
+}
+
+/// Checkbox which restricts an analysis to the time range selected on the timeline.
+pub fn selected_time_range_ui(
+    ui: &mut Ui,
+    only_selected_time_range: &mut bool,
+    selected_time_range: (TimePoint, TimePoint),
+    time_format: &TimeFormat,
+) {
+    ui.checkbox(only_selected_time_range, "Only the selected time range")
+        .on_hover_text(format!(
+            "Analyze only spans which intersect the time range selected on the timeline ({})",
+            time_range_to_display_string(selected_time_range, time_format)
+        ));
+}
+
 /// Creates a search input field with a label and hint text.
 pub fn span_search_ui(
     ui: &mut egui::Ui,
@@ -58,7 +190,7 @@ pub fn span_search_ui(
         ui.label(label);
         let text_edit = TextEdit::singleline(search_text)
             .hint_text(hint_text)
-            .text_color(colors::DARK_GRAY)
+            .text_color(theme().weak_text)
             .desired_width(width);
         ui.add(text_edit);
     });
@@ -77,15 +209,53 @@ pub fn span_selection_list_ui(
 ) -> bool {
     let mut selection_changed = false;
 
-    // Filter names by the search text
-    let search_term = search_text.to_lowercase();
-    let filtered_names: Vec<&String> = unique_span_names
-        .iter()
-        .filter(|name| search_term.is_empty() || name.to_lowercase().contains(&search_term))
-        .collect();
+    // A pattern in the search box selects all names matching it, the list shows these names.
+    // The pattern is parsed once per frame, compiling the regex for every name would be slow.
+    let matcher = SpanNameMatcher::parse(search_text);
+    let is_pattern = matches!(matcher, Ok(SpanNameMatcher::Pattern(_)) | Err(_));
+    let filtered_names: Vec<&String> = match &matcher {
+        Ok(SpanNameMatcher::Exact(_)) => {
+            let search_term = search_text.to_lowercase();
+            unique_span_names
+                .iter()
+                .filter(|name| search_term.is_empty() || name.to_lowercase().contains(&search_term))
+                .collect()
+        }
+        Ok(pattern) => unique_span_names
+            .iter()
+            .filter(|name| pattern.matches(name))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    if is_pattern {
+        match &matcher {
+            Ok(_) => {
+                let is_selected = selected_span_name.as_deref() == Some(search_text);
+                let label = format!(
+                    "All names matching {search_text} ({})",
+                    filtered_names.len()
+                );
+                if ui
+                    .selectable_label(is_selected, RichText::new(label).strong())
+                    .on_hover_text("Analyze the spans of all names listed below together")
+                    .clicked()
+                {
+                    *selected_span_name = Some(search_text.to_string());
+                    selection_changed = true;
+                }
+            }
+            Err(error) => {
+                ui.colored_label(theme().error, format!("Invalid pattern: {error}"));
+            }
+        }
+    }
 
     // Label with count
-    ui.label(format!("Spans ({}):", filtered_names.len()));
+    ui.label(format!("Spans ({}):", filtered_names.len()))
+        .on_hover_text(
+            "Type a glob (e.g. apply_chunk*) or a regex between slashes (e.g. /apply_chunk.*/) to select all matching names",
+        );
 
     // Scrollable list of spans
     ScrollArea::vertical()
@@ -106,6 +276,27 @@ pub fn span_selection_list_ui(
     selection_changed
 }
 
+/// Time range which shows the highlighted spans, with some padding around them. At most a few
+/// seconds after the earliest span are included, so that the spans remain visible.
+pub fn highlighted_spans_range(spans: &[Rc<Span>]) -> Option<(TimePoint, TimePoint)> {
+    const MAX_HIGHLIGHT_SPAN_TIMELINE_ZOOM: f64 = 3.0;
+    let min_time = spans.iter().map(|span| span.start_time).reduce(f64::min)?;
+    let max_time = spans
+        .iter()
+        .map(|span| span.end_time)
+        .fold(f64::MIN, f64::max)
+        .min(min_time + MAX_HIGHLIGHT_SPAN_TIMELINE_ZOOM);
+    let padding = (max_time - min_time) * 0.2;
+    Some((min_time - padding, max_time + padding))
+}
+
+/// Index of the nearest-rank percentile `p` (between 0 and 100) in `len` sorted values, `len`
+/// must be non-zero.
+pub fn percentile_index(p: f64, len: usize) -> usize {
+    let rank = (p / 100.0 * len as f64).ceil() as usize;
+    rank.clamp(1, len) - 1
+}
+
 /// Stores and calculates statistics for a collection of values.
 pub struct Statistics {
     pub count: usize,
@@ -157,6 +348,18 @@ impl Statistics {
         }
     }
 
+    /// Nearest-rank percentile, `p` is between 0 and 100.
+    pub fn percentile(&self, p: f64) -> f64 {
+        if self.data_points.is_empty() {
+            return 0.0;
+        }
+
+        let mut sorted_values = self.data_points.clone();
+        sorted_values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        sorted_values[percentile_index(p, sorted_values.len())]
+    }
+
     pub fn std_dev(&self) -> f64 {
         if self.count == 0 {
             return 0.0;
@@ -277,7 +480,13 @@ pub fn draw_clickable_right_aligned_text_cell(
 
 /// Show details of a specific span in a new window.
 /// Returns true if the window was closed.
-pub fn show_span_details(ctx: &Context, span: &Rc<Span>, max_width: f32, max_height: f32) -> bool {
+pub fn show_span_details(
+    ctx: &Context,
+    span: &Rc<Span>,
+    time_format: &TimeFormat,
+    max_width: f32,
+    max_height: f32,
+) -> bool {
     let mut should_close = false;
 
     // Create a modal dialog for the span details
@@ -296,23 +505,20 @@ pub fn show_span_details(ctx: &Context, span: &Rc<Span>, max_width: f32, max_hei
 
                 // Display timing information
                 ui.strong(format!(
-                    "Duration: {:.3} ms",
-                    (span.end_time - span.start_time) * MILLISECONDS_PER_SECOND
+                    "Duration: {}",
+                    crate::types::format_duration(span.end_time - span.start_time)
                 ));
                 ui.label(format!(
                     "Time: {} - {}",
-                    crate::types::time_point_to_utc_string(span.start_time),
-                    crate::types::time_point_to_utc_string(span.end_time)
+                    time_format.display_string(span.start_time),
+                    time_format.display_string(span.end_time)
                 ));
 
                 // Display span identification
                 ui.add_space(5.0);
                 ui.label(format!("Node: {}", span.node.name));
-                ui.label(format!("Span ID: {}", hex::encode(&span.span_id)));
-                ui.label(format!(
-                    "Parent Span ID: {}",
-                    hex::encode(&span.parent_span_id)
-                ));
+                ui.label(format!("Span ID: {}", span.span_id.to_hex()));
+                ui.label(format!("Parent Span ID: {}", span.parent_span_id.to_hex()));
 
                 ui.add_space(10.0);
                 ui.separator();
@@ -352,7 +558,7 @@ pub fn show_span_details(ctx: &Context, span: &Rc<Span>, max_width: f32, max_hei
                                 ui.collapsing(event.name.clone(), |ui| {
                                     ui.label(format!(
                                         "Time: {}",
-                                        crate::types::time_point_to_utc_string(event.time)
+                                        time_format.display_string(event.time)
                                     ));
 
                                     for (name, value) in &event.attributes {
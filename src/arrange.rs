@@ -0,0 +1,347 @@
+//! Arrangement of spans into non-overlapping rows, and a cache of the arrangement of each lane.
+//! Arranging all spans of every lane on every frame is expensive with large traces, the cache
+//! allows to skip it on frames where nothing that affects the arrangement has changed.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::types::{HeightLevel, Span, TimePoint};
+
+/// Everything that the arrangement of a lane depends on, apart from the spans themselves and their
+/// collapse/highlight state, which invalidate the whole cache when they change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrangementKey {
+    pub selected_start: TimePoint,
+    pub selected_end: TimePoint,
+    /// Screen position of the start of the span area.
+    pub visual_start_x: f32,
+    /// Screen position of the end of the span area.
+    pub visual_end_x: f32,
+    pub node_filter_index: usize,
+}
+
+/// Result of arranging one lane.
+pub struct ArrangedLane {
+    /// Top level spans of the lane which are visible in the selected range.
+    pub spans_in_range: Vec<Rc<Span>>,
+    pub bbox: SpanBoundingBox,
+}
+
+/// Pane of the split view in which a lane is drawn, each pane has its own arrangements.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SpanPane {
+    /// The only pane when the view isn't split.
+    #[default]
+    Upper,
+    Lower,
+}
+
+/// Display state which the arrangement writes into the `Cell`s of a span.
+struct SpanLayout {
+    span: Rc<Span>,
+    display_children: Vec<Rc<Span>>,
+    parent_height_offset: HeightLevel,
+    display_start: f32,
+    display_length: f32,
+    time_display_length: f32,
+}
+
+impl SpanLayout {
+    /// Layouts of the spans and their displayed descendants.
+    fn collect(spans: &[Rc<Span>], result: &mut Vec<SpanLayout>) {
+        for span in spans {
+            let display_children = span.display_children.borrow().clone();
+            SpanLayout::collect(&display_children, result);
+            result.push(SpanLayout {
+                span: span.clone(),
+                display_children,
+                parent_height_offset: span.parent_height_offset.get(),
+                display_start: span.display_start.get(),
+                display_length: span.display_length.get(),
+                time_display_length: span.time_display_length.get(),
+            });
+        }
+    }
+
+    fn restore(&self) {
+        let span = &self.span;
+        *span.display_children.borrow_mut() = self.display_children.clone();
+        span.parent_height_offset.set(self.parent_height_offset);
+        span.display_start.set(self.display_start);
+        span.display_length.set(self.display_length);
+        span.time_display_length.set(self.time_display_length);
+    }
+}
+
+struct CachedLane {
+    key: ArrangementKey,
+    arranged: Rc<ArrangedLane>,
+    /// The arrangement of the spans, written back when the other pane arranged them in between.
+    layout: Vec<SpanLayout>,
+}
+
+/// Arrangement of each lane, keyed by the pane and the lane name.
+/// The arrangement itself is stored in the `Cell`s of the spans (`display_start`,
+/// `parent_height_offset`, ...), the cache remembers for which key they were computed. Both panes
+/// of the split view draw the same spans, so the arrangement of a pane is written back into them
+/// when the other pane has overwritten it.
+#[derive(Default)]
+pub struct ArrangementCache {
+    lanes: HashMap<(SpanPane, String), CachedLane>,
+    /// Pane whose arrangement is currently in the spans of each lane.
+    arranged_for: HashMap<String, SpanPane>,
+    highlighted_spans: Vec<Rc<Span>>,
+}
+
+impl ArrangementCache {
+    pub fn new() -> ArrangementCache {
+        ArrangementCache::default()
+    }
+
+    /// Forgets all arrangements. Has to be called whenever something that affects the arrangement
+    /// and isn't a part of [ArrangementKey] changes, e.g. the displayed spans or the collapse state
+    /// of a span.
+    pub fn invalidate(&mut self) {
+        self.lanes.clear();
+        self.arranged_for.clear();
+    }
+
+    /// Highlighted spans are always expanded and displayed with their full name, so a change of
+    /// highlights changes the arrangement. Cheaper to compare once per frame than to track every
+    /// place which modifies the highlights.
+    pub fn update_highlighted_spans(&mut self, highlighted_spans: &[Rc<Span>]) {
+        let unchanged = self.highlighted_spans.len() == highlighted_spans.len()
+            && self
+                .highlighted_spans
+                .iter()
+                .zip(highlighted_spans)
+                .all(|(a, b)| Rc::ptr_eq(a, b));
+        if !unchanged {
+            self.highlighted_spans = highlighted_spans.to_vec();
+            self.invalidate();
+        }
+    }
+
+    /// Returns the arrangement of the lane in the pane, calling `arrange` only when there is no
+    /// arrangement for this key.
+    pub fn get_or_arrange(
+        &mut self,
+        pane: SpanPane,
+        lane_name: &str,
+        key: ArrangementKey,
+        arrange: impl FnOnce() -> ArrangedLane,
+    ) -> Rc<ArrangedLane> {
+        let lane_key = (pane, lane_name.to_string());
+        if let Some(cached) = self.lanes.get(&lane_key) {
+            if cached.key == key {
+                if self.arranged_for.get(lane_name) != Some(&pane) {
+                    for span_layout in &cached.layout {
+                        span_layout.restore();
+                    }
+                    self.arranged_for.insert(lane_key.1, pane);
+                }
+                return cached.arranged.clone();
+            }
+        }
+        let arranged = Rc::new(arrange());
+        let mut layout = Vec::new();
+        SpanLayout::collect(&arranged.spans_in_range, &mut layout);
+        self.arranged_for.insert(lane_name.to_string(), pane);
+        self.lanes.insert(
+            lane_key,
+            CachedLane {
+                key,
+                arranged: arranged.clone(),
+                layout,
+            },
+        );
+        arranged
+    }
+
+    pub fn len(&self) -> usize {
+        self.lanes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lanes.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SpanBoundingBox {
+    pub start: f32,
+    pub end: f32,
+    pub height: HeightLevel,
+}
+
+/// Assigns `parent_height_offset` to the spans and their displayed children so that they don't
+/// overlap. Uses the `display_start`/`display_length` which have to be set before arranging.
+pub fn arrange_spans_with_viewport(
+    input_spans: &[Rc<Span>],
+    first_invocation: bool,
+    viewport_start: f64,
+    viewport_end: f64,
+) -> SpanBoundingBox {
+    #[cfg(feature = "profiling")]
+    let _timing_guard = crate::profiling::GLOBAL_PROFILER.start_timing("arrange_spans");
+
+    if input_spans.is_empty() {
+        return SpanBoundingBox {
+            start: 0.0,
+            end: 0.0,
+            height: 0,
+        };
+    }
+
+    let mut sorted_spans = input_spans.to_vec();
+    sorted_spans.sort_by(|a, b| {
+        if let Some(start_ordering) = a.min_start_time.partial_cmp(&b.min_start_time) {
+            return start_ordering;
+        }
+        a.max_end_time
+            .partial_cmp(&b.max_end_time)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut span_bounding_boxes: Vec<SpanBoundingBox> = Vec::with_capacity(sorted_spans.len());
+
+    // Spans that can collide with new spans (holds indexes to span_bounding_boxes).
+    let mut active_spans: Vec<usize> = Vec::new();
+
+    for (i, span) in sorted_spans.iter().enumerate() {
+        let mut span_bbox = arrange_span_with_viewport(span, viewport_start, viewport_end);
+        if first_invocation && span_bbox.height > 0 {
+            span_bbox.height += 1; // Top-level spans have one unit of padding below them
+        }
+
+        // Default to height 0, will be updated below
+        span.parent_height_offset.set(0);
+
+        // Remove spans that for sure won't collide with this span or any future ones. Spans are
+        // sorted by start time, so we can be sure that for futures ones the start time will be
+        // larger than the end of the bounding box.
+        active_spans.retain(|&j| span_bounding_boxes[j].end >= span_bbox.start);
+
+        loop {
+            let mut is_colliding = false;
+
+            for &j in &active_spans {
+                let other_span = &sorted_spans[j];
+                let other_span_bbox = &span_bounding_boxes[j];
+
+                if is_intersecting(
+                    span_bbox.start,
+                    span_bbox.end,
+                    other_span_bbox.start,
+                    other_span_bbox.end,
+                ) && do_spans_collide_in_y(
+                    span.parent_height_offset.get(),
+                    span_bbox.height,
+                    other_span.parent_height_offset.get(),
+                    other_span_bbox.height,
+                ) {
+                    is_colliding = true;
+                    break;
+                }
+
+                if span_bbox.start < other_span_bbox.end && span_bbox.end < other_span_bbox.start {
+                    assert!(is_colliding);
+                }
+            }
+
+            if is_colliding {
+                span.parent_height_offset
+                    .set(span.parent_height_offset.get() + 1);
+            } else {
+                break;
+            }
+        }
+
+        span_bounding_boxes.push(span_bbox);
+        active_spans.push(i);
+    }
+
+    let mut final_bbox = SpanBoundingBox {
+        start: f32::INFINITY,
+        end: f32::NEG_INFINITY,
+        height: 0,
+    };
+
+    for i in 0..sorted_spans.len() {
+        let span = &sorted_spans[i];
+        let span_bbox = &span_bounding_boxes[i];
+
+        final_bbox.start = final_bbox.start.min(span_bbox.start);
+        final_bbox.end = final_bbox.end.max(span_bbox.end);
+        final_bbox.height = final_bbox
+            .height
+            .max(span.parent_height_offset.get() + span_bbox.height);
+    }
+
+    final_bbox
+}
+
+fn arrange_span_with_viewport(
+    span: &Rc<Span>,
+    viewport_start: f64,
+    viewport_end: f64,
+) -> SpanBoundingBox {
+    let span_start = span.display_start.get();
+    let span_end = span_start + span.display_length.get();
+
+    if span.display_children.borrow().is_empty() {
+        SpanBoundingBox {
+            start: span_start,
+            end: span_end,
+            height: 1,
+        }
+    } else {
+        // Filter children to only include those that intersect with the viewport
+        let all_children = span.display_children.borrow();
+        let viewport_culled_children: Vec<Rc<Span>> = all_children
+            .iter()
+            .filter(|child| {
+                is_intersecting(
+                    child.min_start_time.get(),
+                    child.max_end_time.get(),
+                    viewport_start,
+                    viewport_end,
+                )
+            })
+            .cloned()
+            .collect();
+
+        let children_bbox = arrange_spans_with_viewport(
+            &viewport_culled_children,
+            false,
+            viewport_start,
+            viewport_end,
+        );
+        SpanBoundingBox {
+            start: span_start.min(children_bbox.start),
+            end: span_end.max(children_bbox.end),
+            height: children_bbox.height + 1,
+        }
+    }
+}
+
+pub fn is_between<T: PartialOrd + Copy>(x: T, a: T, b: T) -> bool {
+    a <= x && x <= b
+}
+
+pub fn is_intersecting<T: PartialOrd + Copy>(a: T, b: T, c: T, d: T) -> bool {
+    is_between(a, c, d) || is_between(b, c, d) || is_between(c, a, b) || is_between(d, a, b)
+}
+
+fn do_spans_collide_in_y(y1: u64, height1: u64, y2: u64, height2: u64) -> bool {
+    if !is_intersecting(y1, y1 + height1, y2, y2 + height2) {
+        return false;
+    }
+
+    // Spans can touch vertically, that's ok
+    if y1 + height1 == y2 || y2 + height2 == y1 {
+        return false;
+    }
+
+    true
+}
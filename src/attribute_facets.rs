@@ -0,0 +1,303 @@
+//! Attribute value explorer - a side panel which lists the distinct values of one attribute in the
+//! selected part of the timeline with their counts. It shows which shard ids, peer ids etc. are
+//! present, the spans with a value can be highlighted or be the only ones displayed.
+
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+
+use eframe::egui::{self, ComboBox, Grid, RichText, ScrollArea, TextEdit, Ui};
+
+use crate::types::{value_to_text, Span, TimePoint};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FacetEntry {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Names of the attributes of `spans`, sorted.
+pub fn attribute_keys(spans: &[Rc<Span>]) -> Vec<String> {
+    let keys: BTreeSet<&String> = spans.iter().flat_map(|s| s.attributes.keys()).collect();
+    keys.into_iter().cloned().collect()
+}
+
+/// Distinct values of the attribute `key` among `spans` which overlap the time window
+/// `[start, end]`, the most common first.
+pub fn compute_facets(
+    spans: &[Rc<Span>],
+    key: &str,
+    start: TimePoint,
+    end: TimePoint,
+) -> Vec<FacetEntry> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for span in spans {
+        if span.end_time < start || span.start_time > end {
+            continue;
+        }
+        if let Some(value) = span.attributes.get(key) {
+            *counts.entry(value_to_text(value)).or_default() += 1;
+        }
+    }
+    let mut entries: Vec<FacetEntry> = counts
+        .into_iter()
+        .map(|(value, count)| FacetEntry { value, count })
+        .collect();
+    entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    entries
+}
+
+/// The values are counted in a window snapped to a grid of about this many steps, so that
+/// scrolling or zooming by a little doesn't recount them every frame.
+const WINDOW_STEPS: f64 = 256.0;
+
+/// `[start, end]` widened to the grid, the step is a power of two so that it stays the same while
+/// the window length changes a little.
+pub fn quantize_window(start: TimePoint, end: TimePoint) -> (TimePoint, TimePoint) {
+    let length = end - start;
+    if length <= 0.0 || !length.is_finite() {
+        return (start, end);
+    }
+    let step = 2f64.powf((length / WINDOW_STEPS).log2().ceil());
+    ((start / step).floor() * step, (end / step).ceil() * step)
+}
+
+fn has_value(span: &Span, key: &str, value: &str) -> bool {
+    span.attributes
+        .get(key)
+        .is_some_and(|v| value_to_text(v) == value)
+}
+
+fn span_or_descendant_has_value(span: &Rc<Span>, key: &str, value: &str) -> bool {
+    has_value(span, key, value)
+        || span
+            .children()
+            .iter()
+            .any(|child| span_or_descendant_has_value(child, key, value))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FacetAction {
+    /// Highlight the spans with this value in the selected time window.
+    Highlight(String),
+    /// The filter was set or cleared, the displayed spans have to be updated.
+    FilterChanged,
+}
+
+/// Attribute key, lowercase value filter and quantized time window of the cached entries.
+type FacetsKey = (String, String, TimePoint, TimePoint);
+
+pub struct AttributeFacets {
+    show: bool,
+    /// All spans in the trace, including children.
+    spans: Vec<Rc<Span>>,
+    keys: Vec<String>,
+    key: String,
+    value_filter: String,
+    /// Entries which pass the value filter, with the key, value filter and quantized time window
+    /// they were computed for. Cleared when a new trace is loaded.
+    entries: Option<(FacetsKey, Vec<FacetEntry>)>,
+    /// Only top level spans which have a span with this (key, value) in their subtree are displayed.
+    filter: Option<(String, String)>,
+}
+
+impl Default for AttributeFacets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AttributeFacets {
+    pub fn new() -> AttributeFacets {
+        AttributeFacets {
+            show: false,
+            spans: Vec::new(),
+            keys: Vec::new(),
+            key: String::new(),
+            value_filter: String::new(),
+            entries: None,
+            filter: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.show
+    }
+
+    pub fn toggle(&mut self) {
+        self.show = !self.show;
+    }
+
+    /// `spans` should contain all spans of the newly loaded trace, including children.
+    pub fn set_spans(&mut self, spans: Vec<Rc<Span>>) {
+        self.keys = attribute_keys(&spans);
+        self.spans = spans;
+        self.entries = None;
+    }
+
+    /// Spans with the value of the current key in the time window.
+    pub fn spans_with_value(&self, value: &str, start: TimePoint, end: TimePoint) -> Vec<Rc<Span>> {
+        self.spans
+            .iter()
+            .filter(|s| {
+                s.end_time >= start && s.start_time <= end && has_value(s, &self.key, value)
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn filter(&self) -> Option<&(String, String)> {
+        self.filter.as_ref()
+    }
+
+    pub fn set_filter(&mut self, key: &str, value: &str) {
+        self.filter = Some((key.to_string(), value.to_string()));
+    }
+
+    pub fn clear_filter(&mut self) {
+        self.filter = None;
+    }
+
+    /// Whether a top level span is displayed with the current filter.
+    pub fn shows(&self, span: &Rc<Span>) -> bool {
+        match &self.filter {
+            Some((key, value)) => span_or_descendant_has_value(span, key, value),
+            None => true,
+        }
+    }
+
+    fn entries(&mut self, start: TimePoint, end: TimePoint) -> &[FacetEntry] {
+        let (start, end) = quantize_window(start, end);
+        let cache_key = (
+            self.key.clone(),
+            self.value_filter.to_lowercase(),
+            start,
+            end,
+        );
+        if self.entries.as_ref().is_none_or(|(k, _)| *k != cache_key) {
+            let value_filter = &cache_key.1;
+            let mut entries = compute_facets(&self.spans, &self.key, start, end);
+            entries.retain(|e| {
+                value_filter.is_empty() || e.value.to_lowercase().contains(value_filter)
+            });
+            self.entries = Some((cache_key, entries));
+        }
+        &self.entries.as_ref().unwrap().1
+    }
+
+    /// Draws the panel for the selected time window, has to be called before the central panel
+    /// is shown.
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        start: TimePoint,
+        end: TimePoint,
+    ) -> Option<FacetAction> {
+        if !self.show {
+            return None;
+        }
+        let mut action = None;
+        egui::SidePanel::right("attribute values")
+            .resizable(true)
+            .default_width(350.0)
+            .show(ctx, |ui| action = self.draw_contents(ui, start, end));
+        action
+    }
+
+    fn draw_contents(
+        &mut self,
+        ui: &mut Ui,
+        start: TimePoint,
+        end: TimePoint,
+    ) -> Option<FacetAction> {
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.strong("Attribute values");
+            if ui.button("Close").clicked() {
+                self.show = false;
+            }
+        });
+        ui.horizontal(|ui| {
+            ComboBox::from_id_salt("attribute values key")
+                .selected_text(if self.key.is_empty() {
+                    "Choose an attribute"
+                } else {
+                    self.key.as_str()
+                })
+                .height(400.0)
+                .show_ui(ui, |ui| {
+                    for key in &self.keys {
+                        ui.selectable_value(&mut self.key, key.clone(), key);
+                    }
+                });
+            ui.add(
+                TextEdit::singleline(&mut self.value_filter)
+                    .hint_text("Filter values")
+                    .desired_width(120.0),
+            );
+        });
+        if let Some((key, value)) = self.filter.clone() {
+            ui.horizontal(|ui| {
+                ui.label(format!("Showing only {key}={value}"));
+                if ui.button("Show all").clicked() {
+                    self.filter = None;
+                    action = Some(FacetAction::FilterChanged);
+                }
+            });
+        }
+        ui.label(
+            RichText::new(
+                "Values in the selected time range. Click a value to highlight its spans.",
+            )
+            .weak(),
+        );
+        ui.separator();
+        if self.key.is_empty() {
+            return action;
+        }
+
+        let key = self.key.clone();
+        let entries = self.entries(start, end);
+        let mut new_filter = None;
+        let row_height = ui
+            .spacing()
+            .interact_size
+            .y
+            .max(ui.text_style_height(&egui::TextStyle::Body))
+            + 4.0;
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, entries.len(), |ui, row_range| {
+                Grid::new("attribute values grid")
+                    .striped(true)
+                    .start_row(row_range.start)
+                    .show(ui, |ui| {
+                        for entry in &entries[row_range] {
+                            if ui
+                                .link(&entry.value)
+                                .on_hover_text("Highlight the spans with this value")
+                                .clicked()
+                            {
+                                action = Some(FacetAction::Highlight(entry.value.clone()));
+                            }
+                            ui.monospace(entry.count.to_string());
+                            if ui
+                                .small_button("Filter")
+                                .on_hover_text(
+                                    "Display only the top level spans which have a span with \
+                                     this value in their subtree",
+                                )
+                                .clicked()
+                            {
+                                new_filter = Some(entry.value.clone());
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        if let Some(value) = new_filter {
+            self.set_filter(&key, &value);
+            action = Some(FacetAction::FilterChanged);
+        }
+        action
+    }
+}
@@ -0,0 +1,175 @@
+//! Table of span attributes used in the clicked span popup.
+//! The rows can be sorted by name or value, filtered, and copied. Clicking on an attribute adds it
+//! as a criterion to the span search.
+
+use std::collections::BTreeMap;
+
+use eframe::egui::{self, Grid, Label, RichText, ScrollArea, Sense, TextEdit, Ui};
+use opentelemetry_proto::tonic::common::v1::any_value::Value;
+
+use crate::colors::theme;
+use crate::types::value_to_text;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AttributeSortColumn {
+    #[default]
+    Name,
+    Value,
+}
+
+#[derive(Debug, Default)]
+pub struct AttributeTable {
+    filter: String,
+    sort_column: AttributeSortColumn,
+    descending: bool,
+}
+
+impl AttributeTable {
+    pub fn new() -> AttributeTable {
+        AttributeTable::default()
+    }
+
+    pub fn set_filter(&mut self, filter: impl Into<String>) {
+        self.filter = filter.into();
+    }
+
+    /// Sorts by the column, clicking the same column again reverses the order.
+    pub fn sort_by(&mut self, column: AttributeSortColumn) {
+        if self.sort_column == column {
+            self.descending = !self.descending;
+        } else {
+            self.sort_column = column;
+            self.descending = false;
+        }
+    }
+
+    /// (name, value) rows which contain the filter text in the name or value (case insensitive), in
+    /// the chosen order.
+    pub fn rows(&self, attributes: &BTreeMap<String, Option<Value>>) -> Vec<(String, String)> {
+        let filter = self.filter.to_lowercase();
+        let mut rows: Vec<(String, String)> = attributes
+            .iter()
+            .map(|(name, value)| (name.clone(), value_to_text(value)))
+            .filter(|(name, value)| {
+                filter.is_empty()
+                    || name.to_lowercase().contains(&filter)
+                    || value.to_lowercase().contains(&filter)
+            })
+            .collect();
+        match self.sort_column {
+            AttributeSortColumn::Name => rows.sort_by(|a, b| a.0.cmp(&b.0)),
+            AttributeSortColumn::Value => {
+                rows.sort_by(|a, b| compare_values(&a.1, &b.1).then_with(|| a.0.cmp(&b.0)))
+            }
+        }
+        if self.descending {
+            rows.reverse();
+        }
+        rows
+    }
+
+    /// Draws the table, returns the (name, value) of the attribute that was clicked.
+    pub fn draw(
+        &mut self,
+        ui: &mut Ui,
+        attributes: &BTreeMap<String, Option<Value>>,
+    ) -> Option<(String, String)> {
+        let mut clicked = None;
+        ui.horizontal(|ui| {
+            ui.label(format!("Attributes ({})", attributes.len()));
+            ui.add(
+                TextEdit::singleline(&mut self.filter)
+                    .hint_text("Filter attributes")
+                    .desired_width(200.0),
+            );
+        });
+
+        let rows = self.rows(attributes);
+        ScrollArea::vertical()
+            .id_salt("attribute table")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                Grid::new("attribute table grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (column, title) in [
+                            (AttributeSortColumn::Name, "Name"),
+                            (AttributeSortColumn::Value, "Value"),
+                        ] {
+                            let arrow = match (self.sort_column == column, self.descending) {
+                                (true, false) => " ⏶",
+                                (true, true) => " ⏷",
+                                (false, _) => "",
+                            };
+                            if ui
+                                .add(
+                                    Label::new(RichText::new(format!("{title}{arrow}")).strong())
+                                        .sense(Sense::click()),
+                                )
+                                .on_hover_text("Click to sort")
+                                .clicked()
+                            {
+                                self.sort_by(column);
+                            }
+                        }
+                        ui.label("");
+                        ui.end_row();
+
+                        for (name, value) in &rows {
+                            if ui
+                                .add(
+                                    Label::new(RichText::new(name).color(theme().link))
+                                        .sense(Sense::click()),
+                                )
+                                .on_hover_text("Click to add to the search")
+                                .clicked()
+                            {
+                                clicked = Some((name.clone(), value.clone()));
+                            }
+                            ui.add(Label::new(value).wrap());
+                            if ui
+                                .small_button("Copy")
+                                .on_hover_text("Copy the value")
+                                .clicked()
+                            {
+                                ui.ctx().copy_text(value.clone());
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        if rows.len() < attributes.len() {
+            ui.label(
+                RichText::new(format!(
+                    "{} attributes hidden by the filter",
+                    attributes.len() - rows.len()
+                ))
+                .weak(),
+            );
+        }
+        if ui
+            .add_enabled(!rows.is_empty(), egui::Button::new("Copy all"))
+            .on_hover_text("Copy the shown attributes as name: value lines")
+            .clicked()
+        {
+            let text = rows
+                .iter()
+                .map(|(name, value)| format!("{name}: {value}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            ui.ctx().copy_text(text);
+        }
+        clicked
+    }
+}
+
+/// Numbers are compared by value, so that 9 comes before 10. Numbers go before other values.
+fn compare_values(a: &str, b: &str) -> std::cmp::Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+        (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
@@ -0,0 +1,131 @@
+//! Running expensive computations (relation matching, dependency analysis) on all cores without
+//! freezing the UI. The work is split into independent items, results are sent back as soon as
+//! each item is finished and the UI polls them every frame. The task can be cancelled at any time.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+
+struct TaskProgress {
+    done_weight: AtomicUsize,
+    total_weight: usize,
+    cancelled: AtomicBool,
+}
+
+pub struct BackgroundTask<T> {
+    receiver: mpsc::Receiver<T>,
+    progress: Arc<TaskProgress>,
+    finished: bool,
+}
+
+impl<T: Send + 'static> BackgroundTask<T> {
+    /// Processes `items` on a pool of worker threads. `weight` estimates how much work an item is,
+    /// it's used only for the progress indicator.
+    /// The order in which results arrive is not specified.
+    pub fn spawn_parallel<I: Send + Sync + 'static>(
+        items: Vec<I>,
+        weight: impl Fn(&I) -> usize,
+        process: impl Fn(&I) -> T + Send + Sync + 'static,
+    ) -> BackgroundTask<T> {
+        let weights: Vec<usize> = items.iter().map(weight).collect();
+        let progress = Arc::new(TaskProgress {
+            done_weight: AtomicUsize::new(0),
+            total_weight: weights.iter().sum(),
+            cancelled: AtomicBool::new(false),
+        });
+        let (sender, receiver) = mpsc::channel();
+
+        let worker_progress = progress.clone();
+        std::thread::spawn(move || {
+            let threads_num = std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+                .min(items.len())
+                .max(1);
+            // Items are taken one by one, so a few big items don't leave the other threads idle.
+            let next_item = AtomicUsize::new(0);
+            std::thread::scope(|scope| {
+                for _ in 0..threads_num {
+                    let sender = sender.clone();
+                    let (items, weights, process, progress, next_item) =
+                        (&items, &weights, &process, &worker_progress, &next_item);
+                    scope.spawn(move || loop {
+                        if progress.cancelled.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        let item_index = next_item.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(item_index) else {
+                            return;
+                        };
+                        let result = process(item);
+                        progress
+                            .done_weight
+                            .fetch_add(weights[item_index], Ordering::Relaxed);
+                        if sender.send(result).is_err() {
+                            // Nobody is waiting for the results anymore.
+                            return;
+                        }
+                    });
+                }
+            });
+        });
+
+        BackgroundTask {
+            receiver,
+            progress,
+            finished: false,
+        }
+    }
+
+    /// Returns the results that arrived since the last call, without blocking.
+    pub fn poll(&mut self) -> Vec<T> {
+        let mut results = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(result) => results.push(result),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+        results
+    }
+
+    /// Blocks until the task is finished and returns all results that weren't polled yet.
+    pub fn wait(&mut self) -> Vec<T> {
+        let results = self.receiver.iter().collect();
+        self.finished = true;
+        results
+    }
+}
+
+impl<T> BackgroundTask<T> {
+    /// True when all workers have stopped and all results were received.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Fraction of the work done, between 0 and 1.
+    pub fn progress(&self) -> f32 {
+        if self.progress.total_weight == 0 {
+            return 1.0;
+        }
+        self.progress.done_weight.load(Ordering::Relaxed) as f32 / self.progress.total_weight as f32
+    }
+
+    /// Stops the workers, items which are being processed are finished, the rest is skipped.
+    pub fn cancel(&self) {
+        self.progress.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.progress.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> Drop for BackgroundTask<T> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
@@ -0,0 +1,69 @@
+//! Breadcrumb of the hovered span - the chain of its ancestors from the top level span. In dense
+//! lanes it's hard to tell which top level span a small rectangle belongs to.
+
+use std::rc::Rc;
+
+use eframe::egui::{self, RichText};
+
+use crate::span_id::SpanId;
+use crate::types::Span;
+
+/// Separator between the names in the breadcrumb.
+pub const BREADCRUMB_SEPARATOR: &str = " › ";
+
+/// Spans on the path from `root` to the span with `span_id`, including both of them. Lazy children
+/// are transformed while searching. Returns `None` if the span isn't in the subtree.
+pub fn ancestor_chain(root: &Rc<Span>, span_id: SpanId) -> Option<Vec<Rc<Span>>> {
+    fn find(span: &Rc<Span>, span_id: SpanId, chain: &mut Vec<Rc<Span>>) -> bool {
+        chain.push(span.clone());
+        if span.span_id == span_id {
+            return true;
+        }
+        for child in span.children().iter() {
+            if find(child, span_id, chain) {
+                return true;
+            }
+        }
+        chain.pop();
+        false
+    }
+
+    let mut chain = Vec::new();
+    find(root, span_id, &mut chain).then_some(chain)
+}
+
+/// The chain as a single line, e.g. "produce_block › apply_chunk › wasm_call".
+pub fn breadcrumb_text(chain: &[Rc<Span>]) -> String {
+    chain
+        .iter()
+        .map(|span| span.name.as_str())
+        .collect::<Vec<_>>()
+        .join(BREADCRUMB_SEPARATOR)
+}
+
+/// Bar at the bottom of the window with the breadcrumb of the last hovered span. Returns the
+/// ancestor which was clicked. Has to be called before the central panel is shown.
+pub fn draw_breadcrumb_bar(ctx: &egui::Context, chain: &[Rc<Span>]) -> Option<Rc<Span>> {
+    let (last, ancestors) = chain.split_last()?;
+    let mut clicked = None;
+    egui::TopBottomPanel::bottom("breadcrumb").show(ctx, |ui| {
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            for ancestor in ancestors {
+                if ui
+                    .link(&ancestor.name)
+                    .on_hover_text(format!(
+                        "Jump to {} on {}",
+                        ancestor.name, ancestor.node.name
+                    ))
+                    .clicked()
+                {
+                    clicked = Some(ancestor.clone());
+                }
+                ui.label(BREADCRUMB_SEPARATOR);
+            }
+            ui.label(RichText::new(&last.name).strong());
+        });
+    });
+    clicked
+}
@@ -1,5 +1,5 @@
 use crate::relation::{
-    make_uuid_from_seed, AttributeRelation, AttributeRelationOp, MatchType, Relation,
+    make_uuid_from_seed, ArrowStyle, AttributeRelation, AttributeRelationOp, MatchType, Relation,
     RelationNodesConfig,
 };
 use crate::structured_modes::{MatchCondition, SpanSelector};
@@ -20,6 +20,9 @@ pub fn produce_block_on_head_to_preprocess_block_relation() -> Relation {
         nodes_config: RelationNodesConfig::AllNodes,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -40,6 +43,9 @@ pub fn preprocess_block_to_postprocess_ready_block_relation() -> Relation {
         nodes_config: RelationNodesConfig::SameNode,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -60,6 +66,9 @@ pub fn postprocess_ready_block_to_produce_block_on_head_relation() -> Relation {
         nodes_config: RelationNodesConfig::SameNode,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -80,6 +89,9 @@ pub fn postprocess_ready_block_to_next_preprocess_block_relation() -> Relation {
         nodes_config: RelationNodesConfig::SameNode,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -110,6 +122,9 @@ pub fn preprocess_block_to_apply_new_chunk_relation() -> Relation {
         nodes_config: RelationNodesConfig::SameNode,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -140,6 +155,9 @@ pub fn apply_new_chunk_normal_to_postprocess_ready_block_relation() -> Relation
         nodes_config: RelationNodesConfig::SameNode,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -173,6 +191,9 @@ pub fn apply_new_chunk_optimistic_to_postprocess_ready_block_relation() -> Relat
         nodes_config: RelationNodesConfig::SameNode,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -193,6 +214,9 @@ pub fn postprocess_ready_block_to_produce_chunk_relation() -> Relation {
         nodes_config: RelationNodesConfig::SameNode,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -220,6 +244,9 @@ pub fn produce_chunk_to_send_chunk_state_witness_relation() -> Relation {
         nodes_config: RelationNodesConfig::SameNode,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -247,6 +274,9 @@ pub fn send_chunk_state_witness_to_validate_chunk_state_witness_relation() -> Re
         nodes_config: RelationNodesConfig::AllNodes,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -274,6 +304,9 @@ pub fn validate_chunk_state_witness_to_send_chunk_endorsement_relation() -> Rela
         nodes_config: RelationNodesConfig::SameNode,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -306,6 +339,9 @@ pub fn send_chunk_endorsement_to_validate_chunk_endorsement_relation() -> Relati
         nodes_config: RelationNodesConfig::AllNodes,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -325,6 +361,9 @@ pub fn validate_chunk_endorsement_to_produce_block_on_head_relation() -> Relatio
         nodes_config: RelationNodesConfig::SameNode,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -345,6 +384,9 @@ pub fn postprocess_ready_block_to_produce_optimistic_block_on_head_relation() ->
         nodes_config: RelationNodesConfig::SameNode,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -365,6 +407,9 @@ pub fn produce_optimistic_block_on_head_to_process_optimistic_block_relation() -
         nodes_config: RelationNodesConfig::AllNodes,
         match_type: MatchType::MatchAll,
         min_time_diff: 0.0,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -398,6 +443,9 @@ pub fn process_optimistic_block_to_apply_new_chunk_optimistic_relation() -> Rela
         nodes_config: RelationNodesConfig::SameNode,
         match_type: MatchType::MatchAll,
         min_time_diff: -0.010, // apply_new_chunk sometimes happens a few ms before the process_optimistic_block that spawns it.
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle::default(),
         is_builtin: true,
     }
 }
@@ -0,0 +1,333 @@
+//! Simple charts painted directly with egui, used by the analysis modals to show values over time,
+//! one value against another, or the distribution of values.
+
+use eframe::egui::{self, Align2, Color32, FontId, Painter, Pos2, Rect, Sense, Stroke, Ui, Vec2};
+
+use crate::colors::theme;
+
+/// Points closer than this to the pointer (in pixels) show a tooltip and can be clicked.
+const HOVER_DISTANCE: f32 = 6.0;
+/// Space for the axis labels.
+const AXIS_MARGIN: f32 = 60.0;
+
+pub struct ChartSeries<'a> {
+    pub name: &'a str,
+    pub color: Color32,
+    /// (x, y)
+    pub points: &'a [(f64, f64)],
+    /// Connect consecutive points with lines, otherwise only the points are drawn.
+    pub draw_lines: bool,
+}
+
+/// Formats values on one axis of the chart.
+pub struct ChartAxis<'a> {
+    pub label: &'a str,
+    pub format: &'a dyn Fn(f64) -> String,
+}
+
+/// Bounds of all points, widened when all values are the same so that they can be scaled.
+fn bounds(series: &[ChartSeries]) -> Option<((f64, f64), (f64, f64))> {
+    let mut points = series.iter().flat_map(|s| s.points.iter());
+    let &(x, y) = points.next()?;
+    let (mut x_range, mut y_range) = ((x, x), (y, y));
+    for &(x, y) in points {
+        x_range = (x_range.0.min(x), x_range.1.max(x));
+        y_range = (y_range.0.min(y), y_range.1.max(y));
+    }
+    let widen = |(min, max): (f64, f64)| {
+        if max > min {
+            (min, max)
+        } else {
+            (min - 0.5, max + 0.5)
+        }
+    };
+    Some((widen(x_range), widen(y_range)))
+}
+
+/// Draws the series in a chart of the given size. Returns `(series index, point index)` of the
+/// point which was clicked.
+pub fn draw_chart(
+    ui: &mut Ui,
+    size: Vec2,
+    series: &[ChartSeries],
+    x_axis: &ChartAxis,
+    y_axis: &ChartAxis,
+) -> Option<(usize, usize)> {
+    let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, theme().chart_background);
+
+    let Some(((x_min, x_max), (y_min, y_max))) = bounds(series) else {
+        painter.text(
+            rect.center(),
+            Align2::CENTER_CENTER,
+            "No data",
+            FontId::proportional(14.0),
+            theme().weak_text,
+        );
+        return None;
+    };
+
+    let plot = plot_rect(rect);
+    let to_screen = |(x, y): (f64, f64)| {
+        Pos2::new(
+            plot.min.x + ((x - x_min) / (x_max - x_min)) as f32 * plot.width(),
+            plot.max.y - ((y - y_min) / (y_max - y_min)) as f32 * plot.height(),
+        )
+    };
+    draw_axes(
+        &painter,
+        rect,
+        plot,
+        (x_min, x_max),
+        (y_min, y_max),
+        x_axis,
+        y_axis,
+    );
+    let font = FontId::proportional(11.0);
+
+    // Legend
+    if series.len() > 1 {
+        let mut legend_pos = Pos2::new(plot.max.x, rect.min.y + 2.0);
+        for s in series.iter().rev() {
+            let text_rect =
+                painter.text(legend_pos, Align2::RIGHT_TOP, s.name, font.clone(), s.color);
+            legend_pos.x = text_rect.min.x - 10.0;
+        }
+    }
+
+    let mut hovered: Option<(usize, usize, f32)> = None;
+    let pointer = response.hover_pos();
+    for (series_idx, s) in series.iter().enumerate() {
+        let screen_points: Vec<Pos2> = s.points.iter().map(|p| to_screen(*p)).collect();
+        if s.draw_lines && screen_points.len() > 1 {
+            painter.add(egui::Shape::line(
+                screen_points.clone(),
+                Stroke::new(1.0, s.color),
+            ));
+        }
+        for (point_idx, pos) in screen_points.iter().enumerate() {
+            painter.circle_filled(*pos, 2.5, s.color);
+            if let Some(pointer) = pointer {
+                let distance = pointer.distance(*pos);
+                if distance < HOVER_DISTANCE && hovered.is_none_or(|(_, _, d)| distance < d) {
+                    hovered = Some((series_idx, point_idx, distance));
+                }
+            }
+        }
+    }
+
+    let (series_idx, point_idx, _) = hovered?;
+    let s = &series[series_idx];
+    let (x, y) = s.points[point_idx];
+    painter.circle_stroke(
+        to_screen((x, y)),
+        5.0,
+        Stroke::new(1.5, theme().chart_hover),
+    );
+    let response = response.on_hover_text(format!(
+        "{}\n{}: {}\n{}: {}",
+        s.name,
+        x_axis.label,
+        (x_axis.format)(x),
+        y_axis.label,
+        (y_axis.format)(y)
+    ));
+    response.clicked().then_some((series_idx, point_idx))
+}
+
+/// Area of the chart in which the values are drawn, the rest is used by the axis labels.
+fn plot_rect(rect: Rect) -> Rect {
+    Rect::from_min_max(
+        Pos2::new(rect.min.x + AXIS_MARGIN, rect.min.y + 20.0),
+        Pos2::new(rect.max.x - 10.0, rect.max.y - 30.0),
+    )
+}
+
+/// Draws the axes with the min and max values.
+fn draw_axes(
+    painter: &Painter,
+    rect: Rect,
+    plot: Rect,
+    (x_min, x_max): (f64, f64),
+    (y_min, y_max): (f64, f64),
+    x_axis: &ChartAxis,
+    y_axis: &ChartAxis,
+) {
+    let axis_stroke = Stroke::new(1.0, theme().chart_axis);
+    painter.line_segment([plot.left_bottom(), plot.right_bottom()], axis_stroke);
+    painter.line_segment([plot.left_bottom(), plot.left_top()], axis_stroke);
+    let font = FontId::proportional(11.0);
+    let text_color = theme().chart_text;
+    let bottom = plot.max.y + 3.0;
+    painter.text(
+        Pos2::new(plot.min.x, bottom),
+        Align2::LEFT_TOP,
+        (x_axis.format)(x_min),
+        font.clone(),
+        text_color,
+    );
+    painter.text(
+        Pos2::new(plot.max.x, bottom),
+        Align2::RIGHT_TOP,
+        (x_axis.format)(x_max),
+        font.clone(),
+        text_color,
+    );
+    painter.text(
+        Pos2::new(plot.center().x, bottom),
+        Align2::CENTER_TOP,
+        x_axis.label,
+        font.clone(),
+        text_color,
+    );
+    painter.text(
+        Pos2::new(plot.min.x - 3.0, plot.max.y),
+        Align2::RIGHT_BOTTOM,
+        (y_axis.format)(y_min),
+        font.clone(),
+        text_color,
+    );
+    painter.text(
+        Pos2::new(plot.min.x - 3.0, plot.min.y),
+        Align2::RIGHT_TOP,
+        (y_axis.format)(y_max),
+        font.clone(),
+        text_color,
+    );
+    painter.text(
+        Pos2::new(rect.min.x + 2.0, rect.min.y + 2.0),
+        Align2::LEFT_TOP,
+        y_axis.label,
+        font,
+        text_color,
+    );
+}
+
+/// A bucket of a histogram, counts the values in `start..end`. The last bucket includes its end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramBucket {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+/// Splits the range of the values into `bucket_count` buckets of the same width and counts the
+/// values in each. Empty when there are no values.
+pub fn histogram(values: &[f64], bucket_count: usize) -> Vec<HistogramBucket> {
+    if values.is_empty() || bucket_count == 0 {
+        return Vec::new();
+    }
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    // All values are the same, one bucket holds all of them.
+    if max <= min {
+        return vec![HistogramBucket {
+            start: min,
+            end: max,
+            count: values.len(),
+        }];
+    }
+    let width = (max - min) / bucket_count as f64;
+    let mut buckets: Vec<HistogramBucket> = (0..bucket_count)
+        .map(|i| HistogramBucket {
+            start: min + i as f64 * width,
+            end: min + (i + 1) as f64 * width,
+            count: 0,
+        })
+        .collect();
+    for value in values {
+        let index = (((value - min) / width) as usize).min(bucket_count - 1);
+        buckets[index].count += 1;
+    }
+    buckets
+}
+
+/// (value, fraction of the values which are less or equal), for the sorted values.
+pub fn cumulative_distribution(values: &[f64]) -> Vec<(f64, f64)> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let count = sorted.len() as f64;
+    sorted
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| (value, (i + 1) as f64 / count))
+        .collect()
+}
+
+/// Draws the buckets as bars of a chart of the given size, hovering over a bar shows its range and
+/// count.
+pub fn draw_histogram(
+    ui: &mut Ui,
+    size: Vec2,
+    buckets: &[HistogramBucket],
+    color: Color32,
+    x_axis: &ChartAxis,
+) {
+    let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, theme().chart_background);
+
+    let (Some(first), Some(last)) = (buckets.first(), buckets.last()) else {
+        painter.text(
+            rect.center(),
+            Align2::CENTER_CENTER,
+            "No data",
+            FontId::proportional(14.0),
+            theme().weak_text,
+        );
+        return;
+    };
+    let max_count = buckets.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+
+    let plot = plot_rect(rect);
+    let y_axis = ChartAxis {
+        label: "Count",
+        format: &|count| format!("{count:.0}"),
+    };
+    draw_axes(
+        &painter,
+        rect,
+        plot,
+        (first.start, last.end),
+        (0.0, max_count as f64),
+        x_axis,
+        &y_axis,
+    );
+
+    let bar_width = plot.width() / buckets.len() as f32;
+    let mut hovered = None;
+    for (i, bucket) in buckets.iter().enumerate() {
+        let left = plot.min.x + i as f32 * bar_width;
+        let bar = Rect::from_min_max(
+            Pos2::new(
+                left,
+                plot.max.y - bucket.count as f32 / max_count as f32 * plot.height(),
+            ),
+            Pos2::new(left + (bar_width - 1.0).max(1.0), plot.max.y),
+        );
+        painter.rect_filled(bar, 0.0, color);
+        let column = Rect::from_x_y_ranges(left..=left + bar_width, plot.y_range());
+        if response
+            .hover_pos()
+            .is_some_and(|pointer| column.contains(pointer))
+        {
+            painter.rect_stroke(
+                bar,
+                0.0,
+                Stroke::new(1.0, theme().chart_hover),
+                egui::StrokeKind::Outside,
+            );
+            hovered = Some(bucket);
+        }
+    }
+
+    if let Some(bucket) = hovered {
+        response.on_hover_text(format!(
+            "{} - {}\nCount: {}",
+            (x_axis.format)(bucket.start),
+            (x_axis.format)(bucket.end),
+            bucket.count
+        ));
+    }
+}
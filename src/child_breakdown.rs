@@ -0,0 +1,311 @@
+//! Child breakdown - "where does this span spend its time". Aggregates over all spans with a name
+//! how much time is spent in each direct child span name.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use eframe::egui::{self, Color32, Grid, Key, Modal, Rect, RichText, Sense, Stroke, Ui, Vec2};
+
+use crate::analyze_utils::{
+    process_spans_for_analysis, span_search_ui, span_selection_list_ui, Statistics,
+};
+use crate::colors::{node_color, theme};
+use crate::types::{format_ms, Span};
+
+/// Name of the row with the time not covered by any child.
+pub const SELF_TIME_NAME: &str = "(self)";
+
+/// Time spent in children with one name, per instance of the parent span. Instances without such
+/// a child count as 0, so the means of all rows add up to the mean duration of the parent.
+#[derive(Debug, Clone)]
+pub struct ChildTime {
+    pub child_name: String,
+    /// Number of child spans with this name, in all instances.
+    pub calls: usize,
+    /// Number of parent instances which have at least one child with this name.
+    pub instances_with_child: usize,
+    /// Seconds
+    pub mean: f64,
+    /// Seconds
+    pub p50: f64,
+    /// Seconds
+    pub p99: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChildBreakdown {
+    pub span_name: String,
+    pub instances: usize,
+    /// Mean duration of the parent span, in seconds.
+    pub mean_duration: f64,
+    /// Sorted by the mean time, largest first.
+    pub children: Vec<ChildTime>,
+    /// Duration of the parent minus the time of its children. Children which run in parallel can
+    /// take more time than the parent, then the self time is 0.
+    pub self_time: ChildTime,
+}
+
+impl ChildBreakdown {
+    /// Share of the mean parent duration, in percent.
+    pub fn share_percent(&self, child: &ChildTime) -> f64 {
+        if self.mean_duration == 0.0 {
+            return 0.0;
+        }
+        child.mean / self.mean_duration * 100.0
+    }
+}
+
+fn child_time(child_name: String, calls: usize, per_instance: &[f64]) -> ChildTime {
+    let mut stats = Statistics::new();
+    for value in per_instance {
+        stats.add_value(*value);
+    }
+    ChildTime {
+        child_name,
+        calls,
+        instances_with_child: per_instance.iter().filter(|v| **v > 0.0).count(),
+        mean: stats.mean(),
+        p50: stats.percentile(50.0),
+        p99: stats.percentile(99.0),
+    }
+}
+
+/// Breakdown of all spans named `span_name` among `spans` (all spans including children), `None`
+/// when there are no such spans.
+pub fn compute_child_breakdown(spans: &[Rc<Span>], span_name: &str) -> Option<ChildBreakdown> {
+    let instances: Vec<&Rc<Span>> = spans
+        .iter()
+        .filter(|s| s.original_name() == span_name)
+        .collect();
+    if instances.is_empty() {
+        return None;
+    }
+
+    // child name -> (calls, time in each instance)
+    let mut per_child: HashMap<String, (usize, Vec<f64>)> = HashMap::new();
+    let mut self_times = Vec::with_capacity(instances.len());
+    let mut durations = Statistics::new();
+    for (instance_idx, span) in instances.iter().enumerate() {
+        let duration = span.end_time - span.start_time;
+        durations.add_value(duration);
+        let mut children_time = 0.0;
+        for child in span.children().iter() {
+            let child_duration = child.end_time - child.start_time;
+            children_time += child_duration;
+            let (calls, times) = per_child
+                .entry(child.original_name().to_string())
+                .or_insert_with(|| (0, vec![0.0; instances.len()]));
+            *calls += 1;
+            times[instance_idx] += child_duration;
+        }
+        self_times.push((duration - children_time).max(0.0));
+    }
+
+    let mut children: Vec<ChildTime> = per_child
+        .into_iter()
+        .map(|(name, (calls, times))| child_time(name, calls, &times))
+        .collect();
+    children.sort_by(|a, b| {
+        b.mean
+            .total_cmp(&a.mean)
+            .then_with(|| a.child_name.cmp(&b.child_name))
+    });
+
+    Some(ChildBreakdown {
+        span_name: span_name.to_string(),
+        instances: instances.len(),
+        mean_duration: durations.mean(),
+        children,
+        self_time: child_time(SELF_TIME_NAME.to_string(), instances.len(), &self_times),
+    })
+}
+
+pub struct ChildBreakdownModal {
+    pub show: bool,
+    all_spans: Vec<Rc<Span>>,
+    unique_span_names: Vec<String>,
+    search_text: String,
+    selected_span_name: Option<String>,
+    breakdown: Option<ChildBreakdown>,
+}
+
+impl Default for ChildBreakdownModal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChildBreakdownModal {
+    pub fn new() -> ChildBreakdownModal {
+        ChildBreakdownModal {
+            show: false,
+            all_spans: Vec::new(),
+            unique_span_names: Vec::new(),
+            search_text: String::new(),
+            selected_span_name: None,
+            breakdown: None,
+        }
+    }
+
+    pub fn open(&mut self, spans_for_analysis: &[Rc<Span>]) {
+        let (all_spans, unique_span_names) = process_spans_for_analysis(spans_for_analysis);
+        self.all_spans = all_spans;
+        self.unique_span_names = unique_span_names;
+        self.show = true;
+    }
+
+    /// Opens the modal with the breakdown of `span_name` already computed.
+    pub fn open_for_span_name(&mut self, spans_for_analysis: &[Rc<Span>], span_name: &str) {
+        self.open(spans_for_analysis);
+        self.search_text = span_name.to_string();
+        self.select(span_name.to_string());
+    }
+
+    fn select(&mut self, span_name: String) {
+        self.breakdown = compute_child_breakdown(&self.all_spans, &span_name);
+        self.selected_span_name = Some(span_name);
+    }
+
+    pub fn show_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        if !self.show {
+            return;
+        }
+
+        Modal::new("child breakdown".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Child breakdown");
+            ui.label(
+                "Where do spans with this name spend their time, aggregated over all of them.",
+            );
+            ui.separator();
+
+            ui.horizontal_top(|ui| {
+                ui.vertical(|ui| {
+                    ui.set_width(250.0);
+                    span_search_ui(ui, &mut self.search_text, "Span name", "Search", 240.0);
+                    if span_selection_list_ui(
+                        ui,
+                        &self.unique_span_names,
+                        &self.search_text,
+                        &mut self.selected_span_name,
+                        max_height - 200.0,
+                        "child breakdown span names",
+                    ) {
+                        if let Some(name) = self.selected_span_name.clone() {
+                            self.select(name);
+                        }
+                    }
+                });
+                ui.separator();
+                ui.vertical(|ui| match &self.breakdown {
+                    Some(breakdown) => draw_breakdown(ui, breakdown, max_width - 300.0),
+                    None if self.selected_span_name.is_some() => {
+                        ui.label("No spans with this name.");
+                    }
+                    None => {
+                        ui.label("Choose a span name.");
+                    }
+                });
+            });
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(Key::Escape)) {
+                self.show = false;
+            }
+        });
+    }
+}
+
+/// Colors of the children in the stacked bar, the self time is gray.
+fn child_color(child: &ChildTime) -> Color32 {
+    if child.child_name == SELF_TIME_NAME {
+        theme().weak_text
+    } else {
+        node_color(&child.child_name)
+    }
+}
+
+fn draw_breakdown(ui: &mut Ui, breakdown: &ChildBreakdown, width: f32) {
+    ui.label(format!(
+        "{} instances of {}, mean duration {}",
+        breakdown.instances,
+        breakdown.span_name,
+        format_ms(breakdown.mean_duration)
+    ));
+
+    let rows: Vec<&ChildTime> = breakdown
+        .children
+        .iter()
+        .chain(std::iter::once(&breakdown.self_time))
+        .collect();
+
+    // Stacked bar of the mean times
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(width, 24.0), Sense::hover());
+    let total: f64 = rows.iter().map(|row| row.mean).sum();
+    let mut x = rect.min.x;
+    for row in &rows {
+        if total <= 0.0 || row.mean <= 0.0 {
+            continue;
+        }
+        let segment_width = (row.mean / total) as f32 * rect.width();
+        let segment = Rect::from_min_size(
+            egui::pos2(x, rect.min.y),
+            Vec2::new(segment_width, rect.height()),
+        );
+        ui.painter().rect_filled(segment, 0.0, child_color(row));
+        ui.painter().vline(
+            segment.max.x,
+            segment.y_range(),
+            Stroke::new(1.0, theme().chart_background),
+        );
+        if ui.rect_contains_pointer(segment) {
+            egui::show_tooltip_at_pointer(
+                ui.ctx(),
+                ui.layer_id(),
+                "breakdown segment".into(),
+                |ui| {
+                    ui.label(format!(
+                        "{}: {} ({:.1}%)",
+                        row.child_name,
+                        format_ms(row.mean),
+                        breakdown.share_percent(row)
+                    ));
+                },
+            );
+        }
+        x += segment_width;
+    }
+    ui.add_space(5.0);
+
+    Grid::new("child breakdown table")
+        .striped(true)
+        .show(ui, |ui| {
+            for title in [
+                "Child",
+                "Share",
+                "Mean",
+                "p50",
+                "p99",
+                "Calls",
+                "In instances",
+            ] {
+                ui.strong(title);
+            }
+            ui.end_row();
+            for row in &rows {
+                ui.label(RichText::new(&row.child_name).color(child_color(row)));
+                ui.monospace(format!("{:.1}%", breakdown.share_percent(row)));
+                ui.monospace(format_ms(row.mean));
+                ui.monospace(format_ms(row.p50));
+                ui.monospace(format_ms(row.p99));
+                ui.monospace(row.calls.to_string());
+                ui.monospace(format!(
+                    "{}/{}",
+                    row.instances_with_child, breakdown.instances
+                ));
+                ui.end_row();
+            }
+        });
+}
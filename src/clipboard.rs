@@ -0,0 +1,31 @@
+//! Copying values that can't be selected in the UI, e.g. span ids, summaries and whole tables.
+
+use eframe::egui::{Response, Ui};
+
+/// Small "Copy" button which puts `text` into the clipboard when clicked.
+pub fn copy_button(ui: &mut Ui, hover_text: &str, text: impl FnOnce() -> String) -> Response {
+    let response = ui.small_button("Copy").on_hover_text(hover_text);
+    if response.clicked() {
+        ui.ctx().copy_text(text());
+    }
+    response
+}
+
+/// A table as tab separated values, which can be pasted into a spreadsheet. Tabs and line breaks
+/// inside of the cells are replaced with spaces.
+pub fn table_to_tsv(headers: &[String], rows: &[Vec<String>]) -> String {
+    let line = |cells: &[String]| {
+        cells
+            .iter()
+            .map(|cell| cell.replace(['\t', '\n', '\r'], " "))
+            .collect::<Vec<_>>()
+            .join("\t")
+    };
+    let mut tsv = line(headers);
+    tsv.push('\n');
+    for row in rows {
+        tsv.push_str(&line(row));
+        tsv.push('\n');
+    }
+    tsv
+}
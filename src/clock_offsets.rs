@@ -0,0 +1,536 @@
+//! Estimation and correction of clock offsets between nodes.
+//!
+//! Nodes have their own clocks, and NTP drift of a few milliseconds is enough to make a message
+//! arrive before it was sent. Relation matching skips such pairs, so the arrows between the nodes
+//! disappear. Offsets are estimated from instances of a cross-node relation: the real delay of a
+//! relation is never negative, so the smallest observed delay bounds the difference between the
+//! clocks of the two nodes. The offsets are then subtracted from all timestamps of the node.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::Rc;
+
+use eframe::egui::{self, ComboBox, DragValue, Grid, Modal, RichText, ScrollArea};
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+
+use crate::colors::{node_color, theme};
+use crate::modes::resource_node_name;
+use crate::relation::{find_relations, Relation, RelationNodesConfig, RelationView};
+use crate::types::{format_ms, Span, MILLISECONDS_PER_SECOND};
+
+/// Clock offset of each node in milliseconds, how far its clock is ahead of the reference clock.
+/// Nodes which aren't in the map have no offset.
+pub type NodeOffsets = BTreeMap<String, f64>;
+
+/// Relation instances with a "to" span starting up to this many seconds before the end of the
+/// "from" span are used for the estimation. Bigger offsets are not expected with NTP.
+pub const MAX_CLOCK_OFFSET: f64 = 1.0;
+
+/// Instances of `relation` between spans on different nodes. Unlike normal relation matching,
+/// instances with a negative delay (up to [MAX_CLOCK_OFFSET]) are included.
+/// Relation instances are attached to the spans, so `spans` shouldn't be the displayed ones.
+pub fn find_offset_links(relation: &Relation, spans: &[Rc<Span>]) -> Vec<(Rc<Span>, Rc<Span>)> {
+    let mut relation = relation.clone();
+    relation.min_time_diff = relation.min_time_diff.min(-MAX_CLOCK_OFFSET);
+    let view = RelationView {
+        enabled_relations: vec![relation.id],
+        name: "Clock offsets".to_string(),
+        is_builtin: false,
+    };
+    find_relations(std::slice::from_ref(&relation), &view, spans)
+        .into_iter()
+        .filter_map(|instance| Some((instance.from_span.upgrade()?, instance.to_span.upgrade()?)))
+        .filter(|(from, to)| from.node.name != to.node.name)
+        .collect()
+}
+
+/// Smallest delay (in seconds) between the end of the "from" span and the start of the "to" span,
+/// for every (from node, to node) pair with links.
+pub fn min_link_delays(links: &[(Rc<Span>, Rc<Span>)]) -> BTreeMap<(String, String), f64> {
+    let mut delays: BTreeMap<(String, String), f64> = BTreeMap::new();
+    for (from, to) in links {
+        let delay = to.start_time - from.end_time;
+        delays
+            .entry((from.node.name.clone(), to.node.name.clone()))
+            .and_modify(|d| *d = d.min(delay))
+            .or_insert(delay);
+    }
+    delays
+}
+
+/// The node with the most links, a good default for the reference clock.
+pub fn default_reference_node(links: &[(Rc<Span>, Rc<Span>)]) -> Option<String> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for (from, to) in links {
+        *counts.entry(from.node.name.as_str()).or_default() += 1;
+        *counts.entry(to.node.name.as_str()).or_default() += 1;
+    }
+    // max_by_key returns the last maximum, iterate in reverse to prefer the first name.
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(name, _)| name.to_string())
+}
+
+/// Estimates the clock offsets of nodes connected to `reference_node` by links.
+///
+/// For links from A to B the offset of B relative to A is at most the smallest delay. When there
+/// are links in both directions the offset is in the middle of the allowed range. With links in one
+/// direction only, the offset is the smallest one which makes all delays non-negative, zero when
+/// they already are. Offsets are propagated from the reference node, unconnected nodes are left
+/// out.
+pub fn estimate_clock_offsets(links: &[(Rc<Span>, Rc<Span>)], reference_node: &str) -> NodeOffsets {
+    let delays = min_link_delays(links);
+    // Offset of the second node relative to the first one, in seconds.
+    let mut neighbours: BTreeMap<&str, Vec<(&str, f64)>> = BTreeMap::new();
+    for ((from, to), &delay) in &delays {
+        let relative = match delays.get(&(to.clone(), from.clone())) {
+            Some(&reverse_delay) => (delay - reverse_delay) / 2.0,
+            None => delay.min(0.0),
+        };
+        neighbours.entry(from).or_default().push((to, relative));
+        neighbours.entry(to).or_default().push((from, -relative));
+    }
+
+    let mut offsets = NodeOffsets::new();
+    if !neighbours.contains_key(reference_node) {
+        return offsets;
+    }
+    offsets.insert(reference_node.to_string(), 0.0);
+    let mut queue = VecDeque::from([reference_node]);
+    while let Some(node) = queue.pop_front() {
+        let node_offset = offsets[node];
+        for (neighbour, relative) in neighbours.get(node).into_iter().flatten() {
+            if offsets.contains_key(*neighbour) {
+                continue;
+            }
+            offsets.insert(
+                neighbour.to_string(),
+                node_offset + relative * MILLISECONDS_PER_SECOND,
+            );
+            queue.push_back(neighbour);
+        }
+    }
+    offsets
+}
+
+/// Offsets in whole nanoseconds, so that applying and reverting them gives the original timestamps.
+fn offset_nanos(offsets: &NodeOffsets, node: &str) -> i64 {
+    offsets
+        .get(node)
+        .map_or(0, |ms| (ms * 1_000_000.0).round() as i64)
+}
+
+/// Corrects the timestamps of spans and events in the raw data, which currently have the `applied`
+/// offsets subtracted, to have the `new` offsets subtracted instead.
+pub fn shift_node_clocks(
+    requests: &mut [ExportTraceServiceRequest],
+    applied: &NodeOffsets,
+    new: &NodeOffsets,
+) {
+    for request in requests {
+        for resource_spans in &mut request.resource_spans {
+            let node = resource_node_name(resource_spans.resource.as_ref());
+            let shift = offset_nanos(applied, &node) - offset_nanos(new, &node);
+            if shift == 0 {
+                continue;
+            }
+            for scope_spans in &mut resource_spans.scope_spans {
+                for span in &mut scope_spans.spans {
+                    span.start_time_unix_nano =
+                        span.start_time_unix_nano.saturating_add_signed(shift);
+                    span.end_time_unix_nano = span.end_time_unix_nano.saturating_add_signed(shift);
+                    for event in &mut span.events {
+                        event.time_unix_nano = event.time_unix_nano.saturating_add_signed(shift);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Short description of the offsets for the UI, e.g. "node1: +2.150 ms, node2: -0.400 ms".
+pub fn describe_offsets(offsets: &NodeOffsets) -> String {
+    offsets
+        .iter()
+        .filter(|(_, ms)| **ms != 0.0)
+        .map(|(node, ms)| format!("{node}: {ms:+.3} ms"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Names of all nodes in the raw data, sorted.
+pub fn trace_node_names(requests: &[ExportTraceServiceRequest]) -> Vec<String> {
+    let mut names: Vec<String> = requests
+        .iter()
+        .flat_map(|request| &request.resource_spans)
+        .map(|resource_spans| resource_node_name(resource_spans.resource.as_ref()))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Dialog for entering the offset of each node by hand, e.g. when the drift of a node is known.
+pub struct EditClockOffsets {
+    show: bool,
+    /// Every node of the trace with its offset in milliseconds.
+    offsets: Vec<(String, f64)>,
+}
+
+impl Default for EditClockOffsets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditClockOffsets {
+    pub fn new() -> EditClockOffsets {
+        EditClockOffsets {
+            show: false,
+            offsets: Vec::new(),
+        }
+    }
+
+    /// `node_names` are all nodes in the trace, `offsets` the currently applied offsets.
+    pub fn open(&mut self, node_names: Vec<String>, offsets: &NodeOffsets) {
+        self.offsets = node_names
+            .into_iter()
+            .map(|node| {
+                let offset = offsets.get(&node).copied().unwrap_or(0.0);
+                (node, offset)
+            })
+            .collect();
+        self.show = true;
+    }
+
+    /// Returns the new offsets when the user clicks "Save", nodes without an offset are left out.
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        max_width: f32,
+        max_height: f32,
+    ) -> Option<NodeOffsets> {
+        if !self.show {
+            return None;
+        }
+
+        let mut result = None;
+        Modal::new("edit clock offsets".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Clock offsets");
+            ui.label(
+                "The offset is subtracted from all timestamps of the node, enter 40 ms for a node \
+                 whose clock is 40 ms ahead. Offsets are remembered for the file.",
+            );
+            ui.separator();
+
+            ScrollArea::vertical()
+                .max_height(max_height - 150.0)
+                .show(ui, |ui| {
+                    Grid::new("edit clock offsets grid")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (node, offset) in &mut self.offsets {
+                                ui.label(RichText::new(node.as_str()).color(node_color(node)));
+                                ui.add(DragValue::new(offset).speed(0.1).suffix(" ms"));
+                                if ui
+                                    .add_enabled(*offset != 0.0, egui::Button::new("Clear"))
+                                    .clicked()
+                                {
+                                    *offset = 0.0;
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    result = Some(
+                        self.offsets
+                            .drain(..)
+                            .filter(|(_, offset)| *offset != 0.0)
+                            .collect(),
+                    );
+                    self.show = false;
+                }
+                if ui.button("Clear all").clicked() {
+                    for (_, offset) in &mut self.offsets {
+                        *offset = 0.0;
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    self.show = false;
+                }
+            });
+        });
+
+        result
+    }
+}
+
+pub struct ClockOffsetsModal {
+    pub show: bool,
+    /// Private copy of the spans in the trace, relation instances found for the estimation are
+    /// attached to them.
+    spans: Vec<Rc<Span>>,
+    /// Relations which can connect spans on different nodes.
+    relations: Vec<Relation>,
+    selected_relation: Option<usize>,
+    links: Vec<(Rc<Span>, Rc<Span>)>,
+    reference_node: Option<String>,
+    /// Offsets which are currently subtracted from the timestamps.
+    applied: NodeOffsets,
+    /// Estimated offsets of the corrected timestamps, they are added to the applied ones.
+    estimated: NodeOffsets,
+    /// Offsets chosen by the user, the app should correct the timestamps with them.
+    pub apply_offsets: Option<NodeOffsets>,
+}
+
+impl Default for ClockOffsetsModal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClockOffsetsModal {
+    pub fn new() -> ClockOffsetsModal {
+        ClockOffsetsModal {
+            show: false,
+            spans: Vec::new(),
+            relations: Vec::new(),
+            selected_relation: None,
+            links: Vec::new(),
+            reference_node: None,
+            applied: NodeOffsets::new(),
+            estimated: NodeOffsets::new(),
+            apply_offsets: None,
+        }
+    }
+
+    /// `spans` shouldn't be shared with the rest of the app, see [find_offset_links].
+    pub fn open(&mut self, spans: Vec<Rc<Span>>, relations: &[Relation], applied: &NodeOffsets) {
+        self.spans = spans;
+        self.relations = relations
+            .iter()
+            .filter(|r| r.nodes_config != RelationNodesConfig::SameNode)
+            .cloned()
+            .collect();
+        if self
+            .selected_relation
+            .is_none_or(|i| i >= self.relations.len())
+        {
+            self.selected_relation = self
+                .relations
+                .iter()
+                .position(|r| r.nodes_config == RelationNodesConfig::DifferentNode)
+                .or_else(|| (!self.relations.is_empty()).then_some(0));
+        }
+        self.applied = applied.clone();
+        self.show = true;
+        self.find_links();
+    }
+
+    fn find_links(&mut self) {
+        self.links = match self.selected_relation.and_then(|i| self.relations.get(i)) {
+            Some(relation) => find_offset_links(relation, &self.spans),
+            None => Vec::new(),
+        };
+        let links_on_reference = |node: &String| {
+            self.links
+                .iter()
+                .any(|(from, to)| from.node.name == *node || to.node.name == *node)
+        };
+        if !self.reference_node.as_ref().is_some_and(links_on_reference) {
+            self.reference_node = default_reference_node(&self.links);
+        }
+        self.estimate();
+    }
+
+    fn estimate(&mut self) {
+        self.estimated = match &self.reference_node {
+            Some(reference_node) => estimate_clock_offsets(&self.links, reference_node),
+            None => NodeOffsets::new(),
+        };
+    }
+
+    /// Applied offsets with the estimated ones added.
+    fn total_offsets(&self) -> NodeOffsets {
+        let mut total = self.applied.clone();
+        for (node, estimated) in &self.estimated {
+            *total.entry(node.clone()).or_default() += estimated;
+        }
+        total
+    }
+
+    pub fn show_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        if !self.show {
+            return;
+        }
+
+        let mut relation_changed = false;
+        let mut reference_changed = false;
+        Modal::new("clock offsets".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Clock offsets");
+            ui.label(
+                "Estimates how far the clock of each node is off, using a relation between spans \
+                 on different nodes whose real delay can't be negative. The offsets can be \
+                 subtracted from all timestamps of the node.",
+            );
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Relation:");
+                let selected_name = self
+                    .selected_relation
+                    .and_then(|i| self.relations.get(i))
+                    .map_or("<none>", |r| r.name.as_str());
+                ComboBox::from_id_salt("clock offsets relation")
+                    .selected_text(selected_name)
+                    .show_ui(ui, |ui| {
+                        for (i, relation) in self.relations.iter().enumerate() {
+                            relation_changed |= ui
+                                .selectable_value(
+                                    &mut self.selected_relation,
+                                    Some(i),
+                                    &relation.name,
+                                )
+                                .changed();
+                        }
+                    });
+                ui.label(format!("{} cross-node instances", self.links.len()));
+            });
+
+            let mut nodes: Vec<String> = self.applied.keys().cloned().collect();
+            for (from, to) in &self.links {
+                nodes.push(from.node.name.clone());
+                nodes.push(to.node.name.clone());
+            }
+            nodes.sort();
+            nodes.dedup();
+
+            ui.horizontal(|ui| {
+                ui.label("Reference node:");
+                ComboBox::from_id_salt("clock offsets reference node")
+                    .selected_text(self.reference_node.as_deref().unwrap_or("<none>"))
+                    .show_ui(ui, |ui| {
+                        for node in &nodes {
+                            reference_changed |= ui
+                                .selectable_value(
+                                    &mut self.reference_node,
+                                    Some(node.clone()),
+                                    node,
+                                )
+                                .changed();
+                        }
+                    });
+            });
+            ui.separator();
+
+            let total = self.total_offsets();
+            let delays = min_link_delays(&self.links);
+            ScrollArea::vertical()
+                .max_height(max_height - 250.0)
+                .show(ui, |ui| {
+                    Grid::new("clock offsets grid")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong("Node");
+                            ui.strong("Applied");
+                            ui.strong("Estimated");
+                            ui.strong("Offset");
+                            ui.strong("Min incoming delay");
+                            ui.end_row();
+                            for node in &nodes {
+                                ui.label(RichText::new(node).color(node_color(node)));
+                                let applied = self.applied.get(node).copied().unwrap_or(0.0);
+                                ui.monospace(format!("{applied:+.3} ms"));
+                                match self.estimated.get(node) {
+                                    Some(estimated) => {
+                                        ui.monospace(format!("{estimated:+.3} ms"));
+                                    }
+                                    None => {
+                                        ui.label(
+                                            RichText::new("not connected").color(theme().weak_text),
+                                        );
+                                    }
+                                }
+                                ui.monospace(format!(
+                                    "{:+.3} ms",
+                                    total.get(node).copied().unwrap_or(0.0)
+                                ));
+                                let min_delay = delays
+                                    .iter()
+                                    .filter(|((_, to), _)| to == node)
+                                    .map(|(_, delay)| *delay)
+                                    .min_by(f64::total_cmp);
+                                match min_delay {
+                                    Some(delay) => {
+                                        let text = format_ms(delay);
+                                        if delay < 0.0 {
+                                            ui.monospace(RichText::new(text).color(theme().error));
+                                        } else {
+                                            ui.monospace(text);
+                                        }
+                                    }
+                                    None => {
+                                        ui.label("");
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+            if !self.applied.is_empty() {
+                ui.label(
+                    RichText::new(
+                        "Timestamps are already corrected, the estimation is done on the \
+                         corrected ones.",
+                    )
+                    .color(theme().weak_text),
+                );
+            }
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!self.estimated.is_empty(), egui::Button::new("Apply"))
+                    .on_hover_text("Subtract the offsets from all timestamps of each node")
+                    .clicked()
+                {
+                    self.apply_offsets = Some(total);
+                    self.show = false;
+                }
+                if ui
+                    .add_enabled(!self.applied.is_empty(), egui::Button::new("Reset"))
+                    .on_hover_text("Go back to the original timestamps")
+                    .clicked()
+                {
+                    self.apply_offsets = Some(NodeOffsets::new());
+                    self.show = false;
+                }
+                if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    self.show = false;
+                }
+            });
+        });
+
+        if relation_changed {
+            self.find_links();
+        } else if reference_changed {
+            self.estimate();
+        }
+        if !self.show {
+            // The copy of the spans isn't needed anymore.
+            self.spans.clear();
+            self.links.clear();
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Collapsing or expanding all spans at once, and collapse choices remembered per display mode.
+//! Switching the display mode creates new spans with the default collapse state of the mode, the
+//! remembered choices are applied to them again.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::types::{LazyChildren, Span};
+
+/// Sets `collapse_children` of the spans and all their descendants. Lazy children get it when
+/// they're transformed, otherwise spans under a lazy subtree would get the default state of the
+/// mode once expanded.
+pub fn set_collapse_all(spans: &[Rc<Span>], collapse: bool) {
+    apply_to_tree(
+        spans,
+        Rc::new(move |span| span.collapse_children.set(collapse)),
+    );
+}
+
+/// Calls `apply` on the spans and their transformed descendants, and on the lazy ones when they're
+/// transformed, without transforming them now.
+fn apply_to_tree(spans: &[Rc<Span>], apply: Rc<dyn Fn(&Span)>) {
+    for span in spans {
+        apply(span);
+        apply_to_tree(&span.transformed_children(), apply.clone());
+        let mut lazy_children = span.lazy_children.borrow_mut();
+        if let Some(LazyChildren { transform, .. }) = lazy_children.as_mut() {
+            let transform_child = transform.clone();
+            let apply = apply.clone();
+            *transform = Rc::new(move |child| {
+                let transformed = transform_child(child);
+                apply_to_tree(std::slice::from_ref(&transformed), apply.clone());
+                transformed
+            });
+        }
+    }
+}
+
+/// Collapse choices made in one display mode.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ModeChoices {
+    /// Set by "Collapse all" or "Expand all".
+    all: Option<bool>,
+    /// Choices made for single spans after that, applied to all spans with the name.
+    by_name: HashMap<String, bool>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CollapseMemory {
+    /// Choices by the name of the display mode.
+    modes: HashMap<String, ModeChoices>,
+}
+
+impl CollapseMemory {
+    /// Remembers "Collapse all" or "Expand all", forgetting the choices for single spans.
+    pub fn remember_all(&mut self, mode_name: &str, collapse: bool) {
+        self.modes.insert(
+            mode_name.to_string(),
+            ModeChoices {
+                all: Some(collapse),
+                by_name: HashMap::new(),
+            },
+        );
+    }
+
+    /// Remembers that a span with this name was collapsed or expanded.
+    pub fn remember_span(&mut self, mode_name: &str, span_name: &str, collapse: bool) {
+        self.modes
+            .entry(mode_name.to_string())
+            .or_default()
+            .by_name
+            .insert(span_name.to_string(), collapse);
+    }
+
+    /// The remembered choice for spans with this name, `None` if there isn't one.
+    pub fn choice(&self, mode_name: &str, span_name: &str) -> Option<bool> {
+        let choices = self.modes.get(mode_name)?;
+        choices.by_name.get(span_name).copied().or(choices.all)
+    }
+
+    /// Applies the remembered choices to the spans and all their descendants, after they were
+    /// created by the display mode. Lazy children get them when they're transformed.
+    pub fn apply(&self, mode_name: &str, spans: &[Rc<Span>]) {
+        let Some(choices) = self.modes.get(mode_name).cloned() else {
+            return;
+        };
+        apply_to_tree(
+            spans,
+            Rc::new(move |span| {
+                let choice = choices
+                    .by_name
+                    .get(&span.original_name)
+                    .copied()
+                    .or(choices.all);
+                if let Some(collapse) = choice {
+                    span.collapse_children.set(collapse);
+                }
+            }),
+        );
+    }
+}
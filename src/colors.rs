@@ -1,4 +1,11 @@
-use eframe::egui::Color32;
+//! Colors used by traviz. The constants are the palette, everything which is drawn picks its color
+//! from the current [`Theme`] (`theme().span_time`, ...) so that the whole UI can be switched
+//! between the dark and light themes.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
+use eframe::egui::{self, Color32};
 
 pub const BLACK: Color32 = Color32::BLACK;
 pub const WHITE: Color32 = Color32::WHITE;
@@ -11,6 +18,7 @@ pub const GRAY_10: Color32 = Color32::from_gray(10);
 pub const GRAY_30: Color32 = Color32::from_gray(30);
 pub const GRAY_40: Color32 = Color32::from_gray(40);
 pub const GRAY_50: Color32 = Color32::from_gray(50);
+pub const GRAY_150: Color32 = Color32::from_gray(150);
 pub const GRAY_230: Color32 = Color32::from_gray(230);
 pub const GRAY_240: Color32 = Color32::from_gray(240);
 
@@ -24,6 +32,7 @@ pub const INTENSE_BLUE2: Color32 = Color32::from_rgb(0, 110, 230);
 pub const DARK_BLUE: Color32 = Color32::from_rgb(51, 102, 153);
 
 pub const TRANSPARENT_GRAY: Color32 = Color32::from_rgba_premultiplied(30, 30, 30, 230);
+pub const TRANSPARENT_WHITE: Color32 = Color32::from_rgba_premultiplied(40, 40, 40, 40);
 pub const BLUE_DARK_GRAY: Color32 = Color32::from_rgb(60, 60, 70);
 pub const ALMOST_BLACK: Color32 = Color32::from_rgb(10, 10, 20);
 pub const VERY_LIGHT_YELLOW: Color32 = Color32::from_rgb(255, 255, 220);
@@ -31,7 +40,426 @@ pub const DARK_YELLOW: Color32 = Color32::from_rgb(242, 176, 34);
 pub const MILD_RED: Color32 = Color32::from_rgb(220, 50, 50);
 pub const INTENSE_RED: Color32 = Color32::from_rgb(255, 51, 0);
 pub const INTENSE_GREEN: Color32 = Color32::from_rgb(50, 200, 50);
+pub const PURPLE: Color32 = Color32::from_rgb(160, 90, 220);
+pub const TEAL: Color32 = Color32::from_rgb(0, 190, 170);
 
 pub fn transparent_yellow() -> Color32 {
     Color32::from_rgba_unmultiplied(242, 176, 34, 1)
 }
+
+/// Colors used to tell nodes apart, readable on the dark node name column and in popups.
+pub const NODE_COLORS: [Color32; 10] = [
+    Color32::from_rgb(230, 159, 0),
+    Color32::from_rgb(86, 180, 233),
+    Color32::from_rgb(0, 158, 115),
+    Color32::from_rgb(240, 228, 66),
+    Color32::from_rgb(0, 114, 178),
+    Color32::from_rgb(213, 94, 0),
+    Color32::from_rgb(204, 121, 167),
+    Color32::from_rgb(150, 200, 80),
+    Color32::from_rgb(170, 130, 230),
+    Color32::from_rgb(120, 210, 200),
+];
+
+/// Darker versions of [`NODE_COLORS`], readable on a light background.
+pub const LIGHT_NODE_COLORS: [Color32; 10] = [
+    Color32::from_rgb(176, 110, 0),
+    Color32::from_rgb(20, 120, 180),
+    Color32::from_rgb(0, 120, 85),
+    Color32::from_rgb(140, 130, 0),
+    Color32::from_rgb(0, 80, 140),
+    Color32::from_rgb(180, 70, 0),
+    Color32::from_rgb(170, 70, 130),
+    Color32::from_rgb(80, 130, 20),
+    Color32::from_rgb(110, 70, 180),
+    Color32::from_rgb(30, 130, 120),
+];
+
+/// Color of a node, used consistently in the node lanes and analysis popups.
+/// The color depends only on the node name, so it's stable between runs and trace files.
+pub fn node_color(node_name: &str) -> Color32 {
+    // FNV-1a, std's DefaultHasher isn't guaranteed to be stable between releases.
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in node_name.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    let node_colors = theme().node_colors;
+    node_colors[hash as usize % node_colors.len()]
+}
+
+/// Colors of everything that traviz draws itself. The egui widgets use the egui style of
+/// `dark_mode`. New palettes are a new constructor, the fields are named by what they color, not
+/// by the color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub dark_mode: bool,
+
+    /// Text drawn directly by the painter, outside of the egui widgets.
+    pub text: Color32,
+    /// Less important text, e.g. hints and the "other" entries.
+    pub weak_text: Color32,
+    pub error: Color32,
+    pub warning: Color32,
+    /// Clickable values in the analysis tables.
+    pub link: Color32,
+    /// Selected item in the editor lists.
+    pub selected_item: Color32,
+    /// Drop indicators, pinned spans, the time origin and other things that should stand out.
+    pub accent: Color32,
+
+    pub timeline_background: Color32,
+    pub timeline_selection: Color32,
+    pub timeline_text: Color32,
+    /// The bar with the search and the time points of the selected range.
+    pub panel_background: Color32,
+    pub input_background: Color32,
+    pub time_points_background: Color32,
+    pub time_points_text: Color32,
+
+    pub spans_background: Color32,
+    /// Background of the extra lanes, e.g. utilization, concurrency and events.
+    pub lane_background: Color32,
+    pub lane_name_background: Color32,
+    pub lane_name_text: Color32,
+    pub lane_separator: Color32,
+
+    /// The part of a span between its start and end time.
+    pub span_time: Color32,
+    /// The parts of a grouped span in which one of the grouped spans was running.
+    pub grouped_span_active: Color32,
+    /// The rest of the span's rectangle, which is there to fit its name.
+    pub span_background: Color32,
+    pub span_text: Color32,
+    pub highlighted_span_time: Color32,
+    pub highlighted_span_background: Color32,
+    pub highlighted_span_border: Color32,
+    pub selection_outline: Color32,
+    pub selection_fill: Color32,
+    pub unmatched_span: Color32,
+    /// Border and badge of the spans displayed under a parent from another node.
+    pub remote_span: Color32,
+    /// Time part of the spans of the focused trace.
+    pub focused_trace: Color32,
+    /// Line at the top of the top level spans.
+    pub top_level_line: Color32,
+    /// Line at the top of the top level grouped spans.
+    pub grouped_span_line: Color32,
+    pub event_tick: Color32,
+    pub event_marker: Color32,
+
+    pub relation_arrow: Color32,
+    pub hovered_relation_arrow: Color32,
+    pub near_miss_halo: Color32,
+    pub arrow_label_text: Color32,
+    pub arrow_label_background: Color32,
+
+    pub chart_background: Color32,
+    pub chart_axis: Color32,
+    pub chart_text: Color32,
+    /// Outline of the hovered point or bar.
+    pub chart_hover: Color32,
+    /// Main data series, e.g. the delays of a dependency analysis.
+    pub chart_line: Color32,
+    /// Secondary data series and bars.
+    pub chart_fill: Color32,
+    /// Values over a threshold and the worst case series (p99, end skew, ...).
+    pub chart_alert: Color32,
+
+    pub node_colors: [Color32; 10],
+}
+
+impl Theme {
+    pub fn dark() -> Theme {
+        Theme {
+            dark_mode: true,
+
+            text: GRAY_240,
+            weak_text: GRAY_150,
+            error: MILD_RED,
+            warning: DARK_YELLOW,
+            link: MILD_BLUE2,
+            selected_item: DARK_BLUE,
+            accent: INTENSE_GREEN,
+
+            timeline_background: MILD_BLUE,
+            timeline_selection: LIGHT_BLUE,
+            timeline_text: GRAY_50,
+            panel_background: GRAY_10,
+            input_background: GRAY_40,
+            time_points_background: BLUE_DARK_GRAY,
+            time_points_text: GRAY_240,
+
+            spans_background: GRAY_30,
+            lane_background: GRAY_50,
+            lane_name_background: ALMOST_BLACK,
+            lane_name_text: WHITE,
+            lane_separator: GRAY_230,
+
+            span_time: DARK_YELLOW,
+            grouped_span_active: DARK_YELLOW,
+            span_background: VERY_LIGHT_YELLOW,
+            span_text: BLACK,
+            highlighted_span_time: INTENSE_BLUE,
+            highlighted_span_background: VERY_LIGHT_BLUE,
+            highlighted_span_border: INTENSE_BLUE2,
+            selection_outline: WHITE,
+            selection_fill: TRANSPARENT_WHITE,
+            unmatched_span: INTENSE_RED,
+            remote_span: PURPLE,
+            focused_trace: TEAL,
+            top_level_line: INTENSE_RED,
+            grouped_span_line: INTENSE_GREEN,
+            event_tick: DARK_BLUE,
+            event_marker: LIGHT_BLUE,
+
+            relation_arrow: INTENSE_BLUE,
+            hovered_relation_arrow: VERY_LIGHT_BLUE2,
+            near_miss_halo: DARK_YELLOW.gamma_multiply(0.6),
+            arrow_label_text: GRAY_240,
+            arrow_label_background: TRANSPARENT_GRAY,
+
+            chart_background: GRAY_10,
+            chart_axis: GRAY_150,
+            chart_text: GRAY_230,
+            chart_hover: WHITE,
+            chart_line: MILD_BLUE2,
+            chart_fill: LIGHT_BLUE,
+            chart_alert: MILD_RED,
+
+            node_colors: NODE_COLORS,
+        }
+    }
+
+    /// Dark text on light backgrounds, readable on projectors. The spans keep their colors.
+    pub fn light() -> Theme {
+        let dark_text = Color32::from_gray(30);
+        let dark_red = Color32::from_rgb(190, 30, 30);
+        let dark_blue = Color32::from_rgb(20, 100, 170);
+        let dark_green = Color32::from_rgb(0, 140, 60);
+        Theme {
+            dark_mode: false,
+
+            text: dark_text,
+            weak_text: Color32::from_gray(110),
+            error: dark_red,
+            warning: Color32::from_rgb(180, 110, 0),
+            link: dark_blue,
+            selected_item: VERY_LIGHT_BLUE,
+            accent: dark_green,
+
+            timeline_background: Color32::from_rgb(170, 200, 220),
+            timeline_selection: Color32::from_rgb(100, 165, 210),
+            timeline_text: dark_text,
+            panel_background: GRAY_230,
+            input_background: WHITE,
+            time_points_background: Color32::from_rgb(205, 210, 220),
+            time_points_text: dark_text,
+
+            spans_background: GRAY_240,
+            lane_background: Color32::from_gray(215),
+            lane_name_background: Color32::from_rgb(225, 228, 238),
+            lane_name_text: BLACK,
+            lane_separator: Color32::from_gray(120),
+
+            span_time: DARK_YELLOW,
+            grouped_span_active: DARK_YELLOW,
+            span_background: Color32::from_rgb(250, 230, 170),
+            span_text: BLACK,
+            highlighted_span_time: INTENSE_BLUE,
+            highlighted_span_background: VERY_LIGHT_BLUE,
+            highlighted_span_border: INTENSE_BLUE2,
+            selection_outline: BLACK,
+            selection_fill: Color32::from_rgba_premultiplied(0, 0, 0, 30),
+            unmatched_span: INTENSE_RED,
+            remote_span: PURPLE,
+            focused_trace: TEAL,
+            top_level_line: INTENSE_RED,
+            grouped_span_line: dark_green,
+            event_tick: DARK_BLUE,
+            event_marker: MILD_BLUE,
+
+            relation_arrow: INTENSE_BLUE2,
+            hovered_relation_arrow: Color32::from_rgb(0, 60, 150),
+            near_miss_halo: DARK_YELLOW.gamma_multiply(0.6),
+            arrow_label_text: GRAY_240,
+            arrow_label_background: TRANSPARENT_GRAY,
+
+            chart_background: WHITE,
+            chart_axis: Color32::from_gray(120),
+            chart_text: Color32::from_gray(40),
+            chart_hover: BLACK,
+            chart_line: dark_blue,
+            chart_fill: Color32::from_rgb(90, 160, 210),
+            chart_alert: dark_red,
+
+            node_colors: LIGHT_NODE_COLORS,
+        }
+    }
+
+    /// Replaces the colors which the palette changes.
+    pub fn with_palette(mut self, palette: Palette) -> Theme {
+        // Colors from the Okabe-Ito palette, which is readable with all common kinds of color
+        // blindness. Red and green aren't used to tell things apart.
+        let orange = Color32::from_rgb(230, 159, 0);
+        let sky_blue = Color32::from_rgb(86, 180, 233);
+        let blue = Color32::from_rgb(0, 114, 178);
+        let vermillion = Color32::from_rgb(213, 94, 0);
+        let reddish_purple = Color32::from_rgb(204, 121, 167);
+        let (alert, accent) = match palette {
+            Palette::Default => return self,
+            Palette::Deuteranopia => (vermillion, reddish_purple),
+            // Red looks dark with protanopia, so alerts use the purple which stays bright.
+            Palette::Protanopia => (reddish_purple, sky_blue),
+        };
+        self.span_time = orange;
+        self.grouped_span_active = orange;
+        self.highlighted_span_time = blue;
+        self.highlighted_span_border = blue;
+        self.highlighted_span_background = Color32::from_rgb(190, 225, 245);
+        self.relation_arrow = blue;
+        self.accent = accent;
+        self.grouped_span_line = accent;
+        self.unmatched_span = alert;
+        self.top_level_line = alert;
+        self.error = alert;
+        self.chart_alert = alert;
+        self.chart_line = blue;
+        self.chart_fill = sky_blue;
+        self
+    }
+
+    /// Replaces the colors which the user has chosen.
+    pub fn with_overrides(mut self, overrides: &ColorOverrides) -> Theme {
+        for (semantic_color, [r, g, b]) in overrides {
+            semantic_color.set(&mut self, Color32::from_rgb(*r, *g, *b));
+        }
+        self
+    }
+
+    pub fn egui_theme(&self) -> egui::Theme {
+        if self.dark_mode {
+            egui::Theme::Dark
+        } else {
+            egui::Theme::Light
+        }
+    }
+}
+
+/// Theme chosen in the preferences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum ThemeKind {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl ThemeKind {
+    pub fn all() -> [ThemeKind; 2] {
+        [ThemeKind::Dark, ThemeKind::Light]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThemeKind::Dark => "Dark",
+            ThemeKind::Light => "Light",
+        }
+    }
+
+    pub fn theme(&self) -> Theme {
+        match self {
+            ThemeKind::Dark => Theme::dark(),
+            ThemeKind::Light => Theme::light(),
+        }
+    }
+}
+
+/// Alternative colors for people who can't tell the default ones apart. Applied on top of the
+/// dark or light theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum Palette {
+    #[default]
+    Default,
+    Deuteranopia,
+    Protanopia,
+}
+
+impl Palette {
+    pub fn all() -> [Palette; 3] {
+        [Palette::Default, Palette::Deuteranopia, Palette::Protanopia]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Palette::Default => "Default",
+            Palette::Deuteranopia => "Deuteranopia safe",
+            Palette::Protanopia => "Protanopia safe",
+        }
+    }
+}
+
+/// Colors of the theme which the user can override.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub enum SemanticColor {
+    Highlight,
+    RelationArrow,
+    GroupedSpanActive,
+}
+
+/// sRGB colors chosen by the user, they take precedence over the theme and the palette.
+pub type ColorOverrides = BTreeMap<SemanticColor, [u8; 3]>;
+
+impl SemanticColor {
+    pub fn all() -> [SemanticColor; 3] {
+        [
+            SemanticColor::Highlight,
+            SemanticColor::RelationArrow,
+            SemanticColor::GroupedSpanActive,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SemanticColor::Highlight => "Highlighted spans",
+            SemanticColor::RelationArrow => "Relation arrows",
+            SemanticColor::GroupedSpanActive => "Active segments of grouped spans",
+        }
+    }
+
+    pub fn get(&self, theme: &Theme) -> Color32 {
+        match self {
+            SemanticColor::Highlight => theme.highlighted_span_time,
+            SemanticColor::RelationArrow => theme.relation_arrow,
+            SemanticColor::GroupedSpanActive => theme.grouped_span_active,
+        }
+    }
+
+    pub fn set(&self, theme: &mut Theme, color: Color32) {
+        match self {
+            SemanticColor::Highlight => {
+                theme.highlighted_span_time = color;
+                theme.highlighted_span_border = color;
+                theme.highlighted_span_background = color.lerp_to_gamma(WHITE, 0.7);
+            }
+            SemanticColor::RelationArrow => theme.relation_arrow = color,
+            SemanticColor::GroupedSpanActive => theme.grouped_span_active = color,
+        }
+    }
+}
+
+thread_local! {
+    // The UI is drawn on one thread, the theme is global so that it doesn't have to be passed to
+    // every function that draws something.
+    static CURRENT_THEME: Cell<Theme> = Cell::new(Theme::dark());
+}
+
+/// The theme that everything is currently drawn with.
+pub fn theme() -> Theme {
+    CURRENT_THEME.with(Cell::get)
+}
+
+pub fn set_theme(theme: Theme) {
+    CURRENT_THEME.with(|current| current.set(theme));
+}
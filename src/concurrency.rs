@@ -0,0 +1,255 @@
+//! Concurrency overlay - a strip over or under each lane with the number of simultaneously open
+//! spans matching a selector. Shows e.g. how many chunk validations run at the same time, which
+//! helps to debug saturated thread pools.
+
+use std::rc::Rc;
+
+use eframe::egui::{self, Align2, FontId, Modal, Pos2, Rect, Sense, Stroke, Ui};
+
+use crate::colors::theme;
+use crate::edit_modes::EditDisplayModes;
+use crate::structured_modes::SpanSelector;
+use crate::types::{Span, TimePoint};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum OverlayPosition {
+    AboveLane,
+    BelowLane,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ConcurrencyOverlaySettings {
+    pub enabled: bool,
+    /// Open spans matching this selector are counted.
+    pub selector: SpanSelector,
+    pub position: OverlayPosition,
+}
+
+impl Default for ConcurrencyOverlaySettings {
+    fn default() -> Self {
+        ConcurrencyOverlaySettings {
+            enabled: false,
+            selector: SpanSelector::new_equal_name("validate_chunk_state_witness"),
+            position: OverlayPosition::BelowLane,
+        }
+    }
+}
+
+/// Number of open spans over time, as a step function.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConcurrencySteps {
+    /// (time, number of open spans from this time until the next step), sorted by time. The first
+    /// step is at the start of the window.
+    pub steps: Vec<(TimePoint, usize)>,
+    pub max: usize,
+}
+
+impl ConcurrencySteps {
+    /// Number of open spans at the time.
+    pub fn count_at(&self, time: TimePoint) -> usize {
+        let idx = self
+            .steps
+            .partition_point(|(step_time, _)| *step_time <= time);
+        idx.checked_sub(1).map_or(0, |i| self.steps[i].1)
+    }
+}
+
+/// Number of spans matching the selector which are open at each moment of `start..end`. `spans`
+/// are searched recursively.
+pub fn compute_concurrency<'a>(
+    spans: impl Iterator<Item = &'a Rc<Span>>,
+    selector: &SpanSelector,
+    start: TimePoint,
+    end: TimePoint,
+) -> ConcurrencySteps {
+    // (time, +1 for a start, -1 for an end)
+    let mut changes: Vec<(TimePoint, i64)> = Vec::new();
+    let mut open_at_start: i64 = 0;
+    for span in spans {
+        collect_changes(span, selector, start, end, &mut open_at_start, &mut changes);
+    }
+    // Ends before starts at the same time, so that back-to-back spans don't count as concurrent
+    changes.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+    let mut result = ConcurrencySteps {
+        steps: vec![(start, open_at_start as usize)],
+        max: open_at_start as usize,
+    };
+    let mut open = open_at_start;
+    for (time, change) in changes {
+        open += change;
+        let open = open.max(0) as usize;
+        match result.steps.last_mut() {
+            Some(last) if last.0 == time => last.1 = open,
+            _ => result.steps.push((time, open)),
+        }
+        result.max = result.max.max(open);
+    }
+    result
+}
+
+fn collect_changes(
+    span: &Rc<Span>,
+    selector: &SpanSelector,
+    start: TimePoint,
+    end: TimePoint,
+    open_at_start: &mut i64,
+    changes: &mut Vec<(TimePoint, i64)>,
+) {
+    if span.start_time < end && span.end_time > start && selector.matches(span) {
+        if span.start_time <= start {
+            *open_at_start += 1;
+        } else {
+            changes.push((span.start_time, 1));
+        }
+        if span.end_time < end {
+            changes.push((span.end_time, -1));
+        }
+    }
+    for child in span.children().iter() {
+        collect_changes(child, selector, start, end, open_at_start, changes);
+    }
+}
+
+/// Draws the number of open spans in `start..end` as a step line filling `rect`, scaled to the
+/// maximum in `start..end`. The steps can also cover a longer range.
+pub fn draw_concurrency_strip(
+    ui: &mut Ui,
+    rect: Rect,
+    concurrency: &ConcurrencySteps,
+    start: TimePoint,
+    end: TimePoint,
+    id_salt: &str,
+) {
+    ui.painter().rect_filled(rect, 0.0, theme().lane_background);
+    // The step open at `start` and the ones after it until `end`.
+    let first = concurrency
+        .steps
+        .partition_point(|(time, _)| *time <= start)
+        .saturating_sub(1);
+    let last = concurrency.steps.partition_point(|(time, _)| *time < end);
+    let steps = &concurrency.steps[first..last.max(first)];
+    let max = steps.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    if max == 0 {
+        return;
+    }
+    let to_x = |time: TimePoint| {
+        rect.min.x + ((time.max(start) - start) / (end - start)) as f32 * rect.width()
+    };
+    let to_y = |count: usize| rect.max.y - count as f32 / max as f32 * rect.height();
+
+    let mut points = Vec::with_capacity(steps.len() * 2 + 1);
+    for (i, (time, count)) in steps.iter().enumerate() {
+        let x = to_x(*time);
+        if i > 0 {
+            points.push(Pos2::new(x, to_y(steps[i - 1].1)));
+        }
+        points.push(Pos2::new(x, to_y(*count)));
+    }
+    if let Some((_, count)) = steps.last() {
+        points.push(Pos2::new(rect.max.x, to_y(*count)));
+    }
+    ui.painter().add(egui::Shape::line(
+        points,
+        Stroke::new(1.5, theme().span_time),
+    ));
+    ui.painter().text(
+        rect.left_top(),
+        Align2::LEFT_TOP,
+        format!("max {max}"),
+        FontId::proportional(10.0),
+        theme().chart_text,
+    );
+
+    let response = ui.interact(rect, ui.id().with(("concurrency", id_salt)), Sense::hover());
+    if let Some(pointer) = response.hover_pos() {
+        let time = start + ((pointer.x - rect.min.x) / rect.width()) as f64 * (end - start);
+        response.on_hover_text_at_pointer(format!("{} open", concurrency.count_at(time)));
+    }
+}
+
+/// Dialog for the settings of the concurrency overlay.
+pub struct EditConcurrencyOverlay {
+    show: bool,
+    settings: ConcurrencyOverlaySettings,
+}
+
+impl Default for EditConcurrencyOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditConcurrencyOverlay {
+    pub fn new() -> EditConcurrencyOverlay {
+        EditConcurrencyOverlay {
+            show: false,
+            settings: ConcurrencyOverlaySettings::default(),
+        }
+    }
+
+    pub fn open(&mut self, settings: ConcurrencyOverlaySettings) {
+        self.settings = settings;
+        self.show = true;
+    }
+
+    /// Returns the new settings when the user clicks "Save".
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        max_width: f32,
+        max_height: f32,
+    ) -> Option<ConcurrencyOverlaySettings> {
+        if !self.show {
+            return None;
+        }
+
+        let mut result = None;
+        Modal::new("edit concurrency overlay".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Concurrency overlay");
+            ui.label("Shows how many spans matching the selector are open at the same time.");
+            ui.separator();
+
+            ui.checkbox(&mut self.settings.enabled, "Show the concurrency overlay");
+            ui.horizontal(|ui| {
+                ui.radio_value(
+                    &mut self.settings.position,
+                    OverlayPosition::AboveLane,
+                    "Above the lane",
+                );
+                ui.radio_value(
+                    &mut self.settings.position,
+                    OverlayPosition::BelowLane,
+                    "Below the lane",
+                );
+            });
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .max_height(max_height - 200.0)
+                .show(ui, |ui| {
+                    EditDisplayModes::draw_edit_span_selector(
+                        &mut self.settings.selector,
+                        ui,
+                        max_width,
+                        "concurrency selector",
+                    );
+                });
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    result = Some(self.settings.clone());
+                    self.show = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.show = false;
+                }
+            });
+        });
+
+        result
+    }
+}
@@ -0,0 +1,350 @@
+//! Correlation between two span names - pairs spans of two names by the value of a linking
+//! attribute and shows how the duration of one relates to the duration of the other (or to the
+//! delay between them). Gives evidence for hypotheses like "slow witness validation causes slow
+//! block production".
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use eframe::egui::{self, Modal, RichText, TextEdit, Vec2};
+
+use crate::analyze_utils::{
+    process_spans_for_analysis, span_search_ui, span_selection_list_ui, ModalFocus,
+};
+use crate::charts::{draw_chart, ChartAxis, ChartSeries};
+use crate::colors::theme;
+use crate::types::{value_to_text, Span, MILLISECONDS_PER_SECOND};
+
+pub const DEFAULT_LINKING_ATTRIBUTE: &str = "height";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CorrelationMetric {
+    /// Duration of the first span against the duration of the second one.
+    Durations,
+    /// Duration of the first span against the time from its end to the start of the second one.
+    DurationAndDelay,
+}
+
+/// Spans of the two names with the same value of the linking attribute.
+#[derive(Debug, Clone)]
+pub struct SpanPair {
+    pub link_value: String,
+    pub first: Rc<Span>,
+    pub second: Rc<Span>,
+}
+
+impl SpanPair {
+    /// (x, y) in seconds.
+    pub fn values(&self, metric: CorrelationMetric) -> (f64, f64) {
+        let first_duration = self.first.end_time - self.first.start_time;
+        let y = match metric {
+            CorrelationMetric::Durations => self.second.end_time - self.second.start_time,
+            CorrelationMetric::DurationAndDelay => self.second.start_time - self.first.end_time,
+        };
+        (first_duration, y)
+    }
+}
+
+/// Pairs spans named `first_name` with spans named `second_name` which have the same value of
+/// `linking_attribute`. With `same_node` only spans on the same node are paired. When there are
+/// multiple spans for one value, the earliest one is used. Pairs are sorted by the start of the
+/// first span.
+pub fn pair_spans(
+    spans: &[Rc<Span>],
+    first_name: &str,
+    second_name: &str,
+    linking_attribute: &str,
+    same_node: bool,
+) -> Vec<SpanPair> {
+    // (link value, node name) -> earliest span
+    let earliest_by_key = |name: &str| {
+        let mut earliest: HashMap<(String, &str), &Rc<Span>> = HashMap::new();
+        for span in spans.iter().filter(|s| s.original_name() == name) {
+            let Some(value) = span.attributes.get(linking_attribute) else {
+                continue;
+            };
+            let node = if same_node {
+                span.node.name.as_str()
+            } else {
+                ""
+            };
+            let entry = earliest.entry((value_to_text(value), node)).or_insert(span);
+            if span.start_time < entry.start_time {
+                *entry = span;
+            }
+        }
+        earliest
+    };
+    let firsts = earliest_by_key(first_name);
+    let seconds = earliest_by_key(second_name);
+
+    let mut pairs: Vec<SpanPair> = firsts
+        .into_iter()
+        .filter_map(|(key, first)| {
+            let second = seconds.get(&key)?;
+            Some(SpanPair {
+                link_value: key.0,
+                first: first.clone(),
+                second: (*second).clone(),
+            })
+        })
+        .collect();
+    pairs.sort_by(|a, b| a.first.start_time.total_cmp(&b.first.start_time));
+    pairs
+}
+
+/// Pearson correlation coefficient of the points, `None` when there are fewer than two points or
+/// one of the values doesn't vary.
+pub fn pearson_correlation(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in points {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+/// Rough description of the strength of the correlation.
+pub fn describe_correlation(r: f64) -> &'static str {
+    match r.abs() {
+        a if a >= 0.7 => "strong",
+        a if a >= 0.4 => "moderate",
+        a if a >= 0.2 => "weak",
+        _ => "none",
+    }
+}
+
+/// Selection of one of the two span names.
+#[derive(Default)]
+struct SpanNameChoice {
+    search_text: String,
+    selected: Option<String>,
+}
+
+pub struct CorrelationModal {
+    pub show: bool,
+    all_spans: Vec<Rc<Span>>,
+    unique_span_names: Vec<String>,
+    first: SpanNameChoice,
+    second: SpanNameChoice,
+    linking_attribute: String,
+    same_node: bool,
+    metric: CorrelationMetric,
+    pairs: Vec<SpanPair>,
+    pub focus: ModalFocus,
+}
+
+impl Default for CorrelationModal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CorrelationModal {
+    pub fn new() -> CorrelationModal {
+        CorrelationModal {
+            show: false,
+            all_spans: Vec::new(),
+            unique_span_names: Vec::new(),
+            first: SpanNameChoice::default(),
+            second: SpanNameChoice::default(),
+            linking_attribute: DEFAULT_LINKING_ATTRIBUTE.to_string(),
+            same_node: true,
+            metric: CorrelationMetric::Durations,
+            pairs: Vec::new(),
+            focus: ModalFocus::default(),
+        }
+    }
+
+    pub fn open(&mut self, spans_for_analysis: &[Rc<Span>]) {
+        let (all_spans, unique_span_names) = process_spans_for_analysis(spans_for_analysis);
+        self.all_spans = all_spans;
+        self.unique_span_names = unique_span_names;
+        self.show = true;
+        self.compute();
+    }
+
+    fn compute(&mut self) {
+        self.pairs = match (&self.first.selected, &self.second.selected) {
+            (Some(first), Some(second)) => pair_spans(
+                &self.all_spans,
+                first,
+                second,
+                self.linking_attribute.trim(),
+                self.same_node,
+            ),
+            _ => Vec::new(),
+        };
+    }
+
+    pub fn show_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        if !self.show {
+            return;
+        }
+
+        let mut compute = false;
+        let mut jump_to_span = None;
+        Modal::new("correlation".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Correlation");
+            ui.label(
+                "Pairs spans of two names by the linking attribute and correlates their timing.",
+            );
+            ui.separator();
+
+            ui.horizontal_top(|ui| {
+                for (choice, label, id_salt) in [
+                    (&mut self.first, "First span (x)", "correlation first span"),
+                    (
+                        &mut self.second,
+                        "Second span (y)",
+                        "correlation second span",
+                    ),
+                ] {
+                    ui.vertical(|ui| {
+                        ui.set_width(220.0);
+                        span_search_ui(ui, &mut choice.search_text, label, "Search", 210.0);
+                        compute |= span_selection_list_ui(
+                            ui,
+                            &self.unique_span_names,
+                            &choice.search_text,
+                            &mut choice.selected,
+                            200.0,
+                            id_salt,
+                        );
+                    });
+                    ui.separator();
+                }
+                ui.vertical(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Linking attribute:");
+                        compute |= ui
+                            .add(
+                                TextEdit::singleline(&mut self.linking_attribute)
+                                    .desired_width(100.0),
+                            )
+                            .lost_focus();
+                    });
+                    compute |= ui
+                        .checkbox(&mut self.same_node, "Only pair spans on the same node")
+                        .changed();
+                    ui.label("Compare the duration of the first span with:");
+                    ui.radio_value(
+                        &mut self.metric,
+                        CorrelationMetric::Durations,
+                        "the duration of the second span",
+                    );
+                    ui.radio_value(
+                        &mut self.metric,
+                        CorrelationMetric::DurationAndDelay,
+                        "the delay from its end to the start of the second span",
+                    );
+                });
+            });
+            ui.separator();
+
+            self.draw_results(ui, max_width - 20.0, max_height - 400.0, &mut jump_to_span);
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.show = false;
+            }
+        });
+
+        if compute {
+            self.compute();
+        }
+        if let Some(span) = jump_to_span {
+            self.focus.jump_to(span);
+            self.show = false;
+        }
+    }
+
+    fn draw_results(
+        &self,
+        ui: &mut egui::Ui,
+        width: f32,
+        height: f32,
+        jump_to_span: &mut Option<Rc<Span>>,
+    ) {
+        let (Some(first), Some(second)) = (&self.first.selected, &self.second.selected) else {
+            ui.label("Choose two span names.");
+            return;
+        };
+        if self.pairs.is_empty() {
+            ui.label(format!(
+                "No {first} and {second} spans with the same {}.",
+                self.linking_attribute
+            ));
+            return;
+        }
+
+        let points: Vec<(f64, f64)> = self
+            .pairs
+            .iter()
+            .map(|pair| {
+                let (x, y) = pair.values(self.metric);
+                (x * MILLISECONDS_PER_SECOND, y * MILLISECONDS_PER_SECOND)
+            })
+            .collect();
+        match pearson_correlation(&points) {
+            Some(r) => ui.label(
+                RichText::new(format!(
+                    "{} pairs, correlation r = {r:.3} ({}), r² = {:.3}",
+                    points.len(),
+                    describe_correlation(r),
+                    r * r
+                ))
+                .strong(),
+            ),
+            None => ui.label(format!(
+                "{} pairs, not enough variation to compute the correlation",
+                points.len()
+            )),
+        };
+
+        let y_label = match self.metric {
+            CorrelationMetric::Durations => format!("{second} duration (ms)"),
+            CorrelationMetric::DurationAndDelay => format!("delay to {second} (ms)"),
+        };
+        let series = [ChartSeries {
+            name: "pairs",
+            color: theme().chart_fill,
+            points: &points,
+            draw_lines: false,
+        }];
+        let clicked = draw_chart(
+            ui,
+            Vec2::new(width, height.max(150.0)),
+            &series,
+            &ChartAxis {
+                label: &format!("{first} duration (ms)"),
+                format: &|ms| format!("{ms:.1}"),
+            },
+            &ChartAxis {
+                label: &y_label,
+                format: &|ms| format!("{ms:.1}"),
+            },
+        );
+        if let Some((_, point_idx)) = clicked {
+            *jump_to_span = Some(self.pairs[point_idx].second.clone());
+        }
+        ui.label(
+            RichText::new("Click on a point to jump to the second span of the pair.")
+                .small()
+                .weak(),
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Export of the links formed by the dependency analysis to JSON, so that they can be joined with
+//! other data (e.g. metrics) outside of traviz.
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use serde::Serialize;
+
+use crate::analyze_dependency::DependencyAnalysisResult;
+use crate::types::{value_to_text, Span, TimePoint};
+
+#[derive(Serialize)]
+struct ExportedAnalysis {
+    description: String,
+    source_span_name: String,
+    target_span_name: String,
+    links: Vec<ExportedLink>,
+}
+
+#[derive(Serialize)]
+struct ExportedLink {
+    /// Node on which the link was formed.
+    node: String,
+    delay_seconds: f64,
+    source_spans: Vec<ExportedSpan>,
+    target_spans: Vec<ExportedSpan>,
+}
+
+#[derive(Serialize)]
+struct ExportedSpan {
+    span_id: String,
+    trace_id: String,
+    name: String,
+    node: String,
+    start_time: TimePoint,
+    end_time: TimePoint,
+    attributes: BTreeMap<String, String>,
+}
+
+fn exported_span(span: &Rc<Span>) -> ExportedSpan {
+    ExportedSpan {
+        span_id: span.span_id.to_hex(),
+        trace_id: span.trace_id.to_hex(),
+        name: span.original_name.clone(),
+        node: span.node.name.clone(),
+        start_time: span.start_time,
+        end_time: span.end_time,
+        attributes: span
+            .attributes
+            .iter()
+            .map(|(name, value)| (name.clone(), value_to_text(value)))
+            .collect(),
+    }
+}
+
+/// Creates a JSON document with all links of the analysis, ordered by node and the start of the
+/// target span. Times are in seconds since the unix epoch.
+pub fn dependency_links_to_json(result: &DependencyAnalysisResult) -> String {
+    let mut node_names: Vec<&String> = result.per_node_results.keys().collect();
+    node_names.sort();
+
+    let mut links = Vec::new();
+    for node_name in node_names {
+        let mut node_links: Vec<ExportedLink> = result.per_node_results[node_name]
+            .links
+            .iter()
+            .map(|link| ExportedLink {
+                node: node_name.clone(),
+                delay_seconds: link.delay_seconds,
+                source_spans: link.source_spans.iter().map(exported_span).collect(),
+                target_spans: link.target_spans.iter().map(exported_span).collect(),
+            })
+            .collect();
+        let target_start =
+            |link: &ExportedLink| link.target_spans.first().map_or(0.0, |s| s.start_time);
+        node_links.sort_by(|a, b| target_start(a).total_cmp(&target_start(b)));
+        links.extend(node_links);
+    }
+
+    let exported = ExportedAnalysis {
+        description: result.description(),
+        source_span_name: result.source_span_name.clone(),
+        target_span_name: result.target_span_name.clone(),
+        links,
+    };
+    serde_json::to_string_pretty(&exported).expect("Serializing to string can't fail")
+}
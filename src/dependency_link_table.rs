@@ -0,0 +1,232 @@
+//! Table of the links formed by the dependency analysis on one node. The aggregate statistics show
+//! that some delays are bad, the table lists the links behind them. The rows can be sorted by time
+//! or delay, and each link can be inspected or shown in the timeline.
+
+use std::collections::{BTreeSet, HashMap};
+
+use eframe::egui::{Button, Grid, Label, RichText, ScrollArea, Sense, TextStyle, Ui};
+
+use crate::analyze_dependency::{parse_linking_patterns, DependencyLink};
+use crate::colors::theme;
+use crate::types::{format_ms_value, value_to_text, TimeFormat, TimePoint};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkSortColumn {
+    #[default]
+    Time,
+    Delay,
+}
+
+/// One row of the table, describes the link at `index` in the node's links.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkRow {
+    pub index: usize,
+    /// Start of the earliest source span.
+    pub time: TimePoint,
+    pub delay_seconds: f64,
+    /// Values of the linking attributes on the first target span, e.g. "height=101".
+    pub linking_values: String,
+    /// Distinct values of the group by attribute in the link.
+    pub group_keys: String,
+}
+
+/// What was clicked in the table, refers to the link at the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkTableAction {
+    ShowDetails(usize),
+    Jump(usize),
+}
+
+/// The table scrolls when the node has more links.
+const MAX_VISIBLE_ROWS: f32 = 20.0;
+
+#[derive(Debug, Default)]
+pub struct DependencyLinkTable {
+    sort_column: LinkSortColumn,
+    descending: bool,
+    /// Rows of the drawn nodes in the chosen order, cleared when the order or the result changes.
+    rows: HashMap<String, Vec<LinkRow>>,
+}
+
+impl DependencyLinkTable {
+    pub fn new() -> DependencyLinkTable {
+        DependencyLinkTable::default()
+    }
+
+    /// Sorts by the column, clicking the same column again reverses the order.
+    pub fn sort_by(&mut self, column: LinkSortColumn) {
+        if self.sort_column == column {
+            self.descending = !self.descending;
+        } else {
+            self.sort_column = column;
+            self.descending = false;
+        }
+        self.rows.clear();
+    }
+
+    /// Has to be called when the analysis result changes, the rows are computed again.
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    /// Rows describing the links, in the chosen order.
+    pub fn rows(
+        &self,
+        links: &[DependencyLink],
+        linking_attribute: &str,
+        group_by_attribute: &str,
+    ) -> Vec<LinkRow> {
+        let linking_patterns = parse_linking_patterns(linking_attribute);
+        let mut rows: Vec<LinkRow> = links
+            .iter()
+            .enumerate()
+            .map(|(index, link)| {
+                let time = link
+                    .source_spans
+                    .iter()
+                    .map(|span| span.start_time)
+                    .fold(f64::INFINITY, f64::min);
+                let linking_values = match link.target_spans.first() {
+                    Some(target) => linking_patterns
+                        .iter()
+                        .filter_map(|pattern| {
+                            let value = target.attributes.get(&pattern.attribute)?;
+                            Some(format!("{}={}", pattern.attribute, value_to_text(value)))
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    None => String::new(),
+                };
+                let group_keys = if group_by_attribute.is_empty() {
+                    String::new()
+                } else {
+                    link.source_spans
+                        .iter()
+                        .chain(&link.target_spans)
+                        .filter_map(|span| span.attributes.get(group_by_attribute))
+                        .map(value_to_text)
+                        .collect::<BTreeSet<_>>()
+                        .into_iter()
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                LinkRow {
+                    index,
+                    time,
+                    delay_seconds: link.delay_seconds,
+                    linking_values,
+                    group_keys,
+                }
+            })
+            .collect();
+        match self.sort_column {
+            LinkSortColumn::Time => rows.sort_by(|a, b| a.time.total_cmp(&b.time)),
+            LinkSortColumn::Delay => {
+                rows.sort_by(|a, b| a.delay_seconds.total_cmp(&b.delay_seconds))
+            }
+        }
+        if self.descending {
+            rows.reverse();
+        }
+        rows
+    }
+
+    /// Draws the table of the node's links, returns what was clicked.
+    pub fn draw(
+        &mut self,
+        ui: &mut Ui,
+        node_name: &str,
+        links: &[DependencyLink],
+        linking_attribute: &str,
+        group_by_attribute: &str,
+        time_format: &TimeFormat,
+    ) -> Option<LinkTableAction> {
+        if !self.rows.contains_key(node_name) {
+            let rows = self.rows(links, linking_attribute, group_by_attribute);
+            self.rows.insert(node_name.to_string(), rows);
+        }
+        let mut action = None;
+        let mut sort_by = None;
+        let rows = &self.rows[node_name];
+        let row_height = ui.text_style_height(&TextStyle::Body) + 4.0;
+        // The header is the first row, so that it's in the same grid as the links.
+        ScrollArea::vertical()
+            .id_salt(("dependency link rows", node_name))
+            .max_height(row_height * MAX_VISIBLE_ROWS)
+            .auto_shrink([false, true])
+            .show_rows(ui, row_height, rows.len() + 1, |ui, row_range| {
+                Grid::new(("dependency link table", node_name))
+                    .num_columns(5)
+                    .striped(true)
+                    .start_row(row_range.start)
+                    .show(ui, |ui| {
+                        if row_range.start == 0 {
+                            sort_by = self.draw_header(ui);
+                        }
+                        let link_rows =
+                            row_range.start.saturating_sub(1)..row_range.end.saturating_sub(1);
+                        for row in &rows[link_rows] {
+                            ui.monospace(time_format.display_string(row.time));
+                            if ui
+                                .add(
+                                    Label::new(
+                                        RichText::new(format_ms_value(row.delay_seconds))
+                                            .monospace()
+                                            .color(theme().link),
+                                    )
+                                    .sense(Sense::click()),
+                                )
+                                .on_hover_text("Show the link details")
+                                .clicked()
+                            {
+                                action = Some(LinkTableAction::ShowDetails(row.index));
+                            }
+                            ui.label(&row.linking_values);
+                            ui.label(&row.group_keys);
+                            if ui
+                                .add(Button::new("Jump").small())
+                                .on_hover_text("Show the target span in the timeline")
+                                .clicked()
+                            {
+                                action = Some(LinkTableAction::Jump(row.index));
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        if let Some(column) = sort_by {
+            self.sort_by(column);
+        }
+        action
+    }
+
+    /// Draws the column titles, returns the column which was clicked to sort by it.
+    fn draw_header(&self, ui: &mut Ui) -> Option<LinkSortColumn> {
+        let mut sort_by = None;
+        for (column, title) in [
+            (LinkSortColumn::Time, "Time"),
+            (LinkSortColumn::Delay, "Delay (ms)"),
+        ] {
+            let arrow = match (self.sort_column == column, self.descending) {
+                (true, false) => " ⏶",
+                (true, true) => " ⏷",
+                (false, _) => "",
+            };
+            if ui
+                .add(
+                    Label::new(RichText::new(format!("{title}{arrow}")).strong())
+                        .sense(Sense::click()),
+                )
+                .on_hover_text("Click to sort")
+                .clicked()
+            {
+                sort_by = Some(column);
+            }
+        }
+        ui.strong("Linking attributes");
+        ui.strong("Group keys");
+        ui.label("");
+        ui.end_row();
+        sort_by
+    }
+}
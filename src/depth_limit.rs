@@ -0,0 +1,51 @@
+//! Limit on the depth of the displayed span tree. Deep traces are mostly investigated in the top
+//! few levels, the children below the limit are hidden unless they're expanded for a single span.
+//! Independent of the collapse state toggled with the middle click.
+
+use std::collections::HashSet;
+
+use crate::span_id::SpanId;
+
+pub const DEFAULT_MAX_DEPTH: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct DepthLimit {
+    pub enabled: bool,
+    /// Number of displayed levels, top level spans are the first level.
+    pub max_depth: usize,
+    /// Spans whose children are displayed even though they're below the limit.
+    expanded: HashSet<SpanId>,
+}
+
+impl Default for DepthLimit {
+    fn default() -> Self {
+        DepthLimit {
+            enabled: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            expanded: HashSet::new(),
+        }
+    }
+}
+
+impl DepthLimit {
+    /// Whether the children of a span displayed at `depth` (0 for top level spans) are hidden.
+    pub fn cuts_children(&self, span_id: SpanId, depth: usize) -> bool {
+        self.enabled && depth + 1 >= self.max_depth.max(1) && !self.expanded.contains(&span_id)
+    }
+
+    pub fn is_expanded(&self, span_id: SpanId) -> bool {
+        self.expanded.contains(&span_id)
+    }
+
+    /// Shows or hides the children of one span below the limit.
+    pub fn toggle_expanded(&mut self, span_id: SpanId) {
+        if !self.expanded.remove(&span_id) {
+            self.expanded.insert(span_id);
+        }
+    }
+
+    /// Forgets the expanded spans, e.g. when another trace is loaded.
+    pub fn clear_expanded(&mut self) {
+        self.expanded.clear();
+    }
+}
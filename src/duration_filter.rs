@@ -0,0 +1,99 @@
+//! Quick filter which hides or dims spans shorter than a threshold. Zoomed out, huge numbers of
+//! very short spans dominate the layout cost and clutter the view, while they can't be seen anyway.
+
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::span_id::SpanId;
+use crate::types::{Span, SpanData, TimePoint, MILLISECONDS_PER_SECOND};
+
+/// Largest threshold which can be chosen with the slider, in milliseconds.
+pub const MAX_MIN_DURATION_MS: f64 = 1000.0;
+
+/// What happens to spans shorter than the threshold.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShortSpans {
+    /// The spans are left out of the layout, together with their children.
+    #[default]
+    Hide,
+    /// The spans are laid out as usual, but drawn faded.
+    Dim,
+}
+
+impl ShortSpans {
+    pub fn all() -> [ShortSpans; 2] {
+        [ShortSpans::Hide, ShortSpans::Dim]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShortSpans::Hide => "Hide",
+            ShortSpans::Dim => "Dim",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DurationFilter {
+    /// Spans shorter than this many milliseconds are hidden or dimmed, 0 turns the filter off.
+    pub min_duration_ms: f64,
+    pub short_spans: ShortSpans,
+}
+
+impl DurationFilter {
+    pub fn is_active(&self) -> bool {
+        self.min_duration_ms > 0.0
+    }
+
+    pub fn is_short(&self, span: &SpanData) -> bool {
+        (span.end_time - span.start_time) * MILLISECONDS_PER_SECOND < self.min_duration_ms
+    }
+
+    /// Spans shorter than this many seconds are left out of the layout, 0 when nothing is hidden.
+    pub fn hidden_below(&self) -> TimePoint {
+        match self.short_spans {
+            ShortSpans::Hide => self.min_duration_ms / MILLISECONDS_PER_SECOND,
+            ShortSpans::Dim => 0.0,
+        }
+    }
+
+    pub fn hides(&self, span: &SpanData) -> bool {
+        self.short_spans == ShortSpans::Hide && self.is_short(span)
+    }
+
+    pub fn dims(&self, span: &SpanData) -> bool {
+        self.short_spans == ShortSpans::Dim && self.is_short(span)
+    }
+}
+
+/// Ids of the highlighted spans in the trees of `spans` and of all their ancestors. Highlighted
+/// spans are always shown, so the short spans above them can't be left out of the layout. Lazy
+/// children aren't transformed.
+pub fn highlighted_span_ancestors(
+    spans: &[Rc<Span>],
+    highlighted_span_ids: &HashSet<SpanId>,
+) -> HashSet<SpanId> {
+    fn collect(
+        span: &Rc<Span>,
+        highlighted_span_ids: &HashSet<SpanId>,
+        result: &mut HashSet<SpanId>,
+    ) -> bool {
+        let mut has_highlighted = highlighted_span_ids.contains(&span.span_id);
+        for child in span.transformed_children().iter() {
+            has_highlighted |= collect(child, highlighted_span_ids, result);
+        }
+        if has_highlighted {
+            result.insert(span.span_id);
+        }
+        has_highlighted
+    }
+
+    let mut result = HashSet::new();
+    if highlighted_span_ids.is_empty() {
+        return result;
+    }
+    for span in spans {
+        collect(span, highlighted_span_ids, &mut result);
+    }
+    result
+}
@@ -0,0 +1,118 @@
+use eframe::egui::{self, ComboBox, Grid, Modal, ScrollArea, TextEdit};
+
+use crate::macros::{bindable_keys, KeyMacro};
+
+/// Dialog for renaming, rebinding and deleting keyboard macros.
+pub struct EditMacros {
+    show: bool,
+    macros: Vec<KeyMacro>,
+}
+
+impl Default for EditMacros {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditMacros {
+    pub fn new() -> EditMacros {
+        EditMacros {
+            show: false,
+            macros: Vec::new(),
+        }
+    }
+
+    pub fn open(&mut self, macros: Vec<KeyMacro>) {
+        self.macros = macros;
+        self.show = true;
+    }
+
+    /// Returns the new list of macros when the user clicks "Save".
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        max_width: f32,
+        max_height: f32,
+    ) -> Option<Vec<KeyMacro>> {
+        if !self.show {
+            return None;
+        }
+
+        let mut result = None;
+        Modal::new("edit macros".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Macros");
+            ui.label(
+                "Record a macro with \"Record macro\" on the top bar, then bind it to a key here.",
+            );
+            ui.separator();
+
+            if self.macros.is_empty() {
+                ui.label("No macros recorded yet.");
+            }
+
+            let mut macro_to_delete = None;
+            ScrollArea::vertical()
+                .max_height(max_height - 150.0)
+                .show(ui, |ui| {
+                    for (macro_idx, key_macro) in self.macros.iter_mut().enumerate() {
+                        ui.push_id(macro_idx, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Name:");
+                                ui.add(TextEdit::singleline(&mut key_macro.name));
+                                ui.label("Key:");
+                                ComboBox::from_id_salt("macro key")
+                                    .selected_text(key_macro.key.as_deref().unwrap_or("None"))
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(&mut key_macro.key, None, "None");
+                                        for key in bindable_keys() {
+                                            ui.selectable_value(
+                                                &mut key_macro.key,
+                                                Some(key.name().to_string()),
+                                                key.name(),
+                                            );
+                                        }
+                                    });
+                                if ui.button("Delete macro").clicked() {
+                                    macro_to_delete = Some(macro_idx);
+                                }
+                            });
+
+                            let mut action_to_delete = None;
+                            Grid::new("macro actions").striped(true).show(ui, |ui| {
+                                for (action_idx, action) in key_macro.actions.iter().enumerate() {
+                                    ui.label(format!("{}.", action_idx + 1));
+                                    ui.label(action.describe());
+                                    if ui.small_button("Remove").clicked() {
+                                        action_to_delete = Some(action_idx);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                            if let Some(action_idx) = action_to_delete {
+                                key_macro.actions.remove(action_idx);
+                            }
+                        });
+                        ui.separator();
+                    }
+                });
+            if let Some(macro_idx) = macro_to_delete {
+                self.macros.remove(macro_idx);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    result = Some(self.macros.clone());
+                    self.show = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.show = false;
+                }
+            });
+        });
+
+        result
+    }
+}
@@ -1,12 +1,22 @@
-use eframe::egui::{self, Button, ComboBox, Modal, ScrollArea, Ui, Vec2, Widget};
+use std::rc::Rc;
 
-use crate::colors;
+use eframe::egui::{
+    self, Button, CollapsingHeader, ComboBox, DragValue, Grid, Modal, RichText, ScrollArea,
+    TextEdit, Ui, Vec2, Widget,
+};
+
+use crate::colors::theme;
+use crate::reorder::{draw_reorderable_list, duplicate_name, move_entry};
 use crate::structured_modes::{
-    MatchCondition, MatchOperator, SpanDecision, SpanRule, SpanSelector, StructuredMode,
+    preview_span_rules, GroupingRule, LaneLayout, MatchCondition, MatchOperator, ModePreview,
+    RenameRule, SpanDecision, SpanRule, SpanSelector, StructuredMode, SyntheticSpanRule,
+    SyntheticSpanSource, ATTRIBUTE_NAME_PLACEHOLDER,
 };
-use crate::types::DisplayLength;
+use crate::types::{value_to_text, DisplayLength, Span, MILLISECONDS_PER_SECOND};
+use crate::undo::UndoStack;
 
-pub const HIGHLIGHT_COLOR: egui::Color32 = colors::DARK_BLUE;
+/// Number of matched spans shown for each rule in the preview.
+const PREVIEW_SAMPLES: usize = 5;
 
 pub struct EditDisplayModes {
     state: EditDisplayModesState,
@@ -14,6 +24,8 @@ pub struct EditDisplayModes {
     editing_or_adding_rule: AddingOrEditing,
 
     all_modes: Vec<StructuredMode>,
+    /// Spans which aren't displayed in any mode, edited together with the modes.
+    muted_spans: Vec<SpanSelector>,
     selected_mode_idx: usize,
     current_mode: StructuredMode,
     selected_span_rule_idx: usize,
@@ -21,6 +33,16 @@ pub struct EditDisplayModes {
     max_width: f32,
     not_editable_message: String,
     max_scrollarea_size: Vec2,
+
+    /// All spans of the loaded trace (including children), used to preview the rules.
+    trace_spans: Vec<Rc<Span>>,
+    /// Preview of the rules of the edited mode, computed when the user asks for it. Stored together
+    /// with the rules serialized to JSON, to notice when they change.
+    mode_preview: Option<(String, ModePreview)>,
+    /// Number of spans matched by the selector of the edited rule, computed when the user asks for it.
+    rule_preview_count: Option<usize>,
+    /// Snapshots of `all_modes` from before each change.
+    undo: UndoStack<Vec<StructuredMode>>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -46,6 +68,7 @@ impl EditDisplayModes {
             editing_or_adding_mode: AddingOrEditing::Adding,
             editing_or_adding_rule: AddingOrEditing::Adding,
             all_modes: Vec::new(),
+            muted_spans: Vec::new(),
             selected_mode_idx: 0,
             current_mode: Self::new_mode(),
             selected_span_rule_idx: 0,
@@ -53,20 +76,37 @@ impl EditDisplayModes {
             max_width: 800.0,
             not_editable_message: String::new(),
             max_scrollarea_size: Vec2::new(800.0, 400.0),
+            trace_spans: Vec::new(),
+            mode_preview: None,
+            rule_preview_count: None,
+            undo: UndoStack::new(),
         }
     }
 
-    pub fn open(&mut self, modes: Vec<StructuredMode>) {
+    /// `trace_spans` should contain all spans of the loaded trace, including children. They're used
+    /// to preview what the rules match.
+    pub fn open(
+        &mut self,
+        modes: Vec<StructuredMode>,
+        muted_spans: Vec<SpanSelector>,
+        trace_spans: Vec<Rc<Span>>,
+    ) {
         self.all_modes = modes;
+        self.muted_spans = muted_spans;
+        self.trace_spans = trace_spans;
+        self.mode_preview = None;
+        self.rule_preview_count = None;
+        self.undo.clear();
         self.state = EditDisplayModesState::Opened;
     }
 
+    /// Returns the modes and the muted spans when the user clicks "Save".
     pub fn draw(
         &mut self,
         ctx: &egui::Context,
         max_width: f32,
         max_height: f32,
-    ) -> Option<Vec<StructuredMode>> {
+    ) -> Option<(Vec<StructuredMode>, Vec<SpanSelector>)> {
         if self.state == EditDisplayModesState::Closed {
             return None;
         }
@@ -96,6 +136,12 @@ impl EditDisplayModes {
         StructuredMode {
             name: "New Mode".to_string(),
             span_rules: vec![Self::new_span_rule()],
+            lane_layout: LaneLayout::PerNode,
+            show_event_markers: false,
+            sub_lane_attribute: String::new(),
+            grouping_rules: Vec::new(),
+            rename_rules: Vec::new(),
+            synthetic_span_rules: Vec::new(),
             is_builtin: false,
         }
     }
@@ -122,13 +168,26 @@ impl EditDisplayModes {
         }
     }
 
+    fn new_grouping_rule() -> GroupingRule {
+        GroupingRule {
+            name: "Group MySpan".to_string(),
+            selector: SpanSelector::new_equal_name("MySpan"),
+            max_gap: 0.01,
+            group_by_attribute: String::new(),
+        }
+    }
+
     fn draw_short_separator(&self, ui: &mut Ui) {
         ui.set_max_width(10.0);
         ui.separator();
         ui.set_max_width(self.max_width);
     }
 
-    fn draw_opened(&mut self, ui: &mut Ui, _ctx: &egui::Context) -> Option<Vec<StructuredMode>> {
+    fn draw_opened(
+        &mut self,
+        ui: &mut Ui,
+        _ctx: &egui::Context,
+    ) -> Option<(Vec<StructuredMode>, Vec<SpanSelector>)> {
         ui.label("Edit Display Modes");
 
         self.draw_short_separator(ui);
@@ -138,21 +197,22 @@ impl EditDisplayModes {
             ScrollArea::vertical()
                 .id_salt("display modes")
                 .show(ui, |ui| {
-                    for (index, mode) in self.all_modes.iter().enumerate() {
-                        let mode_name = if mode.is_builtin {
-                            format!("{} (builtin)", mode.name)
-                        } else {
-                            mode.name.clone()
-                        };
-
-                        let button = if self.selected_mode_idx == index {
-                            Button::new(mode_name).fill(HIGHLIGHT_COLOR)
-                        } else {
-                            Button::new(mode_name)
-                        };
-                        if button.ui(ui).clicked() {
-                            self.selected_mode_idx = index;
-                        }
+                    if let Some((from, to)) = draw_reorderable_list(
+                        ui,
+                        "display modes",
+                        &self.all_modes,
+                        &mut self.selected_mode_idx,
+                        |mode| mode.is_builtin,
+                        |mode| {
+                            if mode.is_builtin {
+                                format!("{} (builtin)", mode.name)
+                            } else {
+                                mode.name.clone()
+                            }
+                        },
+                    ) {
+                        self.undo.push(self.all_modes.clone());
+                        move_entry(&mut self.all_modes, from, to, &mut self.selected_mode_idx);
                     }
                 });
         });
@@ -173,7 +233,7 @@ impl EditDisplayModes {
                     if mode.is_builtin {
                         self.not_editable_message =
                         "This mode is not editable! Builtin modes that are provided in traviz cannot be changed from the UI. \
-                        You can clone this mode to create your own custom one and then edit the custom mode".to_string();
+                        You can duplicate this mode to create your own custom one and then edit the custom mode".to_string();
                         self.state = EditDisplayModesState::NotEditableError;
                     } else {
                         self.current_mode = mode.clone();
@@ -183,10 +243,12 @@ impl EditDisplayModes {
                     }
                 }
             }
-            if ui.button("Clone Mode").clicked() {
+            if ui.button("Duplicate Mode").clicked() {
                 let mut new_mode = self.all_modes[self.selected_mode_idx].clone();
-                new_mode.name = format!("{} Clone", new_mode.name);
+                new_mode.name =
+                    duplicate_name(&new_mode.name, self.all_modes.iter().map(|m| m.name.as_str()));
                 new_mode.is_builtin = false;
+                self.undo.push(self.all_modes.clone());
                 self.all_modes.push(new_mode);
                 self.selected_mode_idx = self.all_modes.len() - 1;
             }
@@ -200,15 +262,26 @@ impl EditDisplayModes {
                     }
                 }
             }
+            ui.separator();
+            if self.undo.draw_buttons(ui, &mut self.all_modes) {
+                self.selected_mode_idx =
+                    self.selected_mode_idx.min(self.all_modes.len().saturating_sub(1));
+            }
         });
 
+        self.draw_short_separator(ui);
+        self.draw_muted_spans(ui);
+
         let mut result = None;
 
         self.draw_short_separator(ui);
         ui.horizontal(|ui| {
             if ui.button("Save").clicked() {
                 self.state = EditDisplayModesState::Closed;
-                result = Some(std::mem::take(&mut self.all_modes));
+                result = Some((
+                    std::mem::take(&mut self.all_modes),
+                    std::mem::take(&mut self.muted_spans),
+                ));
             }
             if ui.button("Cancel").clicked() {
                 self.state = EditDisplayModesState::Closed;
@@ -218,6 +291,42 @@ impl EditDisplayModes {
         result
     }
 
+    fn draw_muted_spans(&mut self, ui: &mut Ui) {
+        ui.label("Muted spans").on_hover_text(
+            "Spans matching one of these selectors aren't displayed in any mode, \
+             their children are hidden with them",
+        );
+        let mut to_remove = None;
+        ui.allocate_ui(Vec2::new(self.max_width, 200.0), |ui| {
+            ScrollArea::vertical()
+                .id_salt("muted spans")
+                .show(ui, |ui| {
+                    for (i, selector) in self.muted_spans.iter_mut().enumerate() {
+                        CollapsingHeader::new(muted_spans_label(selector))
+                            .id_salt(("muted spans", i))
+                            .show(ui, |ui| {
+                                Self::draw_edit_span_selector(
+                                    selector,
+                                    ui,
+                                    self.max_width,
+                                    &format!("muted spans {i}"),
+                                );
+                                if ui.button("Unmute").clicked() {
+                                    to_remove = Some(i);
+                                }
+                            });
+                    }
+                });
+        });
+        if let Some(i) = to_remove {
+            self.muted_spans.remove(i);
+        }
+        if ui.button("Mute Spans").clicked() {
+            self.muted_spans
+                .push(SpanSelector::new_equal_name("MySpan"));
+        }
+    }
+
     fn draw_delete_confirmation(&mut self, ui: &mut Ui, _ctx: &egui::Context) {
         ui.label("Are you sure you want to delete this mode?");
         self.draw_short_separator(ui);
@@ -228,6 +337,7 @@ impl EditDisplayModes {
 
         self.draw_short_separator(ui);
         if ui.button("Yes, Delete").clicked() {
+            self.undo.push(self.all_modes.clone());
             self.all_modes.remove(self.selected_mode_idx);
             self.selected_mode_idx = 0;
             self.state = EditDisplayModesState::Opened;
@@ -252,6 +362,34 @@ impl EditDisplayModes {
             ui.label("Mode Name:");
             ui.text_edit_singleline(&mut self.current_mode.name);
         });
+        ui.horizontal(|ui| {
+            ui.label("Lanes:");
+            ComboBox::from_id_salt("mode lane layout")
+                .selected_text(self.current_mode.lane_layout.display_name())
+                .show_ui(ui, |ui| {
+                    for layout in LaneLayout::all() {
+                        ui.selectable_value(
+                            &mut self.current_mode.lane_layout,
+                            layout,
+                            layout.display_name(),
+                        );
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Split lanes by attribute:");
+            ui.add(
+                TextEdit::singleline(&mut self.current_mode.sub_lane_attribute)
+                    .hint_text("e.g. shard_id"),
+            )
+            .on_hover_text(
+                "Each value of the attribute gets its own sub-lane, leave empty to not split",
+            );
+        });
+        ui.checkbox(
+            &mut self.current_mode.show_event_markers,
+            "Show event markers inside spans",
+        );
         self.draw_short_separator(ui);
         ui.horizontal(|ui| {
             ui.vertical(|ui| {
@@ -260,7 +398,7 @@ impl EditDisplayModes {
                     ScrollArea::vertical().id_salt("span rules").show(ui, |ui| {
                         for (index, rule) in self.current_mode.span_rules.iter().enumerate() {
                             let button = if self.selected_span_rule_idx == index {
-                                Button::new(rule.name.to_string()).fill(HIGHLIGHT_COLOR)
+                                Button::new(rule.name.to_string()).fill(theme().selected_item)
                             } else {
                                 Button::new(rule.name.to_string())
                             };
@@ -280,6 +418,7 @@ impl EditDisplayModes {
         ui.horizontal(|ui| {
             if ui.button("New rule").clicked() {
                 self.current_span_rule = Self::new_span_rule();
+                self.rule_preview_count = None;
                 self.state = EditDisplayModesState::EditingSpanRule;
                 self.editing_or_adding_rule = AddingOrEditing::Adding;
             };
@@ -290,6 +429,7 @@ impl EditDisplayModes {
                     .get(self.selected_span_rule_idx)
                 {
                     self.current_span_rule = span_rule.clone();
+                    self.rule_preview_count = None;
                     self.state = EditDisplayModesState::EditingSpanRule;
                     self.editing_or_adding_rule = AddingOrEditing::Editing;
                 }
@@ -333,8 +473,17 @@ impl EditDisplayModes {
             }
         });
         self.draw_short_separator(ui);
+        self.draw_grouping_rules(ui);
+        self.draw_short_separator(ui);
+        self.draw_rename_rules(ui);
+        self.draw_short_separator(ui);
+        self.draw_synthetic_span_rules(ui);
+        self.draw_short_separator(ui);
+        self.draw_mode_preview(ui);
+        self.draw_short_separator(ui);
         ui.horizontal(|ui| {
             if ui.button("Ok").clicked() {
+                self.undo.push(self.all_modes.clone());
                 match self.editing_or_adding_mode {
                     AddingOrEditing::Adding => {
                         self.all_modes.push(self.current_mode.clone());
@@ -354,6 +503,266 @@ impl EditDisplayModes {
         });
     }
 
+    fn draw_grouping_rules(&mut self, ui: &mut Ui) {
+        ui.label("Grouping rules").on_hover_text(
+            "Displayed spans matching a rule are merged into one span with active segments. \
+             Spans further apart than the max gap start a new group.",
+        );
+        let mut to_remove = None;
+        for (i, rule) in self.current_mode.grouping_rules.iter_mut().enumerate() {
+            CollapsingHeader::new(&rule.name)
+                .id_salt(("grouping rule", i))
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Rule Name:");
+                        ui.text_edit_singleline(&mut rule.name);
+                    });
+                    Self::draw_edit_span_selector(
+                        &mut rule.selector,
+                        ui,
+                        self.max_width,
+                        &format!("grouping rule {i}"),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("Max gap:");
+                        let mut max_gap_ms = rule.max_gap * MILLISECONDS_PER_SECOND;
+                        if ui
+                            .add(
+                                DragValue::new(&mut max_gap_ms)
+                                    .range(0.0..=f64::MAX)
+                                    .suffix(" ms"),
+                            )
+                            .changed()
+                        {
+                            rule.max_gap = max_gap_ms / MILLISECONDS_PER_SECOND;
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Group by attribute:");
+                        ui.add(
+                            TextEdit::singleline(&mut rule.group_by_attribute)
+                                .hint_text("e.g. height"),
+                        )
+                        .on_hover_text(
+                            "Only spans with the same value of the attribute are grouped, \
+                             leave empty to ignore the attributes",
+                        );
+                    });
+                    if ui.button("Delete grouping rule").clicked() {
+                        to_remove = Some(i);
+                    }
+                });
+        }
+        if let Some(i) = to_remove {
+            self.current_mode.grouping_rules.remove(i);
+        }
+        if ui.button("New grouping rule").clicked() {
+            self.current_mode
+                .grouping_rules
+                .push(Self::new_grouping_rule());
+        }
+    }
+
+    fn draw_rename_rules(&mut self, ui: &mut Ui) {
+        ui.label("Rename rules").on_hover_text(
+            "Displayed spans matching a rule get a new label. {name} is the label given by the \
+             span rule, {attribute} is the value of a span attribute.",
+        );
+        let mut to_remove = None;
+        for (i, rule) in self.current_mode.rename_rules.iter_mut().enumerate() {
+            let header = if rule.label.is_empty() {
+                "Rename rule".to_string()
+            } else {
+                format!("Rename to \"{}\"", rule.label)
+            };
+            CollapsingHeader::new(header)
+                .id_salt(("rename rule", i))
+                .show(ui, |ui| {
+                    Self::draw_edit_span_selector(
+                        &mut rule.selector,
+                        ui,
+                        self.max_width,
+                        &format!("rename rule {i}"),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("Label:");
+                        ui.add(
+                            TextEdit::singleline(&mut rule.label)
+                                .hint_text("e.g. PECF or {name} {shard_id}"),
+                        );
+                    });
+                    if ui.button("Delete rename rule").clicked() {
+                        to_remove = Some(i);
+                    }
+                });
+        }
+        if let Some(i) = to_remove {
+            self.current_mode.rename_rules.remove(i);
+        }
+        if ui.button("New rename rule").clicked() {
+            self.current_mode.rename_rules.push(RenameRule {
+                selector: SpanSelector::new_equal_name("MySpan"),
+                label: "{name}".to_string(),
+            });
+        }
+    }
+
+    fn draw_synthetic_span_rules(&mut self, ui: &mut Ui) {
+        ui.label("Synthetic spans").on_hover_text(
+            "Spans matching a rule get child spans made from a pair of events (e.g. phase_start \
+             and phase_end) or from two attributes with unix timestamps in nanoseconds.",
+        );
+        let mut to_remove = None;
+        for (i, rule) in self
+            .current_mode
+            .synthetic_span_rules
+            .iter_mut()
+            .enumerate()
+        {
+            CollapsingHeader::new(&rule.name)
+                .id_salt(("synthetic span rule", i))
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Span name:");
+                        ui.text_edit_singleline(&mut rule.name);
+                    });
+                    ui.label("Created under spans matching:");
+                    Self::draw_edit_span_selector(
+                        &mut rule.selector,
+                        ui,
+                        self.max_width,
+                        &format!("synthetic span rule {i}"),
+                    );
+                    ComboBox::from_id_salt(("synthetic span source", i))
+                        .selected_text(rule.source.display_name())
+                        .show_ui(ui, |ui| {
+                            for source in [
+                                SyntheticSpanSource::Events {
+                                    start_event: String::new(),
+                                    end_event: String::new(),
+                                },
+                                SyntheticSpanSource::Attributes {
+                                    start_attribute: String::new(),
+                                    end_attribute: String::new(),
+                                },
+                            ] {
+                                let selected = std::mem::discriminant(&source)
+                                    == std::mem::discriminant(&rule.source);
+                                let name = source.display_name();
+                                if ui.selectable_label(selected, name).clicked() && !selected {
+                                    rule.source = source;
+                                }
+                            }
+                        });
+                    let (start, end, start_label, end_label) = match &mut rule.source {
+                        SyntheticSpanSource::Events {
+                            start_event,
+                            end_event,
+                        } => (start_event, end_event, "Start event:", "End event:"),
+                        SyntheticSpanSource::Attributes {
+                            start_attribute,
+                            end_attribute,
+                        } => (
+                            start_attribute,
+                            end_attribute,
+                            "Start attribute:",
+                            "End attribute:",
+                        ),
+                    };
+                    ui.horizontal(|ui| {
+                        ui.label(start_label);
+                        ui.text_edit_singleline(start);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label(end_label);
+                        ui.text_edit_singleline(end);
+                    });
+                    if ui.button("Delete synthetic span rule").clicked() {
+                        to_remove = Some(i);
+                    }
+                });
+        }
+        if let Some(i) = to_remove {
+            self.current_mode.synthetic_span_rules.remove(i);
+        }
+        if ui.button("New synthetic span rule").clicked() {
+            self.current_mode
+                .synthetic_span_rules
+                .push(SyntheticSpanRule {
+                    name: "phase".to_string(),
+                    selector: SpanSelector::new_equal_name("MySpan"),
+                    source: SyntheticSpanSource::Events {
+                        start_event: "phase_start".to_string(),
+                        end_event: "phase_end".to_string(),
+                    },
+                });
+        }
+    }
+
+    /// Shows how many spans of the loaded trace each rule matches, with a few examples.
+    fn draw_mode_preview(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Preview");
+            if ui
+                .add_enabled(!self.trace_spans.is_empty(), Button::new("Run preview"))
+                .on_hover_text("Match the rules against the loaded trace")
+                .on_disabled_hover_text("Open a trace file to preview the rules")
+                .clicked()
+            {
+                self.mode_preview = Some((
+                    self.span_rules_json(),
+                    preview_span_rules(
+                        &self.current_mode.span_rules,
+                        &self.trace_spans,
+                        PREVIEW_SAMPLES,
+                    ),
+                ));
+            }
+        });
+        let Some((rules_json, preview)) = &self.mode_preview else {
+            return;
+        };
+        if *rules_json != self.span_rules_json() {
+            ui.label(RichText::new("The rules changed, run the preview again").weak());
+            return;
+        }
+        ScrollArea::vertical()
+            .id_salt("mode preview")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                Grid::new("mode preview grid").striped(true).show(ui, |ui| {
+                    ui.label(RichText::new("Rule").strong());
+                    ui.label(RichText::new("Matched spans").strong());
+                    ui.label(RichText::new("Examples").strong());
+                    ui.end_row();
+                    for (rule, rule_preview) in
+                        self.current_mode.span_rules.iter().zip(&preview.rules)
+                    {
+                        ui.label(&rule.name);
+                        ui.label(rule_preview.matched_count.to_string());
+                        ui.horizontal_wrapped(|ui| {
+                            for span in &rule_preview.samples {
+                                ui.label(&span.original_name).on_hover_ui(|ui| {
+                                    ui.label(format!("node: {}", span.node.name));
+                                    for (name, value) in &span.attributes {
+                                        ui.label(format!("{}: {}", name, value_to_text(value)));
+                                    }
+                                });
+                            }
+                        });
+                        ui.end_row();
+                    }
+                    ui.label("Not matched (hidden)");
+                    ui.label(preview.unmatched_count.to_string());
+                    ui.end_row();
+                });
+            });
+    }
+
+    fn span_rules_json(&self) -> String {
+        serde_json::to_string(&self.current_mode.span_rules).unwrap_or_default()
+    }
+
     fn draw_editing_span_rule(&mut self, ui: &mut Ui, _ctx: &egui::Context) {
         ui.label("Editing Span Rule");
         self.draw_short_separator(ui);
@@ -368,6 +777,25 @@ impl EditDisplayModes {
             self.max_width,
             "span rule selector",
         );
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.trace_spans.is_empty(), Button::new("Test selector"))
+                .on_hover_text("Count spans of the loaded trace which match the selector")
+                .on_disabled_hover_text("Open a trace file to test the selector")
+                .clicked()
+            {
+                let selector = &self.current_span_rule.selector;
+                self.rule_preview_count = Some(
+                    self.trace_spans
+                        .iter()
+                        .filter(|s| selector.matches(s))
+                        .count(),
+                );
+            }
+            if let Some(count) = self.rule_preview_count {
+                ui.label(format!("Matches {count} spans"));
+            }
+        });
         self.draw_short_separator(ui);
         ui.label("Decision");
         ui.horizontal(|ui| {
@@ -450,26 +878,26 @@ impl EditDisplayModes {
 
     pub fn draw_edit_match_condition(ui: &mut Ui, condition: &mut MatchCondition, id_salt: &str) {
         ComboBox::new(id_salt, "")
-            .selected_text(format!("{:?}", condition.operator))
+            .selected_text(condition.operator.display_name())
             .show_ui(ui, |ui| {
-                ui.selectable_value(&mut condition.operator, MatchOperator::Any, "Any");
-                ui.selectable_value(&mut condition.operator, MatchOperator::None, "None");
-                ui.selectable_value(&mut condition.operator, MatchOperator::EqualTo, "Equal To");
-                ui.selectable_value(
-                    &mut condition.operator,
-                    MatchOperator::NotEqualTo,
-                    "Not Equal To",
-                );
-                ui.selectable_value(&mut condition.operator, MatchOperator::Contains, "Contains");
+                for operator in MatchOperator::all() {
+                    let name = operator.display_name();
+                    ui.selectable_value(&mut condition.operator, operator, name);
+                }
+            });
+        if condition.operator.uses_value() {
+            ui.horizontal(|ui| {
+                ui.label("Value:");
+                let hint = match condition.operator {
+                    MatchOperator::InRange => "min..max",
+                    MatchOperator::Regex => "regular expression",
+                    _ => "",
+                };
+                ui.add(TextEdit::singleline(&mut condition.value).hint_text(hint));
+                if let Some(error) = condition.validation_error() {
+                    ui.colored_label(theme().error, error);
+                }
             });
-        match condition.operator {
-            MatchOperator::Any | MatchOperator::None => {}
-            MatchOperator::EqualTo | MatchOperator::NotEqualTo | MatchOperator::Contains => {
-                ui.horizontal(|ui| {
-                    ui.label("Value:");
-                    ui.text_edit_singleline(&mut condition.value);
-                });
-            }
         }
     }
 
@@ -500,8 +928,16 @@ impl EditDisplayModes {
         );
         draw_short_separator(ui);
         ui.label("Attribute Conditions");
+        Self::draw_edit_attribute_conditions(ui, &mut selector.attribute_conditions, ui_seed);
+    }
+
+    pub fn draw_edit_attribute_conditions(
+        ui: &mut Ui,
+        attribute_conditions: &mut Vec<(String, MatchCondition)>,
+        ui_seed: &str,
+    ) {
         let mut attribute_condition_to_remove = None;
-        for (i, attr_condition) in &mut selector.attribute_conditions.iter_mut().enumerate() {
+        for (i, attr_condition) in &mut attribute_conditions.iter_mut().enumerate() {
             ui.horizontal(|ui| {
                 ui.label("Attribute Name:");
                 ui.text_edit_singleline(&mut attr_condition.0);
@@ -516,11 +952,11 @@ impl EditDisplayModes {
             });
         }
         if let Some(idx) = attribute_condition_to_remove {
-            selector.attribute_conditions.remove(idx);
+            attribute_conditions.remove(idx);
         }
         if ui.button("New Attribute Condition").clicked() {
-            selector.attribute_conditions.push((
-                "<attribute name>".to_string(),
+            attribute_conditions.push((
+                ATTRIBUTE_NAME_PLACEHOLDER.to_string(),
                 MatchCondition {
                     operator: MatchOperator::EqualTo,
                     value: "val".to_string(),
@@ -530,6 +966,30 @@ impl EditDisplayModes {
     }
 }
 
+/// Short description of a muted spans selector, e.g. "Equal To handle_network_message".
+fn muted_spans_label(selector: &SpanSelector) -> String {
+    let condition = &selector.span_name_condition;
+    let mut label = if condition.operator.uses_value() {
+        format!("{} {}", condition.operator.display_name(), condition.value)
+    } else {
+        condition.operator.display_name().to_string()
+    };
+    if selector.node_name_condition.operator != MatchOperator::Any {
+        label.push_str(&format!(
+            " on nodes {} {}",
+            selector.node_name_condition.operator.display_name(),
+            selector.node_name_condition.value
+        ));
+    }
+    if !selector.attribute_conditions.is_empty() {
+        label.push_str(&format!(
+            " ({} attribute conditions)",
+            selector.attribute_conditions.len()
+        ));
+    }
+    label
+}
+
 impl Default for EditDisplayModes {
     fn default() -> Self {
         EditDisplayModes::new()
@@ -0,0 +1,321 @@
+use eframe::egui::{self, ComboBox, DragValue, Modal, Slider, TextEdit};
+
+use crate::colors::{Palette, SemanticColor, ThemeKind};
+use crate::modes::{OrphanSpans, RemoteParentLinking};
+use crate::preferences::{InitialZoom, Preferences, UI_SCALE_RANGE};
+use crate::source_location::DEFAULT_EDITOR_COMMAND;
+use crate::sticky_header::StickyLaneHeader;
+use crate::types::{TimeZoneSetting, MAX_TIME_PRECISION};
+
+/// Dialog for editing `Preferences`.
+pub struct EditPreferences {
+    show: bool,
+    preferences: Preferences,
+}
+
+impl Default for EditPreferences {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditPreferences {
+    pub fn new() -> EditPreferences {
+        EditPreferences {
+            show: false,
+            preferences: Preferences::default(),
+        }
+    }
+
+    pub fn open(&mut self, preferences: Preferences) {
+        self.preferences = preferences;
+        self.show = true;
+    }
+
+    /// Returns the new preferences when the user clicks "Save".
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        max_width: f32,
+        max_height: f32,
+    ) -> Option<Preferences> {
+        if !self.show {
+            return None;
+        }
+
+        let mut result = None;
+        Modal::new("edit preferences".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Preferences");
+            ui.separator();
+
+            ui.label("Initial zoom after opening a file");
+            let initial_zoom = &mut self.preferences.initial_zoom;
+            ComboBox::from_id_salt("initial zoom kind")
+                .selected_text(initial_zoom.kind_name())
+                .show_ui(ui, |ui| {
+                    for kind in InitialZoom::all_kinds() {
+                        if ui
+                            .selectable_label(
+                                std::mem::discriminant(initial_zoom)
+                                    == std::mem::discriminant(&kind),
+                                kind.kind_name(),
+                            )
+                            .clicked()
+                        {
+                            *initial_zoom = kind;
+                        }
+                    }
+                });
+            match initial_zoom {
+                InitialZoom::FirstSeconds {
+                    visible_seconds,
+                    selected_seconds,
+                } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Visible seconds:");
+                        ui.add(
+                            DragValue::new(visible_seconds)
+                                .range(0.001..=f64::MAX)
+                                .speed(0.1),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Selected seconds:");
+                        ui.add(
+                            DragValue::new(selected_seconds)
+                                .range(0.001..=f64::MAX)
+                                .speed(0.1),
+                        );
+                    });
+                }
+                InitialZoom::FullTrace => {}
+                InitialZoom::AroundFirstProduceBlock { selected_seconds } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Selected seconds:");
+                        ui.add(
+                            DragValue::new(selected_seconds)
+                                .range(0.001..=f64::MAX)
+                                .speed(0.1),
+                        );
+                    });
+                }
+            }
+
+            ui.separator();
+            ui.checkbox(
+                &mut self.preferences.lazy_mode_transformation,
+                "Transform collapsed spans lazily",
+            )
+            .on_hover_text(
+                "Children of collapsed spans are prepared only when the span is expanded, which \
+                 makes switching display modes on big traces much faster.",
+            );
+            ui.checkbox(
+                &mut self.preferences.show_trace_info_on_load,
+                "Show trace info after opening a file",
+            );
+            ui.horizontal(|ui| {
+                ui.label("Spans with a parent on another node:");
+                ComboBox::from_id_salt("remote parents")
+                    .selected_text(self.preferences.remote_parents.name())
+                    .show_ui(ui, |ui| {
+                        for linking in RemoteParentLinking::all() {
+                            ui.selectable_value(
+                                &mut self.preferences.remote_parents,
+                                linking,
+                                linking.name(),
+                            );
+                        }
+                    });
+            })
+            .response
+            .on_hover_text(
+                "Spans whose parent span was recorded on another node, e.g. the handler of a \
+                 request which carried the trace context. They can be displayed under the parent \
+                 with a \"remote\" badge, or as top level spans on their own node, optionally \
+                 with a relation arrow from the parent.",
+            );
+            ui.horizontal(|ui| {
+                ui.label("Spans with a missing parent:");
+                ComboBox::from_id_salt("orphan spans")
+                    .selected_text(self.preferences.orphan_spans.name())
+                    .show_ui(ui, |ui| {
+                        for orphans in OrphanSpans::all() {
+                            ui.selectable_value(
+                                &mut self.preferences.orphan_spans,
+                                orphans,
+                                orphans.name(),
+                            );
+                        }
+                    });
+            })
+            .response
+            .on_hover_text(
+                "Spans whose parent span isn't in the trace, e.g. because it started before the \
+                 capture. They can be displayed as top level spans, in a separate lane of their \
+                 node, or hidden.",
+            );
+            ui.horizontal(|ui| {
+                ui.label("Sticky lane header:");
+                ComboBox::from_id_salt("sticky lane header")
+                    .selected_text(self.preferences.sticky_lane_header.name())
+                    .show_ui(ui, |ui| {
+                        for header in StickyLaneHeader::all() {
+                            ui.selectable_value(
+                                &mut self.preferences.sticky_lane_header,
+                                header,
+                                header.name(),
+                            );
+                        }
+                    });
+            })
+            .response
+            .on_hover_text(
+                "When the top of a tall lane is scrolled out of view, its name (and optionally \
+                 the first row of its top level spans) stays at the top of the span area.",
+            );
+
+            ui.separator();
+            let time_display = &mut self.preferences.time_display;
+            ui.horizontal(|ui| {
+                ui.label("Show times in:");
+                ComboBox::from_id_salt("time zone kind")
+                    .selected_text(time_display.time_zone.kind_name())
+                    .show_ui(ui, |ui| {
+                        for kind in TimeZoneSetting::all_kinds() {
+                            if ui
+                                .selectable_label(
+                                    std::mem::discriminant(&time_display.time_zone)
+                                        == std::mem::discriminant(&kind),
+                                    kind.kind_name(),
+                                )
+                                .clicked()
+                            {
+                                time_display.time_zone = kind;
+                            }
+                        }
+                    });
+                if let TimeZoneSetting::FixedOffset { minutes } = &mut time_display.time_zone {
+                    ui.add(
+                        DragValue::new(minutes)
+                            .range(-12 * 60..=14 * 60)
+                            .speed(15.0)
+                            .prefix("UTC ")
+                            .suffix(" min"),
+                    );
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Timeline tick precision:");
+                ui.add(
+                    DragValue::new(&mut time_display.tick_precision)
+                        .range(0..=MAX_TIME_PRECISION)
+                        .suffix(" digits"),
+                )
+                .on_hover_text("Number of digits after the seconds in the timeline ticks");
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Editor command:");
+                ui.add(
+                    TextEdit::singleline(&mut self.preferences.editor_command)
+                        .hint_text(DEFAULT_EDITOR_COMMAND)
+                        .desired_width(300.0),
+                )
+                .on_hover_text(
+                    "Opens the source of a span from its code.filepath and code.lineno \
+                     attributes, {file} and {line} are replaced with their values",
+                );
+            });
+            ui.checkbox(
+                &mut self.preferences.cache_downloaded_files,
+                "Reuse downloaded files",
+            )
+            .on_hover_text(
+                "Files opened from a URL are kept in the temp dir, opening the same URL again \
+                 downloads it only if it changed on the server",
+            );
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Theme:");
+                ComboBox::from_id_salt("theme")
+                    .selected_text(self.preferences.theme.name())
+                    .show_ui(ui, |ui| {
+                        for theme in ThemeKind::all() {
+                            ui.selectable_value(&mut self.preferences.theme, theme, theme.name());
+                        }
+                    });
+            });
+            ui.horizontal(|ui| {
+                ui.label("UI scale:");
+                ui.add(
+                    Slider::new(&mut self.preferences.ui_scale, UI_SCALE_RANGE)
+                        .step_by(0.05)
+                        .fixed_decimals(2),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Palette:");
+                ComboBox::from_id_salt("palette")
+                    .selected_text(self.preferences.palette.name())
+                    .show_ui(ui, |ui| {
+                        for palette in Palette::all() {
+                            ui.selectable_value(
+                                &mut self.preferences.palette,
+                                palette,
+                                palette.name(),
+                            );
+                        }
+                    });
+            })
+            .response
+            .on_hover_text("Colors which can be told apart with red-green color blindness");
+
+            ui.label("Colors:");
+            let palette_theme = self
+                .preferences
+                .theme
+                .theme()
+                .with_palette(self.preferences.palette);
+            let overrides = &mut self.preferences.color_overrides;
+            for semantic_color in SemanticColor::all() {
+                ui.horizontal(|ui| {
+                    let mut rgb = overrides.get(&semantic_color).copied().unwrap_or_else(|| {
+                        let color = semantic_color.get(&palette_theme);
+                        [color.r(), color.g(), color.b()]
+                    });
+                    if ui.color_edit_button_srgb(&mut rgb).changed() {
+                        overrides.insert(semantic_color, rgb);
+                    }
+                    ui.label(semantic_color.name());
+                    if overrides.contains_key(&semantic_color)
+                        && ui
+                            .small_button("Reset")
+                            .on_hover_text("Use the color of the theme and palette")
+                            .clicked()
+                    {
+                        overrides.remove(&semantic_color);
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    result = Some(self.preferences.clone());
+                    self.show = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.show = false;
+                }
+            });
+        });
+
+        result
+    }
+}
@@ -0,0 +1,150 @@
+use eframe::egui::{self, Button, Modal, RichText, ScrollArea, TextEdit};
+
+use crate::colors::theme;
+use crate::profiles::Profile;
+use crate::reorder::duplicate_name;
+
+/// Dialog for adding, renaming, duplicating and deleting profiles.
+pub struct EditProfiles {
+    show: bool,
+    profiles: Vec<Profile>,
+    /// Index of the current profile, it's tracked by index because it can be renamed.
+    current_idx: usize,
+}
+
+impl Default for EditProfiles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditProfiles {
+    pub fn new() -> EditProfiles {
+        EditProfiles {
+            show: false,
+            profiles: Vec::new(),
+            current_idx: 0,
+        }
+    }
+
+    pub fn open(&mut self, profiles: Vec<Profile>, current_profile: &str) {
+        self.current_idx = profiles
+            .iter()
+            .position(|p| p.name == current_profile)
+            .unwrap_or(0);
+        self.profiles = profiles;
+        if self.profiles.is_empty() {
+            self.profiles.push(Profile::new(current_profile));
+        }
+        self.show = true;
+    }
+
+    /// Returns the new list of profiles and the (possibly renamed) current profile when the user
+    /// clicks "Save".
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        max_width: f32,
+        max_height: f32,
+    ) -> Option<(Vec<Profile>, String)> {
+        if !self.show {
+            return None;
+        }
+
+        let mut result = None;
+        Modal::new("edit profiles".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Profiles");
+            ui.label(
+                "Each profile has its own display modes, node filters, relations, relation views \
+                 and time markers.",
+            );
+            ui.separator();
+
+            let mut to_duplicate = None;
+            let mut to_delete = None;
+            let can_delete = self.profiles.len() > 1;
+            ScrollArea::vertical()
+                .max_height(max_height - 150.0)
+                .show(ui, |ui| {
+                    for (idx, profile) in self.profiles.iter_mut().enumerate() {
+                        ui.push_id(idx, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.add(TextEdit::singleline(&mut profile.name));
+                                if ui.button("Duplicate").clicked() {
+                                    to_duplicate = Some(idx);
+                                }
+                                if ui
+                                    .add_enabled(can_delete, Button::new("Delete"))
+                                    .on_disabled_hover_text("The last profile can't be deleted")
+                                    .clicked()
+                                {
+                                    to_delete = Some(idx);
+                                }
+                                if idx == self.current_idx {
+                                    ui.label("(current)");
+                                }
+                            });
+                        });
+                    }
+                });
+            if let Some(idx) = to_duplicate {
+                let mut new_profile = self.profiles[idx].clone();
+                new_profile.name = duplicate_name(
+                    &new_profile.name,
+                    self.profiles.iter().map(|p| p.name.as_str()),
+                );
+                self.profiles.push(new_profile);
+            }
+            if let Some(idx) = to_delete {
+                self.profiles.remove(idx);
+                if self.current_idx > idx || self.current_idx == self.profiles.len() {
+                    self.current_idx = self.current_idx.saturating_sub(1);
+                }
+            }
+            if ui.button("New empty profile").clicked() {
+                let name =
+                    duplicate_name("New profile", self.profiles.iter().map(|p| p.name.as_str()));
+                self.profiles.push(Profile::new(name));
+            }
+            ui.separator();
+
+            let error = self.validation_error();
+            if let Some(error) = &error {
+                ui.label(RichText::new(error).color(theme().error));
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(error.is_none(), Button::new("Save"))
+                    .clicked()
+                {
+                    let current_name = self.profiles[self.current_idx].name.clone();
+                    result = Some((std::mem::take(&mut self.profiles), current_name));
+                    self.show = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.show = false;
+                }
+            });
+        });
+
+        result
+    }
+
+    fn validation_error(&self) -> Option<String> {
+        for (idx, profile) in self.profiles.iter().enumerate() {
+            if profile.name.trim().is_empty() {
+                return Some("Profile names can't be empty".to_string());
+            }
+            if self.profiles[..idx].iter().any(|p| p.name == profile.name) {
+                return Some(format!(
+                    "There are two profiles called \"{}\"",
+                    profile.name
+                ));
+            }
+        }
+        None
+    }
+}
@@ -1,12 +1,15 @@
-use eframe::egui::{self, Button, ComboBox, Modal, ScrollArea, Ui, Vec2, Widget};
+use eframe::egui::{self, ComboBox, DragValue, Modal, ScrollArea, Ui, Vec2};
 use std::collections::HashMap;
 use uuid::Uuid;
 
-use crate::edit_modes::{AddingOrEditing, EditDisplayModes, HIGHLIGHT_COLOR};
+use crate::edit_modes::{AddingOrEditing, EditDisplayModes};
 use crate::relation::{
-    AttributeRelation, AttributeRelationOp, MatchType, Relation, RelationNodesConfig, RelationView,
+    ArrowStyle, AttributeRelation, AttributeRelationOp, MatchType, Relation, RelationNodesConfig,
+    RelationView,
 };
+use crate::reorder::{draw_reorderable_list, duplicate_name, move_entry};
 use crate::structured_modes::SpanSelector;
+use crate::undo::UndoStack;
 
 #[derive(Clone, Debug)]
 pub struct EditRelations {
@@ -21,6 +24,9 @@ pub struct EditRelations {
     max_time_difference_string: String,
     max_width: f32,
     max_scrollarea_size: egui::Vec2,
+    /// Snapshots of relations and relation views from before each change, deleting a relation
+    /// also changes the views.
+    undo: UndoStack<(Vec<Relation>, Vec<RelationView>)>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -52,6 +58,7 @@ impl EditRelations {
             max_time_difference_string: String::new(),
             max_width: 0.0,
             max_scrollarea_size: egui::Vec2::ZERO,
+            undo: UndoStack::new(),
         }
     }
 
@@ -59,9 +66,25 @@ impl EditRelations {
         self.relations = relations;
         self.relation_views = relation_views;
         self.selected_relation_idx = 0;
+        self.undo.clear();
         self.state = EditRelationsState::Open;
     }
 
+    fn snapshot(&self) -> (Vec<Relation>, Vec<RelationView>) {
+        (self.relations.clone(), self.relation_views.clone())
+    }
+
+    /// Returns true when undo or redo changed the relations.
+    fn draw_undo_buttons(&mut self, ui: &mut Ui) -> bool {
+        let mut state = (
+            std::mem::take(&mut self.relations),
+            std::mem::take(&mut self.relation_views),
+        );
+        let changed = self.undo.draw_buttons(ui, &mut state);
+        (self.relations, self.relation_views) = state;
+        changed
+    }
+
     pub fn draw(
         &mut self,
         max_width: f32,
@@ -102,25 +125,27 @@ impl EditRelations {
         ui.label("Relations");
         ui.allocate_ui(self.max_scrollarea_size, |ui| {
             ScrollArea::vertical().id_salt("relations").show(ui, |ui| {
-                let mut sorted_relations: Vec<(usize, &Relation)> =
-                    self.relations.iter().enumerate().collect::<Vec<_>>();
-                sorted_relations.sort_by_key(|r| &r.1.name);
-
-                for (index, relation) in sorted_relations {
-                    let relation_name = if relation.is_builtin {
-                        format!("{} (builtin)", relation.name)
-                    } else {
-                        relation.name.clone()
-                    };
-
-                    let button = if self.selected_relation_idx == index {
-                        Button::new(relation_name).fill(HIGHLIGHT_COLOR)
-                    } else {
-                        Button::new(relation_name)
-                    };
-                    if button.ui(ui).clicked() {
-                        self.selected_relation_idx = index;
-                    }
+                if let Some((from, to)) = draw_reorderable_list(
+                    ui,
+                    "relations",
+                    &self.relations,
+                    &mut self.selected_relation_idx,
+                    |relation| relation.is_builtin,
+                    |relation| {
+                        if relation.is_builtin {
+                            format!("{} (builtin)", relation.name)
+                        } else {
+                            relation.name.clone()
+                        }
+                    },
+                ) {
+                    self.undo.push(self.snapshot());
+                    move_entry(
+                        &mut self.relations,
+                        from,
+                        to,
+                        &mut self.selected_relation_idx,
+                    );
                 }
             });
         });
@@ -142,7 +167,7 @@ impl EditRelations {
                     if relation.is_builtin {
                         self.not_editable_message =
                         "This relation is not editable! Builtin relations that are provided in traviz cannot be changed from the UI. \
-                        You can clone this relation to create your own custom one and then edit the custom relation".to_string();
+                        You can duplicate this relation to create your own custom one and then edit the custom relation".to_string();
                         self.state = EditRelationsState::NotEditableError;
                     } else {
                         self.current_relation = relation.clone();
@@ -152,11 +177,15 @@ impl EditRelations {
                     }
                 }
             }
-            if ui.button("Clone Relation").clicked() {
+            if ui.button("Duplicate Relation").clicked() {
                 let mut new_relation = self.relations[self.selected_relation_idx].clone();
                 new_relation.id = Uuid::new_v4();
-                new_relation.name = format!("{} Clone", new_relation.name);
+                new_relation.name = duplicate_name(
+                    &new_relation.name,
+                    self.relations.iter().map(|r| r.name.as_str()),
+                );
                 new_relation.is_builtin = false;
+                self.undo.push(self.snapshot());
                 self.relations.push(new_relation);
                 self.selected_relation_idx = self.relations.len() - 1;
             }
@@ -170,6 +199,11 @@ impl EditRelations {
                     }
                 }
             }
+            ui.separator();
+            if self.draw_undo_buttons(ui) {
+                self.selected_relation_idx =
+                    self.selected_relation_idx.min(self.relations.len().saturating_sub(1));
+            }
         });
 
         let mut result = None;
@@ -202,6 +236,7 @@ impl EditRelations {
         ui.label(format!("Relation Name: {}", relation.name));
         self.draw_short_separator(ui);
         if ui.button("Yes, Delete").clicked() {
+            self.undo.push(self.snapshot());
             // First remove it from all relation views
             for relation_view in &mut self.relation_views {
                 relation_view
@@ -378,8 +413,39 @@ impl EditRelations {
                 });
         });
 
+        ui.horizontal(|ui| {
+            ui.label("Required from spans:");
+            ui.add(DragValue::new(&mut self.current_relation.required_sources).range(1..=10000))
+                .on_hover_text(
+                    "With more than one, a \"to\" span is related only when at least this many \
+                     \"from\" spans match it (fan-in), to the ones which arrived first",
+                );
+        });
+        self.draw_short_separator(ui);
+        ui.strong("Arrow style");
+        ui.horizontal(|ui| {
+            let style = &mut self.current_relation.arrow_style;
+            ui.label("Color:");
+            ui.color_edit_button_srgb(&mut style.color);
+            ui.label("Width:");
+            ui.add(DragValue::new(&mut style.width).range(0.5..=8.0).speed(0.1));
+            ui.checkbox(&mut style.dashed, "Dashed");
+            ui.checkbox(&mut style.show_label, "Show delay label");
+        });
+        ui.add_space(10.0);
+
+        ui.checkbox(
+            &mut self.current_relation.report_unmatched,
+            "Report unmatched spans",
+        )
+        .on_hover_text(
+            "List spans which match a selector but weren't matched with any counterpart, \
+             e.g. a message which was sent but never received",
+        );
+
         ui.horizontal(|ui| {
             if ui.button("Ok").clicked() {
+                self.undo.push(self.snapshot());
                 match self.editing_or_adding_relation {
                     AddingOrEditing::Adding => {
                         self.relations.push(self.current_relation.clone());
@@ -416,6 +482,9 @@ impl EditRelations {
             nodes_config: RelationNodesConfig::AllNodes,
             match_type: MatchType::MatchAll,
             min_time_diff: 0.0,
+            required_sources: 1,
+            report_unmatched: false,
+            arrow_style: ArrowStyle::default(),
             is_builtin: false,
         }
     }
@@ -442,6 +511,8 @@ pub struct EditRelationViews {
     not_editable_message: String,
     max_width: f32,
     max_scrollarea_size: egui::Vec2,
+    /// Snapshots of `relation_views` from before each change.
+    undo: UndoStack<Vec<RelationView>>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -475,6 +546,7 @@ impl EditRelationViews {
             not_editable_message: String::new(),
             max_width: 0.0,
             max_scrollarea_size: egui::Vec2::ZERO,
+            undo: UndoStack::new(),
         }
     }
 
@@ -485,6 +557,7 @@ impl EditRelationViews {
             .collect();
         self.relation_views = relation_views;
         self.selected_relation_view_idx = 0;
+        self.undo.clear();
         self.state = EditRelationViewsState::Open;
     }
 
@@ -526,21 +599,27 @@ impl EditRelationViews {
         ui.label("Relation views");
         ui.allocate_ui(self.max_scrollarea_size, |ui| {
             ScrollArea::vertical().id_salt("relations").show(ui, |ui| {
-                for (index, relation_view) in self.relation_views.iter().enumerate() {
-                    let view_name = if relation_view.is_builtin {
-                        format!("{} (builtin)", relation_view.name)
-                    } else {
-                        relation_view.name.clone()
-                    };
-
-                    let button = if self.selected_relation_view_idx == index {
-                        Button::new(view_name).fill(HIGHLIGHT_COLOR)
-                    } else {
-                        Button::new(view_name)
-                    };
-                    if button.ui(ui).clicked() {
-                        self.selected_relation_view_idx = index;
-                    }
+                if let Some((from, to)) = draw_reorderable_list(
+                    ui,
+                    "relation views",
+                    &self.relation_views,
+                    &mut self.selected_relation_view_idx,
+                    |view| view.is_builtin,
+                    |view| {
+                        if view.is_builtin {
+                            format!("{} (builtin)", view.name)
+                        } else {
+                            view.name.clone()
+                        }
+                    },
+                ) {
+                    self.undo.push(self.relation_views.clone());
+                    move_entry(
+                        &mut self.relation_views,
+                        from,
+                        to,
+                        &mut self.selected_relation_view_idx,
+                    );
                 }
             });
         });
@@ -561,7 +640,7 @@ impl EditRelationViews {
                     if relation_view.is_builtin {
                         self.not_editable_message =
                         "This relation view is not editable! Builtin relation views that are provided in traviz cannot be changed from the UI. \
-                        You can clone this relation view to create your own custom one and then edit the custom relation view".to_string();
+                        You can duplicate this relation view to create your own custom one and then edit the custom relation view".to_string();
                         self.state = EditRelationViewsState::NotEditableError;
                     } else {
                         self.current_relation_view = relation_view.clone();
@@ -570,10 +649,14 @@ impl EditRelationViews {
                     }
                 }
             }
-            if ui.button("Clone Relation view").clicked() {
+            if ui.button("Duplicate Relation view").clicked() {
                 let mut new_relation_view = self.relation_views[self.selected_relation_view_idx].clone();
-                new_relation_view.name = format!("{} Clone", new_relation_view.name);
+                new_relation_view.name = duplicate_name(
+                    &new_relation_view.name,
+                    self.relation_views.iter().map(|v| v.name.as_str()),
+                );
                 new_relation_view.is_builtin = false;
+                self.undo.push(self.relation_views.clone());
                 self.relation_views.push(new_relation_view);
                 self.selected_relation_view_idx = self.relation_views.len() - 1;
             }
@@ -587,6 +670,12 @@ impl EditRelationViews {
                     }
                 }
             }
+            ui.separator();
+            if self.undo.draw_buttons(ui, &mut self.relation_views) {
+                self.selected_relation_view_idx = self
+                    .selected_relation_view_idx
+                    .min(self.relation_views.len().saturating_sub(1));
+            }
         });
 
         let mut result = None;
@@ -616,6 +705,7 @@ impl EditRelationViews {
         ui.label(format!("Relation view name: {}", relation_view.name));
         self.draw_short_separator(ui);
         if ui.button("Yes, Delete").clicked() {
+            self.undo.push(self.relation_views.clone());
             self.relation_views.remove(self.selected_relation_view_idx);
             self.selected_relation_view_idx = 0;
             self.state = EditRelationViewsState::Open;
@@ -696,6 +786,7 @@ impl EditRelationViews {
 
         ui.horizontal(|ui| {
             if ui.button("Ok").clicked() {
+                self.undo.push(self.relation_views.clone());
                 match self.editing_or_adding_view {
                     AddingOrEditing::Adding => {
                         self.relation_views.push(self.current_relation_view.clone());
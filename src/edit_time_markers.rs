@@ -0,0 +1,157 @@
+use eframe::egui::{self, Button, Modal, RichText, ScrollArea, Widget};
+
+use crate::colors::theme;
+use crate::edit_modes::EditDisplayModes;
+use crate::structured_modes::SpanSelector;
+use crate::time_markers::TimeMarkerRule;
+
+/// Dialog for editing the rules which decide where time markers are drawn on the timeline.
+pub struct EditTimeMarkers {
+    show: bool,
+    rules: Vec<TimeMarkerRule>,
+    selected_rule_idx: usize,
+}
+
+impl Default for EditTimeMarkers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditTimeMarkers {
+    pub fn new() -> EditTimeMarkers {
+        EditTimeMarkers {
+            show: false,
+            rules: Vec::new(),
+            selected_rule_idx: 0,
+        }
+    }
+
+    pub fn open(&mut self, rules: Vec<TimeMarkerRule>) {
+        self.rules = rules;
+        self.selected_rule_idx = 0;
+        self.show = true;
+    }
+
+    fn new_rule() -> TimeMarkerRule {
+        TimeMarkerRule {
+            label: "New marker".to_string(),
+            selector: SpanSelector::new_equal_name("MySpan"),
+            color: [255, 200, 0],
+            show_node_name: true,
+            enabled: true,
+        }
+    }
+
+    /// Returns the new list of rules when the user clicks "Save".
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        max_width: f32,
+        max_height: f32,
+    ) -> Option<Vec<TimeMarkerRule>> {
+        if !self.show {
+            return None;
+        }
+
+        let mut result = None;
+        Modal::new("edit time markers".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Time markers");
+            ui.label(
+                "A marker is drawn on the timeline at the start of every span that matches a rule.",
+            );
+            ui.separator();
+
+            ScrollArea::vertical()
+                .id_salt("time marker rules")
+                .max_height(150.0)
+                .show(ui, |ui| {
+                    for (index, rule) in self.rules.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut rule.enabled, "")
+                                .on_hover_text("Show markers for this rule");
+                            let text = RichText::new(&rule.label).color(rule.color32());
+                            let button = if self.selected_rule_idx == index {
+                                Button::new(text).fill(theme().selected_item)
+                            } else {
+                                Button::new(text)
+                            };
+                            if button.ui(ui).clicked() {
+                                self.selected_rule_idx = index;
+                            }
+                        });
+                    }
+                    if self.rules.is_empty() {
+                        ui.label("<empty>");
+                    }
+                });
+
+            ui.horizontal(|ui| {
+                if ui.button("New rule").clicked() {
+                    self.rules.push(Self::new_rule());
+                    self.selected_rule_idx = self.rules.len() - 1;
+                }
+                if ui.button("Clone rule").clicked() && self.selected_rule_idx < self.rules.len() {
+                    let mut new_rule = self.rules[self.selected_rule_idx].clone();
+                    new_rule.label = format!("{} Clone", new_rule.label);
+                    self.rules.push(new_rule);
+                    self.selected_rule_idx = self.rules.len() - 1;
+                }
+                if ui.button("Delete rule").clicked() && self.selected_rule_idx < self.rules.len() {
+                    self.rules.remove(self.selected_rule_idx);
+                    self.selected_rule_idx = self.selected_rule_idx.saturating_sub(1);
+                }
+                if ui.button("Move up").clicked() && self.selected_rule_idx > 0 {
+                    self.rules
+                        .swap(self.selected_rule_idx, self.selected_rule_idx - 1);
+                    self.selected_rule_idx -= 1;
+                }
+                if ui.button("Move down").clicked() && self.selected_rule_idx + 1 < self.rules.len()
+                {
+                    self.rules
+                        .swap(self.selected_rule_idx, self.selected_rule_idx + 1);
+                    self.selected_rule_idx += 1;
+                }
+            });
+            ui.separator();
+
+            if let Some(rule) = self.rules.get_mut(self.selected_rule_idx) {
+                ScrollArea::vertical()
+                    .id_salt("time marker rule")
+                    .max_height(max_height - 350.0)
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Label:");
+                            ui.text_edit_singleline(&mut rule.label);
+                            ui.label("Color:");
+                            ui.color_edit_button_srgb(&mut rule.color);
+                        });
+                        ui.checkbox(&mut rule.show_node_name, "Show node name next to markers");
+                        ui.separator();
+                        EditDisplayModes::draw_edit_span_selector(
+                            &mut rule.selector,
+                            ui,
+                            max_width,
+                            "time marker selector",
+                        );
+                    });
+                ui.separator();
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    result = Some(std::mem::take(&mut self.rules));
+                    self.show = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.show = false;
+                }
+            });
+        });
+
+        result
+    }
+}
@@ -0,0 +1,217 @@
+//! Search of events across all spans in the trace.
+//! Matching events are listed in a modal, and can be shown as markers in an extra strip under each
+//! lane of the span view. State transitions are often recorded as events, without this they could
+//! only be found by clicking on every span.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use eframe::egui::{self, Grid, Key, Modal, RichText, ScrollArea, TextEdit};
+
+use crate::analyze_utils::ModalFocus;
+use crate::colors::node_color;
+use crate::modes::{span_lane_name, OrphanSpans};
+use crate::search::{parse_search_term, SearchCriterion};
+use crate::structured_modes::{LaneLayout, StructuredMode};
+use crate::types::{Event, Span, TimeFormat, TimePoint};
+
+/// An event found by the search, together with the span that emitted it.
+#[derive(Debug, Clone)]
+pub struct EventMatch {
+    pub span: Rc<Span>,
+    event_index: usize,
+}
+
+impl EventMatch {
+    pub fn event(&self) -> &Event {
+        &self.span.events[self.event_index]
+    }
+
+    pub fn time(&self) -> TimePoint {
+        self.event().time
+    }
+}
+
+/// Events of `spans` which match all criteria, sorted by time. With no criteria all events match.
+pub fn find_matching_events(spans: &[Rc<Span>], criteria: &[SearchCriterion]) -> Vec<EventMatch> {
+    let mut matches: Vec<EventMatch> = spans
+        .iter()
+        .flat_map(|span| {
+            span.events
+                .iter()
+                .enumerate()
+                .filter(|(_, event)| {
+                    criteria
+                        .iter()
+                        .all(|c| c.matches_name_and_attributes(&event.name, &event.attributes))
+                })
+                .map(|(event_index, _)| EventMatch {
+                    span: span.clone(),
+                    event_index,
+                })
+        })
+        .collect();
+    matches.sort_by(|a, b| a.time().total_cmp(&b.time()));
+    matches
+}
+
+/// Lane layout and sub-lane attribute of the mode and the placement of orphan spans, they decide
+/// which lane an event belongs to.
+type LanesKey = (LaneLayout, String, OrphanSpans);
+
+#[derive(Default)]
+pub struct EventSearchModal {
+    pub show: bool,
+    query: String,
+    /// All spans in the trace, including children.
+    spans: Vec<Rc<Span>>,
+    results: Vec<EventMatch>,
+    /// Show the results as markers under each lane of the span view.
+    pub show_lane: bool,
+    /// Indexes of results grouped by lane, built on demand for the [LanesKey].
+    results_by_lane: Option<(LanesKey, HashMap<String, Vec<usize>>)>,
+    pub focus: ModalFocus,
+}
+
+impl EventSearchModal {
+    pub fn new() -> EventSearchModal {
+        EventSearchModal::default()
+    }
+
+    /// `spans` should contain all spans in the trace, including children.
+    pub fn open(&mut self, spans: Vec<Rc<Span>>) {
+        self.show = true;
+        self.spans = spans;
+        self.run_query();
+    }
+
+    pub fn set_query(&mut self, query: impl Into<String>) {
+        self.query = query.into();
+        self.run_query();
+    }
+
+    fn run_query(&mut self) {
+        self.results = find_matching_events(&self.spans, &parse_search_term(&self.query));
+        self.results_by_lane = None;
+    }
+
+    pub fn results(&self) -> &[EventMatch] {
+        &self.results
+    }
+
+    /// Forgets the spans and results, used when a new trace is loaded.
+    pub fn clear(&mut self) {
+        self.spans.clear();
+        self.results.clear();
+        self.results_by_lane = None;
+        self.focus.clear();
+    }
+
+    /// Results which should be displayed under the lane, if the event lane is enabled.
+    pub fn lane_results(
+        &mut self,
+        mode: &StructuredMode,
+        orphans: OrphanSpans,
+        lane_name: &str,
+    ) -> Vec<EventMatch> {
+        if !self.show_lane || self.results.is_empty() {
+            return Vec::new();
+        }
+        let lanes_key = (mode.lane_layout, mode.sub_lane_attribute.clone(), orphans);
+        if self
+            .results_by_lane
+            .as_ref()
+            .is_none_or(|(key, _)| *key != lanes_key)
+        {
+            let mut by_lane: HashMap<String, Vec<usize>> = HashMap::new();
+            for (i, result) in self.results.iter().enumerate() {
+                by_lane
+                    .entry(span_lane_name(Some(mode), orphans, &result.span))
+                    .or_default()
+                    .push(i);
+            }
+            self.results_by_lane = Some((lanes_key, by_lane));
+        }
+        let (_, by_lane) = self.results_by_lane.as_ref().unwrap();
+        by_lane
+            .get(lane_name)
+            .map(|indexes| indexes.iter().map(|i| self.results[*i].clone()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn show_modal(
+        &mut self,
+        ctx: &egui::Context,
+        time_format: &TimeFormat,
+        max_width: f32,
+        max_height: f32,
+    ) {
+        if !self.show {
+            return;
+        }
+
+        let mut close = false;
+        Modal::new("event search".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Events");
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    TextEdit::singleline(&mut self.query)
+                        .hint_text("name attr=value")
+                        .desired_width(max_width * 0.5),
+                );
+                if response.changed() {
+                    self.run_query();
+                }
+                ui.label(format!("{} matching events", self.results.len()));
+            });
+            ui.checkbox(
+                &mut self.show_lane,
+                "Show matching events under each lane of the span view",
+            );
+            ui.separator();
+
+            let row_height = ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+            let mut clicked = None;
+            ScrollArea::vertical()
+                .max_height(max_height - 150.0)
+                .auto_shrink([false, true])
+                .show_rows(ui, row_height, self.results.len(), |ui, row_range| {
+                    Grid::new("event search results")
+                        .striped(true)
+                        .start_row(row_range.start)
+                        .show(ui, |ui| {
+                            for (i, result) in self.results[row_range.clone()].iter().enumerate() {
+                                let event = result.event();
+                                ui.monospace(time_format.display_string(event.time));
+                                ui.label(
+                                    RichText::new(&result.span.node.name)
+                                        .color(node_color(&result.span.node.name)),
+                                );
+                                ui.label(&result.span.name);
+                                let response =
+                                    ui.link(&event.name).on_hover_text("Click to show the span");
+                                if response.clicked() {
+                                    clicked = Some(row_range.start + i);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+            if let Some(i) = clicked {
+                self.focus.jump_to(self.results[i].span.clone());
+                close = true;
+            }
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(Key::Escape)) {
+                close = true;
+            }
+        });
+        if close {
+            self.show = false;
+        }
+    }
+}
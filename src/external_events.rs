@@ -0,0 +1,253 @@
+//! Events from outside of the trace (deployments, alerts, chaos injections), imported from a CSV or
+//! JSON file and drawn as vertical markers across the span view, so that the trace can be compared
+//! with the schedule of an experiment.
+//!
+//! Both formats have the same fields:
+//! * `time` (or `timestamp`) - RFC 3339 date, or a unix timestamp in seconds, milliseconds,
+//!   microseconds or nanoseconds, the unit is guessed from the magnitude.
+//! * `label` (or `name`, `message`) - text drawn next to the marker.
+//! * `category` (or `type`), optional - events of the same category have the same color.
+//!
+//! CSV files need a header row. JSON files contain an array of objects, or an object with the array
+//! in the `events` field.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use eframe::egui::{self, Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, Ui};
+use serde_json::Value as JsonValue;
+
+use crate::colors::{node_color, theme};
+use crate::types::{TimeFormat, TimePoint};
+
+const TIME_FIELDS: [&str; 2] = ["time", "timestamp"];
+const LABEL_FIELDS: [&str; 3] = ["label", "name", "message"];
+const CATEGORY_FIELDS: [&str; 2] = ["category", "type"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalEvent {
+    pub time: TimePoint,
+    pub label: String,
+    pub category: Option<String>,
+}
+
+impl ExternalEvent {
+    pub fn color(&self) -> Color32 {
+        match &self.category {
+            Some(category) => node_color(category),
+            None => theme().accent,
+        }
+    }
+}
+
+/// Reads events from the file, the format is chosen by the extension. Events are sorted by time.
+pub fn load_external_events(path: &Path) -> Result<Vec<ExternalEvent>> {
+    let contents = std::fs::read_to_string(path)?;
+    let is_json = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("json") => true,
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => false,
+        _ => contents.trim_start().starts_with(['[', '{']),
+    };
+    if is_json {
+        parse_json_events(&contents)
+    } else {
+        parse_csv_events(&contents)
+    }
+}
+
+pub fn parse_csv_events(csv: &str) -> Result<Vec<ExternalEvent>> {
+    let csv = csv.strip_prefix('\u{feff}').unwrap_or(csv);
+    let mut rows = parse_csv(csv).into_iter();
+    let Some(header) = rows.next() else {
+        bail!("the file is empty");
+    };
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|h| names.iter().any(|name| h.trim().eq_ignore_ascii_case(name)))
+    };
+    let time_column = column(&TIME_FIELDS).context("no 'time' column in the header")?;
+    let label_column = column(&LABEL_FIELDS).context("no 'label' column in the header")?;
+    let category_column = column(&CATEGORY_FIELDS);
+
+    let mut events = Vec::new();
+    // The header is row 1.
+    for (row_number, row) in (2..).zip(rows) {
+        if row.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        let field = |column: usize| row.get(column).map_or("", |f| f.trim());
+        let time = parse_event_time(field(time_column))
+            .with_context(|| format!("invalid time in row {row_number}"))?;
+        events.push(ExternalEvent {
+            time,
+            label: field(label_column).to_string(),
+            category: category_column
+                .map(field)
+                .filter(|c| !c.is_empty())
+                .map(str::to_string),
+        });
+    }
+    events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(events)
+}
+
+pub fn parse_json_events(json: &str) -> Result<Vec<ExternalEvent>> {
+    let value: JsonValue = serde_json::from_str(json)?;
+    let items = match &value {
+        JsonValue::Array(items) => items,
+        JsonValue::Object(object) => match object.get("events") {
+            Some(JsonValue::Array(items)) => items,
+            _ => bail!("expected an array of events or an object with an 'events' array"),
+        },
+        _ => bail!("expected an array of events or an object with an 'events' array"),
+    };
+
+    let mut events = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let JsonValue::Object(object) = item else {
+            bail!("event {index} isn't an object");
+        };
+        let field = |names: &[&str]| names.iter().find_map(|name| object.get(*name));
+        let time = match field(&TIME_FIELDS) {
+            Some(JsonValue::Number(number)) => number.as_f64().map(timestamp_to_seconds),
+            Some(JsonValue::String(text)) => parse_event_time(text).ok(),
+            _ => None,
+        }
+        .with_context(|| format!("event {index} doesn't have a valid time"))?;
+        let text = |names: &[&str]| match field(names) {
+            Some(JsonValue::String(text)) => Some(text.clone()),
+            Some(JsonValue::Null) | None => None,
+            Some(other) => Some(other.to_string()),
+        };
+        events.push(ExternalEvent {
+            time,
+            label: text(&LABEL_FIELDS).unwrap_or_default(),
+            category: text(&CATEGORY_FIELDS).filter(|c| !c.is_empty()),
+        });
+    }
+    events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(events)
+}
+
+/// Parses an RFC 3339 date or a unix timestamp.
+pub fn parse_event_time(text: &str) -> Result<TimePoint> {
+    if let Ok(number) = text.parse::<f64>() {
+        if number.is_finite() {
+            return Ok(timestamp_to_seconds(number));
+        }
+    }
+    let date_time = chrono::DateTime::parse_from_rfc3339(text)
+        .with_context(|| format!("'{text}' is neither a unix timestamp nor an RFC 3339 date"))?;
+    Ok(date_time.timestamp() as f64 + f64::from(date_time.timestamp_subsec_nanos()) / 1e9)
+}
+
+/// Timestamps after 5138 AD in seconds are taken to be in a smaller unit.
+fn timestamp_to_seconds(timestamp: f64) -> TimePoint {
+    let mut seconds = timestamp;
+    while seconds.abs() >= 1e11 {
+        seconds /= 1000.0;
+    }
+    seconds
+}
+
+/// Splits CSV text into rows of fields. Quoted fields can contain commas, newlines and `""`.
+fn parse_csv(csv: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = csv.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => row.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+/// Vertical lines at the events, drawn over the spans.
+pub fn draw_external_event_lines(
+    ui: &mut Ui,
+    events: &[ExternalEvent],
+    start: TimePoint,
+    end: TimePoint,
+    area: Rect,
+) {
+    for event in visible_events(events, start, end) {
+        let x = time_to_x(event.time, start, end, area);
+        ui.painter().line_segment(
+            [Pos2::new(x, area.min.y), Pos2::new(x, area.max.y)],
+            Stroke::new(1.5, event.color()),
+        );
+    }
+}
+
+/// Labels of the events above the spans, hovering a label shows the time and the category.
+pub fn draw_external_event_labels(
+    ui: &mut Ui,
+    events: &[ExternalEvent],
+    start: TimePoint,
+    end: TimePoint,
+    area: Rect,
+    time_format: &TimeFormat,
+) {
+    let font_id = FontId::proportional(0.8 * egui::TextStyle::Body.resolve(ui.style()).size);
+    for (index, event) in visible_events(events, start, end).enumerate() {
+        let x = time_to_x(event.time, start, end, area);
+        ui.painter().line_segment(
+            [Pos2::new(x, area.min.y), Pos2::new(x, area.max.y)],
+            Stroke::new(1.5, event.color()),
+        );
+        let text_rect = ui.painter().text(
+            Pos2::new(x + 3.0, area.center().y),
+            Align2::LEFT_CENTER,
+            &event.label,
+            font_id.clone(),
+            event.color(),
+        );
+        let mut hover_text = format!(
+            "{}\n{}",
+            event.label,
+            time_format.display_string(event.time)
+        );
+        if let Some(category) = &event.category {
+            hover_text.push_str(&format!("\ncategory: {category}"));
+        }
+        ui.interact(
+            text_rect.union(Rect::from_x_y_ranges(x - 2.0..=x + 2.0, area.y_range())),
+            ui.id().with(("external event", index)),
+            Sense::hover(),
+        )
+        .on_hover_text(hover_text);
+    }
+}
+
+fn visible_events(
+    events: &[ExternalEvent],
+    start: TimePoint,
+    end: TimePoint,
+) -> impl Iterator<Item = &ExternalEvent> {
+    events
+        .iter()
+        .filter(move |event| event.time >= start && event.time <= end)
+}
+
+fn time_to_x(time: TimePoint, start: TimePoint, end: TimePoint, area: Rect) -> f32 {
+    area.min.x + ((time - start) / (end - start).max(f64::EPSILON)) as f32 * area.width()
+}
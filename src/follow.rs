@@ -0,0 +1,69 @@
+//! Follow mode, like `tail -f` for traces. The opened file is watched and reloaded when it changes,
+//! and the view is kept at the newest data.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::types::TimePoint;
+
+/// How often the followed file is checked for changes.
+pub const FOLLOW_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches a file by checking its modification time and size.
+#[derive(Debug)]
+pub struct FileWatcher {
+    path: PathBuf,
+    /// Modification time and size seen in the last check, `None` if the file couldn't be read.
+    last_seen: Option<(SystemTime, u64)>,
+    last_check: Instant,
+}
+
+impl FileWatcher {
+    /// Starts watching `path`, the current contents of the file count as seen.
+    pub fn new(path: &Path) -> FileWatcher {
+        FileWatcher {
+            path: path.to_path_buf(),
+            last_seen: file_version(path),
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file changed since the previous call. The file is checked at most once per
+    /// [FOLLOW_POLL_INTERVAL], calls in between return false.
+    pub fn poll(&mut self) -> bool {
+        if self.last_check.elapsed() < FOLLOW_POLL_INTERVAL {
+            return false;
+        }
+        self.check()
+    }
+
+    /// Checks the file now, regardless of when it was last checked.
+    pub fn check(&mut self) -> bool {
+        self.last_check = Instant::now();
+        let version = file_version(&self.path);
+        // A file which is being replaced can be briefly missing, wait until it's back.
+        if version.is_none() || version == self.last_seen {
+            return false;
+        }
+        self.last_seen = version;
+        true
+    }
+}
+
+fn file_version(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Selected range which ends at `newest` and is as wide as `selected_start..selected_end`.
+pub fn follow_range(
+    selected_start: TimePoint,
+    selected_end: TimePoint,
+    newest: TimePoint,
+) -> (TimePoint, TimePoint) {
+    (newest - (selected_end - selected_start), newest)
+}
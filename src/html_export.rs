@@ -0,0 +1,352 @@
+//! Export of the currently displayed spans to a self-contained HTML file.
+//! The file contains the span data as JSON and a small JS viewer which supports panning, zooming
+//! and hovering, so the view can be shared with people who don't run traviz.
+
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use serde::Serialize;
+
+use crate::span_id::SpanId;
+use crate::types::{time_point_to_utc_string, value_to_text, Span, TimePoint};
+
+/// A lane of spans, as displayed in traviz (one per node or one per span name).
+pub struct ExportLane {
+    pub name: String,
+    /// Top level spans of the lane, children are taken from `display_children`.
+    pub spans: Vec<Rc<Span>>,
+}
+
+#[derive(Serialize)]
+struct HtmlViewData {
+    title: String,
+    start: TimePoint,
+    end: TimePoint,
+    lanes: Vec<HtmlLane>,
+    arrows: Vec<HtmlArrow>,
+}
+
+#[derive(Serialize)]
+struct HtmlLane {
+    name: String,
+    rows: usize,
+    spans: Vec<HtmlSpan>,
+}
+
+#[derive(Serialize)]
+struct HtmlSpan {
+    id: String,
+    name: String,
+    /// Seconds relative to `HtmlViewData::start`
+    start: f64,
+    end: f64,
+    row: usize,
+    highlighted: bool,
+    tooltip: String,
+}
+
+#[derive(Serialize)]
+struct HtmlArrow {
+    from: String,
+    to: String,
+    label: String,
+}
+
+/// Spans of a lane which intersect the exported range, with the row of every span.
+pub struct LaidOutLane {
+    pub name: String,
+    pub rows: usize,
+    /// (span, row)
+    pub spans: Vec<(Rc<Span>, usize)>,
+}
+
+/// Places the spans of the lanes which intersect `start..end` in rows. Spans of each depth are
+/// placed below spans of the previous depth, overlapping spans of the same depth are put in
+/// separate rows. Lanes without such spans are skipped.
+pub fn layout_lanes(lanes: &[ExportLane], start: TimePoint, end: TimePoint) -> Vec<LaidOutLane> {
+    let mut laid_out_lanes = Vec::new();
+    for lane in lanes {
+        let mut spans = Vec::new();
+        let mut first_row_of_depth = 0;
+        let mut cur_level: Vec<Rc<Span>> = lane
+            .spans
+            .iter()
+            .filter(|s| s.max_end_time.get() >= start && s.min_start_time.get() <= end)
+            .cloned()
+            .collect();
+
+        while !cur_level.is_empty() {
+            cur_level.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+            let mut next_level = Vec::new();
+            let mut depth_rows: Vec<TimePoint> = Vec::new();
+            for span in &cur_level {
+                next_level.extend(span.display_children.borrow().iter().cloned());
+                if span.end_time < start || span.start_time > end {
+                    continue;
+                }
+                let row = match depth_rows
+                    .iter()
+                    .position(|row_end| *row_end <= span.start_time)
+                {
+                    Some(row) => row,
+                    None => {
+                        depth_rows.push(TimePoint::NEG_INFINITY);
+                        depth_rows.len() - 1
+                    }
+                };
+                depth_rows[row] = span.end_time;
+                spans.push((span.clone(), first_row_of_depth + row));
+            }
+            first_row_of_depth += depth_rows.len();
+            cur_level = next_level;
+        }
+
+        if spans.is_empty() {
+            continue;
+        }
+        laid_out_lanes.push(LaidOutLane {
+            name: lane.name.clone(),
+            rows: first_row_of_depth,
+            spans,
+        });
+    }
+    laid_out_lanes
+}
+
+/// Creates the HTML document with all spans from `lanes` which intersect `start..end`.
+/// Relations between exported spans are included as arrows.
+pub fn export_html(
+    title: &str,
+    lanes: &[ExportLane],
+    start: TimePoint,
+    end: TimePoint,
+    highlighted_span_ids: &HashSet<SpanId>,
+) -> String {
+    let mut html_lanes = Vec::new();
+    let mut exported_span_ids = HashSet::new();
+    let mut exported_spans = Vec::new();
+
+    for lane in layout_lanes(lanes, start, end) {
+        let mut html_spans = Vec::new();
+        for (span, row) in lane.spans {
+            exported_span_ids.insert(span.span_id);
+            html_spans.push(HtmlSpan {
+                id: span.span_id.to_hex(),
+                name: span.name.clone(),
+                start: span.start_time - start,
+                end: span.end_time - start,
+                row,
+                highlighted: highlighted_span_ids.contains(&span.span_id),
+                tooltip: span_tooltip(&span),
+            });
+            exported_spans.push(span);
+        }
+        html_lanes.push(HtmlLane {
+            name: lane.name,
+            rows: lane.rows,
+            spans: html_spans,
+        });
+    }
+
+    let mut arrows = Vec::new();
+    for span in &exported_spans {
+        for relation in span.outgoing_relations.borrow().iter() {
+            let (Some(from), Some(to)) = (relation.from_span.upgrade(), relation.to_span.upgrade())
+            else {
+                continue;
+            };
+            if !exported_span_ids.contains(&to.span_id) {
+                continue;
+            }
+            arrows.push(HtmlArrow {
+                from: from.span_id.to_hex(),
+                to: to.span_id.to_hex(),
+                label: relation.relation.name.clone(),
+            });
+        }
+    }
+
+    let data = HtmlViewData {
+        title: title.to_string(),
+        start,
+        end,
+        lanes: html_lanes,
+        arrows,
+    };
+    // "</" would end the script tag, escape it.
+    let data_json = serde_json::to_string(&data)
+        .expect("Serializing to string can't fail")
+        .replace("</", "<\\/");
+
+    HTML_TEMPLATE
+        .replace("{{TITLE}}", &escape_html(title))
+        .replace("{{DATA}}", &data_json)
+}
+
+pub(crate) fn span_tooltip(span: &Span) -> String {
+    let mut tooltip = format!(
+        "{}\nnode: {}\n{:.3} ms\n{} - {}\n",
+        span.name,
+        span.node.name,
+        (span.end_time - span.start_time) * 1000.0,
+        time_point_to_utc_string(span.start_time),
+        time_point_to_utc_string(span.end_time)
+    );
+    for (name, value) in &span.attributes {
+        tooltip.push_str(&format!("{}: {}\n", name, value_to_text(value)));
+    }
+    tooltip
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const HTML_TEMPLATE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{TITLE}}</title>
+<style>
+  body { margin: 0; background: #1e1e1e; color: #f0f0f0; font-family: sans-serif; overflow: hidden; }
+  #header { padding: 6px 10px; background: #3c3c46; font-size: 14px; }
+  #view { display: block; }
+  #tooltip { position: fixed; display: none; background: #f0f0f0; color: #000; padding: 6px;
+             font-size: 12px; white-space: pre; pointer-events: none; border: 1px solid #444; }
+</style>
+</head>
+<body>
+<div id="header">{{TITLE}} &mdash; drag to pan, scroll to zoom, hover for details</div>
+<canvas id="view"></canvas>
+<div id="tooltip"></div>
+<script>
+const data = {{DATA}};
+const canvas = document.getElementById("view");
+const ctx = canvas.getContext("2d");
+const tooltip = document.getElementById("tooltip");
+const laneNameWidth = 150, rowHeight = 18, rowMargin = 2, rulerHeight = 30;
+let viewStart = 0, viewEnd = Math.max(data.end - data.start, 1e-9);
+let scrollY = 0;
+const boxes = [];
+
+function timeToX(t) {
+  return laneNameWidth + (t - viewStart) / (viewEnd - viewStart) * (canvas.width - laneNameWidth);
+}
+function xToTime(x) {
+  return viewStart + (x - laneNameWidth) / (canvas.width - laneNameWidth) * (viewEnd - viewStart);
+}
+
+function draw() {
+  canvas.width = window.innerWidth;
+  canvas.height = window.innerHeight - document.getElementById("header").offsetHeight;
+  ctx.fillStyle = "#1e1e1e";
+  ctx.fillRect(0, 0, canvas.width, canvas.height);
+  ctx.font = "12px sans-serif";
+  ctx.textBaseline = "middle";
+  boxes.length = 0;
+  const positions = {};
+
+  let y = rulerHeight - scrollY;
+  for (const lane of data.lanes) {
+    const laneHeight = Math.max(lane.rows, 1) * (rowHeight + rowMargin);
+    for (const span of lane.spans) {
+      const x1 = Math.max(timeToX(span.start), laneNameWidth);
+      const x2 = Math.max(timeToX(span.end), x1 + 1);
+      if (x2 < laneNameWidth || x1 > canvas.width) continue;
+      const top = y + span.row * (rowHeight + rowMargin);
+      ctx.fillStyle = span.highlighted ? "#3296dc" : "#f2b022";
+      ctx.fillRect(x1, top, x2 - x1, rowHeight);
+      if (x2 - x1 > 20) {
+        ctx.save();
+        ctx.beginPath();
+        ctx.rect(x1, top, x2 - x1, rowHeight);
+        ctx.clip();
+        ctx.fillStyle = "#000";
+        ctx.fillText(span.name, x1 + 2, top + rowHeight / 2);
+        ctx.restore();
+      }
+      boxes.push({ x1, x2, y1: top, y2: top + rowHeight, span });
+      positions[span.id] = { start: [x1, top + rowHeight / 2], end: [x2, top + rowHeight / 2] };
+    }
+    ctx.fillStyle = "#0a0a14";
+    ctx.fillRect(0, y, laneNameWidth, laneHeight);
+    ctx.fillStyle = "#f0f0f0";
+    ctx.fillText(lane.name, 4, y + Math.min(laneHeight, rowHeight) / 2);
+    y += laneHeight;
+    ctx.strokeStyle = "#e6e6e6";
+    ctx.beginPath();
+    ctx.moveTo(0, y);
+    ctx.lineTo(canvas.width, y);
+    ctx.stroke();
+  }
+
+  ctx.strokeStyle = "#3296dc";
+  ctx.fillStyle = "#3296dc";
+  ctx.lineWidth = 2;
+  for (const arrow of data.arrows) {
+    const from = positions[arrow.from], to = positions[arrow.to];
+    if (!from || !to) continue;
+    ctx.beginPath();
+    ctx.moveTo(from.end[0], from.end[1]);
+    ctx.lineTo(to.start[0], to.start[1]);
+    ctx.stroke();
+    ctx.fillText(arrow.label, (from.end[0] + to.start[0]) / 2, (from.end[1] + to.start[1]) / 2 - 8);
+  }
+  ctx.lineWidth = 1;
+
+  ctx.fillStyle = "#3c3c46";
+  ctx.fillRect(0, 0, canvas.width, rulerHeight);
+  ctx.fillStyle = "#f0f0f0";
+  for (let x = laneNameWidth; x < canvas.width; x += 150) {
+    ctx.fillRect(x, 0, 1, rulerHeight);
+    ctx.fillText(xToTime(x).toFixed(3) + " s", x + 3, rulerHeight / 2);
+  }
+}
+
+let dragging = null;
+canvas.addEventListener("mousedown", e => { dragging = { x: e.clientX, y: e.clientY }; });
+window.addEventListener("mouseup", () => { dragging = null; });
+canvas.addEventListener("mousemove", e => {
+  if (dragging) {
+    const dt = (e.clientX - dragging.x) / (canvas.width - laneNameWidth) * (viewEnd - viewStart);
+    viewStart -= dt;
+    viewEnd -= dt;
+    scrollY = Math.max(0, scrollY - (e.clientY - dragging.y));
+    dragging = { x: e.clientX, y: e.clientY };
+    draw();
+    return;
+  }
+  const rect = canvas.getBoundingClientRect();
+  const x = e.clientX - rect.left, y = e.clientY - rect.top;
+  const box = boxes.find(b => x >= b.x1 && x <= b.x2 && y >= b.y1 && y <= b.y2);
+  if (box) {
+    tooltip.textContent = box.span.tooltip;
+    tooltip.style.display = "block";
+    tooltip.style.left = (e.clientX + 12) + "px";
+    tooltip.style.top = (e.clientY + 12) + "px";
+  } else {
+    tooltip.style.display = "none";
+  }
+});
+canvas.addEventListener("wheel", e => {
+  e.preventDefault();
+  if (e.shiftKey) {
+    scrollY = Math.max(0, scrollY + e.deltaY);
+  } else {
+    const rect = canvas.getBoundingClientRect();
+    const mouseTime = xToTime(e.clientX - rect.left);
+    const factor = e.deltaY > 0 ? 1.2 : 1 / 1.2;
+    viewStart = mouseTime - (mouseTime - viewStart) * factor;
+    viewEnd = mouseTime + (viewEnd - mouseTime) * factor;
+  }
+  draw();
+}, { passive: false });
+window.addEventListener("resize", draw);
+draw();
+</script>
+</body>
+</html>
+"##;
@@ -0,0 +1,167 @@
+//! Export of the span view as an image. SVG images are drawn from the span data, so they can show
+//! the whole selected range at any width, not only the part which fits on the screen. PNG images are
+//! screenshots of the spans area as it is displayed.
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use eframe::egui::{Color32, ColorImage, Pos2, Rect};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::colors::theme;
+use crate::html_export::{escape_html, layout_lanes, span_tooltip, ExportLane};
+use crate::span_id::SpanId;
+use crate::types::{time_point_to_utc_string, TimePoint};
+
+/// Default width of exported SVG images, in pixels.
+pub const DEFAULT_SVG_WIDTH: u32 = 2000;
+const SVG_HEADER_HEIGHT: f64 = 40.0;
+const SVG_LANE_NAME_HEIGHT: f64 = 20.0;
+const SVG_ROW_HEIGHT: f64 = 16.0;
+const SVG_LANE_GAP: f64 = 10.0;
+/// Approximate width of a character of the span names, names which don't fit are left out.
+const SVG_CHAR_WIDTH: f64 = 6.5;
+
+fn color_hex(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+/// Draws all spans from `lanes` which intersect `start..end` as an SVG image `width` pixels wide.
+/// The lanes are stacked vertically, like in the span view. Hovering a span shows its details.
+pub fn export_svg(
+    title: &str,
+    lanes: &[ExportLane],
+    start: TimePoint,
+    end: TimePoint,
+    width: u32,
+    highlighted_span_ids: &HashSet<SpanId>,
+) -> String {
+    let width = f64::from(width.max(100));
+    let duration = (end - start).max(f64::EPSILON);
+    let to_x = |time: TimePoint| ((time - start) / duration * width).clamp(0.0, width);
+
+    let theme = theme();
+    let mut body = String::new();
+    let mut y = SVG_HEADER_HEIGHT;
+    for lane in layout_lanes(lanes, start, end) {
+        body.push_str(&format!(
+            "<text x=\"4\" y=\"{:.1}\" font-size=\"13\" font-weight=\"bold\" fill=\"{}\">{}</text>\n",
+            y + 14.0,
+            color_hex(theme.text),
+            escape_html(&lane.name)
+        ));
+        let rows_top = y + SVG_LANE_NAME_HEIGHT;
+        for (span, row) in &lane.spans {
+            let x = to_x(span.start_time);
+            let span_width = (to_x(span.end_time) - x).max(1.0);
+            let span_y = rows_top + *row as f64 * SVG_ROW_HEIGHT;
+            let fill = if highlighted_span_ids.contains(&span.span_id) {
+                theme.highlighted_span_time
+            } else {
+                theme.span_time
+            };
+            body.push_str(&format!(
+                "<g><title>{}</title><rect x=\"{x:.1}\" y=\"{span_y:.1}\" width=\"{span_width:.1}\" height=\"{:.1}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"0.5\"/>",
+                escape_html(&span_tooltip(span)),
+                SVG_ROW_HEIGHT - 2.0,
+                color_hex(fill),
+                color_hex(theme.spans_background),
+            ));
+            if span_width >= span.name.chars().count() as f64 * SVG_CHAR_WIDTH + 4.0 {
+                body.push_str(&format!(
+                    "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"11\" fill=\"{}\">{}</text>",
+                    x + 2.0,
+                    span_y + SVG_ROW_HEIGHT - 5.0,
+                    color_hex(theme.span_text),
+                    escape_html(&span.name)
+                ));
+            }
+            body.push_str("</g>\n");
+        }
+        y = rows_top + lane.rows as f64 * SVG_ROW_HEIGHT + SVG_LANE_GAP;
+    }
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{y:.0}\" font-family=\"sans-serif\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n\
+         <text x=\"4\" y=\"16\" font-size=\"14\" fill=\"{}\">{}</text>\n\
+         <text x=\"4\" y=\"32\" font-size=\"11\" fill=\"{}\">{} - {}</text>\n\
+         {body}</svg>\n",
+        color_hex(theme.spans_background),
+        color_hex(theme.text),
+        escape_html(title),
+        color_hex(theme.weak_text),
+        time_point_to_utc_string(start),
+        time_point_to_utc_string(end),
+    )
+}
+
+/// Cuts `area` (in points) out of a screenshot, the part outside of the screenshot is left out.
+pub fn crop_screenshot(image: &ColorImage, area: Rect, pixels_per_point: f32) -> ColorImage {
+    let image_rect = Rect::from_min_max(
+        Pos2::ZERO,
+        Pos2::new(
+            image.size[0] as f32 / pixels_per_point,
+            image.size[1] as f32 / pixels_per_point,
+        ),
+    );
+    let area = area.intersect(image_rect);
+    if area.width() <= 0.0 || area.height() <= 0.0 {
+        return ColorImage::new([0, 0], Color32::TRANSPARENT);
+    }
+    image.region(&area, Some(pixels_per_point))
+}
+
+/// Encodes the image as an RGBA PNG file.
+pub fn encode_png(image: &ColorImage) -> Vec<u8> {
+    let [width, height] = image.size;
+    // Every row of pixels starts with the filter type, 0 is no filter.
+    let mut raw = Vec::with_capacity((width * 4 + 1) * height);
+    for row in image.pixels.chunks(width.max(1)) {
+        raw.push(0);
+        for pixel in row {
+            raw.extend_from_slice(&pixel.to_srgba_unmultiplied());
+        }
+    }
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(&raw)
+        .expect("Writing to a Vec can't fail");
+    let data = encoder.finish().expect("Writing to a Vec can't fail");
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // Bit depth 8, color type RGBA, default compression, filtering and no interlacing
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_png_chunk(&mut png, b"IHDR", &header);
+    write_png_chunk(&mut png, b"IDAT", &data);
+    write_png_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_png_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    png.extend_from_slice(&crc32(chunk_type.iter().chain(data)).to_be_bytes());
+}
+
+/// CRC-32 used by PNG chunks.
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
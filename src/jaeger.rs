@@ -0,0 +1,252 @@
+//! Support for traces exported from Jaeger (`jaeger-query` API or the UI "Download JSON" button).
+//! The Jaeger format is converted into OTLP `ExportTraceServiceRequest`s, so that the rest of
+//! traviz can treat it the same way as traces downloaded from the tracing collector.
+//! Jaeger processes become resources (nodes), `CHILD_OF`/`FOLLOWS_FROM` references become parent
+//! links and logs become events.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use opentelemetry_proto::tonic::common::v1::any_value::Value;
+use opentelemetry_proto::tonic::common::v1::{AnyValue, KeyValue};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use opentelemetry_proto::tonic::trace::v1::{span, ResourceSpans, ScopeSpans, Span};
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct JaegerExport {
+    pub data: Vec<JaegerTrace>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JaegerTrace {
+    #[serde(rename = "traceID")]
+    pub trace_id: String,
+    pub spans: Vec<JaegerSpan>,
+    #[serde(default)]
+    pub processes: BTreeMap<String, JaegerProcess>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JaegerSpan {
+    #[serde(rename = "traceID")]
+    pub trace_id: String,
+    #[serde(rename = "spanID")]
+    pub span_id: String,
+    pub operation_name: String,
+    #[serde(default)]
+    pub references: Vec<JaegerReference>,
+    /// Microseconds since epoch
+    pub start_time: u64,
+    /// Microseconds
+    pub duration: u64,
+    #[serde(default)]
+    pub tags: Vec<JaegerTag>,
+    #[serde(default)]
+    pub logs: Vec<JaegerLog>,
+    #[serde(rename = "processID", default)]
+    pub process_id: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JaegerReference {
+    pub ref_type: String,
+    #[serde(rename = "traceID")]
+    pub trace_id: String,
+    #[serde(rename = "spanID")]
+    pub span_id: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JaegerProcess {
+    pub service_name: String,
+    #[serde(default)]
+    pub tags: Vec<JaegerTag>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct JaegerTag {
+    pub key: String,
+    #[serde(rename = "type", default)]
+    pub value_type: String,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct JaegerLog {
+    /// Microseconds since epoch
+    pub timestamp: u64,
+    #[serde(default)]
+    pub fields: Vec<JaegerTag>,
+}
+
+/// Returns true if the file looks like a Jaeger JSON export.
+/// OTLP dumps from the collector are JSON arrays, Jaeger exports are objects with a `data` field.
+pub fn is_jaeger_json(file_str: &str) -> bool {
+    file_str.trim_start().starts_with('{')
+}
+
+/// Parse a Jaeger JSON export and convert it to OTLP requests.
+pub fn parse_jaeger_json(file_str: &str) -> Result<Vec<ExportTraceServiceRequest>> {
+    let export: JaegerExport = serde_json::from_str(file_str)?;
+    jaeger_to_otlp(&export)
+}
+
+/// Convert a Jaeger export to OTLP requests. Each Jaeger trace is converted to one request,
+/// with one `ResourceSpans` per process.
+pub fn jaeger_to_otlp(export: &JaegerExport) -> Result<Vec<ExportTraceServiceRequest>> {
+    let mut requests = Vec::with_capacity(export.data.len());
+
+    for trace in &export.data {
+        let mut spans_by_process: BTreeMap<&str, Vec<Span>> = BTreeMap::new();
+        for jaeger_span in &trace.spans {
+            spans_by_process
+                .entry(jaeger_span.process_id.as_str())
+                .or_default()
+                .push(convert_span(jaeger_span)?);
+        }
+
+        let mut resource_spans = Vec::with_capacity(spans_by_process.len());
+        for (process_id, spans) in spans_by_process {
+            let resource = match trace.processes.get(process_id) {
+                Some(process) => {
+                    let mut attributes = vec![KeyValue {
+                        key: "service.name".to_string(),
+                        value: Some(AnyValue {
+                            value: Some(Value::StringValue(process.service_name.clone())),
+                        }),
+                    }];
+                    attributes.extend(process.tags.iter().map(convert_tag));
+                    Resource {
+                        attributes,
+                        ..Default::default()
+                    }
+                }
+                None => Resource::default(),
+            };
+
+            resource_spans.push(ResourceSpans {
+                resource: Some(resource),
+                scope_spans: vec![ScopeSpans {
+                    scope: None,
+                    spans,
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            });
+        }
+
+        requests.push(ExportTraceServiceRequest { resource_spans });
+    }
+
+    Ok(requests)
+}
+
+fn convert_span(jaeger_span: &JaegerSpan) -> Result<Span> {
+    // Jaeger allows multiple references, the first CHILD_OF is the parent. If there is none,
+    // a FOLLOWS_FROM reference is the best approximation we have.
+    let parent_reference = jaeger_span
+        .references
+        .iter()
+        .find(|r| r.ref_type == "CHILD_OF")
+        .or_else(|| {
+            jaeger_span
+                .references
+                .iter()
+                .find(|r| r.ref_type == "FOLLOWS_FROM")
+        });
+    let parent_span_id = match parent_reference {
+        Some(reference) => decode_id(&reference.span_id)?,
+        None => Vec::new(),
+    };
+
+    let start_time_unix_nano = micros_to_nanos(jaeger_span, jaeger_span.start_time)?;
+    let end_time_unix_nano = jaeger_span
+        .start_time
+        .checked_add(jaeger_span.duration)
+        .ok_or_else(|| time_overflow_error(jaeger_span))
+        .and_then(|end_time| micros_to_nanos(jaeger_span, end_time))?;
+
+    let events = jaeger_span
+        .logs
+        .iter()
+        .map(|log| {
+            let name = log
+                .fields
+                .iter()
+                .find(|f| f.key == "event" || f.key == "message")
+                .map(|f| tag_value_to_string(&f.value))
+                .unwrap_or_else(|| "log".to_string());
+            Ok(span::Event {
+                time_unix_nano: micros_to_nanos(jaeger_span, log.timestamp)?,
+                name,
+                attributes: log.fields.iter().map(convert_tag).collect(),
+                dropped_attributes_count: 0,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Span {
+        trace_id: decode_id(&jaeger_span.trace_id)?,
+        span_id: decode_id(&jaeger_span.span_id)?,
+        parent_span_id,
+        name: jaeger_span.operation_name.clone(),
+        start_time_unix_nano,
+        end_time_unix_nano,
+        attributes: jaeger_span.tags.iter().map(convert_tag).collect(),
+        events,
+        ..Default::default()
+    })
+}
+
+/// Jaeger times are in microseconds, OTLP ones in nanoseconds. Times which don't fit into u64
+/// nanoseconds can only come from a corrupted file.
+fn micros_to_nanos(jaeger_span: &JaegerSpan, micros: u64) -> Result<u64> {
+    micros
+        .checked_mul(1000)
+        .ok_or_else(|| time_overflow_error(jaeger_span))
+}
+
+fn time_overflow_error(jaeger_span: &JaegerSpan) -> anyhow::Error {
+    anyhow!(
+        "Time of Jaeger span '{}' ({}) is out of range",
+        jaeger_span.operation_name,
+        jaeger_span.span_id
+    )
+}
+
+/// Jaeger ids are hex strings, sometimes without leading zeros.
+fn decode_id(id: &str) -> Result<Vec<u8>> {
+    let padded = if id.len() % 2 == 1 {
+        format!("0{id}")
+    } else {
+        id.to_string()
+    };
+    hex::decode(&padded).map_err(|e| anyhow!("Invalid Jaeger id '{}': {}", id, e))
+}
+
+fn convert_tag(tag: &JaegerTag) -> KeyValue {
+    let value = match (tag.value_type.as_str(), &tag.value) {
+        ("bool", serde_json::Value::Bool(b)) => Value::BoolValue(*b),
+        ("int64", serde_json::Value::Number(n)) if n.is_i64() => {
+            Value::IntValue(n.as_i64().unwrap())
+        }
+        ("float64", serde_json::Value::Number(n)) => Value::DoubleValue(n.as_f64().unwrap_or(0.0)),
+        (_, v) => Value::StringValue(tag_value_to_string(v)),
+    };
+    KeyValue {
+        key: tag.key.clone(),
+        value: Some(AnyValue { value: Some(value) }),
+    }
+}
+
+fn tag_value_to_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
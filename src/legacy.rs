@@ -1,9 +1,20 @@
 //! Old code that is being kept for backwards compatibility.
 
+use std::collections::BTreeMap;
+
 use uuid::Uuid;
 
-use crate::relation::{AttributeRelation, MatchType, Relation, RelationNodesConfig};
-use crate::structured_modes::SpanSelector;
+use crate::macros::KeyMacro;
+use crate::node_filter::NodeFilter;
+use crate::persistent::PersistentDataV8;
+use crate::preferences::Preferences;
+use crate::profiles::{Profile, DEFAULT_PROFILE_NAME};
+use crate::recent_files::RecentFile;
+use crate::relation::{
+    ArrowStyle, AttributeRelation, MatchType, Relation, RelationNodesConfig, RelationView,
+};
+use crate::structured_modes::{SpanSelector, StructuredMode};
+use crate::time_markers::{default_time_marker_rules, TimeMarkerRule};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct RelationV0 {
@@ -34,7 +45,174 @@ impl From<RelationV0> for Relation {
             nodes_config: relation.nodes_config,
             match_type: relation.match_type,
             min_time_diff: 0.0,
+            required_sources: 1,
+            report_unmatched: false,
+            arrow_style: ArrowStyle::default(),
             is_builtin: relation.is_builtin,
         }
     }
 }
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PersistentDataV1 {
+    pub(crate) display_modes: Vec<StructuredMode>,
+    pub(crate) node_filters: Vec<NodeFilter>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PersistentDataV2 {
+    pub(crate) display_modes: Vec<StructuredMode>,
+    pub(crate) node_filters: Vec<NodeFilter>,
+    pub(crate) relations: Vec<RelationV0>,
+    pub(crate) relation_views: Vec<RelationView>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PersistentDataV3 {
+    pub(crate) display_modes: Vec<StructuredMode>,
+    pub(crate) node_filters: Vec<NodeFilter>,
+    pub(crate) relations: Vec<Relation>,
+    pub(crate) relation_views: Vec<RelationView>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PersistentDataV4 {
+    pub(crate) display_modes: Vec<StructuredMode>,
+    pub(crate) node_filters: Vec<NodeFilter>,
+    pub(crate) relations: Vec<Relation>,
+    pub(crate) relation_views: Vec<RelationView>,
+    pub(crate) preferences: Preferences,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PersistentDataV5 {
+    pub(crate) display_modes: Vec<StructuredMode>,
+    pub(crate) node_filters: Vec<NodeFilter>,
+    pub(crate) relations: Vec<Relation>,
+    pub(crate) relation_views: Vec<RelationView>,
+    pub(crate) preferences: Preferences,
+    pub(crate) macros: Vec<KeyMacro>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PersistentDataV6 {
+    pub(crate) display_modes: Vec<StructuredMode>,
+    pub(crate) node_filters: Vec<NodeFilter>,
+    pub(crate) relations: Vec<Relation>,
+    pub(crate) relation_views: Vec<RelationView>,
+    pub(crate) preferences: Preferences,
+    pub(crate) macros: Vec<KeyMacro>,
+    pub(crate) recent_files: Vec<RecentFile>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PersistentDataV7 {
+    pub(crate) display_modes: Vec<StructuredMode>,
+    pub(crate) node_filters: Vec<NodeFilter>,
+    pub(crate) relations: Vec<Relation>,
+    pub(crate) relation_views: Vec<RelationView>,
+    pub(crate) preferences: Preferences,
+    pub(crate) macros: Vec<KeyMacro>,
+    pub(crate) recent_files: Vec<RecentFile>,
+    pub(crate) time_marker_rules: Vec<TimeMarkerRule>,
+}
+
+// Migrations between consecutive versions of the persistent data. Each step only has to fill in
+// what was added in the next version, `PersistentData::migrate` chains them.
+
+impl From<PersistentDataV1> for PersistentDataV2 {
+    fn from(data: PersistentDataV1) -> Self {
+        PersistentDataV2 {
+            display_modes: data.display_modes,
+            node_filters: data.node_filters,
+            relations: Vec::new(),
+            relation_views: Vec::new(),
+        }
+    }
+}
+
+impl From<PersistentDataV2> for PersistentDataV3 {
+    fn from(data: PersistentDataV2) -> Self {
+        PersistentDataV3 {
+            display_modes: data.display_modes,
+            node_filters: data.node_filters,
+            relations: data.relations.into_iter().map(RelationV0::into).collect(),
+            relation_views: data.relation_views,
+        }
+    }
+}
+
+impl From<PersistentDataV3> for PersistentDataV4 {
+    fn from(data: PersistentDataV3) -> Self {
+        PersistentDataV4 {
+            display_modes: data.display_modes,
+            node_filters: data.node_filters,
+            relations: data.relations,
+            relation_views: data.relation_views,
+            preferences: Preferences::default(),
+        }
+    }
+}
+
+impl From<PersistentDataV4> for PersistentDataV5 {
+    fn from(data: PersistentDataV4) -> Self {
+        PersistentDataV5 {
+            display_modes: data.display_modes,
+            node_filters: data.node_filters,
+            relations: data.relations,
+            relation_views: data.relation_views,
+            preferences: data.preferences,
+            macros: Vec::new(),
+        }
+    }
+}
+
+impl From<PersistentDataV5> for PersistentDataV6 {
+    fn from(data: PersistentDataV5) -> Self {
+        PersistentDataV6 {
+            display_modes: data.display_modes,
+            node_filters: data.node_filters,
+            relations: data.relations,
+            relation_views: data.relation_views,
+            preferences: data.preferences,
+            macros: data.macros,
+            recent_files: Vec::new(),
+        }
+    }
+}
+
+impl From<PersistentDataV6> for PersistentDataV7 {
+    fn from(data: PersistentDataV6) -> Self {
+        PersistentDataV7 {
+            display_modes: data.display_modes,
+            node_filters: data.node_filters,
+            relations: data.relations,
+            relation_views: data.relation_views,
+            preferences: data.preferences,
+            macros: data.macros,
+            recent_files: data.recent_files,
+            time_marker_rules: default_time_marker_rules(),
+        }
+    }
+}
+
+impl From<PersistentDataV7> for PersistentDataV8 {
+    fn from(data: PersistentDataV7) -> Self {
+        PersistentDataV8 {
+            profiles: vec![Profile {
+                name: DEFAULT_PROFILE_NAME.to_string(),
+                display_modes: data.display_modes,
+                node_filters: data.node_filters,
+                relations: data.relations,
+                relation_views: data.relation_views,
+                time_marker_rules: data.time_marker_rules,
+            }],
+            current_profile: DEFAULT_PROFILE_NAME.to_string(),
+            preferences: data.preferences,
+            macros: data.macros,
+            recent_files: data.recent_files,
+            dependency_presets: Vec::new(),
+            clock_offsets: BTreeMap::new(),
+        }
+    }
+}
@@ -1,20 +1,85 @@
 pub mod analyze_dependency;
+pub mod analyze_relations;
 pub mod analyze_span;
 pub mod analyze_utils;
+pub mod arrange;
+pub mod attribute_facets;
+pub mod attribute_table;
+pub mod background_task;
+pub mod breadcrumb;
 pub mod builtin_relations;
+pub mod charts;
+pub mod child_breakdown;
+pub mod clipboard;
+pub mod clock_offsets;
+pub mod collapse;
 pub mod colors;
+pub mod concurrency;
+pub mod correlation;
+pub mod dependency_export;
+pub mod dependency_link_table;
+pub mod depth_limit;
+pub mod duration_filter;
+pub mod edit_macros;
 pub mod edit_modes;
+pub mod edit_preferences;
+pub mod edit_profiles;
 pub mod edit_relations;
+pub mod edit_time_markers;
+pub mod event_search;
+pub mod external_events;
+pub mod follow;
+pub mod html_export;
+pub mod image_export;
+pub mod jaeger;
 pub mod legacy;
+pub mod log_viewer;
+pub mod logging;
+pub mod macros;
+pub mod metrics;
 pub mod modes;
 pub mod node_filter;
+pub mod node_health;
+pub mod node_logs;
+pub mod notifications;
+pub mod outliers;
 pub mod persistent;
+pub mod preferences;
+pub mod profiles;
 #[cfg(feature = "profiling")]
 pub mod profiling;
+pub mod recent_files;
+pub mod regions;
 pub mod relation;
+pub mod relation_arrows;
+pub mod relation_flow;
+pub mod remote_file;
+pub mod remote_source;
+pub mod reorder;
+pub mod report;
+pub mod scope_filter;
+pub mod search;
+pub mod skew;
+pub mod source_location;
+pub mod span_catalog;
+pub mod span_id;
+pub mod span_index;
+pub mod span_overlay;
+pub mod span_selection;
+pub mod sticky_header;
 pub mod structured_modes;
 pub mod task_timer;
+pub mod throughput;
+pub mod time_markers;
+pub mod time_scrollbar;
+pub mod trace_focus;
+pub mod trace_lint;
+pub mod trace_stats;
 pub mod types;
+pub mod undo;
+pub mod unmatched_relations;
+pub mod utilization;
+pub mod view_link;
 
 pub use analyze_dependency::{AnalyzeDependencyModal, DependencyAnalysisResult, DependencyLink};
 pub use types::{Node, Span, TimePoint};
@@ -0,0 +1,212 @@
+//! Panel which shows the records collected by the logger, with level filtering and text search.
+//! It can be docked at the bottom or at the right side of the window.
+
+use eframe::egui::{self, ComboBox, Label, RichText, ScrollArea, TextEdit, TextStyle, Ui};
+use log::Level;
+
+use crate::colors::theme;
+use crate::logging::{self, LogRecord};
+
+/// The oldest records are dropped after this many.
+const MAX_RECORDS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogDock {
+    Bottom,
+    Right,
+}
+
+pub struct LogViewer {
+    show: bool,
+    dock: LogDock,
+    records: Vec<LogRecord>,
+    /// Records at this level or more severe are shown.
+    level: Level,
+    search: String,
+}
+
+impl Default for LogViewer {
+    fn default() -> Self {
+        LogViewer {
+            show: false,
+            dock: LogDock::Bottom,
+            records: Vec::new(),
+            level: Level::Info,
+            search: String::new(),
+        }
+    }
+}
+
+impl LogViewer {
+    pub fn new() -> LogViewer {
+        LogViewer::default()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.show
+    }
+
+    pub fn toggle(&mut self) {
+        self.show = !self.show;
+    }
+
+    pub fn set_level(&mut self, level: Level) {
+        self.level = level;
+    }
+
+    pub fn set_search(&mut self, search: impl Into<String>) {
+        self.search = search.into();
+    }
+
+    /// Collects the records logged since the last poll. Has to be called every frame, also when the
+    /// panel is closed, otherwise the records would pile up in the logger.
+    pub fn poll(&mut self) {
+        self.add_records(logging::take_records());
+    }
+
+    pub fn add_records(&mut self, records: Vec<LogRecord>) {
+        self.records.extend(records);
+        if self.records.len() > MAX_RECORDS {
+            let excess = self.records.len() - MAX_RECORDS;
+            self.records.drain(..excess);
+        }
+    }
+
+    pub fn records(&self) -> &[LogRecord] {
+        &self.records
+    }
+
+    /// Records which pass the level filter and contain the search text in the message or target
+    /// (case insensitive).
+    pub fn filtered_records(&self) -> Vec<&LogRecord> {
+        let search = self.search.to_lowercase();
+        self.records
+            .iter()
+            .filter(|r| r.level <= self.level)
+            .filter(|r| {
+                search.is_empty()
+                    || r.message.to_lowercase().contains(&search)
+                    || r.target.to_lowercase().contains(&search)
+            })
+            .collect()
+    }
+
+    /// Draws the panel, has to be called before the central panel is shown.
+    pub fn draw(&mut self, ctx: &egui::Context) {
+        if !self.show {
+            return;
+        }
+        match self.dock {
+            LogDock::Bottom => {
+                egui::TopBottomPanel::bottom("log viewer")
+                    .resizable(true)
+                    .default_height(200.0)
+                    .show(ctx, |ui| self.draw_contents(ui));
+            }
+            LogDock::Right => {
+                egui::SidePanel::right("log viewer")
+                    .resizable(true)
+                    .default_width(500.0)
+                    .show(ctx, |ui| self.draw_contents(ui));
+            }
+        }
+    }
+
+    fn draw_contents(&mut self, ui: &mut Ui) {
+        ui.horizontal_wrapped(|ui| {
+            ui.strong("Log");
+            ComboBox::new("log level", "")
+                .selected_text(format!("Level: {}", self.level))
+                .show_ui(ui, |ui| {
+                    for level in [
+                        Level::Error,
+                        Level::Warn,
+                        Level::Info,
+                        Level::Debug,
+                        Level::Trace,
+                    ] {
+                        ui.selectable_value(&mut self.level, level, level.as_str());
+                    }
+                });
+            ui.add(
+                TextEdit::singleline(&mut self.search)
+                    .hint_text("Search")
+                    .desired_width(200.0),
+            );
+        });
+
+        let filtered = self.filtered_records();
+        let mut copy_text = None;
+        let mut clear = false;
+        let mut close = false;
+        let mut dock = self.dock;
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} of {} records",
+                filtered.len(),
+                self.records.len()
+            ));
+            if ui
+                .button("Copy")
+                .on_hover_text("Copy the shown records")
+                .clicked()
+            {
+                copy_text = Some(
+                    filtered
+                        .iter()
+                        .map(|r| r.to_line())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                );
+            }
+            if ui.button("Clear").clicked() {
+                clear = true;
+            }
+            ui.selectable_value(&mut dock, LogDock::Bottom, "Dock bottom");
+            ui.selectable_value(&mut dock, LogDock::Right, "Dock right");
+            if ui.button("Close").clicked() {
+                close = true;
+            }
+        });
+        ui.separator();
+
+        let row_height = ui.text_style_height(&TextStyle::Monospace);
+        ScrollArea::both()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show_rows(ui, row_height, filtered.len(), |ui, row_range| {
+                for record in &filtered[row_range] {
+                    ui.horizontal(|ui| {
+                        ui.monospace(record.time.format("%H:%M:%S%.3f").to_string());
+                        ui.label(
+                            RichText::new(format!("{:<5}", record.level))
+                                .monospace()
+                                .color(level_color(record.level)),
+                        );
+                        ui.label(RichText::new(&record.target).monospace().weak());
+                        ui.add(Label::new(RichText::new(&record.message).monospace()).extend());
+                    });
+                }
+            });
+
+        if let Some(text) = copy_text {
+            ui.ctx().copy_text(text);
+        }
+        if clear {
+            self.records.clear();
+        }
+        if close {
+            self.show = false;
+        }
+        self.dock = dock;
+    }
+}
+
+fn level_color(level: Level) -> egui::Color32 {
+    match level {
+        Level::Error => theme().error,
+        Level::Warn => theme().warning,
+        Level::Info => theme().text,
+        Level::Debug | Level::Trace => theme().link,
+    }
+}
@@ -0,0 +1,103 @@
+//! Logger for the `log` macros used throughout the crate.
+//! Records are printed to stderr and kept in memory, so that they can be browsed in the log viewer.
+//!
+//! Messages from traviz are recorded down to the `Debug` level, messages from dependencies (egui,
+//! winit, ...) only when they're warnings or errors. The level for traviz messages can be changed
+//! with the `TRAVIZ_LOG` environment variable, e.g. `TRAVIZ_LOG=trace`.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Local};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// The oldest records are dropped when there's more than this many records waiting to be taken.
+const MAX_PENDING_RECORDS: usize = 10_000;
+
+/// Only records at this level or more severe are printed to stderr, the rest is only visible in
+/// the log viewer.
+const PRINT_LEVEL: Level = Level::Info;
+
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub time: DateTime<Local>,
+    pub level: Level,
+    /// Module which emitted the record, e.g. `traviz::relation`.
+    pub target: String,
+    pub message: String,
+}
+
+impl LogRecord {
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} {:<5} {}: {}",
+            self.time.format("%H:%M:%S%.3f"),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+struct TravizLogger {
+    traviz_level: LevelFilter,
+}
+
+static PENDING_RECORDS: Mutex<VecDeque<LogRecord>> = Mutex::new(VecDeque::new());
+
+impl Log for TravizLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        if is_traviz_target(metadata.target()) {
+            metadata.level() <= self.traviz_level
+        } else {
+            metadata.level() <= Level::Warn
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        push_record(LogRecord {
+            time: Local::now(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+fn is_traviz_target(target: &str) -> bool {
+    target == "traviz" || target.starts_with("traviz::")
+}
+
+/// Installs the logger. Does nothing if a logger was already installed.
+pub fn init() {
+    let traviz_level = std::env::var("TRAVIZ_LOG")
+        .ok()
+        .and_then(|level| level.parse::<LevelFilter>().ok())
+        .unwrap_or(LevelFilter::Debug);
+    let logger = Box::new(TravizLogger { traviz_level });
+    if log::set_boxed_logger(logger).is_ok() {
+        log::set_max_level(traviz_level.max(LevelFilter::Warn));
+    }
+}
+
+fn push_record(record: LogRecord) {
+    if record.level <= PRINT_LEVEL {
+        eprintln!("{}", record.to_line());
+    }
+    let mut pending = PENDING_RECORDS.lock().unwrap();
+    pending.push_back(record);
+    if pending.len() > MAX_PENDING_RECORDS {
+        pending.pop_front();
+    }
+}
+
+/// Removes and returns all records logged since the last call.
+/// The log viewer calls this every frame.
+pub fn take_records() -> Vec<LogRecord> {
+    PENDING_RECORDS.lock().unwrap().drain(..).collect()
+}
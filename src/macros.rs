@@ -0,0 +1,107 @@
+//! Keyboard macros - named sequences of UI actions bound to a key.
+//! Macros are recorded by performing the actions with recording turned on and are stored in the
+//! persistent data, which makes repetitive routines (e.g. the same triage steps for every nightly
+//! trace) a single key press.
+
+use std::collections::VecDeque;
+
+use eframe::egui::Key;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum MacroAction {
+    /// Switch to the display mode with this name.
+    SwitchDisplayMode(String),
+    /// Switch to the node filter with this name.
+    SetNodeFilter(String),
+    /// Switch to the relation view with this name.
+    SetRelationView(String),
+    /// Run the dependency analysis described by this description, in the format accepted by the
+    /// quick setup of the "Analyze Dependency" dialog.
+    RunDependencyAnalysis(String),
+    /// Export the selected range as HTML to this path.
+    ExportHtml(String),
+}
+
+impl MacroAction {
+    pub fn describe(&self) -> String {
+        match self {
+            MacroAction::SwitchDisplayMode(name) => format!("Switch display mode to '{name}'"),
+            MacroAction::SetNodeFilter(name) => format!("Set node filter to '{name}'"),
+            MacroAction::SetRelationView(name) => format!("Set relation view to '{name}'"),
+            MacroAction::RunDependencyAnalysis(description) => {
+                format!("Run dependency analysis: {description}")
+            }
+            MacroAction::ExportHtml(path) => format!("Export HTML to {path}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct KeyMacro {
+    pub name: String,
+    /// Name of the key which runs the macro, as returned by `egui::Key::name`.
+    pub key: Option<String>,
+    pub actions: Vec<MacroAction>,
+}
+
+impl KeyMacro {
+    pub fn key(&self) -> Option<Key> {
+        self.key.as_deref().and_then(Key::from_name)
+    }
+}
+
+/// A macro whose actions are being run. The dependency analysis runs in the background, so the
+/// actions after it are held back until it finishes, otherwise they'd see the old results.
+pub struct RunningMacro {
+    pub name: String,
+    actions: VecDeque<MacroAction>,
+}
+
+impl RunningMacro {
+    pub fn new(key_macro: &KeyMacro) -> RunningMacro {
+        RunningMacro {
+            name: key_macro.name.clone(),
+            actions: key_macro.actions.iter().cloned().collect(),
+        }
+    }
+
+    /// The next action to run, `None` while `analysis_running` or when all actions were run.
+    pub fn next_action(&mut self, analysis_running: bool) -> Option<MacroAction> {
+        if analysis_running {
+            return None;
+        }
+        self.actions.pop_front()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.actions.is_empty()
+    }
+}
+
+/// Keys which can be bound to macros. Letters are avoided, they're too easy to press by accident.
+pub fn bindable_keys() -> Vec<Key> {
+    vec![
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+        Key::Num0,
+    ]
+}
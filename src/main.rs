@@ -1,15 +1,17 @@
 use core::f32;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::hash::Hash;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::Arc;
 
 use anyhow::Result;
 use eframe::egui::scroll_area::ScrollBarVisibility;
 use eframe::egui::{
-    self, Align2, Button, Color32, ComboBox, FontId, Key, Label, Modal, PointerButton, Pos2, Rect,
-    Response, ScrollArea, Sense, Stroke, TextEdit, Ui, UiBuilder, Vec2, Widget,
+    self, Align2, Button, Color32, ComboBox, DragValue, FontId, Key, Label, Modal, PointerButton,
+    Pos2, ProgressBar, Rect, Response, RichText, ScrollArea, Sense, Slider, Stroke, TextEdit, Ui,
+    UiBuilder, Vec2, Widget,
 };
 use eframe::epaint::PathShape;
 use flate2::read::GzDecoder;
@@ -18,26 +20,125 @@ use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
 #[cfg(feature = "profiling")]
 use traviz::profiling;
 use traviz::{
-    analyze_dependency, analyze_span, builtin_relations, colors, edit_modes, edit_relations, modes,
-    node_filter, persistent, relation, structured_modes, task_timer, types,
+    analyze_dependency, analyze_relations, analyze_span, analyze_utils, arrange, attribute_facets,
+    attribute_table, background_task, breadcrumb, builtin_relations, child_breakdown, clipboard,
+    clock_offsets, collapse, colors, concurrency, correlation, dependency_export, depth_limit,
+    duration_filter, edit_macros, edit_modes, edit_preferences, edit_profiles, edit_relations,
+    edit_time_markers, event_search, external_events, follow, html_export, image_export, jaeger,
+    log_viewer, logging, macros, metrics, modes, node_filter, node_health, node_logs,
+    notifications, outliers, persistent, preferences, recent_files, regions, relation,
+    relation_arrows, relation_flow, remote_file, remote_source, report, scope_filter, search, skew,
+    source_location, span_catalog, span_id, span_index, span_overlay, span_selection,
+    sticky_header, structured_modes, task_timer, throughput, time_markers, time_scrollbar,
+    trace_focus, trace_lint, trace_stats, types, unmatched_relations, utilization, view_link,
 };
 
-use analyze_dependency::{AnalyzeDependencyModal, DependencyLink};
+use analyze_dependency::{AnalyzeDependencyModal, DependencyLink, DependencyPreset};
+use analyze_relations::AnalyzeRelationsModal;
 use analyze_span::AnalyzeSpanModal;
+use analyze_utils::{highlighted_spans_range, process_spans_for_analysis};
+use arrange::{
+    arrange_spans_with_viewport, is_intersecting, ArrangedLane, ArrangementCache, ArrangementKey,
+    SpanPane,
+};
+use attribute_facets::{AttributeFacets, FacetAction};
+use attribute_table::AttributeTable;
+use background_task::BackgroundTask;
+use breadcrumb::{ancestor_chain, breadcrumb_text, draw_breadcrumb_bar};
+use child_breakdown::ChildBreakdownModal;
+use clipboard::copy_button;
+use clock_offsets::{
+    describe_offsets, shift_node_clocks, trace_node_names, ClockOffsetsModal, EditClockOffsets,
+    NodeOffsets,
+};
+use collapse::{set_collapse_all, CollapseMemory};
+use colors::{node_color, theme, Theme};
+use concurrency::{
+    compute_concurrency, draw_concurrency_strip, ConcurrencySteps, EditConcurrencyOverlay,
+    OverlayPosition,
+};
+use correlation::CorrelationModal;
+use depth_limit::DepthLimit;
+use duration_filter::{
+    highlighted_span_ancestors, DurationFilter, ShortSpans, MAX_MIN_DURATION_MS,
+};
+use edit_macros::EditMacros;
 use edit_modes::EditDisplayModes;
+use edit_preferences::EditPreferences;
+use edit_profiles::EditProfiles;
 use edit_relations::{EditRelationViews, EditRelations};
-use modes::structured_mode_transformation;
-use node_filter::{EditNodeFilters, NodeFilter};
+use edit_time_markers::EditTimeMarkers;
+use event_search::{EventMatch, EventSearchModal};
+use external_events::{
+    draw_external_event_labels, draw_external_event_lines, load_external_events, ExternalEvent,
+};
+use follow::{follow_range, FileWatcher, FOLLOW_POLL_INTERVAL};
+use html_export::ExportLane;
+use log_viewer::LogViewer;
+use macros::{KeyMacro, MacroAction, RunningMacro};
+use metrics::{draw_metric_lane, load_metric_series, MetricLane, METRIC_LANE_HEIGHT};
+use modes::{
+    span_lane_name, structured_mode_transformation, structured_mode_transformation_muted,
+    trace_span_ids, RemoteParentLinking,
+};
+use node_filter::{EditNodeFilters, NodeFilter, SpanContentCache};
+use node_health::NodeHealthModal;
+use node_logs::{
+    draw_log_lane, draw_log_lines, ImportNodeLogs, NodeLogLine, NodeLogs, NodeLogsPanel,
+};
+use notifications::Notifications;
 use opentelemetry_proto::tonic::common::v1::any_value::Value;
-use relation::{builtin_relation_views, find_relations, Relation, RelationInstance, RelationView};
-use structured_modes::StructuredMode;
+use outliers::OutliersModal;
+use preferences::{InitialWindow, InitialZoom, Preferences};
+use recent_files::{
+    add_recent_file, draw_recent_files_menu, format_file_size, RecentFile, RecentFilesAction,
+};
+use regions::{
+    draw_region_bands, draw_region_labels, load_regions, save_regions, EditRegions, Region,
+};
+use relation::{
+    builtin_relation_views, link_remote_parents, related_spans, Relation, RelationInstance,
+    RelationSearch, RelationView,
+};
+use relation_arrows::{draw_arrow_declutter_settings, plan_arrows, ArrowCandidate};
+use relation_flow::RelationFlowModal;
+use remote_file::{download_cache_folder, is_remote_path, is_remote_url, OpenUrl, RemoteDownload};
+use remote_source::{save_fetched_traces, FetchTraces};
+use report::{ReportFormat, ReportModal};
+use scope_filter::ScopeFilter;
+use search::{add_attribute_criterion, Search};
+use skew::SkewModal;
+use source_location::{open_in_editor, SourceLocation};
+use span_catalog::{SpanCatalog, SpanCatalogAction};
+use span_id::{SpanId, TraceId};
+use span_index::SpanIntervalIndex;
+use span_overlay::SpanOverlayModal;
+use span_selection::{
+    draw_selection_panel, find_spans_by_id, selection_to_csv, spans_in_rect, SelectionAction,
+    SpanSelection,
+};
+use sticky_header::{sticky_header_top, StickyLaneHeader};
+use structured_modes::{LaneLayout, SpanSelector, StructuredMode};
 use task_timer::TaskTimer;
+use throughput::ThroughputModal;
+use time_markers::{collect_time_markers, default_time_marker_rules, TimeMarker, TimeMarkerRule};
+use trace_focus::TraceFocus;
+use trace_lint::{lint_trace, TraceLintModal};
+use trace_stats::{compute_trace_stats, TraceStatsModal};
 use types::{
-    time_point_to_utc_string, value_to_text, DisplayLength, Event, HeightLevel, Node, Span,
-    TimePoint, MILLISECONDS_PER_SECOND,
+    format_duration, format_ms, precision_for_interval, set_min_max_time, time_point_to_utc_string,
+    value_to_text, DisplayLength, Event, Node, Span, TimeFormat, TimePoint,
+    MILLISECONDS_PER_SECOND, MIN_TIME_WINDOW,
+};
+use unmatched_relations::UnmatchedRelationsModal;
+use utilization::{
+    compute_utilization, draw_utilization_strip, quantize_bucket_width, EditUtilizationLane,
+    LaneCache,
 };
+use view_link::{file_hash, is_view_link, ViewLink};
 
 fn main() -> eframe::Result {
+    logging::init();
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([1280.0, 800.0]),
         ..Default::default()
@@ -45,7 +146,7 @@ fn main() -> eframe::Result {
     eframe::run_native("traviz", options, Box::new(|_cc| Ok(Box::<App>::default())))
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Timeline {
     absolute_start: TimePoint,
     absolute_end: TimePoint,
@@ -58,18 +159,173 @@ struct Timeline {
 }
 
 impl Timeline {
-    fn init(&mut self, min_time: TimePoint, max_time: TimePoint) {
+    fn init(&mut self, min_time: TimePoint, max_time: TimePoint, window: InitialWindow) {
         self.absolute_start = min_time;
         self.absolute_end = max_time;
 
-        self.visible_start = min_time;
-        self.visible_end = (self.visible_start + 5.0).min(max_time);
+        self.visible_start = window.visible_start;
+        self.visible_end = window.visible_end;
+
+        self.selected_start = window.selected_start;
+        self.selected_end = window.selected_end;
+    }
+}
+
+/// The second span pane, shown under the first one when the view is split. Both panes share the
+/// loaded spans, the display mode, the filters and the highlights.
+#[derive(Debug)]
+struct SplitView {
+    /// Both panes show the range selected on the timeline, they only scroll separately.
+    locked: bool,
+    /// Time range of the second pane when it isn't locked. It's swapped with [App::timeline] while
+    /// the pane is drawn, so that the pane can be zoomed and panned like the main one.
+    timeline: Timeline,
+}
+
+/// Raw data of a reloaded file and the hash of its contents.
+type ReloadedFile = Result<(Vec<ExportTraceServiceRequest>, String)>;
+
+/// A trace opened in a tab, with the state of the views of the trace. Display modes, node filters
+/// and relations aren't part of it, they're shared by all tabs.
+struct TraceTab {
+    timeline: Timeline,
+    split_view: Option<SplitView>,
+    /// Watches the loaded file when follow mode is on.
+    follow: Option<FileWatcher>,
+    /// Reads and parses the followed file after it changed.
+    follow_reload: Option<BackgroundTask<ReloadedFile>>,
+    timeline_bar1_time: TimePoint,
+    timeline_bar2_time: TimePoint,
+    raw_data: Vec<ExportTraceServiceRequest>,
+    /// Clock offsets which are subtracted from the timestamps in `raw_data`.
+    clock_offsets: NodeOffsets,
+    /// Absolute path of the loaded file.
+    loaded_file: Option<PathBuf>,
+    /// URL the loaded file was downloaded from, `loaded_file` is then the downloaded copy.
+    loaded_url: Option<String>,
+    /// Hash of the contents of the loaded file, included in view links.
+    loaded_file_hash: Option<String>,
+    all_spans_for_analysis: Vec<Rc<Span>>,
+    clicked_span: Option<Rc<Span>>,
+    /// Attributes of the clicked span.
+    attribute_table: AttributeTable,
+    /// Spans selected with Ctrl+click or by dragging a rectangle over the background.
+    span_selection: SpanSelection,
+    /// Spans which stay marked after the selection is cleared.
+    pinned_span_ids: HashSet<SpanId>,
+    highlighted_spans: Vec<Rc<Span>>,
+    /// Trace whose spans are tinted or which is the only one displayed.
+    trace_focus: TraceFocus,
+    /// Markers for the loaded trace, updated when the trace or the rules change.
+    time_markers: Vec<TimeMarker>,
+    /// Named time ranges of the loaded trace, saved in a sidecar file next to it.
+    regions: Vec<Region>,
+    /// User-chosen t=0, all displayed times are shown relative to it when it's set.
+    time_origin: Option<TimePoint>,
+    /// Start of the trace, used by `TimeZoneSetting::RelativeToTraceStart`.
+    trace_start: Option<TimePoint>,
+    search: Search,
+    event_search: EventSearchModal,
+    span_catalog: SpanCatalog,
+    attribute_facets: AttributeFacets,
+    trace_stats: TraceStatsModal,
+    trace_lint: TraceLintModal,
+    analyze_span_modal: AnalyzeSpanModal,
+    analyze_dependency_modal: AnalyzeDependencyModal,
+    child_breakdown_modal: ChildBreakdownModal,
+    node_health_modal: NodeHealthModal,
+    outliers_modal: OutliersModal,
+    skew_modal: SkewModal,
+    correlation_modal: CorrelationModal,
+    throughput_modal: ThroughputModal,
+    relation_flow_modal: RelationFlowModal,
+    analyze_relations_modal: AnalyzeRelationsModal,
+    clock_offsets_modal: ClockOffsetsModal,
+}
+
+impl TraceTab {
+    /// A tab without a trace.
+    fn new() -> TraceTab {
+        let mut timeline = Timeline {
+            absolute_start: 0.0,
+            absolute_end: 0.0,
+            visible_start: 0.0,
+            visible_end: 0.0,
+            selected_start: 0.0,
+            selected_end: 0.0,
+        };
+        timeline.init(
+            1.0,
+            3.0,
+            InitialZoom::default().initial_window(1.0, 3.0, None),
+        );
+        TraceTab {
+            timeline_bar1_time: timeline.selected_start,
+            timeline_bar2_time: timeline.selected_end,
+            timeline,
+            split_view: None,
+            follow: None,
+            follow_reload: None,
+            raw_data: vec![],
+            clock_offsets: NodeOffsets::new(),
+            loaded_file: None,
+            loaded_url: None,
+            loaded_file_hash: None,
+            all_spans_for_analysis: vec![],
+            clicked_span: None,
+            attribute_table: AttributeTable::new(),
+            span_selection: SpanSelection::new(),
+            pinned_span_ids: HashSet::new(),
+            highlighted_spans: Vec::new(),
+            trace_focus: TraceFocus::default(),
+            time_markers: Vec::new(),
+            regions: Vec::new(),
+            time_origin: None,
+            trace_start: None,
+            search: Search::default(),
+            event_search: EventSearchModal::new(),
+            span_catalog: SpanCatalog::new(),
+            attribute_facets: AttributeFacets::new(),
+            trace_stats: TraceStatsModal::new(),
+            trace_lint: TraceLintModal::new(),
+            analyze_span_modal: AnalyzeSpanModal::default(),
+            analyze_dependency_modal: AnalyzeDependencyModal::new(),
+            child_breakdown_modal: ChildBreakdownModal::new(),
+            node_health_modal: NodeHealthModal::new(),
+            outliers_modal: OutliersModal::new(),
+            skew_modal: SkewModal::new(),
+            correlation_modal: CorrelationModal::new(),
+            throughput_modal: ThroughputModal::new(),
+            relation_flow_modal: RelationFlowModal::new(),
+            analyze_relations_modal: AnalyzeRelationsModal::new(),
+            clock_offsets_modal: ClockOffsetsModal::new(),
+        }
+    }
 
-        self.selected_start = min_time;
-        self.selected_end = (self.selected_start + 1.0).min(max_time);
+    /// Exchanges the time ranges of the two span panes, if the view is split.
+    fn swap_split_timelines(&mut self) {
+        if let Some(split_view) = &mut self.split_view {
+            std::mem::swap(&mut self.timeline, &mut split_view.timeline);
+        }
     }
 }
 
+/// Name of a tab, the name of its file.
+fn tab_name(loaded_file: Option<&PathBuf>, loaded_url: Option<&String>) -> String {
+    let name = match (loaded_url, loaded_file) {
+        (Some(url), _) => url
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .map(str::to_string),
+        (None, Some(path)) => path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string()),
+        (None, None) => None,
+    };
+    name.unwrap_or_else(|| "New tab".to_string())
+}
+
 /// Holds parameters for converting a `TimePoint` to a horizontal (X-axis) screen coordinate.
 ///
 /// This struct encapsulates the current timeline view's mapping:
@@ -114,21 +370,34 @@ fn screen_change_to_time_change(
     after - before
 }
 
-type NodeSpans = (Rc<Node>, Vec<Rc<Span>>);
-/// A map from a node name to the node itself and a list of its associated spans.
+type NodeSpans = (Arc<Node>, Rc<SpanIntervalIndex>);
+/// A map from a lane name to the node itself and an index of the lane's spans.
 type NodeSpansMap = BTreeMap<String, NodeSpans>;
 /// Same as [NodeSpansMap] but in vector of pairs.
 type NodeSpansVec = Vec<(String, NodeSpans)>;
 
 struct App {
     layout: Layout,
-    timeline: Timeline,
-    raw_data: Vec<ExportTraceServiceRequest>,
+    /// Spans which the user wants to hide, they're muted after the spans are drawn.
+    mute_request: Option<SpanSelector>,
+    /// Open traces, `tabs[current_tab]` is the one which is shown.
+    tabs: Vec<TraceTab>,
+    current_tab: usize,
+    /// File which is being downloaded, it's opened when the download finishes.
+    download: Option<RemoteDownload>,
+    /// View link which is applied when the download of its file finishes.
+    pending_view_link: Option<ViewLink>,
+    open_url: OpenUrl,
     spans_to_display: Vec<Rc<Span>>,
     cached_node_spans: Option<NodeSpansMap>,
-    timeline_bar1_time: TimePoint,
-    timeline_bar2_time: TimePoint,
-    clicked_span: Option<Rc<Span>>,
+    arrangement_cache: ArrangementCache,
+    utilization_cache: LaneCache<Vec<f64>>,
+    concurrency_cache: LaneCache<ConcurrencySteps>,
+    span_content_cache: SpanContentCache,
+    /// Where the rectangle selection started, if it's in progress.
+    rubber_band_start: Option<Pos2>,
+    /// Rectangles of spans drawn in the current frame, used for the rectangle selection.
+    drawn_span_rects: Vec<(Rect, Rc<Span>)>,
     include_children_events: bool,
 
     display_modes: Vec<StructuredMode>,
@@ -137,37 +406,111 @@ struct App {
     node_filters: Vec<NodeFilter>,
     current_node_filter_index: usize,
 
-    search: Search,
     edit_display_modes: EditDisplayModes,
     edit_node_filters: EditNodeFilters,
+    edit_preferences: EditPreferences,
+    edit_macros: EditMacros,
+    edit_time_markers: EditTimeMarkers,
+    edit_utilization_lane: EditUtilizationLane,
+    edit_concurrency_overlay: EditConcurrencyOverlay,
+    edit_clock_offsets: EditClockOffsets,
     edit_relations: EditRelations,
     edit_relation_views: EditRelationViews,
 
     // Analyze 'features'
-    all_spans_for_analysis: Vec<Rc<Span>>,
-    analyze_span_modal: AnalyzeSpanModal,
-    analyze_dependency_modal: AnalyzeDependencyModal,
-
-    // Spans highlighting
-    highlighted_spans: Vec<Rc<Span>>,
+    span_overlay_modal: SpanOverlayModal,
+    /// Results of analyses collected for export, kept when another file is opened.
+    report_modal: ReportModal,
+
+    /// Hides spans of some instrumentation scopes or resources, together with the node filter.
+    scope_filter: ScopeFilter,
+    /// Hides or dims spans shorter than a threshold.
+    duration_filter: DurationFilter,
+    /// Hides the children of spans below a depth of the span tree.
+    depth_limit: DepthLimit,
+    /// Collapse choices of each display mode, applied again when the mode is shown.
+    collapse_memory: CollapseMemory,
+    /// Names of node sections which are collapsed in the span view.
+    collapsed_sections: HashSet<String>,
+    // Lane which should be scrolled into view on the next frame
+    scroll_to_lane: Option<String>,
 
     // Cache for span ID to root span lookup (for highlighted spans performance)
-    span_id_to_root_cache: Option<HashMap<Vec<u8>, Rc<Span>>>,
+    span_id_to_root_cache: Option<HashMap<SpanId, Rc<Span>>>,
+    /// Ancestors of the last hovered span, from the top level span to the span itself.
+    breadcrumb: Vec<Rc<Span>>,
 
     // Dependency arrow interactivity
     clicked_arrow_info: Option<ArrowInfo>,
     hovered_arrow_key: Option<ArrowKey>,
-
-    cached_produce_block_starts: Option<Vec<(TimePoint, String)>>,
+    /// Relation arrows are drawn separately from the dependency arrows, so they keep their own
+    /// hover state.
+    hovered_relation_arrow_key: Option<ArrowKey>,
+
+    /// Rules which decide where time markers are drawn on the timeline.
+    time_marker_rules: Vec<TimeMarkerRule>,
+    edit_regions: EditRegions,
+    /// Events imported from a CSV or JSON file, kept when another trace is opened.
+    external_events: Vec<ExternalEvent>,
+    /// Metric series drawn above the lanes, kept when another trace is opened.
+    metric_lanes: Vec<MetricLane>,
+    /// Lines of imported node logs, kept when another trace is opened.
+    node_logs: NodeLogs,
+    node_logs_panel: NodeLogsPanel,
+    import_node_logs: ImportNodeLogs,
+    fetch_traces: FetchTraces,
 
     defined_relations: Vec<Relation>,
+    /// Saved dependency analysis configurations, shared by all traces.
+    dependency_presets: Vec<DependencyPreset>,
+    /// Clock offsets applied to trace files, by the path (or URL) of the file.
+    clock_offsets_by_file: BTreeMap<PathBuf, NodeOffsets>,
     relation_views: Vec<RelationView>,
     current_relation_view_index: usize,
     active_relations: Vec<RelationInstance>,
+    /// Relations of the current view which are still being searched for in the background.
+    relation_search: Option<RelationSearch>,
+    /// Spans of the current relation view which weren't matched with a counterpart.
+    unmatched_relations: UnmatchedRelationsModal,
 
     // If `Some`, on the next render the App will set window name to this name and reset this field
     // back to `None`.
     set_window_name: Option<String>,
+
+    preferences: Preferences,
+    /// Profile whose display modes, filters, relations and time markers are used.
+    current_profile: String,
+    profile_names: Vec<String>,
+    edit_profiles: EditProfiles,
+    macros: Vec<KeyMacro>,
+    /// Actions recorded since "Record macro" was clicked, `None` when not recording.
+    macro_recording: Option<Vec<MacroAction>>,
+    /// Macros whose actions haven't all been run yet.
+    running_macros: Vec<RunningMacro>,
+
+    notifications: Notifications,
+    log_viewer: LogViewer,
+    recent_files: Vec<RecentFile>,
+
+    /// Where the spans were drawn in the last frame, the part of the window exported as PNG.
+    spans_area: Rect,
+    /// PNG export which waits for a screenshot of the window.
+    screenshot_export: Option<ScreenshotExport>,
+    /// Width of exported SVG images, in pixels.
+    svg_export_width: u32,
+    /// Theme and UI scale from the preferences which were last applied to the egui context.
+    applied_appearance: Option<(Theme, f32)>,
+}
+
+/// The screenshot is taken in the frame after the export was requested, so that the menu with the
+/// export button isn't in it. The screenshot arrives as an event in a later frame.
+#[derive(Debug)]
+enum ScreenshotExport {
+    /// Set in the frame in which the menu was clicked, the menu is still drawn in it.
+    Requested(PathBuf),
+    /// The menu is closed, the screenshot is taken at the end of this frame.
+    TakeScreenshot(PathBuf),
+    WaitingForScreenshot(PathBuf),
 }
 
 struct Layout {
@@ -179,13 +522,14 @@ struct Layout {
     span_margin: f32,
     spans_time_points_height: f32,
     middle_bar_height: f32,
+    time_scrollbar_height: f32,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct ArrowKey {
-    source_span_id: Vec<u8>,
+    source_span_id: SpanId,
     source_node_name: String,
-    target_span_id: Vec<u8>,
+    target_span_id: SpanId,
     target_node_name: String,
 }
 
@@ -201,6 +545,32 @@ struct ArrowInfo {
     target_end_time: TimePoint,
     /// Duration of the link (target_start_time - source_end_time)
     duration: TimePoint,
+    source_span: Rc<Span>,
+    target_span: Rc<Span>,
+    /// The relation which produced the arrow, `None` for dependency analysis arrows.
+    relation: Option<Rc<Relation>>,
+    /// Number of arrows drawn as one, see [relation_arrows]. The info describes the first of them.
+    bundled_arrows: usize,
+}
+
+impl ArrowInfo {
+    fn new(source_span: &Rc<Span>, target_span: &Rc<Span>) -> ArrowInfo {
+        ArrowInfo {
+            source_span_name: source_span.name.clone(),
+            source_node_name: source_span.node.name.clone(),
+            source_start_time: source_span.start_time,
+            source_end_time: source_span.end_time,
+            target_span_name: target_span.name.clone(),
+            target_node_name: target_span.node.name.clone(),
+            target_start_time: target_span.start_time,
+            target_end_time: target_span.end_time,
+            duration: target_span.start_time - source_span.end_time,
+            source_span: source_span.clone(),
+            target_span: target_span.clone(),
+            relation: None,
+            bundled_arrows: 1,
+        }
+    }
 }
 
 impl Default for App {
@@ -223,58 +593,92 @@ impl Default for App {
                 span_margin: 3.0,
                 spans_time_points_height: 80.0,
                 middle_bar_height: 30.0,
+                time_scrollbar_height: 14.0,
             },
-            timeline: Timeline {
-                absolute_start: 0.0,
-                absolute_end: 0.0,
-                visible_start: 0.0,
-                visible_end: 0.0,
-                selected_start: 0.0,
-                selected_end: 0.0,
-            },
-            raw_data: vec![],
+            mute_request: None,
+            tabs: vec![TraceTab::new()],
+            current_tab: 0,
+            download: None,
+            pending_view_link: None,
+            open_url: OpenUrl::new(),
             spans_to_display: vec![],
-            timeline_bar1_time: 0.0,
-            timeline_bar2_time: 0.0,
-            clicked_span: None,
+            rubber_band_start: None,
+            drawn_span_rects: Vec::new(),
             include_children_events: true,
             display_modes,
             current_display_mode_index: selected_display_mode,
             node_filters: vec![NodeFilter::show_all(), NodeFilter::show_none()],
             current_node_filter_index: 0,
-            search: Search::default(),
             edit_display_modes: EditDisplayModes::new(),
             edit_node_filters: EditNodeFilters::new(),
+            edit_preferences: EditPreferences::new(),
+            edit_macros: EditMacros::new(),
+            edit_time_markers: EditTimeMarkers::new(),
+            edit_utilization_lane: EditUtilizationLane::new(),
+            edit_concurrency_overlay: EditConcurrencyOverlay::new(),
+            edit_clock_offsets: EditClockOffsets::new(),
             edit_relations: EditRelations::new(),
             edit_relation_views: EditRelationViews::new(),
-            all_spans_for_analysis: vec![],
-            analyze_span_modal: AnalyzeSpanModal::default(),
-            analyze_dependency_modal: AnalyzeDependencyModal::new(),
-            highlighted_spans: Vec::new(),
+            span_overlay_modal: SpanOverlayModal::new(),
+            report_modal: ReportModal::new(),
+            scope_filter: ScopeFilter::default(),
+            duration_filter: DurationFilter::default(),
+            depth_limit: DepthLimit::default(),
+            collapse_memory: CollapseMemory::default(),
+            collapsed_sections: HashSet::new(),
+            scroll_to_lane: None,
             span_id_to_root_cache: None,
+            breadcrumb: Vec::new(),
             clicked_arrow_info: None,
             hovered_arrow_key: None,
-            cached_produce_block_starts: None,
+            hovered_relation_arrow_key: None,
+            time_marker_rules: default_time_marker_rules(),
+            edit_regions: EditRegions::new(),
+            external_events: Vec::new(),
+            metric_lanes: Vec::new(),
+            node_logs: NodeLogs::new(),
+            node_logs_panel: NodeLogsPanel::new(),
+            import_node_logs: ImportNodeLogs::new(),
+            fetch_traces: FetchTraces::new(),
             cached_node_spans: None,
+            arrangement_cache: ArrangementCache::new(),
+            utilization_cache: LaneCache::default(),
+            concurrency_cache: LaneCache::default(),
+            span_content_cache: SpanContentCache::default(),
             defined_relations: builtin_relations::builtin_relations(),
+            dependency_presets: Vec::new(),
+            clock_offsets_by_file: BTreeMap::new(),
             relation_views: builtin_relation_views(),
             current_relation_view_index: 0,
             active_relations: vec![],
+            relation_search: None,
+            unmatched_relations: UnmatchedRelationsModal::new(),
             set_window_name: None,
+            preferences: Preferences::default(),
+            current_profile: String::new(),
+            profile_names: Vec::new(),
+            edit_profiles: EditProfiles::new(),
+            macros: Vec::new(),
+            macro_recording: None,
+            running_macros: Vec::new(),
+            notifications: Notifications::new(),
+            log_viewer: LogViewer::new(),
+            recent_files: Vec::new(),
+            spans_area: Rect::NOTHING,
+            screenshot_export: None,
+            svg_export_width: image_export::DEFAULT_SVG_WIDTH,
+            applied_appearance: None,
         };
-        res.timeline.init(1.0, 3.0);
-        res.set_timeline_end_bars_to_selected();
-        res.search.search_term = "NOT IMPLEMENTED".to_string();
-
         res.load_peristent_data();
 
-        // If a file path is provided as the first argument, try to load it.
+        // If a file path or a view link is provided as the first argument, try to load it.
         if let Some(first_arg) = std::env::args().nth(1) {
-            println!("Trying to open file: {first_arg}");
-            if let Err(err) = res.load_file(&PathBuf::from(first_arg)) {
-                println!("Error loading file: {err}");
+            if is_view_link(&first_arg) {
+                log::info!("Trying to open view link: {first_arg}");
+                res.open_view_link(&first_arg);
             } else {
-                println!("File loaded successfully.");
+                log::info!("Trying to open file: {first_arg}");
+                res.open_file(&PathBuf::from(&first_arg));
             }
         }
 
@@ -284,6 +688,24 @@ impl Default for App {
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.apply_appearance(ctx);
+
+        // The log panel has to be drawn before the central panel, which takes the remaining space.
+        self.log_viewer.poll();
+        self.log_viewer.draw(ctx);
+        self.node_logs_panel
+            .draw(ctx, &self.node_logs, &self.time_format());
+        if let Some(time) = self.node_logs_panel.go_to.take() {
+            self.center_selected_range_on(time);
+        }
+        self.draw_span_catalog(ctx);
+        self.draw_attribute_facets(ctx);
+        if let Some(ancestor) = draw_breadcrumb_bar(ctx, &self.breadcrumb) {
+            self.jump_to_span(&ancestor);
+        }
+        self.poll_download(ctx);
+        self.poll_follow(ctx);
+
         egui::CentralPanel::default()
             .frame(eframe::egui::Frame::new())
             .show(ctx, |ui| {
@@ -302,6 +724,7 @@ impl eframe::App for App {
                 } else {
                     None
                 };
+                self.poll_relation_search(ctx);
                 self.draw_top_bar(ui);
 
                 let timeline_area = Rect::from_min_size(
@@ -320,21 +743,29 @@ impl eframe::App for App {
                     Pos2::new(0.0, middle_bar_area.max.y),
                     Vec2::new(window_width, window_height - middle_bar_area.max.y),
                 );
-                self.draw_spans(spans_area, ui, ctx);
+                self.draw_span_panes(spans_area, ui, ctx);
+                self.spans_area = spans_area;
 
                 self.draw_clicked_span(ctx, window_width - 100.0, window_height - 100.0);
+                self.draw_selection_panel(ctx);
+
+                if let Some(selector) = self.mute_request.take() {
+                    self.mute_spans(selector);
+                }
 
-                if let Some(new_display_modes) =
+                if let Some((new_display_modes, muted_spans)) =
                     self.edit_display_modes
                         .draw(ctx, window_width - 100.0, window_height - 100.0)
                 {
                     self.display_modes = new_display_modes;
+                    self.preferences.muted_spans = muted_spans;
                     self.save_persistent_data();
                     if self.current_display_mode_index >= self.display_modes.len() {
                         self.current_display_mode_index = 0;
                     }
                     if let Err(e) = self.apply_current_mode() {
-                        println!("Failed to apply display mode: {e}");
+                        self.notifications
+                            .error(format!("Failed to apply display mode: {e}"));
                     }
                 }
 
@@ -343,6 +774,7 @@ impl eframe::App for App {
                         .draw(ctx, window_width - 100.0, window_height - 100.0)
                 {
                     self.node_filters = new_node_filters;
+                    self.arrangement_cache.invalidate();
                     self.save_persistent_data();
                     if self.current_node_filter_index >= self.node_filters.len() {
                         self.current_node_filter_index = 0;
@@ -371,6 +803,126 @@ impl eframe::App for App {
                     self.apply_current_relations_view();
                 }
 
+                if let Some(new_preferences) =
+                    self.edit_preferences
+                        .draw(ctx, window_width - 100.0, window_height - 100.0)
+                {
+                    let linking_changed = new_preferences.remote_parents
+                        != self.preferences.remote_parents
+                        || new_preferences.orphan_spans != self.preferences.orphan_spans;
+                    self.preferences = new_preferences;
+                    self.save_persistent_data();
+                    if linking_changed {
+                        if let Err(e) = self.apply_current_mode() {
+                            self.notifications
+                                .error(format!("Failed to apply display mode: {e}"));
+                        }
+                    }
+                }
+
+                if let Some(new_macros) =
+                    self.edit_macros
+                        .draw(ctx, window_width - 100.0, window_height - 100.0)
+                {
+                    self.macros = new_macros;
+                    self.save_persistent_data();
+                }
+
+                if let Some((profiles, current_profile)) =
+                    self.edit_profiles
+                        .draw(ctx, window_width - 100.0, window_height - 100.0)
+                {
+                    if let Err(err) = persistent::save_profiles(profiles, &current_profile) {
+                        self.notifications
+                            .error(format!("Failed to save profiles: {err}"));
+                    }
+                    self.switch_profile(current_profile);
+                }
+
+                if let Some(new_rules) =
+                    self.edit_time_markers
+                        .draw(ctx, window_width - 100.0, window_height - 100.0)
+                {
+                    self.time_marker_rules = new_rules;
+                    self.save_persistent_data();
+                    self.update_time_markers();
+                }
+
+                if let Some((lines, timestamp_regex)) =
+                    self.import_node_logs.draw(ctx, &self.trace_node_names())
+                {
+                    self.notifications.info(format!(
+                        "Imported {} log lines of {}",
+                        lines.len(),
+                        lines[0].node
+                    ));
+                    self.node_logs.add(lines);
+                    if self.preferences.node_log_timestamp_regex != timestamp_regex {
+                        self.preferences.node_log_timestamp_regex = timestamp_regex;
+                        self.save_persistent_data();
+                    }
+                }
+
+                if let Some(url) = self.open_url.draw(ctx) {
+                    self.start_download(url, None);
+                }
+
+                if let Some((query, traces)) = self.fetch_traces.draw(ctx) {
+                    if self.preferences.remote_source != query.settings {
+                        self.preferences.remote_source = query.settings.clone();
+                        self.save_persistent_data();
+                    }
+                    match save_fetched_traces(&persistent::fetched_traces_folder(), &query, &traces)
+                    {
+                        Ok(path) => {
+                            self.open_file(&path);
+                        }
+                        Err(e) => self
+                            .notifications
+                            .error(format!("Failed to save the fetched traces: {e:#}")),
+                    }
+                }
+
+                if let Some(new_regions) = self.edit_regions.draw(
+                    ctx,
+                    &self.time_format(),
+                    window_width - 100.0,
+                    window_height - 100.0,
+                ) {
+                    self.set_regions(new_regions);
+                }
+                if let Some((start, end)) = self.edit_regions.go_to.take() {
+                    self.set_selected_range(start, end);
+                }
+
+                if let Some(settings) = self.edit_utilization_lane.draw(
+                    ctx,
+                    window_width - 100.0,
+                    window_height - 100.0,
+                ) {
+                    self.preferences.utilization_lane = settings;
+                    self.save_persistent_data();
+                    self.utilization_cache.clear();
+                }
+
+                if let Some(settings) = self.edit_concurrency_overlay.draw(
+                    ctx,
+                    window_width - 100.0,
+                    window_height - 100.0,
+                ) {
+                    self.preferences.concurrency_overlay = settings;
+                    self.save_persistent_data();
+                    self.concurrency_cache.clear();
+                }
+
+                if let Some(offsets) =
+                    self.edit_clock_offsets
+                        .draw(ctx, window_width - 100.0, window_height - 100.0)
+                {
+                    self.set_clock_offsets(offsets);
+                }
+
+                let time_format = self.time_format();
                 self.draw_analyze_span_modal(ctx, window_width - 200.0, window_height - 200.0);
 
                 self.draw_analyze_dependency_modal(
@@ -378,7 +930,80 @@ impl eframe::App for App {
                     window_width - 200.0,
                     window_height - 200.0,
                 );
+                self.trace_mut().child_breakdown_modal.show_modal(
+                    ctx,
+                    window_width - 200.0,
+                    window_height - 200.0,
+                );
+                self.span_overlay_modal.show_modal(
+                    ctx,
+                    window_width - 200.0,
+                    window_height - 200.0,
+                );
+                self.trace_mut().node_health_modal.show_modal(
+                    ctx,
+                    window_width - 200.0,
+                    window_height - 200.0,
+                );
+                self.draw_outliers_modal(ctx, window_width - 200.0, window_height - 200.0);
+                self.trace_mut().skew_modal.show_modal(
+                    ctx,
+                    &time_format,
+                    window_width - 200.0,
+                    window_height - 200.0,
+                );
+                self.trace_mut().correlation_modal.show_modal(
+                    ctx,
+                    window_width - 200.0,
+                    window_height - 200.0,
+                );
+                self.draw_throughput_modal(ctx, window_width - 200.0, window_height - 200.0);
+                self.trace_mut().relation_flow_modal.show_modal(
+                    ctx,
+                    window_width - 200.0,
+                    window_height - 200.0,
+                );
+                self.draw_analyze_relations_modal(ctx, window_width - 200.0, window_height - 200.0);
+                self.draw_report_modal(ctx, window_width - 200.0, window_height - 200.0);
+                self.trace_mut().event_search.show_modal(
+                    ctx,
+                    &time_format,
+                    window_width - 200.0,
+                    window_height - 200.0,
+                );
+                self.trace_mut().trace_stats.show_modal(
+                    ctx,
+                    &time_format,
+                    window_width - 200.0,
+                    window_height - 200.0,
+                );
+                self.draw_trace_lint_modal(ctx, window_width - 200.0, window_height - 200.0);
+                self.draw_clock_offsets_modal(ctx, window_width - 200.0, window_height - 200.0);
+                self.unmatched_relations.show_modal(
+                    ctx,
+                    &time_format,
+                    window_width - 200.0,
+                    window_height - 200.0,
+                );
+                self.jump_to_modal_focus();
                 self.draw_clicked_arrow_popup(ctx, window_width - 150.0, window_height - 150.0);
+                self.notifications
+                    .draw_log(ctx, window_width - 200.0, window_height - 200.0);
+                self.notifications.draw_toasts(ctx);
+
+                if let Some(description) = self
+                    .trace_mut()
+                    .analyze_dependency_modal
+                    .completed_analysis
+                    .take()
+                {
+                    self.record_macro_action(MacroAction::RunDependencyAnalysis(description));
+                }
+                self.run_macros_for_pressed_keys(ctx);
+                self.continue_running_macros();
+                self.handle_collapse_shortcuts(ctx);
+                self.poll_screenshot_export(ctx);
+                self.copy_span_ids_on_shortcut(ctx);
 
                 // If Ctrl+Q clicked, quit the app
                 if ctx.input(|i| i.key_down(Key::Q) && i.modifiers.ctrl) {
@@ -396,35 +1021,138 @@ impl eframe::App for App {
     }
 }
 
-// TODO - implement search
-#[allow(unused)]
-#[derive(Default, Debug)]
-struct Search {
-    search_term: String,
-    search_results: Vec<Rc<Span>>,
-    matching_span_ids: HashSet<Vec<u8>>,
-    // TODO - non functional for now
-    hide_non_matching: bool,
-}
-
 impl App {
     fn draw_top_bar(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
+            if self.tabs.len() > 1 {
+                let mut switch_to = None;
+                let mut close = None;
+                for (i, tab) in self.tabs.iter().enumerate() {
+                    let (file, url) = (tab.loaded_file.as_ref(), tab.loaded_url.as_ref());
+                    let path = url
+                        .cloned()
+                        .or_else(|| file.map(|path| path.display().to_string()));
+                    if ui
+                        .selectable_label(i == self.current_tab, tab_name(file, url))
+                        .on_hover_text(path.unwrap_or_else(|| "No file opened".to_string()))
+                        .clicked()
+                    {
+                        switch_to = Some(i);
+                    }
+                    if ui
+                        .small_button("x")
+                        .on_hover_text("Close the tab")
+                        .clicked()
+                    {
+                        close = Some(i);
+                    }
+                }
+                if let Some(index) = switch_to {
+                    self.switch_tab(index);
+                }
+                if let Some(index) = close {
+                    self.close_tab(index);
+                }
+            }
+            if ui
+                .button("+")
+                .on_hover_text("New tab, files opened in it don't replace the current trace")
+                .clicked()
+            {
+                self.tabs.push(TraceTab::new());
+                self.switch_tab(self.tabs.len() - 1);
+            }
+            ui.separator();
+
             let open_file_button = ui.button("Open file");
 
             if open_file_button.clicked() {
-                println!(
+                log::debug!(
                     "Opened file picker. sometimes the file picker opens behind the main window :/"
                 );
                 // TODO - fix file picker
 
                 if let Some(path) = rfd::FileDialog::new().pick_file() {
-                    println!("Loading file: {path:?}...");
-                    match self.load_file(&path) {
-                        Ok(()) => println!("Successfully loaded file."),
-                        Err(e) => println!("Error loading file: {e}"),
-                    }
+                    log::info!("Loading file: {path:?}...");
+                    self.open_file(&path);
+                }
+            }
+
+            if ui
+                .button("Open URL")
+                .on_hover_text("Open a trace from an https:// or s3:// URL")
+                .clicked()
+            {
+                self.open_url.open();
+            }
+
+            if ui
+                .button("Fetch traces")
+                .on_hover_text("Download the traces of a service from Tempo or Jaeger")
+                .clicked()
+            {
+                self.load_peristent_data();
+                self.fetch_traces
+                    .open(self.preferences.remote_source.clone());
+            }
+
+            if let Some(download) = &self.download {
+                ui.spinner();
+                let (downloaded, total) = download.progress();
+                let progress = match total {
+                    Some(total) => format!(
+                        "{} / {}",
+                        format_file_size(downloaded),
+                        format_file_size(total)
+                    ),
+                    None => format_file_size(downloaded),
+                };
+                ui.label(format!("Downloading... {progress}"))
+                    .on_hover_text(download.url());
+                if ui.button("Cancel").clicked() {
+                    self.download = None;
+                    self.pending_view_link = None;
+                }
+            }
+
+            match draw_recent_files_menu(ui, &self.recent_files) {
+                Some(RecentFilesAction::Open(path)) => {
+                    log::info!("Reopening recent file: {path:?}...");
+                    self.open_file(&path);
+                }
+                Some(RecentFilesAction::Clear) => {
+                    self.recent_files.clear();
+                    self.save_persistent_data();
                 }
+                None => {}
+            }
+
+            let mut new_profile = None;
+            ComboBox::new("profile chooser", "")
+                .selected_text(format!("Profile: {}", self.current_profile))
+                .show_ui(ui, |ui| {
+                    for name in &self.profile_names {
+                        if ui
+                            .selectable_label(*name == self.current_profile, name)
+                            .clicked()
+                        {
+                            new_profile = Some(name.clone());
+                        }
+                    }
+                    ui.separator();
+                    if ui.selectable_label(false, "Manage profiles...").clicked() {
+                        match persistent::load_profiles() {
+                            Ok((profiles, _)) => {
+                                self.edit_profiles.open(profiles, &self.current_profile)
+                            }
+                            Err(err) => self
+                                .notifications
+                                .error(format!("Failed to load profiles: {err}")),
+                        }
+                    }
+                });
+            if let Some(name) = new_profile.filter(|name| *name != self.current_profile) {
+                self.switch_profile(name);
             }
 
             let previous_display_mode_index = self.current_display_mode_index;
@@ -445,12 +1173,19 @@ impl App {
                 });
             if previous_display_mode_index != self.current_display_mode_index {
                 if let Err(e) = self.apply_current_mode() {
-                    println!("Failed to apply display mode: {e}");
+                    self.notifications
+                        .error(format!("Failed to apply display mode: {e}"));
                     // Go back to the previous mode
                     self.current_display_mode_index = previous_display_mode_index;
+                } else {
+                    let new_mode_name = self.display_modes[self.current_display_mode_index]
+                        .name
+                        .clone();
+                    self.record_macro_action(MacroAction::SwitchDisplayMode(new_mode_name));
                 }
             }
 
+            let previous_node_filter_index = self.current_node_filter_index;
             let current_node_filter_name = self
                 .node_filters
                 .get(self.current_node_filter_index)
@@ -466,6 +1201,57 @@ impl App {
                         );
                     }
                 });
+            if previous_node_filter_index != self.current_node_filter_index {
+                if let Some(filter) = self.node_filters.get(self.current_node_filter_index) {
+                    self.record_macro_action(MacroAction::SetNodeFilter(filter.name.clone()));
+                }
+            }
+            let scopes_text = if self.scope_filter.is_active() {
+                RichText::new("Scopes (filtered)").color(theme().accent)
+            } else {
+                RichText::new("Scopes")
+            };
+            ui.menu_button(scopes_text, |ui| {
+                if self
+                    .scope_filter
+                    .draw(ui, &self.tabs[self.current_tab].all_spans_for_analysis)
+                {
+                    if let Err(e) = self.apply_current_mode() {
+                        self.notifications
+                            .error(format!("Failed to apply display mode: {e}"));
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Hide spans by instrumentation scope or resource attributes");
+
+            ui.menu_button("Collapse", |ui| {
+                if ui.button("Collapse all children ([)").clicked() {
+                    self.collapse_all(true);
+                    ui.close_menu();
+                }
+                if ui
+                    .button("Expand all (])")
+                    .on_hover_text("Also turns off the max depth")
+                    .clicked()
+                {
+                    self.collapse_all(false);
+                    ui.close_menu();
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Collapse to depth")
+                        .on_hover_text("Alt+1 to Alt+9 collapse to that depth")
+                        .clicked()
+                    {
+                        self.collapse_to_depth(self.depth_limit.max_depth);
+                        ui.close_menu();
+                    }
+                    ui.add(DragValue::new(&mut self.depth_limit.max_depth).range(1..=usize::MAX));
+                });
+            })
+            .response
+            .on_hover_text("Collapse or expand all spans, the choices are kept per display mode");
 
             let previous_relations_view_idx = self.current_relation_view_index;
             let current_relations_view_name = self
@@ -485,11 +1271,55 @@ impl App {
                 });
             if previous_relations_view_idx != self.current_relation_view_index {
                 self.apply_current_relations_view();
+                if let Some(view) = self.relation_views.get(self.current_relation_view_index) {
+                    self.record_macro_action(MacroAction::SetRelationView(view.name.clone()));
+                }
+            }
+            ui.menu_button("Arrows", |ui| {
+                if draw_arrow_declutter_settings(ui, &mut self.preferences.relation_arrows) {
+                    self.save_persistent_data();
+                }
+            });
+            if ui
+                .button("Flow")
+                .on_hover_text("Summary of the relations found in the view, as a flow diagram")
+                .clicked()
+            {
+                self.tabs[self.current_tab]
+                    .relation_flow_modal
+                    .open(&current_relations_view_name, &self.active_relations);
+            }
+            if let Some(search) = &self.relation_search {
+                ui.add(
+                    ProgressBar::new(search.progress())
+                        .desired_width(120.0)
+                        .text("Finding relations"),
+                );
+                if ui.button("Cancel").clicked() {
+                    search.cancel();
+                }
+            }
+            let unmatched = self.unmatched_relations.unmatched().len();
+            if unmatched > 0
+                && ui
+                    .button(RichText::new(format!("Unmatched ({unmatched})")).color(theme().error))
+                    .on_hover_text(
+                        "Spans of the relations which weren't matched with a counterpart",
+                    )
+                    .clicked()
+            {
+                self.unmatched_relations.show = true;
             }
 
             if ui.button("Edit display modes").clicked() {
                 self.load_peristent_data();
-                self.edit_display_modes.open(self.display_modes.clone());
+                let (trace_spans, _) =
+                    process_spans_for_analysis(&self.trace().all_spans_for_analysis);
+                self.edit_display_modes.open(
+                    self.display_modes.clone(),
+                    self.preferences.muted_spans.clone(),
+                    trace_spans,
+                );
             }
 
             if ui.button("Edit node filters").clicked() {
@@ -509,100 +1339,1193 @@ impl App {
                     .open(self.defined_relations.clone(), self.relation_views.clone());
             }
 
-            // Analyze Span button, disabled if no spans are loaded
-            let has_spans = !self.spans_to_display.is_empty();
-            let analyze_button = ui.add_enabled(has_spans, Button::new("Analyze Span"));
-            if analyze_button.clicked() {
-                self.analyze_span_modal.open(&self.all_spans_for_analysis);
-            }
-
-            // Analyze Dependency button, disabled if no spans are loaded
+            match &self.macro_recording {
+                None => {
+                    if ui
+                        .button("Record macro")
+                        .on_hover_text("Record the following actions as a macro")
+                        .clicked()
+                    {
+                        self.macro_recording = Some(Vec::new());
+                    }
+                }
+                Some(actions) => {
+                    if ui
+                        .button(
+                            RichText::new(format!("Stop recording ({} actions)", actions.len()))
+                                .color(theme().error),
+                        )
+                        .clicked()
+                    {
+                        self.stop_macro_recording();
+                    }
+                }
+            }
+
+            if ui.button("Macros").clicked() {
+                self.load_peristent_data();
+                self.edit_macros.open(self.macros.clone());
+            }
+
+            if ui.button("Time markers").clicked() {
+                self.load_peristent_data();
+                self.edit_time_markers.open(self.time_marker_rules.clone());
+            }
+
+            ui.add_enabled_ui(self.trace().loaded_file.is_some(), |ui| {
+                ui.menu_button("Regions", |ui| {
+                    let selected_range = (
+                        self.trace().timeline.selected_start,
+                        self.trace().timeline.selected_end,
+                    );
+                    if ui
+                        .button("Add region")
+                        .on_hover_text("Name the range selected on the timeline and add a note")
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        self.edit_regions
+                            .open_with_new_region(self.trace().regions.clone(), selected_range);
+                    }
+                    if ui.button("Edit regions").clicked() {
+                        ui.close_menu();
+                        self.edit_regions
+                            .open(self.trace().regions.clone(), selected_range);
+                    }
+                });
+            });
+
+            ui.menu_button("External events", |ui| {
+                if ui
+                    .button("Import from CSV/JSON")
+                    .on_hover_text(
+                        "Events like deployments or alerts, with 'time', 'label' and an optional \
+                         'category' field. They are drawn as markers across the span view.",
+                    )
+                    .clicked()
+                {
+                    ui.close_menu();
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Events", &["csv", "json"])
+                        .pick_file()
+                    {
+                        self.import_external_events(&path);
+                    }
+                }
+                if ui
+                    .add_enabled(
+                        !self.external_events.is_empty(),
+                        Button::new(format!("Clear ({})", self.external_events.len())),
+                    )
+                    .clicked()
+                {
+                    ui.close_menu();
+                    self.external_events.clear();
+                }
+            });
+
+            ui.menu_button("Node logs", |ui| {
+                if ui
+                    .button("Import log file")
+                    .on_hover_text(
+                        "Log of one node, the lines are drawn under the lane of the node and \
+                         listed in the info of clicked spans",
+                    )
+                    .clicked()
+                {
+                    ui.close_menu();
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        self.load_peristent_data();
+                        self.import_node_logs.open(
+                            path,
+                            &self.trace_node_names(),
+                            &self.preferences.node_log_timestamp_regex,
+                        );
+                    }
+                }
+                if ui
+                    .add_enabled(
+                        !self.node_logs.is_empty(),
+                        egui::SelectableLabel::new(self.node_logs_panel.is_open(), "Show panel"),
+                    )
+                    .clicked()
+                {
+                    ui.close_menu();
+                    self.node_logs_panel.toggle();
+                }
+                if ui
+                    .add_enabled(
+                        !self.node_logs.is_empty(),
+                        Button::new(format!("Clear ({} lines)", self.node_logs.len())),
+                    )
+                    .clicked()
+                {
+                    ui.close_menu();
+                    self.node_logs.clear();
+                }
+            });
+
+            ui.menu_button("Metrics", |ui| {
+                if ui
+                    .button("Import from Prometheus/CSV")
+                    .on_hover_text(
+                        "The JSON response of a Prometheus range query, or CSV with the time in \
+                         the first column and a column for each series. Every series is drawn as \
+                         a line chart above the lanes.",
+                    )
+                    .clicked()
+                {
+                    ui.close_menu();
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Metrics", &["json", "csv"])
+                        .pick_file()
+                    {
+                        self.import_metrics(&path);
+                    }
+                }
+                if self.metric_lanes.is_empty() {
+                    return;
+                }
+                ui.separator();
+                let mut removed_lane = None;
+                for (index, lane) in self.metric_lanes.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut lane.shown, &lane.series.name);
+                        if ui.small_button("Remove").clicked() {
+                            removed_lane = Some(index);
+                        }
+                    });
+                }
+                if let Some(index) = removed_lane {
+                    self.metric_lanes.remove(index);
+                }
+                if ui.button("Remove all").clicked() {
+                    ui.close_menu();
+                    self.metric_lanes.clear();
+                }
+            });
+
+            if ui.button("Preferences").clicked() {
+                self.load_peristent_data();
+                self.edit_preferences.open(self.preferences.clone());
+            }
+
+            // Analyze Span button, disabled if no spans are loaded
+            let has_spans = !self.spans_to_display.is_empty();
+
+            if ui
+                .add_enabled(has_spans, Button::new("Export HTML"))
+                .on_hover_text("Save the selected range as an interactive HTML file")
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("HTML", &["html"])
+                    .set_file_name("traviz_export.html")
+                    .save_file()
+                {
+                    match self.export_html(&path) {
+                        Ok(()) => self
+                            .notifications
+                            .info(format!("Exported view to {}", path.display())),
+                        Err(e) => self
+                            .notifications
+                            .error(format!("Error exporting view: {e}")),
+                    }
+                    self.record_macro_action(MacroAction::ExportHtml(
+                        path.to_string_lossy().to_string(),
+                    ));
+                }
+            }
+
+            if ui
+                .add_enabled(has_spans, Button::new("Copy view link"))
+                .on_hover_text(
+                    "Copy a traviz:// link to this file, range, display mode, filters and \
+                     highlighted spans. Pass it to traviz as the argument to open the same view.",
+                )
+                .clicked()
+            {
+                if let Some(link) = self.view_link() {
+                    ui.ctx().copy_text(link.to_uri());
+                    self.notifications.info("Copied the view link");
+                }
+            }
+
+            ui.add_enabled_ui(has_spans, |ui| {
+                ui.menu_button("Export image", |ui| {
+                    if ui
+                        .button("PNG of the view")
+                        .on_hover_text("Screenshot of the spans area as it is displayed")
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("PNG", &["png"])
+                            .set_file_name("traviz_view.png")
+                            .save_file()
+                        {
+                            self.screenshot_export = Some(ScreenshotExport::Requested(path));
+                        }
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Width:");
+                        ui.add(
+                            DragValue::new(&mut self.svg_export_width)
+                                .range(100..=100_000)
+                                .suffix(" px"),
+                        );
+                    });
+                    if ui
+                        .button("SVG of the selected range")
+                        .on_hover_text(
+                            "All spans in the selected time range, including the lanes scrolled \
+                             out of view, drawn at the chosen width",
+                        )
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("SVG", &["svg"])
+                            .set_file_name("traviz_view.svg")
+                            .save_file()
+                        {
+                            match self.export_svg(&path) {
+                                Ok(()) => self
+                                    .notifications
+                                    .info(format!("Exported image to {}", path.display())),
+                                Err(e) => self
+                                    .notifications
+                                    .error(format!("Error exporting image: {e}")),
+                            }
+                        }
+                    }
+                });
+            });
+
+            let analyze_button = ui.add_enabled(has_spans, Button::new("Analyze Span"));
+            if analyze_button.clicked() {
+                let tab = self.trace_mut();
+                tab.analyze_span_modal.open(&tab.all_spans_for_analysis);
+            }
+
+            // Analyze Dependency button, disabled if no spans are loaded
             let analyze_dep_button = ui.add_enabled(has_spans, Button::new("Analyze Dependency"));
             if analyze_dep_button.clicked() {
-                self.analyze_dependency_modal
-                    .open(&self.all_spans_for_analysis);
+                let tab = self.trace_mut();
+                tab.analyze_dependency_modal
+                    .open(&tab.all_spans_for_analysis);
+            }
+
+            if ui
+                .add_enabled(
+                    !self.active_relations.is_empty(),
+                    Button::new("Analyze Relations"),
+                )
+                .on_hover_text("Delays of the relations found in the relation view")
+                .clicked()
+            {
+                let view_name = self
+                    .relation_views
+                    .get(self.current_relation_view_index)
+                    .map_or("Deleted".to_string(), |view| view.name.clone());
+                self.tabs[self.current_tab].analyze_relations_modal.open(
+                    &view_name,
+                    &self.active_relations,
+                    self.preferences.relation_arrows.near_miss_percent,
+                );
             }
 
+            if ui
+                .add_enabled(has_spans, Button::new("Node Health"))
+                .on_hover_text("Compare key span durations of every node with the median")
+                .clicked()
+            {
+                let tab = self.trace_mut();
+                tab.node_health_modal.open(&tab.all_spans_for_analysis);
+            }
+
+            ui.add_enabled_ui(has_spans, |ui| {
+                ui.menu_button("More analyses", |ui| {
+                    if ui
+                        .button("Outliers")
+                        .on_hover_text(
+                            "Spans which take much longer than others with the same name",
+                        )
+                        .clicked()
+                    {
+                        let tab = self.trace_mut();
+                        tab.outliers_modal.open(&tab.all_spans_for_analysis);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button("Cross-node skew")
+                        .on_hover_text("How far apart the nodes start and finish the same work")
+                        .clicked()
+                    {
+                        let tab = self.trace_mut();
+                        tab.skew_modal.open(&tab.all_spans_for_analysis);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button("Correlation")
+                        .on_hover_text("Does the timing of one span name depend on another one?")
+                        .clicked()
+                    {
+                        let tab = self.trace_mut();
+                        tab.correlation_modal.open(&tab.all_spans_for_analysis);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button("Throughput")
+                        .on_hover_text("How many spans with a name start per time bucket")
+                        .clicked()
+                    {
+                        let tab = self.trace_mut();
+                        tab.throughput_modal.open(&tab.all_spans_for_analysis);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui
+                        .button("Utilization lane...")
+                        .on_hover_text("Show how busy each lane is with the chosen spans")
+                        .clicked()
+                    {
+                        self.edit_utilization_lane
+                            .open(self.preferences.utilization_lane.clone());
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button("Concurrency overlay...")
+                        .on_hover_text(
+                            "Show how many of the chosen spans are open at the same time",
+                        )
+                        .clicked()
+                    {
+                        self.edit_concurrency_overlay
+                            .open(self.preferences.concurrency_overlay.clone());
+                        ui.close_menu();
+                    }
+                });
+            });
+
+            let report_sections = self.report_modal.report.sections.len();
+            if ui
+                .button(format!("Report ({report_sections})"))
+                .on_hover_text("Results added from the analyses, export them as Markdown or HTML")
+                .clicked()
+            {
+                self.report_modal.show = true;
+            }
+
+            if ui
+                .add_enabled(has_spans, Button::new("Events"))
+                .on_hover_text("Search events of all spans")
+                .clicked()
+            {
+                let (all_spans, _) =
+                    process_spans_for_analysis(&self.trace().all_spans_for_analysis);
+                self.trace_mut().event_search.open(all_spans);
+            }
+
+            if ui
+                .add_enabled(
+                    self.trace().trace_stats.has_stats(),
+                    Button::new("Trace info"),
+                )
+                .on_hover_text("What is in the loaded file: spans, nodes, top span names")
+                .clicked()
+            {
+                self.trace_mut().trace_stats.show = true;
+            }
+
+            let lint_issues = self.trace().trace_lint.report().issues.len();
+            let trace_health_text = if lint_issues > 0 {
+                RichText::new(format!("Trace health ({lint_issues})")).color(theme().error)
+            } else {
+                RichText::new("Trace health")
+            };
+            if ui
+                .add_enabled(
+                    self.trace().trace_stats.has_stats(),
+                    Button::new(trace_health_text),
+                )
+                .on_hover_text(
+                    "Structural problems in the trace: missing parents, duplicate ids, ...",
+                )
+                .clicked()
+            {
+                self.trace_mut().trace_lint.show = true;
+            }
+
+            let clock_offsets_text = if self.trace().clock_offsets.values().any(|ms| *ms != 0.0) {
+                RichText::new("Clock offsets (applied)").color(theme().warning)
+            } else {
+                RichText::new("Clock offsets")
+            };
+            let clock_offsets_hover = match describe_offsets(&self.trace().clock_offsets) {
+                applied if applied.is_empty() => {
+                    "Estimate clock differences between nodes and correct the timestamps"
+                        .to_string()
+                }
+                applied => format!("Subtracted from the timestamps: {applied}"),
+            };
+            ui.add_enabled_ui(has_spans, |ui| {
+                ui.menu_button(clock_offsets_text, |ui| {
+                    if ui
+                        .button("Estimate...")
+                        .on_hover_text("Estimate the offsets from a relation between nodes")
+                        .clicked()
+                    {
+                        let spans = self.everything_mode_spans();
+                        let tab = &mut self.tabs[self.current_tab];
+                        tab.clock_offsets_modal.open(
+                            spans,
+                            &self.defined_relations,
+                            &tab.clock_offsets,
+                        );
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button("Edit...")
+                        .on_hover_text("Enter the offset of each node by hand")
+                        .clicked()
+                    {
+                        let tab = &self.tabs[self.current_tab];
+                        self.edit_clock_offsets
+                            .open(trace_node_names(&tab.raw_data), &tab.clock_offsets);
+                        ui.close_menu();
+                    }
+                })
+                .response
+                .on_hover_text(clock_offsets_hover);
+            });
+
             // Clear Highlights button, only enabled when there are highlighted spans
-            let has_highlights = !self.highlighted_spans.is_empty();
+            let has_highlights = !self.trace().highlighted_spans.is_empty();
             ui.with_layout(
                 egui::Layout::right_to_left(eframe::emath::Align::RIGHT),
                 |ui| {
+                    self.notifications.draw_badge_button(ui);
+
+                    if ui
+                        .selectable_label(self.log_viewer.is_open(), "Log")
+                        .on_hover_text("Show the log panel")
+                        .clicked()
+                    {
+                        self.log_viewer.toggle();
+                    }
+
+                    if ui
+                        .selectable_label(self.trace().span_catalog.is_open(), "Span catalog")
+                        .on_hover_text("List span names in the selected range with their durations")
+                        .clicked()
+                    {
+                        self.trace_mut().span_catalog.toggle();
+                    }
+
+                    if ui
+                        .selectable_label(
+                            self.trace().attribute_facets.is_open(),
+                            "Attribute values",
+                        )
+                        .on_hover_text(
+                            "List the values of an attribute in the selected range with counts",
+                        )
+                        .clicked()
+                    {
+                        self.trace_mut().attribute_facets.toggle();
+                    }
+
                     let clear_button =
                         ui.add_enabled(has_highlights, Button::new("Clear Highlights"));
                     if clear_button.clicked() {
-                        println!(
+                        log::debug!(
                             "Clearing {} highlighted spans",
-                            self.highlighted_spans.len()
+                            self.trace().highlighted_spans.len()
                         );
-                        self.highlighted_spans.clear();
-                        self.analyze_dependency_modal.clear_focus();
+                        self.trace_mut().highlighted_spans.clear();
+                        self.trace_mut().analyze_dependency_modal.clear_focus();
                     }
                 },
             );
         });
     }
 
-    fn load_file(&mut self, path: &PathBuf) -> Result<()> {
-        // Read json file, supporting gzip if needed
-        let mut file_bytes = Vec::new();
-        let file = std::fs::File::open(path)?;
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_ascii_lowercase();
-        if ext == "gz" || ext == "gzip" {
-            // Gzip file
-            let mut decoder = GzDecoder::new(file);
-            decoder.read_to_end(&mut file_bytes)?;
-        } else {
-            // Regular file
-            let mut reader = file;
-            reader.read_to_end(&mut file_bytes)?;
+    /// The trace of the current tab.
+    fn trace(&self) -> &TraceTab {
+        &self.tabs[self.current_tab]
+    }
+
+    fn trace_mut(&mut self) -> &mut TraceTab {
+        &mut self.tabs[self.current_tab]
+    }
+
+    /// How times of the current trace are shown.
+    fn time_format(&self) -> TimeFormat {
+        TimeFormat {
+            settings: self.preferences.time_display,
+            origin: self.trace().time_origin,
+            trace_start: self.trace().trace_start,
+        }
+    }
+
+    /// Shows the trace of tab `index`.
+    fn switch_tab(&mut self, index: usize) {
+        if index == self.current_tab || index >= self.tabs.len() {
+            return;
+        }
+        self.current_tab = index;
+        self.show_current_tab();
+    }
+
+    /// Closes tab `index`. Closing the last tab leaves an empty one.
+    fn close_tab(&mut self, index: usize) {
+        if index >= self.tabs.len() {
+            return;
+        }
+        if self.tabs.len() == 1 {
+            let closed = std::mem::replace(&mut self.tabs[0], TraceTab::new());
+            self.show_current_tab();
+            self.forget_span_ids(&closed.raw_data);
+            return;
+        }
+        if index == self.current_tab {
+            self.switch_tab(if index == 0 { 1 } else { index - 1 });
+        }
+        let closed = self.tabs.remove(index);
+        if index < self.current_tab {
+            self.current_tab -= 1;
+        }
+        self.forget_span_ids(&closed.raw_data);
+    }
+
+    /// Removes the ids of an unloaded trace from the id table, except for the ids which are also in
+    /// the traces of the open tabs.
+    fn forget_span_ids(&self, raw_data: &[ExportTraceServiceRequest]) {
+        let mut ids = trace_span_ids(raw_data);
+        for tab in &self.tabs {
+            if ids.is_empty() {
+                return;
+            }
+            for id in trace_span_ids(&tab.raw_data) {
+                ids.remove(&id);
+            }
+        }
+        SpanId::forget(ids);
+    }
+
+    /// Rebuilds what's derived from the trace after switching tabs. The spans are transformed
+    /// again, the display mode and the relations could have changed while the tab was hidden.
+    fn show_current_tab(&mut self) {
+        self.cached_node_spans = None;
+        self.scope_filter.invalidate_counts();
+        self.arrangement_cache.invalidate();
+        self.utilization_cache.clear();
+        self.concurrency_cache.clear();
+        self.span_id_to_root_cache = None;
+        self.breadcrumb.clear();
+        self.clicked_arrow_info = None;
+        self.hovered_arrow_key = None;
+        self.hovered_relation_arrow_key = None;
+        self.rubber_band_start = None;
+        self.drawn_span_rects.clear();
+        self.scroll_to_lane = None;
+        self.relation_search = None;
+        if self.trace().raw_data.is_empty() {
+            self.spans_to_display.clear();
+            self.active_relations.clear();
+            self.unmatched_relations.clear();
+        } else if let Err(e) = self.apply_current_mode() {
+            self.notifications
+                .error(format!("Failed to apply display mode: {e}"));
+        }
+        let name = self.trace().loaded_url.clone().or_else(|| {
+            self.trace()
+                .loaded_file
+                .as_ref()
+                .map(|p| p.display().to_string())
+        });
+        self.set_window_name = Some(match name {
+            Some(name) => format!("traviz - {name}"),
+            None => "traviz".to_string(),
+        });
+    }
+
+    /// Loads the file and reports the result in notifications. Successfully loaded files are added to
+    /// the recent files. Returns whether the file was loaded. URLs are downloaded in the background
+    /// and opened when the download finishes, `false` is returned for them.
+    fn open_file(&mut self, path: &PathBuf) -> bool {
+        if is_remote_path(path) {
+            self.start_download(path.to_string_lossy().to_string(), None);
+            return false;
+        }
+        self.open_local_file(path, None)
+    }
+
+    /// Opens a local file, `url` is set when the file was downloaded from it. The URL is what's
+    /// shown, saved in the recent files and put into view links.
+    fn open_local_file(&mut self, path: &PathBuf, url: Option<String>) -> bool {
+        let name = url.clone().unwrap_or_else(|| path.display().to_string());
+        match self.load_file(path) {
+            Ok(file_size) => {
+                self.notifications.info(format!("Loaded {name}"));
+                let (all_spans, _) =
+                    process_spans_for_analysis(&self.trace().all_spans_for_analysis);
+                let stats = compute_trace_stats(self.trace().raw_data.len(), &all_spans);
+                self.tabs[self.current_tab].trace_stats.set_stats(
+                    name.clone(),
+                    stats,
+                    self.preferences.show_trace_info_on_load,
+                );
+                let report = lint_trace(&all_spans, &self.trace().raw_data);
+                if !report.is_empty() {
+                    self.notifications.warn(format!(
+                        "The trace has {} structural problems, see \"Trace health\"",
+                        report.issues.len()
+                    ));
+                }
+                self.trace_mut().trace_lint.set_report(report, &all_spans);
+                let absolute_path = std::path::absolute(path).unwrap_or_else(|_| path.clone());
+                let recent_path = url.as_ref().map_or(absolute_path.clone(), PathBuf::from);
+                let saved_clock_offsets = self
+                    .clock_offsets_by_file
+                    .get(&recent_path)
+                    .cloned()
+                    .unwrap_or_default();
+                self.trace_mut().loaded_file = Some(absolute_path.clone());
+                if url.is_some() {
+                    self.set_window_name = Some(format!("traviz - {name}"));
+                }
+                self.trace_mut().loaded_url = url;
+                self.trace_mut().regions = load_regions(&absolute_path).unwrap_or_else(|e| {
+                    self.notifications
+                        .error(format!("Failed to load the regions: {e:#}"));
+                    Vec::new()
+                });
+                if let Some((trace_start, trace_end)) =
+                    get_min_max_time(&self.trace().all_spans_for_analysis)
+                {
+                    add_recent_file(
+                        &mut self.recent_files,
+                        RecentFile {
+                            path: recent_path,
+                            file_size,
+                            trace_start,
+                            trace_end,
+                        },
+                    );
+                    self.save_persistent_data();
+                }
+                if !saved_clock_offsets.is_empty() {
+                    self.set_clock_offsets(saved_clock_offsets);
+                }
+                true
+            }
+            Err(e) => {
+                self.notifications
+                    .error(format!("Error loading file {name}: {e}"));
+                false
+            }
+        }
+    }
+
+    /// Starts downloading the file at `url`, replacing a download which is in progress. The file is
+    /// opened and `view_link` applied by [App::poll_download] when it's downloaded.
+    fn start_download(&mut self, url: String, view_link: Option<ViewLink>) {
+        log::info!("Downloading {url}...");
+        match RemoteDownload::start(
+            &url,
+            &download_cache_folder(),
+            self.preferences.cache_downloaded_files,
+        ) {
+            Ok(download) => {
+                self.download = Some(download);
+                self.pending_view_link = view_link;
+            }
+            Err(e) => self
+                .notifications
+                .error(format!("Failed to download {url}: {e:#}")),
+        }
+    }
+
+    /// Opens the downloaded file once the download is finished.
+    fn poll_download(&mut self, ctx: &egui::Context) {
+        let Some(download) = &self.download else {
+            return;
+        };
+        let Some(result) = download.poll() else {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+            return;
+        };
+        let url = download.url().to_string();
+        self.download = None;
+        let view_link = self.pending_view_link.take();
+        match result {
+            Ok(path) => {
+                if self.open_local_file(&path, Some(url)) {
+                    if let Some(link) = view_link {
+                        self.apply_view_link(link);
+                    }
+                }
+            }
+            Err(e) => self
+                .notifications
+                .error(format!("Failed to download {url}: {e:#}")),
+        }
+    }
+
+    /// Names of the nodes which have spans in the trace.
+    fn trace_node_names(&self) -> Vec<String> {
+        let names: BTreeSet<&String> = self
+            .trace()
+            .all_spans_for_analysis
+            .iter()
+            .map(|span| &span.node.name)
+            .collect();
+        names.into_iter().cloned().collect()
+    }
+
+    /// Moves the selected range, without changing its length, so that `time` is in the middle.
+    fn center_selected_range_on(&mut self, time: TimePoint) {
+        let half_window =
+            (self.trace().timeline.selected_end - self.trace().timeline.selected_start) / 2.0;
+        self.set_selected_range(time - half_window, time + half_window);
+    }
+
+    /// Adds a lane for every series in the file.
+    fn import_metrics(&mut self, path: &Path) {
+        match load_metric_series(path) {
+            Ok(all_series) if all_series.iter().all(|s| s.points.is_empty()) => {
+                self.notifications
+                    .warn(format!("{} doesn't have any values", path.display()));
+            }
+            Ok(all_series) => {
+                self.notifications.info(format!(
+                    "Imported {} metric series from {}",
+                    all_series.len(),
+                    path.display()
+                ));
+                self.metric_lanes
+                    .extend(all_series.into_iter().map(|series| MetricLane {
+                        series,
+                        shown: true,
+                    }));
+            }
+            Err(e) => self.notifications.error(format!(
+                "Failed to import metrics from {}: {e:#}",
+                path.display()
+            )),
+        }
+    }
+
+    /// Adds the events from the file to the imported ones.
+    fn import_external_events(&mut self, path: &Path) {
+        match load_external_events(path) {
+            Ok(events) => {
+                let in_trace = events
+                    .iter()
+                    .filter(|e| {
+                        e.time >= self.trace().timeline.absolute_start
+                            && e.time <= self.trace().timeline.absolute_end
+                    })
+                    .count();
+                self.notifications.info(format!(
+                    "Imported {} events from {}, {in_trace} of them are in the time range of the \
+                     trace",
+                    events.len(),
+                    path.display()
+                ));
+                self.external_events.extend(events);
+                self.external_events
+                    .sort_by(|a, b| a.time.total_cmp(&b.time));
+            }
+            Err(e) => self.notifications.error(format!(
+                "Failed to import events from {}: {e:#}",
+                path.display()
+            )),
+        }
+    }
+
+    /// Replaces the regions of the loaded trace and saves them next to it.
+    fn set_regions(&mut self, regions: Vec<Region>) {
+        self.trace_mut().regions = regions;
+        if let Some(path) = &self.trace().loaded_file {
+            if let Err(e) = save_regions(path, &self.trace().regions) {
+                self.notifications
+                    .error(format!("Failed to save the regions: {e:#}"));
+            }
+        }
+    }
+
+    /// Link which opens the loaded file with the current display mode, node filter, relation view,
+    /// selected range and highlighted spans.
+    fn view_link(&self) -> Option<ViewLink> {
+        let file = match &self.trace().loaded_url {
+            Some(url) => url.clone(),
+            None => self
+                .trace()
+                .loaded_file
+                .as_ref()?
+                .to_string_lossy()
+                .to_string(),
+        };
+        Some(ViewLink {
+            file,
+            file_hash: self.trace().loaded_file_hash.clone(),
+            start: self.trace().timeline.selected_start,
+            end: self.trace().timeline.selected_end,
+            display_mode: self
+                .display_modes
+                .get(self.current_display_mode_index)
+                .map(|mode| mode.name.clone()),
+            node_filter: self
+                .node_filters
+                .get(self.current_node_filter_index)
+                .map(|filter| filter.name.clone()),
+            relation_view: self
+                .relation_views
+                .get(self.current_relation_view_index)
+                .map(|view| view.name.clone()),
+            highlighted_span_ids: self
+                .trace()
+                .highlighted_spans
+                .iter()
+                .map(|s| s.span_id)
+                .collect(),
+        })
+    }
+
+    /// Opens the file of the link and restores the view. Parts of the view which can't be restored
+    /// (e.g. a display mode which doesn't exist in the current profile) are reported and skipped.
+    /// Links to a URL are applied once the file is downloaded.
+    fn open_view_link(&mut self, uri: &str) {
+        let link = match ViewLink::parse(uri) {
+            Ok(link) => link,
+            Err(e) => {
+                self.notifications.error(format!("Invalid view link: {e}"));
+                return;
+            }
+        };
+        if is_remote_url(&link.file) {
+            self.start_download(link.file.clone(), Some(link));
+            return;
+        }
+        if self.open_file(&PathBuf::from(&link.file)) {
+            self.apply_view_link(link);
+        }
+    }
+
+    /// Restores the view of a link whose file was just opened.
+    fn apply_view_link(&mut self, link: ViewLink) {
+        if link.file_hash.is_some() && link.file_hash != self.trace().loaded_file_hash {
+            self.notifications.warn(format!(
+                "{} has changed since the link was created, the view might be different",
+                link.file
+            ));
+        }
+
+        let actions = [
+            link.display_mode.map(MacroAction::SwitchDisplayMode),
+            link.node_filter.map(MacroAction::SetNodeFilter),
+            link.relation_view.map(MacroAction::SetRelationView),
+        ];
+        for action in actions.into_iter().flatten() {
+            if let Err(e) = self.run_macro_action(&action) {
+                self.notifications
+                    .error(format!("Failed to restore the view: {e}"));
+            }
+        }
+
+        self.set_selected_range(link.start, link.end);
+
+        if !link.highlighted_span_ids.is_empty() {
+            let ids: HashSet<SpanId> = link.highlighted_span_ids.into_iter().collect();
+            let (all_spans, _) = process_spans_for_analysis(&self.trace().all_spans_for_analysis);
+            self.trace_mut().highlighted_spans = all_spans
+                .into_iter()
+                .filter(|span| ids.contains(&span.span_id))
+                .collect();
+            if self.trace().highlighted_spans.len() < ids.len() {
+                self.notifications.warn(format!(
+                    "{} of the highlighted spans weren't found in the trace",
+                    ids.len() - self.trace().highlighted_spans.len()
+                ));
+            }
         }
+    }
 
-        self.raw_data = parse_trace_file(&file_bytes)?;
+    /// Returns the size of the loaded file.
+    fn load_file(&mut self, path: &Path) -> Result<u64> {
+        let (file_bytes, file_size) = read_trace_file(path)?;
+
+        let raw_data = parse_trace_file(&file_bytes)?;
+        let tab = self.trace_mut();
+        let unloaded_data = std::mem::replace(&mut tab.raw_data, raw_data);
+        tab.loaded_file_hash = Some(file_hash(&file_bytes));
+        tab.clock_offsets = NodeOffsets::new();
+        tab.follow = None;
+        tab.follow_reload = None;
 
         // Clear old data before loading new traces
-        self.all_spans_for_analysis.clear();
+        tab.all_spans_for_analysis.clear();
+        tab.clicked_span = None;
+        tab.span_selection.clear();
+        tab.pinned_span_ids.clear();
+        tab.highlighted_spans.clear();
+        tab.trace_focus = TraceFocus::default();
+        tab.attribute_facets.clear_filter();
+        tab.analyze_span_modal = AnalyzeSpanModal::default();
+        tab.analyze_dependency_modal = AnalyzeDependencyModal::new();
+        tab.child_breakdown_modal = ChildBreakdownModal::new();
+        tab.event_search.clear();
+        tab.search.clear_results();
+        tab.node_health_modal.focus.clear();
+        tab.outliers_modal = OutliersModal::new();
+        tab.skew_modal = SkewModal::new();
+        tab.correlation_modal = CorrelationModal::new();
+        tab.throughput_modal = ThroughputModal::new();
+        tab.relation_flow_modal = RelationFlowModal::new();
+        tab.analyze_relations_modal = AnalyzeRelationsModal::new();
+        tab.clock_offsets_modal = ClockOffsetsModal::new();
+        tab.time_origin = None;
         self.spans_to_display.clear();
-        self.clicked_span = None;
-        self.highlighted_spans.clear();
+        self.scope_filter.invalidate_counts();
+        self.depth_limit.clear_expanded();
         self.span_id_to_root_cache = None;
-        self.analyze_span_modal = AnalyzeSpanModal::default();
-        self.analyze_dependency_modal = AnalyzeDependencyModal::new();
-        self.cached_produce_block_starts = None;
-
-        let everything_mode = self
-            .display_modes
-            .iter()
-            .find(|m| m.name == "Everything")
-            .expect("'Everything' display mode not found during initialization.");
+        self.span_overlay_modal = SpanOverlayModal::new();
+        self.forget_span_ids(&unloaded_data);
 
-        // Populate all_spans_for_analysis using the transformation from the "Everything" mode.
-        self.all_spans_for_analysis =
-            structured_mode_transformation(&self.raw_data, everything_mode)
-                .expect("Failed to transform data using 'Everything' mode.");
+        self.trace_mut().all_spans_for_analysis = self.everything_mode_spans();
 
-        println!(
+        log::debug!(
             "Stored {} spans from 'Everything' mode for analysis after file load.",
-            self.all_spans_for_analysis.len()
+            self.trace().all_spans_for_analysis.len()
         );
 
-        // Populate the cache for produce_block_starts
-        self.cached_produce_block_starts = Some(collect_produce_block_starts_with_nodes(
-            &self.all_spans_for_analysis,
-        ));
+        self.update_time_markers();
+        let tab = self.trace_mut();
+        let analysis_spans = process_spans_for_analysis(&tab.all_spans_for_analysis).0;
+        tab.attribute_facets.set_spans(analysis_spans.clone());
+        tab.span_catalog.set_spans(analysis_spans);
 
         self.apply_current_mode()?;
         let (min_time, max_time) = get_min_max_time(&self.spans_to_display).unwrap();
-        self.timeline.init(min_time, max_time);
+        let first_produce_block =
+            find_first_produce_block_start(&self.trace().all_spans_for_analysis);
+        let initial_window =
+            self.preferences
+                .initial_zoom
+                .initial_window(min_time, max_time, first_produce_block);
+        let tab = self.trace_mut();
+        tab.timeline.init(min_time, max_time, initial_window);
+        if let Some(split_view) = &mut tab.split_view {
+            split_view.timeline = tab.timeline.clone();
+        }
+        self.trace_mut().trace_start = Some(min_time);
         self.set_timeline_end_bars_to_selected();
 
         self.set_window_name = Some(format!("traviz - {}", path.to_string_lossy()));
 
-        Ok(())
+        Ok(file_size)
+    }
+
+    /// All spans in the trace, built using the transformation from the "Everything" mode.
+    fn everything_mode_spans(&self) -> Vec<Rc<Span>> {
+        let everything_mode = self
+            .display_modes
+            .iter()
+            .find(|m| m.name == "Everything")
+            .expect("'Everything' display mode not found during initialization.");
+        structured_mode_transformation(&self.trace().raw_data, everything_mode)
+            .expect("Failed to transform data using 'Everything' mode.")
+    }
+
+    /// Corrects the timestamps in the raw data to have `offsets` subtracted instead of the current
+    /// clock offsets, and rebuilds all spans from it.
+    fn set_clock_offsets(&mut self, offsets: NodeOffsets) {
+        let tab = self.trace_mut();
+        shift_node_clocks(&mut tab.raw_data, &tab.clock_offsets, &offsets);
+        tab.clock_offsets = offsets;
+        self.rebuild_spans();
+
+        // Remember the offsets for the next time the file is opened.
+        let tab = &self.tabs[self.current_tab];
+        let file = tab
+            .loaded_url
+            .as_ref()
+            .map(PathBuf::from)
+            .or_else(|| tab.loaded_file.clone());
+        if let Some(file) = file {
+            let saved = self.clock_offsets_by_file.get(&file);
+            if saved.map_or(!tab.clock_offsets.is_empty(), |saved| {
+                *saved != tab.clock_offsets
+            }) {
+                if tab.clock_offsets.is_empty() {
+                    self.clock_offsets_by_file.remove(&file);
+                } else {
+                    self.clock_offsets_by_file
+                        .insert(file, tab.clock_offsets.clone());
+                }
+                if let Err(err) = persistent::save_clock_offsets(&self.clock_offsets_by_file) {
+                    self.notifications
+                        .error(format!("Failed to save the clock offsets: {err}"));
+                }
+            }
+        }
+
+        match describe_offsets(&self.trace().clock_offsets) {
+            applied if applied.is_empty() => self
+                .notifications
+                .info("Clock offsets reset to the original timestamps"),
+            applied => self
+                .notifications
+                .info(format!("Applied clock offsets: {applied}")),
+        }
+    }
+
+    /// Builds the spans again after `raw_data` was changed. The selected range stays where it is.
+    fn rebuild_spans(&mut self) {
+        // The old spans are replaced, forget everything that refers to them.
+        self.trace_mut().clicked_span = None;
+        self.trace_mut().span_selection.clear();
+        self.trace_mut().highlighted_spans.clear();
+        self.span_id_to_root_cache = None;
+        self.trace_mut().event_search.clear();
+        self.trace_mut().search.clear_results();
+        self.scope_filter.invalidate_counts();
+
+        self.trace_mut().all_spans_for_analysis = self.everything_mode_spans();
+        self.update_time_markers();
+        let tab = self.trace_mut();
+        let analysis_spans = process_spans_for_analysis(&tab.all_spans_for_analysis).0;
+        tab.attribute_facets.set_spans(analysis_spans.clone());
+        tab.span_catalog.set_spans(analysis_spans);
+        if let Err(e) = self.apply_current_mode() {
+            self.notifications
+                .error(format!("Error applying the display mode: {e}"));
+        }
+        if let Some((min_time, max_time)) = get_min_max_time(&self.spans_to_display) {
+            self.trace_mut().timeline.absolute_start = min_time;
+            self.trace_mut().timeline.absolute_end = max_time;
+        }
+    }
+
+    /// [Self::rebuild_spans], the clicked, selected and highlighted spans which are still in the
+    /// trace stay selected.
+    fn rebuild_spans_keeping_selection(&mut self) {
+        let tab = self.trace();
+        let clicked_span_id = tab.clicked_span.as_ref().map(|span| span.span_id);
+        let selected_span_ids = tab.span_selection.span_ids();
+        let highlighted_span_ids: Vec<SpanId> = tab
+            .highlighted_spans
+            .iter()
+            .map(|span| span.span_id)
+            .collect();
+
+        self.rebuild_spans();
+
+        let kept_span_ids: HashSet<SpanId> = clicked_span_id
+            .iter()
+            .chain(&selected_span_ids)
+            .chain(&highlighted_span_ids)
+            .copied()
+            .collect();
+        let spans_by_id = find_spans_by_id(&self.spans_to_display, &kept_span_ids);
+        let tab = self.trace_mut();
+        tab.clicked_span = clicked_span_id.and_then(|id| spans_by_id.get(&id).cloned());
+        tab.span_selection
+            .select_ids(&selected_span_ids, &spans_by_id);
+        tab.highlighted_spans = highlighted_span_ids
+            .iter()
+            .filter_map(|id| spans_by_id.get(id).cloned())
+            .collect();
+    }
+
+    /// Adds `selector` to the muted spans, spans matching it aren't displayed anymore.
+    fn mute_spans(&mut self, selector: SpanSelector) {
+        if self.preferences.muted_spans.contains(&selector) {
+            return;
+        }
+        self.preferences.muted_spans.push(selector);
+        self.save_persistent_data();
+        if let Err(e) = self.apply_current_mode() {
+            self.notifications
+                .error(format!("Failed to apply display mode: {e}"));
+        }
+        self.notifications
+            .info("Spans hidden, they can be shown again in \"Edit display modes\"");
+    }
+
+    /// Starts or stops following the loaded file.
+    fn set_follow(&mut self, follow: bool) {
+        let tab = self.trace_mut();
+        tab.follow = match (&tab.loaded_file, follow) {
+            (Some(path), true) => Some(FileWatcher::new(path)),
+            _ => None,
+        };
+        tab.follow_reload = None;
+        if self.trace().follow.is_some() {
+            self.show_newest_data();
+        }
+    }
+
+    /// Moves the selected range to end at the newest span, without changing its width.
+    fn show_newest_data(&mut self) {
+        let (start, end) = follow_range(
+            self.trace().timeline.selected_start,
+            self.trace().timeline.selected_end,
+            self.trace().timeline.absolute_end,
+        );
+        self.set_selected_range(start, end);
+    }
+
+    /// Reloads the followed file in the background when it changes, and shows the new spans once
+    /// the file is parsed.
+    fn poll_follow(&mut self, ctx: &egui::Context) {
+        let tab = self.trace_mut();
+        let Some(watcher) = &mut tab.follow else {
+            return;
+        };
+        // Keep checking the file even when there is no input.
+        ctx.request_repaint_after(FOLLOW_POLL_INTERVAL);
+        let path = watcher.path().to_path_buf();
+        if watcher.poll() {
+            // A reload of the previous version of the file is cancelled.
+            tab.follow_reload = Some(BackgroundTask::spawn_parallel(
+                vec![path.clone()],
+                |_| 1,
+                |path| {
+                    let (file_bytes, _) = read_trace_file(path)?;
+                    let raw_data = parse_trace_file(&file_bytes)?;
+                    Ok((raw_data, file_hash(&file_bytes)))
+                },
+            ));
+        }
+        let Some(task) = &mut tab.follow_reload else {
+            return;
+        };
+        let Some(reloaded) = task.poll().pop() else {
+            if task.is_finished() {
+                tab.follow_reload = None;
+            } else {
+                ctx.request_repaint_after(std::time::Duration::from_millis(100));
+            }
+            return;
+        };
+        tab.follow_reload = None;
+        match reloaded {
+            Ok((mut raw_data, hash)) => {
+                shift_node_clocks(&mut raw_data, &NodeOffsets::new(), &tab.clock_offsets);
+                let unloaded_data = std::mem::replace(&mut tab.raw_data, raw_data);
+                tab.loaded_file_hash = Some(hash);
+                self.forget_span_ids(&unloaded_data);
+                self.rebuild_spans_keeping_selection();
+                self.show_newest_data();
+            }
+            // The file can be in the middle of being written, it's read again after the next change.
+            Err(e) => log::warn!("Failed to reload {}: {e:#}", path.display()),
+        }
     }
 
     fn apply_current_mode(&mut self) -> Result<()> {
@@ -611,26 +2534,180 @@ impl App {
             .get(self.current_display_mode_index)
             .ok_or_else(|| anyhow::anyhow!("Invalid display mode index"))?;
 
-        self.spans_to_display = structured_mode_transformation(&self.raw_data, mode)?;
+        self.spans_to_display = structured_mode_transformation_muted(
+            &self.trace().raw_data,
+            mode,
+            &self.preferences.muted_spans,
+            &self.scope_filter,
+            self.preferences.remote_parents,
+            self.preferences.orphan_spans,
+            self.preferences.lazy_mode_transformation,
+        )?;
         set_min_max_time(&self.spans_to_display);
+        self.breadcrumb.clear();
+        self.collapse_memory
+            .apply(&mode.name, &self.spans_to_display);
         self.cached_node_spans = None;
+        self.arrangement_cache.invalidate();
+        self.utilization_cache.clear();
+        self.concurrency_cache.clear();
 
         self.apply_current_relations_view();
 
         Ok(())
     }
 
+    /// Tints the spans of `trace_id`, or shows only them if the filter is on. `None` shows all
+    /// traces normally.
+    fn focus_trace(&mut self, trace_id: Option<TraceId>) {
+        match trace_id {
+            Some(trace_id) => self.trace_mut().trace_focus.focus(trace_id),
+            None => self.trace_mut().trace_focus.clear(),
+        }
+        self.cached_node_spans = None;
+        self.arrangement_cache.invalidate();
+    }
+
+    /// Minimum duration slider on the middle bar.
+    fn draw_duration_filter(&mut self, ui: &mut Ui) {
+        let filter = &mut self.duration_filter;
+        let before = *filter;
+        ui.label("Min duration:");
+        ui.add(
+            Slider::new(&mut filter.min_duration_ms, 0.0..=MAX_MIN_DURATION_MS)
+                .logarithmic(true)
+                .suffix(" ms"),
+        )
+        .on_hover_text("Spans shorter than this are hidden or dimmed, 0 shows all spans");
+        ComboBox::from_id_salt("short spans")
+            .selected_text(filter.short_spans.name())
+            .width(60.0)
+            .show_ui(ui, |ui| {
+                for short_spans in ShortSpans::all() {
+                    ui.selectable_value(&mut filter.short_spans, short_spans, short_spans.name());
+                }
+            });
+        if filter.hidden_below() != before.hidden_below() {
+            self.arrangement_cache.invalidate();
+        }
+    }
+
+    /// Max depth checkbox and value on the middle bar.
+    fn draw_depth_limit(&mut self, ui: &mut Ui) {
+        let limit = &mut self.depth_limit;
+        let mut changed = ui
+            .checkbox(&mut limit.enabled, "Max depth")
+            .on_hover_text(
+                "Hide the children of spans below this level, expand single spans in their right \
+                 click menu",
+            )
+            .changed();
+        changed |= ui
+            .add_enabled(
+                limit.enabled,
+                DragValue::new(&mut limit.max_depth).range(1..=usize::MAX),
+            )
+            .changed();
+        if changed {
+            self.arrangement_cache.invalidate();
+        }
+    }
+
+    /// Trace id box and the trace filter on the middle bar.
+    fn draw_trace_focus(&mut self, ui: &mut Ui) {
+        let trace_edit = TextEdit::singleline(&mut self.trace_mut().trace_focus.input)
+            .background_color(theme().input_background)
+            .hint_text("trace id")
+            .desired_width(120.0)
+            .ui(ui)
+            .on_hover_text("Hex trace id, press Enter to tint the spans of the trace");
+        if trace_edit.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+            if self.trace().trace_focus.input.trim().is_empty() {
+                self.focus_trace(None);
+            } else if let Err(err) = self.trace_mut().trace_focus.focus_input() {
+                self.notifications.warn(format!("{err:#}"));
+            } else {
+                self.focus_trace(self.trace().trace_focus.trace_id);
+            }
+        }
+        if self.trace().trace_focus.trace_id.is_none() {
+            return;
+        }
+        if ui
+            .checkbox(&mut self.trace_mut().trace_focus.filter, "Only this trace")
+            .on_hover_text("Hide the spans of other traces instead of tinting this one")
+            .changed()
+        {
+            self.focus_trace(self.trace().trace_focus.trace_id);
+        }
+        if ui.button("Clear trace").clicked() {
+            self.focus_trace(None);
+        }
+    }
+
+    /// Replaces a grouped span with the individual spans it was made of. The display mode
+    /// groups them again when it's applied the next time.
+    fn ungroup_span(&mut self, group: &Rc<Span>) {
+        let Some(index) = self
+            .spans_to_display
+            .iter()
+            .position(|s| Rc::ptr_eq(s, group))
+        else {
+            return;
+        };
+        self.spans_to_display
+            .splice(index..=index, group.grouped_spans.iter().cloned());
+        self.cached_node_spans = None;
+        self.arrangement_cache.invalidate();
+        self.utilization_cache.clear();
+        self.concurrency_cache.clear();
+        self.apply_current_relations_view();
+    }
+
     fn apply_current_relations_view(&mut self) {
         let Some(view) = self.relation_views.get(self.current_relation_view_index) else {
-            println!(
-                "WARN: No relation view found at index {}",
+            self.notifications.warn(format!(
+                "No relation view found at index {}",
                 self.current_relation_view_index
-            );
+            ));
             return;
         };
 
-        self.active_relations =
-            find_relations(&self.defined_relations, view, &self.spans_to_display);
+        self.active_relations.clear();
+        self.unmatched_relations.clear();
+        self.relation_search = Some(RelationSearch::start(
+            &self.defined_relations,
+            view,
+            &self.spans_to_display,
+        ));
+        if self.preferences.remote_parents == RemoteParentLinking::Relation {
+            self.active_relations
+                .extend(link_remote_parents(&self.spans_to_display));
+        }
+    }
+
+    /// Adds relations found by the background search since the last frame.
+    fn poll_relation_search(&mut self, ctx: &egui::Context) {
+        let Some(search) = &mut self.relation_search else {
+            return;
+        };
+        self.active_relations.extend(search.poll());
+        if search.is_finished() {
+            self.notifications
+                .info(format!("Found {} relations", self.active_relations.len()));
+            let unmatched = search.unmatched().to_vec();
+            if !unmatched.is_empty() {
+                self.notifications.warn(format!(
+                    "{} spans weren't matched by relations, see \"Unmatched\"",
+                    unmatched.len()
+                ));
+            }
+            self.unmatched_relations.set_unmatched(unmatched);
+            self.relation_search = None;
+        } else {
+            // Keep polling even when there is no input.
+            ctx.request_repaint();
+        }
     }
 
     // TODO - make this better. Time points should shift when the timeline is moved, not stay in place.
@@ -639,16 +2716,16 @@ impl App {
         let background_button = ui.put(
             area,
             Button::new("")
-                .fill(colors::MILD_BLUE)
+                .fill(theme().timeline_background)
                 .sense(Sense::click_and_drag()),
         );
 
         let timeline_bar1_pos = time_to_screen(
-            self.timeline_bar1_time,
+            self.trace().timeline_bar1_time,
             area.min.x,
             area.max.x,
-            self.timeline.visible_start,
-            self.timeline.visible_end,
+            self.trace().timeline.visible_start,
+            self.trace().timeline.visible_end,
         );
         let bar1_button = ui.put(
             Rect::from_min_size(
@@ -661,11 +2738,11 @@ impl App {
             Button::new("").sense(Sense::drag()),
         );
         let timeline_bar2_pos = time_to_screen(
-            self.timeline_bar2_time,
+            self.trace().timeline_bar2_time,
             area.min.x,
             area.max.x,
-            self.timeline.visible_start,
-            self.timeline.visible_end,
+            self.trace().timeline.visible_start,
+            self.trace().timeline.visible_end,
         );
         let bar2_button = ui.put(
             Rect::from_min_size(
@@ -696,25 +2773,51 @@ impl App {
             middle_rect,
             Button::new("")
                 .sense(Sense::drag())
-                .fill(colors::LIGHT_BLUE),
+                .fill(theme().timeline_selection),
         );
 
+        // Outline the range of the lower pane when it's scrolled separately.
+        if let Some(split_view) = self
+            .trace()
+            .split_view
+            .as_ref()
+            .filter(|split| !split.locked)
+        {
+            let to_x = |time| {
+                time_to_screen(
+                    time,
+                    area.min.x,
+                    area.max.x,
+                    self.trace().timeline.visible_start,
+                    self.trace().timeline.visible_end,
+                )
+            };
+            let start_x = to_x(split_view.timeline.selected_start);
+            let end_x = to_x(split_view.timeline.selected_end).max(start_x + 2.0);
+            ui.painter().rect_stroke(
+                Rect::from_x_y_ranges(start_x..=end_x, area.y_range()),
+                0.0,
+                Stroke::new(1.5, theme().accent),
+                egui::StrokeKind::Inside,
+            );
+        }
+
         // Dragging end of selected area should adjust the selected area
         if bar1_button.dragged_by(PointerButton::Primary) {
-            self.timeline_bar1_time += screen_change_to_time_change(
+            self.trace_mut().timeline_bar1_time += screen_change_to_time_change(
                 bar1_button.drag_delta().x,
                 area.width(),
-                self.timeline.visible_start,
-                self.timeline.visible_end,
+                self.trace().timeline.visible_start,
+                self.trace().timeline.visible_end,
             );
             self.set_timeline_selected_to_end_bars();
         }
         if bar2_button.dragged_by(PointerButton::Primary) {
-            self.timeline_bar2_time += screen_change_to_time_change(
+            self.trace_mut().timeline_bar2_time += screen_change_to_time_change(
                 bar2_button.drag_delta().x,
                 area.width(),
-                self.timeline.visible_start,
-                self.timeline.visible_end,
+                self.trace().timeline.visible_start,
+                self.trace().timeline.visible_end,
             );
             self.set_timeline_selected_to_end_bars();
         }
@@ -724,11 +2827,11 @@ impl App {
             let time_shift = screen_change_to_time_change(
                 middle_button.drag_delta().x,
                 area.width(),
-                self.timeline.visible_start,
-                self.timeline.visible_end,
+                self.trace().timeline.visible_start,
+                self.trace().timeline.visible_end,
             );
-            self.timeline_bar1_time += time_shift;
-            self.timeline_bar2_time += time_shift;
+            self.trace_mut().timeline_bar1_time += time_shift;
+            self.trace_mut().timeline_bar2_time += time_shift;
             self.set_timeline_selected_to_end_bars();
         }
 
@@ -748,11 +2851,11 @@ impl App {
             let time_shift = screen_change_to_time_change(
                 shift_x,
                 area.width(),
-                self.timeline.visible_start,
-                self.timeline.visible_end,
+                self.trace().timeline.visible_start,
+                self.trace().timeline.visible_end,
             );
-            self.timeline.visible_start -= time_shift;
-            self.timeline.visible_end -= time_shift;
+            self.trace_mut().timeline.visible_start -= time_shift;
+            self.trace_mut().timeline.visible_end -= time_shift;
         }
 
         // Handle scrolling to zoom in/out
@@ -771,37 +2874,130 @@ impl App {
                     latest_pos.x,
                     area.min.x,
                     area.max.x,
-                    self.timeline.visible_start,
-                    self.timeline.visible_end,
+                    self.trace().timeline.visible_start,
+                    self.trace().timeline.visible_end,
                 );
-                let len_before = self.timeline.visible_end - self.timeline.visible_start;
+                let len_before =
+                    self.trace().timeline.visible_end - self.trace().timeline.visible_start;
                 let len_after = len_before * scale as f64;
+                if len_after < MIN_TIME_WINDOW && len_after < len_before {
+                    return;
+                }
                 let new_start =
-                    mouse_time - (mouse_time - self.timeline.visible_start) * scale as f64;
+                    mouse_time - (mouse_time - self.trace().timeline.visible_start) * scale as f64;
                 let new_end = new_start + len_after;
-                self.timeline.visible_start = new_start;
-                self.timeline.visible_end = new_end;
+                self.trace_mut().timeline.visible_start = new_start;
+                self.trace_mut().timeline.visible_end = new_end;
             }
         });
 
         self.draw_time_points(
-            self.timeline.visible_start,
-            self.timeline.visible_end,
-            self.timeline.absolute_start,
+            self.trace().timeline.visible_start,
+            self.trace().timeline.visible_end,
+            self.trace().timeline.absolute_start,
             area,
-            colors::GRAY_50,
+            theme().timeline_text,
             ui,
         );
     }
 
+    /// Scrollbar under the spans, the thumb is the selected range within the whole trace. Dragging
+    /// the thumb or clicking on the track moves the selected range, like dragging the spans with
+    /// the right button.
+    fn draw_time_scrollbar(&mut self, area: Rect, ui: &mut Ui) {
+        ui.painter()
+            .rect_filled(area, 0.0, theme().time_points_background);
+        let track_rect = area.with_min_x(area.min.x + self.layout.node_name_width);
+        let track = ui.put(
+            track_rect,
+            Button::new("")
+                .fill(theme().timeline_background)
+                .sense(Sense::click_and_drag()),
+        );
+
+        let Timeline {
+            absolute_start,
+            absolute_end,
+            selected_start,
+            selected_end,
+            ..
+        } = self.trace().timeline;
+        let selected_len = selected_end - selected_start;
+        let (start_fraction, end_fraction) = time_scrollbar::thumb_fractions(
+            absolute_start,
+            absolute_end,
+            selected_start,
+            selected_end,
+        );
+        let mut thumb_start = track_rect.min.x + track_rect.width() * start_fraction;
+        let mut thumb_end = track_rect.min.x + track_rect.width() * end_fraction;
+        if thumb_end - thumb_start < time_scrollbar::MIN_THUMB_WIDTH {
+            let center = ((thumb_start + thumb_end) / 2.0).clamp(
+                track_rect.min.x + time_scrollbar::MIN_THUMB_WIDTH / 2.0,
+                track_rect.max.x - time_scrollbar::MIN_THUMB_WIDTH / 2.0,
+            );
+            thumb_start = center - time_scrollbar::MIN_THUMB_WIDTH / 2.0;
+            thumb_end = center + time_scrollbar::MIN_THUMB_WIDTH / 2.0;
+        }
+        let thumb = ui
+            .put(
+                Rect::from_x_y_ranges(thumb_start..=thumb_end, track_rect.shrink(2.0).y_range()),
+                Button::new("")
+                    .fill(theme().timeline_selection)
+                    .sense(Sense::click_and_drag()),
+            )
+            .on_hover_text("Drag to move the selected time range");
+
+        let new_start = if thumb.dragged_by(PointerButton::Primary) {
+            let time_shift = screen_change_to_time_change(
+                thumb.drag_delta().x,
+                track_rect.width(),
+                absolute_start,
+                absolute_end,
+            );
+            Some(time_scrollbar::clamp_selected_start(
+                absolute_start,
+                absolute_end,
+                selected_len,
+                selected_start + time_shift,
+            ))
+        } else if track.clicked() || track.dragged_by(PointerButton::Primary) {
+            track.interact_pointer_pos().map(|pos| {
+                time_scrollbar::selected_start_for_click(
+                    absolute_start,
+                    absolute_end,
+                    selected_len,
+                    (pos.x - track_rect.min.x) / track_rect.width(),
+                )
+            })
+        } else {
+            None
+        };
+        if let Some(new_start) = new_start {
+            self.shift_selected_time(new_start - selected_start);
+        }
+    }
+
     fn set_timeline_selected_to_end_bars(&mut self) {
-        self.timeline.selected_start = self.timeline_bar1_time.min(self.timeline_bar2_time);
-        self.timeline.selected_end = self.timeline_bar1_time.max(self.timeline_bar2_time);
+        let tab = self.trace_mut();
+        tab.timeline.selected_start = tab.timeline_bar1_time.min(tab.timeline_bar2_time);
+        tab.timeline.selected_end = tab.timeline_bar1_time.max(tab.timeline_bar2_time);
     }
 
     fn set_timeline_end_bars_to_selected(&mut self) {
-        self.timeline_bar1_time = self.timeline.selected_start;
-        self.timeline_bar2_time = self.timeline.selected_end;
+        let tab = self.trace_mut();
+        tab.timeline_bar1_time = tab.timeline.selected_start;
+        tab.timeline_bar2_time = tab.timeline.selected_end;
+    }
+
+    /// Shows `start..end` in the span view, the timeline is widened if it doesn't contain the range.
+    fn set_selected_range(&mut self, start: TimePoint, end: TimePoint) {
+        let timeline = &mut self.trace_mut().timeline;
+        timeline.selected_start = start;
+        timeline.selected_end = end.max(start + MIN_TIME_WINDOW);
+        timeline.visible_start = timeline.visible_start.min(start);
+        timeline.visible_end = timeline.visible_end.max(timeline.selected_end);
+        self.set_timeline_end_bars_to_selected();
     }
 
     fn draw_time_points(
@@ -813,6 +3009,7 @@ impl App {
         color: Color32,
         ui: &mut Ui,
     ) {
+        let time_format = self.time_format();
         for dot in get_time_dots(start_time, end_time) {
             ui.painter().rect_filled(
                 Rect::from_min_size(
@@ -827,10 +3024,17 @@ impl App {
             );
         }
 
+        // Short windows get more digits, so that the ticks don't all show the same time
+        let seconds_per_tick = (end_time - start_time) / area.width().max(1.0) as f64 * 150.0;
+        let tick_precision = self
+            .preferences
+            .time_display
+            .tick_precision
+            .max(precision_for_interval(seconds_per_tick));
         let mut cur_pos = area.min.x;
         while cur_pos < area.max.x {
             let cur_time = screen_to_time(cur_pos, area.min.x, area.max.x, start_time, end_time);
-            let time_str = time_point_to_utc_string(cur_time);
+            let time_str = time_format.time_string_with_precision(cur_time, tick_precision);
             ui.painter().rect_filled(
                 Rect::from_min_size(Pos2::new(cur_pos, area.min.y), Vec2::new(2.0, 30.0)),
                 0.0,
@@ -843,7 +3047,10 @@ impl App {
                 FontId::default(),
                 color,
             );
-            let time_since_start_str = format!("{:.3} s", cur_time - absolute_start);
+            let time_since_start_str = match time_format.origin {
+                Some(origin) => format!("t0{:+.tick_precision$} s", cur_time - origin),
+                None => format!("{:.tick_precision$} s", cur_time - absolute_start),
+            };
             ui.painter().text(
                 Pos2::new(text_rect.min.x, text_rect.max.y + 4.0),
                 Align2::LEFT_TOP,
@@ -854,97 +3061,290 @@ impl App {
             cur_pos += text_rect.width() + 50.0;
         }
 
-        // Draw red lines for produce_block
-        let produce_block_starts_data = self
-            .cached_produce_block_starts
-            .as_ref()
-            .cloned()
-            .unwrap_or_else(|| {
-                if self.all_spans_for_analysis.is_empty() {
-                    // If there are no spans, no need to reconstruct, return empty.
-                    Vec::new()
-                } else {
-                    println!(
-                        "Reconstructing produce_block_starts in draw_time_points (cache was None)..."
-                    );
-                    collect_produce_block_starts_with_nodes(&self.all_spans_for_analysis)
-                }
-            });
-
-        for (t_ref, node_name) in &produce_block_starts_data {
-            let t = *t_ref;
-            if (t >= start_time) && (t <= end_time) {
-                let x = time_to_screen(t, area.min.x, area.max.x, start_time, end_time);
-                let marker_height = 20.0;
+        if let Some(origin) = time_format.origin {
+            if origin >= start_time && origin <= end_time {
+                let x = time_to_screen(origin, area.min.x, area.max.x, start_time, end_time);
                 ui.painter().line_segment(
-                    [
-                        Pos2::new(x, area.max.y),
-                        Pos2::new(x, area.max.y - marker_height),
-                    ],
-                    Stroke::new(2.0, colors::RED),
+                    [Pos2::new(x, area.min.y), Pos2::new(x, area.max.y)],
+                    Stroke::new(2.0, theme().accent),
+                );
+                ui.painter().text(
+                    Pos2::new(x + 4.0, area.max.y - 2.0),
+                    Align2::LEFT_BOTTOM,
+                    "t=0",
+                    FontId::default(),
+                    theme().accent,
                 );
+            }
+        }
 
-                // Remove "neard:" prefix if present
-                let short_node_name = node_name.strip_prefix("neard:").unwrap_or(node_name);
+        self.draw_time_markers(start_time, end_time, area, ui);
+    }
 
-                // Draw node name
-                let small_font_id =
-                    FontId::proportional(0.6 * egui::TextStyle::Body.resolve(ui.style()).size);
+    /// Lines at the start of spans chosen by the time marker rules, with a legend of the rules
+    /// which have markers in the visible range.
+    fn draw_time_markers(
+        &self,
+        start_time: TimePoint,
+        end_time: TimePoint,
+        area: Rect,
+        ui: &mut Ui,
+    ) {
+        let small_font_id =
+            FontId::proportional(0.6 * egui::TextStyle::Body.resolve(ui.style()).size);
+        let marker_height = 20.0;
+        let mut visible_rules = BTreeSet::new();
+        for marker in &self.trace().time_markers {
+            if marker.time < start_time || marker.time > end_time {
+                continue;
+            }
+            let Some(rule) = self.time_marker_rules.get(marker.rule_index) else {
+                continue;
+            };
+            visible_rules.insert(marker.rule_index);
+            let color = rule.color32();
+            let x = time_to_screen(marker.time, area.min.x, area.max.x, start_time, end_time);
+            ui.painter().line_segment(
+                [
+                    Pos2::new(x, area.max.y),
+                    Pos2::new(x, area.max.y - marker_height),
+                ],
+                Stroke::new(2.0, color),
+            );
+            if rule.show_node_name {
+                // Remove "neard:" prefix if present
+                let short_node_name = marker
+                    .node_name
+                    .strip_prefix("neard:")
+                    .unwrap_or(&marker.node_name);
                 ui.painter().text(
                     Pos2::new(x + 4.0, area.max.y - 10.0),
                     Align2::LEFT_TOP,
                     short_node_name,
-                    small_font_id,
-                    colors::RED,
+                    small_font_id.clone(),
+                    color,
                 );
+            }
+        }
+
+        // Legend with labels of the rules
+        let font_id = FontId::proportional(0.7 * egui::TextStyle::Body.resolve(ui.style()).size);
+        let mut legend_x = area.min.x + 2.0;
+        for rule_index in visible_rules {
+            let rule = &self.time_marker_rules[rule_index];
+            let text_rect = ui.painter().text(
+                Pos2::new(legend_x, area.max.y - 7.0),
+                Align2::LEFT_CENTER,
+                &rule.label,
+                font_id.clone(),
+                rule.color32(),
+            );
+            legend_x = text_rect.max.x + 10.0;
+        }
+    }
+
+    fn draw_middle_bar(&mut self, area: Rect, ui: &mut Ui) {
+        let time_format = self.time_format();
+        ui.painter()
+            .rect_filled(area, 0.0, theme().panel_background);
+
+        let top_margin = 5;
+        let ui_area = Rect::from_min_max(
+            Pos2::new(area.min.x, area.min.y + top_margin as f32),
+            area.max,
+        );
+        ui.allocate_new_ui(UiBuilder::new().max_rect(ui_area), |ui| {
+            ui.horizontal(|ui| {
+                let search_edit = TextEdit::singleline(&mut self.trace_mut().search.search_term)
+                    .background_color(theme().input_background)
+                    .hint_text("name attr=value")
+                    .ui(ui)
+                    .on_hover_text(
+                        "Span name substring and attribute=value criteria separated by spaces",
+                    );
+                let enter_pressed =
+                    search_edit.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                if ui.button("Search").clicked() || enter_pressed {
+                    self.run_search();
+                }
+                if ui
+                    .add_enabled(
+                        !self.trace().search.search_results.is_empty(),
+                        Button::new("Next"),
+                    )
+                    .clicked()
+                {
+                    if let Some(span) = self.trace_mut().search.next_result() {
+                        self.jump_to_span(&span);
+                    }
+                }
+                if !self.trace().search.search_term.is_empty() {
+                    ui.label(self.trace().search.position_text());
+                }
+                ui.checkbox(
+                    &mut self.trace_mut().search.hide_non_matching,
+                    "Hide non-matching",
+                )
+                .clicked();
+
+                ui.separator();
+                self.draw_trace_focus(ui);
+
+                ui.separator();
+                self.draw_duration_filter(ui);
+
+                ui.separator();
+                self.draw_depth_limit(ui);
+
+                ui.separator();
+                match time_format.origin {
+                    Some(origin) => {
+                        ui.label(format!("t=0: {}", time_format.time_string(origin)));
+                        if ui.button("Clear t=0").clicked() {
+                            self.trace_mut().time_origin = None;
+                        }
+                    }
+                    None => {
+                        ui.label("t=0 not set");
+                    }
+                }
+                if ui
+                    .button("Set t=0 at selection start")
+                    .on_hover_text("Show all times relative to the start of the selected range")
+                    .clicked()
+                {
+                    self.trace_mut().time_origin = Some(self.trace().timeline.selected_start);
+                }
+
+                ui.separator();
+                let mut follow = self.trace().follow.is_some();
+                let can_follow =
+                    self.trace().loaded_file.is_some() && self.trace().loaded_url.is_none();
+                if ui
+                    .add_enabled(can_follow, egui::Checkbox::new(&mut follow, "Follow"))
+                    .on_hover_text(
+                        "Reload the file when it changes and keep showing the newest spans",
+                    )
+                    .on_disabled_hover_text("Only local files can be followed")
+                    .changed()
+                {
+                    self.set_follow(follow);
+                }
+
+                ui.separator();
+                let mut split = self.trace().split_view.is_some();
+                if ui
+                    .checkbox(&mut split, "Split view")
+                    .on_hover_text("Show a second span pane under the first one")
+                    .changed()
+                {
+                    self.trace_mut().split_view = split.then(|| SplitView {
+                        locked: false,
+                        timeline: self.trace().timeline.clone(),
+                    });
+                }
+                let mut swap_panes = false;
+                let tab = &mut self.tabs[self.current_tab];
+                if let Some(split_view) = &mut tab.split_view {
+                    if ui
+                        .checkbox(&mut split_view.locked, "Lock ranges")
+                        .on_hover_text(
+                            "Both panes show the selected range, they only scroll separately",
+                        )
+                        .changed()
+                        && !split_view.locked
+                    {
+                        split_view.timeline = tab.timeline.clone();
+                    }
+                    if !split_view.locked {
+                        ui.label(format!(
+                            "Offset: {}",
+                            format_duration(
+                                split_view.timeline.selected_start - tab.timeline.selected_start
+                            )
+                        ))
+                        .on_hover_text("Start of the lower pane minus the start of the upper one");
+                        swap_panes = ui.button("Swap panes").clicked();
+                    }
+                }
+                if swap_panes {
+                    self.trace_mut().swap_split_timelines();
+                    self.set_timeline_end_bars_to_selected();
+                }
+            });
+        });
+    }
+
+    /// Draws the span pane, or both panes when the view is split.
+    fn draw_span_panes(&mut self, area: Rect, ui: &mut Ui, ctx: &egui::Context) {
+        let Some(locked) = self.trace().split_view.as_ref().map(|split| split.locked) else {
+            self.draw_spans(SpanPane::Upper, area, ui, ctx);
+            return;
+        };
+        let (upper_area, lower_area) = area.split_top_bottom_at_fraction(0.5);
+        let lower_area = lower_area.with_min_y(lower_area.min.y + SPLIT_VIEW_GAP);
+        self.draw_spans(SpanPane::Upper, upper_area, ui, ctx);
+        ui.painter().rect_filled(
+            Rect::from_x_y_ranges(area.x_range(), upper_area.max.y..=lower_area.min.y),
+            0.0,
+            theme().panel_background,
+        );
 
-                // Draw indicator for produce_block
-                let label_rect = Rect::from_min_size(
-                    Pos2::new(area.min.x - 15.0, area.max.y - 16.0),
-                    Vec2::new(90.0, 18.0),
-                );
-                ui.painter()
-                    .rect_filled(label_rect, 3.0, colors::TRANSPARENT);
-                let font_id =
-                    FontId::proportional(0.7 * egui::TextStyle::Body.resolve(ui.style()).size);
-                ui.painter().text(
-                    label_rect.center(),
-                    Align2::CENTER_CENTER,
-                    "produce_block",
-                    font_id,
-                    color,
-                );
+        ui.push_id("lower span pane", |ui| {
+            if locked {
+                self.draw_spans(SpanPane::Lower, lower_area, ui, ctx);
+                return;
             }
-        }
+            let bars = (
+                self.trace().timeline_bar1_time,
+                self.trace().timeline_bar2_time,
+            );
+            self.trace_mut().swap_split_timelines();
+            self.draw_spans(SpanPane::Lower, lower_area, ui, ctx);
+            self.trace_mut().swap_split_timelines();
+            (
+                self.trace_mut().timeline_bar1_time,
+                self.trace_mut().timeline_bar2_time,
+            ) = bars;
+        });
     }
 
-    fn draw_middle_bar(&mut self, area: Rect, ui: &mut Ui) {
-        ui.painter().rect_filled(area, 0.0, colors::GRAY_10);
+    /// Whether a top level span passes the trace focus and attribute value filters. Spans hidden by
+    /// the scope filter are dropped by the display mode transformation.
+    fn shows_top_level_span(&self, span: &Rc<Span>) -> bool {
+        self.trace().trace_focus.shows(span) && self.trace().attribute_facets.shows(span)
+    }
 
-        let top_margin = 5;
-        let ui_area = Rect::from_min_max(
-            Pos2::new(area.min.x, area.min.y + top_margin as f32),
-            area.max,
-        );
-        ui.allocate_new_ui(UiBuilder::new().max_rect(ui_area), |ui| {
-            ui.horizontal(|ui| {
-                TextEdit::singleline(&mut self.search.search_term)
-                    .background_color(colors::GRAY_40)
-                    .ui(ui);
-                ui.button("Search").clicked();
-                ui.button("Next").clicked();
-                ui.checkbox(&mut self.search.hide_non_matching, "Hide non-matching")
-                    .clicked();
-            });
-        });
+    /// Groups the spans into lanes according to the current lane layout and indexes each lane.
+    fn build_lanes(&self, spans: &[Rc<Span>]) -> NodeSpansMap {
+        let mut lane_spans: BTreeMap<String, (Arc<Node>, Vec<Rc<Span>>)> = BTreeMap::new();
+        for span in spans.iter().filter(|span| self.shows_top_level_span(span)) {
+            lane_spans
+                .entry(self.lane_name(span))
+                .or_insert((span.node.clone(), vec![]))
+                .1
+                .push(span.clone());
+        }
+        lane_spans
+            .into_iter()
+            .map(|(lane_name, (node, spans))| {
+                (lane_name, (node, Rc::new(SpanIntervalIndex::new(spans))))
+            })
+            .collect()
     }
 
-    fn draw_spans(&mut self, area: Rect, ui: &mut Ui, ctx: &egui::Context) {
+    fn draw_spans(&mut self, pane: SpanPane, area: Rect, ui: &mut Ui, ctx: &egui::Context) {
+        let time_format = self.time_format();
         #[cfg(feature = "profiling")]
         let _timing_guard = profiling::GLOBAL_PROFILER.start_timing("draw_spans");
 
+        self.drawn_span_rects.clear();
+        let scrollbar_area = area.with_min_y(area.max.y - self.layout.time_scrollbar_height);
+        self.draw_time_scrollbar(scrollbar_area, ui);
+        let area = area.with_max_y(scrollbar_area.min.y);
+
         let mut final_spans_for_drawing_owned: Option<Vec<Rc<Span>>> = None;
-        if !self.highlighted_spans.is_empty() {
+        if !self.trace().highlighted_spans.is_empty() {
             #[cfg(feature = "profiling")]
             let _timing_guard_highlight =
                 profiling::GLOBAL_PROFILER.start_timing("highlighted_spans_processing");
@@ -956,7 +3356,7 @@ impl App {
                     profiling::GLOBAL_PROFILER.start_timing("build_span_id_cache");
 
                 let mut cache = HashMap::new();
-                for root_span in &self.all_spans_for_analysis {
+                for root_span in &self.trace().all_spans_for_analysis {
                     populate_span_cache_recursive(root_span, root_span, &mut cache);
                 }
                 self.span_id_to_root_cache = Some(cache);
@@ -965,15 +3365,12 @@ impl App {
             // Use the cache for fast lookups
             let cache = self.span_id_to_root_cache.as_ref().unwrap();
             let mut roots_to_add_if_highlighted: Vec<Rc<Span>> = Vec::new();
-            let mut current_display_plus_new_root_ids: HashSet<Vec<u8>> = self
-                .spans_to_display
-                .iter()
-                .map(|s| s.span_id.clone())
-                .collect();
+            let mut current_display_plus_new_root_ids: HashSet<SpanId> =
+                self.spans_to_display.iter().map(|s| s.span_id).collect();
 
-            for highlighted_span_rc in &self.highlighted_spans {
+            for highlighted_span_rc in &self.trace().highlighted_spans {
                 if let Some(root_span) = cache.get(&highlighted_span_rc.span_id) {
-                    if current_display_plus_new_root_ids.insert(root_span.span_id.clone()) {
+                    if current_display_plus_new_root_ids.insert(root_span.span_id) {
                         roots_to_add_if_highlighted.push(root_span.clone());
                     }
                 }
@@ -990,7 +3387,7 @@ impl App {
             .as_ref()
             .unwrap_or(&self.spans_to_display);
 
-        let node_spans_items_for_loop: NodeSpansVec;
+        let mut node_spans_items_for_loop: NodeSpansVec;
 
         if final_spans_for_drawing_owned.is_some() {
             #[cfg(feature = "profiling")]
@@ -998,27 +3395,11 @@ impl App {
                 profiling::GLOBAL_PROFILER.start_timing("build_temp_node_map_for_highlights");
 
             // Highlights are active and modified the span list, build a temporary map
-            let mut temp_map_for_highlight: NodeSpansMap = BTreeMap::new();
-            for span_ref in spans_to_render {
-                temp_map_for_highlight
-                    .entry(span_ref.node.name.clone())
-                    .or_insert((span_ref.node.clone(), vec![]))
-                    .1
-                    .push(span_ref.clone());
-            }
-            node_spans_items_for_loop = temp_map_for_highlight.into_iter().collect();
+            node_spans_items_for_loop = self.build_lanes(spans_to_render).into_iter().collect();
         } else {
             // No active highlights modifying the list, or no highlights at all. Use cache.
             if self.cached_node_spans.is_none() {
-                let mut node_spans_map: NodeSpansMap = BTreeMap::new();
-                for span in &self.spans_to_display {
-                    node_spans_map
-                        .entry(span.node.name.clone())
-                        .or_insert((span.node.clone(), vec![]))
-                        .1
-                        .push(span.clone());
-                }
-                self.cached_node_spans = Some(node_spans_map);
+                self.cached_node_spans = Some(self.build_lanes(&self.spans_to_display));
             }
             node_spans_items_for_loop = self
                 .cached_node_spans
@@ -1040,15 +3421,47 @@ impl App {
         ui.painter().rect_filled(
             Rect::from_min_max(area.min, time_points_area.max),
             0.0,
-            colors::BLUE_DARK_GRAY,
+            theme().time_points_background,
+        );
+        ui.painter().text(
+            area.min + Vec2::new(4.0, 4.0),
+            Align2::LEFT_TOP,
+            format!(
+                "Window: {}",
+                format_duration(
+                    self.trace().timeline.selected_end - self.trace().timeline.selected_start
+                )
+            ),
+            FontId::default(),
+            theme().time_points_text,
         );
         self.draw_time_points(
-            self.timeline.selected_start,
-            self.timeline.selected_end,
-            self.timeline.absolute_start,
+            self.trace().timeline.selected_start,
+            self.trace().timeline.selected_end,
+            self.trace().timeline.absolute_start,
             time_points_area,
-            colors::GRAY_240,
+            theme().time_points_text,
+            ui,
+        );
+        let region_labels_area = Rect::from_min_max(
+            Pos2::new(time_points_area.min.x, time_points_area.max.y - 18.0),
+            time_points_area.max,
+        );
+        draw_region_labels(
+            ui,
+            &self.trace().regions,
+            self.trace().timeline.selected_start,
+            self.trace().timeline.selected_end,
+            region_labels_area,
+            &time_format,
+        );
+        draw_external_event_labels(
             ui,
+            &self.external_events,
+            self.trace().timeline.selected_start,
+            self.trace().timeline.selected_end,
+            region_labels_area.translate(Vec2::new(0.0, -region_labels_area.height())),
+            &time_format,
         );
 
         let under_time_points_area =
@@ -1079,107 +3492,278 @@ impl App {
                 .animated(false)
                 .show_viewport(ui, |ui, visible_rect| {
                     ui.style_mut().spacing.button_padding = Vec2::ZERO;
-                    ui.style_mut().visuals.override_text_color = Some(colors::BLACK);
+                    ui.style_mut().visuals.override_text_color = Some(theme().span_text);
 
                     // TODO - a button for background feels hacky x.x
                     let background_button = ui.put(
                         under_time_points_area,
                         Button::new("")
-                            .fill(colors::GRAY_30)
+                            .fill(theme().spans_background)
                             .sense(Sense::click_and_drag()),
                     );
+                    if background_button.drag_started_by(PointerButton::Primary) {
+                        self.rubber_band_start = background_button.interact_pointer_pos();
+                    }
+                    draw_region_bands(
+                        ui,
+                        &self.trace().regions,
+                        self.trace().timeline.selected_start,
+                        self.trace().timeline.selected_end,
+                        Rect::from_min_max(
+                            Pos2::new(
+                                under_time_points_area.min.x + self.layout.node_name_width,
+                                under_time_points_area.min.y,
+                            ),
+                            under_time_points_area.max,
+                        ),
+                    );
                     if background_button.dragged_by(PointerButton::Secondary) {
                         self.shift_selected_time(screen_change_to_time_change(
                             -background_button.drag_delta().x,
                             under_time_points_area.width() - self.layout.node_name_width,
-                            self.timeline.selected_start,
-                            self.timeline.selected_end,
+                            self.trace().timeline.selected_start,
+                            self.trace().timeline.selected_end,
                         ));
                     }
 
                     let span_height = ui.fonts(|fs| {
-                        fs.layout_no_wrap("A".to_string(), FontId::default(), colors::BLACK)
+                        fs.layout_no_wrap("A".to_string(), FontId::default(), theme().span_text)
                             .rect
                             .height()
                     }) * 1.2;
                     self.layout.span_name_threshold = ui.fonts(|fs| {
-                        fs.layout_no_wrap("...".to_string(), FontId::default(), colors::BLACK)
+                        fs.layout_no_wrap("...".to_string(), FontId::default(), theme().span_text)
                             .rect
                             .width()
                     });
 
                     let mut cur_height = under_time_points_area.min.y - visible_rect.min.y;
 
-                    let mut span_positions: HashMap<Vec<u8>, f32> = HashMap::new();
+                    let mut span_positions: HashMap<SpanId, f32> = HashMap::new();
 
-                    let highlighted_span_ids_set: HashSet<Vec<u8>> =
-                        if !self.highlighted_spans.is_empty() {
-                            self.highlighted_spans
+                    let highlighted_span_ids_set: HashSet<SpanId> =
+                        if !self.trace().highlighted_spans.is_empty() {
+                            self.trace()
+                                .highlighted_spans
                                 .iter()
-                                .map(|s| s.span_id.clone())
+                                .map(|s| s.span_id)
                                 .collect()
                         } else {
                             HashSet::new()
                         };
 
                     let time_params = TimeToScreenParams {
-                        selected_start_time: self.timeline.selected_start,
-                        selected_end_time: self.timeline.selected_end,
+                        selected_start_time: self.trace().timeline.selected_start,
+                        selected_end_time: self.trace().timeline.selected_end,
                         visual_start_x: under_time_points_area.min.x + self.layout.node_name_width,
                         visual_end_x: under_time_points_area.max.x,
                     };
-                    for (node_name, (_node, spans)) in node_spans_items_for_loop {
-                        // TODO - filter spans before displaying, like display modes. It'd work better with search etc.
-                        if let Some(current_node_filter) =
-                            self.node_filters.get(self.current_node_filter_index)
-                        {
-                            if !current_node_filter.should_show_span(&node_name) {
-                                continue;
-                            }
-                        }
+                    let lane_layout = self.current_lane_layout();
+                    self.arrangement_cache
+                        .update_highlighted_spans(&self.tabs[self.current_tab].highlighted_spans);
 
-                        let spans_in_range: Vec<Rc<Span>> = spans
-                            .iter()
-                            .filter(|s| {
-                                is_intersecting(
-                                    s.min_start_time.get(),
-                                    s.max_end_time.get(),
-                                    self.timeline.selected_start,
-                                    self.timeline.selected_end,
-                                )
-                            })
-                            .cloned()
-                            .collect();
-
-                        set_display_children_with_highlights(
-                            &spans_in_range,
-                            &self.highlighted_spans,
+                    for lane in self.metric_lanes.iter().filter(|lane| lane.shown) {
+                        let next_height = cur_height + METRIC_LANE_HEIGHT;
+                        draw_metric_lane(
+                            ui,
+                            &lane.series,
+                            self.trace().timeline.selected_start,
+                            self.trace().timeline.selected_end,
+                            Rect::from_min_max(
+                                Pos2::new(node_names_area.min.x, cur_height),
+                                Pos2::new(node_names_area.max.x, next_height),
+                            ),
+                            Rect::from_min_max(
+                                Pos2::new(time_params.visual_start_x, cur_height),
+                                Pos2::new(time_params.visual_end_x, next_height),
+                            ),
+                            &time_format,
+                        );
+                        ui.painter().line(
+                            vec![
+                                Pos2::new(area.min.x, next_height),
+                                Pos2::new(area.max.x, next_height),
+                            ],
+                            Stroke::new(1.0, theme().lane_separator),
                         );
+                        cur_height = next_height + self.layout.span_margin;
+                    }
 
-                        #[cfg(feature = "profiling")]
-                        let _timing_guard_display_params = profiling::GLOBAL_PROFILER
-                            .start_timing("set_display_params_with_highlights");
+                    // Lanes of each node section are drawn together, under the section's header.
+                    let node_sections =
+                        self.node_sections_for_lanes(&mut node_spans_items_for_loop);
+                    let mut current_section = None;
 
-                        Self::set_display_params_with_highlights(
-                            &spans_in_range,
-                            &highlighted_span_ids_set,
-                            self.timeline.selected_start,
-                            self.timeline.selected_end,
-                            under_time_points_area.min.x + self.layout.node_name_width,
-                            under_time_points_area.max.x,
-                            ui,
-                        );
+                    for (lane_idx, (node_name, (node, spans))) in
+                        node_spans_items_for_loop.into_iter().enumerate()
+                    {
+                        // TODO - filter spans before displaying, like display modes. It'd work better with search etc.
+                        let current_node_filter =
+                            self.node_filters.get(self.current_node_filter_index);
+                        if lane_layout == LaneLayout::PerNode {
+                            if let Some(current_node_filter) = current_node_filter {
+                                let timeline = &self.tabs[self.current_tab].timeline;
+                                if !current_node_filter.should_show_span(&node.name)
+                                    || !self.span_content_cache.has_matching_spans(
+                                        current_node_filter,
+                                        &spans,
+                                        timeline.selected_start,
+                                        timeline.selected_end,
+                                    )
+                                {
+                                    continue;
+                                }
+                            }
+                        }
 
-                        #[cfg(feature = "profiling")]
-                        let _timing_guard_arrange =
-                            profiling::GLOBAL_PROFILER.start_timing("arrange_spans");
-
-                        let bbox = arrange_spans_with_viewport(
-                            &spans_in_range,
-                            true,
-                            self.timeline.selected_start,
-                            self.timeline.selected_end,
+                        if let Some((section_name, lane_count)) = &node_sections[lane_idx] {
+                            if current_section.as_ref() != Some(section_name) {
+                                let header_rect = Rect::from_min_max(
+                                    Pos2::new(area.min.x, cur_height),
+                                    Pos2::new(area.max.x, cur_height + span_height * 1.2),
+                                );
+                                Self::draw_node_section_header(
+                                    &mut self.collapsed_sections,
+                                    section_name,
+                                    *lane_count,
+                                    header_rect,
+                                    ui,
+                                );
+                                cur_height = header_rect.max.y;
+                                current_section = Some(section_name.clone());
+                            }
+                            if self.collapsed_sections.contains(section_name) {
+                                continue;
+                            }
+                        }
+
+                        let arrangement_key = ArrangementKey {
+                            selected_start: time_params.selected_start_time,
+                            selected_end: time_params.selected_end_time,
+                            visual_start_x: time_params.visual_start_x,
+                            visual_end_x: time_params.visual_end_x,
+                            node_filter_index: self.current_node_filter_index,
+                        };
+                        let arranged = self.arrangement_cache.get_or_arrange(
+                            pane,
+                            &node_name,
+                            arrangement_key,
+                            || {
+                                let mut spans_in_range: Vec<Rc<Span>> = spans
+                                    .query(
+                                        time_params.selected_start_time,
+                                        time_params.selected_end_time,
+                                    )
+                                    .filter(|s| {
+                                        // In per span name lanes the nodes are mixed, filter each span.
+                                        lane_layout == LaneLayout::PerNode
+                                            || current_node_filter
+                                                .is_none_or(|f| f.should_show_span(&s.node.name))
+                                    })
+                                    .cloned()
+                                    .collect();
+                                let hidden_below = self.duration_filter.hidden_below();
+                                let shown_short_spans = if hidden_below > 0.0 {
+                                    highlighted_span_ancestors(
+                                        &spans_in_range,
+                                        &highlighted_span_ids_set,
+                                    )
+                                } else {
+                                    HashSet::new()
+                                };
+                                spans_in_range.retain(|s| {
+                                    !self.duration_filter.hides(s)
+                                        || shown_short_spans.contains(&s.span_id)
+                                });
+
+                                set_display_children_with_highlights(
+                                    &spans_in_range,
+                                    &self.tabs[self.current_tab].highlighted_spans,
+                                    &DisplayLimits {
+                                        hidden_below,
+                                        shown_short_spans: &shown_short_spans,
+                                        depth_limit: &self.depth_limit,
+                                    },
+                                );
+
+                                #[cfg(feature = "profiling")]
+                                let _timing_guard_display_params = profiling::GLOBAL_PROFILER
+                                    .start_timing("set_display_params_with_highlights");
+
+                                Self::set_display_params_with_highlights(
+                                    &spans_in_range,
+                                    &highlighted_span_ids_set,
+                                    time_params.selected_start_time,
+                                    time_params.selected_end_time,
+                                    time_params.visual_start_x,
+                                    time_params.visual_end_x,
+                                    ui,
+                                );
+
+                                let bbox = arrange_spans_with_viewport(
+                                    &spans_in_range,
+                                    true,
+                                    time_params.selected_start_time,
+                                    time_params.selected_end_time,
+                                );
+                                ArrangedLane {
+                                    spans_in_range,
+                                    bbox,
+                                }
+                            },
                         );
+                        let spans_in_range = &arranged.spans_in_range;
+                        let bbox = arranged.bbox;
+
+                        let lane_top = cur_height;
+                        let utilization = &self.preferences.utilization_lane;
+                        if utilization.enabled {
+                            let rect = Rect::from_min_max(
+                                Pos2::new(time_params.visual_start_x, cur_height),
+                                Pos2::new(time_params.visual_end_x, cur_height + span_height),
+                            );
+                            let window = (
+                                time_params.selected_start_time,
+                                time_params.selected_end_time,
+                            );
+                            let bucket_width = quantize_bucket_width(
+                                (window.1 - window.0) / f64::from(rect.width())
+                                    * f64::from(utilization.bucket_width_px),
+                            );
+                            let (fractions, range) = self.utilization_cache.get_or_compute(
+                                &node_name,
+                                spans.id(),
+                                window.0,
+                                window.1,
+                                bucket_width,
+                                |range| {
+                                    compute_utilization(
+                                        spans.query(range.start, range.end),
+                                        &utilization.selector,
+                                        range.start,
+                                        range.end,
+                                        range.buckets(),
+                                    )
+                                },
+                            );
+                            draw_utilization_strip(ui, rect, fractions, &range, window, &node_name);
+                            cur_height = rect.max.y + self.layout.span_margin;
+                        }
+                        let concurrency_position = self
+                            .preferences
+                            .concurrency_overlay
+                            .enabled
+                            .then_some(self.preferences.concurrency_overlay.position);
+                        if concurrency_position == Some(OverlayPosition::AboveLane) {
+                            cur_height = self.draw_concurrency_overlay(
+                                &node_name,
+                                &spans,
+                                cur_height,
+                                span_height,
+                                &time_params,
+                                ui,
+                            );
+                        }
 
                         if !highlighted_span_ids_set.is_empty() || !self.active_relations.is_empty()
                         {
@@ -1188,35 +3772,101 @@ impl App {
                                 profiling::GLOBAL_PROFILER.start_timing("collect_span_positions");
 
                             self.collect_span_positions(
-                                &spans_in_range,
+                                spans_in_range,
                                 cur_height,
                                 span_height,
                                 &mut span_positions,
                             );
                         }
 
-                        ui.style_mut().visuals.override_text_color = Some(colors::BLACK);
+                        ui.style_mut().visuals.override_text_color = Some(theme().span_text);
+                        let spans_top = cur_height;
                         self.draw_arranged_spans(
-                            &spans_in_range,
+                            spans_in_range,
                             ui,
                             cur_height,
                             span_height,
                             0,
+                            None,
                             &highlighted_span_ids_set,
                         );
 
-                        let next_height = cur_height
+                        let mut next_height = cur_height
                             + bbox.height as f32 * (span_height + self.layout.span_margin);
-                        ui.style_mut().visuals.override_text_color = Some(colors::WHITE);
+                        if concurrency_position == Some(OverlayPosition::BelowLane) {
+                            next_height = self.draw_concurrency_overlay(
+                                &node_name,
+                                &spans,
+                                next_height,
+                                span_height,
+                                &time_params,
+                                ui,
+                            );
+                        }
 
-                        let line_color = colors::GRAY_230;
-                        ui.put(
-                            Rect::from_min_max(
-                                Pos2::new(node_names_area.min.x, cur_height),
-                                Pos2::new(node_names_area.max.x, next_height),
+                        let lane_events =
+                            match self.display_modes.get(self.current_display_mode_index) {
+                                Some(mode) => self.tabs[self.current_tab]
+                                    .event_search
+                                    .lane_results(mode, self.preferences.orphan_spans, &node_name),
+                                None => Vec::new(),
+                            };
+                        if !lane_events.is_empty() {
+                            let events_rect = Rect::from_min_max(
+                                Pos2::new(time_params.visual_start_x, next_height),
+                                Pos2::new(time_params.visual_end_x, next_height + span_height),
+                            );
+                            self.draw_event_lane(&lane_events, events_rect, &time_params, ui);
+                            next_height = events_rect.max.y;
+                        }
+                        let lane_log_lines = match lane_layout {
+                            LaneLayout::PerNode => self.node_logs.lines_in_range(
+                                &node.name,
+                                self.trace().timeline.selected_start,
+                                self.trace().timeline.selected_end,
                             ),
-                            Button::new(node_name)
-                                .fill(colors::ALMOST_BLACK)
+                            LaneLayout::PerSpanName => &[],
+                        };
+                        if !lane_log_lines.is_empty() {
+                            let logs_rect = Rect::from_min_max(
+                                Pos2::new(time_params.visual_start_x, next_height),
+                                Pos2::new(time_params.visual_end_x, next_height + span_height),
+                            );
+                            let to_x = |time| {
+                                time_to_screen(
+                                    time,
+                                    time_params.visual_start_x,
+                                    time_params.visual_end_x,
+                                    time_params.selected_start_time,
+                                    time_params.selected_end_time,
+                                )
+                            };
+                            if let Some(line) =
+                                draw_log_lane(ui, lane_log_lines, logs_rect, to_x, &node.name)
+                            {
+                                self.node_logs_panel.show_line(line);
+                            }
+                            next_height = logs_rect.max.y;
+                        }
+                        ui.style_mut().visuals.override_text_color = Some(theme().lane_name_text);
+
+                        let line_color = theme().lane_separator;
+                        let node_name_rect = Rect::from_min_max(
+                            Pos2::new(node_names_area.min.x, lane_top),
+                            Pos2::new(node_names_area.max.x, next_height),
+                        );
+                        let node_name_color = match lane_layout {
+                            LaneLayout::PerNode => node_color(&node.name),
+                            LaneLayout::PerSpanName => theme().lane_name_text,
+                        };
+                        if self.scroll_to_lane.as_ref() == Some(&node_name) {
+                            ui.scroll_to_rect(node_name_rect, Some(egui::Align::Center));
+                            self.scroll_to_lane = None;
+                        }
+                        ui.put(
+                            node_name_rect,
+                            Button::new(RichText::new(&node_name).color(node_name_color))
+                                .fill(theme().lane_name_background)
                                 .stroke(Stroke::new(1.0, line_color)),
                         );
                         ui.painter().line(
@@ -1226,9 +3876,56 @@ impl App {
                             ],
                             Stroke::new(1.0, line_color),
                         );
+                        self.draw_sticky_lane_header(
+                            RichText::new(node_name).color(node_name_color),
+                            spans_in_range,
+                            node_name_rect,
+                            spans_top,
+                            span_height,
+                            &time_params,
+                            &highlighted_span_ids_set,
+                            ui,
+                        );
                         cur_height = next_height;
                     }
 
+                    // The node might be hidden by the node filter, don't keep it for later.
+                    self.scroll_to_lane = None;
+
+                    self.handle_rubber_band_selection(&background_button, ui);
+
+                    if let Some(origin) = time_format.origin {
+                        if origin >= self.trace().timeline.selected_start
+                            && origin <= self.trace().timeline.selected_end
+                        {
+                            let x = time_to_screen(
+                                origin,
+                                time_params.visual_start_x,
+                                time_params.visual_end_x,
+                                time_params.selected_start_time,
+                                time_params.selected_end_time,
+                            );
+                            ui.painter().line_segment(
+                                [
+                                    Pos2::new(x, under_time_points_area.min.y),
+                                    Pos2::new(x, under_time_points_area.max.y),
+                                ],
+                                Stroke::new(1.0, theme().accent),
+                            );
+                        }
+                    }
+
+                    draw_external_event_lines(
+                        ui,
+                        &self.external_events,
+                        time_params.selected_start_time,
+                        time_params.selected_end_time,
+                        Rect::from_min_max(
+                            Pos2::new(time_params.visual_start_x, under_time_points_area.min.y),
+                            Pos2::new(time_params.visual_end_x, under_time_points_area.max.y),
+                        ),
+                    );
+
                     // Draw dependency arrows if needed
                     if !highlighted_span_ids_set.is_empty() {
                         #[cfg(feature = "profiling")]
@@ -1247,51 +3944,423 @@ impl App {
                             let Some(latest_pos) = i.pointer.latest_pos() else {
                                 return; // latest_pos can sometimes be None here.
                             };
+                            // With a split view only the pane under the pointer is zoomed.
+                            if self.trace().split_view.is_some() && !area.contains(latest_pos) {
+                                return;
+                            }
 
                             let mouse_time = screen_to_time(
                                 latest_pos.x,
                                 area.min.x + self.layout.node_name_width,
                                 area.max.x,
-                                self.timeline.selected_start,
-                                self.timeline.selected_end,
+                                self.trace().timeline.selected_start,
+                                self.trace().timeline.selected_end,
                             );
-                            let selected_len_before =
-                                self.timeline.selected_end - self.timeline.selected_start;
+                            let selected_len_before = self.trace().timeline.selected_end
+                                - self.trace().timeline.selected_start;
                             let selected_len_after = selected_len_before * delta;
-                            let new_selected_start =
-                                mouse_time - (mouse_time - self.timeline.selected_start) * delta;
+                            if selected_len_after < MIN_TIME_WINDOW && delta < 1.0 {
+                                return;
+                            }
+                            let new_selected_start = mouse_time
+                                - (mouse_time - self.trace().timeline.selected_start) * delta;
                             let new_selected_end = new_selected_start + selected_len_after;
 
-                            let visible_len_before =
-                                self.timeline.visible_end - self.timeline.visible_start;
+                            let visible_len_before = self.trace().timeline.visible_end
+                                - self.trace().timeline.visible_start;
                             let visible_len_after = visible_len_before * delta;
-                            let new_visible_start =
-                                mouse_time - (mouse_time - self.timeline.visible_start) * delta;
+                            let new_visible_start = mouse_time
+                                - (mouse_time - self.trace().timeline.visible_start) * delta;
                             let new_visible_end = new_visible_start + visible_len_after;
 
-                            self.timeline.selected_start = new_selected_start;
-                            self.timeline.selected_end = new_selected_end;
-                            self.timeline.visible_start = new_visible_start;
-                            self.timeline.visible_end = new_visible_end;
+                            self.trace_mut().timeline.selected_start = new_selected_start;
+                            self.trace_mut().timeline.selected_end = new_selected_end;
+                            self.trace_mut().timeline.visible_start = new_visible_start;
+                            self.trace_mut().timeline.visible_end = new_visible_end;
                             self.set_timeline_end_bars_to_selected();
                         }
                     })
                 });
-        });
+        });
+    }
+
+    /// Pins the lane's name, and optionally the first row of its top level spans, at the top of the
+    /// viewport when the top of the lane is scrolled out of view. `spans_top` is where the top level
+    /// spans of the lane are drawn.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_sticky_lane_header(
+        &mut self,
+        name: RichText,
+        spans_in_range: &[Rc<Span>],
+        node_name_rect: Rect,
+        spans_top: f32,
+        span_height: f32,
+        time_params: &TimeToScreenParams,
+        highlighted_span_ids: &HashSet<SpanId>,
+        ui: &mut Ui,
+    ) {
+        let sticky_header = self.preferences.sticky_lane_header;
+        if sticky_header == StickyLaneHeader::Off {
+            return;
+        }
+        let header_height = span_height + self.layout.span_margin;
+        let Some(header_top) = sticky_header_top(
+            node_name_rect.min.y,
+            node_name_rect.max.y,
+            ui.clip_rect().min.y,
+            header_height,
+        ) else {
+            return;
+        };
+
+        let line_color = theme().lane_separator;
+        if sticky_header.pins_top_level_spans() && spans_top < header_top {
+            ui.painter().rect_filled(
+                Rect::from_min_max(
+                    Pos2::new(time_params.visual_start_x, header_top),
+                    Pos2::new(time_params.visual_end_x, header_top + header_height),
+                ),
+                0,
+                theme().spans_background,
+            );
+            // The spans are drawn and interactive in their own row, the pinned copies are only
+            // painted.
+            for span in spans_in_range
+                .iter()
+                .filter(|s| s.parent_height_offset.get() == 0)
+            {
+                self.paint_pinned_span(
+                    span,
+                    ui,
+                    header_top,
+                    span_height,
+                    highlighted_span_ids.contains(&span.span_id),
+                );
+            }
+            ui.painter().line(
+                vec![
+                    Pos2::new(time_params.visual_start_x, header_top + header_height),
+                    Pos2::new(time_params.visual_end_x, header_top + header_height),
+                ],
+                Stroke::new(1.0, line_color),
+            );
+        }
+        ui.style_mut().visuals.override_text_color = Some(theme().lane_name_text);
+        ui.put(
+            Rect::from_min_size(
+                Pos2::new(node_name_rect.min.x, header_top),
+                Vec2::new(node_name_rect.width(), header_height),
+            ),
+            Button::new(name)
+                .truncate()
+                .fill(theme().lane_name_background)
+                .stroke(Stroke::new(1.0, line_color)),
+        );
+    }
+
+    /// Paints a top level span in the sticky lane header, without the interactions of
+    /// [Self::draw_arranged_span].
+    fn paint_pinned_span(
+        &self,
+        span: &Rc<Span>,
+        ui: &Ui,
+        start_height: f32,
+        span_height: f32,
+        is_highlighted: bool,
+    ) {
+        let theme = theme();
+        let painter = ui.painter();
+        let start_x = span.display_start.get();
+        let end_x = start_x + span.display_length.get().max(0.0);
+        let display_rect = Rect::from_min_max(
+            Pos2::new(start_x, start_height),
+            Pos2::new(end_x, start_height + span_height),
+        );
+
+        let top_line_color = if let Some(active_segments) = &span.active_segments {
+            let (active_color, gap_color) = if is_highlighted {
+                (
+                    theme.highlighted_span_time,
+                    theme.highlighted_span_background,
+                )
+            } else {
+                (self.span_time_color(span), theme.span_background)
+            };
+            painter.rect_filled(display_rect, 0, gap_color);
+            for (segment_start, segment_end) in active_segments {
+                let segment_rect = Self::grouped_span_segment_to_rect(
+                    *segment_start,
+                    *segment_end,
+                    span,
+                    start_x,
+                    start_height,
+                    span_height,
+                );
+                painter.rect_filled(segment_rect, 0, active_color);
+            }
+            theme.grouped_span_line
+        } else {
+            let (time_color, base_color) = self.span_colors(span, is_highlighted);
+            let time_rect = Rect::from_min_max(
+                Pos2::new(start_x, start_height),
+                Pos2::new(
+                    start_x + span.time_display_length.get().max(0.0),
+                    start_height + span_height,
+                ),
+            );
+            painter.rect_filled(display_rect, 0, base_color);
+            painter.rect_filled(time_rect, 0, time_color);
+            theme.top_level_line
+        };
+        if is_highlighted {
+            painter.rect_stroke(
+                display_rect,
+                0,
+                Stroke::new(2.5, theme.highlighted_span_border),
+                egui::StrokeKind::Middle,
+            );
+        }
+        painter.line_segment(
+            [display_rect.left_top(), display_rect.right_top()],
+            Stroke::new(2.0, top_line_color),
+        );
+        if display_rect.width() > self.layout.span_name_threshold {
+            painter.with_clip_rect(display_rect).text(
+                display_rect.center(),
+                Align2::CENTER_CENTER,
+                &span.name,
+                egui::TextStyle::Button.resolve(ui.style()),
+                theme.span_text,
+            );
+        }
+    }
+
+    /// Dragging over the background with the primary button selects the spans inside of the dragged
+    /// rectangle. With Ctrl held the spans are added to the current selection.
+    fn handle_rubber_band_selection(&mut self, background_button: &Response, ui: &Ui) {
+        let Some(start) = self.rubber_band_start else {
+            return;
+        };
+        let Some(current) = ui.input(|i| i.pointer.latest_pos()) else {
+            return;
+        };
+        let rect = Rect::from_two_pos(start, current);
+        if background_button.dragged_by(PointerButton::Primary) {
+            ui.painter().rect(
+                rect,
+                0,
+                theme().selection_fill,
+                Stroke::new(1.0, theme().selection_outline),
+                egui::StrokeKind::Inside,
+            );
+        }
+        if background_button.drag_stopped() {
+            self.rubber_band_start = None;
+            if !ui.input(|i| i.modifiers.command) {
+                self.trace_mut().span_selection.clear();
+            }
+            self.tabs[self.current_tab]
+                .span_selection
+                .extend(spans_in_rect(&self.drawn_span_rects, rect));
+        }
+    }
+
+    fn draw_span_catalog(&mut self, ctx: &egui::Context) {
+        let (start, end) = (
+            self.trace().timeline.selected_start,
+            self.trace().timeline.selected_end,
+        );
+        match self.trace_mut().span_catalog.draw(ctx, start, end) {
+            Some(SpanCatalogAction::Highlight(name)) => {
+                self.trace_mut().highlighted_spans =
+                    self.trace().span_catalog.spans_with_name(&name, start, end);
+            }
+            Some(SpanCatalogAction::Analyze(name)) => {
+                let tab = self.trace_mut();
+                tab.analyze_span_modal.selected_time_range = (start, end);
+                tab.analyze_span_modal
+                    .open_for_span_name(&tab.all_spans_for_analysis, &name);
+            }
+            Some(SpanCatalogAction::Breakdown(name)) => {
+                let tab = self.trace_mut();
+                tab.child_breakdown_modal
+                    .open_for_span_name(&tab.all_spans_for_analysis, &name);
+            }
+            None => {}
+        }
+    }
+
+    fn draw_attribute_facets(&mut self, ctx: &egui::Context) {
+        let (start, end) = (
+            self.trace().timeline.selected_start,
+            self.trace().timeline.selected_end,
+        );
+        match self.trace_mut().attribute_facets.draw(ctx, start, end) {
+            Some(FacetAction::Highlight(value)) => {
+                self.trace_mut().highlighted_spans = self
+                    .trace()
+                    .attribute_facets
+                    .spans_with_value(&value, start, end);
+            }
+            Some(FacetAction::FilterChanged) => {
+                self.cached_node_spans = None;
+                self.arrangement_cache.invalidate();
+            }
+            None => {}
+        }
+    }
+
+    fn draw_selection_panel(&mut self, ctx: &egui::Context) {
+        let Some(action) = draw_selection_panel(
+            ctx,
+            &self.trace().span_selection,
+            self.trace().pinned_span_ids.len(),
+            &self.time_format(),
+        ) else {
+            return;
+        };
+        match action {
+            SelectionAction::Highlight => {
+                let tab = self.trace_mut();
+                tab.highlighted_spans = tab.span_selection.spans().to_vec();
+            }
+            SelectionAction::Pin => {
+                let tab = self.trace_mut();
+                tab.pinned_span_ids
+                    .extend(tab.span_selection.spans().iter().map(|s| s.span_id));
+                tab.span_selection.clear();
+            }
+            SelectionAction::UnpinAll => self.trace_mut().pinned_span_ids.clear(),
+            SelectionAction::Export => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .set_file_name("traviz_selection.csv")
+                    .save_file()
+                {
+                    match std::fs::write(
+                        &path,
+                        selection_to_csv(self.trace().span_selection.spans()),
+                    ) {
+                        Ok(()) => self.notifications.info(format!(
+                            "Exported {} spans to {}",
+                            self.trace().span_selection.len(),
+                            path.display()
+                        )),
+                        Err(e) => self
+                            .notifications
+                            .error(format!("Error exporting selection: {e}")),
+                    }
+                }
+            }
+            SelectionAction::Clear => self.trace_mut().span_selection.clear(),
+        }
+    }
+
+    /// Strip with the number of open spans matching the concurrency overlay's selector, drawn at
+    /// `top`. Returns the y coordinate under the strip.
+    fn draw_concurrency_overlay(
+        &mut self,
+        lane_name: &str,
+        spans: &SpanIntervalIndex,
+        top: f32,
+        span_height: f32,
+        time_params: &TimeToScreenParams,
+        ui: &mut Ui,
+    ) -> f32 {
+        let rect = Rect::from_min_max(
+            Pos2::new(time_params.visual_start_x, top),
+            Pos2::new(time_params.visual_end_x, top + span_height),
+        );
+        let (start, end) = (
+            self.trace().timeline.selected_start,
+            self.trace().timeline.selected_end,
+        );
+        let selector = &self.preferences.concurrency_overlay.selector;
+        let pixel_width = quantize_bucket_width((end - start) / f64::from(rect.width()));
+        let (steps, _) = self.concurrency_cache.get_or_compute(
+            lane_name,
+            spans.id(),
+            start,
+            end,
+            pixel_width,
+            |range| {
+                compute_concurrency(
+                    spans.query(range.start, range.end),
+                    selector,
+                    range.start,
+                    range.end,
+                )
+            },
+        );
+        draw_concurrency_strip(ui, rect, steps, start, end, lane_name);
+        rect.max.y + self.layout.span_margin
+    }
+
+    /// Strip under a lane with markers at the times of events found by the event search.
+    fn draw_event_lane(
+        &mut self,
+        events: &[EventMatch],
+        rect: Rect,
+        time_params: &TimeToScreenParams,
+        ui: &mut Ui,
+    ) {
+        let time_format = self.time_format();
+        ui.painter().rect_filled(rect, 0.0, theme().lane_background);
+        for event_match in events {
+            let time = event_match.time();
+            if time < time_params.selected_start_time || time > time_params.selected_end_time {
+                continue;
+            }
+            let x = time_to_screen(
+                time,
+                time_params.visual_start_x,
+                time_params.visual_end_x,
+                time_params.selected_start_time,
+                time_params.selected_end_time,
+            );
+            ui.painter().line_segment(
+                [Pos2::new(x, rect.min.y), Pos2::new(x, rect.max.y)],
+                Stroke::new(2.0, theme().event_marker),
+            );
+            let marker_rect = Rect::from_min_max(
+                Pos2::new(x - 3.0, rect.min.y),
+                Pos2::new(x + 3.0, rect.max.y),
+            );
+            let event = event_match.event();
+            let response = ui
+                .interact(
+                    marker_rect,
+                    ui.id()
+                        .with(("event lane", event_match.span.span_id, event.time.to_bits())),
+                    Sense::click(),
+                )
+                .on_hover_ui_at_pointer(|ui| {
+                    ui.label(RichText::new(&event.name).strong());
+                    ui.label(format!("span: {}", event_match.span.name));
+                    ui.label(format!("node: {}", event_match.span.node.name));
+                    ui.label(time_format.display_string(event.time));
+                    for (name, value) in &event.attributes {
+                        ui.label(format!("{}: {}", name, value_to_text(value)));
+                    }
+                });
+            if response.clicked() {
+                self.trace_mut().clicked_span = Some(event_match.span.clone());
+            }
+        }
     }
 
     fn shift_selected_time(&mut self, shift: TimePoint) {
-        self.timeline.selected_start += shift;
-        self.timeline.selected_end += shift;
-        self.timeline.visible_start += shift;
-        self.timeline.visible_end += shift;
-        self.timeline_bar1_time += shift;
-        self.timeline_bar2_time += shift;
+        self.trace_mut().timeline.selected_start += shift;
+        self.trace_mut().timeline.selected_end += shift;
+        self.trace_mut().timeline.visible_start += shift;
+        self.trace_mut().timeline.visible_end += shift;
+        self.trace_mut().timeline_bar1_time += shift;
+        self.trace_mut().timeline_bar2_time += shift;
     }
 
     fn set_display_params_with_highlights(
         spans: &[Rc<Span>],
-        highlighted_span_ids: &HashSet<Vec<u8>>,
+        highlighted_span_ids: &HashSet<SpanId>,
         start_time: TimePoint,
         end_time: TimePoint,
         start_pos: f32,
@@ -1315,9 +4384,13 @@ impl App {
                 DisplayLength::Time => time_display_len,
                 DisplayLength::Text => {
                     let text_len = ui.fonts(|fs| {
-                        fs.layout_no_wrap(span.name.to_string(), FontId::default(), colors::BLACK)
-                            .rect
-                            .width()
+                        fs.layout_no_wrap(
+                            span.name.to_string(),
+                            FontId::default(),
+                            theme().span_text,
+                        )
+                        .rect
+                        .width()
                     });
                     text_len.max(time_display_len)
                 }
@@ -1353,6 +4426,8 @@ impl App {
         }
     }
 
+    /// `root` is the top level span which the spans are under, `None` for the top level spans.
+    #[allow(clippy::too_many_arguments)]
     fn draw_arranged_spans(
         &mut self,
         spans: &[Rc<Span>],
@@ -1360,12 +4435,14 @@ impl App {
         start_height: f32,
         span_height: f32,
         level: u64,
-        highlighted_span_ids: &HashSet<Vec<u8>>,
+        root: Option<&Rc<Span>>,
+        highlighted_span_ids: &HashSet<SpanId>,
     ) {
         #[cfg(feature = "profiling")]
         let _timing_guard = profiling::GLOBAL_PROFILER.start_timing("draw_arranged_spans");
 
         for span_rc in spans {
+            let root = root.unwrap_or(span_rc);
             let current_span_draw_y = start_height
                 + span_rc.parent_height_offset.get() as f32
                     * (span_height + self.layout.span_margin);
@@ -1379,6 +4456,7 @@ impl App {
                     current_span_draw_y,
                     span_height,
                     level,
+                    root,
                     highlighted_span_ids,
                 );
             }
@@ -1396,6 +4474,7 @@ impl App {
                         children_block_start_y,
                         span_height,
                         level + 1,
+                        Some(root),
                         highlighted_span_ids,
                     );
                 }
@@ -1403,6 +4482,7 @@ impl App {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn draw_arranged_span(
         &mut self,
         span: &Rc<Span>,
@@ -1410,8 +4490,10 @@ impl App {
         start_height: f32,
         span_height: f32,
         level: u64,
-        highlighted_span_ids: &HashSet<Vec<u8>>,
+        root: &Rc<Span>,
+        highlighted_span_ids: &HashSet<SpanId>,
     ) {
+        let time_format = self.time_format();
         if span.active_segments.is_some() {
             self.draw_grouped_span(
                 span,
@@ -1440,14 +4522,8 @@ impl App {
                 ""
             };
 
-            // Set colors based on whether it's a highlighted span
-            let (time_color, base_color) = if is_highlighted {
-                // Use blue color for highlighted spans
-                (colors::INTENSE_BLUE, colors::VERY_LIGHT_BLUE)
-            } else {
-                // Use yellow/gold colors for normal spans
-                (colors::DARK_YELLOW, colors::VERY_LIGHT_YELLOW)
-            };
+            let theme = theme();
+            let (time_color, base_color) = self.span_colors(span, is_highlighted);
 
             let time_rect = Rect::from_min_max(
                 Pos2::new(start_x, start_height),
@@ -1465,7 +4541,7 @@ impl App {
 
             // Highlighted spans also have a nice border around them
             if is_highlighted {
-                let border_stroke = Stroke::new(2.5, colors::INTENSE_BLUE2);
+                let border_stroke = Stroke::new(2.5, theme.highlighted_span_border);
                 let points = vec![
                     display_rect.min,
                     Pos2::new(display_rect.max.x, display_rect.min.y),
@@ -1476,6 +4552,57 @@ impl App {
                 ui.painter().add(border_shape);
             }
 
+            // Selected spans get a white outline, pinned ones a green one
+            let is_selected = self.trace().span_selection.contains(span.span_id);
+            let is_pinned = self.trace().pinned_span_ids.contains(&span.span_id);
+            if is_selected || is_pinned {
+                let stroke = if is_selected {
+                    Stroke::new(2.0, theme.selection_outline)
+                } else {
+                    Stroke::new(2.5, theme.accent)
+                };
+                ui.painter()
+                    .rect_stroke(display_rect, 0, stroke, egui::StrokeKind::Inside);
+            }
+            if self.unmatched_relations.is_marked(span.span_id) {
+                ui.painter().circle_filled(
+                    display_rect.right_top(),
+                    UNMATCHED_BADGE_RADIUS,
+                    theme.unmatched_span,
+                );
+            }
+            // A span under a parent from another node, the parent's lane holds spans of two nodes
+            if span.remote_parent && level > 0 {
+                ui.painter().rect_stroke(
+                    display_rect,
+                    0,
+                    Stroke::new(1.5, theme.remote_span),
+                    egui::StrokeKind::Inside,
+                );
+                if display_rect.width() > self.layout.span_name_threshold {
+                    ui.painter().text(
+                        display_rect.left_bottom() + Vec2::new(2.0, -1.0),
+                        Align2::LEFT_BOTTOM,
+                        "remote",
+                        FontId::proportional(REMOTE_BADGE_FONT_SIZE),
+                        theme.remote_span,
+                    );
+                }
+            }
+            let has_children = span.has_children();
+            let depth_cut =
+                has_children && self.depth_limit.cuts_children(span.span_id, level as usize);
+            if depth_cut && display_rect.width() > self.layout.span_name_threshold {
+                ui.painter().text(
+                    display_rect.right_center() - Vec2::new(2.0, 0.0),
+                    Align2::RIGHT_CENTER,
+                    "+",
+                    FontId::proportional(DEPTH_BADGE_FONT_SIZE),
+                    theme.accent,
+                );
+            }
+            self.drawn_span_rects.push((display_rect, span.clone()));
+
             if level == 0 {
                 // Top level spans get a color line at the top
                 ui.painter().line(
@@ -1483,7 +4610,7 @@ impl App {
                         Pos2::new(start_x, start_height),
                         Pos2::new(end_x, start_height),
                     ],
-                    Stroke::new(2.0, colors::INTENSE_RED),
+                    Stroke::new(2.0, theme.top_level_line),
                 );
             }
 
@@ -1495,30 +4622,91 @@ impl App {
             );
 
             if span_button.clicked_by(PointerButton::Primary) {
-                self.clicked_span = Some(span.clone());
+                if ui.input(|i| i.modifiers.command) {
+                    self.trace_mut().span_selection.toggle(span);
+                } else {
+                    self.trace_mut().clicked_span = Some(span.clone());
+                }
             }
 
             if span_button.clicked_by(PointerButton::Middle) {
-                span.collapse_children.set(!span.collapse_children.get());
+                let collapse = !span.collapse_children.get();
+                span.collapse_children.set(collapse);
+                self.collapse_memory.remember_span(
+                    &self.current_mode_name(),
+                    &span.original_name,
+                    collapse,
+                );
+                self.arrangement_cache.invalidate();
+            }
+
+            span_button.context_menu(|ui| {
+                if depth_cut || self.depth_limit.is_expanded(span.span_id) {
+                    let label = if depth_cut {
+                        "Show children below the max depth"
+                    } else {
+                        "Hide children below the max depth"
+                    };
+                    if ui.button(label).clicked() {
+                        self.depth_limit.toggle_expanded(span.span_id);
+                        self.arrangement_cache.invalidate();
+                        ui.close_menu();
+                    }
+                }
+                if ui
+                    .button(format!("Hide spans like this ({})", span.original_name))
+                    .on_hover_text("Don't display spans with this name in any mode")
+                    .clicked()
+                {
+                    self.mute_request = Some(SpanSelector::new_equal_name(&span.original_name));
+                    ui.close_menu();
+                }
+                if !span.trace_id.is_empty()
+                    && ui
+                        .button("Focus this trace")
+                        .on_hover_text("Tint all spans with this trace id")
+                        .clicked()
+                {
+                    self.focus_trace(Some(span.trace_id));
+                    ui.close_menu();
+                }
+            });
+
+            if self.show_event_markers() {
+                Self::draw_event_markers(span, ui, time_rect, &time_format);
+            }
+
+            if span_button.hovered() && level > 0 {
+                let hovered_again = self
+                    .breadcrumb
+                    .last()
+                    .is_some_and(|last| Rc::ptr_eq(last, span));
+                if !hovered_again {
+                    self.breadcrumb = ancestor_chain(root, span.span_id).unwrap_or_default();
+                }
             }
 
             span_button.on_hover_ui_at_pointer(|ui| {
+                if level > 0 && !self.breadcrumb.is_empty() {
+                    ui.label(RichText::new(breadcrumb_text(&self.breadcrumb)).weak());
+                }
                 ui.label(span.name.clone());
+                ui.label(format!("node: {}", span.node.name));
                 ui.separator();
-                ui.label(format!(
-                    "{:.3} ms",
-                    (span.end_time - span.start_time) * MILLISECONDS_PER_SECOND
-                ));
+                ui.label(format_duration(span.end_time - span.start_time));
                 ui.label(format!(
                     "{} - {}",
-                    time_point_to_utc_string(span.start_time),
-                    time_point_to_utc_string(span.end_time)
-                ));
-                ui.label(format!("span_id: {}", hex::encode(&span.span_id)));
-                ui.label(format!(
-                    "parent_span_id: {}",
-                    hex::encode(&span.parent_span_id)
+                    time_format.display_string(span.start_time),
+                    time_format.display_string(span.end_time)
                 ));
+                ui.label(format!("span_id: {}", span.span_id.to_hex()));
+                ui.label(format!("parent_span_id: {}", span.parent_span_id.to_hex()));
+                if span.remote_parent {
+                    ui.label("The parent span is on another node");
+                }
+                if depth_cut {
+                    ui.label("The children are below the max depth, see the right click menu");
+                }
                 ui.separator();
                 for (name, value) in &span.attributes {
                     ui.label(format!("{}: {}", name, value_to_text(value)));
@@ -1534,19 +4722,296 @@ impl App {
         }
     }
 
+    /// Lanes of the spans in the view, as they are currently displayed, for the exports.
+    fn export_lanes(&self) -> Vec<ExportLane> {
+        let node_filter = self.node_filters.get(self.current_node_filter_index);
+        let mut lanes: BTreeMap<String, Vec<Rc<Span>>> = BTreeMap::new();
+        for span in &self.spans_to_display {
+            if node_filter.is_some_and(|f| !f.should_show_span(&span.node.name))
+                || !self.shows_top_level_span(span)
+            {
+                continue;
+            }
+            lanes
+                .entry(self.lane_name(span))
+                .or_default()
+                .push(span.clone());
+        }
+        lanes
+            .into_iter()
+            .map(|(name, spans)| ExportLane { name, spans })
+            .collect()
+    }
+
+    fn export_title(&self) -> String {
+        let mode_name = self
+            .display_modes
+            .get(self.current_display_mode_index)
+            .map_or("", |mode| mode.name.as_str());
+        format!(
+            "traviz - {} - {}",
+            mode_name,
+            time_point_to_utc_string(self.trace().timeline.selected_start)
+        )
+    }
+
+    /// Writes the spans in the selected range, as they are currently displayed, to an HTML file.
+    fn export_html(&self, path: &PathBuf) -> Result<()> {
+        let highlighted_span_ids: HashSet<SpanId> = self
+            .trace()
+            .highlighted_spans
+            .iter()
+            .map(|s| s.span_id)
+            .collect();
+        let html = html_export::export_html(
+            &self.export_title(),
+            &self.export_lanes(),
+            self.trace().timeline.selected_start,
+            self.trace().timeline.selected_end,
+            &highlighted_span_ids,
+        );
+        std::fs::write(path, html)?;
+        Ok(())
+    }
+
+    /// Writes the spans in the selected range to an SVG image `svg_export_width` pixels wide.
+    fn export_svg(&self, path: &PathBuf) -> Result<()> {
+        let highlighted_span_ids: HashSet<SpanId> = self
+            .trace()
+            .highlighted_spans
+            .iter()
+            .map(|s| s.span_id)
+            .collect();
+        let svg = image_export::export_svg(
+            &self.export_title(),
+            &self.export_lanes(),
+            self.trace().timeline.selected_start,
+            self.trace().timeline.selected_end,
+            self.svg_export_width,
+            &highlighted_span_ids,
+        );
+        std::fs::write(path, svg)?;
+        Ok(())
+    }
+
+    /// Requests the screenshot for a PNG export and saves the spans area once it arrives.
+    fn poll_screenshot_export(&mut self, ctx: &egui::Context) {
+        match self.screenshot_export.take() {
+            Some(ScreenshotExport::Requested(path)) => {
+                self.screenshot_export = Some(ScreenshotExport::TakeScreenshot(path));
+                ctx.request_repaint();
+            }
+            Some(ScreenshotExport::TakeScreenshot(path)) => {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot(Default::default()));
+                self.screenshot_export = Some(ScreenshotExport::WaitingForScreenshot(path));
+                ctx.request_repaint();
+            }
+            Some(ScreenshotExport::WaitingForScreenshot(path)) => {
+                let screenshot = ctx.input(|i| {
+                    i.raw.events.iter().find_map(|event| match event {
+                        egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                        _ => None,
+                    })
+                });
+                let Some(screenshot) = screenshot else {
+                    self.screenshot_export = Some(ScreenshotExport::WaitingForScreenshot(path));
+                    ctx.request_repaint();
+                    return;
+                };
+                let image = image_export::crop_screenshot(
+                    &screenshot,
+                    self.spans_area,
+                    ctx.pixels_per_point(),
+                );
+                match std::fs::write(&path, image_export::encode_png(&image)) {
+                    Ok(()) => self
+                        .notifications
+                        .info(format!("Exported image to {}", path.display())),
+                    Err(e) => self
+                        .notifications
+                        .error(format!("Error exporting image: {e}")),
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn show_event_markers(&self) -> bool {
+        self.display_modes
+            .get(self.current_display_mode_index)
+            .is_some_and(|mode| mode.show_event_markers)
+    }
+
+    /// Ticks at the bottom of the span at the times of its events, hovering a tick shows the event.
+    fn draw_event_markers(span: &Rc<Span>, ui: &mut Ui, time_rect: Rect, time_format: &TimeFormat) {
+        // Markers would cover the whole span if it's too short
+        const MIN_SPAN_WIDTH: f32 = 6.0;
+        if span.events.is_empty() || time_rect.width() < MIN_SPAN_WIDTH {
+            return;
+        }
+        let tick_top = time_rect.min.y + time_rect.height() * 0.5;
+        for (i, (x, event)) in span.event_marker_positions().into_iter().enumerate() {
+            ui.painter().line_segment(
+                [Pos2::new(x, tick_top), Pos2::new(x, time_rect.max.y)],
+                Stroke::new(1.5, theme().event_tick),
+            );
+            let hover_rect = Rect::from_min_max(
+                Pos2::new(x - 2.0, tick_top),
+                Pos2::new(x + 2.0, time_rect.max.y),
+            );
+            ui.interact(
+                hover_rect,
+                ui.id().with(("event marker", span.span_id, i)),
+                Sense::hover(),
+            )
+            .on_hover_ui_at_pointer(|ui| {
+                ui.label(RichText::new(&event.name).strong());
+                ui.label(time_format.display_string(event.time));
+                for (name, value) in &event.attributes {
+                    ui.label(format!("{}: {}", name, value_to_text(value)));
+                }
+            });
+        }
+    }
+
+    fn current_lane_layout(&self) -> LaneLayout {
+        self.display_modes
+            .get(self.current_display_mode_index)
+            .map(|mode| mode.lane_layout)
+            .unwrap_or_default()
+    }
+
+    /// Sorts the lanes by node section of the current node filter and returns (section name,
+    /// number of visible lanes in the section) for each lane. Returns `None`s when the filter
+    /// doesn't have sections, or when the lanes aren't per node.
+    fn node_sections_for_lanes(
+        &mut self,
+        lanes: &mut NodeSpansVec,
+    ) -> Vec<Option<(String, usize)>> {
+        let node_filter = self.node_filters.get(self.current_node_filter_index);
+        let Some(node_filter) = node_filter.filter(|f| {
+            !f.sections.is_empty() && self.current_lane_layout() == LaneLayout::PerNode
+        }) else {
+            return vec![None; lanes.len()];
+        };
+        lanes.sort_by_cached_key(|(_, (node, _))| node_filter.section_index(node));
+
+        let mut lane_counts: HashMap<usize, usize> = HashMap::new();
+        let (start, end) = (
+            self.trace().timeline.selected_start,
+            self.trace().timeline.selected_end,
+        );
+        for (_, (node, spans)) in lanes.iter() {
+            if node_filter.should_show_span(&node.name)
+                && self
+                    .span_content_cache
+                    .has_matching_spans(node_filter, spans, start, end)
+            {
+                *lane_counts
+                    .entry(node_filter.section_index(node))
+                    .or_default() += 1;
+            }
+        }
+        lanes
+            .iter()
+            .map(|(_, (node, _))| {
+                let section_index = node_filter.section_index(node);
+                Some((
+                    node_filter.section_name(section_index).to_string(),
+                    lane_counts.get(&section_index).copied().unwrap_or(0),
+                ))
+            })
+            .collect()
+    }
+
+    /// Header above the lanes of a node section, clicking it collapses or expands the section.
+    fn draw_node_section_header(
+        collapsed_sections: &mut HashSet<String>,
+        section_name: &str,
+        lane_count: usize,
+        rect: Rect,
+        ui: &mut Ui,
+    ) {
+        let collapsed = collapsed_sections.contains(section_name);
+        let arrow = if collapsed { "⏵" } else { "⏷" };
+        let lanes_text = if lane_count == 1 { "lane" } else { "lanes" };
+        let response = ui
+            .put(
+                rect,
+                Button::new(
+                    RichText::new(format!(
+                        "{arrow} {section_name} ({lane_count} {lanes_text})"
+                    ))
+                    .strong()
+                    .color(theme().lane_name_text),
+                )
+                .fill(theme().time_points_background),
+            )
+            .on_hover_text("Click to collapse or expand the section");
+        if response.clicked() {
+            if collapsed {
+                collapsed_sections.remove(section_name);
+            } else {
+                collapsed_sections.insert(section_name.to_string());
+            }
+        }
+    }
+
+    /// Name of the lane (or sub-lane) in which the span is displayed in the current mode.
+    fn lane_name(&self, span: &Span) -> String {
+        span_lane_name(
+            self.display_modes.get(self.current_display_mode_index),
+            self.preferences.orphan_spans,
+            span,
+        )
+    }
+
+    /// When lanes are per span name, the node is shown using the span's color.
+    /// Colors of the time part and the rest of a span which isn't grouped, `(time, background)`.
+    fn span_colors(&self, span: &Span, is_highlighted: bool) -> (Color32, Color32) {
+        let theme = theme();
+        if is_highlighted {
+            // Use blue color for highlighted spans
+            return (
+                theme.highlighted_span_time,
+                theme.highlighted_span_background,
+            );
+        }
+        // Use yellow/gold colors for normal spans
+        let time_color = if self.trace().trace_focus.is_tinted(span) {
+            theme.focused_trace
+        } else {
+            self.span_time_color(span)
+        };
+        if self.duration_filter.dims(span) {
+            (
+                time_color.gamma_multiply(DIMMED_SPAN_OPACITY),
+                theme.span_background.gamma_multiply(DIMMED_SPAN_OPACITY),
+            )
+        } else {
+            (time_color, theme.span_background)
+        }
+    }
+
+    fn span_time_color(&self, span: &Span) -> Color32 {
+        match self.current_lane_layout() {
+            LaneLayout::PerNode if span.active_segments.is_some() => theme().grouped_span_active,
+            LaneLayout::PerNode => theme().span_time,
+            LaneLayout::PerSpanName => node_color(&span.node.name),
+        }
+    }
+
     fn add_grouped_span_hover_tooltip(&self, span_button: Response, span: &Span) {
+        let time_format = self.time_format();
         span_button.on_hover_ui_at_pointer(|ui| {
             ui.label(span.name.clone().to_string());
             ui.separator();
 
-            ui.label(format!(
-                "{:.3} ms",
-                (span.end_time - span.start_time) * MILLISECONDS_PER_SECOND
-            ));
+            ui.label(format_duration(span.end_time - span.start_time));
             ui.label(format!(
                 "{} - {}",
-                time_point_to_utc_string(span.start_time),
-                time_point_to_utc_string(span.end_time)
+                time_format.display_string(span.start_time),
+                time_format.display_string(span.end_time)
             ));
             ui.separator();
 
@@ -1623,7 +5088,7 @@ impl App {
         start_height: f32,
         span_height: f32,
         level: u64,
-        highlighted_span_ids: &HashSet<Vec<u8>>,
+        highlighted_span_ids: &HashSet<SpanId>,
     ) {
         let visible_rect = ui.clip_rect();
 
@@ -1642,10 +5107,14 @@ impl App {
             ""
         };
 
+        let theme = theme();
         let (active_color, gap_color) = if is_highlighted {
-            (colors::INTENSE_BLUE, colors::VERY_LIGHT_BLUE)
+            (
+                theme.highlighted_span_time,
+                theme.highlighted_span_background,
+            )
         } else {
-            (colors::DARK_YELLOW, colors::VERY_LIGHT_YELLOW)
+            (self.span_time_color(span), theme.span_background)
         };
 
         // Draw the full span range with gap color
@@ -1676,7 +5145,7 @@ impl App {
 
         // Highlighted spans get a border
         if is_highlighted {
-            let border_stroke = Stroke::new(2.5, colors::INTENSE_BLUE2);
+            let border_stroke = Stroke::new(2.5, theme.highlighted_span_border);
             let points = vec![
                 full_rect.min,
                 Pos2::new(full_rect.max.x, full_rect.min.y),
@@ -1694,7 +5163,7 @@ impl App {
                     Pos2::new(start_x, start_height),
                     Pos2::new(end_x, start_height),
                 ],
-                Stroke::new(2.0, colors::INTENSE_GREEN),
+                Stroke::new(2.0, theme.grouped_span_line),
             );
         }
 
@@ -1704,20 +5173,32 @@ impl App {
         );
 
         if full_span_button.clicked_by(PointerButton::Primary) {
-            self.clicked_span = Some(span.clone());
+            self.trace_mut().clicked_span = Some(span.clone());
         }
 
         self.add_grouped_span_hover_tooltip(full_span_button, span);
     }
 
     fn draw_clicked_span(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
-        if self.clicked_span.is_none() {
+        let time_format = self.time_format();
+        if self.trace().clicked_span.is_none() {
             return;
         }
 
+        let mut analyze_span = false;
+        let mut analyze_dependency = false;
+        let mut child_breakdown = false;
+        let mut span_overlay = false;
+        let mut clicked_attribute = None;
+        let mut follow_span = None;
+        let mut jump_to_grouped_span = None;
+        let mut ungroup = false;
+        let mut focus_trace = None;
+        let mut open_source = None;
+        let mut clicked_log_line: Option<NodeLogLine> = None;
         Modal::new("clicked span".into()).show(ctx, |ui| {
             ui.vertical(|ui| {
-                let span = self.clicked_span.as_ref().unwrap();
+                let span = &self.trace().clicked_span.clone().unwrap();
                 ui.set_max_width(max_width);
                 ui.set_max_height(max_height);
 
@@ -1727,24 +5208,107 @@ impl App {
                     ui.set_max_width(max_width);
                 };
 
-                let close_button = ui.button("Close");
+                let mut close_button = None;
+                ui.horizontal(|ui| {
+                    close_button = Some(ui.button("Close"));
+                    if ui
+                        .button("Set start as t=0")
+                        .on_hover_text("Show all times relative to the start of this span")
+                        .clicked()
+                    {
+                        self.trace_mut().time_origin = Some(span.start_time);
+                    }
+                    if ui
+                        .button("Analyze span")
+                        .on_hover_text("Duration statistics of all spans with this name")
+                        .clicked()
+                    {
+                        analyze_span = true;
+                    }
+                    if ui
+                        .button("Child breakdown")
+                        .on_hover_text("Where spans with this name spend their time")
+                        .clicked()
+                    {
+                        child_breakdown = true;
+                    }
+                    if ui
+                        .button("Overlay")
+                        .on_hover_text(
+                            "All spans with this name aligned at their start and drawn on top of \
+                             each other",
+                        )
+                        .clicked()
+                    {
+                        span_overlay = true;
+                    }
+                    if ui
+                        .button("Analyze dependency")
+                        .on_hover_text("Analyze dependency with spans of this name as the source")
+                        .clicked()
+                    {
+                        analyze_dependency = true;
+                    }
+                    if !span.grouped_spans.is_empty()
+                        && ui
+                            .button("Ungroup")
+                            .on_hover_text(
+                                "Show the individual spans instead of the group, until the display mode is applied again",
+                            )
+                            .clicked()
+                    {
+                        ungroup = true;
+                    }
+                });
+                let close_button = close_button.unwrap();
                 draw_separator(ui);
-                ui.label(span.name.clone());
+                ui.horizontal(|ui| {
+                    ui.label(span.name.clone());
+                    copy_button(ui, "Copy the span name", || span.name.to_string());
+                });
                 ui.label("");
-                ui.label(format!(
-                    "{:.3} ms",
-                    (span.end_time - span.start_time) * MILLISECONDS_PER_SECOND
-                ));
-                ui.label(format!(
-                    "{} - {}",
-                    time_point_to_utc_string(span.start_time),
-                    time_point_to_utc_string(span.end_time)
-                ));
+                ui.label(format_duration(span.end_time - span.start_time));
+                ui.horizontal(|ui| {
+                    let time_range = format!(
+                        "{} - {}",
+                        time_format.display_string(span.start_time),
+                        time_format.display_string(span.end_time)
+                    );
+                    ui.label(&time_range);
+                    copy_button(ui, "Copy the start and end time", || time_range);
+                });
 
                 if span.active_segments.is_some() {
                     // Grouped span
                     draw_separator(ui);
-                    if let Some(Some(Value::StringValue(spans_info))) =
+                    if !span.grouped_spans.is_empty() {
+                        ui.label(format!("Individual Spans ({}):", span.grouped_spans.len()));
+                        ScrollArea::vertical()
+                            .id_salt("clicked span grouped spans")
+                            .max_height(200.0)
+                            .show(ui, |ui| {
+                                for grouped_span in &span.grouped_spans {
+                                    ui.horizontal(|ui| {
+                                        if ui
+                                            .button("Jump")
+                                            .on_hover_text("Show this span on the timeline")
+                                            .clicked()
+                                        {
+                                            jump_to_grouped_span = Some(grouped_span.clone());
+                                        }
+                                        ui.label(format!(
+                                            "{}: {} [{} - {}]",
+                                            grouped_span.name,
+                                            format_duration(
+                                                grouped_span.end_time - grouped_span.start_time
+                                            ),
+                                            time_format.display_string(grouped_span.start_time),
+                                            time_format.display_string(grouped_span.end_time)
+                                        ));
+                                    });
+                                }
+                            });
+                    } else if let Some(Some(Value::StringValue(spans_info))) =
                         span.attributes.get("grouped_spans_info")
                     {
                         ui.label("Individual Spans:");
@@ -1756,17 +5320,96 @@ impl App {
                     }
                 } else {
                     // Regular span
-                    ui.label(format!("span_id: {}", hex::encode(&span.span_id)));
-                    ui.label(format!(
-                        "parent_span_id: {}",
-                        hex::encode(&span.parent_span_id)
-                    ));
+                    for (name, id) in [
+                        ("span_id", span.span_id.to_hex()),
+                        ("parent_span_id", span.parent_span_id.to_hex()),
+                        ("trace_id", span.trace_id.to_hex()),
+                    ] {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{name}: {id}"));
+                            copy_button(ui, &format!("Copy the {name}"), || id);
+                            if name == "trace_id"
+                                && !span.trace_id.is_empty()
+                                && ui
+                                    .button("Focus trace")
+                                    .on_hover_text(
+                                        "Tint all spans of this trace, or show only them",
+                                    )
+                                    .clicked()
+                            {
+                                focus_trace = Some(span.trace_id);
+                            }
+                        });
+                    }
+                    if let Some(location) = SourceLocation::from_attributes(&span.attributes) {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("source: {}", location.display_string()));
+                            if ui
+                                .button("Open in editor")
+                                .on_hover_text(format!(
+                                    "Runs '{}', the command can be changed in the preferences",
+                                    self.preferences.editor_command
+                                ))
+                                .clicked()
+                            {
+                                open_source = Some(location);
+                            }
+                        });
+                    }
                     draw_separator(ui);
-                    for (name, value) in &span.attributes {
-                        ui.label(format!("{}: {}", name, value_to_text(value)));
+                    if let Some(attribute) = self.trace_mut().attribute_table.draw(ui, &span.attributes) {
+                        clicked_attribute = Some(attribute);
                     }
                     draw_separator(ui);
 
+                    let related = related_spans(span);
+                    if !related.is_empty() {
+                        ui.label(format!("Relations ({})", related.len()));
+                        ScrollArea::vertical()
+                            .id_salt("clicked span relations")
+                            .max_height(150.0)
+                            .show(ui, |ui| {
+                                for related_span in related {
+                                    ui.horizontal(|ui| {
+                                        ui.label(if related_span.incoming { "from" } else { "to" });
+                                        ui.label(
+                                            RichText::new(&related_span.span.node.name)
+                                                .color(node_color(&related_span.span.node.name)),
+                                        );
+                                        if ui
+                                            .link(&related_span.span.name)
+                                            .on_hover_text("Click to go to this span")
+                                            .clicked()
+                                        {
+                                            follow_span = Some(related_span.span.clone());
+                                        }
+                                        ui.label(format!(
+                                            "{}, {:.3} ms",
+                                            related_span.relation.name,
+                                            related_span.delay * MILLISECONDS_PER_SECOND
+                                        ));
+                                    });
+                                }
+                            });
+                        draw_separator(ui);
+                    }
+
+                    let log_lines: Vec<&NodeLogLine> = self
+                        .node_logs
+                        .lines_in_range(&span.node.name, span.start_time, span.end_time)
+                        .iter()
+                        .collect();
+                    if !log_lines.is_empty() {
+                        ui.label(format!("Node logs ({})", log_lines.len()));
+                        ui.scope(|ui| {
+                            ui.set_max_height(200.0);
+                            if let Some(line) = draw_log_lines(ui, &log_lines, &time_format, false, None) {
+                                clicked_log_line = Some(line.clone());
+                            }
+                        });
+                        draw_separator(ui);
+                    }
+
                     let mut events = if self.include_children_events {
                         collect_events(span)
                     } else {
@@ -1788,7 +5431,7 @@ impl App {
                     ScrollArea::vertical().show(ui, |ui| {
                         for event in events {
                             draw_separator(ui);
-                            ui.label(time_point_to_utc_string(event.time));
+                            ui.label(time_format.display_string(event.time));
                             ui.label(event.name);
                             ui.label("");
                             for (name, value) in event.attributes {
@@ -1799,25 +5442,234 @@ impl App {
                 }
 
                 if close_button.clicked() {
-                    self.clicked_span = None;
+                    self.trace_mut().clicked_span = None;
                 }
             })
         });
 
+        if let Some(line) = clicked_log_line {
+            self.trace_mut().clicked_span = None;
+            self.node_logs_panel.show_line(&line);
+            self.center_selected_range_on(line.time);
+        }
+        if let Some(location) = open_source {
+            if let Err(err) = open_in_editor(&self.preferences.editor_command, &location) {
+                self.notifications.error(format!(
+                    "Failed to open {}: {err}",
+                    location.display_string()
+                ));
+            }
+        }
+        if let Some(span) = follow_span {
+            // Keep the modal open on the related span, so that a message can be followed hop by
+            // hop.
+            self.jump_to_span(&span);
+            self.trace_mut().clicked_span = Some(span);
+            return;
+        }
+
+        if let Some(trace_id) = focus_trace {
+            self.trace_mut().clicked_span = None;
+            self.focus_trace(Some(trace_id));
+            return;
+        }
+
+        if ungroup || jump_to_grouped_span.is_some() {
+            let group = self.trace_mut().clicked_span.take().unwrap();
+            // A span inside a group isn't drawn, so the group is expanded before jumping to it.
+            self.ungroup_span(&group);
+            if let Some(span) = jump_to_grouped_span {
+                self.jump_to_span(&span);
+            }
+            return;
+        }
+
+        if let Some((name, value)) = clicked_attribute {
+            add_attribute_criterion(&mut self.trace_mut().search.search_term, &name, &value);
+            self.run_search();
+        }
+
+        if analyze_span || analyze_dependency || child_breakdown || span_overlay {
+            let tab = &mut self.tabs[self.current_tab];
+            let span = tab.clicked_span.take().unwrap();
+            if child_breakdown {
+                tab.child_breakdown_modal
+                    .open_for_span_name(&tab.all_spans_for_analysis, &span.original_name);
+            } else if span_overlay {
+                self.span_overlay_modal
+                    .open_for_span_name(&tab.all_spans_for_analysis, &span.original_name);
+            } else if analyze_span {
+                tab.analyze_span_modal.selected_time_range =
+                    (tab.timeline.selected_start, tab.timeline.selected_end);
+                tab.analyze_span_modal.open_for_span(
+                    &tab.all_spans_for_analysis,
+                    &span.original_name,
+                    &span.node.name,
+                );
+            } else {
+                tab.analyze_dependency_modal.open_for_source_span(
+                    &tab.all_spans_for_analysis,
+                    &span.original_name,
+                    self.node_filters.get(self.current_node_filter_index),
+                );
+            }
+            return;
+        }
+
         // Esc closes the popup
         ctx.input(|i| {
             if i.key_down(Key::Escape) {
-                self.clicked_span = None;
+                self.trace_mut().clicked_span = None;
             }
         })
     }
 
+    /// Jumps to the span chosen in one of the analysis modals.
+    /// The modals show times in the time format of the current trace.
+    fn jump_to_modal_focus(&mut self) {
+        let tab = &mut self.tabs[self.current_tab];
+        let focuses = [
+            &mut tab.analyze_dependency_modal.focus,
+            &mut tab.node_health_modal.focus,
+            &mut tab.outliers_modal.focus,
+            &mut tab.skew_modal.focus,
+            &mut tab.correlation_modal.focus,
+            &mut tab.analyze_relations_modal.focus,
+            &mut tab.event_search.focus,
+            &mut tab.trace_lint.focus,
+            &mut self.unmatched_relations.focus,
+        ];
+        let mut span = None;
+        for focus in focuses {
+            span = focus.take().or(span);
+        }
+        if let Some(span) = span {
+            self.jump_to_span(&span);
+        }
+    }
+
+    fn draw_outliers_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        let time_format = self.time_format();
+        self.trace_mut()
+            .outliers_modal
+            .show_modal(ctx, &time_format, max_width, max_height);
+        if let Some(spans) = self.trace_mut().outliers_modal.highlight_spans.take() {
+            self.trace_mut().highlighted_spans = spans;
+        }
+    }
+
+    fn draw_throughput_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        let time_format = self.time_format();
+        self.trace_mut()
+            .throughput_modal
+            .show_modal(ctx, &time_format, max_width, max_height);
+        if let Some((start, end)) = self.trace_mut().throughput_modal.focus_range.take() {
+            self.select_time_range(start, end);
+        }
+    }
+
+    fn draw_analyze_relations_modal(
+        &mut self,
+        ctx: &egui::Context,
+        max_width: f32,
+        max_height: f32,
+    ) {
+        let time_format = self.time_format();
+        self.trace_mut().analyze_relations_modal.show_modal(
+            ctx,
+            &time_format,
+            max_width,
+            max_height,
+        );
+        let near_miss_percent = self.trace().analyze_relations_modal.near_miss_percent;
+        if self.trace().analyze_relations_modal.show
+            && self.preferences.relation_arrows.near_miss_percent != near_miss_percent
+        {
+            self.preferences.relation_arrows.near_miss_percent = near_miss_percent;
+            self.save_persistent_data();
+        }
+    }
+
+    /// Adds the results requested by the analysis modals to the report and draws the report.
+    fn draw_report_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        let sections = [
+            self.trace_mut().analyze_span_modal.report_section.take(),
+            self.trace_mut()
+                .analyze_dependency_modal
+                .report_section
+                .take(),
+            self.trace_mut()
+                .analyze_relations_modal
+                .report_section
+                .take(),
+        ];
+        for section in sections.into_iter().flatten() {
+            self.notifications
+                .info(format!("Added \"{}\" to the report", section.title));
+            self.report_modal.add_section(section);
+        }
+
+        self.report_modal.show_modal(ctx, max_width, max_height);
+        let Some(format) = self.report_modal.export_requested.take() else {
+            return;
+        };
+        let (extension, file_name, contents) = match format {
+            ReportFormat::Markdown => (
+                "md",
+                "traviz_report.md",
+                self.report_modal.report.to_markdown(),
+            ),
+            ReportFormat::Html => (
+                "html",
+                "traviz_report.html",
+                self.report_modal.report.to_html(),
+            ),
+        };
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter(extension, &[extension])
+            .set_file_name(file_name)
+            .save_file()
+        {
+            match std::fs::write(&path, contents) {
+                Ok(()) => self
+                    .notifications
+                    .info(format!("Exported the report to {}", path.display())),
+                Err(e) => self
+                    .notifications
+                    .error(format!("Error exporting the report: {e}")),
+            }
+        }
+    }
+
+    fn draw_trace_lint_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        self.trace_mut().trace_lint.orphan_spans = self.preferences.orphan_spans;
+        self.trace_mut()
+            .trace_lint
+            .show_modal(ctx, max_width, max_height);
+    }
+
+    fn draw_clock_offsets_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        self.trace_mut()
+            .clock_offsets_modal
+            .show_modal(ctx, max_width, max_height);
+        if let Some(offsets) = self.trace_mut().clock_offsets_modal.apply_offsets.take() {
+            self.set_clock_offsets(offsets);
+        }
+    }
+
     fn draw_analyze_span_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
-        if !self.analyze_span_modal.show {
+        if !self.trace().analyze_span_modal.show {
             return;
         }
-        let modal = &mut self.analyze_span_modal;
-        modal.show_modal(ctx, max_width, max_height);
+        let time_format = self.time_format();
+        let tab = self.trace_mut();
+        let modal = &mut tab.analyze_span_modal;
+        modal.selected_time_range = (tab.timeline.selected_start, tab.timeline.selected_end);
+        modal.show_modal(ctx, &time_format, max_width, max_height);
+        if let Some(spans) = self.trace_mut().analyze_span_modal.highlight_spans.take() {
+            self.trace_mut().highlighted_spans = spans;
+            self.zoom_to_highlighted_spans();
+        }
     }
 
     fn draw_analyze_dependency_modal(
@@ -1826,24 +5678,76 @@ impl App {
         max_width: f32,
         max_height: f32,
     ) {
-        if self.analyze_dependency_modal.show {
-            let modal = &mut self.analyze_dependency_modal;
-            modal.show_modal(ctx, max_width, max_height);
+        if self.trace().analyze_dependency_modal.show {
+            let time_format = self.time_format();
+            let tab = &mut self.tabs[self.current_tab];
+            let modal = &mut tab.analyze_dependency_modal;
+            modal.selected_time_range = (tab.timeline.selected_start, tab.timeline.selected_end);
+            modal.show_modal(
+                ctx,
+                &time_format,
+                &mut self.dependency_presets,
+                max_width,
+                max_height,
+            );
+            if let Some(relation) = modal.saved_relation.take() {
+                self.notifications.info(format!(
+                    "Added relation \"{}\", enable it in a relation view to see the arrows",
+                    relation.name
+                ));
+                self.defined_relations.push(relation);
+                self.save_persistent_data();
+            }
+            let modal = &mut self.tabs[self.current_tab].analyze_dependency_modal;
+            if std::mem::take(&mut modal.export_links_requested) {
+                if let Some(result) = &modal.analysis_result {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("JSON", &["json"])
+                        .set_file_name("traviz_dependency_links.json")
+                        .save_file()
+                    {
+                        let json = dependency_export::dependency_links_to_json(result);
+                        match std::fs::write(&path, json) {
+                            Ok(()) => self.notifications.info(format!(
+                                "Exported {} links to {}",
+                                result.overall_stats.count,
+                                path.display()
+                            )),
+                            Err(e) => self
+                                .notifications
+                                .error(format!("Error exporting links: {e}")),
+                        }
+                    }
+                }
+            }
+            let modal = &mut self.tabs[self.current_tab].analyze_dependency_modal;
+            if modal.presets_changed {
+                modal.presets_changed = false;
+                if let Err(err) = persistent::save_dependency_presets(&self.dependency_presets) {
+                    self.notifications
+                        .error(format!("Failed to save the dependency presets: {err}"));
+                }
+            }
             return;
         }
         // Modal is not set to be shown. Check if it was just closed by a selection.
-        if self.analyze_dependency_modal.focus_node.is_none() {
+        if self.trace().analyze_dependency_modal.focus_node.is_none() {
             // If modal.show is false and focus_node is None, we do nothing further.
             return;
         }
 
-        let focus_node_name = self.analyze_dependency_modal.focus_node.take().unwrap();
+        let focus_node_name = self
+            .trace_mut()
+            .analyze_dependency_modal
+            .focus_node
+            .take()
+            .unwrap();
 
         // Clear previous highlights
-        self.highlighted_spans.clear();
+        self.trace_mut().highlighted_spans.clear();
 
         // Get all dependency links and filter for those involving the focused node
-        let links_to_highlight = match &self.analyze_dependency_modal.analysis_result {
+        let links_to_highlight = match &self.trace().analyze_dependency_modal.analysis_result {
             Some(analysis) => {
                 let mut relevant_links = Vec::new();
 
@@ -1866,7 +5770,7 @@ impl App {
                     }
                 }
 
-                println!(
+                log::debug!(
                     "Found {} links involving focused node '{}'",
                     relevant_links.len(),
                     focus_node_name
@@ -1874,7 +5778,7 @@ impl App {
                 relevant_links
             }
             None => {
-                println!("No analysis result available!");
+                log::warn!("No analysis result available!");
                 return;
             }
         };
@@ -1888,7 +5792,7 @@ impl App {
         spans: &[Rc<Span>],
         start_height_param: f32,
         span_height: f32,
-        positions: &mut HashMap<Vec<u8>, f32>,
+        positions: &mut HashMap<SpanId, f32>,
     ) {
         for span in spans {
             let y_pos = start_height_param
@@ -1896,7 +5800,7 @@ impl App {
                 + span_height / 2.0;
 
             // Only store position if this span's ID is in the highlighted set
-            positions.insert(span.span_id.clone(), y_pos);
+            positions.insert(span.span_id, y_pos);
 
             let children = span.display_children.borrow();
             if !children.is_empty() {
@@ -1920,24 +5824,26 @@ impl App {
     fn draw_dependency_links(
         &mut self,
         ui: &mut Ui,
-        span_positions: &HashMap<Vec<u8>, f32>,
+        span_positions: &HashMap<SpanId, f32>,
         time_params: &TimeToScreenParams,
         ctx: &egui::Context,
     ) {
-        if self.highlighted_spans.is_empty() {
+        if self.trace().highlighted_spans.is_empty() {
             return;
         }
         let mut new_hovered_arrow_key = None;
 
         // Get all highlighted span IDs for efficient lookup
-        let highlighted_span_ids_set: HashSet<Vec<u8>> = self
+        let highlighted_span_ids_set: HashSet<SpanId> = self
+            .trace()
             .highlighted_spans
             .iter()
-            .map(|s| s.span_id.clone())
+            .map(|s| s.span_id)
             .collect();
 
         // Find the focused node from the highlighted spans
         let focused_node_names: HashSet<String> = self
+            .trace()
             .highlighted_spans
             .iter()
             .map(|s| s.node.name.clone())
@@ -1945,7 +5851,10 @@ impl App {
 
         // Look through all nodes that have dependency analysis results
         // and find links that involve both highlighted spans AND the focused node
-        let analysis_result = match &self.analyze_dependency_modal.analysis_result {
+        let analysis_result = match &self.tabs[self.current_tab]
+            .analyze_dependency_modal
+            .analysis_result
+        {
             Some(result) => result,
             None => return,
         };
@@ -1983,7 +5892,7 @@ impl App {
             return;
         }
 
-        let arrow_color = colors::INTENSE_BLUE;
+        let arrow_color = theme().relation_arrow;
         let base_arrow_stroke = Stroke::new(2.0, arrow_color);
 
         for link in all_links_to_draw.iter() {
@@ -2037,9 +5946,9 @@ impl App {
                     }
 
                     let arrow_key = ArrowKey {
-                        source_span_id: source_span.span_id.clone(),
+                        source_span_id: source_span.span_id,
                         source_node_name: source_span.node.name.clone(),
-                        target_span_id: target_span.span_id.clone(),
+                        target_span_id: target_span.span_id,
                         target_node_name: target_span.node.name.clone(),
                     };
 
@@ -2053,7 +5962,8 @@ impl App {
                         from_pos,
                         to_pos,
                         base_arrow_stroke,
-                        format!("{distance_ms:.2} ms"),
+                        false,
+                        Some(format!("{distance_ms:.2} ms")),
                         should_draw_highlighted,
                         &arrow_key,
                     );
@@ -2064,17 +5974,7 @@ impl App {
                     }
 
                     if arrow_interaction_result.response.clicked() {
-                        self.clicked_arrow_info = Some(ArrowInfo {
-                            source_span_name: source_span.name.clone(),
-                            source_node_name: source_span.node.name.clone(),
-                            source_start_time: source_span.start_time,
-                            source_end_time: source_span.end_time,
-                            target_span_name: target_span.name.clone(),
-                            target_node_name: target_span.node.name.clone(),
-                            target_start_time: target_span.start_time,
-                            target_end_time: target_span.end_time,
-                            duration: target_span.start_time - source_span.end_time,
-                        });
+                        self.clicked_arrow_info = Some(ArrowInfo::new(source_span, target_span));
                     }
                 }
             }
@@ -2090,17 +5990,28 @@ impl App {
 
     fn draw_relation_links(
         &mut self,
-        span_positions: &HashMap<Vec<u8>, f32>,
+        span_positions: &HashMap<SpanId, f32>,
         time_params: &TimeToScreenParams,
         ui: &mut Ui,
-        _ctx: &egui::Context,
+        ctx: &egui::Context,
     ) {
-        let arrow_color = colors::INTENSE_BLUE;
-        let base_arrow_stroke = Stroke::new(2.0, arrow_color);
-
+        let mut new_hovered_arrow_key = None;
+        let mut clicked_arrow_info = None;
+        let mut candidates = Vec::new();
+        let mut candidate_instances = Vec::new();
+        let near_miss_percent = self
+            .preferences
+            .relation_arrows
+            .highlight_near_misses
+            .then_some(self.preferences.relation_arrows.near_miss_percent);
+        let is_near_miss = |instance: &RelationInstance| {
+            near_miss_percent.is_some_and(|percent| instance.is_near_miss(percent))
+        };
         for relation in &self.active_relations {
             let from_span = relation.from_span.upgrade().unwrap();
             let to_span = relation.to_span.upgrade().unwrap();
+            let style = &relation.relation.arrow_style;
+            let base_arrow_stroke = Stroke::new(style.width, style.color32());
 
             let from_span_x_position = time_to_screen(
                 from_span.end_time,
@@ -2133,38 +6044,174 @@ impl App {
             let distance_ms = (to_span.start_time - from_span.end_time) * MILLISECONDS_PER_SECOND;
 
             let arrow_key = ArrowKey {
-                source_span_id: from_span.span_id.clone(),
+                source_span_id: from_span.span_id,
                 source_node_name: from_span.node.name.clone(),
-                target_span_id: to_span.span_id.clone(),
+                target_span_id: to_span.span_id,
                 target_node_name: to_span.node.name.clone(),
             };
 
-            let should_draw_highlighted = self.hovered_arrow_key.as_ref() == Some(&arrow_key);
+            let should_draw_highlighted =
+                self.hovered_relation_arrow_key.as_ref() == Some(&arrow_key);
 
             let from_pos = Pos2::new(from_span_x_position, from_span_y_position);
             let to_pos = Pos2::new(to_span_x_position, to_span_y_position);
 
-            draw_dependency_arrow(
-                ui,
-                from_pos,
-                to_pos,
-                base_arrow_stroke,
-                format!("{distance_ms:.2} ms"),
-                should_draw_highlighted,
-                &arrow_key,
+            if relation.arrival.is_some() {
+                // Arrows of a fan-in relation are bundled: the "from" spans are connected to a
+                // junction in front of the "to" span, and a single arrow goes from there.
+                let junction = to_pos - Vec2::new(FAN_IN_BUNDLE_LENGTH, 0.0);
+                if !relation.is_last_arrival() {
+                    let stroke = Stroke::new(
+                        (style.width / 2.0).max(1.0),
+                        style.color32().gamma_multiply(0.6),
+                    );
+                    draw_arrow_line(ui, from_pos, junction, stroke, style.dashed);
+                    continue;
+                }
+                if is_near_miss(relation) {
+                    draw_near_miss_halo(ui, from_pos, junction, style.width);
+                    draw_near_miss_halo(ui, junction, to_pos, style.width);
+                }
+                draw_arrow_line(ui, from_pos, junction, base_arrow_stroke, style.dashed);
+                let arrow_interaction_result = draw_dependency_arrow(
+                    ui,
+                    junction,
+                    to_pos,
+                    base_arrow_stroke,
+                    style.dashed,
+                    style.show_label.then(|| {
+                        format!(
+                            "last of {}: {distance_ms:.2} ms",
+                            relation.relation.required_sources
+                        )
+                    }),
+                    should_draw_highlighted,
+                    &arrow_key,
+                );
+                if arrow_interaction_result.is_precisely_hovered {
+                    new_hovered_arrow_key = Some(arrow_key.clone());
+                }
+                if arrow_interaction_result.response.clicked() {
+                    let mut info = ArrowInfo::new(&from_span, &to_span);
+                    info.relation = Some(relation.relation.clone());
+                    clicked_arrow_info = Some(info);
+                }
+                continue;
+            }
+
+            candidates.push(ArrowCandidate {
+                key: (
+                    relation.relation.id,
+                    from_span.node.name.clone(),
+                    to_span.node.name.clone(),
+                ),
+                from: from_pos,
+                to: to_pos,
+                delay_ms: distance_ms,
+            });
+            candidate_instances.push((relation, from_span, to_span, arrow_key));
+        }
+
+        let plan = plan_arrows(&candidates, &self.preferences.relation_arrows);
+        for bundle in &plan.bundles {
+            let (relation, from_span, to_span, arrow_key) = &candidate_instances[bundle.members[0]];
+            let style = &relation.relation.arrow_style;
+            let count = bundle.members.len();
+            let is_hovered = self.hovered_relation_arrow_key.as_ref() == Some(arrow_key);
+            if bundle
+                .members
+                .iter()
+                .any(|&i| is_near_miss(candidate_instances[i].0))
+            {
+                draw_near_miss_halo(ui, bundle.from, bundle.to, style.width);
+            }
+            let arrow_interaction_result = if count == 1 {
+                let distance_ms = bundle.mean_delay_ms;
+                draw_dependency_arrow(
+                    ui,
+                    bundle.from,
+                    bundle.to,
+                    Stroke::new(style.width, style.color32()),
+                    style.dashed,
+                    style.show_label.then(|| format!("{distance_ms:.2} ms")),
+                    is_hovered,
+                    arrow_key,
+                )
+            } else {
+                // Thickness grows with the logarithm of the count, so that large bundles don't
+                // cover the spans.
+                let width = (style.width * (1.0 + (count as f32).log2())).min(MAX_BUNDLE_WIDTH);
+                draw_dependency_arrow(
+                    ui,
+                    bundle.from,
+                    bundle.to,
+                    Stroke::new(width, style.color32()),
+                    style.dashed,
+                    style
+                        .show_label
+                        .then(|| format!("{count}x avg {:.2} ms", bundle.mean_delay_ms)),
+                    is_hovered,
+                    arrow_key,
+                )
+            };
+            if arrow_interaction_result.is_precisely_hovered {
+                new_hovered_arrow_key = Some(arrow_key.clone());
+            }
+            if arrow_interaction_result.response.clicked() {
+                let mut info = ArrowInfo::new(from_span, to_span);
+                info.relation = Some(relation.relation.clone());
+                info.bundled_arrows = count;
+                clicked_arrow_info = Some(info);
+            }
+        }
+
+        if plan.truncated > 0 || plan.hidden_short > 0 {
+            let mut text = Vec::new();
+            if plan.truncated > 0 {
+                text.push(format!(
+                    "Showing {} of {} arrows",
+                    plan.bundles.len(),
+                    plan.bundles.len() + plan.truncated
+                ));
+            }
+            if plan.hidden_short > 0 {
+                text.push(format!("{} short arrows hidden", plan.hidden_short));
+            }
+            let clip_rect = ui.clip_rect();
+            ui.painter().text(
+                clip_rect.right_top() + Vec2::new(-10.0, 10.0),
+                Align2::RIGHT_TOP,
+                text.join(", "),
+                FontId::proportional(12.0),
+                theme().error,
             );
         }
+
+        if clicked_arrow_info.is_some() {
+            self.clicked_arrow_info = clicked_arrow_info;
+        }
+        if self.hovered_relation_arrow_key != new_hovered_arrow_key {
+            self.hovered_relation_arrow_key = new_hovered_arrow_key;
+            ctx.request_repaint();
+        }
     }
 
     fn draw_clicked_arrow_popup(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        let time_format = self.time_format();
         if self.clicked_arrow_info.is_none() {
             return;
         }
 
         let mut open = true;
+        let mut jump_to_span = None;
         let info = self.clicked_arrow_info.as_ref().unwrap().clone();
 
-        egui::Window::new("Dependency Link Information")
+        let title = if info.relation.is_some() {
+            "Relation Link Information"
+        } else {
+            "Dependency Link Information"
+        };
+        egui::Window::new(title)
             .id(egui::Id::new("clicked_arrow_modal_window")) // Unique ID for the window
             .open(&mut open)
             .collapsible(false)
@@ -2175,7 +6222,7 @@ impl App {
                 ui.set_max_height(max_height * 0.6);
 
                 ui.vertical_centered(|ui| {
-                    ui.heading("Dependency Link Information"); // Already centered by vertical_centered
+                    ui.heading(title); // Already centered by vertical_centered
                 });
                 ui.add_space(5.0);
                 ui.separator();
@@ -2186,19 +6233,55 @@ impl App {
                     .spacing([10.0, 4.0])
                     .striped(true)
                     .show(ui, |ui| {
+                        if let Some(relation) = &info.relation {
+                            ui.strong("Relation:");
+                            ui.label(&relation.name)
+                                .on_hover_text(&relation.description);
+                            ui.end_row();
+
+                            if let Some(max_time_diff) = relation.max_time_diff {
+                                ui.strong("Max Time Diff:");
+                                ui.label(format!(
+                                    "{:.3} ms",
+                                    max_time_diff * MILLISECONDS_PER_SECOND
+                                ));
+                                ui.end_row();
+                            }
+
+                            if info.bundled_arrows > 1 {
+                                ui.strong("Bundled Arrows:");
+                                ui.label(format!(
+                                    "{} (showing the first one)",
+                                    info.bundled_arrows
+                                ));
+                                ui.end_row();
+                            }
+
+                            ui.separator();
+                            ui.end_row();
+                        }
+
                         ui.strong("Source Node:");
-                        ui.label(&info.source_node_name);
+                        ui.label(
+                            RichText::new(&info.source_node_name)
+                                .color(node_color(&info.source_node_name)),
+                        );
                         ui.end_row();
 
                         ui.strong("Source Span:");
-                        ui.label(&info.source_span_name);
+                        ui.horizontal(|ui| {
+                            ui.label(&info.source_span_name);
+                            if ui.small_button("Jump").clicked() {
+                                jump_to_span = Some(info.source_span.clone());
+                            }
+                        });
                         ui.end_row();
 
                         ui.strong("Source Time:");
                         ui.label(format!(
                             "{} - {}",
-                            time_point_to_utc_string(info.source_start_time),
-                            time_point_to_utc_string(info.source_end_time)
+                            time_format.display_string(info.source_start_time),
+                            time_format.display_string(info.source_end_time)
                         ));
                         ui.end_row();
 
@@ -2206,18 +6289,26 @@ impl App {
                         ui.end_row();
 
                         ui.strong("Target Node:");
-                        ui.label(&info.target_node_name);
+                        ui.label(
+                            RichText::new(&info.target_node_name)
+                                .color(node_color(&info.target_node_name)),
+                        );
                         ui.end_row();
 
                         ui.strong("Target Span:");
-                        ui.label(&info.target_span_name);
+                        ui.horizontal(|ui| {
+                            ui.label(&info.target_span_name);
+                            if ui.small_button("Jump").clicked() {
+                                jump_to_span = Some(info.target_span.clone());
+                            }
+                        });
                         ui.end_row();
 
                         ui.strong("Target Time:");
                         ui.label(format!(
                             "{} - {}",
-                            time_point_to_utc_string(info.target_start_time),
-                            time_point_to_utc_string(info.target_end_time)
+                            time_format.display_string(info.target_start_time),
+                            time_format.display_string(info.target_end_time)
                         ));
                         ui.end_row();
 
@@ -2225,15 +6316,15 @@ impl App {
                         ui.end_row();
 
                         ui.strong("Link Start Time:");
-                        ui.label(time_point_to_utc_string(info.source_end_time));
+                        ui.label(time_format.display_string(info.source_end_time));
                         ui.end_row();
 
                         ui.strong("Link End Time:");
-                        ui.label(time_point_to_utc_string(info.target_start_time));
+                        ui.label(time_format.display_string(info.target_start_time));
                         ui.end_row();
 
                         ui.strong("Link Duration:");
-                        ui.label(format!("{:.3} ms", info.duration * MILLISECONDS_PER_SECOND));
+                        ui.label(format_ms(info.duration));
                         ui.end_row();
                     });
 
@@ -2253,28 +6344,358 @@ impl App {
         if ctx.input(|i| i.key_down(Key::Escape)) {
             self.clicked_arrow_info = None;
         }
+
+        if let Some(span) = jump_to_span {
+            self.clicked_arrow_info = None;
+            self.jump_to_span(&span);
+        }
+    }
+
+    /// Runs the span search over all spans in the trace, including the ones hidden by the current
+    /// display mode.
+    fn run_search(&mut self) {
+        let (all_spans, _) = process_spans_for_analysis(&self.trace().all_spans_for_analysis);
+        self.trace_mut().search.run(&all_spans);
+    }
+
+    /// Shows the time range in the timeline, the visible range is widened if needed.
+    fn select_time_range(&mut self, start: TimePoint, end: TimePoint) {
+        let timeline = &mut self.trace_mut().timeline;
+        timeline.selected_start = start;
+        timeline.selected_end = end;
+        timeline.visible_start = timeline.visible_start.min(start);
+        timeline.visible_end = timeline.visible_end.max(end);
+        self.set_timeline_end_bars_to_selected();
+    }
+
+    /// Moves the timeline and scrolls the node lanes so that the span is visible, and highlights it.
+    fn jump_to_span(&mut self, span: &Rc<Span>) {
+        let highlighted_spans = &mut self.trace_mut().highlighted_spans;
+        if !highlighted_spans.iter().any(|s| s.span_id == span.span_id) {
+            highlighted_spans.push(span.clone());
+        }
+
+        let span_center = (span.start_time + span.end_time) / 2.0;
+        let timeline = &self.trace().timeline;
+        let selected_center = (timeline.selected_start + timeline.selected_end) / 2.0;
+        self.shift_selected_time(span_center - selected_center);
+
+        // Zoom out if the span doesn't fit in the selected range
+        let timeline = &mut self.trace_mut().timeline;
+        let selected_len = timeline.selected_end - timeline.selected_start;
+        let wanted_len = (span.end_time - span.start_time) * 1.4;
+        if wanted_len > selected_len {
+            timeline.selected_start = span_center - wanted_len / 2.0;
+            timeline.selected_end = span_center + wanted_len / 2.0;
+            timeline.visible_start = timeline.visible_start.min(timeline.selected_start);
+            timeline.visible_end = timeline.visible_end.max(timeline.selected_end);
+            self.set_timeline_end_bars_to_selected();
+        }
+
+        self.scroll_to_lane = Some(self.lane_name(span));
+    }
+
+    /// Ctrl+C copies the ids of the selected spans, or of the clicked span, one per line. Text fields
+    /// handle the shortcut themselves.
+    fn copy_span_ids_on_shortcut(&self, ctx: &egui::Context) {
+        let copy_pressed = ctx.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Copy)));
+        if !copy_pressed || ctx.wants_keyboard_input() {
+            return;
+        }
+        let spans: Vec<&Rc<Span>> = if self.trace().span_selection.is_empty() {
+            self.trace().clicked_span.iter().collect()
+        } else {
+            self.trace().span_selection.spans().iter().collect()
+        };
+        if spans.is_empty() {
+            return;
+        }
+        let ids: Vec<String> = spans.iter().map(|span| span.span_id.to_hex()).collect();
+        ctx.copy_text(ids.join("\n"));
+    }
+
+    /// Switches to the theme and UI scale from the preferences when they change.
+    fn apply_appearance(&mut self, ctx: &egui::Context) {
+        let theme = self.preferences.build_theme();
+        let appearance = (theme, self.preferences.ui_scale);
+        if self.applied_appearance == Some(appearance) {
+            return;
+        }
+        colors::set_theme(theme);
+        ctx.set_theme(theme.egui_theme());
+        ctx.set_zoom_factor(appearance.1);
+        self.applied_appearance = Some(appearance);
     }
 
     fn load_peristent_data(&mut self) {
         if let Err(err) = persistent::load_persistent_data(
+            &mut self.current_profile,
+            &mut self.profile_names,
             &mut self.display_modes,
             &mut self.node_filters,
             &mut self.defined_relations,
             &mut self.relation_views,
+            &mut self.preferences,
+            &mut self.macros,
+            &mut self.recent_files,
+            &mut self.time_marker_rules,
         ) {
-            eprintln!("Failed to load persistent data: {err}");
+            self.notifications
+                .error(format!("Failed to load persistent data: {err}"));
+        }
+        match persistent::load_dependency_presets() {
+            Ok(presets) => self.dependency_presets = presets,
+            Err(err) => self
+                .notifications
+                .error(format!("Failed to load the dependency presets: {err}")),
+        }
+        match persistent::load_clock_offsets() {
+            Ok(clock_offsets) => self.clock_offsets_by_file = clock_offsets,
+            Err(err) => self
+                .notifications
+                .error(format!("Failed to load the clock offsets: {err}")),
         }
     }
 
-    fn save_persistent_data(&self) {
+    fn save_persistent_data(&mut self) {
         if let Err(err) = persistent::save_persistent_data(
+            &self.current_profile,
             &self.display_modes,
             &self.node_filters,
             &self.defined_relations,
             &self.relation_views,
+            &self.preferences,
+            &self.macros,
+            &self.recent_files,
+            &self.time_marker_rules,
         ) {
-            eprintln!("Failed to save persistent data: {err}");
+            self.notifications
+                .error(format!("Failed to save persistent data: {err}"));
+        }
+        if !self.profile_names.contains(&self.current_profile) {
+            self.profile_names.push(self.current_profile.clone());
+        }
+    }
+
+    /// Switches to the display modes, node filters, relations and time markers of the profile.
+    /// Entries with the same name as the current ones stay selected.
+    fn switch_profile(&mut self, profile_name: String) {
+        let mode_name = self
+            .display_modes
+            .get(self.current_display_mode_index)
+            .map(|mode| mode.name.clone());
+        let filter_name = self
+            .node_filters
+            .get(self.current_node_filter_index)
+            .map(|filter| filter.name.clone());
+        let view_name = self
+            .relation_views
+            .get(self.current_relation_view_index)
+            .map(|view| view.name.clone());
+
+        self.current_profile = profile_name;
+        self.load_peristent_data();
+        // Remember the profile, it will be opened on the next start.
+        self.save_persistent_data();
+
+        self.current_display_mode_index = self
+            .display_modes
+            .iter()
+            .position(|mode| Some(&mode.name) == mode_name.as_ref())
+            .unwrap_or(0);
+        self.current_node_filter_index = self
+            .node_filters
+            .iter()
+            .position(|filter| Some(&filter.name) == filter_name.as_ref())
+            .unwrap_or(0);
+        self.current_relation_view_index = self
+            .relation_views
+            .iter()
+            .position(|view| Some(&view.name) == view_name.as_ref())
+            .unwrap_or(0);
+        self.arrangement_cache.invalidate();
+        if let Err(e) = self.apply_current_mode() {
+            self.notifications
+                .error(format!("Failed to apply display mode: {e}"));
+        }
+        self.apply_current_relations_view();
+        self.update_time_markers();
+    }
+
+    fn update_time_markers(&mut self) {
+        self.trace_mut().time_markers = collect_time_markers(
+            &self.time_marker_rules,
+            &self.trace().all_spans_for_analysis,
+        );
+    }
+
+    fn record_macro_action(&mut self, action: MacroAction) {
+        if let Some(actions) = &mut self.macro_recording {
+            log::debug!("Recorded macro action: {}", action.describe());
+            actions.push(action);
+        }
+    }
+
+    /// Stops recording and opens the macro editor with the new macro, so that it can be named
+    /// and bound to a key.
+    fn stop_macro_recording(&mut self) {
+        let Some(actions) = self.macro_recording.take() else {
+            return;
+        };
+        if actions.is_empty() {
+            return;
+        }
+        self.load_peristent_data();
+        let mut macros = self.macros.clone();
+        macros.push(KeyMacro {
+            name: format!("Macro {}", macros.len() + 1),
+            key: None,
+            actions,
+        });
+        self.edit_macros.open(macros);
+    }
+
+    fn current_mode_name(&self) -> String {
+        self.display_modes
+            .get(self.current_display_mode_index)
+            .map_or(String::new(), |mode| mode.name.clone())
+    }
+
+    /// Collapses or expands the children of all spans. Expanding also turns off the max depth.
+    fn collapse_all(&mut self, collapse: bool) {
+        set_collapse_all(&self.spans_to_display, collapse);
+        self.collapse_memory
+            .remember_all(&self.current_mode_name(), collapse);
+        if !collapse {
+            self.depth_limit.enabled = false;
+            self.depth_limit.clear_expanded();
+        }
+        self.arrangement_cache.invalidate();
+    }
+
+    /// Displays only the top `depth` levels of the span tree, using the max depth.
+    fn collapse_to_depth(&mut self, depth: usize) {
+        self.depth_limit.enabled = true;
+        self.depth_limit.max_depth = depth.max(1);
+        self.depth_limit.clear_expanded();
+        self.arrangement_cache.invalidate();
+    }
+
+    /// `[` collapses all spans, `]` expands them, Alt+1 to Alt+9 collapse to that depth.
+    fn handle_collapse_shortcuts(&mut self, ctx: &egui::Context) {
+        // Don't steal keys typed into text fields.
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        if ctx.input(|i| i.key_pressed(Key::OpenBracket) && i.modifiers.is_none()) {
+            self.collapse_all(true);
+        }
+        if ctx.input(|i| i.key_pressed(Key::CloseBracket) && i.modifiers.is_none()) {
+            self.collapse_all(false);
+        }
+        let depth_keys = [
+            Key::Num1,
+            Key::Num2,
+            Key::Num3,
+            Key::Num4,
+            Key::Num5,
+            Key::Num6,
+            Key::Num7,
+            Key::Num8,
+            Key::Num9,
+        ];
+        let pressed_depth = depth_keys.iter().position(|key| {
+            ctx.input(|i| i.key_pressed(*key) && i.modifiers.matches_exact(egui::Modifiers::ALT))
+        });
+        if let Some(index) = pressed_depth {
+            self.collapse_to_depth(index + 1);
+        }
+    }
+
+    fn run_macros_for_pressed_keys(&mut self, ctx: &egui::Context) {
+        // Don't steal keys typed into text fields.
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let pressed_macros: Vec<KeyMacro> = self
+            .macros
+            .iter()
+            .filter(|m| {
+                m.key()
+                    .is_some_and(|key| ctx.input(|i| i.key_pressed(key) && i.modifiers.is_none()))
+            })
+            .cloned()
+            .collect();
+        for key_macro in pressed_macros {
+            log::info!("Running macro '{}'", key_macro.name);
+            self.running_macros.push(RunningMacro::new(&key_macro));
+        }
+    }
+
+    /// Runs the actions of the running macros until one has to wait for a dependency analysis,
+    /// should be called every frame. Macros run one after another, in the order of their keys.
+    fn continue_running_macros(&mut self) {
+        while let Some(running_macro) = self.running_macros.first_mut() {
+            let modal = &mut self.tabs[self.current_tab].analyze_dependency_modal;
+            modal.poll_analysis();
+            let Some(action) = running_macro.next_action(modal.is_analysis_running()) else {
+                if !running_macro.is_finished() {
+                    return;
+                }
+                self.running_macros.remove(0);
+                continue;
+            };
+            if let Err(e) = self.run_macro_action(&action) {
+                let running_macro = self.running_macros.remove(0);
+                self.notifications.error(format!(
+                    "Macro '{}' stopped at '{}': {e}",
+                    running_macro.name,
+                    action.describe()
+                ));
+            }
+        }
+    }
+
+    fn run_macro_action(&mut self, action: &MacroAction) -> Result<()> {
+        match action {
+            MacroAction::SwitchDisplayMode(name) => {
+                self.current_display_mode_index = self
+                    .display_modes
+                    .iter()
+                    .position(|m| &m.name == name)
+                    .ok_or_else(|| anyhow::anyhow!("No display mode named '{name}'"))?;
+                self.apply_current_mode()?;
+            }
+            MacroAction::SetNodeFilter(name) => {
+                self.current_node_filter_index = self
+                    .node_filters
+                    .iter()
+                    .position(|f| &f.name == name)
+                    .ok_or_else(|| anyhow::anyhow!("No node filter named '{name}'"))?;
+            }
+            MacroAction::SetRelationView(name) => {
+                self.current_relation_view_index = self
+                    .relation_views
+                    .iter()
+                    .position(|v| &v.name == name)
+                    .ok_or_else(|| anyhow::anyhow!("No relation view named '{name}'"))?;
+                self.apply_current_relations_view();
+            }
+            MacroAction::RunDependencyAnalysis(description) => {
+                let tab = self.trace_mut();
+                let modal = &mut tab.analyze_dependency_modal;
+                modal.open(&tab.all_spans_for_analysis);
+                modal
+                    .parse_and_fill_from_description(description)
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                modal.start_analysis();
+            }
+            MacroAction::ExportHtml(path) => {
+                self.export_html(&PathBuf::from(path))?;
+                self.notifications.info(format!("Exported view to {path}"));
+            }
         }
+        Ok(())
     }
 
     fn highlight_spans_for_dependency_links(&mut self, links: &[DependencyLink]) {
@@ -2288,70 +6709,56 @@ impl App {
         for (i, link) in links.iter().enumerate() {
             // Process source spans
             for (s_idx, source_s) in link.source_spans.iter().enumerate() {
-                println!(
+                log::debug!(
                     "[Link {}][Source {}/{}] Name: {} (node: {}, ID: {:?})",
                     i,
                     s_idx + 1,
                     link.source_spans.len(),
                     source_s.original_name,
                     source_s.node.name,
-                    hex::encode(&source_s.span_id)
+                    source_s.span_id.to_hex()
                 );
-                if unique_span_ids_to_highlight.insert(source_s.span_id.clone()) {
+                if unique_span_ids_to_highlight.insert(source_s.span_id) {
                     spans_to_highlight.push(source_s.clone());
                 }
             }
 
             // Process target spans
             for (t_idx, target_s) in link.target_spans.iter().enumerate() {
-                println!(
+                log::debug!(
                     "[Link {}][Target {}/{}] Name: {} (node: {}, ID: {:?})",
                     i,
                     t_idx + 1,
                     link.target_spans.len(),
                     target_s.original_name,
                     target_s.node.name,
-                    hex::encode(&target_s.span_id)
+                    target_s.span_id.to_hex()
                 );
-                if unique_span_ids_to_highlight.insert(target_s.span_id.clone()) {
+                if unique_span_ids_to_highlight.insert(target_s.span_id) {
                     spans_to_highlight.push(target_s.clone());
                 }
             }
         }
 
         // Assign the collected unique spans
-        self.highlighted_spans = spans_to_highlight;
+        self.trace_mut().highlighted_spans = spans_to_highlight;
+        self.zoom_to_highlighted_spans();
+    }
 
-        // Adjust timeline to show these spans if needed
-        if self.highlighted_spans.is_empty() {
-            println!("No spans were highlighted!");
+    /// Adjusts the timeline to show the highlighted spans if they are outside of it.
+    fn zoom_to_highlighted_spans(&mut self) {
+        let Some((min_time, max_time)) = highlighted_spans_range(&self.trace().highlighted_spans)
+        else {
+            log::debug!("No spans were highlighted!");
             return;
-        }
-
-        let mut min_time = f64::MAX;
-        let mut max_time = f64::MIN;
-
-        for span in &self.highlighted_spans {
-            min_time = min_time.min(span.start_time);
-            max_time = max_time.max(span.end_time);
-        }
-
-        // Limit the range to 3 seconds maximum
-        const MAX_HIGHLIGHT_SPAN_TIMELINE_ZOOM: f64 = 3.0;
-        let desired_max_time = min_time + MAX_HIGHLIGHT_SPAN_TIMELINE_ZOOM;
-        if max_time > desired_max_time {
-            max_time = desired_max_time;
-        }
-
-        // Add padding around the time range
-        let padding = (max_time - min_time) * 0.2;
-        min_time -= padding;
-        max_time += padding;
+        };
 
         // Update timeline if needed
-        if min_time < self.timeline.selected_start || max_time > self.timeline.selected_end {
-            self.timeline.selected_start = min_time;
-            self.timeline.selected_end = max_time;
+        if min_time < self.trace().timeline.selected_start
+            || max_time > self.trace().timeline.selected_end
+        {
+            self.trace_mut().timeline.selected_start = min_time;
+            self.trace_mut().timeline.selected_end = max_time;
             self.set_timeline_end_bars_to_selected();
         }
     }
@@ -2361,11 +6768,11 @@ impl App {
 fn populate_span_cache_recursive(
     current_span: &Rc<Span>,
     root_span: &Rc<Span>,
-    cache: &mut HashMap<Vec<u8>, Rc<Span>>,
+    cache: &mut HashMap<SpanId, Rc<Span>>,
 ) {
-    cache.insert(current_span.span_id.clone(), root_span.clone());
+    cache.insert(current_span.span_id, root_span.clone());
 
-    for child in current_span.children.borrow().iter() {
+    for child in current_span.children().iter() {
         populate_span_cache_recursive(child, root_span, cache);
     }
 }
@@ -2388,160 +6795,26 @@ fn get_min_max_time(spans: &[Rc<Span>]) -> Option<(TimePoint, TimePoint)> {
     min_max
 }
 
-#[derive(Debug, Clone, Copy)]
-struct SpanBoundingBox {
-    start: f32,
-    end: f32,
-    height: HeightLevel,
-}
-
-fn arrange_spans_with_viewport(
-    input_spans: &[Rc<Span>],
-    first_invocation: bool,
-    viewport_start: f64,
-    viewport_end: f64,
-) -> SpanBoundingBox {
-    #[cfg(feature = "profiling")]
-    let _timing_guard = profiling::GLOBAL_PROFILER.start_timing("arrange_spans");
-
-    if input_spans.is_empty() {
-        return SpanBoundingBox {
-            start: 0.0,
-            end: 0.0,
-            height: 0,
-        };
-    }
-
-    let mut sorted_spans = input_spans.to_vec();
-    sorted_spans.sort_by(|a, b| {
-        if let Some(start_ordering) = a.min_start_time.partial_cmp(&b.min_start_time) {
-            return start_ordering;
-        }
-        a.max_end_time
-            .partial_cmp(&b.max_end_time)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
-
-    let mut span_bounding_boxes: Vec<SpanBoundingBox> = Vec::with_capacity(sorted_spans.len());
-
-    // Spans that can collide with new spans (holds indexes to span_bounding_boxes).
-    let mut active_spans: Vec<usize> = Vec::new();
-
-    for (i, span) in sorted_spans.iter().enumerate() {
-        let mut span_bbox = arrange_span_with_viewport(span, viewport_start, viewport_end);
-        if first_invocation && span_bbox.height > 0 {
-            span_bbox.height += 1; // Top-level spans have one unit of padding below them
-        }
-
-        // Default to height 0, will be updated below
-        span.parent_height_offset.set(0);
-
-        // Remove spans that for sure won't collide with this span or any future ones. Spans are
-        // sorted by start time, so we can be sure that for futures ones the start time will be
-        // larger than the end of the bounding box.
-        active_spans.retain(|&j| span_bounding_boxes[j].end >= span_bbox.start);
-
-        loop {
-            let mut is_colliding = false;
-
-            for &j in &active_spans {
-                let other_span = &sorted_spans[j];
-                let other_span_bbox = &span_bounding_boxes[j];
-
-                if is_intersecting(
-                    span_bbox.start,
-                    span_bbox.end,
-                    other_span_bbox.start,
-                    other_span_bbox.end,
-                ) && do_spans_collide_in_y(
-                    span.parent_height_offset.get(),
-                    span_bbox.height,
-                    other_span.parent_height_offset.get(),
-                    other_span_bbox.height,
-                ) {
-                    is_colliding = true;
-                    break;
-                }
-
-                if span_bbox.start < other_span_bbox.end && span_bbox.end < other_span_bbox.start {
-                    assert!(is_colliding);
-                }
-            }
-
-            if is_colliding {
-                span.parent_height_offset
-                    .set(span.parent_height_offset.get() + 1);
-            } else {
-                break;
-            }
-        }
-
-        span_bounding_boxes.push(span_bbox);
-        active_spans.push(i);
-    }
-
-    let mut final_bbox = SpanBoundingBox {
-        start: f32::INFINITY,
-        end: f32::NEG_INFINITY,
-        height: 0,
-    };
-
-    for i in 0..sorted_spans.len() {
-        let span = &sorted_spans[i];
-        let span_bbox = &span_bounding_boxes[i];
-
-        final_bbox.start = final_bbox.start.min(span_bbox.start);
-        final_bbox.end = final_bbox.end.max(span_bbox.end);
-        final_bbox.height = final_bbox
-            .height
-            .max(span.parent_height_offset.get() + span_bbox.height);
-    }
-
-    final_bbox
-}
-
-fn arrange_span_with_viewport(
-    span: &Rc<Span>,
-    viewport_start: f64,
-    viewport_end: f64,
-) -> SpanBoundingBox {
-    let span_start = span.display_start.get();
-    let span_end = span_start + span.display_length.get();
-
-    if span.display_children.borrow().is_empty() {
-        SpanBoundingBox {
-            start: span_start,
-            end: span_end,
-            height: 1,
-        }
+/// Reads the trace file, decompressing gzipped files. Returns the contents and the size of the file.
+fn read_trace_file(path: &Path) -> Result<(Vec<u8>, u64)> {
+    let mut file_bytes = Vec::new();
+    let file = std::fs::File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    if ext == "gz" || ext == "gzip" {
+        // Gzip file
+        let mut decoder = GzDecoder::new(file);
+        decoder.read_to_end(&mut file_bytes)?;
     } else {
-        // Filter children to only include those that intersect with the viewport
-        let all_children = span.display_children.borrow();
-        let viewport_culled_children: Vec<Rc<Span>> = all_children
-            .iter()
-            .filter(|child| {
-                is_intersecting(
-                    child.min_start_time.get(),
-                    child.max_end_time.get(),
-                    viewport_start,
-                    viewport_end,
-                )
-            })
-            .cloned()
-            .collect();
-
-        let children_bbox = arrange_spans_with_viewport(
-            &viewport_culled_children,
-            false,
-            viewport_start,
-            viewport_end,
-        );
-        SpanBoundingBox {
-            start: span_start.min(children_bbox.start),
-            end: span_end.max(children_bbox.end),
-            height: children_bbox.height + 1,
-        }
+        // Regular file
+        let mut reader = file;
+        reader.read_to_end(&mut file_bytes)?;
     }
+    Ok((file_bytes, file_size))
 }
 
 fn parse_trace_file(file_bytes: &[u8]) -> Result<Vec<ExportTraceServiceRequest>> {
@@ -2549,54 +6822,19 @@ fn parse_trace_file(file_bytes: &[u8]) -> Result<Vec<ExportTraceServiceRequest>>
 
     let file_str =
         std::str::from_utf8(file_bytes).map_err(|e| anyhow::anyhow!("File is not UTF8!: {}", e))?;
-    let traces: Vec<ExportTraceServiceRequest> = serde_json::from_str(file_str)?;
+    let traces: Vec<ExportTraceServiceRequest> = if jaeger::is_jaeger_json(file_str) {
+        jaeger::parse_jaeger_json(file_str)?
+    } else {
+        serde_json::from_str(file_str)?
+    };
 
     t.stop();
     Ok(traces)
 }
 
-fn set_min_max_time(spans: &[Rc<Span>]) {
-    for span in spans {
-        let mut min_start_time = span.start_time;
-        let mut max_end_time = span.end_time;
-
-        let children = span.children.borrow();
-        set_min_max_time(children.as_slice());
-
-        for child in children.iter() {
-            min_start_time = min_start_time.min(child.min_start_time.get());
-            max_end_time = max_end_time.max(child.max_end_time.get());
-        }
-
-        span.min_start_time.set(min_start_time);
-        span.max_end_time.set(max_end_time);
-    }
-}
-
-fn is_between<T: PartialOrd + Copy>(x: T, a: T, b: T) -> bool {
-    a <= x && x <= b
-}
-
-fn is_intersecting<T: PartialOrd + Copy>(a: T, b: T, c: T, d: T) -> bool {
-    is_between(a, c, d) || is_between(b, c, d) || is_between(c, a, b) || is_between(d, a, b)
-}
-
-fn do_spans_collide_in_y(y1: u64, height1: u64, y2: u64, height2: u64) -> bool {
-    if !is_intersecting(y1, y1 + height1, y2, y2 + height2) {
-        return false;
-    }
-
-    // Spans can touch vertically, that's ok
-    if y1 + height1 == y2 || y2 + height2 == y1 {
-        return false;
-    }
-
-    true
-}
-
 fn count_events(span: &Span) -> usize {
     let mut count = span.events.len();
-    for child in span.children.borrow().iter() {
+    for child in span.children().iter() {
         count += count_events(child);
     }
     count
@@ -2604,39 +6842,43 @@ fn count_events(span: &Span) -> usize {
 
 fn collect_events(span: &Span) -> Vec<Event> {
     let mut result = span.events.clone();
-    for c in span.children.borrow().iter() {
-        result.extend(collect_events(c).into_iter());
+    for c in span.children().iter() {
+        result.extend(collect_events(c));
     }
     result
 }
 
+/// Evenly spaced round times in `start_time..end_time`, between 5 and 50 of them.
+/// Returns nothing for empty or invalid ranges.
 fn get_time_dots(start_time: TimePoint, end_time: TimePoint) -> Vec<TimePoint> {
-    let mut delta = 10.0f64.powf((end_time - start_time).log10().ceil() + 10.0);
-    let mut iterations: usize = 0;
-    loop {
-        let num_points = (end_time - start_time) / delta;
-        if (5.0..100.0).contains(&num_points) {
-            break;
-        }
-        delta /= 10.0;
-        iterations += 1;
-        if iterations > 10000 {
-            println!("WARN: get_time_dots looped!: start_time: {start_time}, end_time: {end_time}");
-            return vec![];
-        }
+    let range = end_time - start_time;
+    if !range.is_finite() || range <= 0.0 {
+        return vec![];
     }
-    let rounded_start = (start_time / delta).round() * delta;
+    let delta = 10.0f64.powf((range / 5.0).log10().floor());
+    // Multiplying instead of adding the delta in a loop, a delta below the resolution of the time
+    // points wouldn't move them forward.
+    let first_index = (start_time / delta).ceil();
+    (0..50)
+        .map(|i| (first_index + i as f64) * delta)
+        .take_while(|dot| *dot < end_time)
+        .collect()
+}
 
-    let mut dots = vec![];
-    let mut cur_time = rounded_start;
-    while cur_time < end_time {
-        dots.push(cur_time);
-        cur_time += delta;
-    }
-    dots
+/// Which spans are left out of the display regardless of their collapse state.
+struct DisplayLimits<'a> {
+    /// Spans shorter than this many seconds aren't displayed, together with their subtrees.
+    hidden_below: TimePoint,
+    /// Short spans which are displayed anyway, the highlighted ones and their ancestors.
+    shown_short_spans: &'a HashSet<SpanId>,
+    depth_limit: &'a DepthLimit,
 }
 
-fn set_display_children_with_highlights(spans: &[Rc<Span>], highlighted_spans: &[Rc<Span>]) {
+fn set_display_children_with_highlights(
+    spans: &[Rc<Span>],
+    highlighted_spans: &[Rc<Span>],
+    limits: &DisplayLimits,
+) {
     #[cfg(feature = "profiling")]
     let _timing_guard =
         profiling::GLOBAL_PROFILER.start_timing("set_display_children_with_highlights");
@@ -2648,7 +6890,7 @@ fn set_display_children_with_highlights(spans: &[Rc<Span>], highlighted_spans: &
 
     // Now run the regular set_display_children
     for s in spans {
-        set_display_children_rec(s, false, &mut vec![]);
+        set_display_children_rec(s, false, 0, limits, &mut vec![]);
     }
 
     // After layout is done, reset the flags to avoid affecting future layouts
@@ -2660,10 +6902,17 @@ fn set_display_children_with_highlights(spans: &[Rc<Span>], highlighted_spans: &
 fn set_display_children_rec(
     span: &Rc<Span>,
     mut collapse_children_active: bool,
+    depth: usize,
+    limits: &DisplayLimits,
     cur_children: &mut Vec<Rc<Span>>,
 ) {
     if span.dont_collapse_this_span.get() {
         collapse_children_active = false;
+    } else if span.end_time - span.start_time < limits.hidden_below
+        && !limits.shown_short_spans.contains(&span.span_id)
+    {
+        // The subtree has no highlighted spans, it's hidden with the span.
+        return;
     }
 
     let display_this_span = !collapse_children_active;
@@ -2676,38 +6925,71 @@ fn set_display_children_rec(
         }
 
         span.display_children.borrow_mut().clear();
-        for c in span.children.borrow().iter() {
+        if limits.depth_limit.cuts_children(span.span_id, depth) {
+            return;
+        }
+        // Lazy children never contain spans which are displayed under a collapsed parent, there's
+        // no need to transform them until the span is expanded.
+        if collapse_children_active && span.has_lazy_children() {
+            return;
+        }
+        for c in span.children().iter() {
             set_display_children_rec(
                 c,
                 collapse_children_active,
+                depth + 1,
+                limits,
                 &mut span.display_children.borrow_mut(),
             );
         }
-    } else {
-        for c in span.children.borrow().iter() {
-            set_display_children_rec(c, collapse_children_active, cur_children);
+    } else if !span.has_lazy_children() {
+        for c in span.children().iter() {
+            set_display_children_rec(c, collapse_children_active, depth, limits, cur_children);
         }
     }
 }
 
-fn collect_produce_block_starts_with_nodes(spans: &[Rc<Span>]) -> Vec<(TimePoint, String)> {
-    let mut result = Vec::new();
+/// Start of the earliest produce_block span, used for the initial zoom.
+fn find_first_produce_block_start(spans: &[Rc<Span>]) -> Option<TimePoint> {
+    let mut result: Option<TimePoint> = None;
     for span in spans {
         if span.name.starts_with("produce_block") {
-            result.push((span.start_time, span.node.name.clone()));
+            result = Some(result.map_or(span.start_time, |t| t.min(span.start_time)));
+        }
+        let children = span.children();
+        if let Some(child_start) = find_first_produce_block_start(children.as_slice()) {
+            result = Some(result.map_or(child_start, |t| t.min(child_start)));
         }
-        let children = span.children.borrow();
-        result.extend(collect_produce_block_starts_with_nodes(children.as_slice()));
     }
     result
 }
 
+/// Opacity of spans faded by the minimum duration filter.
+const DIMMED_SPAN_OPACITY: f32 = 0.3;
+
+/// Radius of the badge on spans which weren't matched by a relation, in pixels.
+const UNMATCHED_BADGE_RADIUS: f32 = 4.0;
+const REMOTE_BADGE_FONT_SIZE: f32 = 9.0;
+/// Size of the "+" on spans whose children are hidden by the max depth.
+const DEPTH_BADGE_FONT_SIZE: f32 = 12.0;
+
+/// Bundled relation arrows are never drawn thicker than this.
+const MAX_BUNDLE_WIDTH: f32 = 10.0;
+
+/// Length of the common part of bundled fan-in arrows, in pixels.
+const FAN_IN_BUNDLE_LENGTH: f32 = 20.0;
+
+/// Height of the gap between the two span panes of a split view, in pixels.
+const SPLIT_VIEW_GAP: f32 = 4.0;
+
+#[allow(clippy::too_many_arguments)]
 fn draw_dependency_arrow(
     ui: &mut Ui,
     from: Pos2,
     to: Pos2,
     base_stroke: Stroke,
-    label: String,
+    dashed: bool,
+    label: Option<String>,
     is_hovered: bool,
     arrow_key: &ArrowKey,
 ) -> ArrowInteractionOutput {
@@ -2731,11 +7013,11 @@ fn draw_dependency_arrow(
 
     // Draw the main line
     let line_stroke = if is_hovered {
-        Stroke::new(base_stroke.width + 1.5, colors::VERY_LIGHT_BLUE2)
+        Stroke::new(base_stroke.width + 1.5, theme().hovered_relation_arrow)
     } else {
         base_stroke
     };
-    ui.painter().line_segment([from, to], line_stroke);
+    draw_arrow_line(ui, from, to, line_stroke, dashed);
 
     // Arrow head size should be proportional to line length but capped
     let arrow_size = (length * 0.1).clamp(6.0, 12.0);
@@ -2747,27 +7029,30 @@ fn draw_dependency_arrow(
     ui.painter()
         .line_segment([to, arrow_point - normal * arrow_size * 0.5], line_stroke);
 
-    // Position the label at a fixed offset perpendicular to the line
-    let label_offset = normal * 15.0;
-    let label_pos = from + vec * 0.5 + label_offset;
-    let font_id = FontId::proportional(12.0);
-    let text_color = colors::GRAY_240;
-
-    // Measure text for background
-    let galley = ui.fonts(|fonts| fonts.layout_no_wrap(label.clone(), font_id.clone(), text_color));
-    let padding = Vec2::new(6.0, 4.0);
-    let text_rect = Rect::from_min_size(
-        label_pos - Vec2::new(galley.rect.width() / 2.0, galley.rect.height() / 2.0),
-        galley.rect.size() + padding,
-    );
+    if let Some(label) = label {
+        // Position the label at a fixed offset perpendicular to the line
+        let label_offset = normal * 15.0;
+        let label_pos = from + vec * 0.5 + label_offset;
+        let font_id = FontId::proportional(12.0);
+        let text_color = theme().arrow_label_text;
+
+        // Measure text for background
+        let galley =
+            ui.fonts(|fonts| fonts.layout_no_wrap(label.clone(), font_id.clone(), text_color));
+        let padding = Vec2::new(6.0, 4.0);
+        let text_rect = Rect::from_min_size(
+            label_pos - Vec2::new(galley.rect.width() / 2.0, galley.rect.height() / 2.0),
+            galley.rect.size() + padding,
+        );
 
-    // Draw text background for better visibility
-    ui.painter()
-        .rect_filled(text_rect, 4.0, colors::TRANSPARENT_GRAY);
+        // Draw text background for better visibility
+        ui.painter()
+            .rect_filled(text_rect, 4.0, theme().arrow_label_background);
 
-    // Draw the text
-    ui.painter()
-        .text(label_pos, Align2::CENTER_CENTER, label, font_id, text_color);
+        // Draw the text
+        ui.painter()
+            .text(label_pos, Align2::CENTER_CENTER, label, font_id, text_color);
+    }
 
     // Use a unique ID derived from the arrow_key to avoid conflicts
     let interact_id = ui.id().with(arrow_key);
@@ -2805,6 +7090,25 @@ fn draw_dependency_arrow(
     }
 }
 
+/// Wide yellow line under an arrow of a relation instance which is close to the relation's
+/// `max_time_diff`.
+fn draw_near_miss_halo(ui: &Ui, from: Pos2, to: Pos2, arrow_width: f32) {
+    ui.painter().line_segment(
+        [from, to],
+        Stroke::new(arrow_width + 6.0, theme().near_miss_halo),
+    );
+}
+
+/// Draws a solid or dashed line between two points.
+fn draw_arrow_line(ui: &Ui, from: Pos2, to: Pos2, stroke: Stroke, dashed: bool) {
+    if dashed {
+        ui.painter()
+            .extend(egui::Shape::dashed_line(&[from, to], stroke, 6.0, 4.0));
+    } else {
+        ui.painter().line_segment([from, to], stroke);
+    }
+}
+
 /// Describes the interaction result for a drawn arrow, including precise hover detection.
 struct ArrowInteractionOutput {
     /// The raw Egui response from interacting with the arrow's bounding box.
@@ -2836,5 +7140,14 @@ fn distance_sq_to_segment(p: Pos2, a: Pos2, b: Pos2) -> f32 {
 
 #[test]
 fn test_time_dots() {
-    println!("{:?}", get_time_dots(0.001234, 0.00235));
+    let dots = get_time_dots(0.001234, 0.00235);
+    assert!((5..=50).contains(&dots.len()));
+    assert!(dots.iter().all(|dot| (0.001234..0.00235).contains(dot)));
+
+    // A window below the resolution of the time points doesn't loop forever
+    let start = 1_700_000_000.123;
+    let dots = get_time_dots(start, start + 1e-9);
+    assert!(dots.len() <= 50);
+    assert!(get_time_dots(start, start).is_empty());
+    assert!(get_time_dots(start, f64::NAN).is_empty());
 }
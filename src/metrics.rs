@@ -0,0 +1,285 @@
+//! Metrics overlay - series of values (e.g. CPU usage or mempool size) loaded from a file and drawn
+//! as line chart lanes above the span lanes, aligned with the timeline.
+//!
+//! Two formats are supported:
+//! * The JSON response of a Prometheus range query (`/api/v1/query_range`), every series of the
+//!   result is a separate lane.
+//! * CSV with a header row, the first column is the time (RFC 3339 or a unix timestamp), every
+//!   other column is a series named by the header. Empty cells are skipped.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use eframe::egui::{self, Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, Ui};
+use serde_json::Value as JsonValue;
+
+use crate::colors::{node_color, theme};
+use crate::external_events::parse_event_time;
+use crate::types::{TimeFormat, TimePoint};
+
+/// Height of the chart of one series in the span view.
+pub const METRIC_LANE_HEIGHT: f32 = 60.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSeries {
+    pub name: String,
+    /// (time, value), sorted by time.
+    pub points: Vec<(TimePoint, f64)>,
+}
+
+impl MetricSeries {
+    pub fn color(&self) -> Color32 {
+        node_color(&self.name)
+    }
+
+    /// The points in `start..end` and one point on each side of it, so that the line reaches the
+    /// edges of the chart.
+    fn points_in_range(&self, start: TimePoint, end: TimePoint) -> &[(TimePoint, f64)] {
+        let first = self.points.partition_point(|(time, _)| *time < start);
+        let last = self.points.partition_point(|(time, _)| *time <= end);
+        &self.points[first.saturating_sub(1)..(last + 1).min(self.points.len())]
+    }
+}
+
+/// A loaded series and whether its lane is shown.
+#[derive(Debug, Clone)]
+pub struct MetricLane {
+    pub series: MetricSeries,
+    pub shown: bool,
+}
+
+/// Reads the series from the file, the format is chosen by the extension.
+pub fn load_metric_series(path: &Path) -> Result<Vec<MetricSeries>> {
+    let contents = std::fs::read_to_string(path)?;
+    let is_json = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("json") => true,
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => false,
+        _ => contents.trim_start().starts_with('{'),
+    };
+    if is_json {
+        parse_prometheus_json(&contents)
+    } else {
+        parse_metrics_csv(&contents)
+    }
+}
+
+/// Parses the response of a Prometheus range query (`matrix` result) or instant query (`vector`).
+pub fn parse_prometheus_json(json: &str) -> Result<Vec<MetricSeries>> {
+    let value: JsonValue = serde_json::from_str(json)?;
+    if let Some(status) = value.get("status").and_then(JsonValue::as_str) {
+        if status != "success" {
+            let error = value.get("error").and_then(JsonValue::as_str).unwrap_or("");
+            bail!("the query failed with status '{status}': {error}");
+        }
+    }
+    let Some(results) = value.pointer("/data/result").and_then(JsonValue::as_array) else {
+        bail!("no 'data.result' array, expected the response of a Prometheus query");
+    };
+
+    let mut all_series = Vec::new();
+    for (index, result) in results.iter().enumerate() {
+        let samples: Vec<&JsonValue> = match (result.get("values"), result.get("value")) {
+            (Some(JsonValue::Array(values)), _) => values.iter().collect(),
+            (None, Some(value)) => vec![value],
+            _ => bail!("series {index} doesn't have values"),
+        };
+        let mut points = Vec::new();
+        for sample in samples {
+            let (Some(time), Some(value)) = (
+                sample.get(0).and_then(JsonValue::as_f64),
+                sample.get(1).and_then(JsonValue::as_str),
+            ) else {
+                bail!("invalid sample {sample} in series {index}");
+            };
+            // Prometheus writes special values as "NaN" and "+Inf", they can't be drawn.
+            let value: f64 = value
+                .parse()
+                .with_context(|| format!("invalid value '{value}' in series {index}"))?;
+            if value.is_finite() {
+                points.push((time, value));
+            }
+        }
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        all_series.push(MetricSeries {
+            name: prometheus_series_name(result.get("metric")),
+            points,
+        });
+    }
+    Ok(all_series)
+}
+
+/// `metric_name{label="value", ...}` like in the Prometheus UI, labels are sorted by name.
+fn prometheus_series_name(metric: Option<&JsonValue>) -> String {
+    let Some(JsonValue::Object(labels)) = metric else {
+        return "{}".to_string();
+    };
+    let name = labels
+        .get("__name__")
+        .and_then(JsonValue::as_str)
+        .unwrap_or("");
+    let mut other_labels: Vec<(&String, String)> = labels
+        .iter()
+        .filter(|(label, _)| *label != "__name__")
+        .map(|(label, value)| {
+            let value = value
+                .as_str()
+                .map_or_else(|| value.to_string(), str::to_string);
+            (label, value)
+        })
+        .collect();
+    other_labels.sort();
+    if other_labels.is_empty() && !name.is_empty() {
+        return name.to_string();
+    }
+    let labels: Vec<String> = other_labels
+        .iter()
+        .map(|(label, value)| format!("{label}=\"{value}\""))
+        .collect();
+    format!("{name}{{{}}}", labels.join(", "))
+}
+
+pub fn parse_metrics_csv(csv: &str) -> Result<Vec<MetricSeries>> {
+    let csv = csv.strip_prefix('\u{feff}').unwrap_or(csv);
+    let mut lines = csv.lines().filter(|line| !line.trim().is_empty());
+    let Some(header) = lines.next() else {
+        bail!("the file is empty");
+    };
+    let mut all_series: Vec<MetricSeries> = header
+        .split(',')
+        .skip(1)
+        .map(|name| MetricSeries {
+            name: name.trim().to_string(),
+            points: Vec::new(),
+        })
+        .collect();
+    if all_series.is_empty() {
+        bail!("the header has no columns with values, expected 'time,<series name>,...'");
+    }
+
+    // The header is row 1.
+    for (row_number, line) in (2..).zip(lines) {
+        let mut fields = line.split(',').map(str::trim);
+        let time = parse_event_time(fields.next().unwrap_or(""))
+            .with_context(|| format!("invalid time in row {row_number}"))?;
+        for (series, field) in all_series.iter_mut().zip(fields) {
+            if field.is_empty() {
+                continue;
+            }
+            let value: f64 = field
+                .parse()
+                .with_context(|| format!("invalid value '{field}' in row {row_number}"))?;
+            if value.is_finite() {
+                series.points.push((time, value));
+            }
+        }
+    }
+    for series in &mut all_series {
+        series.points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+    Ok(all_series)
+}
+
+/// Formats values of the series, with more decimals for small values.
+pub fn format_metric_value(value: f64) -> String {
+    let abs = value.abs();
+    if abs >= 1e6 || (abs > 0.0 && abs < 1e-3) {
+        format!("{value:.3e}")
+    } else if abs >= 100.0 || value.fract() == 0.0 {
+        format!("{value:.0}")
+    } else {
+        format!("{value:.3}")
+    }
+}
+
+/// Draws the series as a line chart in `chart_area`, scaled to the values in `start..end`, with the
+/// name of the series in `name_area`. Hovering the chart shows the value at the pointer.
+pub fn draw_metric_lane(
+    ui: &mut Ui,
+    series: &MetricSeries,
+    start: TimePoint,
+    end: TimePoint,
+    name_area: Rect,
+    chart_area: Rect,
+    time_format: &TimeFormat,
+) {
+    let painter = ui.painter_at(chart_area);
+    painter.rect_filled(chart_area, 0.0, theme().lane_background);
+    ui.painter_at(name_area).text(
+        name_area.left_center() + egui::vec2(4.0, 0.0),
+        Align2::LEFT_CENTER,
+        &series.name,
+        FontId::proportional(0.8 * egui::TextStyle::Body.resolve(ui.style()).size),
+        series.color(),
+    );
+
+    let points = series.points_in_range(start, end);
+    if points.is_empty() || end <= start {
+        return;
+    }
+    let (mut min, mut max) = (f64::MAX, f64::MIN);
+    for (_, value) in points {
+        min = min.min(*value);
+        max = max.max(*value);
+    }
+    if max <= min {
+        (min, max) = (min - 0.5, max + 0.5);
+    }
+
+    // Keep a little space at the top and bottom, so that the line doesn't touch the lane borders.
+    let plot_area = chart_area.shrink2(egui::vec2(0.0, 4.0));
+    let to_pos = |(time, value): (TimePoint, f64)| {
+        Pos2::new(
+            plot_area.min.x + ((time - start) / (end - start)) as f32 * plot_area.width(),
+            plot_area.max.y - ((value - min) / (max - min)) as f32 * plot_area.height(),
+        )
+    };
+    let line: Vec<Pos2> = points.iter().map(|point| to_pos(*point)).collect();
+    painter.line(line, Stroke::new(1.5, series.color()));
+
+    let font_id = FontId::proportional(0.7 * egui::TextStyle::Body.resolve(ui.style()).size);
+    painter.text(
+        chart_area.right_top() + egui::vec2(-4.0, 2.0),
+        Align2::RIGHT_TOP,
+        format_metric_value(max),
+        font_id.clone(),
+        theme().weak_text,
+    );
+    painter.text(
+        chart_area.right_bottom() + egui::vec2(-4.0, -2.0),
+        Align2::RIGHT_BOTTOM,
+        format_metric_value(min),
+        font_id,
+        theme().weak_text,
+    );
+
+    let response = ui.interact(
+        chart_area,
+        ui.id().with(("metric lane", &series.name)),
+        Sense::hover(),
+    );
+    if let Some(pointer) = response.hover_pos() {
+        let time =
+            start + ((pointer.x - chart_area.min.x) / chart_area.width()) as f64 * (end - start);
+        let closest = points
+            .iter()
+            .min_by(|a, b| (a.0 - time).abs().total_cmp(&(b.0 - time).abs()))
+            .copied();
+        if let Some(point) = closest {
+            let pos = to_pos(point);
+            painter.circle_filled(pos, 3.0, series.color());
+            painter.line_segment(
+                [
+                    Pos2::new(pos.x, chart_area.min.y),
+                    Pos2::new(pos.x, chart_area.max.y),
+                ],
+                Stroke::new(1.0, theme().chart_hover),
+            );
+            response.on_hover_text_at_pointer(format!(
+                "{}\n{}\n{}",
+                series.name,
+                time_format.display_string(point.0),
+                format_metric_value(point.1)
+            ));
+        }
+    }
+}
@@ -2,19 +2,23 @@
 //! Each "Mode" is a function which takes the raw trace data and prepares the spans that should be displayed.
 //! They can filter, modify, transform, re-arrange the spans as needed for each mode.
 
-use std::cell::{Cell, RefCell};
-use std::collections::{BTreeMap, HashMap};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::rc::Rc;
+use std::sync::Arc;
 
 use anyhow::Result;
 use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
 use opentelemetry_proto::tonic::common::v1::any_value::Value;
+use opentelemetry_proto::tonic::resource::v1::Resource;
 
-use crate::structured_modes::{self, StructuredMode};
+use crate::scope_filter::ScopeFilter;
+use crate::span_id::SpanId;
+use crate::structured_modes::{self, LaneLayout, SpanSelector, StructuredMode};
 use crate::task_timer::TaskTimer;
 use crate::types::{
-    time_point_from_unix_nano, time_point_to_utc_string, value_to_text, DisplayLength, Event, Node,
-    Scope, Span, SpanDisplayConfig, MILLISECONDS_PER_SECOND,
+    set_min_max_time, time_point_from_unix_nano, time_point_to_utc_string, value_to_text, Event,
+    LazyChildren, Node, Scope, Span, SpanData, TimePoint, MILLISECONDS_PER_SECOND,
 };
 
 #[allow(unused)]
@@ -44,6 +48,91 @@ pub fn get_all_modes() -> Vec<DisplayMode> {
     structured_modes
 }
 
+/// What to do with a span whose parent was recorded on another node, e.g. the handler of a request
+/// which carried the trace context of the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum RemoteParentLinking {
+    /// Display the span under its parent, marked as remote.
+    #[default]
+    Stitch,
+    /// Display the span as a top level span on its own node.
+    Root,
+    /// Display the span as a top level span on its own node, with a relation arrow from its
+    /// parent.
+    Relation,
+}
+
+impl RemoteParentLinking {
+    pub fn all() -> [RemoteParentLinking; 3] {
+        [
+            RemoteParentLinking::Stitch,
+            RemoteParentLinking::Root,
+            RemoteParentLinking::Relation,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RemoteParentLinking::Stitch => "Under the parent",
+            RemoteParentLinking::Root => "Top level span",
+            RemoteParentLinking::Relation => "Top level span with a relation",
+        }
+    }
+}
+
+/// What to do with a span whose parent isn't in the trace, e.g. because the parent was recorded
+/// before the capture started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum OrphanSpans {
+    /// Display the span as a top level span.
+    #[default]
+    Root,
+    /// Display the span as a top level span in a separate "orphans" lane of its node.
+    Lane,
+    /// Don't display the span and its children.
+    Hide,
+}
+
+impl OrphanSpans {
+    pub fn all() -> [OrphanSpans; 3] {
+        [OrphanSpans::Root, OrphanSpans::Lane, OrphanSpans::Hide]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OrphanSpans::Root => "Top level span",
+            OrphanSpans::Lane => "Orphans lane of the node",
+            OrphanSpans::Hide => "Hidden",
+        }
+    }
+
+    /// Where the spans end up, completes "the spans are ...".
+    pub fn description(&self) -> &'static str {
+        match self {
+            OrphanSpans::Root => "displayed as top level spans",
+            OrphanSpans::Lane => "displayed in the orphans lane of their node",
+            OrphanSpans::Hide => "hidden together with their children",
+        }
+    }
+}
+
+/// Name of the lane which holds the orphan spans of `node_name` with [OrphanSpans::Lane].
+pub fn orphans_lane_name(node_name: &str) -> String {
+    format!("{node_name} orphans")
+}
+
+/// Name of the lane (or sub-lane) in which the span is displayed in `mode`, when the orphan spans
+/// are placed as `orphans` says. Without a mode the spans are in lanes per node.
+pub fn span_lane_name(mode: Option<&StructuredMode>, orphans: OrphanSpans, span: &Span) -> String {
+    if span.orphan && orphans == OrphanSpans::Lane {
+        return orphans_lane_name(&span.node.name);
+    }
+    match mode {
+        Some(mode) => mode.lane_name(span),
+        None => LaneLayout::default().lane_name(span),
+    }
+}
+
 /// Perform the transformation to the spans based on the structured mode.
 /// - for each span hierarchy, find the first (highest) span that should be visible and make it a
 ///   top level span, discard the ones above it.
@@ -53,91 +142,253 @@ pub fn structured_mode_transformation(
     trace_data: &[ExportTraceServiceRequest],
     structured_mode: &StructuredMode,
 ) -> Result<Vec<Rc<Span>>> {
-    let all_spans = extract_spans(trace_data)?;
-    let mut new_spans = Vec::new();
+    transform_trace(
+        trace_data,
+        structured_mode,
+        &[],
+        &ScopeFilter::default(),
+        RemoteParentLinking::Stitch,
+        OrphanSpans::Root,
+        false,
+    )
+}
 
+/// Same as [structured_mode_transformation], but the children of a span are transformed only when
+/// they're first accessed through [Span::children] (e.g. when the user expands the span), if none of
+/// them contain a span that is visible in this mode. Copying and renaming such subtrees is most of
+/// the work of the transformation, so switching modes on giant traces becomes near-instant.
+pub fn structured_mode_transformation_lazy(
+    trace_data: &[ExportTraceServiceRequest],
+    structured_mode: &StructuredMode,
+) -> Result<Vec<Rc<Span>>> {
+    transform_trace(
+        trace_data,
+        structured_mode,
+        &[],
+        &ScopeFilter::default(),
+        RemoteParentLinking::Stitch,
+        OrphanSpans::Root,
+        true,
+    )
+}
+
+/// [structured_mode_transformation], or the lazy one if `lazy` is set, which also drops the spans
+/// matching one of the `muted` selectors or hidden by the `scope_filter`. Their children are dropped
+/// with them. Spans with a parent on another node are placed as `remote_parents` says, spans with a
+/// parent which isn't in the trace as `orphans` says.
+pub fn structured_mode_transformation_muted(
+    trace_data: &[ExportTraceServiceRequest],
+    structured_mode: &StructuredMode,
+    muted: &[SpanSelector],
+    scope_filter: &ScopeFilter,
+    remote_parents: RemoteParentLinking,
+    orphans: OrphanSpans,
+    lazy: bool,
+) -> Result<Vec<Rc<Span>>> {
+    transform_trace(
+        trace_data,
+        structured_mode,
+        muted,
+        scope_filter,
+        remote_parents,
+        orphans,
+        lazy,
+    )
+}
+
+fn transform_trace(
+    trace_data: &[ExportTraceServiceRequest],
+    structured_mode: &StructuredMode,
+    muted: &[SpanSelector],
+    scope_filter: &ScopeFilter,
+    remote_parents: RemoteParentLinking,
+    orphans: OrphanSpans,
+    lazy: bool,
+) -> Result<Vec<Rc<Span>>> {
+    let all_spans = extract_spans(trace_data, remote_parents, orphans)?;
+    let uses_grouping = !structured_mode.grouping_rules.is_empty()
+        || structured_mode
+            .span_rules
+            .iter()
+            .any(|rule| rule.decision.group);
+
+    let mut spans_with_visible_subtree = HashSet::new();
+    if lazy {
+        // Lazy spans keep the time range of the original subtree.
+        set_min_max_time(&all_spans);
+        for span in &all_spans {
+            collect_spans_with_visible_subtree(
+                structured_mode,
+                muted,
+                scope_filter,
+                span,
+                &mut spans_with_visible_subtree,
+            );
+        }
+    }
+
+    let transformer = Rc::new(SpanTransformer {
+        mode: structured_mode.clone(),
+        muted: muted.to_vec(),
+        scope_filter: scope_filter.clone(),
+        lazy,
+        spans_with_visible_subtree,
+    });
+    let mut new_spans = Vec::new();
     for span in all_spans {
-        structured_mode_transformation_rek(structured_mode, &span, &mut new_spans, false);
+        transformer.transform_rek(&span, &mut new_spans, false);
     }
 
     // Only apply grouping if any rule uses it
-    if structured_mode
-        .span_rules
-        .iter()
-        .any(|rule| rule.decision.group)
-    {
-        apply_grouping(&mut new_spans);
+    if uses_grouping {
+        apply_grouping(&mut new_spans, structured_mode);
     }
 
     Ok(new_spans)
 }
 
-fn structured_mode_transformation_rek(
+/// Adds ids of spans which are visible in the mode, or have a visible descendant, to `result`.
+/// Returns true if the span was added. Grouped spans are moved out of the tree by the grouping, so
+/// they count as visible.
+fn collect_spans_with_visible_subtree(
     mode: &StructuredMode,
+    muted: &[SpanSelector],
+    scope_filter: &ScopeFilter,
     span: &Rc<Span>,
-    visible_top_level_spans: &mut Vec<Rc<Span>>,
-    under_visible_top_level_span: bool,
-) -> Option<Rc<Span>> {
+    result: &mut HashSet<SpanId>,
+) -> bool {
+    if is_muted(muted, scope_filter, span) {
+        return false;
+    }
     let decision = mode.get_decision_for_span(span);
+    let mut has_visible =
+        decision.visible || decision.group || mode.grouping_rule_for(span).is_some();
+    for child in span.children().iter() {
+        has_visible |= collect_spans_with_visible_subtree(mode, muted, scope_filter, child, result);
+    }
+    for child in &mode.synthetic_children(span) {
+        has_visible |= collect_spans_with_visible_subtree(mode, muted, scope_filter, child, result);
+    }
+    if has_visible {
+        result.insert(span.span_id);
+    }
+    has_visible
+}
+
+fn is_muted(muted: &[SpanSelector], scope_filter: &ScopeFilter, span: &Span) -> bool {
+    !scope_filter.shows(span) || muted.iter().any(|selector| selector.matches(span))
+}
 
-    if !decision.visible && !under_visible_top_level_span {
-        for child in span.children.borrow().iter() {
-            structured_mode_transformation_rek(mode, child, visible_top_level_spans, false);
+struct SpanTransformer {
+    mode: StructuredMode,
+    /// Spans matching these selectors are dropped together with their subtrees.
+    muted: Vec<SpanSelector>,
+    /// Spans hidden by this filter are dropped like the muted ones.
+    scope_filter: ScopeFilter,
+    /// Transform children of a span only when they're accessed, if none of them has a visible
+    /// subtree.
+    lazy: bool,
+    /// Only filled in the lazy mode.
+    spans_with_visible_subtree: HashSet<SpanId>,
+}
+
+impl SpanTransformer {
+    fn transform_rek(
+        self: &Rc<Self>,
+        span: &Rc<Span>,
+        visible_top_level_spans: &mut Vec<Rc<Span>>,
+        under_visible_top_level_span: bool,
+    ) -> Option<Rc<Span>> {
+        if is_muted(&self.muted, &self.scope_filter, span) {
+            return None;
         }
-        return None;
-    }
+        let decision = self.mode.get_decision_for_span(span);
+        let synthetic_children = self.mode.synthetic_children(span);
 
-    let taken_children = std::mem::take(&mut *span.children.borrow_mut());
+        if !decision.visible && !under_visible_top_level_span {
+            for child in span.children().iter().chain(&synthetic_children) {
+                self.transform_rek(child, visible_top_level_spans, false);
+            }
+            return None;
+        }
 
-    let mut modified_span: Span = (**span).clone();
-    if decision.visible {
-        modified_span.dont_collapse_this_span.set(true);
-        modified_span.collapse_children.set(true);
-    }
+        let mut taken_children = std::mem::take(&mut *span.children_mut());
+        taken_children.extend(synthetic_children);
 
-    let will_change_name = !decision.replace_name.is_empty()
-        || decision.add_height_to_name
-        || decision.add_shard_id_to_name;
-    if will_change_name {
-        modified_span.attributes.insert(
-            "original.span.name".to_string(),
-            Some(Value::StringValue(modified_span.name.clone())),
-        );
-    }
+        let mut modified_span: Span = (**span).clone();
+        if decision.visible {
+            modified_span.dont_collapse_this_span.set(true);
+            modified_span.collapse_children.set(true);
+        }
 
-    if !decision.replace_name.is_empty() {
-        modified_span.name = decision.replace_name;
-    }
-    if decision.add_height_to_name {
-        add_height_to_name(&mut modified_span);
-    }
-    if decision.add_shard_id_to_name {
-        add_shard_id_to_name(&mut modified_span);
-    }
-    add_part_ord_to_name(&mut modified_span);
-    add_busy_percent(&mut modified_span);
-    modified_span.display_options.display_length = decision.display_length;
+        let rename_rule = self.mode.rename_rule_for(span);
+        let will_change_name = !decision.replace_name.is_empty()
+            || decision.add_height_to_name
+            || decision.add_shard_id_to_name
+            || rename_rule.is_some();
+        if will_change_name {
+            let original_name = modified_span.name.clone();
+            Arc::make_mut(&mut modified_span.data).attributes.insert(
+                "original.span.name".to_string(),
+                Some(Value::StringValue(original_name)),
+            );
+        }
 
-    // Optionally mark span for grouping
-    if decision.group {
-        modified_span.active_segments = Some(Vec::new());
-    }
+        if !decision.replace_name.is_empty() {
+            modified_span.name = decision.replace_name;
+        }
+        if decision.add_height_to_name {
+            add_height_to_name(&mut modified_span);
+        }
+        if decision.add_shard_id_to_name {
+            add_shard_id_to_name(&mut modified_span);
+        }
+        if let Some(rule) = rename_rule {
+            modified_span.name = rule.apply(&modified_span);
+        }
+        add_part_ord_to_name(&mut modified_span);
+        add_busy_percent(&mut modified_span);
+        modified_span.display_options.display_length = decision.display_length;
 
-    let mut new_children = Vec::new();
-    for child in taken_children.iter() {
-        if let Some(new_child) =
-            structured_mode_transformation_rek(mode, child, visible_top_level_spans, true)
-        {
-            new_children.push(new_child);
+        // Optionally mark span for grouping
+        if decision.group || self.mode.grouping_rule_for(span).is_some() {
+            modified_span.active_segments = Some(Vec::new());
+        }
+
+        let defer_children = self.lazy
+            && !taken_children.is_empty()
+            && !taken_children
+                .iter()
+                .any(|child| self.spans_with_visible_subtree.contains(&child.span_id));
+        if defer_children {
+            let transformer = self.clone();
+            modified_span.lazy_children = RefCell::new(Some(LazyChildren {
+                original: taken_children
+                    .into_iter()
+                    .filter(|child| !is_muted(&self.muted, &self.scope_filter, child))
+                    .collect(),
+                transform: Rc::new(move |child| {
+                    transformer
+                        .transform_rek(child, &mut Vec::new(), true)
+                        .expect("spans under a visible span are always transformed")
+                }),
+            }));
+        } else {
+            let mut new_children = Vec::new();
+            for child in taken_children.iter() {
+                if let Some(new_child) = self.transform_rek(child, visible_top_level_spans, true) {
+                    new_children.push(new_child);
+                }
+            }
+            modified_span.set_children(new_children);
         }
-    }
-    modified_span.children = RefCell::new(new_children);
 
-    if !under_visible_top_level_span {
-        visible_top_level_spans.push(Rc::new(modified_span));
-        None
-    } else {
-        Some(Rc::new(modified_span))
+        if !under_visible_top_level_span {
+            visible_top_level_spans.push(Rc::new(modified_span));
+            None
+        } else {
+            Some(Rc::new(modified_span))
+        }
     }
 }
 
@@ -187,17 +438,35 @@ pub fn add_busy_percent(s: &mut Span) {
         *busy_ns as f64 / (busy_ns + idle_ns) as f64 * 100.0
     );
 
-    s.attributes.insert(
+    Arc::make_mut(&mut s.data).attributes.insert(
         "busy_percent".to_string(),
         Some(Value::StringValue(busy_percent)),
     );
 }
 
-// Parse the raw OTel data into a tree of spans/
-fn extract_spans(requests: &[ExportTraceServiceRequest]) -> Result<Vec<Rc<Span>>> {
+/// Name of the node which produced spans of the resource: its "service.name" attribute.
+pub fn resource_node_name(resource: Option<&Resource>) -> String {
+    let Some(resource) = resource else {
+        return "no resource".to_string();
+    };
+    resource
+        .attributes
+        .iter()
+        .find(|a| a.key == "service.name")
+        .and_then(|a| a.value.as_ref())
+        .and_then(|v| match &v.value {
+            Some(Value::StringValue(name)) => Some(name.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Read the data of all spans from the raw OTel data.
+/// Doesn't touch any `Rc`, so it can be done on a worker thread.
+pub fn extract_span_data(requests: &[ExportTraceServiceRequest]) -> Result<Vec<Arc<SpanData>>> {
     let t = TaskTimer::new("Extracting spans");
 
-    let mut spans_by_id = BTreeMap::new();
+    let mut span_data = Vec::new();
     for request in requests {
         for rs in &request.resource_spans {
             let resource = match &rs.resource {
@@ -210,15 +479,11 @@ fn extract_spans(requests: &[ExportTraceServiceRequest]) -> Result<Vec<Rc<Span>>
                         );
                     }
 
-                    let name = match attributes.get("service.name") {
-                        Some(Some(Value::StringValue(service_name))) => service_name.clone(),
-                        _ => "unknown".to_string(),
-                    };
-
-                    Rc::new(Node { name, attributes })
+                    let name = resource_node_name(Some(r));
+                    Arc::new(Node { name, attributes })
                 }
-                None => Rc::new(Node {
-                    name: "no resource".to_string(),
+                None => Arc::new(Node {
+                    name: resource_node_name(None),
                     attributes: BTreeMap::new(),
                 }),
             };
@@ -232,7 +497,7 @@ fn extract_spans(requests: &[ExportTraceServiceRequest]) -> Result<Vec<Rc<Span>>
                             attribute.value.clone().and_then(|v| v.value),
                         );
                     }
-                    Rc::new(Scope {
+                    Arc::new(Scope {
                         name: s.name.clone(),
                         version: s.version.clone(),
                         attributes,
@@ -268,67 +533,92 @@ fn extract_spans(requests: &[ExportTraceServiceRequest]) -> Result<Vec<Rc<Span>>
                         });
                     }
 
-                    spans_by_id.insert(
-                        span.span_id.clone(),
-                        Rc::new(Span {
-                            name: span.name.clone(),
-                            original_name: span.name.clone(),
-                            span_id: span.span_id.clone(),
-                            trace_id: span.trace_id.clone(),
-                            parent_span_id: span.parent_span_id.clone(),
-                            start_time,
-                            end_time,
-                            attributes,
-                            events,
-                            node: resource.clone(),
-                            scope: scope.clone(),
-                            children: RefCell::new(Vec::new()),
-                            display_children: RefCell::new(Vec::new()),
-                            min_start_time: Cell::new(start_time),
-                            max_end_time: Cell::new(end_time),
-                            display_options: SpanDisplayConfig {
-                                display_length: DisplayLength::Time,
-                            },
-                            collapse_children: Cell::new(false),
-                            dont_collapse_this_span: Cell::new(false),
-                            parent_height_offset: Cell::new(0),
-                            display_start: Cell::new(0.0),
-                            display_length: Cell::new(0.0),
-                            time_display_length: Cell::new(0.0),
-
-                            incoming_relations: RefCell::new(Vec::new()),
-                            outgoing_relations: RefCell::new(Vec::new()),
-
-                            active_segments: None,
-                        }),
-                    );
+                    span_data.push(Arc::new(SpanData {
+                        original_name: span.name.clone(),
+                        span_id: SpanId::intern(&span.span_id),
+                        trace_id: SpanId::intern(&span.trace_id),
+                        parent_span_id: SpanId::intern(&span.parent_span_id),
+                        start_time,
+                        end_time,
+                        attributes,
+                        events,
+                        node: resource.clone(),
+                        scope: scope.clone(),
+                    }));
+                }
+            }
+        }
+    }
+
+    t.stop();
+
+    Ok(span_data)
+}
+
+/// Handles of the span, trace and parent ids in the raw data, ids which aren't interned are skipped.
+pub fn trace_span_ids(requests: &[ExportTraceServiceRequest]) -> HashSet<SpanId> {
+    let mut ids = HashSet::new();
+    for request in requests {
+        for rs in &request.resource_spans {
+            for ss in &rs.scope_spans {
+                for span in &ss.spans {
+                    for id in [&span.span_id, &span.trace_id, &span.parent_span_id] {
+                        ids.extend(SpanId::find(id));
+                    }
                 }
             }
         }
     }
+    ids.remove(&SpanId::EMPTY);
+    ids
+}
+
+// Parse the raw OTel data into a tree of spans
+fn extract_spans(
+    requests: &[ExportTraceServiceRequest],
+    remote_parents: RemoteParentLinking,
+    orphans: OrphanSpans,
+) -> Result<Vec<Rc<Span>>> {
+    let span_data = extract_span_data(requests)?;
+    let node_by_id: HashMap<SpanId, &str> = span_data
+        .iter()
+        .map(|data| (data.span_id, data.node.name.as_str()))
+        .collect();
+    // Spans are kept in the order of the file, a later span with the same id replaces the earlier.
+    let mut spans_by_id = HashMap::new();
+    let mut span_ids = Vec::new();
+    for data in &span_data {
+        let mut span = Span::new(data.clone());
+        match node_by_id.get(&data.parent_span_id) {
+            Some(parent_node) => span.remote_parent = *parent_node != data.node.name,
+            None => span.orphan = !data.parent_span_id.is_empty(),
+        }
+        if spans_by_id.insert(data.span_id, Rc::new(span)).is_none() {
+            span_ids.push(data.span_id);
+        }
+    }
 
     let mut top_level_spans = vec![];
-    for span in spans_by_id.values() {
-        if let Some(parent_span) = spans_by_id.get(&span.parent_span_id) {
-            parent_span.children.borrow_mut().push(span.clone());
-        } else {
+    for span in span_ids.iter().map(|span_id| &spans_by_id[span_id]) {
+        let stitch = !span.remote_parent || remote_parents == RemoteParentLinking::Stitch;
+        if let Some(parent_span) = spans_by_id.get(&span.parent_span_id).filter(|_| stitch) {
+            parent_span.children_mut().push(span.clone());
+        } else if !(span.orphan && orphans == OrphanSpans::Hide) {
             top_level_spans.push(span.clone());
         }
     }
 
-    t.stop();
-
     Ok(top_level_spans)
 }
 
-fn apply_grouping(spans: &mut Vec<Rc<Span>>) {
+fn apply_grouping(spans: &mut Vec<Rc<Span>>, mode: &StructuredMode) {
     // Collect all spans that should be grouped (including children spans)
     let mut all_groupable_spans = Vec::new();
     let mut span_locations = HashMap::new();
     collect_groupable_spans_recursive(spans, &mut all_groupable_spans, &mut span_locations, None);
 
     // Separate top-level spans: preserve non-groupable spans, prepare groupable spans for merging
-    let mut groups: HashMap<_, Vec<Rc<Span>>> = HashMap::new();
+    let mut groups: BTreeMap<_, Vec<Rc<Span>>> = BTreeMap::new();
     let mut non_grouped_top_level = Vec::new();
     for span in spans.drain(..) {
         let should_be_grouped = span
@@ -354,17 +644,41 @@ fn apply_grouping(spans: &mut Vec<Rc<Span>>) {
             })
             .unwrap_or_else(|| span.name.clone());
 
-        // TODO: make grouping attribute customizable
-        let height_value_opt = span.attributes.get("height").cloned().unwrap_or(None);
-        let height = value_to_text(&height_value_opt);
-        if height == "empty" {
-            println!(
-                "WARN: grouping span '{}' on node '{}' without 'height' attribute",
-                original_name, span.node.name
-            );
-        }
+        // Spans grouped by a grouping rule are split by its attribute, the ones grouped by the
+        // decision of a span rule by height.
+        let rule_index = mode
+            .grouping_rules
+            .iter()
+            .position(|rule| rule.selector.matches(&span));
+        let attribute_value = match rule_index {
+            Some(index) => {
+                let attribute = &mode.grouping_rules[index].group_by_attribute;
+                if attribute.is_empty() {
+                    String::new()
+                } else {
+                    value_to_text(&span.attributes.get(attribute).cloned().unwrap_or(None))
+                }
+            }
+            None => {
+                let height_value_opt = span.attributes.get("height").cloned().unwrap_or(None);
+                let height = value_to_text(&height_value_opt);
+                if height == "empty" {
+                    log::warn!(
+                        "Grouping span '{}' on node '{}' without 'height' attribute",
+                        original_name,
+                        span.node.name
+                    );
+                }
+                height
+            }
+        };
 
-        let grouping_key = (span.node.name.clone(), height, original_name);
+        let grouping_key = (
+            rule_index,
+            span.node.name.clone(),
+            attribute_value,
+            original_name,
+        );
         groups.entry(grouping_key).or_default().push(span);
     }
 
@@ -375,24 +689,49 @@ fn apply_grouping(spans: &mut Vec<Rc<Span>>) {
     spans.extend(non_grouped_top_level);
 
     // Create grouped spans and add to top level
-    for ((_node_name, _height, original_name), span_group) in groups {
-        if span_group.len() > 1 {
-            let grouped_span = create_grouped_span(original_name, span_group);
-            spans.push(Rc::new(grouped_span));
-        } else {
-            let span_rc = span_group.into_iter().next().unwrap();
-            let mut single_span = (*span_rc).clone();
-            single_span.active_segments = None;
-            spans.push(Rc::new(single_span));
+    for ((rule_index, _node_name, _attribute_value, original_name), span_group) in groups {
+        let max_gap = rule_index.map_or(f64::INFINITY, |index| mode.grouping_rules[index].max_gap);
+        for span_group in split_by_gap(span_group, max_gap) {
+            if span_group.len() > 1 {
+                let grouped_span = create_grouped_span(original_name.clone(), span_group);
+                spans.push(Rc::new(grouped_span));
+            } else {
+                let span_rc = span_group.into_iter().next().unwrap();
+                let mut single_span = (*span_rc).clone();
+                single_span.active_segments = None;
+                spans.push(Rc::new(single_span));
+            }
         }
     }
 }
 
+/// Splits the spans into groups of spans which start at most `max_gap` after the end of the
+/// previous spans in the group.
+fn split_by_gap(mut spans: Vec<Rc<Span>>, max_gap: TimePoint) -> Vec<Vec<Rc<Span>>> {
+    spans.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    let mut groups: Vec<Vec<Rc<Span>>> = Vec::new();
+    let mut group_end = f64::NEG_INFINITY;
+    for span in spans {
+        let (start, end) = (span.start_time, span.end_time);
+        match groups.last_mut() {
+            Some(group) if start - group_end <= max_gap => {
+                group.push(span);
+                group_end = group_end.max(end);
+            }
+            _ => {
+                groups.push(vec![span]);
+                group_end = end;
+            }
+        }
+    }
+    groups
+}
+
 fn collect_groupable_spans_recursive(
     spans: &[Rc<Span>],
     collector: &mut Vec<Rc<Span>>,
-    locations: &mut HashMap<Vec<u8>, Option<Vec<u8>>>, // span_id -> parent_span_id
-    parent_id: Option<Vec<u8>>,
+    locations: &mut HashMap<SpanId, Option<SpanId>>, // span_id -> parent_span_id
+    parent_id: Option<SpanId>,
 ) {
     for span in spans {
         let should_be_grouped = span
@@ -403,22 +742,18 @@ fn collect_groupable_spans_recursive(
 
         if should_be_grouped {
             collector.push(span.clone());
-            locations.insert(span.span_id.clone(), parent_id.clone());
+            locations.insert(span.span_id, parent_id);
         }
 
-        let children = span.children.borrow();
-        collect_groupable_spans_recursive(
-            &children,
-            collector,
-            locations,
-            Some(span.span_id.clone()),
-        );
+        // Grouped spans count as visible, so they are never under lazy children.
+        let children = span.transformed_children();
+        collect_groupable_spans_recursive(&children, collector, locations, Some(span.span_id));
     }
 }
 
 fn remove_spans_recursive(
     spans: &mut Vec<Rc<Span>>,
-    span_locations: &HashMap<Vec<u8>, Option<Vec<u8>>>,
+    span_locations: &HashMap<SpanId, Option<SpanId>>,
 ) {
     spans.retain(|span| {
         // Drop this span if it's a top-level grouped span to remove
@@ -429,13 +764,13 @@ fn remove_spans_recursive(
             return false;
         }
 
-        // Recurse into children
-        {
-            let mut children = span.children.borrow_mut();
+        // Recurse into children, lazy children don't contain grouped spans
+        if !span.has_lazy_children() {
+            let mut children = span.children_mut();
             remove_spans_recursive(&mut children, span_locations);
 
             // Then prune any child that is scheduled for removal under this parent
-            let parent_id = span.span_id.clone();
+            let parent_id = span.span_id;
             children.retain(|child| {
                 span_locations
                     .get(&child.span_id)
@@ -487,8 +822,9 @@ fn create_grouped_span(base_name: String, spans: Vec<Rc<Span>>) -> Span {
     let mut grouped_span = (**base_span).clone();
 
     grouped_span.name = format!("{base_name} (total={span_count})");
-    grouped_span.start_time = min_start;
-    grouped_span.end_time = max_end;
+    let data = Arc::make_mut(&mut grouped_span.data);
+    data.start_time = min_start;
+    data.end_time = max_end;
     grouped_span.min_start_time.set(min_start);
     grouped_span.max_end_time.set(max_end);
     grouped_span.active_segments = Some(active_segments);
@@ -509,14 +845,24 @@ fn create_grouped_span(base_name: String, spans: Vec<Rc<Span>>) -> Span {
         .collect::<Vec<_>>()
         .join("\n");
 
-    grouped_span.attributes.insert(
+    Arc::make_mut(&mut grouped_span.data).attributes.insert(
         "grouped_spans_info".to_string(),
         Some(Value::StringValue(spans_info)),
     );
 
     // Remove all children for now, to keep things simple
-    grouped_span.children = RefCell::new(Vec::new());
+    grouped_span.set_children(Vec::new());
     grouped_span.display_children = RefCell::new(Vec::new());
 
+    // Keep the individual spans, so that the group can be expanded back into them
+    grouped_span.grouped_spans = spans_sorted_by_start
+        .iter()
+        .map(|s| {
+            let mut span = (**s).clone();
+            span.active_segments = None;
+            Rc::new(span)
+        })
+        .collect();
+
     grouped_span
 }
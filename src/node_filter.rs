@@ -1,12 +1,33 @@
-use eframe::egui::{self, Button, ComboBox, Modal, ScrollArea, Ui, Vec2, Widget};
+use std::collections::HashMap;
+use std::rc::Rc;
 
-use crate::edit_modes::{AddingOrEditing, EditDisplayModes, HIGHLIGHT_COLOR};
-use crate::structured_modes::{MatchCondition, MatchOperator};
+use eframe::egui::{self, Button, CollapsingHeader, ComboBox, Modal, ScrollArea, Ui, Vec2, Widget};
+
+use crate::colors::theme;
+use crate::edit_modes::{AddingOrEditing, EditDisplayModes};
+use crate::reorder::{draw_reorderable_list, duplicate_name, move_entry};
+use crate::span_index::SpanIntervalIndex;
+use crate::structured_modes::{
+    is_unfilled_attribute_name, MatchCondition, MatchOperator, SpanSelector,
+};
+use crate::types::{value_to_text, Node, Span, TimePoint};
+use crate::undo::UndoStack;
+
+/// Section for nodes which don't match any of the filter's sections.
+pub const OTHER_SECTION_NAME: &str = "Other";
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct NodeFilter {
     pub name: String,
     pub rules: Vec<NodeRule>,
+    /// Nodes can be grouped into labeled sections (e.g. validators, RPC nodes), each section is
+    /// drawn under its own header. Without sections the nodes are shown in one flat list.
+    #[serde(default)]
+    pub sections: Vec<NodeSection>,
+    /// When set, only nodes which have a span matching this selector in the selected time range
+    /// are shown. The nodes change as the selected range moves. Used only with lanes per node.
+    #[serde(default)]
+    pub span_content_selector: Option<SpanSelector>,
     /// Built-in filters (everything, etc.) are not editable and are not saved in persistent data.
     pub is_builtin: bool,
 }
@@ -18,6 +39,87 @@ pub struct NodeRule {
     pub visible: bool,
 }
 
+/// A node belongs to the first section whose conditions it matches.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NodeSection {
+    pub name: String,
+    pub node_name_condition: MatchCondition,
+    /// Node's attributes must match these conditions.
+    /// If the attribute is not present, the node doesn't match.
+    pub attribute_conditions: Vec<(String, MatchCondition)>,
+}
+
+impl NodeSection {
+    pub fn matches(&self, node: &Node) -> bool {
+        if !self.node_name_condition.matches(&node.name) {
+            return false;
+        }
+        self.attribute_conditions
+            .iter()
+            // Skip conditions which were added but never filled in, like in span selectors
+            .filter(|(attr_name, _)| !is_unfilled_attribute_name(attr_name))
+            .all(
+                |(attr_name, condition)| match node.attributes.get(attr_name) {
+                    Some(value) => condition.matches(&value_to_text(value)),
+                    None => condition.matches_missing(),
+                },
+            )
+    }
+}
+
+fn span_or_descendant_matches(
+    selector: &SpanSelector,
+    span: &Rc<Span>,
+    start: TimePoint,
+    end: TimePoint,
+) -> bool {
+    // Descendants are within the min_start_time..max_end_time of the span
+    if span.max_end_time.get() < start || span.min_start_time.get() > end {
+        return false;
+    }
+    if span.start_time <= end && span.end_time >= start && selector.matches(span) {
+        return true;
+    }
+    span.children()
+        .iter()
+        .any(|child| span_or_descendant_matches(selector, child, start, end))
+}
+
+/// Remembers which lanes have spans matching the span content selector of the node filter. The
+/// span trees are walked again only when the selector or the selected range changes, or for lanes
+/// which weren't seen yet.
+#[derive(Default)]
+pub struct SpanContentCache {
+    selector: Option<SpanSelector>,
+    range: (TimePoint, TimePoint),
+    /// [SpanIntervalIndex::id] of a lane -> whether the lane has matching spans.
+    lanes: HashMap<u64, bool>,
+}
+
+impl SpanContentCache {
+    /// Same as [NodeFilter::has_matching_spans] for the spans of `lane` in `start..end`.
+    pub fn has_matching_spans(
+        &mut self,
+        filter: &NodeFilter,
+        lane: &SpanIntervalIndex,
+        start: TimePoint,
+        end: TimePoint,
+    ) -> bool {
+        if filter.span_content_selector.is_none() {
+            return true;
+        }
+        if self.selector != filter.span_content_selector || self.range != (start, end) {
+            self.selector = filter.span_content_selector.clone();
+            self.range = (start, end);
+            self.lanes.clear();
+        }
+        *self
+            .lanes
+            .entry(lane.id())
+            .or_insert_with(|| filter.has_matching_spans(lane.query(start, end), start, end))
+    }
+}
+
 pub fn builtin_filters() -> Vec<NodeFilter> {
     vec![NodeFilter::show_all(), NodeFilter::show_none()]
 }
@@ -31,6 +133,8 @@ impl NodeFilter {
                 condition: MatchCondition::any(),
                 visible: true,
             }],
+            sections: vec![],
+            span_content_selector: None,
             is_builtin: true,
         }
     }
@@ -43,6 +147,8 @@ impl NodeFilter {
                 condition: MatchCondition::any(),
                 visible: false,
             }],
+            sections: vec![],
+            span_content_selector: None,
             is_builtin: true,
         }
     }
@@ -55,6 +161,37 @@ impl NodeFilter {
         }
         false
     }
+
+    /// Whether one of `spans` or their descendants matches the span content selector and
+    /// intersects `start..end`. Always true when the filter doesn't have the selector.
+    pub fn has_matching_spans<'a>(
+        &self,
+        spans: impl IntoIterator<Item = &'a Rc<Span>>,
+        start: TimePoint,
+        end: TimePoint,
+    ) -> bool {
+        let Some(selector) = &self.span_content_selector else {
+            return true;
+        };
+        spans
+            .into_iter()
+            .any(|span| span_or_descendant_matches(selector, span, start, end))
+    }
+
+    /// Index of the section that the node belongs to. Nodes which don't match any section get
+    /// `sections.len()`, the index of the "Other" section.
+    pub fn section_index(&self, node: &Node) -> usize {
+        self.sections
+            .iter()
+            .position(|section| section.matches(node))
+            .unwrap_or(self.sections.len())
+    }
+
+    pub fn section_name(&self, section_index: usize) -> &str {
+        self.sections
+            .get(section_index)
+            .map_or(OTHER_SECTION_NAME, |section| section.name.as_str())
+    }
 }
 
 #[derive(Debug)]
@@ -70,6 +207,8 @@ pub struct EditNodeFilters {
     editing_or_adding_rule: AddingOrEditing,
     not_editable_message: String,
     max_scrollarea_size: Vec2,
+    /// Snapshots of `filters` from before each change.
+    undo: UndoStack<Vec<NodeFilter>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -102,11 +241,13 @@ impl EditNodeFilters {
             editing_or_adding_rule: AddingOrEditing::Adding,
             not_editable_message: String::new(),
             max_scrollarea_size: Vec2::new(800.0, 600.0),
+            undo: UndoStack::new(),
         }
     }
 
     pub fn open(&mut self, filters: Vec<NodeFilter>) {
         self.filters = filters;
+        self.undo.clear();
         self.state = EditNodeFiltersState::Open;
     }
 
@@ -149,21 +290,22 @@ impl EditNodeFilters {
             ScrollArea::vertical()
                 .id_salt("node filters")
                 .show(ui, |ui| {
-                    for (index, filter) in self.filters.iter().enumerate() {
-                        let filter_name = if filter.is_builtin {
-                            format!("{} (builtin)", filter.name)
-                        } else {
-                            filter.name.clone()
-                        };
-
-                        let button = if self.selected_filter_idx == index {
-                            Button::new(filter_name).fill(HIGHLIGHT_COLOR)
-                        } else {
-                            Button::new(filter_name)
-                        };
-                        if button.ui(ui).clicked() {
-                            self.selected_filter_idx = index;
-                        }
+                    if let Some((from, to)) = draw_reorderable_list(
+                        ui,
+                        "node filters",
+                        &self.filters,
+                        &mut self.selected_filter_idx,
+                        |filter| filter.is_builtin,
+                        |filter| {
+                            if filter.is_builtin {
+                                format!("{} (builtin)", filter.name)
+                            } else {
+                                filter.name.clone()
+                            }
+                        },
+                    ) {
+                        self.undo.push(self.filters.clone());
+                        move_entry(&mut self.filters, from, to, &mut self.selected_filter_idx);
                     }
                 });
         });
@@ -183,7 +325,7 @@ impl EditNodeFilters {
                     if filter.is_builtin {
                         self.not_editable_message =
                         "This filter is not editable! Builtin filters that are provided in traviz cannot be changed from the UI. \
-                        You can clone this filter to create your own custom one and then edit the custom filter".to_string();
+                        You can duplicate this filter to create your own custom one and then edit the custom filter".to_string();
                         self.state = EditNodeFiltersState::NotEditableError;
                     } else {
                         self.current_filter = filter.clone();
@@ -193,10 +335,12 @@ impl EditNodeFilters {
                     }
                 }
             }
-            if ui.button("Clone Filter").clicked() {
+            if ui.button("Duplicate Filter").clicked() {
                 let mut new_filter = self.filters[self.selected_filter_idx].clone();
-                new_filter.name = format!("{} Clone", new_filter.name);
+                new_filter.name =
+                    duplicate_name(&new_filter.name, self.filters.iter().map(|f| f.name.as_str()));
                 new_filter.is_builtin = false;
+                self.undo.push(self.filters.clone());
                 self.filters.push(new_filter);
                 self.selected_filter_idx = self.filters.len() - 1;
             }
@@ -210,6 +354,11 @@ impl EditNodeFilters {
                     }
                 }
             }
+            ui.separator();
+            if self.undo.draw_buttons(ui, &mut self.filters) {
+                self.selected_filter_idx =
+                    self.selected_filter_idx.min(self.filters.len().saturating_sub(1));
+            }
         });
 
         let mut result = None;
@@ -238,6 +387,7 @@ impl EditNodeFilters {
 
         self.draw_short_separator(ui);
         if ui.button("Yes, Delete").clicked() {
+            self.undo.push(self.filters.clone());
             self.filters.remove(self.selected_filter_idx);
             self.selected_filter_idx = 0;
             self.state = EditNodeFiltersState::Open;
@@ -272,7 +422,7 @@ impl EditNodeFilters {
                         .show(ui, |ui| {
                             for (index, rule) in self.current_filter.rules.iter().enumerate() {
                                 let button = if self.selected_rule_idx == index {
-                                    Button::new(rule.name.to_string()).fill(HIGHLIGHT_COLOR)
+                                    Button::new(rule.name.to_string()).fill(theme().selected_item)
                                 } else {
                                     Button::new(rule.name.to_string())
                                 };
@@ -338,8 +488,31 @@ impl EditNodeFilters {
             }
         });
         self.draw_short_separator(ui);
+        self.draw_edit_sections(ui);
+        self.draw_short_separator(ui);
+        let mut filter_by_spans = self.current_filter.span_content_selector.is_some();
+        if ui
+            .checkbox(
+                &mut filter_by_spans,
+                "Only show nodes with matching spans in the selected time range",
+            )
+            .changed()
+        {
+            self.current_filter.span_content_selector =
+                filter_by_spans.then(|| SpanSelector::new_equal_name("MySpan"));
+        }
+        if let Some(selector) = &mut self.current_filter.span_content_selector {
+            EditDisplayModes::draw_edit_span_selector(
+                selector,
+                ui,
+                self.max_width,
+                "node filter span content",
+            );
+        }
+        self.draw_short_separator(ui);
         ui.horizontal(|ui| {
             if ui.button("Ok").clicked() {
+                self.undo.push(self.filters.clone());
                 match self.editing_or_adding_filter {
                     AddingOrEditing::Adding => {
                         self.filters.push(self.current_filter.clone());
@@ -359,6 +532,51 @@ impl EditNodeFilters {
         });
     }
 
+    fn draw_edit_sections(&mut self, ui: &mut Ui) {
+        ui.label("Sections").on_hover_text(
+            "Nodes are grouped into sections, a node belongs to the first section it matches",
+        );
+        let mut section_to_remove = None;
+        for (i, section) in self.current_filter.sections.iter_mut().enumerate() {
+            CollapsingHeader::new(&section.name)
+                .id_salt(("node section", i))
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Section Name:");
+                        ui.text_edit_singleline(&mut section.name);
+                    });
+                    ui.label("Node name condition");
+                    EditDisplayModes::draw_edit_match_condition(
+                        ui,
+                        &mut section.node_name_condition,
+                        &format!("section node name condition {i}"),
+                    );
+                    ui.label("Node attribute conditions");
+                    EditDisplayModes::draw_edit_attribute_conditions(
+                        ui,
+                        &mut section.attribute_conditions,
+                        &format!("section {i}"),
+                    );
+                    if ui.button("Remove Section").clicked() {
+                        section_to_remove = Some(i);
+                    }
+                });
+        }
+        if let Some(i) = section_to_remove {
+            self.current_filter.sections.remove(i);
+        }
+        if ui.button("New Section").clicked() {
+            self.current_filter.sections.push(NodeSection {
+                name: format!("Section {}", self.current_filter.sections.len() + 1),
+                node_name_condition: MatchCondition {
+                    operator: MatchOperator::Contains,
+                    value: "node".to_string(),
+                },
+                attribute_conditions: vec![],
+            });
+        }
+    }
+
     fn draw_edit_filter_rule(&mut self, ui: &mut Ui, _ctx: &egui::Context) {
         ui.label("Editing Rule");
         self.draw_short_separator(ui);
@@ -414,6 +632,8 @@ impl EditNodeFilters {
         NodeFilter {
             name: "New Filter".to_string(),
             rules: vec![Self::new_rule()],
+            sections: vec![],
+            span_content_selector: None,
             is_builtin: false,
         }
     }
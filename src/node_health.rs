@@ -0,0 +1,372 @@
+//! Node health report - compares the durations of key spans on every node with the median across
+//! all nodes, to quickly answer "which node is the slow one?".
+
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use eframe::egui::{
+    self, Button, CollapsingHeader, DragValue, Grid, Modal, RichText, ScrollArea, TextEdit, Ui,
+};
+
+use crate::analyze_utils::{percentile_index, process_spans_for_analysis, ModalFocus, Statistics};
+use crate::colors::{node_color, theme};
+use crate::types::{format_ms, Span};
+
+/// Spans which are on the critical path of block production, a good default set to compare nodes.
+pub const DEFAULT_KEY_SPAN_NAMES: &str = "produce_block_on_head, preprocess_block, postprocess_ready_block, apply_new_chunk, validate_chunk_state_witness";
+
+pub const DEFAULT_DEVIATION_THRESHOLD_PERCENT: f64 = 20.0;
+
+/// Durations of one span name on one node, compared to the median of all nodes.
+pub struct SpanHealth {
+    pub span_name: String,
+    pub count: usize,
+    /// Seconds
+    pub mean: f64,
+    /// Seconds
+    pub p99: f64,
+    /// How much the mean is above (positive) or below (negative) the cross-node median of means.
+    pub mean_deviation_percent: f64,
+    /// Same as `mean_deviation_percent`, for p99.
+    pub p99_deviation_percent: f64,
+    /// The span with the p99 duration, used to drill down into the trace.
+    pub p99_span: Rc<Span>,
+}
+
+impl SpanHealth {
+    /// The larger of the mean and p99 deviations, positive means slower than the other nodes.
+    pub fn deviation_percent(&self) -> f64 {
+        self.mean_deviation_percent.max(self.p99_deviation_percent)
+    }
+
+    pub fn is_flagged(&self, threshold_percent: f64) -> bool {
+        self.mean_deviation_percent.abs() > threshold_percent
+            || self.p99_deviation_percent.abs() > threshold_percent
+    }
+}
+
+pub struct NodeHealth {
+    pub node_name: String,
+    /// One entry per key span name that occurs on the node.
+    pub spans: Vec<SpanHealth>,
+}
+
+impl NodeHealth {
+    /// Deviation of the worst span on this node, used for ranking.
+    pub fn worst_deviation_percent(&self) -> f64 {
+        self.spans
+            .iter()
+            .map(|s| s.deviation_percent())
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    pub fn flagged_count(&self, threshold_percent: f64) -> usize {
+        self.spans
+            .iter()
+            .filter(|s| s.is_flagged(threshold_percent))
+            .count()
+    }
+}
+
+pub struct NodeHealthReport {
+    /// Nodes ranked from the slowest (the largest deviation from the median) to the fastest.
+    pub nodes: Vec<NodeHealth>,
+    /// Cross-node medians of (mean, p99) for each key span name, in seconds.
+    pub medians: BTreeMap<String, (f64, f64)>,
+    pub threshold_percent: f64,
+}
+
+/// Computes the health report for the given key span names.
+/// `spans` should contain all spans (including children) of the trace.
+pub fn compute_node_health(
+    spans: &[Rc<Span>],
+    key_span_names: &[String],
+    threshold_percent: f64,
+) -> NodeHealthReport {
+    // span name -> node name -> spans
+    let mut spans_by_name_and_node: BTreeMap<&str, BTreeMap<&str, Vec<&Rc<Span>>>> =
+        BTreeMap::new();
+    for span in spans {
+        if key_span_names
+            .iter()
+            .any(|name| name == span.original_name())
+        {
+            spans_by_name_and_node
+                .entry(span.original_name())
+                .or_default()
+                .entry(span.node.name.as_str())
+                .or_default()
+                .push(span);
+        }
+    }
+
+    let mut medians = BTreeMap::new();
+    let mut nodes: HashMap<&str, Vec<SpanHealth>> = HashMap::new();
+    for (span_name, spans_by_node) in &spans_by_name_and_node {
+        let mut per_node = Vec::new();
+        for (node_name, node_spans) in spans_by_node {
+            let mut durations = Statistics::new();
+            for span in node_spans {
+                durations.add_value(span.end_time - span.start_time);
+            }
+            let mut by_duration = node_spans.clone();
+            by_duration.sort_by(|a, b| {
+                (a.end_time - a.start_time).total_cmp(&(b.end_time - b.start_time))
+            });
+            let p99_span = by_duration[percentile_index(99.0, by_duration.len())].clone();
+            let p99 = p99_span.end_time - p99_span.start_time;
+            per_node.push((*node_name, durations.count, durations.mean(), p99, p99_span));
+        }
+
+        let mut means = Statistics::new();
+        let mut p99s = Statistics::new();
+        for (_, _, mean, p99, _) in &per_node {
+            means.add_value(*mean);
+            p99s.add_value(*p99);
+        }
+        let (median_mean, median_p99) = (means.median(), p99s.median());
+        medians.insert(span_name.to_string(), (median_mean, median_p99));
+
+        for (node_name, count, mean, p99, p99_span) in per_node {
+            nodes.entry(node_name).or_default().push(SpanHealth {
+                span_name: span_name.to_string(),
+                count,
+                mean,
+                p99,
+                mean_deviation_percent: deviation_percent(mean, median_mean),
+                p99_deviation_percent: deviation_percent(p99, median_p99),
+                p99_span,
+            });
+        }
+    }
+
+    let mut nodes: Vec<NodeHealth> = nodes
+        .into_iter()
+        .map(|(node_name, mut spans)| {
+            spans.sort_by(|a, b| b.deviation_percent().total_cmp(&a.deviation_percent()));
+            NodeHealth {
+                node_name: node_name.to_string(),
+                spans,
+            }
+        })
+        .collect();
+    nodes.sort_by(|a, b| {
+        b.worst_deviation_percent()
+            .total_cmp(&a.worst_deviation_percent())
+            .then_with(|| a.node_name.cmp(&b.node_name))
+    });
+
+    NodeHealthReport {
+        nodes,
+        medians,
+        threshold_percent,
+    }
+}
+
+fn deviation_percent(value: f64, median: f64) -> f64 {
+    if median == 0.0 {
+        return 0.0;
+    }
+    (value - median) / median * 100.0
+}
+
+/// Parses a comma separated list of span names.
+pub fn parse_span_names(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+pub struct NodeHealthModal {
+    pub show: bool,
+    key_span_names: String,
+    threshold_percent: f64,
+    all_spans_for_analysis: Vec<Rc<Span>>,
+    report: Option<NodeHealthReport>,
+    pub focus: ModalFocus,
+}
+
+impl Default for NodeHealthModal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NodeHealthModal {
+    pub fn new() -> Self {
+        NodeHealthModal {
+            show: false,
+            key_span_names: DEFAULT_KEY_SPAN_NAMES.to_string(),
+            threshold_percent: DEFAULT_DEVIATION_THRESHOLD_PERCENT,
+            all_spans_for_analysis: Vec::new(),
+            report: None,
+            focus: ModalFocus::default(),
+        }
+    }
+
+    /// Opens the modal and computes the report right away, it's meant to be a one-click check.
+    pub fn open(&mut self, spans_for_analysis: &[Rc<Span>]) {
+        self.show = true;
+        self.all_spans_for_analysis = process_spans_for_analysis(spans_for_analysis).0;
+        self.compute();
+    }
+
+    fn compute(&mut self) {
+        self.report = Some(compute_node_health(
+            &self.all_spans_for_analysis,
+            &parse_span_names(&self.key_span_names),
+            self.threshold_percent,
+        ));
+    }
+
+    pub fn show_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        if !self.show {
+            return;
+        }
+
+        let mut jump_to_span = None;
+        Modal::new("node health".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Node health");
+            ui.horizontal(|ui| {
+                ui.label("Key spans:");
+                ui.add(TextEdit::singleline(&mut self.key_span_names).desired_width(500.0));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Flag nodes deviating from the median by more than");
+                ui.add(
+                    DragValue::new(&mut self.threshold_percent)
+                        .range(0.0..=10000.0)
+                        .suffix("%"),
+                );
+                if ui.button("Compute").clicked() {
+                    self.compute();
+                }
+            });
+            ui.separator();
+
+            if let Some(report) = &self.report {
+                draw_report(ui, report, max_height - 200.0, &mut jump_to_span);
+            }
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.show = false;
+            }
+        });
+
+        if let Some(span) = jump_to_span {
+            self.focus.jump_to(span);
+            self.show = false;
+        }
+    }
+}
+
+fn draw_report(
+    ui: &mut Ui,
+    report: &NodeHealthReport,
+    max_height: f32,
+    jump_to_span: &mut Option<Rc<Span>>,
+) {
+    if report.nodes.is_empty() {
+        ui.label("None of the key spans were found in the trace.");
+        return;
+    }
+
+    ui.label("Cross-node medians:");
+    Grid::new("node health medians")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("Span");
+            ui.strong("Median mean");
+            ui.strong("Median p99");
+            ui.end_row();
+            for (span_name, (mean, p99)) in &report.medians {
+                ui.label(span_name);
+                ui.monospace(format_ms(*mean));
+                ui.monospace(format_ms(*p99));
+                ui.end_row();
+            }
+        });
+    ui.add_space(10.0);
+
+    ui.label("Nodes, slowest first (expand a node for details):");
+    ScrollArea::vertical()
+        .max_height(max_height)
+        .show(ui, |ui| {
+            for (rank, node) in report.nodes.iter().enumerate() {
+                let flagged = node.flagged_count(report.threshold_percent);
+                let header = RichText::new(format!(
+                    "{}. {}  worst: {:+.1}%  flagged: {}/{}",
+                    rank + 1,
+                    node.node_name,
+                    node.worst_deviation_percent(),
+                    flagged,
+                    node.spans.len()
+                ))
+                .color(if flagged > 0 {
+                    theme().error
+                } else {
+                    node_color(&node.node_name)
+                });
+
+                CollapsingHeader::new(header)
+                    .id_salt(&node.node_name)
+                    .show(ui, |ui| {
+                        Grid::new(("node health spans", &node.node_name))
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("Span");
+                                ui.strong("Count");
+                                ui.strong("Mean");
+                                ui.strong("vs median");
+                                ui.strong("p99");
+                                ui.strong("vs median");
+                                ui.strong("");
+                                ui.end_row();
+                                for span_health in &node.spans {
+                                    draw_span_health_row(
+                                        ui,
+                                        span_health,
+                                        report.threshold_percent,
+                                        jump_to_span,
+                                    );
+                                }
+                            });
+                    });
+            }
+        });
+}
+
+fn draw_span_health_row(
+    ui: &mut Ui,
+    span_health: &SpanHealth,
+    threshold_percent: f64,
+    jump_to_span: &mut Option<Rc<Span>>,
+) {
+    let deviation_text = |deviation: f64| {
+        let text = RichText::new(format!("{deviation:+.1}%")).monospace();
+        if deviation.abs() > threshold_percent {
+            text.color(theme().error)
+        } else {
+            text
+        }
+    };
+    ui.label(&span_health.span_name);
+    ui.monospace(span_health.count.to_string());
+    ui.monospace(format_ms(span_health.mean));
+    ui.label(deviation_text(span_health.mean_deviation_percent));
+    ui.monospace(format_ms(span_health.p99));
+    ui.label(deviation_text(span_health.p99_deviation_percent));
+    if ui
+        .add(Button::new("Jump to p99").small())
+        .on_hover_text("Show the span with the p99 duration in the timeline")
+        .clicked()
+    {
+        *jump_to_span = Some(span_health.p99_span.clone());
+    }
+    ui.end_row();
+}
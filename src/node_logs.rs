@@ -0,0 +1,497 @@
+//! Log files of the nodes, shown next to the trace. Every line gets the time from a timestamp
+//! regex and is assigned to a node of the trace, so the lines can be drawn on the lane of the node,
+//! listed for a clicked span and searched in a panel.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::{bail, Context, Result};
+use eframe::egui::{
+    self, ComboBox, Label, Modal, Pos2, Rect, RichText, ScrollArea, Sense, Stroke, TextEdit,
+    TextStyle, Ui,
+};
+use regex::Regex;
+
+use crate::colors::theme;
+use crate::external_events::parse_event_time;
+use crate::types::{TimeFormat, TimePoint};
+
+/// Matches the timestamps at the start of neard logs (`2025-01-01T12:00:00.123456Z  INFO ...`) and
+/// most other RFC 3339 or `2025-01-01 12:00:00.123` timestamps.
+pub const DEFAULT_TIMESTAMP_REGEX: &str =
+    r"^(\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?:Z|[+-]\d{2}:?\d{2})?)";
+
+/// Hovering a log lane lists at most this many lines.
+const MAX_HOVER_LINES: usize = 10;
+
+static ANSI_ESCAPE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").unwrap());
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeLogLine {
+    pub time: TimePoint,
+    pub node: String,
+    /// The whole line, including the timestamp. Lines without a timestamp (e.g. backtraces) are
+    /// appended to the line before them.
+    pub text: String,
+}
+
+/// Parses the log of one node. The timestamp is the `time` capture group of the regex, or the first
+/// group, or the whole match. Timestamps without a time zone are in UTC. Fails if no line has a
+/// timestamp.
+pub fn parse_node_log(
+    contents: &str,
+    node: &str,
+    timestamp_regex: &Regex,
+) -> Result<Vec<NodeLogLine>> {
+    let mut lines: Vec<NodeLogLine> = Vec::new();
+    for raw_line in contents.lines() {
+        let line = ANSI_ESCAPE.replace_all(raw_line, "");
+        match parse_line_time(&line, timestamp_regex) {
+            Some(time) => lines.push(NodeLogLine {
+                time,
+                node: node.to_string(),
+                text: line.into_owned(),
+            }),
+            None => {
+                if let Some(previous) = lines.last_mut() {
+                    if !line.trim().is_empty() {
+                        previous.text.push('\n');
+                        previous.text.push_str(&line);
+                    }
+                }
+            }
+        }
+    }
+    if lines.is_empty() {
+        bail!("no line has a timestamp matching '{timestamp_regex}'");
+    }
+    // Logs are usually in order, but writes from multiple threads can be slightly out of order.
+    lines.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(lines)
+}
+
+fn parse_line_time(line: &str, timestamp_regex: &Regex) -> Option<TimePoint> {
+    let captures = timestamp_regex.captures(line)?;
+    let timestamp = captures
+        .name("time")
+        .or_else(|| captures.get(1))
+        .or_else(|| captures.get(0))?
+        .as_str()
+        .replace(',', ".");
+    if let Ok(time) = parse_event_time(&timestamp) {
+        return Some(time);
+    }
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(&timestamp, format).ok())
+        .map(|date_time| {
+            let utc = date_time.and_utc();
+            utc.timestamp() as f64 + f64::from(utc.timestamp_subsec_nanos()) / 1e9
+        })
+}
+
+/// The trace node which the log file most likely belongs to: a node with the same name as the file,
+/// or a node whose name contains it (`node0.log` -> `neard:node0`). The file name if there's none.
+pub fn guess_node_name(log_path: &Path, node_names: &[String]) -> String {
+    let stem = log_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    node_names
+        .iter()
+        .find(|name| **name == stem)
+        .or_else(|| node_names.iter().find(|name| name.contains(&stem)))
+        .cloned()
+        .unwrap_or(stem)
+}
+
+/// Position of a log line: the index of its node among the nodes sorted by name, and the index of
+/// the line among the lines of the node.
+pub type LogLineIndex = (usize, usize);
+
+/// Imported log lines, grouped by node and sorted by time.
+#[derive(Default)]
+pub struct NodeLogs {
+    lines: BTreeMap<String, Vec<NodeLogLine>>,
+    /// Changed whenever lines are added or removed.
+    generation: u64,
+}
+
+impl NodeLogs {
+    pub fn new() -> NodeLogs {
+        NodeLogs::default()
+    }
+
+    pub fn add(&mut self, lines: Vec<NodeLogLine>) {
+        for line in lines {
+            self.lines.entry(line.node.clone()).or_default().push(line);
+        }
+        for node_lines in self.lines.values_mut() {
+            node_lines.sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
+        self.generation += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.generation += 1;
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.values().map(Vec::len).sum()
+    }
+
+    pub fn node_names(&self) -> impl Iterator<Item = &String> {
+        self.lines.keys()
+    }
+
+    /// Lines of the node with the time in `start..=end`.
+    pub fn lines_in_range(&self, node: &str, start: TimePoint, end: TimePoint) -> &[NodeLogLine] {
+        let Some(lines) = self.lines.get(node) else {
+            return &[];
+        };
+        let first = lines.partition_point(|line| line.time < start);
+        let last = lines.partition_point(|line| line.time <= end);
+        &lines[first..last.max(first)]
+    }
+
+    /// Lines of the node (or of all nodes if `None`) which contain the search text (case
+    /// insensitive), sorted by time.
+    pub fn search(&self, node: Option<&str>, search: &str) -> Vec<&NodeLogLine> {
+        self.search_indices(node, search)
+            .into_iter()
+            .map(|index| self.line(index))
+            .collect()
+    }
+
+    /// Like [`NodeLogs::search`], but returns the positions of the lines.
+    pub fn search_indices(&self, node: Option<&str>, search: &str) -> Vec<LogLineIndex> {
+        let search = search.to_lowercase();
+        let mut found: Vec<(TimePoint, LogLineIndex)> = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, (name, _))| node.is_none_or(|node| node == name.as_str()))
+            .flat_map(|(node_index, (_, lines))| {
+                lines
+                    .iter()
+                    .enumerate()
+                    .map(move |(line_index, line)| (node_index, line_index, line))
+            })
+            .filter(|(_, _, line)| search.is_empty() || line.text.to_lowercase().contains(&search))
+            .map(|(node_index, line_index, line)| (line.time, (node_index, line_index)))
+            .collect();
+        found.sort_by(|a, b| a.0.total_cmp(&b.0));
+        found.into_iter().map(|(_, index)| index).collect()
+    }
+
+    /// The line at a position returned by [`NodeLogs::search_indices`].
+    pub fn line(&self, (node_index, line_index): LogLineIndex) -> &NodeLogLine {
+        let lines = self.lines.values().nth(node_index).unwrap();
+        &lines[line_index]
+    }
+}
+
+/// Draws a marker for every line in `rect`, `to_x` converts the time to the x coordinate. Hovering a
+/// marker shows the lines around it. Returns the line whose marker was clicked.
+pub fn draw_log_lane<'a>(
+    ui: &mut Ui,
+    lines: &'a [NodeLogLine],
+    rect: Rect,
+    to_x: impl Fn(TimePoint) -> f32,
+    id_salt: &str,
+) -> Option<&'a NodeLogLine> {
+    ui.painter().rect_filled(rect, 0.0, theme().lane_background);
+    // Many lines can be at the same pixel, one marker is enough for them.
+    let mut last_x = f32::NEG_INFINITY;
+    for line in lines {
+        let x = to_x(line.time).round();
+        if x != last_x {
+            ui.painter().line_segment(
+                [Pos2::new(x, rect.min.y), Pos2::new(x, rect.max.y)],
+                Stroke::new(1.0, theme().link),
+            );
+            last_x = x;
+        }
+    }
+
+    let response = ui.interact(rect, ui.id().with(("log lane", id_salt)), Sense::click());
+    let pointer = response.hover_pos()?;
+    let near: Vec<&NodeLogLine> = lines
+        .iter()
+        .filter(|line| (to_x(line.time) - pointer.x).abs() <= 3.0)
+        .collect();
+    if near.is_empty() {
+        return None;
+    }
+    let clicked = response.clicked().then_some(near[0]);
+    response.on_hover_ui_at_pointer(|ui| {
+        for line in near.iter().take(MAX_HOVER_LINES) {
+            ui.label(RichText::new(first_line(&line.text)).monospace());
+        }
+        if near.len() > MAX_HOVER_LINES {
+            ui.label(format!("... and {} more", near.len() - MAX_HOVER_LINES));
+        }
+        ui.weak("Click to show in the node logs panel");
+    });
+    clicked
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or("")
+}
+
+/// Lists the lines, with the time and node of each line. Returns the line which was clicked.
+pub fn draw_log_lines<'a>(
+    ui: &mut Ui,
+    lines: &[&'a NodeLogLine],
+    time_format: &TimeFormat,
+    show_node: bool,
+    scroll_to_row: Option<usize>,
+) -> Option<&'a NodeLogLine> {
+    draw_log_rows(
+        ui,
+        lines.len(),
+        |row| lines[row],
+        time_format,
+        show_node,
+        scroll_to_row,
+    )
+}
+
+/// Like [`draw_log_lines`], the lines are got by their row, only for the rows which are visible.
+fn draw_log_rows<'a>(
+    ui: &mut Ui,
+    row_count: usize,
+    line_at_row: impl Fn(usize) -> &'a NodeLogLine,
+    time_format: &TimeFormat,
+    show_node: bool,
+    scroll_to_row: Option<usize>,
+) -> Option<&'a NodeLogLine> {
+    let mut clicked = None;
+    let row_height = ui.text_style_height(&TextStyle::Monospace);
+    let mut scroll_area = ScrollArea::both()
+        .id_salt("node log lines")
+        .auto_shrink([false, true]);
+    if let Some(row) = scroll_to_row {
+        let row_height_with_spacing = row_height + ui.spacing().item_spacing.y;
+        scroll_area = scroll_area.vertical_scroll_offset(row as f32 * row_height_with_spacing);
+    }
+    scroll_area.show_rows(ui, row_height, row_count, |ui, row_range| {
+        for line in row_range.map(&line_at_row) {
+            ui.horizontal(|ui| {
+                if ui
+                    .link(RichText::new(time_format.display_string(line.time)).monospace())
+                    .on_hover_text("Show this time in the span view")
+                    .clicked()
+                {
+                    clicked = Some(line);
+                }
+                if show_node {
+                    ui.label(RichText::new(&line.node).monospace().weak());
+                }
+                ui.add(Label::new(RichText::new(first_line(&line.text)).monospace()).extend())
+                    .on_hover_text(&line.text);
+            });
+        }
+    });
+    clicked
+}
+
+/// Side panel with the imported lines, which can be searched and filtered by node.
+#[derive(Default)]
+pub struct NodeLogsPanel {
+    show: bool,
+    search: String,
+    /// Only lines of this node are shown, all nodes if `None`.
+    node: Option<String>,
+    /// The list is scrolled to the first line at this time on the next frame.
+    scroll_to_time: Option<TimePoint>,
+    /// Set when the time of a line is clicked, the view should be moved to it.
+    pub go_to: Option<TimePoint>,
+    /// The lines which pass the node and search filters, with the filters and the generation of
+    /// the logs they were found for.
+    filtered: Option<(LogsFilter, Vec<LogLineIndex>)>,
+}
+
+/// Node, search text and logs generation of the filtered lines.
+type LogsFilter = (Option<String>, String, u64);
+
+impl NodeLogsPanel {
+    pub fn new() -> NodeLogsPanel {
+        NodeLogsPanel::default()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.show
+    }
+
+    pub fn toggle(&mut self) {
+        self.show = !self.show;
+    }
+
+    /// Opens the panel with the lines of the node, scrolled to the line.
+    pub fn show_line(&mut self, line: &NodeLogLine) {
+        self.show = true;
+        self.node = Some(line.node.clone());
+        self.search.clear();
+        self.scroll_to_time = Some(line.time);
+    }
+
+    /// Draws the panel, has to be called before the central panel is shown.
+    pub fn draw(&mut self, ctx: &egui::Context, logs: &NodeLogs, time_format: &TimeFormat) {
+        if !self.show {
+            return;
+        }
+        egui::SidePanel::right("node logs")
+            .resizable(true)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.strong("Node logs");
+                    ComboBox::new("node logs node", "")
+                        .selected_text(self.node.as_deref().unwrap_or("All nodes"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.node, None, "All nodes");
+                            for node in logs.node_names() {
+                                ui.selectable_value(&mut self.node, Some(node.clone()), node);
+                            }
+                        });
+                    ui.add(
+                        TextEdit::singleline(&mut self.search)
+                            .hint_text("Search")
+                            .desired_width(200.0),
+                    );
+                    if ui.button("Close").clicked() {
+                        self.show = false;
+                    }
+                });
+                let filter = (self.node.clone(), self.search.clone(), logs.generation());
+                if self.filtered.as_ref().is_none_or(|(f, _)| *f != filter) {
+                    let lines = logs.search_indices(self.node.as_deref(), &self.search);
+                    self.filtered = Some((filter, lines));
+                }
+                let lines = &self.filtered.as_ref().unwrap().1;
+                ui.label(format!("{} of {} lines", lines.len(), logs.len()));
+                ui.separator();
+                let scroll_to_row = self
+                    .scroll_to_time
+                    .take()
+                    .map(|time| lines.partition_point(|index| logs.line(*index).time < time));
+                if let Some(line) = draw_log_rows(
+                    ui,
+                    lines.len(),
+                    |row| logs.line(lines[row]),
+                    time_format,
+                    self.node.is_none(),
+                    scroll_to_row,
+                ) {
+                    self.go_to = Some(line.time);
+                }
+            });
+    }
+}
+
+/// Dialog which asks for the node and the timestamp regex of a log file.
+#[derive(Default)]
+pub struct ImportNodeLogs {
+    show: bool,
+    path: PathBuf,
+    node: String,
+    timestamp_regex: String,
+    error: Option<String>,
+}
+
+impl ImportNodeLogs {
+    pub fn new() -> ImportNodeLogs {
+        ImportNodeLogs::default()
+    }
+
+    pub fn open(&mut self, path: PathBuf, node_names: &[String], timestamp_regex: &str) {
+        self.node = guess_node_name(&path, node_names);
+        self.path = path;
+        self.timestamp_regex = timestamp_regex.to_string();
+        self.error = None;
+        self.show = true;
+    }
+
+    /// Returns the parsed lines and the regex which was used when "Import" is clicked and the file
+    /// was parsed. Errors are shown in the dialog.
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        node_names: &[String],
+    ) -> Option<(Vec<NodeLogLine>, String)> {
+        if !self.show {
+            return None;
+        }
+
+        let mut result = None;
+        Modal::new("import node logs".into()).show(ctx, |ui| {
+            ui.set_max_width(600.0);
+            ui.heading("Import node logs");
+            ui.label(format!("File: {}", self.path.display()));
+            ui.horizontal(|ui| {
+                ui.label("Node:");
+                ui.add(TextEdit::singleline(&mut self.node).desired_width(250.0));
+                ComboBox::new("import node logs node", "")
+                    .selected_text("Trace nodes")
+                    .show_ui(ui, |ui| {
+                        for name in node_names {
+                            ui.selectable_value(&mut self.node, name.clone(), name);
+                        }
+                    });
+            });
+            ui.horizontal(|ui| {
+                ui.label("Timestamp regex:");
+                ui.add(TextEdit::singleline(&mut self.timestamp_regex).desired_width(400.0));
+                if ui.small_button("Default").clicked() {
+                    self.timestamp_regex = DEFAULT_TIMESTAMP_REGEX.to_string();
+                }
+            });
+            ui.weak(
+                "The timestamp is the 'time' capture group, or the first group, or the whole match. \
+                 Lines without a timestamp are appended to the line before them.",
+            );
+            if let Some(error) = &self.error {
+                ui.colored_label(theme().error, error);
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Import").clicked() {
+                    match self.parse() {
+                        Ok(lines) => {
+                            result = Some((lines, self.timestamp_regex.clone()));
+                            self.show = false;
+                        }
+                        Err(e) => self.error = Some(format!("{e:#}")),
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    self.show = false;
+                }
+            });
+        });
+        result
+    }
+
+    fn parse(&self) -> Result<Vec<NodeLogLine>> {
+        if self.node.trim().is_empty() {
+            bail!("the node name is empty");
+        }
+        let regex = Regex::new(&self.timestamp_regex).context("invalid timestamp regex")?;
+        let contents = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("failed to read {}", self.path.display()))?;
+        parse_node_log(&contents, self.node.trim(), &regex)
+    }
+}
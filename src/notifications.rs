@@ -0,0 +1,263 @@
+//! Notification center - collects info messages, warnings and errors with timestamps.
+//! Warnings and errors pop up as toasts and are counted on a badge in the top bar, all messages can
+//! be viewed and copied in the notification log. Without it errors would only be visible in the
+//! terminal, which doesn't exist when traviz is launched from a desktop icon.
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use eframe::egui::{
+    self, Align2, Button, Color32, Frame, Grid, Modal, RichText, ScrollArea, Ui, Vec2,
+};
+
+use crate::colors::theme;
+
+/// How long a toast stays on the screen.
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+const MAX_TOASTS: usize = 5;
+/// The oldest notifications are dropped after this many.
+const MAX_NOTIFICATIONS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NotificationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl NotificationLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotificationLevel::Info => "INFO",
+            NotificationLevel::Warning => "WARN",
+            NotificationLevel::Error => "ERROR",
+        }
+    }
+
+    fn color(&self) -> Color32 {
+        match self {
+            NotificationLevel::Info => theme().text,
+            NotificationLevel::Warning => theme().warning,
+            NotificationLevel::Error => theme().error,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub level: NotificationLevel,
+    pub time: DateTime<Local>,
+    pub message: String,
+    /// Used to expire toasts, `time` can jump with the wall clock.
+    created: Instant,
+}
+
+impl Notification {
+    /// One line description, in the format used when copying diagnostics.
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} [{}] {}",
+            self.time.format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.level.name(),
+            self.message
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct Notifications {
+    notifications: Vec<Notification>,
+    /// Warnings and errors which arrived since the log was last opened.
+    unread: usize,
+    show_log: bool,
+}
+
+impl Notifications {
+    pub fn new() -> Notifications {
+        Notifications::default()
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(NotificationLevel::Info, message.into());
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.push(NotificationLevel::Warning, message.into());
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(NotificationLevel::Error, message.into());
+    }
+
+    /// Adds a notification. It's also logged, for those who run traviz from a terminal.
+    pub fn push(&mut self, level: NotificationLevel, message: String) {
+        let notification = Notification {
+            level,
+            time: Local::now(),
+            message,
+            created: Instant::now(),
+        };
+        let log_level = match level {
+            NotificationLevel::Info => log::Level::Info,
+            NotificationLevel::Warning => log::Level::Warn,
+            NotificationLevel::Error => log::Level::Error,
+        };
+        log::log!(log_level, "{}", notification.message);
+        if level >= NotificationLevel::Warning {
+            self.unread += 1;
+        }
+        self.notifications.push(notification);
+        if self.notifications.len() > MAX_NOTIFICATIONS {
+            let excess = self.notifications.len() - MAX_NOTIFICATIONS;
+            self.notifications.drain(..excess);
+        }
+    }
+
+    /// All notifications, the oldest first.
+    pub fn notifications(&self) -> &[Notification] {
+        &self.notifications
+    }
+
+    pub fn unread_count(&self) -> usize {
+        self.unread
+    }
+
+    /// All notifications as text, one per line, ready to be pasted into a bug report.
+    pub fn diagnostics_text(&self) -> String {
+        self.notifications
+            .iter()
+            .map(|n| n.to_line())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn clear(&mut self) {
+        self.notifications.clear();
+        self.unread = 0;
+    }
+
+    pub fn open_log(&mut self) {
+        self.show_log = true;
+        self.unread = 0;
+    }
+
+    /// Button for the top bar which opens the log, shows the number of unread warnings and errors.
+    pub fn draw_badge_button(&mut self, ui: &mut Ui) {
+        let has_unread_errors = self
+            .notifications
+            .iter()
+            .rev()
+            .take(self.unread)
+            .any(|n| n.level == NotificationLevel::Error);
+        let text = if self.unread > 0 {
+            RichText::new(format!("Notifications ({})", self.unread)).color(if has_unread_errors {
+                theme().error
+            } else {
+                theme().warning
+            })
+        } else {
+            RichText::new("Notifications")
+        };
+        if ui.button(text).clicked() {
+            self.open_log();
+        }
+    }
+
+    /// Shows recent warnings and errors in the bottom right corner of the window.
+    pub fn draw_toasts(&mut self, ctx: &egui::Context) {
+        let toasts: Vec<&Notification> = self
+            .notifications
+            .iter()
+            .rev()
+            .filter(|n| {
+                n.level >= NotificationLevel::Warning && n.created.elapsed() < TOAST_DURATION
+            })
+            .take(MAX_TOASTS)
+            .collect();
+        let Some(newest) = toasts.first() else {
+            return;
+        };
+        // Repaint when the newest toast expires, the older ones expire before it.
+        ctx.request_repaint_after(TOAST_DURATION.saturating_sub(newest.created.elapsed()));
+
+        let mut open_log = false;
+        egui::Area::new("notification toasts".into())
+            .anchor(Align2::RIGHT_BOTTOM, Vec2::new(-10.0, -10.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for toast in toasts.iter().rev() {
+                    Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(400.0);
+                        let response = ui.add(
+                            egui::Label::new(
+                                RichText::new(format!("{}: {}", toast.level.name(), toast.message))
+                                    .color(toast.level.color()),
+                            )
+                            .wrap()
+                            .sense(egui::Sense::click()),
+                        );
+                        if response.on_hover_text("Click to open the log").clicked() {
+                            open_log = true;
+                        }
+                    });
+                }
+            });
+        if open_log {
+            self.open_log();
+        }
+    }
+
+    /// Modal with all notifications.
+    pub fn draw_log(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        if !self.show_log {
+            return;
+        }
+
+        Modal::new("notification log".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Notifications");
+            ui.separator();
+
+            if self.notifications.is_empty() {
+                ui.label("Nothing to report.");
+            }
+            ScrollArea::vertical()
+                .max_height(max_height - 100.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    Grid::new("notifications").striped(true).show(ui, |ui| {
+                        for notification in &self.notifications {
+                            ui.monospace(notification.time.format("%H:%M:%S").to_string());
+                            ui.label(
+                                RichText::new(notification.level.name())
+                                    .color(notification.level.color()),
+                            );
+                            ui.label(&notification.message);
+                            ui.end_row();
+                        }
+                    });
+                });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        !self.notifications.is_empty(),
+                        Button::new("Copy diagnostics"),
+                    )
+                    .clicked()
+                {
+                    ctx.copy_text(self.diagnostics_text());
+                }
+                if ui.button("Clear").clicked() {
+                    self.clear();
+                }
+                if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    self.show_log = false;
+                }
+            });
+        });
+    }
+}
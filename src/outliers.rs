@@ -0,0 +1,313 @@
+//! Outlier detection - finds spans which take much longer than other spans with the same name, so
+//! that a few pathological spans can be found among a million normal ones without eyeballing.
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use eframe::egui::{self, Button, DragValue, Grid, Modal, RichText, ScrollArea, TextEdit};
+
+use crate::analyze_utils::{process_spans_for_analysis, ModalFocus, Statistics};
+use crate::colors::node_color;
+use crate::types::{format_ms, Span, TimeFormat};
+
+pub const DEFAULT_MAD_FACTOR: f64 = 10.0;
+pub const DEFAULT_PERCENTILE: f64 = 99.9;
+/// Span names with fewer instances don't have a distribution to compare against.
+pub const MIN_INSTANCES: usize = 10;
+/// The MAD is at least this fraction of the median. When more than half of the spans have the same
+/// duration the MAD is 0, and every span which is a bit longer would be an outlier.
+pub const MIN_MAD_OF_MEDIAN: f64 = 0.01;
+
+/// How the duration threshold of a span name is computed from the durations of all its spans.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlierThreshold {
+    /// median + k * MAD (median absolute deviation from the median, at least
+    /// [`MIN_MAD_OF_MEDIAN`] of the median).
+    Mad { k: f64 },
+    /// The given percentile of durations, e.g. 99.9.
+    Percentile(f64),
+}
+
+#[derive(Debug, Clone)]
+pub struct Outlier {
+    pub span: Rc<Span>,
+    /// Seconds
+    pub duration: f64,
+    /// Median duration of spans with this name, in seconds.
+    pub median: f64,
+    /// Spans with this name longer than this are outliers, in seconds.
+    pub threshold: f64,
+}
+
+impl Outlier {
+    /// How many times longer than the median the span is.
+    pub fn times_median(&self) -> f64 {
+        if self.median == 0.0 {
+            return f64::INFINITY;
+        }
+        self.duration / self.median
+    }
+}
+
+/// Spans which are longer than the threshold of their name, sorted by how many times longer than
+/// the median they are. `spans` should contain all spans (including children) of the trace, with
+/// `span_name` only spans with this name are checked.
+pub fn find_outliers(
+    spans: &[Rc<Span>],
+    span_name: Option<&str>,
+    threshold: OutlierThreshold,
+) -> Vec<Outlier> {
+    let mut spans_by_name: BTreeMap<&str, Vec<&Rc<Span>>> = BTreeMap::new();
+    for span in spans {
+        if span_name.is_none_or(|name| name == span.original_name()) {
+            spans_by_name
+                .entry(span.original_name())
+                .or_default()
+                .push(span);
+        }
+    }
+
+    let mut outliers = Vec::new();
+    for name_spans in spans_by_name.values() {
+        if name_spans.len() < MIN_INSTANCES {
+            continue;
+        }
+        let mut durations = Statistics::new();
+        for span in name_spans {
+            durations.add_value(span.end_time - span.start_time);
+        }
+        let median = durations.median();
+        let name_threshold = match threshold {
+            OutlierThreshold::Mad { k } => {
+                let mut deviations = Statistics::new();
+                for duration in &durations.data_points {
+                    deviations.add_value((duration - median).abs());
+                }
+                median + k * deviations.median().max(median * MIN_MAD_OF_MEDIAN)
+            }
+            OutlierThreshold::Percentile(percentile) => durations.percentile(percentile),
+        };
+        for span in name_spans {
+            let duration = span.end_time - span.start_time;
+            if duration > name_threshold {
+                outliers.push(Outlier {
+                    span: (*span).clone(),
+                    duration,
+                    median,
+                    threshold: name_threshold,
+                });
+            }
+        }
+    }
+    outliers.sort_by(|a, b| {
+        b.times_median()
+            .total_cmp(&a.times_median())
+            .then_with(|| b.duration.total_cmp(&a.duration))
+    });
+    outliers
+}
+
+pub struct OutliersModal {
+    pub show: bool,
+    /// Empty means all span names.
+    span_name: String,
+    use_percentile: bool,
+    mad_factor: f64,
+    percentile: f64,
+    /// Highlight the outliers in the trace view after every computation.
+    auto_highlight: bool,
+    all_spans_for_analysis: Vec<Rc<Span>>,
+    outliers: Option<Vec<Outlier>>,
+    pub focus: ModalFocus,
+    /// If set, the trace view should highlight these spans.
+    pub highlight_spans: Option<Vec<Rc<Span>>>,
+}
+
+impl Default for OutliersModal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutliersModal {
+    pub fn new() -> Self {
+        OutliersModal {
+            show: false,
+            span_name: String::new(),
+            use_percentile: false,
+            mad_factor: DEFAULT_MAD_FACTOR,
+            percentile: DEFAULT_PERCENTILE,
+            auto_highlight: false,
+            all_spans_for_analysis: Vec::new(),
+            outliers: None,
+            focus: ModalFocus::default(),
+            highlight_spans: None,
+        }
+    }
+
+    /// Opens the modal and scans the trace right away.
+    pub fn open(&mut self, spans_for_analysis: &[Rc<Span>]) {
+        self.show = true;
+        self.all_spans_for_analysis = process_spans_for_analysis(spans_for_analysis).0;
+        self.compute();
+    }
+
+    fn threshold(&self) -> OutlierThreshold {
+        if self.use_percentile {
+            OutlierThreshold::Percentile(self.percentile)
+        } else {
+            OutlierThreshold::Mad { k: self.mad_factor }
+        }
+    }
+
+    fn compute(&mut self) {
+        let span_name = self.span_name.trim();
+        let outliers = find_outliers(
+            &self.all_spans_for_analysis,
+            (!span_name.is_empty()).then_some(span_name),
+            self.threshold(),
+        );
+        if self.auto_highlight {
+            self.highlight_spans = Some(outliers.iter().map(|o| o.span.clone()).collect());
+        }
+        self.outliers = Some(outliers);
+    }
+
+    pub fn show_modal(
+        &mut self,
+        ctx: &egui::Context,
+        time_format: &TimeFormat,
+        max_width: f32,
+        max_height: f32,
+    ) {
+        if !self.show {
+            return;
+        }
+
+        let mut jump_to_span = None;
+        let mut compute = false;
+        Modal::new("outliers".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Outliers");
+            ui.label(format!(
+                "Spans which take much longer than other spans with the same name. Names with \
+                 fewer than {MIN_INSTANCES} spans are skipped."
+            ));
+            ui.horizontal(|ui| {
+                ui.label("Span name:");
+                ui.add(
+                    TextEdit::singleline(&mut self.span_name)
+                        .hint_text("all span names")
+                        .desired_width(300.0),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.use_percentile, false, "Longer than median +");
+                ui.add_enabled(
+                    !self.use_percentile,
+                    DragValue::new(&mut self.mad_factor)
+                        .range(0.0..=1000.0)
+                        .speed(0.1),
+                );
+                ui.label("× MAD");
+            })
+            .response
+            .on_hover_text("MAD is the median absolute deviation of durations from the median");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.use_percentile, true, "Longer than percentile");
+                ui.add_enabled(
+                    self.use_percentile,
+                    DragValue::new(&mut self.percentile)
+                        .range(0.0..=100.0)
+                        .speed(0.1),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(
+                    &mut self.auto_highlight,
+                    "Highlight outliers in the trace view",
+                );
+                if ui.button("Compute").clicked() {
+                    compute = true;
+                }
+            });
+            ui.separator();
+
+            if let Some(outliers) = &self.outliers {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} outliers", outliers.len()));
+                    if ui
+                        .add_enabled(!outliers.is_empty(), Button::new("Highlight all"))
+                        .clicked()
+                    {
+                        self.highlight_spans =
+                            Some(outliers.iter().map(|o| o.span.clone()).collect());
+                    }
+                });
+                draw_outliers(
+                    ui,
+                    outliers,
+                    time_format,
+                    max_height - 300.0,
+                    &mut jump_to_span,
+                );
+            }
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.show = false;
+            }
+        });
+
+        if compute {
+            self.compute();
+        }
+        if let Some(span) = jump_to_span {
+            self.focus.jump_to(span);
+            self.show = false;
+        }
+    }
+}
+
+fn draw_outliers(
+    ui: &mut egui::Ui,
+    outliers: &[Outlier],
+    time_format: &TimeFormat,
+    max_height: f32,
+    jump_to_span: &mut Option<Rc<Span>>,
+) {
+    let row_height = ui.text_style_height(&egui::TextStyle::Body) + 6.0;
+    ScrollArea::vertical()
+        .max_height(max_height)
+        .auto_shrink([false, true])
+        .show_rows(ui, row_height, outliers.len(), |ui, row_range| {
+            Grid::new("outliers")
+                .striped(true)
+                .start_row(row_range.start)
+                .show(ui, |ui| {
+                    for outlier in &outliers[row_range] {
+                        let span = &outlier.span;
+                        ui.label(span.original_name());
+                        ui.label(RichText::new(&span.node.name).color(node_color(&span.node.name)));
+                        ui.monospace(time_format.display_string(span.start_time));
+                        ui.monospace(format_ms(outlier.duration));
+                        ui.monospace(format!("{:.1}× median", outlier.times_median()))
+                            .on_hover_text(format!(
+                                "median {}, threshold {}",
+                                format_ms(outlier.median),
+                                format_ms(outlier.threshold)
+                            ));
+                        if ui
+                            .add(Button::new("Jump").small())
+                            .on_hover_text("Show the span in the timeline")
+                            .clicked()
+                        {
+                            *jump_to_span = Some(span.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
@@ -1,55 +1,180 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
+use crate::analyze_dependency::DependencyPreset;
 use crate::builtin_relations::builtin_relations;
-use crate::legacy::RelationV0;
+use crate::clock_offsets::NodeOffsets;
+use crate::legacy::{
+    PersistentDataV1, PersistentDataV2, PersistentDataV3, PersistentDataV4, PersistentDataV5,
+    PersistentDataV6, PersistentDataV7,
+};
+use crate::macros::KeyMacro;
 use crate::node_filter::{builtin_filters, NodeFilter};
+use crate::preferences::Preferences;
+use crate::profiles::{Profile, DEFAULT_PROFILE_NAME};
+use crate::recent_files::RecentFile;
 use crate::relation::{builtin_relation_views, Relation, RelationView};
 use crate::structured_modes::{builtin_structured_modes, StructuredMode};
+use crate::time_markers::TimeMarkerRule;
 
 /// Persistent data structure that holds user-defined display modes and node filters.
 /// If the data structure changes, it should be versioned to maintain compatibility with data saved
-/// using older versions of traviz.
+/// using older versions of traviz. The variant name is the schema version stored in the file
+/// (`{"V8": {...}}`).
+///
+/// Adding a version:
+/// * Move the current latest struct to `legacy.rs` and add the new one here
+/// * Add a `From` migration from the previous version in `legacy.rs` and a step in [`Self::migrate`]
+/// * Update [`CURRENT_VERSION`] and [`PersistentDataLatest`]
+/// * Add the new version to `tests/persistent_test.rs`
+///
+/// New fields of the saved types (e.g. `StructuredMode`) don't need a new version if they have
+/// `#[serde(default)]`, without it data saved before the field was added can't be loaded.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum PersistentData {
     V1(PersistentDataV1),
     V2(PersistentDataV2),
     V3(PersistentDataV3),
+    V4(PersistentDataV4),
+    V5(PersistentDataV5),
+    V6(PersistentDataV6),
+    V7(PersistentDataV7),
+    V8(PersistentDataV8),
 }
 
+/// Version in which the data is saved.
+pub const CURRENT_VERSION: u32 = 8;
+
+pub type PersistentDataLatest = PersistentDataV8;
+
 impl Default for PersistentData {
     fn default() -> Self {
         PersistentData::V2(PersistentDataV2::default())
     }
 }
 
-#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
-pub struct PersistentDataV1 {
-    display_modes: Vec<StructuredMode>,
-    node_filters: Vec<NodeFilter>,
+impl PersistentData {
+    pub fn version(&self) -> u32 {
+        match self {
+            PersistentData::V1(_) => 1,
+            PersistentData::V2(_) => 2,
+            PersistentData::V3(_) => 3,
+            PersistentData::V4(_) => 4,
+            PersistentData::V5(_) => 5,
+            PersistentData::V6(_) => 6,
+            PersistentData::V7(_) => 7,
+            PersistentData::V8(_) => 8,
+        }
+    }
+
+    /// Converts the data to the latest version, one version at a time.
+    pub fn migrate(self) -> PersistentDataLatest {
+        let mut data = self;
+        loop {
+            data = match data {
+                PersistentData::V1(data) => PersistentData::V2(data.into()),
+                PersistentData::V2(data) => PersistentData::V3(data.into()),
+                PersistentData::V3(data) => PersistentData::V4(data.into()),
+                PersistentData::V4(data) => PersistentData::V5(data.into()),
+                PersistentData::V5(data) => PersistentData::V6(data.into()),
+                PersistentData::V6(data) => PersistentData::V7(data.into()),
+                PersistentData::V7(data) => PersistentData::V8(data.into()),
+                PersistentData::V8(data) => return data,
+            };
+        }
+    }
+
+    /// Parses data saved by any supported version. Data saved by a newer traviz gets a clear error
+    /// instead of a generic parsing one.
+    pub fn from_json(json: &str) -> Result<PersistentData> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let version = value
+            .as_object()
+            .filter(|object| object.len() == 1)
+            .and_then(|object| object.keys().next())
+            .and_then(|tag| tag.strip_prefix('V'))
+            .and_then(|version| version.parse::<u32>().ok());
+        match version {
+            Some(version) if version > CURRENT_VERSION => bail!(
+                "the data was saved by a newer version of traviz (data version {version}, \
+                 this version supports up to {CURRENT_VERSION})"
+            ),
+            Some(version) => serde_json::from_value(value)
+                .with_context(|| format!("failed to parse data version {version}")),
+            None => bail!("the data has no version"),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
 }
 
-#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
-pub struct PersistentDataV2 {
-    display_modes: Vec<StructuredMode>,
-    node_filters: Vec<NodeFilter>,
-    relations: Vec<RelationV0>,
-    relation_views: Vec<RelationView>,
+/// Since V8 the display modes, node filters, relations, relation views and time marker rules are
+/// stored in profiles.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PersistentDataV8 {
+    pub profiles: Vec<Profile>,
+    /// Profile which was used last, it's opened when traviz starts.
+    pub current_profile: String,
+    pub preferences: Preferences,
+    pub macros: Vec<KeyMacro>,
+    pub recent_files: Vec<RecentFile>,
+    /// Saved configurations of the dependency analysis, shared by all profiles.
+    #[serde(default)]
+    pub dependency_presets: Vec<DependencyPreset>,
+    /// Clock offsets applied to trace files, by the path (or URL) of the file. They're applied
+    /// again when the file is opened.
+    #[serde(default)]
+    pub clock_offsets: BTreeMap<PathBuf, NodeOffsets>,
 }
 
-#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
-pub struct PersistentDataV3 {
-    display_modes: Vec<StructuredMode>,
-    node_filters: Vec<NodeFilter>,
-    relations: Vec<Relation>,
-    relation_views: Vec<RelationView>,
+impl PersistentDataV8 {
+    /// Replaces the profile with the same name, or adds it if there is none. The profile becomes
+    /// the current one.
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.current_profile = profile.name.clone();
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    /// The profile called `name`, falls back to the current profile, then to the first one, and
+    /// then to a new empty profile when `name` isn't found.
+    pub fn get_profile(&self, name: &str) -> Profile {
+        self.profiles
+            .iter()
+            .find(|p| p.name == name)
+            .or_else(|| {
+                self.profiles
+                    .iter()
+                    .find(|p| p.name == self.current_profile)
+            })
+            .or_else(|| self.profiles.first())
+            .cloned()
+            .unwrap_or_else(|| Profile::new(DEFAULT_PROFILE_NAME))
+    }
+
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.iter().map(|p| p.name.clone()).collect()
+    }
 }
 
+/// Saves the data of the profile called `profile_name` and makes it the current profile. Other
+/// profiles are kept as they are in the saved data.
+#[allow(clippy::too_many_arguments)]
 pub fn save_persistent_data(
+    profile_name: &str,
     display_modes: &[StructuredMode],
     node_filters: &[NodeFilter],
     relations: &[Relation],
     relation_views: &[RelationView],
+    preferences: &Preferences,
+    macros: &[KeyMacro],
+    recent_files: &[RecentFile],
+    time_marker_rules: &[TimeMarkerRule],
 ) -> Result<()> {
     let mut dmodes = display_modes.to_vec();
     dmodes.retain(|mode| !mode.is_builtin);
@@ -63,64 +188,119 @@ pub fn save_persistent_data(
     let mut relation_views = relation_views.to_vec();
     relation_views.retain(|view| !view.is_builtin);
 
-    let data = PersistentData::V3(PersistentDataV3 {
+    let mut data = read_data_for_update();
+    data.set_profile(Profile {
+        name: profile_name.to_string(),
         display_modes: dmodes,
         node_filters: filters,
         relations,
         relation_views,
+        time_marker_rules: time_marker_rules.to_vec(),
     });
+    data.preferences = preferences.clone();
+    data.macros = macros.to_vec();
+    data.recent_files = recent_files.to_vec();
 
-    write_data(&data)
+    write_data(&PersistentData::V8(data))
 }
 
+/// Loads the profile called `profile_name`, or the profile which was used last when there is no
+/// such profile (e.g. when `profile_name` is empty on startup). `profile_name` is set to the name
+/// of the loaded profile and `profile_names` to the names of all profiles.
+#[allow(clippy::too_many_arguments)]
 pub fn load_persistent_data(
+    profile_name: &mut String,
+    profile_names: &mut Vec<String>,
     display_modes: &mut Vec<StructuredMode>,
     node_filters: &mut Vec<NodeFilter>,
     relations: &mut Vec<Relation>,
     relation_views: &mut Vec<RelationView>,
+    preferences: &mut Preferences,
+    macros: &mut Vec<KeyMacro>,
+    recent_files: &mut Vec<RecentFile>,
+    time_marker_rules: &mut Vec<TimeMarkerRule>,
 ) -> Result<()> {
-    let data = read_data()?;
-    let (modes, filters, read_relations, views) = match data {
-        PersistentData::V1(data) => (
-            data.display_modes,
-            data.node_filters,
-            Vec::new(),
-            Vec::new(),
-        ),
-        PersistentData::V2(data) => (
-            data.display_modes,
-            data.node_filters,
-            data.relations.into_iter().map(RelationV0::into).collect(),
-            data.relation_views,
-        ),
-        PersistentData::V3(data) => (
-            data.display_modes,
-            data.node_filters,
-            data.relations,
-            data.relation_views,
-        ),
-    };
+    let data = read_data()?.migrate();
+    let profile = data.get_profile(profile_name);
 
     // Add builtin modes and filters which are not saved in persistent data
     *display_modes = builtin_structured_modes()
         .into_iter()
-        .chain(modes)
+        .chain(profile.display_modes)
         .collect();
 
-    *node_filters = builtin_filters().into_iter().chain(filters).collect();
+    *node_filters = builtin_filters()
+        .into_iter()
+        .chain(profile.node_filters)
+        .collect();
 
     *relations = builtin_relations()
         .into_iter()
-        .chain(read_relations)
+        .chain(profile.relations)
         .collect();
-    *relation_views = builtin_relation_views().into_iter().chain(views).collect();
+    *relation_views = builtin_relation_views()
+        .into_iter()
+        .chain(profile.relation_views)
+        .collect();
+    *time_marker_rules = profile.time_marker_rules;
+    *profile_name = profile.name;
+    *profile_names = data.profile_names();
+    *preferences = data.preferences;
+    *macros = data.macros;
+    *recent_files = data.recent_files;
 
     Ok(())
 }
 
+pub fn load_dependency_presets() -> Result<Vec<DependencyPreset>> {
+    Ok(read_data()?.migrate().dependency_presets)
+}
+
+pub fn save_dependency_presets(presets: &[DependencyPreset]) -> Result<()> {
+    let mut data = read_data_for_update();
+    data.dependency_presets = presets.to_vec();
+    write_data(&PersistentData::V8(data))
+}
+
+pub fn load_clock_offsets() -> Result<BTreeMap<PathBuf, NodeOffsets>> {
+    Ok(read_data()?.migrate().clock_offsets)
+}
+
+pub fn save_clock_offsets(clock_offsets: &BTreeMap<PathBuf, NodeOffsets>) -> Result<()> {
+    let mut data = read_data_for_update();
+    data.clock_offsets = clock_offsets.clone();
+    write_data(&PersistentData::V8(data))
+}
+
+/// All profiles and the name of the current one, for the profile manager.
+pub fn load_profiles() -> Result<(Vec<Profile>, String)> {
+    let data = read_data()?.migrate();
+    Ok((data.profiles, data.current_profile))
+}
+
+/// Replaces all profiles, used by the profile manager to add, rename and delete them.
+pub fn save_profiles(profiles: Vec<Profile>, current_profile: &str) -> Result<()> {
+    let mut data = read_data_for_update();
+    data.profiles = profiles;
+    data.current_profile = current_profile.to_string();
+    write_data(&PersistentData::V8(data))
+}
+
+/// Saved data which is about to be partially replaced. If it can't be read, `read_data` keeps a
+/// backup of it and the parts which aren't replaced fall back to defaults.
+fn read_data_for_update() -> PersistentDataLatest {
+    match read_data() {
+        Ok(data) => data.migrate(),
+        Err(err) => {
+            log::warn!("Failed to read persistent data before saving: {err:#}");
+            PersistentData::default().migrate()
+        }
+    }
+}
+
 fn write_data(data: &PersistentData) -> Result<()> {
     let persistent_data_file = persistent_data_file_path();
-    println!(
+    log::debug!(
         "Writing persistent data to {}",
         persistent_data_file.display()
     );
@@ -143,14 +323,24 @@ fn write_data(data: &PersistentData) -> Result<()> {
 
 fn read_data() -> Result<PersistentData> {
     let path = persistent_data_file_path();
-    println!("Readng persistent data from {}", path.display());
+    log::debug!("Reading persistent data from {}", path.display());
     if !path.try_exists()? {
-        println!("File not found, using default data");
+        log::info!("Persistent data file not found, using default data");
         return Ok(PersistentData::default());
     }
-    let file = std::fs::File::open(&path)?;
-    let data: PersistentData = serde_json::from_reader(file)?;
-    Ok(data)
+    let json = std::fs::read_to_string(&path)?;
+    match PersistentData::from_json(&json) {
+        Ok(data) => Ok(data),
+        Err(err) => {
+            // The data would be overwritten on the next save, keep a copy so that it isn't lost.
+            let backup_path = backup_file_path();
+            std::fs::copy(&path, &backup_path)?;
+            Err(anyhow!(
+                "{err:#}, the unreadable data was copied to {}",
+                backup_path.display()
+            ))
+        }
+    }
 }
 
 fn persistent_data_folder() -> PathBuf {
@@ -160,10 +350,22 @@ fn persistent_data_folder() -> PathBuf {
         .to_path_buf()
 }
 
+/// Where traces fetched from a tracing backend are saved.
+pub fn fetched_traces_folder() -> PathBuf {
+    persistent_data_folder().join("fetched_traces")
+}
+
 fn persistent_data_file_path() -> PathBuf {
     persistent_data_folder().join("persistent_data.json")
 }
 
+fn backup_file_path() -> PathBuf {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    persistent_data_folder().join(format!("persistent_data_backup_{timestamp}.json"))
+}
+
 fn temporary_write_file_path() -> PathBuf {
     let random_number: u64 = rand::random();
     persistent_data_folder().join(format!("temporary_persistent_data{random_number}.json"))
@@ -0,0 +1,229 @@
+//! User preferences which aren't tied to any display mode, filter or relation.
+//! They are saved together with the rest of the persistent data.
+
+use crate::colors::{ColorOverrides, Palette, Theme, ThemeKind};
+use crate::concurrency::ConcurrencyOverlaySettings;
+use crate::modes::{OrphanSpans, RemoteParentLinking};
+use crate::node_logs::DEFAULT_TIMESTAMP_REGEX;
+use crate::relation_arrows::ArrowDeclutterSettings;
+use crate::remote_source::RemoteSourceSettings;
+use crate::source_location::DEFAULT_EDITOR_COMMAND;
+use crate::sticky_header::StickyLaneHeader;
+use crate::structured_modes::SpanSelector;
+use crate::types::{TimeDisplaySettings, TimePoint};
+use crate::utilization::UtilizationLaneSettings;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Preferences {
+    #[serde(default)]
+    pub initial_zoom: InitialZoom,
+    /// Transform hidden subtrees only when they're expanded, makes switching display modes faster.
+    #[serde(default = "default_lazy_mode_transformation")]
+    pub lazy_mode_transformation: bool,
+    /// Show the trace info (number of spans, nodes, top span names, ...) after a file is opened.
+    #[serde(default = "default_show_trace_info_on_load")]
+    pub show_trace_info_on_load: bool,
+    #[serde(default)]
+    pub utilization_lane: UtilizationLaneSettings,
+    #[serde(default)]
+    pub concurrency_overlay: ConcurrencyOverlaySettings,
+    #[serde(default)]
+    pub relation_arrows: ArrowDeclutterSettings,
+    #[serde(default)]
+    pub theme: ThemeKind,
+    #[serde(default)]
+    pub palette: Palette,
+    #[serde(default)]
+    pub color_overrides: ColorOverrides,
+    #[serde(default)]
+    pub time_display: TimeDisplaySettings,
+    /// Command which opens the source of a span, `{file}` and `{line}` are replaced with the values
+    /// of the span's `code.*` attributes.
+    #[serde(default = "default_editor_command")]
+    pub editor_command: String,
+    /// Regex which finds the timestamp in the lines of imported node logs.
+    #[serde(default = "default_node_log_timestamp_regex")]
+    pub node_log_timestamp_regex: String,
+    /// Backend, service and limit used the last time traces were fetched from a backend.
+    #[serde(default)]
+    pub remote_source: RemoteSourceSettings,
+    /// Reuse the downloaded copy when a URL is opened again and the file didn't change, instead of
+    /// downloading it again.
+    #[serde(default = "default_cache_downloaded_files")]
+    pub cache_downloaded_files: bool,
+    /// Size of the whole UI, 1.0 is the native size.
+    #[serde(default = "default_ui_scale")]
+    pub ui_scale: f32,
+    /// Spans matching these selectors aren't displayed in any mode, e.g. noisy message handlers.
+    #[serde(default)]
+    pub muted_spans: Vec<SpanSelector>,
+    /// Where spans whose parent was recorded on another node are displayed.
+    #[serde(default)]
+    pub remote_parents: RemoteParentLinking,
+    /// Where spans whose parent isn't in the trace are displayed.
+    #[serde(default)]
+    pub orphan_spans: OrphanSpans,
+    /// What stays at the top of the viewport when scrolling inside a tall lane.
+    #[serde(default)]
+    pub sticky_lane_header: StickyLaneHeader,
+}
+
+/// Bounds of the UI scale slider.
+pub const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=3.0;
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            initial_zoom: InitialZoom::default(),
+            lazy_mode_transformation: default_lazy_mode_transformation(),
+            show_trace_info_on_load: default_show_trace_info_on_load(),
+            utilization_lane: UtilizationLaneSettings::default(),
+            concurrency_overlay: ConcurrencyOverlaySettings::default(),
+            relation_arrows: ArrowDeclutterSettings::default(),
+            theme: ThemeKind::default(),
+            palette: Palette::default(),
+            color_overrides: ColorOverrides::new(),
+            time_display: TimeDisplaySettings::default(),
+            editor_command: default_editor_command(),
+            node_log_timestamp_regex: default_node_log_timestamp_regex(),
+            remote_source: RemoteSourceSettings::default(),
+            cache_downloaded_files: default_cache_downloaded_files(),
+            ui_scale: default_ui_scale(),
+            muted_spans: Vec::new(),
+            remote_parents: RemoteParentLinking::default(),
+            orphan_spans: OrphanSpans::default(),
+            sticky_lane_header: StickyLaneHeader::default(),
+        }
+    }
+}
+
+impl Preferences {
+    /// The chosen theme with the palette and the color overrides applied.
+    pub fn build_theme(&self) -> Theme {
+        self.theme
+            .theme()
+            .with_palette(self.palette)
+            .with_overrides(&self.color_overrides)
+    }
+}
+
+fn default_lazy_mode_transformation() -> bool {
+    true
+}
+
+fn default_show_trace_info_on_load() -> bool {
+    true
+}
+
+fn default_editor_command() -> String {
+    DEFAULT_EDITOR_COMMAND.to_string()
+}
+
+fn default_node_log_timestamp_regex() -> String {
+    DEFAULT_TIMESTAMP_REGEX.to_string()
+}
+
+fn default_cache_downloaded_files() -> bool {
+    true
+}
+
+fn default_ui_scale() -> f32 {
+    1.0
+}
+
+/// Which part of the trace is shown after a file is opened.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum InitialZoom {
+    /// Show the first `visible_seconds` on the timeline and select the first `selected_seconds`.
+    FirstSeconds {
+        visible_seconds: f64,
+        selected_seconds: f64,
+    },
+    /// Show and select the whole trace.
+    FullTrace,
+    /// Select `selected_seconds` starting slightly before the first produce_block span.
+    AroundFirstProduceBlock { selected_seconds: f64 },
+}
+
+impl Default for InitialZoom {
+    fn default() -> Self {
+        InitialZoom::FirstSeconds {
+            visible_seconds: 5.0,
+            selected_seconds: 1.0,
+        }
+    }
+}
+
+/// Time ranges that the timeline should show after opening a file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InitialWindow {
+    pub visible_start: TimePoint,
+    pub visible_end: TimePoint,
+    pub selected_start: TimePoint,
+    pub selected_end: TimePoint,
+}
+
+impl InitialZoom {
+    pub fn all_kinds() -> [InitialZoom; 3] {
+        [
+            InitialZoom::default(),
+            InitialZoom::FullTrace,
+            InitialZoom::AroundFirstProduceBlock {
+                selected_seconds: 1.0,
+            },
+        ]
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            InitialZoom::FirstSeconds { .. } => "First N seconds",
+            InitialZoom::FullTrace => "Full trace",
+            InitialZoom::AroundFirstProduceBlock { .. } => "Around first produce_block",
+        }
+    }
+
+    /// Computes the initial timeline window for a trace spanning `min_time..max_time`.
+    /// Falls back to the start of the trace when there is no produce_block.
+    pub fn initial_window(
+        &self,
+        min_time: TimePoint,
+        max_time: TimePoint,
+        first_produce_block: Option<TimePoint>,
+    ) -> InitialWindow {
+        match *self {
+            InitialZoom::FirstSeconds {
+                visible_seconds,
+                selected_seconds,
+            } => InitialWindow {
+                visible_start: min_time,
+                visible_end: (min_time + visible_seconds).min(max_time),
+                selected_start: min_time,
+                selected_end: (min_time + selected_seconds).min(max_time),
+            },
+            InitialZoom::FullTrace => InitialWindow {
+                visible_start: min_time,
+                visible_end: max_time,
+                selected_start: min_time,
+                selected_end: max_time,
+            },
+            InitialZoom::AroundFirstProduceBlock { selected_seconds } => {
+                let Some(produce_block_time) = first_produce_block else {
+                    return InitialZoom::FirstSeconds {
+                        visible_seconds: selected_seconds * 5.0,
+                        selected_seconds,
+                    }
+                    .initial_window(min_time, max_time, None);
+                };
+                // Leave a bit of space before the produce_block to see what led to it.
+                let selected_start = (produce_block_time - selected_seconds * 0.1).max(min_time);
+                let selected_end = (selected_start + selected_seconds).min(max_time);
+                InitialWindow {
+                    visible_start: (selected_start - selected_seconds * 2.0).max(min_time),
+                    visible_end: (selected_end + selected_seconds * 2.0).min(max_time),
+                    selected_start,
+                    selected_end,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//! Profiles are named sets of display modes, node filters, relations, relation views and time
+//! marker rules, e.g. one for debugging mainnet and one for forknet experiments. One profile is
+//! active at a time, preferences, macros and recent files are shared by all of them.
+
+use crate::node_filter::NodeFilter;
+use crate::relation::{Relation, RelationView};
+use crate::structured_modes::StructuredMode;
+use crate::time_markers::{default_time_marker_rules, TimeMarkerRule};
+
+/// Profile which holds the data saved before profiles existed.
+pub const DEFAULT_PROFILE_NAME: &str = "Default";
+
+/// Only user-defined entries are stored in a profile, builtin ones are added when it's loaded.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub display_modes: Vec<StructuredMode>,
+    pub node_filters: Vec<NodeFilter>,
+    pub relations: Vec<Relation>,
+    pub relation_views: Vec<RelationView>,
+    pub time_marker_rules: Vec<TimeMarkerRule>,
+}
+
+impl Profile {
+    /// Profile without any user-defined entries.
+    pub fn new(name: impl Into<String>) -> Profile {
+        Profile {
+            name: name.into(),
+            display_modes: Vec::new(),
+            node_filters: Vec::new(),
+            relations: Vec::new(),
+            relation_views: Vec::new(),
+            time_marker_rules: default_time_marker_rules(),
+        }
+    }
+}
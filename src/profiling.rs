@@ -56,7 +56,7 @@ impl Profiler {
                     last_report_time = now;
                 }
 
-                println!(
+                log::info!(
                     "[PROFILE] Report for the last {:.2}s:",
                     interval_duration.as_secs_f32()
                 );
@@ -70,7 +70,7 @@ impl Profiler {
                     } else {
                         0.0
                     };
-                    println!(
+                    log::info!(
                         "[PROFILE]  - {}: {:.3}ms total ({} calls, avg {:.3}ms/call)",
                         name,
                         timing.total_duration.as_secs_f64() * 1000.0,
@@ -84,8 +84,8 @@ impl Profiler {
                 } else {
                     0.0
                 };
-                println!("[PROFILE]  - Average FPS: {fps:.2}");
-                println!("[PROFILE] --- End of Report ---");
+                log::info!("[PROFILE]  - Average FPS: {fps:.2}");
+                log::info!("[PROFILE] --- End of Report ---");
             }
         });
 
@@ -0,0 +1,105 @@
+//! Recently opened trace files, they can be reopened from the "Recent" menu next to "Open file".
+//! The list is kept in persistent data, together with some information about each file captured
+//! when it was last opened.
+
+use std::path::PathBuf;
+
+use eframe::egui::{self, RichText, Ui};
+
+use crate::remote_file::is_remote_path;
+use crate::types::{time_point_to_utc_string, TimePoint};
+
+/// Only this many most recently opened files are remembered.
+pub const MAX_RECENT_FILES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    /// Size of the file in bytes, at the time when it was last opened.
+    pub file_size: u64,
+    /// Time range covered by the trace, at the time when it was last opened.
+    pub trace_start: TimePoint,
+    pub trace_end: TimePoint,
+}
+
+impl RecentFile {
+    /// File name without the directories, falls back to the full path.
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    /// Size and time range of the file, e.g. "12.3 MB, 2025-01-01 10:00:00.000 UTC, 5.000 s"
+    pub fn description(&self) -> String {
+        format!(
+            "{}, {} UTC, {:.3} s",
+            format_file_size(self.file_size),
+            time_point_to_utc_string(self.trace_start),
+            (self.trace_end - self.trace_start).max(0.0)
+        )
+    }
+}
+
+/// Puts `file` at the front of the list. An older entry with the same path is replaced and the list
+/// is truncated to `MAX_RECENT_FILES`.
+pub fn add_recent_file(recent_files: &mut Vec<RecentFile>, file: RecentFile) {
+    recent_files.retain(|f| f.path != file.path);
+    recent_files.insert(0, file);
+    recent_files.truncate(MAX_RECENT_FILES);
+}
+
+pub fn format_file_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit + 1 < UNITS.len() {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+pub enum RecentFilesAction {
+    Open(PathBuf),
+    Clear,
+}
+
+/// Dropdown menu with the recent files, the newest first.
+pub fn draw_recent_files_menu(
+    ui: &mut Ui,
+    recent_files: &[RecentFile],
+) -> Option<RecentFilesAction> {
+    let mut action = None;
+    ui.add_enabled_ui(!recent_files.is_empty(), |ui| {
+        ui.menu_button("Recent", |ui| {
+            for file in recent_files {
+                // URLs are downloaded when they're opened, they can't be checked here.
+                let exists = is_remote_path(&file.path) || file.path.exists();
+                let mut text = RichText::new(file.file_name());
+                if !exists {
+                    text = text.strikethrough();
+                }
+                let response = ui
+                    .add_enabled(exists, egui::Button::new(text))
+                    .on_hover_text(file.path.display().to_string())
+                    .on_disabled_hover_text(format!("{} doesn't exist", file.path.display()));
+                ui.label(RichText::new(file.description()).small().weak());
+                if response.clicked() {
+                    action = Some(RecentFilesAction::Open(file.path.clone()));
+                    ui.close_menu();
+                }
+            }
+            ui.separator();
+            if ui.button("Clear recent files").clicked() {
+                action = Some(RecentFilesAction::Clear);
+                ui.close_menu();
+            }
+        });
+    });
+    action
+}
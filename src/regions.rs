@@ -0,0 +1,344 @@
+//! Named regions of a trace, e.g. "node restarted" or "patch deployed". A region is a time range
+//! with a name and a note, drawn as a shaded band across all lanes. Regions belong to the trace
+//! file, so they are stored in a sidecar file next to it rather than in the persistent data.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use eframe::egui::{self, Align2, Button, Color32, FontId, Modal, Pos2, Rect, ScrollArea, Sense};
+use eframe::egui::{Stroke, Ui, Widget};
+
+use crate::colors::theme;
+use crate::types::{TimeFormat, TimePoint};
+
+/// Opacity of the band drawn over the spans, the spans have to stay readable.
+const BAND_ALPHA: u8 = 40;
+const DEFAULT_REGION_COLOR: [u8; 3] = [255, 160, 0];
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Region {
+    pub name: String,
+    /// Longer description, shown when the label of the region is hovered.
+    pub note: String,
+    pub start: TimePoint,
+    pub end: TimePoint,
+    /// sRGB color of the band.
+    pub color: [u8; 3],
+}
+
+impl Region {
+    pub fn new(name: String, start: TimePoint, end: TimePoint) -> Region {
+        Region {
+            name,
+            note: String::new(),
+            start: start.min(end),
+            end: start.max(end),
+            color: DEFAULT_REGION_COLOR,
+        }
+    }
+
+    pub fn color32(&self) -> Color32 {
+        let [r, g, b] = self.color;
+        Color32::from_rgb(r, g, b)
+    }
+
+    fn time_range_string(&self, time_format: &TimeFormat) -> String {
+        format!(
+            "{} - {}",
+            time_format.display_string(self.start),
+            time_format.display_string(self.end)
+        )
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RegionsFile {
+    version: u32,
+    regions: Vec<Region>,
+}
+
+/// `trace.json` has its regions in `trace.json.traviz-regions.json`.
+pub fn regions_sidecar_path(trace_path: &Path) -> PathBuf {
+    let mut file_name = trace_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".traviz-regions.json");
+    trace_path.with_file_name(file_name)
+}
+
+/// Regions saved for the trace file, empty if there is no sidecar file.
+pub fn load_regions(trace_path: &Path) -> Result<Vec<Region>> {
+    let path = regions_sidecar_path(trace_path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let json = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let file: RegionsFile = serde_json::from_str(&json)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    Ok(file.regions)
+}
+
+/// Writes the regions next to the trace file. The sidecar file is removed when there are no
+/// regions, so that traces without regions don't get one.
+pub fn save_regions(trace_path: &Path, regions: &[Region]) -> Result<()> {
+    let path = regions_sidecar_path(trace_path);
+    if regions.is_empty() {
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("failed to remove {}", path.display()))?;
+        }
+        return Ok(());
+    }
+    let file = RegionsFile {
+        version: 1,
+        regions: regions.to_vec(),
+    };
+    std::fs::write(&path, serde_json::to_string_pretty(&file)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Horizontal extent of the region in `area`, `None` if it's outside of `start..end`.
+fn region_x_range(
+    region: &Region,
+    start: TimePoint,
+    end: TimePoint,
+    area: Rect,
+) -> Option<(f32, f32)> {
+    if region.end < start || region.start > end || end <= start {
+        return None;
+    }
+    let to_x = |time: TimePoint| {
+        area.min.x + ((time.clamp(start, end) - start) / (end - start)) as f32 * area.width()
+    };
+    Some((to_x(region.start), to_x(region.end)))
+}
+
+/// Shaded bands of the regions, drawn under the spans.
+pub fn draw_region_bands(
+    ui: &mut Ui,
+    regions: &[Region],
+    start: TimePoint,
+    end: TimePoint,
+    area: Rect,
+) {
+    for region in regions {
+        let Some((min_x, max_x)) = region_x_range(region, start, end, area) else {
+            continue;
+        };
+        let [r, g, b] = region.color;
+        let band = Rect::from_x_y_ranges(min_x..=max_x.max(min_x + 1.0), area.y_range());
+        ui.painter().rect_filled(
+            band,
+            0.0,
+            Color32::from_rgba_unmultiplied(r, g, b, BAND_ALPHA),
+        );
+        for x in [min_x, max_x] {
+            ui.painter().line_segment(
+                [Pos2::new(x, area.min.y), Pos2::new(x, area.max.y)],
+                Stroke::new(1.0, region.color32()),
+            );
+        }
+    }
+}
+
+/// Names of the regions above the spans, hovering a name shows the note.
+pub fn draw_region_labels(
+    ui: &mut Ui,
+    regions: &[Region],
+    start: TimePoint,
+    end: TimePoint,
+    area: Rect,
+    time_format: &TimeFormat,
+) {
+    let font_id = FontId::proportional(0.8 * egui::TextStyle::Body.resolve(ui.style()).size);
+    for (index, region) in regions.iter().enumerate() {
+        let Some((min_x, max_x)) = region_x_range(region, start, end, area) else {
+            continue;
+        };
+        let label_area = Rect::from_x_y_ranges(min_x..=max_x.max(min_x + 1.0), area.y_range());
+        ui.painter().rect_filled(label_area, 0.0, region.color32());
+        ui.painter().with_clip_rect(label_area).text(
+            Pos2::new(min_x + 3.0, area.center().y),
+            Align2::LEFT_CENTER,
+            &region.name,
+            font_id.clone(),
+            Color32::BLACK,
+        );
+        let hover_text = if region.note.is_empty() {
+            format!("{}\n{}", region.name, region.time_range_string(time_format))
+        } else {
+            format!(
+                "{}\n{}\n\n{}",
+                region.name,
+                region.time_range_string(time_format),
+                region.note
+            )
+        };
+        ui.interact(
+            label_area,
+            ui.id().with(("region label", index)),
+            Sense::hover(),
+        )
+        .on_hover_text(hover_text);
+    }
+}
+
+/// Dialog for adding, editing and removing the regions of the loaded trace.
+pub struct EditRegions {
+    show: bool,
+    regions: Vec<Region>,
+    selected_region_idx: usize,
+    /// Range selected on the timeline when the dialog was opened, used for new regions.
+    selected_range: (TimePoint, TimePoint),
+    /// Set when "Go to" is clicked, the changes are saved and the view should be moved to this
+    /// range.
+    pub go_to: Option<(TimePoint, TimePoint)>,
+}
+
+impl Default for EditRegions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditRegions {
+    pub fn new() -> EditRegions {
+        EditRegions {
+            show: false,
+            regions: Vec::new(),
+            selected_region_idx: 0,
+            selected_range: (0.0, 0.0),
+            go_to: None,
+        }
+    }
+
+    pub fn open(&mut self, regions: Vec<Region>, selected_range: (TimePoint, TimePoint)) {
+        self.regions = regions;
+        self.selected_region_idx = 0;
+        self.selected_range = selected_range;
+        self.show = true;
+    }
+
+    /// Opens the dialog with a new region covering the selected range.
+    pub fn open_with_new_region(
+        &mut self,
+        regions: Vec<Region>,
+        selected_range: (TimePoint, TimePoint),
+    ) {
+        self.open(regions, selected_range);
+        self.add_region_for_selected_range();
+    }
+
+    fn add_region_for_selected_range(&mut self) {
+        let (start, end) = self.selected_range;
+        let name = format!("Region {}", self.regions.len() + 1);
+        self.regions.push(Region::new(name, start, end));
+        self.selected_region_idx = self.regions.len() - 1;
+    }
+
+    /// Returns the new list of regions when the user clicks "Save" or "Go to".
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        time_format: &TimeFormat,
+        max_width: f32,
+        max_height: f32,
+    ) -> Option<Vec<Region>> {
+        if !self.show {
+            return None;
+        }
+
+        let mut result = None;
+        Modal::new("edit regions".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Regions");
+            ui.label(
+                "Named time ranges drawn across all lanes. They are saved in a file next to the \
+                 trace.",
+            );
+            ui.separator();
+
+            ScrollArea::vertical()
+                .id_salt("regions")
+                .max_height(150.0)
+                .show(ui, |ui| {
+                    for (index, region) in self.regions.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            let button = if self.selected_region_idx == index {
+                                Button::new(&region.name).fill(theme().selected_item)
+                            } else {
+                                Button::new(&region.name)
+                            };
+                            if button.ui(ui).clicked() {
+                                self.selected_region_idx = index;
+                            }
+                            ui.colored_label(
+                                theme().weak_text,
+                                region.time_range_string(time_format),
+                            );
+                        });
+                    }
+                    if self.regions.is_empty() {
+                        ui.label("<empty>");
+                    }
+                });
+
+            ui.horizontal(|ui| {
+                if ui
+                    .button("New region")
+                    .on_hover_text("Region covering the range selected on the timeline")
+                    .clicked()
+                {
+                    self.add_region_for_selected_range();
+                }
+                if ui.button("Delete region").clicked()
+                    && self.selected_region_idx < self.regions.len()
+                {
+                    self.regions.remove(self.selected_region_idx);
+                    self.selected_region_idx = self.selected_region_idx.saturating_sub(1);
+                }
+            });
+            ui.separator();
+
+            if let Some(region) = self.regions.get_mut(self.selected_region_idx) {
+                ui.horizontal(|ui| {
+                    ui.label("Name:");
+                    ui.text_edit_singleline(&mut region.name);
+                    ui.label("Color:");
+                    ui.color_edit_button_srgb(&mut region.color);
+                });
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "Time range: {}",
+                        region.time_range_string(time_format)
+                    ));
+                    if ui
+                        .button("Set to the selected range")
+                        .on_hover_text("Use the range selected on the timeline")
+                        .clicked()
+                    {
+                        (region.start, region.end) = self.selected_range;
+                    }
+                    if ui.button("Go to").clicked() {
+                        self.go_to = Some((region.start, region.end));
+                    }
+                });
+                ui.label("Note:");
+                ui.text_edit_multiline(&mut region.note);
+                ui.separator();
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() || self.go_to.is_some() {
+                    result = Some(std::mem::take(&mut self.regions));
+                    self.show = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.show = false;
+                }
+            });
+        });
+        result
+    }
+}
@@ -1,13 +1,19 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
 use std::rc::{Rc, Weak};
+use std::sync::Arc;
 
+use eframe::egui::Color32;
 use sha2::Digest;
 use uuid::Uuid;
 
+use crate::background_task::BackgroundTask;
 use crate::builtin_relations;
-use crate::structured_modes::SpanSelector;
+use crate::colors;
+use crate::span_id::SpanId;
+use crate::structured_modes::{MatchCondition, SpanSelector};
 use crate::task_timer::TaskTimer;
-use crate::types::{value_to_text, Span};
+use crate::types::{value_to_text, Span, SpanData, TimePoint};
 
 pub fn make_uuid_from_seed(seed: &str) -> Uuid {
     let digest_bytes: [u8; 32] = sha2::Sha256::digest(seed).into();
@@ -34,9 +40,64 @@ pub struct Relation {
     /// Setting it to a negative value allows relations to match even if the "to" span starts before the "from" span ends.
     pub min_time_diff: f64,
 
+    /// Number of "from" spans required by each "to" span, e.g. a block is produced after enough
+    /// endorsements arrive. With more than one the relation is a fan-in: a "to" span is related
+    /// only when at least this many "from" spans match it, and only to the ones which arrived first.
+    /// The last of them decides when the "to" span could start.
+    #[serde(default = "default_required_sources")]
+    pub required_sources: usize,
+
+    /// List spans which match one of the selectors, but weren't matched with any counterpart, e.g.
+    /// a message which was sent but never received.
+    #[serde(default)]
+    pub report_unmatched: bool,
+
+    #[serde(default)]
+    pub arrow_style: ArrowStyle,
+
     pub is_builtin: bool,
 }
 
+fn default_required_sources() -> usize {
+    1
+}
+
+/// How the arrows of a relation are drawn in the span view, so that relations can be told apart.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ArrowStyle {
+    /// sRGB color of the arrows.
+    pub color: [u8; 3],
+    pub width: f32,
+    pub dashed: bool,
+    /// Write the delay next to each arrow.
+    pub show_label: bool,
+}
+
+impl Default for ArrowStyle {
+    fn default() -> Self {
+        let blue = colors::INTENSE_BLUE;
+        ArrowStyle {
+            color: [blue.r(), blue.g(), blue.b()],
+            width: 2.0,
+            dashed: false,
+            show_label: true,
+        }
+    }
+}
+
+impl ArrowStyle {
+    /// Arrows left at the default color follow the theme, so that the palette and the relation
+    /// arrow color override apply to them.
+    pub fn color32(&self) -> Color32 {
+        if self.color == ArrowStyle::default().color {
+            return colors::theme().relation_arrow;
+        }
+        let [r, g, b] = self.color;
+        Color32::from_rgb(r, g, b)
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct AttributeRelation {
     pub from_attribute: String,
@@ -64,7 +125,11 @@ pub enum MatchType {
 }
 
 impl Relation {
-    pub fn matches(&self, from_span: &Span, to_span: &Span) -> bool {
+    pub fn is_fan_in(&self) -> bool {
+        self.required_sources > 1
+    }
+
+    pub fn matches(&self, from_span: &SpanData, to_span: &SpanData) -> bool {
         if !self.from_span_selector.matches(from_span) {
             return false;
         }
@@ -99,7 +164,7 @@ impl Relation {
 }
 
 impl AttributeRelation {
-    fn matches(&self, from_span: &Span, to_span: &Span) -> bool {
+    fn matches(&self, from_span: &SpanData, to_span: &SpanData) -> bool {
         let Some(from_value) = from_span.attributes.get(&self.from_attribute) else {
             return false;
         };
@@ -139,10 +204,392 @@ pub struct RelationViews {
 pub struct RelationInstance {
     pub from_span: Weak<Span>,
     pub to_span: Weak<Span>,
-    #[allow(unused)]
     pub relation: Rc<Relation>,
+    /// For fan-in relations, position of the "from" span in the order of arrival at the "to" span,
+    /// starting from 1.
+    pub arrival: Option<usize>,
+}
+
+impl RelationInstance {
+    /// The "from" span whose arrival completed a fan-in relation.
+    pub fn is_last_arrival(&self) -> bool {
+        self.arrival == Some(self.relation.required_sources)
+    }
+
+    /// Which part of the relation's `max_time_diff` is used by this instance, e.g. 0.95 when the
+    /// "to" span started after 95% of the limit. `None` for relations without a limit.
+    pub fn time_limit_usage(&self) -> Option<f64> {
+        let max_time_diff = self.relation.max_time_diff.filter(|max| *max > 0.0)?;
+        let from_span = self.from_span.upgrade()?;
+        let to_span = self.to_span.upgrade()?;
+        Some((to_span.start_time - from_span.start_time) / max_time_diff)
+    }
+
+    /// True when the instance is within `percent`% of the relation's `max_time_diff`, i.e. it
+    /// almost wasn't matched.
+    pub fn is_near_miss(&self, percent: f64) -> bool {
+        self.time_limit_usage()
+            .is_some_and(|usage| usage >= 1.0 - percent / 100.0)
+    }
+}
+
+/// A span connected to another one by a relation instance, see [related_spans].
+#[derive(Debug, Clone)]
+pub struct RelatedSpan {
+    /// The span on the other end of the relation.
+    pub span: Rc<Span>,
+    pub relation: Rc<Relation>,
+    /// True when the related span is the "from" span of the relation.
+    pub incoming: bool,
+    /// Time between the end of the "from" span and the start of the "to" span.
+    pub delay: TimePoint,
+}
+
+/// Spans connected to `span` by the relation instances found so far, the incoming ones first, each
+/// group sorted by the start time of the related span. Used to follow a message across nodes.
+pub fn related_spans(span: &Span) -> Vec<RelatedSpan> {
+    let mut related = Vec::new();
+    for (instances, incoming) in [
+        (&span.incoming_relations, true),
+        (&span.outgoing_relations, false),
+    ] {
+        let mut group: Vec<RelatedSpan> = instances
+            .borrow()
+            .iter()
+            .filter_map(|instance| {
+                let from_span = instance.from_span.upgrade()?;
+                let to_span = instance.to_span.upgrade()?;
+                Some(RelatedSpan {
+                    delay: to_span.start_time - from_span.end_time,
+                    span: if incoming { from_span } else { to_span },
+                    relation: instance.relation.clone(),
+                    incoming,
+                })
+            })
+            .collect();
+        group.sort_by(|a, b| a.span.start_time.total_cmp(&b.span.start_time));
+        related.extend(group);
+    }
+    related
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum UnmatchedSide {
+    /// A "from" span without any "to" span.
+    From,
+    /// A "to" span without a "from" span, or without enough of them for fan-in relations.
+    To,
+}
+
+/// A span of a relation with [Relation::report_unmatched] which wasn't matched with a counterpart.
+#[derive(Debug, Clone)]
+pub struct UnmatchedSpan {
+    pub span: Rc<Span>,
+    pub relation: Rc<Relation>,
+    pub side: UnmatchedSide,
+}
+
+/// Spans are matched in batches of this size, so that big groups are split between threads.
+const RELATION_SEARCH_BATCH_SIZE: usize = 4096;
+
+/// Part of the relation search processed by one worker thread: "from" spans
+/// `from_range` of group `from_group` are matched against all spans of group `to_group`.
+struct RelationSearchItem {
+    relation_index: usize,
+    from_group: usize,
+    to_group: usize,
+    from_range: Range<usize>,
+}
+
+/// A found relation instance, spans are identified by (group, index in group).
+struct RelationMatch {
+    relation_index: usize,
+    from: (usize, usize),
+    to: (usize, usize),
+}
+
+/// Relation matching running in the background. Matching is done on the thread safe [SpanData],
+/// found instances are attached to the spans on the UI thread in [RelationSearch::poll].
+pub struct RelationSearch {
+    /// Spans grouped by name, sorted by start time. Workers have the same groups of [SpanData].
+    span_groups: Vec<Vec<Rc<Span>>>,
+    relations: Vec<Rc<Relation>>,
+    found_per_relation: Vec<usize>,
+    /// Matches of fan-in relations, they can be resolved only when all of them are found.
+    fan_in_matches: Vec<RelationMatch>,
+    /// Groups of spans with names matching the "from" and "to" selector of each relation.
+    relation_groups: Vec<(Vec<usize>, Vec<usize>)>,
+    /// (relation index, span) of matched "from" and "to" spans, only for relations which report
+    /// unmatched spans.
+    matched_from: HashSet<(usize, (usize, usize))>,
+    matched_to: HashSet<(usize, (usize, usize))>,
+    unmatched: Vec<UnmatchedSpan>,
+    task: BackgroundTask<Vec<RelationMatch>>,
+    task_timer: TaskTimer,
+}
+
+impl RelationSearch {
+    /// Starts finding instances of relations enabled in `view`.
+    /// Relations found previously are removed from the spans.
+    pub fn start(all_relations: &[Relation], view: &RelationView, spans: &[Rc<Span>]) -> Self {
+        let task_timer = TaskTimer::new("Finding relations");
+
+        let relations: Vec<Relation> = view
+            .enabled_relations
+            .iter()
+            .filter_map(|id| all_relations.iter().find(|r| &r.id == id))
+            .cloned()
+            .collect();
+
+        // Spans grouped by name, sorted by start time. Lazy children have no relations to clear, they
+        // have to be transformed only if there are relations to find.
+        let transform_lazy_children = !relations.is_empty();
+        let mut spans_by_name: HashMap<String, Vec<Rc<Span>>> = HashMap::new();
+        for span in spans {
+            gather_spans_by_name(span, transform_lazy_children, &mut spans_by_name);
+        }
+        let mut group_names = Vec::with_capacity(spans_by_name.len());
+        let mut span_groups = Vec::with_capacity(spans_by_name.len());
+        for (name, mut spans) in spans_by_name {
+            // Sort spans by start time
+            spans.sort_by(|a, b| {
+                a.start_time
+                    .partial_cmp(&b.start_time)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+
+            // Clear outgoing and incoming relations for each span
+            for span in &spans {
+                span.outgoing_relations.borrow_mut().clear();
+                span.incoming_relations.borrow_mut().clear();
+            }
+
+            group_names.push(name);
+            span_groups.push(spans);
+        }
+        let data_groups: Vec<Vec<Arc<SpanData>>> = span_groups
+            .iter()
+            .map(|spans| spans.iter().map(|s| s.data.clone()).collect())
+            .collect();
+
+        let mut items = Vec::new();
+        let mut relation_groups = Vec::with_capacity(relations.len());
+        for (relation_index, relation) in relations.iter().enumerate() {
+            let matching_groups = |selector: &SpanSelector| {
+                group_names
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, name)| selector.span_name_condition.matches(name))
+                    .map(|(group, _)| group)
+                    .collect::<Vec<_>>()
+            };
+            let to_groups = matching_groups(&relation.to_span_selector);
+            let from_groups = matching_groups(&relation.from_span_selector);
+            for &from_group in &from_groups {
+                for &to_group in &to_groups {
+                    let from_len = span_groups[from_group].len();
+                    for batch_start in (0..from_len).step_by(RELATION_SEARCH_BATCH_SIZE) {
+                        items.push(RelationSearchItem {
+                            relation_index,
+                            from_group,
+                            to_group,
+                            from_range: batch_start
+                                ..(batch_start + RELATION_SEARCH_BATCH_SIZE).min(from_len),
+                        });
+                    }
+                }
+            }
+            relation_groups.push((from_groups, to_groups));
+        }
+
+        let relations_for_workers = relations.clone();
+        let task = BackgroundTask::spawn_parallel(
+            items,
+            |item| item.from_range.len(),
+            move |item| find_relation_matches(&relations_for_workers, &data_groups, item),
+        );
+
+        RelationSearch {
+            span_groups,
+            found_per_relation: vec![0; relations.len()],
+            fan_in_matches: Vec::new(),
+            relation_groups,
+            matched_from: HashSet::new(),
+            matched_to: HashSet::new(),
+            unmatched: Vec::new(),
+            relations: relations.into_iter().map(Rc::new).collect(),
+            task,
+            task_timer,
+        }
+    }
+
+    /// Attaches relation instances found since the last poll to the spans and returns them.
+    pub fn poll(&mut self) -> Vec<RelationInstance> {
+        let batches = self.task.poll();
+        self.attach_matches(batches)
+    }
+
+    /// Blocks until the search is finished, returns all instances which weren't polled yet.
+    pub fn wait(mut self) -> Vec<RelationInstance> {
+        let batches = self.task.wait();
+        self.attach_matches(batches)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Fraction of the work done, between 0 and 1.
+    pub fn progress(&self) -> f32 {
+        self.task.progress()
+    }
+
+    /// Stops the search, relations found so far stay attached to the spans.
+    pub fn cancel(&self) {
+        self.task.cancel();
+    }
+
+    fn attach_matches(&mut self, batches: Vec<Vec<RelationMatch>>) -> Vec<RelationInstance> {
+        let mut res = Vec::new();
+        for relation_match in batches.into_iter().flatten() {
+            if self.relations[relation_match.relation_index].is_fan_in() {
+                self.fan_in_matches.push(relation_match);
+                continue;
+            }
+            res.push(self.attach_instance(&relation_match, None));
+        }
+
+        if self.task.is_finished() {
+            if self.task.is_cancelled() {
+                log::info!("Finding relations was cancelled");
+            } else {
+                res.extend(self.resolve_fan_in());
+                self.unmatched = self.find_unmatched();
+            }
+            for (relation, found) in self.relations.iter().zip(&self.found_per_relation) {
+                log::info!("Found {} instances of relation '{}'", found, relation.name);
+            }
+            self.task_timer.stop();
+        }
+        res
+    }
+
+    fn attach_instance(
+        &mut self,
+        relation_match: &RelationMatch,
+        arrival: Option<usize>,
+    ) -> RelationInstance {
+        let from_span = &self.span_groups[relation_match.from.0][relation_match.from.1];
+        let to_span = &self.span_groups[relation_match.to.0][relation_match.to.1];
+        let instance = RelationInstance {
+            from_span: Rc::<Span>::downgrade(from_span),
+            to_span: Rc::<Span>::downgrade(to_span),
+            relation: self.relations[relation_match.relation_index].clone(),
+            arrival,
+        };
+
+        from_span
+            .outgoing_relations
+            .borrow_mut()
+            .push(instance.clone());
+        to_span
+            .incoming_relations
+            .borrow_mut()
+            .push(instance.clone());
+        self.found_per_relation[relation_match.relation_index] += 1;
+        // Fan-in matches get here only after the fan-in is resolved, so spans whose matches were
+        // dropped by it are still reported as unmatched.
+        if instance.relation.report_unmatched {
+            self.matched_from
+                .insert((relation_match.relation_index, relation_match.from));
+            self.matched_to
+                .insert((relation_match.relation_index, relation_match.to));
+        }
+        instance
+    }
+
+    /// Spans of relations with [Relation::report_unmatched] which weren't matched, sorted by start
+    /// time. Available when the search is finished, unless it was cancelled.
+    pub fn unmatched(&self) -> &[UnmatchedSpan] {
+        &self.unmatched
+    }
+
+    fn find_unmatched(&self) -> Vec<UnmatchedSpan> {
+        let mut res = Vec::new();
+        for (relation_index, relation) in self.relations.iter().enumerate() {
+            if !relation.report_unmatched {
+                continue;
+            }
+            let (from_groups, to_groups) = &self.relation_groups[relation_index];
+            let sides = [
+                (
+                    UnmatchedSide::From,
+                    from_groups,
+                    &relation.from_span_selector,
+                    &self.matched_from,
+                ),
+                (
+                    UnmatchedSide::To,
+                    to_groups,
+                    &relation.to_span_selector,
+                    &self.matched_to,
+                ),
+            ];
+            for (side, groups, selector, matched) in sides {
+                for &group in groups {
+                    for (index, span) in self.span_groups[group].iter().enumerate() {
+                        if selector.matches(span)
+                            && !matched.contains(&(relation_index, (group, index)))
+                        {
+                            res.push(UnmatchedSpan {
+                                span: span.clone(),
+                                relation: relation.clone(),
+                                side,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        res.sort_by(|a, b| a.span.start_time.total_cmp(&b.span.start_time));
+        res
+    }
+
+    /// Attaches fan-in relations whose "to" spans are matched by enough "from" spans, from the
+    /// earliest to arrive.
+    fn resolve_fan_in(&mut self) -> Vec<RelationInstance> {
+        let mut sources_per_target: BTreeMap<(usize, (usize, usize)), Vec<RelationMatch>> =
+            BTreeMap::new();
+        for relation_match in std::mem::take(&mut self.fan_in_matches) {
+            sources_per_target
+                .entry((relation_match.relation_index, relation_match.to))
+                .or_default()
+                .push(relation_match);
+        }
+
+        let mut res = Vec::new();
+        for ((relation_index, _), mut sources) in sources_per_target {
+            let required = self.relations[relation_index].required_sources;
+            if sources.len() < required {
+                continue;
+            }
+            sources.sort_by(|a, b| {
+                let a_span = &self.span_groups[a.from.0][a.from.1];
+                let b_span = &self.span_groups[b.from.0][b.from.1];
+                a_span
+                    .end_time
+                    .total_cmp(&b_span.end_time)
+                    .then(a.from.cmp(&b.from))
+            });
+            for (i, source) in sources.iter().take(required).enumerate() {
+                res.push(self.attach_instance(source, Some(i + 1)));
+            }
+        }
+        res
+    }
+}
+
+/// Finds instances of all relations enabled in `view`, blocking until all of them are found.
 pub fn find_relations(
     all_relations: &[Relation],
     view: &RelationView,
@@ -151,135 +598,155 @@ pub fn find_relations(
     #[cfg(feature = "profiling")]
     let _timing_guard = crate::profiling::GLOBAL_PROFILER.start_timing("find_relations");
 
-    let task_timer = TaskTimer::new("Finding relations");
-
-    let mut res = Vec::new();
+    let res = RelationSearch::start(all_relations, view, spans).wait();
+    log::info!("Found {} relations", res.len());
+    res
+}
 
-    // Spans grouped by name, sorted by start time.
-    let mut spans_by_name: HashMap<String, Vec<Rc<Span>>> = HashMap::new();
-    for span in spans {
-        gather_spans_by_name(span, &mut spans_by_name);
+/// Relation from a span to its child on another node, drawn when spans with a remote parent are
+/// displayed as [crate::modes::RemoteParentLinking::Relation]. It isn't a part of any relation
+/// view, its instances come from the parent span ids.
+pub fn remote_parent_relation() -> Relation {
+    let any_span = SpanSelector {
+        span_name_condition: MatchCondition::any(),
+        node_name_condition: MatchCondition::any(),
+        attribute_conditions: vec![],
+    };
+    Relation {
+        id: make_uuid_from_seed("remote parent relation"),
+        name: "Remote parent".to_string(),
+        description: "The child span was recorded on another node than its parent".to_string(),
+        from_span_selector: any_span.clone(),
+        to_span_selector: any_span,
+        attribute_relations: vec![],
+        max_time_diff: None,
+        nodes_config: RelationNodesConfig::DifferentNode,
+        match_type: MatchType::MatchAll,
+        // The child usually starts while the parent is still running.
+        min_time_diff: f64::MIN,
+        required_sources: 1,
+        report_unmatched: false,
+        arrow_style: ArrowStyle {
+            dashed: true,
+            ..Default::default()
+        },
+        is_builtin: true,
     }
-    for (_name, spans) in spans_by_name.iter_mut() {
-        // Sort spans by start time
-        spans.sort_by(|a, b| {
-            a.start_time
-                .partial_cmp(&b.start_time)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-
-        // Clear outgoing and incoming relations for each span
-        for span in spans {
-            span.outgoing_relations.borrow_mut().clear();
-            span.incoming_relations.borrow_mut().clear();
+}
+
+/// Connects the spans which have a remote parent with the parent, if both of them are displayed.
+/// Should be called after the relation search is started, the search clears the relations of all
+/// spans.
+pub fn link_remote_parents(spans: &[Rc<Span>]) -> Vec<RelationInstance> {
+    fn collect(
+        span: &Rc<Span>,
+        spans_by_id: &mut HashMap<SpanId, Rc<Span>>,
+        span_ids: &mut Vec<SpanId>,
+    ) {
+        if spans_by_id.insert(span.span_id, span.clone()).is_none() {
+            span_ids.push(span.span_id);
+        }
+        for child in span.children().iter() {
+            collect(child, spans_by_id, span_ids);
         }
     }
+    let mut spans_by_id = HashMap::new();
+    let mut span_ids = Vec::new();
+    for span in spans {
+        collect(span, &mut spans_by_id, &mut span_ids);
+    }
 
-    for enabled_relation_id in &view.enabled_relations {
-        let Some(relation) = all_relations.iter().find(|r| &r.id == enabled_relation_id) else {
+    let relation = Rc::new(remote_parent_relation());
+    let mut res = Vec::new();
+    let spans_in_order = span_ids.iter().map(|span_id| &spans_by_id[span_id]);
+    for span in spans_in_order.filter(|span| span.remote_parent) {
+        let Some(parent) = spans_by_id.get(&span.parent_span_id) else {
             continue;
         };
-        let relation = Rc::new(relation.clone());
-
-        let matching_from_span_names = spans_by_name
-            .keys()
-            .filter(|name| {
-                relation
-                    .from_span_selector
-                    .span_name_condition
-                    .matches(name)
-            })
-            .collect::<Vec<_>>();
-        let matching_to_span_names = spans_by_name
-            .keys()
-            .filter(|name| relation.to_span_selector.span_name_condition.matches(name))
-            .collect::<Vec<_>>();
-
-        let mut found_relation_instances = 0;
-        for from_span_name in &matching_from_span_names {
-            let Some(from_spans) = spans_by_name.get(from_span_name.as_str()) else {
+        let instance = RelationInstance {
+            from_span: Rc::downgrade(parent),
+            to_span: Rc::downgrade(span),
+            relation: relation.clone(),
+            arrival: None,
+        };
+        parent
+            .outgoing_relations
+            .borrow_mut()
+            .push(instance.clone());
+        span.incoming_relations.borrow_mut().push(instance.clone());
+        res.push(instance);
+    }
+    res
+}
+
+fn find_relation_matches(
+    relations: &[Relation],
+    data_groups: &[Vec<Arc<SpanData>>],
+    item: &RelationSearchItem,
+) -> Vec<RelationMatch> {
+    let relation = &relations[item.relation_index];
+    let from_spans = &data_groups[item.from_group];
+    let to_spans = &data_groups[item.to_group];
+
+    let mut res = Vec::new();
+    for from_index in item.from_range.clone() {
+        let from_span = &from_spans[from_index];
+        let first_to_span_index =
+            find_first_span_after(to_spans, from_span.end_time + relation.min_time_diff);
+        for (to_index, to_span) in to_spans.iter().enumerate().skip(first_to_span_index) {
+            if let Some(max_time_diff) = relation.max_time_diff {
+                if to_span.start_time - from_span.start_time > max_time_diff {
+                    break;
+                }
+            }
+
+            if !relation.matches(from_span, to_span) {
                 continue;
-            };
-            for to_span_name in &matching_to_span_names {
-                let Some(to_spans) = spans_by_name.get(to_span_name.as_str()) else {
-                    continue;
-                };
-
-                for from_span in from_spans {
-                    let first_to_span_index = find_first_span_after(
-                        to_spans,
-                        from_span.end_time + relation.min_time_diff,
-                    );
-                    for to_span in &to_spans[first_to_span_index..] {
-                        if let Some(max_time_diff) = relation.max_time_diff {
-                            if to_span.start_time - from_span.start_time > max_time_diff {
-                                break;
-                            }
-                        }
+            }
 
-                        if !relation.matches(from_span, to_span) {
-                            continue;
-                        }
+            res.push(RelationMatch {
+                relation_index: item.relation_index,
+                from: (item.from_group, from_index),
+                to: (item.to_group, to_index),
+            });
 
-                        let instance = RelationInstance {
-                            from_span: Rc::<Span>::downgrade(from_span),
-                            to_span: Rc::<Span>::downgrade(to_span),
-                            relation: relation.clone(),
-                        };
-
-                        from_span
-                            .outgoing_relations
-                            .borrow_mut()
-                            .push(instance.clone());
-                        to_span
-                            .incoming_relations
-                            .borrow_mut()
-                            .push(instance.clone());
-                        res.push(instance);
-                        found_relation_instances += 1;
-
-                        match relation.match_type {
-                            MatchType::MatchAll => {
-                                // For MatchAll, we continue to find more matches
-                                continue;
-                            }
-                            MatchType::MatchClosest => {
-                                // For MatchClosest, we break after the first match
-                                break;
-                            }
-                        }
-                    }
+            match relation.match_type {
+                MatchType::MatchAll => {
+                    // For MatchAll, we continue to find more matches
+                    continue;
+                }
+                MatchType::MatchClosest => {
+                    // For MatchClosest, we break after the first match
+                    break;
                 }
             }
         }
-        println!(
-            "Found {} instances of relation '{}'",
-            found_relation_instances, relation.name
-        );
     }
-
-    task_timer.stop();
-    println!("Found {} relations", res.len());
-
     res
 }
 
-fn gather_spans_by_name(span: &Rc<Span>, spans_by_name: &mut HashMap<String, Vec<Rc<Span>>>) {
+fn gather_spans_by_name(
+    span: &Rc<Span>,
+    transform_lazy_children: bool,
+    spans_by_name: &mut HashMap<String, Vec<Rc<Span>>>,
+) {
     spans_by_name
         .entry(span.original_name().to_string())
         .or_default()
         .push(span.clone());
-    for child in span.children.borrow().iter() {
-        gather_spans_by_name(child, spans_by_name);
+    let children = if transform_lazy_children {
+        span.children()
+    } else {
+        span.transformed_children()
+    };
+    for child in children.iter() {
+        gather_spans_by_name(child, transform_lazy_children, spans_by_name);
     }
 }
 
-fn find_first_span_after(spans: &[Rc<Span>], start_time: f64) -> usize {
-    // TODO - this could be optimized with binary search
-    spans
-        .iter()
-        .position(|span| span.start_time >= start_time)
-        .unwrap_or(spans.len())
+/// `spans` must be sorted by start time.
+fn find_first_span_after(spans: &[Arc<SpanData>], start_time: f64) -> usize {
+    spans.partition_point(|span| span.start_time < start_time)
 }
 
 pub fn builtin_relation_views() -> Vec<RelationView> {
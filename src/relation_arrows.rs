@@ -0,0 +1,205 @@
+//! Decluttering of relation arrows. A dense relation view draws thousands of arrows on top of each
+//! other, so before drawing, arrows with a short delay can be hidden, arrows between the same pair
+//! of nodes which start and end close to each other can be bundled into one, and the number of
+//! drawn arrows is capped.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use eframe::egui::{DragValue, Pos2, Ui, Vec2};
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ArrowDeclutterSettings {
+    /// Don't draw arrows with a delay below `min_delay_ms`.
+    pub hide_short_delays: bool,
+    pub min_delay_ms: f64,
+    /// Draw arrows between the same pair of nodes, whose ends are in the same pixel bucket, as one.
+    pub bundle: bool,
+    pub bundle_bucket_px: f32,
+    /// At most this many arrows (or bundles) are drawn, the ones with the longest delay.
+    pub max_arrows: usize,
+    /// Highlight arrows of relations with a `max_time_diff`, which are within
+    /// `near_miss_percent`% of the limit.
+    pub highlight_near_misses: bool,
+    pub near_miss_percent: f64,
+}
+
+impl Default for ArrowDeclutterSettings {
+    fn default() -> Self {
+        ArrowDeclutterSettings {
+            hide_short_delays: false,
+            min_delay_ms: 1.0,
+            bundle: false,
+            bundle_bucket_px: 20.0,
+            max_arrows: 2000,
+            highlight_near_misses: true,
+            near_miss_percent: 10.0,
+        }
+    }
+}
+
+/// An arrow which could be drawn. Arrows are bundled only when they have the same `key`, e.g. the
+/// relation and the names of the nodes.
+#[derive(Debug, Clone)]
+pub struct ArrowCandidate<K> {
+    pub key: K,
+    pub from: Pos2,
+    pub to: Pos2,
+    pub delay_ms: f64,
+}
+
+/// One or more arrows drawn as one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrowBundle {
+    /// Indexes of the bundled candidates.
+    pub members: Vec<usize>,
+    /// Average positions of the bundled arrows.
+    pub from: Pos2,
+    pub to: Pos2,
+    pub mean_delay_ms: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArrowPlan {
+    pub bundles: Vec<ArrowBundle>,
+    /// Arrows hidden because of their short delay.
+    pub hidden_short: usize,
+    /// Bundles which weren't drawn because of `max_arrows`.
+    pub truncated: usize,
+}
+
+/// Decides which arrows are drawn, see [ArrowDeclutterSettings].
+pub fn plan_arrows<K: Hash + Eq + Clone>(
+    candidates: &[ArrowCandidate<K>],
+    settings: &ArrowDeclutterSettings,
+) -> ArrowPlan {
+    let mut plan = ArrowPlan::default();
+    let mut bundle_index: HashMap<(K, i64, i64, i64, i64), usize> = HashMap::new();
+    let bucket = settings.bundle_bucket_px.max(1.0);
+    let to_bucket = |value: f32| (value / bucket).floor() as i64;
+
+    for (i, candidate) in candidates.iter().enumerate() {
+        if settings.hide_short_delays && candidate.delay_ms < settings.min_delay_ms {
+            plan.hidden_short += 1;
+            continue;
+        }
+        if settings.bundle {
+            let bucket_key = (
+                candidate.key.clone(),
+                to_bucket(candidate.from.x),
+                to_bucket(candidate.from.y),
+                to_bucket(candidate.to.x),
+                to_bucket(candidate.to.y),
+            );
+            if let Some(&index) = bundle_index.get(&bucket_key) {
+                plan.bundles[index].members.push(i);
+                continue;
+            }
+            bundle_index.insert(bucket_key, plan.bundles.len());
+        }
+        plan.bundles.push(ArrowBundle {
+            members: vec![i],
+            from: candidate.from,
+            to: candidate.to,
+            mean_delay_ms: candidate.delay_ms,
+        });
+    }
+
+    for bundle in &mut plan.bundles {
+        if bundle.members.len() < 2 {
+            continue;
+        }
+        let mut from_sum = Vec2::ZERO;
+        let mut to_sum = Vec2::ZERO;
+        let mut delay_sum = 0.0;
+        for candidate in bundle.members.iter().map(|i| &candidates[*i]) {
+            from_sum += candidate.from.to_vec2();
+            to_sum += candidate.to.to_vec2();
+            delay_sum += candidate.delay_ms;
+        }
+        let count = bundle.members.len();
+        bundle.from = (from_sum / count as f32).to_pos2();
+        bundle.to = (to_sum / count as f32).to_pos2();
+        bundle.mean_delay_ms = delay_sum / count as f64;
+    }
+
+    if plan.bundles.len() > settings.max_arrows {
+        plan.bundles
+            .sort_by(|a, b| b.mean_delay_ms.total_cmp(&a.mean_delay_ms));
+        plan.truncated = plan.bundles.len() - settings.max_arrows;
+        plan.bundles.truncate(settings.max_arrows);
+    }
+    plan
+}
+
+/// Controls of the settings, shown in the "Arrows" menu next to the relation view chooser.
+/// Returns true when the settings were changed.
+pub fn draw_arrow_declutter_settings(ui: &mut Ui, settings: &mut ArrowDeclutterSettings) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        changed |= ui
+            .checkbox(
+                &mut settings.hide_short_delays,
+                "Hide arrows with delay below",
+            )
+            .changed();
+        changed |= ui
+            .add_enabled(
+                settings.hide_short_delays,
+                DragValue::new(&mut settings.min_delay_ms)
+                    .speed(0.1)
+                    .suffix(" ms"),
+            )
+            .changed();
+    });
+    ui.horizontal(|ui| {
+        changed |= ui
+            .checkbox(
+                &mut settings.bundle,
+                "Bundle arrows between the same nodes within",
+            )
+            .on_hover_text(
+                "Arrows of the same relation between the same pair of nodes, whose ends are \
+                 close to each other, are drawn as one thick arrow with a count",
+            )
+            .changed();
+        changed |= ui
+            .add_enabled(
+                settings.bundle,
+                DragValue::new(&mut settings.bundle_bucket_px)
+                    .range(1.0..=500.0)
+                    .suffix(" px"),
+            )
+            .changed();
+    });
+    ui.horizontal(|ui| {
+        ui.label("Draw at most");
+        changed |= ui
+            .add(DragValue::new(&mut settings.max_arrows).range(1..=100_000))
+            .changed();
+        ui.label("arrows");
+    });
+    ui.horizontal(|ui| {
+        changed |= ui
+            .checkbox(
+                &mut settings.highlight_near_misses,
+                "Highlight arrows within",
+            )
+            .on_hover_text(
+                "Arrows of relations with a max time diff, which almost weren't matched because \
+                 they are close to the limit",
+            )
+            .changed();
+        changed |= ui
+            .add_enabled(
+                settings.highlight_near_misses,
+                DragValue::new(&mut settings.near_miss_percent)
+                    .range(0.0..=100.0)
+                    .suffix(" %"),
+            )
+            .changed();
+        ui.label("of the time limit");
+    });
+    changed
+}
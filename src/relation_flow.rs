@@ -0,0 +1,372 @@
+//! Aggregate flow diagram of the relation instances of the active relation view. Instances are
+//! grouped by the span name and node on both ends, and drawn as a Sankey diagram - sources on the
+//! left, targets on the right, connected by bands as thick as the number of instances (or the mean
+//! delay). It summarizes on one screen how data flows through the network.
+
+use std::collections::BTreeMap;
+
+use eframe::egui::epaint::CubicBezierShape;
+use eframe::egui::{
+    self, Align2, FontId, Grid, Modal, Pos2, Rect, ScrollArea, Sense, Stroke, Vec2,
+};
+
+use crate::colors::node_color;
+use crate::relation::RelationInstance;
+use crate::types::MILLISECONDS_PER_SECOND;
+
+/// One end of a flow, a span name on a node.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FlowEndpoint {
+    pub span_name: String,
+    pub node_name: String,
+}
+
+impl FlowEndpoint {
+    pub fn label(&self) -> String {
+        format!("{} @ {}", self.span_name, self.node_name)
+    }
+}
+
+/// Relation instances between the same pair of endpoints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flow {
+    pub from: FlowEndpoint,
+    pub to: FlowEndpoint,
+    pub count: usize,
+    /// Mean time between the end of the "from" span and the start of the "to" span, in ms.
+    pub mean_delay_ms: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowWeight {
+    Count,
+    MeanDelay,
+}
+
+impl Flow {
+    pub fn weight(&self, weight: FlowWeight) -> f64 {
+        match weight {
+            FlowWeight::Count => self.count as f64,
+            FlowWeight::MeanDelay => self.mean_delay_ms.max(0.0),
+        }
+    }
+}
+
+/// Groups the instances by (from name, from node) -> (to name, to node), sorted by the endpoints.
+pub fn aggregate_flows(instances: &[RelationInstance]) -> Vec<Flow> {
+    let mut sums: BTreeMap<(FlowEndpoint, FlowEndpoint), (usize, f64)> = BTreeMap::new();
+    for instance in instances {
+        let (Some(from_span), Some(to_span)) =
+            (instance.from_span.upgrade(), instance.to_span.upgrade())
+        else {
+            continue;
+        };
+        let key = (
+            FlowEndpoint {
+                span_name: from_span.original_name().to_string(),
+                node_name: from_span.node.name.clone(),
+            },
+            FlowEndpoint {
+                span_name: to_span.original_name().to_string(),
+                node_name: to_span.node.name.clone(),
+            },
+        );
+        let sum = sums.entry(key).or_default();
+        sum.0 += 1;
+        sum.1 += (to_span.start_time - from_span.end_time) * MILLISECONDS_PER_SECOND;
+    }
+    sums.into_iter()
+        .map(|((from, to), (count, delay_sum))| Flow {
+            from,
+            to,
+            count,
+            mean_delay_ms: delay_sum / count as f64,
+        })
+        .collect()
+}
+
+/// A column entry of the diagram, `y` is relative to the top of the diagram.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SankeyNode {
+    pub endpoint: FlowEndpoint,
+    pub y_start: f32,
+    pub y_end: f32,
+}
+
+/// A flow drawn between two column entries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SankeyBand {
+    pub flow_index: usize,
+    /// Vertical range of the band at the source entry.
+    pub from_y: (f32, f32),
+    /// Vertical range of the band at the target entry.
+    pub to_y: (f32, f32),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SankeyLayout {
+    pub sources: Vec<SankeyNode>,
+    pub targets: Vec<SankeyNode>,
+    pub bands: Vec<SankeyBand>,
+}
+
+/// Places the endpoints in two columns of `height` pixels, each entry as tall as the total weight of
+/// its flows, with `gap` pixels between the entries. Bands are stacked inside the entries in the
+/// order of the other end, so that they cross as little as possible.
+pub fn sankey_layout(flows: &[Flow], weight: FlowWeight, height: f32, gap: f32) -> SankeyLayout {
+    let mut source_weights: BTreeMap<&FlowEndpoint, f64> = BTreeMap::new();
+    let mut target_weights: BTreeMap<&FlowEndpoint, f64> = BTreeMap::new();
+    for flow in flows {
+        *source_weights.entry(&flow.from).or_default() += flow.weight(weight);
+        *target_weights.entry(&flow.to).or_default() += flow.weight(weight);
+    }
+    let total: f64 = source_weights.values().sum();
+    if total <= 0.0 {
+        return SankeyLayout::default();
+    }
+    let column_entries = source_weights.len().max(target_weights.len());
+    let available = (height - gap * column_entries.saturating_sub(1) as f32).max(1.0);
+    let scale = available as f64 / total;
+
+    let place = |weights: &BTreeMap<&FlowEndpoint, f64>| -> Vec<SankeyNode> {
+        let mut y = 0.0;
+        weights
+            .iter()
+            .map(|(endpoint, w)| {
+                let node = SankeyNode {
+                    endpoint: (*endpoint).clone(),
+                    y_start: y,
+                    y_end: y + (*w * scale) as f32,
+                };
+                y = node.y_end + gap;
+                node
+            })
+            .collect()
+    };
+    let sources = place(&source_weights);
+    let targets = place(&target_weights);
+    let index_of = |nodes: &[SankeyNode], endpoint: &FlowEndpoint| {
+        nodes.iter().position(|n| n.endpoint == *endpoint).unwrap()
+    };
+
+    let mut flow_order: Vec<usize> = (0..flows.len()).collect();
+    let mut source_offsets: Vec<f32> = sources.iter().map(|n| n.y_start).collect();
+    flow_order.sort_by_key(|&i| {
+        (
+            index_of(&sources, &flows[i].from),
+            index_of(&targets, &flows[i].to),
+        )
+    });
+    let mut from_ranges = vec![(0.0, 0.0); flows.len()];
+    for &i in &flow_order {
+        let source = index_of(&sources, &flows[i].from);
+        let band_height = (flows[i].weight(weight) * scale) as f32;
+        from_ranges[i] = (source_offsets[source], source_offsets[source] + band_height);
+        source_offsets[source] += band_height;
+    }
+    let mut target_offsets: Vec<f32> = targets.iter().map(|n| n.y_start).collect();
+    flow_order.sort_by_key(|&i| {
+        (
+            index_of(&targets, &flows[i].to),
+            index_of(&sources, &flows[i].from),
+        )
+    });
+    let mut bands = Vec::new();
+    for &i in &flow_order {
+        let target = index_of(&targets, &flows[i].to);
+        let band_height = (flows[i].weight(weight) * scale) as f32;
+        bands.push(SankeyBand {
+            flow_index: i,
+            from_y: from_ranges[i],
+            to_y: (target_offsets[target], target_offsets[target] + band_height),
+        });
+        target_offsets[target] += band_height;
+    }
+    bands.sort_by_key(|b| b.flow_index);
+
+    SankeyLayout {
+        sources,
+        targets,
+        bands,
+    }
+}
+
+const COLUMN_WIDTH: f32 = 12.0;
+const ENTRY_GAP: f32 = 8.0;
+
+pub struct RelationFlowModal {
+    pub show: bool,
+    view_name: String,
+    flows: Vec<Flow>,
+    weight: FlowWeight,
+}
+
+impl Default for RelationFlowModal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RelationFlowModal {
+    pub fn new() -> RelationFlowModal {
+        RelationFlowModal {
+            show: false,
+            view_name: String::new(),
+            flows: Vec::new(),
+            weight: FlowWeight::Count,
+        }
+    }
+
+    /// Aggregates the relation instances found for the relation view.
+    pub fn open(&mut self, view_name: &str, instances: &[RelationInstance]) {
+        self.view_name = view_name.to_string();
+        self.flows = aggregate_flows(instances);
+        self.show = true;
+    }
+
+    pub fn show_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        if !self.show {
+            return;
+        }
+
+        Modal::new("relation flow".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading(format!("Relation flow: {}", self.view_name));
+            ui.label(
+                "Relation instances of the view grouped by the span name and node on both ends.",
+            );
+            ui.horizontal(|ui| {
+                ui.label("Band width:");
+                ui.radio_value(&mut self.weight, FlowWeight::Count, "Count");
+                ui.radio_value(&mut self.weight, FlowWeight::MeanDelay, "Mean delay");
+            });
+            ui.separator();
+
+            if self.flows.is_empty() {
+                ui.label("No relations were found in the view.");
+            } else {
+                self.draw_diagram(ui, max_width - 20.0, (max_height - 350.0).max(200.0));
+                ui.separator();
+                self.draw_flow_table(ui);
+            }
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.show = false;
+            }
+        });
+    }
+
+    fn draw_diagram(&self, ui: &mut egui::Ui, width: f32, height: f32) {
+        let (rect, response) = ui.allocate_exact_size(Vec2::new(width, height), Sense::hover());
+        let layout = sankey_layout(&self.flows, self.weight, height, ENTRY_GAP);
+        let painter = ui.painter_at(rect);
+        // Leave room for the labels on both sides.
+        let left_x = rect.left() + width * 0.3;
+        let right_x = rect.right() - width * 0.3;
+        let font = FontId::proportional(12.0);
+
+        let hover_pos = response.hover_pos();
+        let mut hovered_flow = None;
+        for band in &layout.bands {
+            let from_mid = rect.top() + (band.from_y.0 + band.from_y.1) / 2.0;
+            let to_mid = rect.top() + (band.to_y.0 + band.to_y.1) / 2.0;
+            let band_width = (band.from_y.1 - band.from_y.0).max(1.0);
+            let start = Pos2::new(left_x + COLUMN_WIDTH, from_mid);
+            let end = Pos2::new(right_x, to_mid);
+            let control_dx = (end.x - start.x) / 2.0;
+            let bezier = CubicBezierShape::from_points_stroke(
+                [
+                    start,
+                    Pos2::new(start.x + control_dx, start.y),
+                    Pos2::new(end.x - control_dx, end.y),
+                    end,
+                ],
+                false,
+                egui::Color32::TRANSPARENT,
+                Stroke::NONE,
+            );
+            let is_hovered = hover_pos.is_some_and(|pos| {
+                (0..=20).any(|i| bezier.sample(i as f32 / 20.0).distance(pos) <= band_width / 2.0)
+            });
+            if is_hovered {
+                hovered_flow = Some(band.flow_index);
+            }
+            let flow = &self.flows[band.flow_index];
+            let color = node_color(&flow.from.node_name).gamma_multiply(if is_hovered {
+                0.9
+            } else {
+                0.45
+            });
+            painter.add(CubicBezierShape {
+                stroke: Stroke::new(band_width, color).into(),
+                ..bezier
+            });
+        }
+
+        for (nodes, x, align, label_x) in [
+            (&layout.sources, left_x, Align2::RIGHT_CENTER, left_x - 4.0),
+            (
+                &layout.targets,
+                right_x,
+                Align2::LEFT_CENTER,
+                right_x + COLUMN_WIDTH + 4.0,
+            ),
+        ] {
+            for node in nodes {
+                let node_rect = Rect::from_min_max(
+                    Pos2::new(x, rect.top() + node.y_start),
+                    Pos2::new(
+                        x + COLUMN_WIDTH,
+                        rect.top() + node.y_end.max(node.y_start + 1.0),
+                    ),
+                );
+                painter.rect_filled(node_rect, 0.0, node_color(&node.endpoint.node_name));
+                painter.text(
+                    Pos2::new(label_x, node_rect.center().y),
+                    align,
+                    node.endpoint.label(),
+                    font.clone(),
+                    ui.visuals().text_color(),
+                );
+            }
+        }
+
+        if let Some(flow) = hovered_flow.map(|i| &self.flows[i]) {
+            response.on_hover_text(format!(
+                "{} -> {}\n{} instances, mean delay {:.3} ms",
+                flow.from.label(),
+                flow.to.label(),
+                flow.count,
+                flow.mean_delay_ms
+            ));
+        }
+    }
+
+    fn draw_flow_table(&self, ui: &mut egui::Ui) {
+        ScrollArea::vertical()
+            .id_salt("relation flow table")
+            .max_height(200.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                Grid::new("relation flow grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("From");
+                        ui.strong("To");
+                        ui.strong("Count");
+                        ui.strong("Mean delay");
+                        ui.end_row();
+                        for flow in &self.flows {
+                            ui.label(flow.from.label());
+                            ui.label(flow.to.label());
+                            ui.label(flow.count.to_string());
+                            ui.label(format!("{:.3} ms", flow.mean_delay_ms));
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
@@ -0,0 +1,554 @@
+//! Opening trace files which live in object storage or on a web server. `https://`, `http://` and
+//! `s3://` URLs are accepted wherever a file path is (the command line, view links, recent files).
+//! The file is downloaded in the background into a cache folder in the temp dir and then opened
+//! like a local file. When the same URL is opened again, the cached copy is revalidated with its
+//! `ETag` and `Last-Modified` and only downloaded again when it changed.
+//!
+//! `s3://bucket/key` URLs are fetched with the S3 REST API, without awscli. The configuration is
+//! read from the standard environment variables:
+//! * `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and optionally `AWS_SESSION_TOKEN` - requests are
+//!   signed with them (Signature Version 4). Without them the object has to be public.
+//! * `AWS_REGION` (or `AWS_DEFAULT_REGION`) - `us-east-1` when not set.
+//! * `AWS_ENDPOINT_URL_S3` (or `AWS_ENDPOINT_URL`) - S3 compatible storage like MinIO, objects are
+//!   addressed as `<endpoint>/<bucket>/<key>`.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use eframe::egui::{self, Modal, TextEdit};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::colors::theme;
+use crate::view_link::percent_encode;
+
+const DEFAULT_S3_REGION: &str = "us-east-1";
+/// SHA-256 of an empty body, GET requests don't have a body.
+const EMPTY_PAYLOAD_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Whether the path is a URL which has to be downloaded, rather than a local file.
+pub fn is_remote_url(path: &str) -> bool {
+    ["https://", "http://", "s3://"]
+        .iter()
+        .any(|scheme| path.starts_with(scheme))
+}
+
+/// Same as [is_remote_url], for paths which came from a file dialog or the recent files.
+pub fn is_remote_path(path: &Path) -> bool {
+    path.to_str().is_some_and(is_remote_url)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3Location {
+    pub bucket: String,
+    pub key: String,
+}
+
+/// Splits `s3://bucket/path/to/key` into the bucket and the key.
+pub fn parse_s3_url(url: &str) -> Result<S3Location> {
+    let Some(rest) = url.strip_prefix("s3://") else {
+        bail!("an S3 URL has to start with s3://");
+    };
+    let Some((bucket, key)) = rest.split_once('/') else {
+        bail!("the S3 URL doesn't have a key, expected s3://<bucket>/<key>");
+    };
+    if bucket.is_empty() || key.is_empty() {
+        bail!("expected s3://<bucket>/<key>");
+    }
+    Ok(S3Location {
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3Config {
+    pub region: String,
+    /// Endpoint of S3 compatible storage, `None` for AWS.
+    pub endpoint: Option<String>,
+    /// `None` for anonymous access to public objects.
+    pub credentials: Option<AwsCredentials>,
+}
+
+impl S3Config {
+    /// Reads the configuration from the AWS environment variables.
+    pub fn from_env() -> S3Config {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+        };
+        let credentials = match (var(&["AWS_ACCESS_KEY_ID"]), var(&["AWS_SECRET_ACCESS_KEY"])) {
+            (Some(access_key_id), Some(secret_access_key)) => Some(AwsCredentials {
+                access_key_id,
+                secret_access_key,
+                session_token: var(&["AWS_SESSION_TOKEN"]),
+            }),
+            _ => None,
+        };
+        S3Config {
+            region: var(&["AWS_REGION", "AWS_DEFAULT_REGION"])
+                .unwrap_or_else(|| DEFAULT_S3_REGION.to_string()),
+            endpoint: var(&["AWS_ENDPOINT_URL_S3", "AWS_ENDPOINT_URL"]),
+            credentials,
+        }
+    }
+}
+
+/// A GET request, with the headers which have to be sent with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+}
+
+/// Turns the URL into the request which downloads it, `s3://` URLs are converted to the S3 REST
+/// API and signed when there are credentials. `now` is the signing time.
+pub fn resolve_request(
+    url: &str,
+    s3_config: &S3Config,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<HttpRequest> {
+    if !url.starts_with("s3://") {
+        if !is_remote_url(url) {
+            bail!("unsupported URL {url}, expected https://, http:// or s3://");
+        }
+        return Ok(HttpRequest {
+            url: url.to_string(),
+            headers: Vec::new(),
+        });
+    }
+
+    let location = parse_s3_url(url)?;
+    let key = percent_encode(&location.key);
+    let (scheme, host, path) = match &s3_config.endpoint {
+        Some(endpoint) => {
+            let endpoint = endpoint.trim_end_matches('/');
+            let (scheme, host) = endpoint
+                .split_once("://")
+                .with_context(|| format!("invalid S3 endpoint {endpoint}"))?;
+            (
+                scheme,
+                host.to_string(),
+                format!("/{}/{key}", location.bucket),
+            )
+        }
+        None => (
+            "https",
+            format!("{}.s3.{}.amazonaws.com", location.bucket, s3_config.region),
+            format!("/{key}"),
+        ),
+    };
+    let mut headers = Vec::new();
+    if let Some(credentials) = &s3_config.credentials {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let mut signed_headers = vec![
+            ("host".to_string(), host.clone()),
+            (
+                "x-amz-content-sha256".to_string(),
+                EMPTY_PAYLOAD_HASH.to_string(),
+            ),
+            ("x-amz-date".to_string(), amz_date.clone()),
+        ];
+        if let Some(token) = &credentials.session_token {
+            signed_headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        let authorization = sign_s3_get(
+            &path,
+            &signed_headers,
+            credentials,
+            &s3_config.region,
+            &amz_date,
+        );
+        // The Host header is added by the HTTP client.
+        headers.extend(
+            signed_headers
+                .into_iter()
+                .filter(|(name, _)| name != "host"),
+        );
+        headers.push(("authorization".to_string(), authorization));
+    }
+    Ok(HttpRequest {
+        url: format!("{scheme}://{host}{path}"),
+        headers,
+    })
+}
+
+/// The `Authorization` header of an S3 GET request without a query string, signed with Signature
+/// Version 4. `headers` are the signed headers with lowercase names, they must include `host`,
+/// `x-amz-date` and `x-amz-content-sha256`. `amz_date` is the value of `x-amz-date`.
+pub fn sign_s3_get(
+    canonical_uri: &str,
+    headers: &[(String, String)],
+    credentials: &AwsCredentials,
+    region: &str,
+    amz_date: &str,
+) -> String {
+    let mut headers: Vec<&(String, String)> = headers.iter().collect();
+    headers.sort();
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+        .collect();
+    let signed_headers: Vec<&str> = headers.iter().map(|(name, _)| name.as_str()).collect();
+    let signed_headers = signed_headers.join(";");
+    let canonical_request = format!(
+        "GET\n{canonical_uri}\n\n{canonical_headers}\n{signed_headers}\n{EMPTY_PAYLOAD_HASH}"
+    );
+
+    let date = &amz_date[..8.min(amz_date.len())];
+    let scope = format!("{date}/{region}/s3/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let mut key = hmac_sha256(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    for part in [region, "s3", "aws4_request"] {
+        key = hmac_sha256(&key, part.as_bytes());
+    }
+    let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope},SignedHeaders={signed_headers},Signature={signature}",
+        credentials.access_key_id
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// Folder in the temp dir where downloaded files are kept.
+pub fn download_cache_folder() -> PathBuf {
+    std::env::temp_dir().join("traviz-downloads")
+}
+
+/// Where the file downloaded from `url` is stored. The name starts with a hash of the URL, so that
+/// files with the same name from different places don't collide, and ends with the name from the
+/// URL, so that the extension (e.g. `.gz`) is kept.
+pub fn cached_file_path(cache_folder: &Path, url: &str) -> PathBuf {
+    let without_query = url.split(['?', '#']).next().unwrap_or(url);
+    let name: String = without_query
+        .rsplit('/')
+        .next()
+        .unwrap_or("")
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect();
+    let name = if name.is_empty() { "trace.json" } else { &name };
+    let hash = hex::encode(Sha256::digest(url.as_bytes()));
+    cache_folder.join(format!("{}-{name}", &hash[..16]))
+}
+
+/// `ETag` and `Last-Modified` of a cached download, they're sent with the next request for the URL
+/// and the server answers with 304 Not Modified when the file didn't change.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    fn from_response(response: &ureq::Response) -> CacheValidators {
+        CacheValidators {
+            etag: response.header("ETag").map(str::to_string),
+            last_modified: response.header("Last-Modified").map(str::to_string),
+        }
+    }
+
+    /// Conditional request headers, a server which doesn't know `If-None-Match` checks the date.
+    fn request_headers(&self) -> Vec<(&'static str, &str)> {
+        let mut headers = Vec::new();
+        if let Some(etag) = &self.etag {
+            headers.push(("If-None-Match", etag.as_str()));
+        }
+        if let Some(last_modified) = &self.last_modified {
+            headers.push(("If-Modified-Since", last_modified.as_str()));
+        }
+        headers
+    }
+}
+
+/// Where the validators of the cached download at `path` are kept.
+pub fn cache_validators_path(path: &Path) -> PathBuf {
+    let mut validators_path = path.as_os_str().to_os_string();
+    validators_path.push(".validators");
+    PathBuf::from(validators_path)
+}
+
+fn read_cache_validators(path: &Path) -> CacheValidators {
+    std::fs::read(cache_validators_path(path))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+struct DownloadProgress {
+    downloaded_bytes: AtomicU64,
+    /// 0 when the server didn't send the size.
+    total_bytes: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+/// A file which is being downloaded on a background thread.
+pub struct RemoteDownload {
+    url: String,
+    progress: Arc<DownloadProgress>,
+    receiver: mpsc::Receiver<Result<PathBuf>>,
+}
+
+impl RemoteDownload {
+    /// Starts downloading `url` into `cache_folder`. When `use_cache` is set and the file was
+    /// downloaded before, the cached copy is used if the server says it didn't change, or if the
+    /// server can't be reached.
+    pub fn start(url: &str, cache_folder: &Path, use_cache: bool) -> Result<RemoteDownload> {
+        let request = resolve_request(url, &S3Config::from_env(), chrono::Utc::now())?;
+        let path = cached_file_path(cache_folder, url);
+        let progress = Arc::new(DownloadProgress {
+            downloaded_bytes: AtomicU64::new(0),
+            total_bytes: AtomicU64::new(0),
+            cancelled: AtomicBool::new(false),
+        });
+        let (sender, receiver) = mpsc::channel();
+
+        let thread_progress = progress.clone();
+        let cache_folder = cache_folder.to_path_buf();
+        std::thread::spawn(move || {
+            let result = if use_cache && path.exists() {
+                let validators = read_cache_validators(&path);
+                match download(
+                    &request,
+                    &validators,
+                    &cache_folder,
+                    &path,
+                    &thread_progress,
+                ) {
+                    Ok(()) => Ok(path),
+                    Err(e) => {
+                        log::warn!(
+                            "Failed to check whether {} changed, using the cached download: {e:#}",
+                            request.url
+                        );
+                        Ok(path)
+                    }
+                }
+            } else {
+                let validators = CacheValidators::default();
+                download(
+                    &request,
+                    &validators,
+                    &cache_folder,
+                    &path,
+                    &thread_progress,
+                )
+                .map(|()| path)
+            };
+            // Nobody might be waiting for the result anymore.
+            let _ = sender.send(result);
+        });
+
+        Ok(RemoteDownload {
+            url: url.to_string(),
+            progress,
+            receiver,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Path of the downloaded file once the download is finished.
+    pub fn poll(&self) -> Option<Result<PathBuf>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Some(Err(anyhow!("the download thread stopped unexpectedly")))
+            }
+        }
+    }
+
+    /// Downloaded bytes and the size of the file, if it's known.
+    pub fn progress(&self) -> (u64, Option<u64>) {
+        let total = self.progress.total_bytes.load(Ordering::Relaxed);
+        (
+            self.progress.downloaded_bytes.load(Ordering::Relaxed),
+            (total > 0).then_some(total),
+        )
+    }
+
+    pub fn cancel(&self) {
+        self.progress.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for RemoteDownload {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Streams the response into a temporary file next to `path` and moves it to `path` when it's
+/// complete, so that a cancelled download doesn't leave a truncated file in the cache. The request
+/// is conditional when there are `validators`, the file at `path` is kept when it didn't change.
+fn download(
+    request: &HttpRequest,
+    validators: &CacheValidators,
+    cache_folder: &Path,
+    path: &Path,
+    progress: &DownloadProgress,
+) -> Result<()> {
+    // No limit on the whole download, big files take long, but a stalled connection fails.
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(DOWNLOAD_TIMEOUT)
+        .timeout_read(DOWNLOAD_TIMEOUT)
+        .build();
+    let mut http_request = agent.get(&request.url);
+    for (name, value) in &request.headers {
+        http_request = http_request.set(name, value);
+    }
+    for (name, value) in validators.request_headers() {
+        http_request = http_request.set(name, value);
+    }
+    let response = http_request.call().map_err(|e| match e {
+        ureq::Error::Status(status, response) => {
+            let body = response.into_string().unwrap_or_default();
+            anyhow!("the server returned {status}: {}", body.trim())
+        }
+        ureq::Error::Transport(transport) => anyhow!("{transport}"),
+    })?;
+    if response.status() == 304 {
+        log::info!("{} didn't change, using the cached download", request.url);
+        return Ok(());
+    }
+    let new_validators = CacheValidators::from_response(&response);
+    if let Some(size) = response
+        .header("Content-Length")
+        .and_then(|size| size.parse().ok())
+    {
+        progress.total_bytes.store(size, Ordering::Relaxed);
+    }
+
+    std::fs::create_dir_all(cache_folder)
+        .with_context(|| format!("failed to create {}", cache_folder.display()))?;
+    let mut part_path = path.as_os_str().to_os_string();
+    part_path.push(".part");
+    let part_path = PathBuf::from(part_path);
+    let mut file = std::fs::File::create(&part_path)
+        .with_context(|| format!("failed to create {}", part_path.display()))?;
+    let mut reader = response.into_reader();
+    let mut buffer = vec![0u8; 256 * 1024];
+    loop {
+        if progress.cancelled.load(Ordering::Relaxed) {
+            drop(file);
+            let _ = std::fs::remove_file(&part_path);
+            bail!("the download was cancelled");
+        }
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                drop(file);
+                let _ = std::fs::remove_file(&part_path);
+                return Err(e).context("the download failed");
+            }
+        };
+        file.write_all(&buffer[..read])?;
+        progress
+            .downloaded_bytes
+            .fetch_add(read as u64, Ordering::Relaxed);
+    }
+    file.flush()?;
+    drop(file);
+    std::fs::rename(&part_path, path)
+        .with_context(|| format!("failed to move the download to {}", path.display()))?;
+    let validators_path = cache_validators_path(path);
+    if new_validators == CacheValidators::default() {
+        let _ = std::fs::remove_file(&validators_path);
+    } else if let Err(e) = std::fs::write(&validators_path, serde_json::to_vec(&new_validators)?) {
+        log::warn!("Failed to save {}: {e}", validators_path.display());
+    }
+    Ok(())
+}
+
+/// Dialog asking for the URL of a trace file.
+#[derive(Default)]
+pub struct OpenUrl {
+    show: bool,
+    url: String,
+    error: Option<String>,
+}
+
+impl OpenUrl {
+    pub fn new() -> OpenUrl {
+        OpenUrl::default()
+    }
+
+    pub fn open(&mut self) {
+        self.error = None;
+        self.show = true;
+    }
+
+    /// Returns the URL when "Open" is clicked.
+    pub fn draw(&mut self, ctx: &egui::Context) -> Option<String> {
+        if !self.show {
+            return None;
+        }
+
+        let mut result = None;
+        Modal::new("open url".into()).show(ctx, |ui| {
+            ui.set_max_width(600.0);
+            ui.heading("Open URL");
+            let response = ui.add(
+                TextEdit::singleline(&mut self.url)
+                    .hint_text("https://... or s3://bucket/key")
+                    .desired_width(550.0),
+            );
+            ui.weak(
+                "s3:// URLs use the AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY, AWS_REGION and \
+                 AWS_ENDPOINT_URL environment variables.",
+            );
+            if let Some(error) = &self.error {
+                ui.colored_label(theme().error, error);
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("Open").clicked() || enter {
+                    let url = self.url.trim();
+                    if is_remote_url(url) {
+                        result = Some(url.to_string());
+                        self.show = false;
+                    } else {
+                        self.error = Some("expected an https://, http:// or s3:// URL".to_string());
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    self.show = false;
+                }
+            });
+        });
+        result
+    }
+}
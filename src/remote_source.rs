@@ -0,0 +1,439 @@
+//! Fetching traces straight from a tracing backend over HTTP, so that traces which are already
+//! stored in Tempo or Jaeger can be opened without exporting them to a file first.
+//!
+//! * Tempo - the ids of the traces of the service are found with `/api/search`, then every trace is
+//!   downloaded from `/api/traces/<id>` as protobuf. Tempo's `Trace` message has the same layout as
+//!   OTLP `ExportTraceServiceRequest`, so it's decoded directly into it.
+//! * Jaeger - `/api/traces` of `jaeger-query` returns the traces of the service in the same JSON
+//!   format as the "Download JSON" button, it's converted like a Jaeger file.
+//!
+//! The fetched traces are saved as an OTLP JSON file in the data folder and opened like any other
+//! file, so that they show up in the recent files and view links to them work.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use eframe::egui::{self, ComboBox, DragValue, Modal, TextEdit};
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use prost::Message;
+use serde_json::Value as JsonValue;
+
+use crate::background_task::BackgroundTask;
+use crate::colors::theme;
+use crate::external_events::parse_event_time;
+use crate::jaeger;
+use crate::types::TimePoint;
+use crate::view_link::percent_encode;
+
+/// Responses bigger than this are rejected, a trace that big wouldn't be usable anyway.
+const MAX_RESPONSE_BYTES: u64 = 1 << 30;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RemoteBackend {
+    Tempo,
+    Jaeger,
+}
+
+impl RemoteBackend {
+    pub fn all() -> [RemoteBackend; 2] {
+        [RemoteBackend::Tempo, RemoteBackend::Jaeger]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RemoteBackend::Tempo => "Tempo",
+            RemoteBackend::Jaeger => "Jaeger",
+        }
+    }
+
+    /// Address of the query API when the backend runs locally with the default ports.
+    pub fn default_url(&self) -> &'static str {
+        match self {
+            RemoteBackend::Tempo => "http://localhost:3200",
+            RemoteBackend::Jaeger => "http://localhost:16686",
+        }
+    }
+}
+
+/// Backend used the last time, saved in the preferences.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RemoteSourceSettings {
+    pub backend: RemoteBackend,
+    /// Base address of the query API, e.g. `http://tempo:3200`.
+    pub url: String,
+    pub service: String,
+    /// Maximum number of traces fetched at once.
+    pub limit: usize,
+}
+
+impl Default for RemoteSourceSettings {
+    fn default() -> Self {
+        RemoteSourceSettings {
+            backend: RemoteBackend::Tempo,
+            url: RemoteBackend::Tempo.default_url().to_string(),
+            service: String::new(),
+            limit: 20,
+        }
+    }
+}
+
+/// Traces of a service in a time range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteQuery {
+    pub settings: RemoteSourceSettings,
+    pub start: TimePoint,
+    pub end: TimePoint,
+}
+
+impl RemoteQuery {
+    fn base_url(&self) -> &str {
+        self.settings.url.trim().trim_end_matches('/')
+    }
+
+    /// Tempo: the search returning trace ids. Jaeger: the query returning the traces themselves.
+    pub fn search_url(&self) -> String {
+        let service = self.settings.service.trim();
+        match self.settings.backend {
+            // Tempo takes seconds, the tags are in logfmt.
+            RemoteBackend::Tempo => format!(
+                "{}/api/search?tags={}&start={}&end={}&limit={}",
+                self.base_url(),
+                percent_encode(&format!("service.name=\"{service}\"")),
+                self.start.floor() as i64,
+                self.end.ceil() as i64,
+                self.settings.limit
+            ),
+            // Jaeger takes microseconds.
+            RemoteBackend::Jaeger => format!(
+                "{}/api/traces?service={}&start={}&end={}&limit={}",
+                self.base_url(),
+                percent_encode(service),
+                (self.start * 1e6).floor() as i64,
+                (self.end * 1e6).ceil() as i64,
+                self.settings.limit
+            ),
+        }
+    }
+
+    /// Tempo only, the address of a single trace.
+    pub fn trace_url(&self, trace_id: &str) -> String {
+        format!(
+            "{}/api/traces/{}",
+            self.base_url(),
+            percent_encode(trace_id)
+        )
+    }
+
+    /// Name of the file with the fetched traces, e.g. `tempo-my_service-20250102-030405.json`.
+    pub fn file_name(&self) -> String {
+        let service: String = self
+            .settings
+            .service
+            .trim()
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+                _ => '_',
+            })
+            .collect();
+        let start = chrono::DateTime::from_timestamp(self.start as i64, 0)
+            .map_or_else(String::new, |t| t.format("%Y%m%d-%H%M%S").to_string());
+        format!(
+            "{}-{service}-{start}.json",
+            self.settings.backend.name().to_ascii_lowercase()
+        )
+    }
+}
+
+/// Ids of the traces in the response of Tempo's `/api/search`.
+pub fn parse_tempo_search(json: &str) -> Result<Vec<String>> {
+    let value: JsonValue = serde_json::from_str(json)?;
+    let traces = match value.get("traces") {
+        Some(JsonValue::Array(traces)) => traces.as_slice(),
+        // Tempo omits the field when nothing was found.
+        Some(JsonValue::Null) | None => &[],
+        Some(_) => bail!("'traces' isn't an array, expected the response of Tempo's search"),
+    };
+    traces
+        .iter()
+        .enumerate()
+        .map(|(index, trace)| {
+            trace
+                .get("traceID")
+                .and_then(JsonValue::as_str)
+                .map(str::to_string)
+                .with_context(|| format!("trace {index} of the search result doesn't have an id"))
+        })
+        .collect()
+}
+
+/// Decodes the protobuf returned by Tempo's `/api/traces/<id>`.
+pub fn decode_tempo_trace(bytes: &[u8]) -> Result<ExportTraceServiceRequest> {
+    ExportTraceServiceRequest::decode(bytes).context("failed to decode the trace protobuf")
+}
+
+/// Converts the response of Jaeger's `/api/traces`, reporting the errors returned by the query.
+pub fn parse_jaeger_response(json: &str) -> Result<Vec<ExportTraceServiceRequest>> {
+    let value: JsonValue = serde_json::from_str(json)?;
+    if let Some(JsonValue::Array(errors)) = value.get("errors") {
+        if !errors.is_empty() {
+            let messages: Vec<String> = errors
+                .iter()
+                .map(|error| match error.get("msg").and_then(JsonValue::as_str) {
+                    Some(msg) => msg.to_string(),
+                    None => error.to_string(),
+                })
+                .collect();
+            bail!("the query failed: {}", messages.join(", "));
+        }
+    }
+    if matches!(value.get("data"), Some(JsonValue::Null) | None) {
+        return Ok(Vec::new());
+    }
+    jaeger::parse_jaeger_json(json)
+}
+
+/// Downloads the traces, blocks until all of them are fetched.
+pub fn fetch_traces(query: &RemoteQuery) -> Result<Vec<ExportTraceServiceRequest>> {
+    if query.settings.service.trim().is_empty() {
+        bail!("the service name is empty");
+    }
+    if query.start >= query.end {
+        bail!("the start of the time range has to be before its end");
+    }
+    let traces = match query.settings.backend {
+        RemoteBackend::Tempo => {
+            let search = http_get(&query.search_url(), "application/json")?;
+            let trace_ids = parse_tempo_search(&String::from_utf8_lossy(&search))?;
+            trace_ids
+                .iter()
+                .map(|trace_id| {
+                    let bytes = http_get(&query.trace_url(trace_id), "application/protobuf")?;
+                    decode_tempo_trace(&bytes).with_context(|| format!("trace {trace_id}"))
+                })
+                .collect::<Result<Vec<_>>>()?
+        }
+        RemoteBackend::Jaeger => {
+            let response = http_get(&query.search_url(), "application/json")?;
+            parse_jaeger_response(&String::from_utf8_lossy(&response))?
+        }
+    };
+    if traces.iter().all(|trace| trace.resource_spans.is_empty()) {
+        bail!(
+            "no traces of '{}' in the time range",
+            query.settings.service.trim()
+        );
+    }
+    Ok(traces)
+}
+
+fn http_get(url: &str, accept: &str) -> Result<Vec<u8>> {
+    let response = ureq::get(url)
+        .set("Accept", accept)
+        .timeout(REQUEST_TIMEOUT)
+        .call()
+        .map_err(|e| match e {
+            ureq::Error::Status(status, response) => {
+                let body = response.into_string().unwrap_or_default();
+                anyhow!("{url} returned {status}: {}", body.trim())
+            }
+            ureq::Error::Transport(transport) => anyhow!("failed to reach {url}: {transport}"),
+        })?;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_RESPONSE_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("failed to read the response of {url}"))?;
+    if bytes.len() as u64 > MAX_RESPONSE_BYTES {
+        bail!("the response of {url} is bigger than {MAX_RESPONSE_BYTES} bytes");
+    }
+    Ok(bytes)
+}
+
+/// Writes the traces as an OTLP JSON file in `folder`, returns the path of the file.
+pub fn save_fetched_traces(
+    folder: &Path,
+    query: &RemoteQuery,
+    traces: &[ExportTraceServiceRequest],
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(folder)
+        .with_context(|| format!("failed to create {}", folder.display()))?;
+    let path = folder.join(query.file_name());
+    std::fs::write(&path, serde_json::to_vec(traces)?)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Formats the time the way the dialog expects it, e.g. `2025-01-02T03:04:05Z`.
+fn format_query_time(time: TimePoint) -> String {
+    chrono::DateTime::from_timestamp(time as i64, 0).map_or_else(
+        || time.to_string(),
+        |t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    )
+}
+
+fn now() -> TimePoint {
+    chrono::Utc::now().timestamp() as TimePoint
+}
+
+/// Dialog for choosing the backend, service and time range, the traces are fetched in the
+/// background while the dialog shows a spinner.
+#[derive(Default)]
+pub struct FetchTraces {
+    show: bool,
+    settings: RemoteSourceSettings,
+    start: String,
+    end: String,
+    error: Option<String>,
+    task: Option<(
+        RemoteQuery,
+        BackgroundTask<Result<Vec<ExportTraceServiceRequest>>>,
+    )>,
+}
+
+impl FetchTraces {
+    pub fn new() -> FetchTraces {
+        FetchTraces::default()
+    }
+
+    /// Opens the dialog with the last used settings and the last hour as the time range.
+    pub fn open(&mut self, settings: RemoteSourceSettings) {
+        self.settings = settings;
+        self.set_last_seconds(3600.0);
+        self.error = None;
+        self.task = None;
+        self.show = true;
+    }
+
+    fn set_last_seconds(&mut self, seconds: f64) {
+        let now = now();
+        self.start = format_query_time(now - seconds);
+        self.end = format_query_time(now);
+    }
+
+    fn start_fetching(&mut self) -> Result<()> {
+        let start = parse_event_time(self.start.trim()).context("invalid start")?;
+        let end = parse_event_time(self.end.trim()).context("invalid end")?;
+        let query = RemoteQuery {
+            settings: self.settings.clone(),
+            start,
+            end,
+        };
+        let task = BackgroundTask::spawn_parallel(vec![query.clone()], |_| 1, fetch_traces);
+        self.task = Some((query, task));
+        Ok(())
+    }
+
+    /// Returns the query and the fetched traces once the download is finished. Errors are shown in
+    /// the dialog.
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+    ) -> Option<(RemoteQuery, Vec<ExportTraceServiceRequest>)> {
+        if !self.show {
+            return None;
+        }
+
+        let mut result = None;
+        if let Some((query, task)) = &mut self.task {
+            match task.poll().pop() {
+                Some(Ok(traces)) => {
+                    result = Some((query.clone(), traces));
+                    self.task = None;
+                    self.show = false;
+                }
+                Some(Err(e)) => {
+                    self.error = Some(format!("{e:#}"));
+                    self.task = None;
+                }
+                None => ctx.request_repaint_after(Duration::from_millis(100)),
+            }
+        }
+
+        Modal::new("fetch traces".into()).show(ctx, |ui| {
+            ui.set_max_width(600.0);
+            ui.heading("Fetch traces from a backend");
+            let fetching = self.task.is_some();
+            ui.add_enabled_ui(!fetching, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Backend:");
+                    let previous_backend = self.settings.backend;
+                    ComboBox::new("fetch traces backend", "")
+                        .selected_text(self.settings.backend.name())
+                        .show_ui(ui, |ui| {
+                            for backend in RemoteBackend::all() {
+                                ui.selectable_value(
+                                    &mut self.settings.backend,
+                                    backend,
+                                    backend.name(),
+                                );
+                            }
+                        });
+                    // Keep a custom address, but switch the default one to the other backend.
+                    if self.settings.backend != previous_backend
+                        && self.settings.url == previous_backend.default_url()
+                    {
+                        self.settings.url = self.settings.backend.default_url().to_string();
+                    }
+                    ui.label("URL:");
+                    ui.add(TextEdit::singleline(&mut self.settings.url).desired_width(300.0));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Service:");
+                    ui.add(TextEdit::singleline(&mut self.settings.service).desired_width(250.0));
+                    ui.label("Max traces:");
+                    ui.add(DragValue::new(&mut self.settings.limit).range(1..=10000));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("From:");
+                    ui.add(TextEdit::singleline(&mut self.start).desired_width(180.0));
+                    ui.label("To:");
+                    ui.add(TextEdit::singleline(&mut self.end).desired_width(180.0));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Last:");
+                    for (label, seconds) in [
+                        ("5 min", 300.0),
+                        ("15 min", 900.0),
+                        ("1 hour", 3600.0),
+                        ("6 hours", 6.0 * 3600.0),
+                        ("1 day", 24.0 * 3600.0),
+                    ] {
+                        if ui.small_button(label).clicked() {
+                            self.set_last_seconds(seconds);
+                        }
+                    }
+                });
+                ui.weak("Times are RFC 3339 dates or unix timestamps.");
+            });
+            if let Some(error) = &self.error {
+                ui.colored_label(theme().error, error);
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                if fetching {
+                    ui.spinner();
+                    ui.label("Fetching...");
+                    if ui.button("Cancel").clicked() {
+                        self.task = None;
+                    }
+                } else {
+                    if ui.button("Fetch").clicked() {
+                        self.error = None;
+                        if let Err(e) = self.start_fetching() {
+                            self.error = Some(format!("{e:#}"));
+                        }
+                    }
+                    if ui.button("Close").clicked() {
+                        self.show = false;
+                    }
+                }
+            });
+        });
+        result
+    }
+}
@@ -0,0 +1,100 @@
+//! Helpers shared by the edit dialogs: a list of entries which can be reordered by dragging, and
+//! naming of duplicated entries.
+
+use eframe::egui::{self, Button, Id, RichText, Stroke, Ui, Widget};
+
+use crate::colors::theme;
+
+/// Moves the entry at `from` to index `to`, shifting the entries in between. `selected` is set to
+/// the new index of the moved entry.
+pub fn move_entry<T>(items: &mut Vec<T>, from: usize, to: usize, selected: &mut usize) {
+    if from >= items.len() || to >= items.len() {
+        return;
+    }
+    let item = items.remove(from);
+    items.insert(to, item);
+    *selected = to;
+}
+
+/// Name for a duplicate of the entry called `name`, which isn't used by any of the `existing`
+/// entries: "Name Copy", "Name Copy 2", ...
+pub fn duplicate_name<'a>(name: &str, existing: impl Iterator<Item = &'a str> + Clone) -> String {
+    let mut candidate = format!("{name} Copy");
+    let mut counter = 2;
+    while existing
+        .clone()
+        .any(|existing_name| existing_name == candidate)
+    {
+        candidate = format!("{name} Copy {counter}");
+        counter += 1;
+    }
+    candidate
+}
+
+/// Payload of a dragged entry, the list id prevents dropping entries into a different list.
+struct DraggedEntry {
+    list_id: Id,
+    index: usize,
+}
+
+/// Draws the entries as buttons, the selected one is highlighted. Entries can be dragged by the
+/// handle on the left to reorder them. `is_fixed` entries (e.g. builtin ones) can't be moved and
+/// nothing can be dropped in their place.
+/// Returns `(from, to)` when an entry was dropped, the caller should apply it with [`move_entry`].
+pub fn draw_reorderable_list<T>(
+    ui: &mut Ui,
+    id_salt: &str,
+    items: &[T],
+    selected_idx: &mut usize,
+    is_fixed: impl Fn(&T) -> bool,
+    label: impl Fn(&T) -> String,
+) -> Option<(usize, usize)> {
+    let list_id = Id::new(("reorderable list", id_salt));
+    let mut moved = None;
+    for (index, item) in items.iter().enumerate() {
+        let fixed = is_fixed(item);
+        let row = ui.horizontal(|ui| {
+            if fixed {
+                ui.add_enabled(false, egui::Label::new("  "));
+            } else {
+                ui.dnd_drag_source(list_id.with(index), DraggedEntry { list_id, index }, |ui| {
+                    ui.label(RichText::new("☰").color(theme().weak_text));
+                })
+                .response
+                .on_hover_text("Drag to reorder");
+            }
+            let button = if *selected_idx == index {
+                Button::new(label(item)).fill(theme().selected_item)
+            } else {
+                Button::new(label(item))
+            };
+            if button.ui(ui).clicked() {
+                *selected_idx = index;
+            }
+        });
+
+        if fixed {
+            continue;
+        }
+        let row_response = row.response;
+        if let Some(dragged) = row_response.dnd_hover_payload::<DraggedEntry>() {
+            if dragged.list_id == list_id && dragged.index != index {
+                // Show where the entry will be put
+                let rect = row_response.rect;
+                let y = if dragged.index < index {
+                    rect.max.y
+                } else {
+                    rect.min.y
+                };
+                ui.painter()
+                    .hline(rect.x_range(), y, Stroke::new(2.0, theme().accent));
+            }
+        }
+        if let Some(dragged) = row_response.dnd_release_payload::<DraggedEntry>() {
+            if dragged.list_id == list_id {
+                moved = Some((dragged.index, index));
+            }
+        }
+    }
+    moved
+}
@@ -0,0 +1,427 @@
+//! Report which collects the results of several analyses (span and dependency statistics, relation
+//! delays, charts) so that they can be exported together as one Markdown or HTML document, e.g.
+//! for an incident write-up. Charts are embedded as SVG images, the exported file is self-contained.
+
+use eframe::egui::{self, Button, Modal, ScrollArea, TextEdit};
+
+use crate::charts::HistogramBucket;
+use crate::clipboard::table_to_tsv;
+use crate::html_export::escape_html;
+use crate::reorder::move_entry;
+
+/// Colors of the chart series, in the order of the series.
+const SERIES_COLORS: [&str; 4] = ["#2f7ed8", "#d8432f", "#3fa34d", "#9b59b6"];
+const CHART_WIDTH: f64 = 640.0;
+const CHART_HEIGHT: f64 = 300.0;
+/// Space for the axis labels.
+const CHART_MARGIN: f64 = 50.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportSeries {
+    pub name: String,
+    /// (x, y)
+    pub points: Vec<(f64, f64)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReportChart {
+    /// Lines connecting the points of every series.
+    Lines {
+        x_label: String,
+        y_label: String,
+        series: Vec<ReportSeries>,
+    },
+    Histogram {
+        x_label: String,
+        buckets: Vec<HistogramBucket>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReportBlock {
+    Text(String),
+    Table {
+        headers: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+    Chart {
+        title: String,
+        chart: ReportChart,
+    },
+}
+
+/// Result of one analysis, added to the report with the "Add to report" button of the analysis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportSection {
+    pub title: String,
+    pub blocks: Vec<ReportBlock>,
+}
+
+impl ReportSection {
+    /// The tables of the section as tab separated values, separated by empty lines.
+    pub fn tables_to_tsv(&self) -> String {
+        self.blocks
+            .iter()
+            .filter_map(|block| match block {
+                ReportBlock::Table { headers, rows } => Some(table_to_tsv(headers, rows)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub title: String,
+    pub sections: Vec<ReportSection>,
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Report {
+            title: "Analysis report".to_string(),
+            sections: Vec::new(),
+        }
+    }
+}
+
+impl Report {
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("# {}\n", self.title);
+        for section in &self.sections {
+            markdown.push_str(&format!("\n## {}\n", section.title));
+            for block in &section.blocks {
+                markdown.push('\n');
+                match block {
+                    ReportBlock::Text(text) => {
+                        markdown.push_str(text);
+                        markdown.push('\n');
+                    }
+                    ReportBlock::Table { headers, rows } => {
+                        let row_line = |cells: &[String]| {
+                            let cells: Vec<String> = cells
+                                .iter()
+                                .map(|cell| cell.replace('|', "\\|").replace('\n', " "))
+                                .collect();
+                            format!("| {} |\n", cells.join(" | "))
+                        };
+                        markdown.push_str(&row_line(headers));
+                        markdown.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+                        for row in rows {
+                            markdown.push_str(&row_line(row));
+                        }
+                    }
+                    ReportBlock::Chart { title, chart } => {
+                        markdown.push_str(&format!(
+                            "![{}](data:image/svg+xml;utf8,{})\n",
+                            title.replace(['[', ']'], ""),
+                            percent_encode(&chart_svg(chart))
+                        ));
+                    }
+                }
+            }
+        }
+        markdown
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut body = String::new();
+        for section in &self.sections {
+            body.push_str(&format!("<h2>{}</h2>\n", escape_html(&section.title)));
+            for block in &section.blocks {
+                match block {
+                    ReportBlock::Text(text) => {
+                        body.push_str(&format!("<p>{}</p>\n", escape_html(text)));
+                    }
+                    ReportBlock::Table { headers, rows } => {
+                        body.push_str("<table>\n<tr>");
+                        for header in headers {
+                            body.push_str(&format!("<th>{}</th>", escape_html(header)));
+                        }
+                        body.push_str("</tr>\n");
+                        for row in rows {
+                            body.push_str("<tr>");
+                            for cell in row {
+                                body.push_str(&format!("<td>{}</td>", escape_html(cell)));
+                            }
+                            body.push_str("</tr>\n");
+                        }
+                        body.push_str("</table>\n");
+                    }
+                    ReportBlock::Chart { title, chart } => {
+                        body.push_str(&format!(
+                            "<figure>\n{}<figcaption>{}</figcaption>\n</figure>\n",
+                            chart_svg(chart),
+                            escape_html(title)
+                        ));
+                    }
+                }
+            }
+        }
+        HTML_TEMPLATE
+            .replace("{{TITLE}}", &escape_html(&self.title))
+            .replace("{{BODY}}", &body)
+    }
+}
+
+/// Renders the chart as a standalone SVG image, with the ranges of the axes as labels.
+pub fn chart_svg(chart: &ReportChart) -> String {
+    let (x_label, y_label, shapes, (x_min, x_max), (y_min, y_max)) = match chart {
+        ReportChart::Lines {
+            x_label,
+            y_label,
+            series,
+        } => {
+            let points = || series.iter().flat_map(|s| s.points.iter());
+            let x_range = value_range(points().map(|p| p.0));
+            let y_range = value_range(points().map(|p| p.1));
+            let mut shapes = String::new();
+            for (i, s) in series.iter().enumerate() {
+                let color = SERIES_COLORS[i % SERIES_COLORS.len()];
+                let coordinates: Vec<String> = s
+                    .points
+                    .iter()
+                    .map(|&(x, y)| {
+                        let (x, y) = to_svg_point(x, y, x_range, y_range);
+                        format!("{x:.1},{y:.1}")
+                    })
+                    .collect();
+                shapes.push_str(&format!(
+                    "<polyline fill=\"none\" stroke=\"{color}\" stroke-width=\"2\" points=\"{}\"/>\n",
+                    coordinates.join(" ")
+                ));
+                shapes.push_str(&format!(
+                    "<text x=\"{:.0}\" y=\"{:.0}\" fill=\"{color}\" font-size=\"12\">{}</text>\n",
+                    CHART_MARGIN + 10.0,
+                    20.0 + 15.0 * i as f64,
+                    escape_html(&s.name)
+                ));
+            }
+            (x_label, y_label.as_str(), shapes, x_range, y_range)
+        }
+        ReportChart::Histogram { x_label, buckets } => {
+            let x_range = value_range(buckets.iter().flat_map(|b| [b.start, b.end]));
+            let y_range = value_range(buckets.iter().map(|b| b.count as f64).chain([0.0]));
+            let mut shapes = String::new();
+            for bucket in buckets {
+                let (left, top) = to_svg_point(bucket.start, bucket.count as f64, x_range, y_range);
+                let (right, bottom) = to_svg_point(bucket.end, 0.0, x_range, y_range);
+                shapes.push_str(&format!(
+                    "<rect x=\"{left:.1}\" y=\"{top:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>\n",
+                    (right - left - 1.0).max(1.0),
+                    bottom - top,
+                    SERIES_COLORS[0]
+                ));
+            }
+            (x_label, "count", shapes, x_range, y_range)
+        }
+    };
+
+    let plot_bottom = CHART_HEIGHT - CHART_MARGIN;
+    let plot_right = CHART_WIDTH - 10.0;
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{CHART_WIDTH}\" height=\"{CHART_HEIGHT}\" font-family=\"sans-serif\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n\
+         <line x1=\"{CHART_MARGIN}\" y1=\"10\" x2=\"{CHART_MARGIN}\" y2=\"{plot_bottom}\" stroke=\"black\"/>\n\
+         <line x1=\"{CHART_MARGIN}\" y1=\"{plot_bottom}\" x2=\"{plot_right}\" y2=\"{plot_bottom}\" stroke=\"black\"/>\n\
+         {shapes}\
+         <text x=\"{CHART_MARGIN}\" y=\"{}\" font-size=\"11\">{}</text>\n\
+         <text x=\"{plot_right}\" y=\"{}\" font-size=\"11\" text-anchor=\"end\">{}</text>\n\
+         <text x=\"{}\" y=\"{}\" font-size=\"12\" text-anchor=\"middle\">{}</text>\n\
+         <text x=\"{}\" y=\"{plot_bottom}\" font-size=\"11\" text-anchor=\"end\">{}</text>\n\
+         <text x=\"{}\" y=\"14\" font-size=\"11\" text-anchor=\"end\">{}</text>\n\
+         <text x=\"12\" y=\"{}\" font-size=\"12\" text-anchor=\"middle\" transform=\"rotate(-90 12 {})\">{}</text>\n\
+         </svg>\n",
+        plot_bottom + 15.0,
+        format_axis_value(x_min),
+        plot_bottom + 15.0,
+        format_axis_value(x_max),
+        (CHART_MARGIN + plot_right) / 2.0,
+        CHART_HEIGHT - 10.0,
+        escape_html(x_label),
+        CHART_MARGIN - 4.0,
+        format_axis_value(y_min),
+        CHART_MARGIN - 4.0,
+        format_axis_value(y_max),
+        plot_bottom / 2.0,
+        plot_bottom / 2.0,
+        escape_html(y_label),
+    )
+}
+
+/// Range of the values, widened when all values are the same so that they can be scaled.
+fn value_range(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+        (min.min(v), max.max(v))
+    });
+    if min > max {
+        (0.0, 1.0)
+    } else if min == max {
+        (min - 0.5, max + 0.5)
+    } else {
+        (min, max)
+    }
+}
+
+fn to_svg_point(x: f64, y: f64, x_range: (f64, f64), y_range: (f64, f64)) -> (f64, f64) {
+    let plot_width = CHART_WIDTH - CHART_MARGIN - 10.0;
+    let plot_height = CHART_HEIGHT - CHART_MARGIN - 10.0;
+    (
+        CHART_MARGIN + (x - x_range.0) / (x_range.1 - x_range.0) * plot_width,
+        CHART_HEIGHT - CHART_MARGIN - (y - y_range.0) / (y_range.1 - y_range.0) * plot_height,
+    )
+}
+
+fn format_axis_value(value: f64) -> String {
+    if value.abs() >= 1000.0 || value.fract() == 0.0 {
+        format!("{value:.0}")
+    } else {
+        format!("{value:.3}")
+    }
+}
+
+/// Encodes the characters which can't be a part of a data URL in Markdown.
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '%' | '#' | '<' | '>' | '"' | '(' | ')' | ' ' | '\n' | '[' | ']' => {
+                encoded.push_str(&format!("%{:02X}", c as u32))
+            }
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+/// Format in which the report is exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+/// Modal showing the sections of the report, they can be renamed, reordered and removed.
+#[derive(Default)]
+pub struct ReportModal {
+    pub show: bool,
+    pub report: Report,
+    /// Set when the user asks to export the report, the app asks for the file and writes it.
+    pub export_requested: Option<ReportFormat>,
+}
+
+impl ReportModal {
+    pub fn new() -> ReportModal {
+        ReportModal::default()
+    }
+
+    pub fn add_section(&mut self, section: ReportSection) {
+        self.report.sections.push(section);
+    }
+
+    pub fn show_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        if !self.show {
+            return;
+        }
+
+        let mut close = false;
+        Modal::new("analysis report".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Report");
+            ui.label(
+                "Results added with the \"Add to report\" buttons of the analyses. Export the \
+                 report to share all of them in one document.",
+            );
+            ui.horizontal(|ui| {
+                ui.label("Title:");
+                ui.add(TextEdit::singleline(&mut self.report.title).desired_width(400.0));
+            });
+            ui.separator();
+
+            let sections = &mut self.report.sections;
+            if sections.is_empty() {
+                ui.label("The report is empty.");
+            }
+            let mut moved = None;
+            let mut removed = None;
+            ScrollArea::vertical()
+                .max_height((max_height - 200.0).max(100.0))
+                .show(ui, |ui| {
+                    let count = sections.len();
+                    for (i, section) in sections.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(i > 0, Button::new("⏶")).clicked() {
+                                moved = Some((i, i - 1));
+                            }
+                            if ui.add_enabled(i + 1 < count, Button::new("⏷")).clicked() {
+                                moved = Some((i, i + 1));
+                            }
+                            ui.add(TextEdit::singleline(&mut section.title).desired_width(400.0));
+                            ui.label(format!("{} blocks", section.blocks.len()));
+                            if ui.button("Remove").clicked() {
+                                removed = Some(i);
+                            }
+                        });
+                    }
+                });
+            if let Some((from, to)) = moved {
+                let mut selected = to;
+                move_entry(sections, from, to, &mut selected);
+            }
+            if let Some(i) = removed {
+                sections.remove(i);
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                let has_sections = !self.report.sections.is_empty();
+                if ui
+                    .add_enabled(has_sections, Button::new("Export Markdown"))
+                    .clicked()
+                {
+                    self.export_requested = Some(ReportFormat::Markdown);
+                }
+                if ui
+                    .add_enabled(has_sections, Button::new("Export HTML"))
+                    .clicked()
+                {
+                    self.export_requested = Some(ReportFormat::Html);
+                }
+                if ui.add_enabled(has_sections, Button::new("Clear")).clicked() {
+                    self.report.sections.clear();
+                }
+                if ui.button("Close").clicked() {
+                    close = true;
+                }
+            });
+        });
+
+        if close {
+            self.show = false;
+        }
+    }
+}
+
+const HTML_TEMPLATE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{TITLE}}</title>
+<style>
+  body { font-family: sans-serif; margin: 20px auto; max-width: 1000px; color: #202020; }
+  table { border-collapse: collapse; margin: 10px 0; }
+  th, td { border: 1px solid #c0c0c0; padding: 4px 8px; text-align: right; }
+  th:first-child, td:first-child { text-align: left; }
+  th { background: #f0f0f0; }
+  figure { margin: 10px 0; }
+  figcaption { font-size: 13px; color: #606060; }
+</style>
+</head>
+<body>
+<h1>{{TITLE}}</h1>
+{{BODY}}</body>
+</html>
+"##;
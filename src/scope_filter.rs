@@ -0,0 +1,150 @@
+//! Filter by instrumentation scope and resource attributes, applied together with the node filter.
+//! Traces which mix several instrumented libraries can be narrowed down to the interesting ones.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+
+use eframe::egui::{Button, CollapsingHeader, ScrollArea, Ui};
+
+use crate::types::{value_to_text, Span, SpanData};
+
+/// Name shown for spans without an instrumentation scope.
+pub const NO_SCOPE_NAME: &str = "<no scope>";
+
+pub fn scope_name(span: &SpanData) -> &str {
+    span.scope
+        .as_ref()
+        .map_or(NO_SCOPE_NAME, |scope| scope.name.as_str())
+}
+
+/// Scopes and resource attributes found in the trace, with the number of spans of each.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScopeCounts {
+    pub scopes: BTreeMap<String, usize>,
+    /// Spans per (attribute name, value) of the resource (node) which recorded them.
+    pub resource_attributes: BTreeMap<(String, String), usize>,
+}
+
+/// Counts the spans in `spans` and all their descendants.
+pub fn count_scopes(spans: &[Rc<Span>]) -> ScopeCounts {
+    fn count(span: &Rc<Span>, counts: &mut ScopeCounts) {
+        *counts
+            .scopes
+            .entry(scope_name(span).to_string())
+            .or_default() += 1;
+        for (name, value) in &span.node.attributes {
+            *counts
+                .resource_attributes
+                .entry((name.clone(), value_to_text(value)))
+                .or_default() += 1;
+        }
+        for child in span.children().iter() {
+            count(child, counts);
+        }
+    }
+
+    let mut counts = ScopeCounts::default();
+    for span in spans {
+        count(span, &mut counts);
+    }
+    counts
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScopeFilter {
+    pub hidden_scopes: BTreeSet<String>,
+    /// (attribute name, value) pairs, spans of resources with one of them are hidden.
+    pub hidden_resource_attributes: BTreeSet<(String, String)>,
+    /// Counted when the picker is first drawn for a trace.
+    counts: Option<ScopeCounts>,
+}
+
+impl ScopeFilter {
+    pub fn is_active(&self) -> bool {
+        !self.hidden_scopes.is_empty() || !self.hidden_resource_attributes.is_empty()
+    }
+
+    /// Whether a span is displayed. The display mode transformation drops hidden spans together
+    /// with their children.
+    pub fn shows(&self, span: &SpanData) -> bool {
+        if self.hidden_scopes.contains(scope_name(span)) {
+            return false;
+        }
+        if self.hidden_resource_attributes.is_empty() {
+            return true;
+        }
+        !span.node.attributes.iter().any(|(name, value)| {
+            self.hidden_resource_attributes
+                .contains(&(name.clone(), value_to_text(value)))
+        })
+    }
+
+    /// The counts have to be computed again, e.g. because another trace was loaded.
+    pub fn invalidate_counts(&mut self) {
+        self.counts = None;
+    }
+
+    /// Draws the checkboxes of the scopes and resource attributes in `spans`. Returns true if the
+    /// filter changed.
+    pub fn draw(&mut self, ui: &mut Ui, spans: &[Rc<Span>]) -> bool {
+        let counts = self.counts.get_or_insert_with(|| count_scopes(spans));
+        let mut changed = false;
+
+        ui.label("Instrumentation scopes");
+        for (scope, count) in &counts.scopes {
+            let mut shown = !self.hidden_scopes.contains(scope);
+            if ui
+                .checkbox(&mut shown, format!("{scope} ({count})"))
+                .changed()
+            {
+                if shown {
+                    self.hidden_scopes.remove(scope);
+                } else {
+                    self.hidden_scopes.insert(scope.clone());
+                }
+                changed = true;
+            }
+        }
+
+        ui.separator();
+        ui.label("Resource attributes");
+        let mut by_attribute: BTreeMap<&str, Vec<(&str, usize)>> = BTreeMap::new();
+        for ((name, value), count) in &counts.resource_attributes {
+            by_attribute.entry(name).or_default().push((value, *count));
+        }
+        ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            for (name, values) in by_attribute {
+                CollapsingHeader::new(format!("{name} ({} values)", values.len()))
+                    .id_salt(("resource attribute", name))
+                    .show(ui, |ui| {
+                        for (value, count) in values {
+                            let key = (name.to_string(), value.to_string());
+                            let mut shown = !self.hidden_resource_attributes.contains(&key);
+                            if ui
+                                .checkbox(&mut shown, format!("{value} ({count})"))
+                                .changed()
+                            {
+                                if shown {
+                                    self.hidden_resource_attributes.remove(&key);
+                                } else {
+                                    self.hidden_resource_attributes.insert(key);
+                                }
+                                changed = true;
+                            }
+                        }
+                    });
+            }
+        });
+
+        ui.separator();
+        if ui
+            .add_enabled(self.is_active(), Button::new("Show all"))
+            .clicked()
+        {
+            self.hidden_scopes.clear();
+            self.hidden_resource_attributes.clear();
+            changed = true;
+        }
+        changed
+    }
+}
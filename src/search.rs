@@ -0,0 +1,155 @@
+//! Span search on the middle bar. The same search terms are used to search events.
+//!
+//! The search term is a list of criteria separated by spaces, a span matches when it matches all of
+//! them:
+//! * `name` - span name contains `name` (case insensitive)
+//! * `key=value` - the span has attribute `key` with value `value`
+//!
+//! Values with spaces can be put in double quotes: `key="some value"`.
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use opentelemetry_proto::tonic::common::v1::any_value::Value;
+
+use crate::types::{value_to_text, Span};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchCriterion {
+    NameContains(String),
+    AttributeEquals { key: String, value: String },
+}
+
+impl SearchCriterion {
+    pub fn matches(&self, span: &Span) -> bool {
+        self.matches_name_and_attributes(&span.name, &span.attributes)
+    }
+
+    /// Used for both spans and events.
+    pub fn matches_name_and_attributes(
+        &self,
+        name: &str,
+        attributes: &BTreeMap<String, Option<Value>>,
+    ) -> bool {
+        match self {
+            SearchCriterion::NameContains(part) => {
+                name.to_lowercase().contains(&part.to_lowercase())
+            }
+            SearchCriterion::AttributeEquals { key, value } => attributes
+                .get(key)
+                .is_some_and(|v| &value_to_text(v) == value),
+        }
+    }
+}
+
+pub fn parse_search_term(search_term: &str) -> Vec<SearchCriterion> {
+    split_respecting_quotes(search_term)
+        .into_iter()
+        .map(|token| match token.split_once('=') {
+            Some((key, value)) if !key.is_empty() => SearchCriterion::AttributeEquals {
+                key: key.to_string(),
+                value: value.to_string(),
+            },
+            _ => SearchCriterion::NameContains(token),
+        })
+        .collect()
+}
+
+/// Splits on whitespace, except inside of double quotes. The quotes are removed.
+fn split_respecting_quotes(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in text.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// Appends `key=value` to the search term, unless it's already there.
+pub fn add_attribute_criterion(search_term: &mut String, key: &str, value: &str) {
+    let criterion = SearchCriterion::AttributeEquals {
+        key: key.to_string(),
+        value: value.to_string(),
+    };
+    if parse_search_term(search_term).contains(&criterion) {
+        return;
+    }
+    let value = if value.is_empty() || value.contains(char::is_whitespace) {
+        format!("\"{value}\"")
+    } else {
+        value.to_string()
+    };
+    if !search_term.trim().is_empty() {
+        search_term.push(' ');
+    }
+    search_term.push_str(&format!("{key}={value}"));
+}
+
+/// Spans which match all criteria, sorted by start time. Spans aren't matched if there are no criteria.
+pub fn find_matching_spans(spans: &[Rc<Span>], criteria: &[SearchCriterion]) -> Vec<Rc<Span>> {
+    if criteria.is_empty() {
+        return Vec::new();
+    }
+    let mut matching: Vec<Rc<Span>> = spans
+        .iter()
+        .filter(|span| criteria.iter().all(|c| c.matches(span)))
+        .cloned()
+        .collect();
+    matching.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    matching
+}
+
+#[derive(Default, Debug)]
+pub struct Search {
+    pub search_term: String,
+    pub search_results: Vec<Rc<Span>>,
+    /// Index of the result which was shown last, None before the first one is shown.
+    current_result: Option<usize>,
+    // TODO - non functional for now
+    pub hide_non_matching: bool,
+}
+
+impl Search {
+    /// Searches `spans` (all spans in the trace, including children) using the current search term.
+    pub fn run(&mut self, spans: &[Rc<Span>]) {
+        self.search_results = find_matching_spans(spans, &parse_search_term(&self.search_term));
+        self.current_result = None;
+    }
+
+    /// Moves to the next result, wrapping around at the end.
+    pub fn next_result(&mut self) -> Option<Rc<Span>> {
+        if self.search_results.is_empty() {
+            return None;
+        }
+        let next = self
+            .current_result
+            .map_or(0, |i| (i + 1) % self.search_results.len());
+        self.current_result = Some(next);
+        Some(self.search_results[next].clone())
+    }
+
+    /// e.g. "3/10", shown next to the search buttons.
+    pub fn position_text(&self) -> String {
+        match self.current_result {
+            Some(i) => format!("{}/{}", i + 1, self.search_results.len()),
+            None => format!("{} results", self.search_results.len()),
+        }
+    }
+
+    pub fn clear_results(&mut self) {
+        self.search_results.clear();
+        self.current_result = None;
+    }
+}
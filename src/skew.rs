@@ -0,0 +1,421 @@
+//! Cross-node skew - for spans which are processed by many nodes (e.g. a witness for the same
+//! height), how far apart in time the nodes start and finish them. Spans of one occurrence are
+//! linked by the value of an attribute.
+
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use eframe::egui::{self, Grid, Modal, RichText, ScrollArea, TextEdit, Vec2};
+
+use crate::analyze_utils::{
+    process_spans_for_analysis, span_search_ui, span_selection_list_ui, ModalFocus, Statistics,
+};
+use crate::charts::{draw_chart, ChartAxis, ChartSeries};
+use crate::colors::{node_color, theme};
+use crate::types::{
+    format_ms, value_to_text, Span, TimeFormat, TimePoint, MILLISECONDS_PER_SECOND,
+};
+
+pub const DEFAULT_LINKING_ATTRIBUTE: &str = "height";
+
+/// One occurrence of the span name, e.g. the spans of all nodes for one height.
+#[derive(Debug, Clone)]
+pub struct SkewOccurrence {
+    /// Value of the linking attribute.
+    pub link_value: String,
+    /// The earliest span of each node, sorted by the start time.
+    pub node_spans: Vec<Rc<Span>>,
+}
+
+impl SkewOccurrence {
+    pub fn first_start(&self) -> TimePoint {
+        self.node_spans[0].start_time
+    }
+
+    fn first_end(&self) -> TimePoint {
+        self.node_spans
+            .iter()
+            .map(|s| s.end_time)
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Time between the first and the last node starting the span, in seconds.
+    pub fn start_skew(&self) -> f64 {
+        self.last_to_start().start_time - self.first_start()
+    }
+
+    /// Time between the first and the last node finishing the span, in seconds.
+    pub fn end_skew(&self) -> f64 {
+        self.last_to_finish().end_time - self.first_end()
+    }
+
+    pub fn last_to_start(&self) -> &Rc<Span> {
+        self.node_spans.last().unwrap()
+    }
+
+    pub fn last_to_finish(&self) -> &Rc<Span> {
+        self.node_spans
+            .iter()
+            .max_by(|a, b| a.end_time.total_cmp(&b.end_time))
+            .unwrap()
+    }
+}
+
+/// How much one node lags behind the first node, over all occurrences it took part in.
+#[derive(Debug, Clone)]
+pub struct NodeLag {
+    pub node_name: String,
+    pub occurrences: usize,
+    pub last_to_start: usize,
+    pub last_to_finish: usize,
+    /// Mean time between the first node starting and this node starting, in seconds.
+    pub mean_start_lag: f64,
+    /// Mean time between the first node finishing and this node finishing, in seconds.
+    pub mean_end_lag: f64,
+}
+
+/// Occurrences of `span_name` on at least two nodes, linked by the value of `linking_attribute` and
+/// sorted by time. When a node has multiple spans with the same value, the earliest one is used.
+/// `spans` should contain all spans (including children) of the trace.
+pub fn compute_skew(
+    spans: &[Rc<Span>],
+    span_name: &str,
+    linking_attribute: &str,
+) -> Vec<SkewOccurrence> {
+    // link value -> node name -> earliest span
+    let mut by_link_value: HashMap<String, BTreeMap<&str, &Rc<Span>>> = HashMap::new();
+    for span in spans {
+        if span.original_name() != span_name {
+            continue;
+        }
+        let Some(value) = span.attributes.get(linking_attribute) else {
+            continue;
+        };
+        let earliest = by_link_value
+            .entry(value_to_text(value))
+            .or_default()
+            .entry(span.node.name.as_str())
+            .or_insert(span);
+        if span.start_time < earliest.start_time {
+            *earliest = span;
+        }
+    }
+
+    let mut occurrences: Vec<SkewOccurrence> = by_link_value
+        .into_iter()
+        .filter(|(_, nodes)| nodes.len() >= 2)
+        .map(|(link_value, nodes)| {
+            let mut node_spans: Vec<Rc<Span>> = nodes.into_values().cloned().collect();
+            node_spans.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+            SkewOccurrence {
+                link_value,
+                node_spans,
+            }
+        })
+        .collect();
+    occurrences.sort_by(|a, b| a.first_start().total_cmp(&b.first_start()));
+    occurrences
+}
+
+/// Lag of every node, the node which lags the most when finishing first.
+pub fn compute_node_lags(occurrences: &[SkewOccurrence]) -> Vec<NodeLag> {
+    // node name -> (occurrences, last to start, last to finish, start lags, end lags)
+    let mut per_node: HashMap<&str, (usize, usize, usize, Statistics, Statistics)> = HashMap::new();
+    for occurrence in occurrences {
+        let first_start = occurrence.first_start();
+        let first_end = occurrence.first_end();
+        let last_to_start = occurrence.last_to_start();
+        let last_to_finish = occurrence.last_to_finish();
+        for span in &occurrence.node_spans {
+            let entry = per_node.entry(span.node.name.as_str()).or_default();
+            entry.0 += 1;
+            if Rc::ptr_eq(span, last_to_start) {
+                entry.1 += 1;
+            }
+            if Rc::ptr_eq(span, last_to_finish) {
+                entry.2 += 1;
+            }
+            entry.3.add_value(span.start_time - first_start);
+            entry.4.add_value(span.end_time - first_end);
+        }
+    }
+
+    let mut lags: Vec<NodeLag> = per_node
+        .into_iter()
+        .map(
+            |(node_name, (occurrences, last_to_start, last_to_finish, start_lags, end_lags))| {
+                NodeLag {
+                    node_name: node_name.to_string(),
+                    occurrences,
+                    last_to_start,
+                    last_to_finish,
+                    mean_start_lag: start_lags.mean(),
+                    mean_end_lag: end_lags.mean(),
+                }
+            },
+        )
+        .collect();
+    lags.sort_by(|a, b| {
+        b.mean_end_lag
+            .total_cmp(&a.mean_end_lag)
+            .then_with(|| a.node_name.cmp(&b.node_name))
+    });
+    lags
+}
+
+pub struct SkewModal {
+    pub show: bool,
+    all_spans: Vec<Rc<Span>>,
+    unique_span_names: Vec<String>,
+    search_text: String,
+    selected_span_name: Option<String>,
+    linking_attribute: String,
+    occurrences: Vec<SkewOccurrence>,
+    node_lags: Vec<NodeLag>,
+    pub focus: ModalFocus,
+}
+
+impl Default for SkewModal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SkewModal {
+    pub fn new() -> SkewModal {
+        SkewModal {
+            show: false,
+            all_spans: Vec::new(),
+            unique_span_names: Vec::new(),
+            search_text: String::new(),
+            selected_span_name: None,
+            linking_attribute: DEFAULT_LINKING_ATTRIBUTE.to_string(),
+            occurrences: Vec::new(),
+            node_lags: Vec::new(),
+            focus: ModalFocus::default(),
+        }
+    }
+
+    pub fn open(&mut self, spans_for_analysis: &[Rc<Span>]) {
+        let (all_spans, unique_span_names) = process_spans_for_analysis(spans_for_analysis);
+        self.all_spans = all_spans;
+        self.unique_span_names = unique_span_names;
+        self.show = true;
+        self.compute();
+    }
+
+    fn compute(&mut self) {
+        self.occurrences = match &self.selected_span_name {
+            Some(name) => compute_skew(&self.all_spans, name, self.linking_attribute.trim()),
+            None => Vec::new(),
+        };
+        self.node_lags = compute_node_lags(&self.occurrences);
+    }
+
+    pub fn show_modal(
+        &mut self,
+        ctx: &egui::Context,
+        time_format: &TimeFormat,
+        max_width: f32,
+        max_height: f32,
+    ) {
+        if !self.show {
+            return;
+        }
+
+        let mut compute = false;
+        let mut jump_to_span = None;
+        Modal::new("cross-node skew".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Cross-node skew");
+            ui.label(
+                "How far apart the nodes start and finish spans with the same value of the \
+                 linking attribute.",
+            );
+            ui.separator();
+
+            ui.horizontal_top(|ui| {
+                ui.vertical(|ui| {
+                    ui.set_width(250.0);
+                    span_search_ui(ui, &mut self.search_text, "Span name", "Search", 240.0);
+                    compute |= span_selection_list_ui(
+                        ui,
+                        &self.unique_span_names,
+                        &self.search_text,
+                        &mut self.selected_span_name,
+                        max_height - 200.0,
+                        "skew span names",
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("Linking attribute:");
+                        compute |= ui
+                            .add(
+                                TextEdit::singleline(&mut self.linking_attribute)
+                                    .desired_width(100.0),
+                            )
+                            .lost_focus();
+                    });
+                });
+                ui.separator();
+                ui.vertical(|ui| {
+                    self.draw_results(
+                        ui,
+                        time_format,
+                        max_width - 300.0,
+                        max_height - 150.0,
+                        &mut jump_to_span,
+                    );
+                });
+            });
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.show = false;
+            }
+        });
+
+        if compute {
+            self.compute();
+        }
+        if let Some(span) = jump_to_span {
+            self.focus.jump_to(span);
+            self.show = false;
+        }
+    }
+
+    fn draw_results(
+        &self,
+        ui: &mut egui::Ui,
+        time_format: &TimeFormat,
+        width: f32,
+        max_height: f32,
+        jump_to_span: &mut Option<Rc<Span>>,
+    ) {
+        let Some(span_name) = &self.selected_span_name else {
+            ui.label("Choose a span name.");
+            return;
+        };
+        if self.occurrences.is_empty() {
+            ui.label(format!(
+                "No {span_name} spans with the same {} on two or more nodes.",
+                self.linking_attribute
+            ));
+            return;
+        }
+
+        let start_skews: Vec<(f64, f64)> = self
+            .occurrences
+            .iter()
+            .map(|o| (o.first_start(), o.start_skew() * MILLISECONDS_PER_SECOND))
+            .collect();
+        let end_skews: Vec<(f64, f64)> = self
+            .occurrences
+            .iter()
+            .map(|o| (o.first_start(), o.end_skew() * MILLISECONDS_PER_SECOND))
+            .collect();
+        let series = [
+            ChartSeries {
+                name: "start skew",
+                color: theme().chart_fill,
+                points: &start_skews,
+                draw_lines: true,
+            },
+            ChartSeries {
+                name: "end skew",
+                color: theme().chart_alert,
+                points: &end_skews,
+                draw_lines: true,
+            },
+        ];
+        let clicked = draw_chart(
+            ui,
+            Vec2::new(width, 200.0),
+            &series,
+            &ChartAxis {
+                label: "time",
+                format: &|time| time_format.time_string(time),
+            },
+            &ChartAxis {
+                label: "ms",
+                format: &|ms| format!("{ms:.1}"),
+            },
+        );
+        if let Some((series_idx, point_idx)) = clicked {
+            let occurrence = &self.occurrences[point_idx];
+            let span = if series_idx == 0 {
+                occurrence.last_to_start()
+            } else {
+                occurrence.last_to_finish()
+            };
+            *jump_to_span = Some(span.clone());
+        }
+        ui.label(
+            RichText::new("Click on a point to jump to the node which was the last one.")
+                .small()
+                .weak(),
+        );
+        ui.add_space(5.0);
+
+        ui.label(format!(
+            "{} occurrences. Nodes which lag the most first:",
+            self.occurrences.len()
+        ));
+        ScrollArea::vertical()
+            .max_height(max_height - 280.0)
+            .show(ui, |ui| {
+                Grid::new("skew node lags").striped(true).show(ui, |ui| {
+                    for title in [
+                        "Node",
+                        "Occurrences",
+                        "Last to start",
+                        "Last to finish",
+                        "Mean start lag",
+                        "Mean end lag",
+                    ] {
+                        ui.strong(title);
+                    }
+                    ui.end_row();
+                    for lag in &self.node_lags {
+                        ui.label(RichText::new(&lag.node_name).color(node_color(&lag.node_name)));
+                        ui.monospace(lag.occurrences.to_string());
+                        ui.monospace(lag.last_to_start.to_string());
+                        ui.monospace(lag.last_to_finish.to_string());
+                        ui.monospace(format_ms(lag.mean_start_lag));
+                        ui.monospace(format_ms(lag.mean_end_lag));
+                        ui.end_row();
+                    }
+                });
+
+                ui.add_space(10.0);
+                Grid::new("skew occurrences").striped(true).show(ui, |ui| {
+                    for title in [
+                        &self.linking_attribute,
+                        "Start",
+                        "Start skew",
+                        "End skew",
+                        "Last to finish",
+                    ] {
+                        ui.strong(title);
+                    }
+                    ui.end_row();
+                    for occurrence in &self.occurrences {
+                        ui.monospace(&occurrence.link_value);
+                        ui.monospace(time_format.display_string(occurrence.first_start()));
+                        ui.monospace(format_ms(occurrence.start_skew()));
+                        ui.monospace(format_ms(occurrence.end_skew()));
+                        let last = occurrence.last_to_finish();
+                        if ui
+                            .link(RichText::new(&last.node.name).color(node_color(&last.node.name)))
+                            .on_hover_text("Show the span in the timeline")
+                            .clicked()
+                        {
+                            *jump_to_span = Some(last.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+}
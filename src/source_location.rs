@@ -0,0 +1,79 @@
+//! Opening the code which produced a span, from the `code.*` attributes of OpenTelemetry spans.
+
+use std::collections::BTreeMap;
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use opentelemetry_proto::tonic::common::v1::any_value::Value;
+
+use crate::types::value_to_text;
+
+/// Used when the preferences don't have an editor command.
+pub const DEFAULT_EDITOR_COMMAND: &str = "code --goto {file}:{line}";
+
+/// Attribute names of the file and line, the older semantic conventions go first.
+const FILE_ATTRIBUTES: [&str; 2] = ["code.filepath", "code.file.path"];
+const LINE_ATTRIBUTES: [&str; 2] = ["code.lineno", "code.line.number"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: Option<u64>,
+}
+
+impl SourceLocation {
+    /// The location of the code which created the span, if the span has the file attribute.
+    pub fn from_attributes(attributes: &BTreeMap<String, Option<Value>>) -> Option<SourceLocation> {
+        let attribute = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| attributes.get(*name))
+                .map(value_to_text)
+        };
+        let file = attribute(&FILE_ATTRIBUTES).filter(|file| !file.is_empty())?;
+        let line = attribute(&LINE_ATTRIBUTES).and_then(|line| line.parse().ok());
+        Some(SourceLocation { file, line })
+    }
+
+    /// "file:line", or only the file when the line isn't known.
+    pub fn display_string(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{line}", self.file),
+            None => self.file.clone(),
+        }
+    }
+}
+
+/// Program and arguments of the editor command. The template is split on whitespace first, so
+/// `{file}` stays one argument even if the path contains spaces. Without a line number `{line}`
+/// is replaced with 1.
+pub fn editor_command(template: &str, location: &SourceLocation) -> Result<Vec<String>> {
+    let line = location.line.unwrap_or(1).to_string();
+    // The path comes from the trace, a path like "--some-flag" would be taken as an option of
+    // the editor.
+    let file = if location.file.starts_with('-') {
+        format!("./{}", location.file)
+    } else {
+        location.file.clone()
+    };
+    let command: Vec<String> = template
+        .split_whitespace()
+        .map(|part| part.replace("{file}", &file).replace("{line}", &line))
+        .collect();
+    if command.is_empty() {
+        bail!("the editor command is empty, set it in the preferences");
+    }
+    Ok(command)
+}
+
+/// Starts the editor without waiting for it.
+pub fn open_in_editor(template: &str, location: &SourceLocation) -> Result<()> {
+    let command = editor_command(template, location)?;
+    let mut child = Command::new(&command[0])
+        .args(&command[1..])
+        .spawn()
+        .with_context(|| format!("failed to run '{}'", command.join(" ")))?;
+    // Waiting in the background, so that the finished process doesn't stay around as a zombie.
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
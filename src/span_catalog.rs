@@ -0,0 +1,304 @@
+//! Span catalog - a side panel which lists every span name in the selected part of the timeline
+//! with its count and durations. It's the entry point for finding out which spans matter, from
+//! there all spans with a name can be highlighted or analyzed.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use eframe::egui::{self, Button, Grid, RichText, ScrollArea, TextEdit, Ui};
+
+use crate::analyze_utils::Statistics;
+use crate::types::{format_ms, Span, TimePoint};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanCatalogEntry {
+    pub name: String,
+    pub count: usize,
+    /// Seconds
+    pub total_duration: f64,
+    /// Seconds
+    pub mean_duration: f64,
+    /// Seconds
+    pub p99_duration: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogColumn {
+    Name,
+    Count,
+    Total,
+    Mean,
+    P99,
+}
+
+impl CatalogColumn {
+    pub fn all() -> [CatalogColumn; 5] {
+        [
+            CatalogColumn::Name,
+            CatalogColumn::Count,
+            CatalogColumn::Total,
+            CatalogColumn::Mean,
+            CatalogColumn::P99,
+        ]
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            CatalogColumn::Name => "Name",
+            CatalogColumn::Count => "Count",
+            CatalogColumn::Total => "Total",
+            CatalogColumn::Mean => "Mean",
+            CatalogColumn::P99 => "p99",
+        }
+    }
+}
+
+/// Entries for all span names among `spans` which overlap the time window `[start, end]`, sorted
+/// by name.
+pub fn compute_span_catalog(
+    spans: &[Rc<Span>],
+    start: TimePoint,
+    end: TimePoint,
+) -> Vec<SpanCatalogEntry> {
+    let mut durations: HashMap<&str, Statistics> = HashMap::new();
+    for span in spans {
+        if span.end_time < start || span.start_time > end {
+            continue;
+        }
+        durations
+            .entry(span.original_name())
+            .or_default()
+            .add_value(span.end_time - span.start_time);
+    }
+    let mut entries: Vec<SpanCatalogEntry> = durations
+        .into_iter()
+        .map(|(name, stats)| SpanCatalogEntry {
+            name: name.to_string(),
+            count: stats.count,
+            total_duration: stats.total,
+            mean_duration: stats.mean(),
+            p99_duration: stats.percentile(99.0),
+        })
+        .collect();
+    sort_span_catalog(&mut entries, CatalogColumn::Name, false);
+    entries
+}
+
+/// Sorts by the column, ties are broken by name.
+pub fn sort_span_catalog(
+    entries: &mut [SpanCatalogEntry],
+    column: CatalogColumn,
+    descending: bool,
+) {
+    entries.sort_by(|a, b| {
+        let ordering = match column {
+            CatalogColumn::Name => a.name.cmp(&b.name),
+            CatalogColumn::Count => a.count.cmp(&b.count),
+            CatalogColumn::Total => a.total_duration.total_cmp(&b.total_duration),
+            CatalogColumn::Mean => a.mean_duration.total_cmp(&b.mean_duration),
+            CatalogColumn::P99 => a.p99_duration.total_cmp(&b.p99_duration),
+        };
+        let ordering = if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+        ordering.then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpanCatalogAction {
+    /// Highlight all spans with this name in the selected time window.
+    Highlight(String),
+    /// Open the span analysis for this name.
+    Analyze(String),
+    /// Open the child breakdown for this name.
+    Breakdown(String),
+}
+
+pub struct SpanCatalog {
+    show: bool,
+    /// All spans in the trace, including children.
+    spans: Vec<Rc<Span>>,
+    sort_column: CatalogColumn,
+    descending: bool,
+    filter: String,
+    /// Entries for the time window they were computed for, sorted by the current column.
+    entries: Option<((TimePoint, TimePoint), Vec<SpanCatalogEntry>)>,
+}
+
+impl Default for SpanCatalog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpanCatalog {
+    pub fn new() -> SpanCatalog {
+        SpanCatalog {
+            show: false,
+            spans: Vec::new(),
+            sort_column: CatalogColumn::Total,
+            descending: true,
+            filter: String::new(),
+            entries: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.show
+    }
+
+    pub fn toggle(&mut self) {
+        self.show = !self.show;
+    }
+
+    /// `spans` should contain all spans of the newly loaded trace, including children.
+    pub fn set_spans(&mut self, spans: Vec<Rc<Span>>) {
+        self.spans = spans;
+        self.entries = None;
+    }
+
+    /// Spans with the name in the time window.
+    pub fn spans_with_name(&self, name: &str, start: TimePoint, end: TimePoint) -> Vec<Rc<Span>> {
+        self.spans
+            .iter()
+            .filter(|s| s.original_name() == name && s.end_time >= start && s.start_time <= end)
+            .cloned()
+            .collect()
+    }
+
+    fn entries(&mut self, start: TimePoint, end: TimePoint) -> &[SpanCatalogEntry] {
+        if self
+            .entries
+            .as_ref()
+            .is_none_or(|(window, _)| *window != (start, end))
+        {
+            let mut entries = compute_span_catalog(&self.spans, start, end);
+            sort_span_catalog(&mut entries, self.sort_column, self.descending);
+            self.entries = Some(((start, end), entries));
+        }
+        &self.entries.as_ref().unwrap().1
+    }
+
+    /// Draws the panel for the selected time window, has to be called before the central panel
+    /// is shown.
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        start: TimePoint,
+        end: TimePoint,
+    ) -> Option<SpanCatalogAction> {
+        if !self.show {
+            return None;
+        }
+        let mut action = None;
+        egui::SidePanel::right("span catalog")
+            .resizable(true)
+            .default_width(450.0)
+            .show(ctx, |ui| action = self.draw_contents(ui, start, end));
+        action
+    }
+
+    fn draw_contents(
+        &mut self,
+        ui: &mut Ui,
+        start: TimePoint,
+        end: TimePoint,
+    ) -> Option<SpanCatalogAction> {
+        ui.horizontal(|ui| {
+            ui.strong("Span catalog");
+            ui.add(
+                TextEdit::singleline(&mut self.filter)
+                    .hint_text("Filter names")
+                    .desired_width(150.0),
+            );
+            if ui.button("Close").clicked() {
+                self.show = false;
+            }
+        });
+        ui.label(RichText::new("Spans in the selected time range. Click a header to sort.").weak());
+        ui.separator();
+
+        let mut clicked_column = None;
+        let mut action = None;
+        let sort_column = self.sort_column;
+        let descending = self.descending;
+        let filter = self.filter.to_lowercase();
+        let entries = self.entries(start, end);
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                Grid::new("span catalog grid").striped(true).show(ui, |ui| {
+                    for column in CatalogColumn::all() {
+                        let arrow = match (column == sort_column, descending) {
+                            (false, _) => "",
+                            (true, true) => " ⏷",
+                            (true, false) => " ⏶",
+                        };
+                        if ui
+                            .add(
+                                Button::new(
+                                    RichText::new(format!("{}{arrow}", column.title())).strong(),
+                                )
+                                .frame(false),
+                            )
+                            .clicked()
+                        {
+                            clicked_column = Some(column);
+                        }
+                    }
+                    ui.label("");
+                    ui.label("");
+                    ui.end_row();
+
+                    for entry in entries
+                        .iter()
+                        .filter(|e| filter.is_empty() || e.name.to_lowercase().contains(&filter))
+                    {
+                        if ui
+                            .link(&entry.name)
+                            .on_hover_text("Highlight all spans with this name")
+                            .clicked()
+                        {
+                            action = Some(SpanCatalogAction::Highlight(entry.name.clone()));
+                        }
+                        ui.monospace(entry.count.to_string());
+                        ui.monospace(format_ms(entry.total_duration));
+                        ui.monospace(format_ms(entry.mean_duration));
+                        ui.monospace(format_ms(entry.p99_duration));
+                        if ui
+                            .small_button("Analyze")
+                            .on_hover_text("Analyze the durations of spans with this name")
+                            .clicked()
+                        {
+                            action = Some(SpanCatalogAction::Analyze(entry.name.clone()));
+                        }
+                        if ui
+                            .small_button("Breakdown")
+                            .on_hover_text("Where spans with this name spend their time")
+                            .clicked()
+                        {
+                            action = Some(SpanCatalogAction::Breakdown(entry.name.clone()));
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+
+        if let Some(column) = clicked_column {
+            if column == self.sort_column {
+                self.descending = !self.descending;
+            } else {
+                self.sort_column = column;
+                // Names are easiest to read from A to Z, numbers from the largest.
+                self.descending = column != CatalogColumn::Name;
+            }
+            if let Some((_, entries)) = &mut self.entries {
+                sort_span_catalog(entries, self.sort_column, self.descending);
+            }
+        }
+        action
+    }
+}
@@ -0,0 +1,143 @@
+//! Interned span and trace ids.
+//! Ids are stored in sets and maps all over the place (highlights, arrow positions, caches), with
+//! `Vec<u8>` every insert was an allocation and every lookup hashed the whole id. [SpanId] is a
+//! small handle into a global table of ids, cheap to copy, compare and hash.
+//!
+//! The table is shared by all open traces, so the same id in two traces has the same handle. Ids of
+//! a trace which is unloaded are removed with [SpanId::forget], unless another open trace has them.
+//! Ids made up by display modes for synthetic spans are kept, they're the same every time a mode
+//! transforms the trace.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, LazyLock, RwLock};
+
+/// Handle to an interned id. Two handles are equal if and only if the ids are equal.
+/// Handles aren't ordered, the order in which ids are interned depends on the loaded traces.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpanId {
+    slot: u32,
+    /// Incremented every time the slot is reused, so that handles of forgotten ids never equal
+    /// handles of the ids interned after them.
+    generation: u32,
+}
+
+/// Trace ids are interned in the same table as span ids.
+pub type TraceId = SpanId;
+
+struct Slot {
+    id: Arc<[u8]>,
+    generation: u32,
+}
+
+struct Interner {
+    slots: Vec<Slot>,
+    handles: HashMap<Arc<[u8]>, SpanId>,
+    /// Slots of forgotten ids, they're reused for new ids.
+    free_slots: Vec<u32>,
+}
+
+static INTERNER: LazyLock<RwLock<Interner>> = LazyLock::new(|| {
+    let empty: Arc<[u8]> = Arc::from(&[][..]);
+    RwLock::new(Interner {
+        slots: vec![Slot {
+            id: empty.clone(),
+            generation: 0,
+        }],
+        handles: HashMap::from([(empty, SpanId::EMPTY)]),
+        free_slots: Vec::new(),
+    })
+});
+
+impl SpanId {
+    /// The empty id, used e.g. as the parent id of top level spans.
+    pub const EMPTY: SpanId = SpanId {
+        slot: 0,
+        generation: 0,
+    };
+
+    pub fn intern(bytes: &[u8]) -> SpanId {
+        if bytes.is_empty() {
+            return SpanId::EMPTY;
+        }
+        if let Some(handle) = SpanId::find(bytes) {
+            return handle;
+        }
+
+        let mut interner = INTERNER.write().unwrap();
+        // Another thread could have interned the id in the meantime.
+        if let Some(handle) = interner.handles.get(bytes) {
+            return *handle;
+        }
+        let id: Arc<[u8]> = Arc::from(bytes);
+        let handle = match interner.free_slots.pop() {
+            Some(slot) => {
+                let slot_entry = &mut interner.slots[slot as usize];
+                slot_entry.id = id.clone();
+                SpanId {
+                    slot,
+                    generation: slot_entry.generation,
+                }
+            }
+            None => {
+                let slot = interner.slots.len() as u32;
+                interner.slots.push(Slot {
+                    id: id.clone(),
+                    generation: 0,
+                });
+                SpanId {
+                    slot,
+                    generation: 0,
+                }
+            }
+        };
+        interner.handles.insert(id, handle);
+        handle
+    }
+
+    /// Handle of the id if it's interned.
+    pub fn find(bytes: &[u8]) -> Option<SpanId> {
+        INTERNER.read().unwrap().handles.get(bytes).copied()
+    }
+
+    /// Removes the ids from the table, their handles must not be used afterwards. A forgotten id
+    /// which is interned again gets a new handle.
+    pub fn forget(ids: impl IntoIterator<Item = SpanId>) {
+        let mut interner = INTERNER.write().unwrap();
+        for id in ids {
+            let slot = &mut interner.slots[id.slot as usize];
+            if id.is_empty() || slot.generation != id.generation {
+                continue;
+            }
+            let bytes = std::mem::replace(&mut slot.id, Arc::from(&[][..]));
+            slot.generation = slot.generation.wrapping_add(1);
+            interner.handles.remove(&bytes);
+            interner.free_slots.push(id.slot);
+        }
+    }
+
+    /// Bytes of the id, empty for a handle of a forgotten id.
+    pub fn bytes(self) -> Arc<[u8]> {
+        let interner = INTERNER.read().unwrap();
+        let slot = &interner.slots[self.slot as usize];
+        if slot.generation == self.generation {
+            slot.id.clone()
+        } else {
+            interner.slots[0].id.clone()
+        }
+    }
+
+    pub fn is_empty(self) -> bool {
+        self == SpanId::EMPTY
+    }
+
+    pub fn to_hex(self) -> String {
+        hex::encode(&*self.bytes())
+    }
+}
+
+impl fmt::Debug for SpanId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
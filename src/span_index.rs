@@ -0,0 +1,76 @@
+//! Index of the top level spans of a lane, used to find the spans visible in the selected range
+//! without scanning all spans of the lane every frame.
+
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::types::{Span, TimePoint};
+
+static NEXT_INDEX_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Spans sorted by `min_start_time`, together with the running maximum of `max_end_time`.
+/// The running maximum is non-decreasing, so both ends of the range of candidate spans can be
+/// found with a binary search. It uses the `min_start_time`/`max_end_time` of the spans at the time
+/// of building, the index has to be rebuilt when they change (i.e. when a display mode is applied).
+pub struct SpanIntervalIndex {
+    spans: Vec<Rc<Span>>,
+    max_end_prefix: Vec<TimePoint>,
+    /// Different for every built index.
+    id: u64,
+}
+
+impl SpanIntervalIndex {
+    pub fn new(mut spans: Vec<Rc<Span>>) -> SpanIntervalIndex {
+        spans.sort_by(|a, b| {
+            a.min_start_time
+                .get()
+                .total_cmp(&b.min_start_time.get())
+                .then(a.max_end_time.get().total_cmp(&b.max_end_time.get()))
+        });
+        let mut max_end_prefix = Vec::with_capacity(spans.len());
+        let mut max_end = TimePoint::NEG_INFINITY;
+        for span in &spans {
+            max_end = max_end.max(span.max_end_time.get());
+            max_end_prefix.push(max_end);
+        }
+        SpanIntervalIndex {
+            spans,
+            max_end_prefix,
+            id: NEXT_INDEX_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Identifies the contents of the index, values computed from the spans can be cached by it.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// All spans in the index, sorted by start time.
+    pub fn spans(&self) -> &[Rc<Span>] {
+        &self.spans
+    }
+
+    /// Spans whose `min_start_time..max_end_time` intersects `start..end`, sorted by start time.
+    pub fn query(&self, start: TimePoint, end: TimePoint) -> impl Iterator<Item = &Rc<Span>> {
+        // Spans before `first` end before `start`, spans from `last` on start after `end`.
+        let first = self
+            .max_end_prefix
+            .partition_point(|max_end| *max_end < start);
+        let last = self
+            .spans
+            .partition_point(|span| span.min_start_time.get() <= end);
+        // A long span early in the lane keeps the running maximum high, so some of the candidates
+        // may still end before `start`.
+        self.spans[first..last.max(first)]
+            .iter()
+            .filter(move |span| span.max_end_time.get() >= start)
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+}
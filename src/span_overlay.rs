@@ -0,0 +1,315 @@
+//! Span overlay - all instances of a span name aligned at t=0 and drawn on top of each other,
+//! semi-transparently and with their children. Structure shared by most instances shows up as
+//! solid bars, while the divergent ones stay faint.
+
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use eframe::egui::{self, Key, Modal, Pos2, Rect, RichText, Sense, Stroke, Ui, Vec2};
+
+use crate::analyze_utils::{
+    process_spans_for_analysis, span_search_ui, span_selection_list_ui, Statistics,
+};
+use crate::colors::{node_color, theme};
+use crate::types::{format_ms, Span};
+
+/// More instances don't make the overlay any clearer, only slower to draw.
+pub const MAX_OVERLAY_INSTANCES: usize = 1000;
+/// Children deeper than this under the instance aren't drawn.
+pub const MAX_OVERLAY_DEPTH: usize = 8;
+
+/// One span of one instance, with times relative to the start of the instance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverlayBar {
+    pub name: String,
+    /// 0 for the instance itself.
+    pub depth: usize,
+    /// Seconds
+    pub start: f64,
+    /// Seconds
+    pub end: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpanOverlay {
+    pub span_name: String,
+    /// Number of overlaid instances.
+    pub instances: usize,
+    /// Number of all spans with the name, the first `MAX_OVERLAY_INSTANCES` are overlaid.
+    pub total_instances: usize,
+    /// Duration of each overlaid instance, in seconds.
+    pub durations: Vec<f64>,
+    /// Seconds, the end of the latest bar.
+    pub max_end: f64,
+    pub max_depth: usize,
+    pub bars: Vec<OverlayBar>,
+}
+
+impl SpanOverlay {
+    /// Names of the bars at `depth` which cover `time`, with the number of such bars, the most
+    /// common first.
+    pub fn names_at(&self, depth: usize, time: f64) -> Vec<(String, usize)> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for bar in &self.bars {
+            if bar.depth == depth && bar.start <= time && time <= bar.end {
+                *counts.entry(bar.name.as_str()).or_default() += 1;
+            }
+        }
+        let mut names: Vec<(String, usize)> = counts
+            .into_iter()
+            .map(|(name, count)| (name.to_string(), count))
+            .collect();
+        names.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        names
+    }
+
+    /// Names of all bars, each with the number of bars.
+    pub fn name_counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for bar in &self.bars {
+            *counts.entry(bar.name.as_str()).or_default() += 1;
+        }
+        counts
+    }
+}
+
+fn collect_bars(span: &Rc<Span>, instance_start: f64, depth: usize, bars: &mut Vec<OverlayBar>) {
+    bars.push(OverlayBar {
+        name: span.original_name().to_string(),
+        depth,
+        start: span.start_time - instance_start,
+        end: span.end_time - instance_start,
+    });
+    if depth < MAX_OVERLAY_DEPTH {
+        for child in span.children().iter() {
+            collect_bars(child, instance_start, depth + 1, bars);
+        }
+    }
+}
+
+/// Overlay of the spans named `span_name` among `spans` (all spans including children), `None`
+/// when there are no such spans. The earliest instances are used when there are too many.
+pub fn compute_span_overlay(spans: &[Rc<Span>], span_name: &str) -> Option<SpanOverlay> {
+    let mut instances: Vec<&Rc<Span>> = spans
+        .iter()
+        .filter(|s| s.original_name() == span_name)
+        .collect();
+    if instances.is_empty() {
+        return None;
+    }
+    let total_instances = instances.len();
+    instances.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    instances.truncate(MAX_OVERLAY_INSTANCES);
+
+    let mut bars = Vec::new();
+    for span in &instances {
+        collect_bars(span, span.start_time, 0, &mut bars);
+    }
+    Some(SpanOverlay {
+        span_name: span_name.to_string(),
+        instances: instances.len(),
+        total_instances,
+        durations: instances
+            .iter()
+            .map(|s| s.end_time - s.start_time)
+            .collect(),
+        max_end: bars.iter().map(|bar| bar.end).fold(0.0, f64::max),
+        max_depth: bars.iter().map(|bar| bar.depth).max().unwrap_or(0),
+        bars,
+    })
+}
+
+pub struct SpanOverlayModal {
+    pub show: bool,
+    all_spans: Vec<Rc<Span>>,
+    unique_span_names: Vec<String>,
+    search_text: String,
+    selected_span_name: Option<String>,
+    overlay: Option<SpanOverlay>,
+}
+
+impl Default for SpanOverlayModal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpanOverlayModal {
+    pub fn new() -> SpanOverlayModal {
+        SpanOverlayModal {
+            show: false,
+            all_spans: Vec::new(),
+            unique_span_names: Vec::new(),
+            search_text: String::new(),
+            selected_span_name: None,
+            overlay: None,
+        }
+    }
+
+    pub fn open(&mut self, spans_for_analysis: &[Rc<Span>]) {
+        let (all_spans, unique_span_names) = process_spans_for_analysis(spans_for_analysis);
+        self.all_spans = all_spans;
+        self.unique_span_names = unique_span_names;
+        self.show = true;
+    }
+
+    /// Opens the modal with the overlay of `span_name` already computed.
+    pub fn open_for_span_name(&mut self, spans_for_analysis: &[Rc<Span>], span_name: &str) {
+        self.open(spans_for_analysis);
+        self.search_text = span_name.to_string();
+        self.select(span_name.to_string());
+    }
+
+    fn select(&mut self, span_name: String) {
+        self.overlay = compute_span_overlay(&self.all_spans, &span_name);
+        self.selected_span_name = Some(span_name);
+    }
+
+    pub fn show_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        if !self.show {
+            return;
+        }
+
+        Modal::new("span overlay".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Span overlay");
+            ui.label(
+                "All spans with this name aligned at their start and drawn on top of each other. \
+                 Solid bars are common to most of them, faint ones to only a few.",
+            );
+            ui.separator();
+
+            ui.horizontal_top(|ui| {
+                ui.vertical(|ui| {
+                    ui.set_width(250.0);
+                    span_search_ui(ui, &mut self.search_text, "Span name", "Search", 240.0);
+                    if span_selection_list_ui(
+                        ui,
+                        &self.unique_span_names,
+                        &self.search_text,
+                        &mut self.selected_span_name,
+                        max_height - 200.0,
+                        "span overlay span names",
+                    ) {
+                        if let Some(name) = self.selected_span_name.clone() {
+                            self.select(name);
+                        }
+                    }
+                });
+                ui.separator();
+                ui.vertical(|ui| match &self.overlay {
+                    Some(overlay) => draw_overlay(ui, overlay, max_width - 300.0),
+                    None if self.selected_span_name.is_some() => {
+                        ui.label("No spans with this name.");
+                    }
+                    None => {
+                        ui.label("Choose a span name.");
+                    }
+                });
+            });
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(Key::Escape)) {
+                self.show = false;
+            }
+        });
+    }
+}
+
+const OVERLAY_ROW_HEIGHT: f32 = 18.0;
+const DURATION_STRIP_HEIGHT: f32 = 14.0;
+
+/// Opacity of a single bar. All instances together make a bar common to all of them solid.
+fn bar_opacity(instances: usize) -> f32 {
+    (3.0 / instances as f32).clamp(0.02, 0.8)
+}
+
+fn draw_overlay(ui: &mut Ui, overlay: &SpanOverlay, width: f32) {
+    let mut durations = Statistics::new();
+    for duration in &overlay.durations {
+        durations.add_value(*duration);
+    }
+    let instances_text = if overlay.instances < overlay.total_instances {
+        format!(
+            "the first {} of {} instances",
+            overlay.instances, overlay.total_instances
+        )
+    } else {
+        format!("{} instances", overlay.instances)
+    };
+    ui.label(format!(
+        "{} of {}, median duration {}, max {}",
+        instances_text,
+        overlay.span_name,
+        format_ms(durations.median()),
+        format_ms(durations.max)
+    ));
+
+    let height = DURATION_STRIP_HEIGHT + (overlay.max_depth + 1) as f32 * OVERLAY_ROW_HEIGHT;
+    let (rect, response) = ui.allocate_exact_size(Vec2::new(width, height), Sense::hover());
+    ui.painter()
+        .rect_filled(rect, 0.0, theme().chart_background);
+    let max_end = overlay.max_end.max(f64::MIN_POSITIVE);
+    let to_x = |time: f64| rect.min.x + (time / max_end) as f32 * rect.width();
+    let rows_top = rect.min.y + DURATION_STRIP_HEIGHT;
+
+    // The end of every instance as a tick, long outliers stand out on the right.
+    for duration in &overlay.durations {
+        let x = to_x(*duration);
+        ui.painter().vline(
+            x,
+            rect.min.y..=rows_top - 2.0,
+            Stroke::new(1.0, theme().chart_line.gamma_multiply(0.5)),
+        );
+    }
+    ui.painter().vline(
+        to_x(durations.median()),
+        rect.min.y..=rect.max.y,
+        Stroke::new(1.0, theme().chart_axis),
+    );
+
+    let opacity = bar_opacity(overlay.instances);
+    for bar in &overlay.bars {
+        let top = rows_top + bar.depth as f32 * OVERLAY_ROW_HEIGHT;
+        let bar_rect = Rect::from_min_max(
+            Pos2::new(to_x(bar.start), top + 1.0),
+            Pos2::new(
+                to_x(bar.end).max(to_x(bar.start) + 1.0),
+                top + OVERLAY_ROW_HEIGHT - 1.0,
+            ),
+        );
+        ui.painter()
+            .rect_filled(bar_rect, 0.0, node_color(&bar.name).gamma_multiply(opacity));
+    }
+
+    if let Some(pos) = response.hover_pos() {
+        let time = (pos.x - rect.min.x) as f64 / rect.width() as f64 * max_end;
+        let hovered_depth = ((pos.y - rows_top) / OVERLAY_ROW_HEIGHT).floor();
+        response.on_hover_ui_at_pointer(|ui| {
+            ui.label(format!("t = {}", format_ms(time)));
+            if hovered_depth < 0.0 {
+                let ended = overlay.durations.iter().filter(|d| **d <= time).count();
+                ui.label(format!(
+                    "{ended} of {} instances ended by now",
+                    overlay.instances
+                ));
+                return;
+            }
+            for (name, count) in overlay.names_at(hovered_depth as usize, time) {
+                ui.label(format!(
+                    "{name}: {count} of {} instances",
+                    overlay.instances
+                ));
+            }
+        });
+    }
+
+    ui.add_space(5.0);
+    ui.horizontal_wrapped(|ui| {
+        for (name, count) in overlay.name_counts() {
+            ui.label(RichText::new(format!("■ {name} ({count})")).color(node_color(name)));
+        }
+    });
+}
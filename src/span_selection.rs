@@ -0,0 +1,270 @@
+//! Selection of multiple spans in the span area, made with Ctrl+click or by dragging a rectangle
+//! over the background. Aggregate stats of the selection are shown in a small panel, which also has
+//! buttons to highlight, pin or export the selected spans.
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use eframe::egui::{self, Align2, Grid, Rect, Vec2};
+
+use crate::span_id::SpanId;
+use crate::types::{Span, TimeFormat, TimePoint, MILLISECONDS_PER_SECOND};
+
+#[derive(Default)]
+pub struct SpanSelection {
+    spans: Vec<Rc<Span>>,
+    span_ids: HashSet<SpanId>,
+}
+
+impl SpanSelection {
+    pub fn new() -> SpanSelection {
+        SpanSelection::default()
+    }
+
+    /// Adds the span to the selection, or removes it if it's already selected.
+    pub fn toggle(&mut self, span: &Rc<Span>) {
+        if self.span_ids.remove(&span.span_id) {
+            self.spans.retain(|s| s.span_id != span.span_id);
+        } else {
+            self.span_ids.insert(span.span_id);
+            self.spans.push(span.clone());
+        }
+    }
+
+    /// Adds spans which aren't selected yet.
+    pub fn extend(&mut self, spans: impl IntoIterator<Item = Rc<Span>>) {
+        for span in spans {
+            if self.span_ids.insert(span.span_id) {
+                self.spans.push(span);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.spans.clear();
+        self.span_ids.clear();
+    }
+
+    /// Ids of the selected spans, in the order in which they were selected.
+    pub fn span_ids(&self) -> Vec<SpanId> {
+        self.spans.iter().map(|span| span.span_id).collect()
+    }
+
+    /// Selects the spans with the ids which are in `spans_by_id`, e.g. after the spans were built
+    /// again from a reloaded trace. The rest is deselected.
+    pub fn select_ids(&mut self, span_ids: &[SpanId], spans_by_id: &HashMap<SpanId, Rc<Span>>) {
+        self.clear();
+        self.extend(
+            span_ids
+                .iter()
+                .filter_map(|id| spans_by_id.get(id).cloned()),
+        );
+    }
+
+    pub fn contains(&self, span_id: SpanId) -> bool {
+        self.span_ids.contains(&span_id)
+    }
+
+    /// Selected spans, in the order in which they were selected.
+    pub fn spans(&self) -> &[Rc<Span>] {
+        &self.spans
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn stats(&self) -> Option<SelectionStats> {
+        SelectionStats::calculate(&self.spans)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectionStats {
+    pub count: usize,
+    /// Sum of durations of all selected spans, in milliseconds.
+    pub total_duration: f64,
+    /// Mean span duration, in milliseconds.
+    pub mean_duration: f64,
+    /// The earliest start and the latest end of the selected spans.
+    pub start_time: TimePoint,
+    pub end_time: TimePoint,
+}
+
+impl SelectionStats {
+    pub fn calculate(spans: &[Rc<Span>]) -> Option<SelectionStats> {
+        if spans.is_empty() {
+            return None;
+        }
+        let total_duration: f64 = spans
+            .iter()
+            .map(|s| (s.end_time - s.start_time) * MILLISECONDS_PER_SECOND)
+            .sum();
+        Some(SelectionStats {
+            count: spans.len(),
+            total_duration,
+            mean_duration: total_duration / spans.len() as f64,
+            start_time: spans.iter().map(|s| s.start_time).fold(f64::MAX, f64::min),
+            end_time: spans.iter().map(|s| s.end_time).fold(f64::MIN, f64::max),
+        })
+    }
+
+    /// Time between the earliest start and the latest end, in milliseconds.
+    pub fn extent(&self) -> f64 {
+        (self.end_time - self.start_time) * MILLISECONDS_PER_SECOND
+    }
+}
+
+/// Spans with one of the `span_ids` in the trees of `spans`, by their ids. Lazy children aren't
+/// transformed.
+pub fn find_spans_by_id(
+    spans: &[Rc<Span>],
+    span_ids: &HashSet<SpanId>,
+) -> HashMap<SpanId, Rc<Span>> {
+    fn find(spans: &[Rc<Span>], span_ids: &HashSet<SpanId>, found: &mut HashMap<SpanId, Rc<Span>>) {
+        for span in spans {
+            if span_ids.contains(&span.span_id) {
+                found.insert(span.span_id, span.clone());
+            }
+            find(&span.transformed_children(), span_ids, found);
+        }
+    }
+
+    let mut found = HashMap::new();
+    if !span_ids.is_empty() {
+        find(spans, span_ids, &mut found);
+    }
+    found
+}
+
+/// Spans whose drawn rectangles intersect with `rect`.
+pub fn spans_in_rect(drawn_spans: &[(Rect, Rc<Span>)], rect: Rect) -> Vec<Rc<Span>> {
+    drawn_spans
+        .iter()
+        .filter(|(span_rect, _)| span_rect.intersects(rect))
+        .map(|(_, span)| span.clone())
+        .collect()
+}
+
+/// The selected spans in CSV format, one span per row.
+pub fn selection_to_csv(spans: &[Rc<Span>]) -> String {
+    let mut csv = "name,node,start_time,end_time,duration_ms,span_id,trace_id\n".to_string();
+    for span in spans {
+        csv.push_str(&format!(
+            "{},{},{:.9},{:.9},{:.3},{},{}\n",
+            csv_field(&span.name),
+            csv_field(&span.node.name),
+            span.start_time,
+            span.end_time,
+            (span.end_time - span.start_time) * MILLISECONDS_PER_SECOND,
+            span.span_id.to_hex(),
+            span.trace_id.to_hex()
+        ));
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub enum SelectionAction {
+    Highlight,
+    Pin,
+    UnpinAll,
+    Export,
+    Clear,
+}
+
+/// Small panel in the bottom left corner with stats of the selection.
+/// It's also shown when nothing is selected but some spans are pinned, so that they can be unpinned.
+pub fn draw_selection_panel(
+    ctx: &egui::Context,
+    selection: &SpanSelection,
+    pinned_count: usize,
+    time_format: &TimeFormat,
+) -> Option<SelectionAction> {
+    let stats = selection.stats();
+    if stats.is_none() && pinned_count == 0 {
+        return None;
+    }
+    let has_selection = stats.is_some();
+    let mut action = None;
+    egui::Window::new("Selection")
+        .anchor(Align2::LEFT_BOTTOM, Vec2::new(10.0, -10.0))
+        .resizable(false)
+        .collapsible(true)
+        .show(ctx, |ui| {
+            Grid::new("selection stats").num_columns(2).show(ui, |ui| {
+                ui.label("Pinned spans:");
+                ui.label(pinned_count.to_string());
+                ui.end_row();
+                let Some(stats) = &stats else {
+                    return;
+                };
+                ui.label("Selected spans:");
+                ui.label(stats.count.to_string());
+                ui.end_row();
+                ui.label("Total duration:");
+                ui.label(format!("{:.3} ms", stats.total_duration));
+                ui.end_row();
+                ui.label("Mean duration:");
+                ui.label(format!("{:.3} ms", stats.mean_duration));
+                ui.end_row();
+                ui.label("Time extent:");
+                ui.label(format!("{:.3} ms", stats.extent()));
+                ui.end_row();
+                ui.label("From:");
+                ui.label(time_format.display_string(stats.start_time));
+                ui.end_row();
+                ui.label("To:");
+                ui.label(time_format.display_string(stats.end_time));
+                ui.end_row();
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(has_selection, egui::Button::new("Highlight"))
+                    .on_hover_text("Replace highlighted spans with the selection")
+                    .clicked()
+                {
+                    action = Some(SelectionAction::Highlight);
+                }
+                if ui
+                    .add_enabled(has_selection, egui::Button::new("Pin"))
+                    .on_hover_text("Keep the selected spans marked after the selection is cleared")
+                    .clicked()
+                {
+                    action = Some(SelectionAction::Pin);
+                }
+                if ui
+                    .add_enabled(pinned_count > 0, egui::Button::new("Unpin all"))
+                    .clicked()
+                {
+                    action = Some(SelectionAction::UnpinAll);
+                }
+                if ui
+                    .add_enabled(has_selection, egui::Button::new("Export"))
+                    .on_hover_text("Save the selected spans to a CSV file")
+                    .clicked()
+                {
+                    action = Some(SelectionAction::Export);
+                }
+                if ui
+                    .add_enabled(has_selection, egui::Button::new("Clear"))
+                    .clicked()
+                {
+                    action = Some(SelectionAction::Clear);
+                }
+            });
+        });
+    action
+}
@@ -0,0 +1,50 @@
+//! Header of a node lane which stays at the top of the viewport while scrolling inside the lane.
+//! In lanes with hundreds of rows the node name, drawn in the middle of the lane, is easily lost.
+
+/// What is pinned at the top of the viewport when the top of a lane is scrolled out of view.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum StickyLaneHeader {
+    Off,
+    /// Only the name of the node (or span name) in the lane names column.
+    #[default]
+    NodeName,
+    /// The name and the first row of the lane's top level spans.
+    NodeNameAndTopLevelSpans,
+}
+
+impl StickyLaneHeader {
+    pub fn all() -> [StickyLaneHeader; 3] {
+        [
+            StickyLaneHeader::Off,
+            StickyLaneHeader::NodeName,
+            StickyLaneHeader::NodeNameAndTopLevelSpans,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StickyLaneHeader::Off => "Off",
+            StickyLaneHeader::NodeName => "Node name",
+            StickyLaneHeader::NodeNameAndTopLevelSpans => "Node name and top level spans",
+        }
+    }
+
+    pub fn pins_top_level_spans(&self) -> bool {
+        *self == StickyLaneHeader::NodeNameAndTopLevelSpans
+    }
+}
+
+/// Screen y of the pinned header of a lane spanning `lane_top..lane_bottom`, `None` when the top of
+/// the lane is visible or the lane is scrolled out of view. Near the end of the lane the header is
+/// pushed up by the lane's bottom edge, so it never covers the next lane.
+pub fn sticky_header_top(
+    lane_top: f32,
+    lane_bottom: f32,
+    viewport_top: f32,
+    header_height: f32,
+) -> Option<f32> {
+    if lane_top >= viewport_top || lane_bottom <= viewport_top {
+        return None;
+    }
+    Some(viewport_top.min(lane_bottom - header_height))
+}
@@ -4,7 +4,18 @@
 //! whether a rule matches a particular span, and if it does then the decision specifies how to
 //! display the span in this mode.
 
-use crate::types::{value_to_text, DisplayLength, Span};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use opentelemetry_proto::tonic::common::v1::any_value::Value;
+use regex::Regex;
+
+use crate::span_id::SpanId;
+use crate::types::{
+    time_point_from_unix_nano, value_to_text, DisplayLength, Span, SpanData, TimePoint,
+};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct StructuredMode {
@@ -13,10 +24,76 @@ pub struct StructuredMode {
     /// For each span, the first rule that matches the span will be used to determine how to display it.
     /// If no rule matches, the span will not be visible.
     pub span_rules: Vec<SpanRule>,
+    /// Displayed spans matching one of these rules are merged into segmented spans, the first
+    /// matching rule is used.
+    #[serde(default)]
+    pub grouping_rules: Vec<GroupingRule>,
+    /// Displayed spans matching one of these rules get a new label, the first matching rule is
+    /// used. The original name is kept for analysis.
+    #[serde(default)]
+    pub rename_rules: Vec<RenameRule>,
+    /// Spans created from events or attributes of the spans matching a rule, e.g. phases which are
+    /// recorded as events. They are added as children and displayed like other spans.
+    #[serde(default)]
+    pub synthetic_span_rules: Vec<SyntheticSpanRule>,
+    /// How spans are divided into lanes.
+    #[serde(default)]
+    pub lane_layout: LaneLayout,
+    /// Draw tick marks inside of spans at the times of their events.
+    #[serde(default)]
+    pub show_event_markers: bool,
+    /// When not empty, each lane is split into sub-lanes by the value of this span attribute (e.g.
+    /// `shard_id`). Spans without the attribute stay in the main lane.
+    #[serde(default)]
+    pub sub_lane_attribute: String,
     /// Built-in modes (chain, everything, etc.) are not editable and are not saved in persistent data.
     pub is_builtin: bool,
 }
 
+/// Decides which lane a span is displayed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LaneLayout {
+    /// One lane per node, the default.
+    #[default]
+    PerNode,
+    /// One lane per span name ("swimlane per operation"), the node is shown using the span's color.
+    /// Makes it easy to compare the same operation across nodes.
+    PerSpanName,
+}
+
+impl LaneLayout {
+    pub fn all() -> [LaneLayout; 2] {
+        [LaneLayout::PerNode, LaneLayout::PerSpanName]
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            LaneLayout::PerNode => "Lane per node",
+            LaneLayout::PerSpanName => "Lane per span name",
+        }
+    }
+
+    /// Name of the lane in which the span should be displayed.
+    pub fn lane_name(&self, span: &Span) -> String {
+        match self {
+            LaneLayout::PerNode => span.node.name.clone(),
+            LaneLayout::PerSpanName => span.original_name.clone(),
+        }
+    }
+}
+
+/// Name of the sub-lane for `span`, e.g. "node0 shard_id=3". Returns the lane name if the span
+/// doesn't have the attribute.
+pub fn sub_lane_name(lane_name: String, attribute: &str, span: &Span) -> String {
+    if attribute.is_empty() {
+        return lane_name;
+    }
+    match span.attributes.get(attribute) {
+        Some(value) => format!("{lane_name} {attribute}={}", value_to_text(value)),
+        None => lane_name,
+    }
+}
+
 /// A rule that defines how to display a span that matches the selector.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct SpanRule {
@@ -27,8 +104,171 @@ pub struct SpanRule {
     pub decision: SpanDecision,
 }
 
+/// Merges the spans matched by the selector into one span with active segments, like
+/// [SpanDecision::group], but only spans that follow each other closely. Spans are grouped per node
+/// and span name.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GroupingRule {
+    pub name: String,
+    pub selector: SpanSelector,
+    /// A span which starts more than this many seconds after the end of the previous span starts a
+    /// new group.
+    pub max_gap: TimePoint,
+    /// Only spans with the same value of this attribute (e.g. `height`) are grouped together.
+    /// Ignored when empty.
+    pub group_by_attribute: String,
+}
+
+/// Relabels the displayed spans matched by the selector, e.g. to shorten long span names which
+/// don't fit into narrow spans.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RenameRule {
+    pub selector: SpanSelector,
+    /// The new label. `{name}` is replaced with the label given by the span rule and `{attribute}`
+    /// with the value of the span attribute, e.g. `PECF` or `{name} {shard_id}`.
+    pub label: String,
+}
+
+impl RenameRule {
+    /// Label of `span` with this rule. Placeholders of attributes which the span doesn't have are
+    /// left out.
+    pub fn apply(&self, span: &Span) -> String {
+        let mut label = String::new();
+        let mut rest = self.label.as_str();
+        while let Some(open) = rest.find('{') {
+            let Some(close) = rest[open..].find('}').map(|close| open + close) else {
+                break;
+            };
+            label.push_str(&rest[..open]);
+            match &rest[open + 1..close] {
+                "name" => label.push_str(&span.name),
+                attribute => {
+                    if let Some(value) = span.attributes.get(attribute) {
+                        label.push_str(&value_to_text(value));
+                    }
+                }
+            }
+            rest = &rest[close + 1..];
+        }
+        label.push_str(rest);
+        label.trim().to_string()
+    }
+}
+
+/// Creates child spans of the spans matched by the selector, for phases of the span which are
+/// recorded as a pair of events or attributes instead of a span.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SyntheticSpanRule {
+    /// Name of the created spans.
+    pub name: String,
+    pub selector: SpanSelector,
+    pub source: SyntheticSpanSource,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum SyntheticSpanSource {
+    /// A child span from each start event to the next end event. Start events without an end
+    /// event are ignored.
+    Events {
+        start_event: String,
+        end_event: String,
+    },
+    /// One child span between two timestamps (unix time in nanoseconds) in the span's attributes.
+    Attributes {
+        start_attribute: String,
+        end_attribute: String,
+    },
+}
+
+impl SyntheticSpanSource {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            SyntheticSpanSource::Events { .. } => "Pair of events",
+            SyntheticSpanSource::Attributes { .. } => "Pair of attributes",
+        }
+    }
+}
+
+impl SyntheticSpanRule {
+    /// Data of the spans which this rule creates under `span`, the span has to match the selector.
+    /// The ids are derived from the parent's id, so the same spans get the same ids every time.
+    pub fn synthesize(&self, rule_index: usize, span: &SpanData) -> Vec<Arc<SpanData>> {
+        let mut ranges = Vec::new();
+        let mut attributes = Vec::new();
+        match &self.source {
+            SyntheticSpanSource::Events {
+                start_event,
+                end_event,
+            } => {
+                let mut started = VecDeque::new();
+                for event in &span.events {
+                    if event.name == *start_event {
+                        started.push_back(event);
+                    } else if event.name == *end_event {
+                        if let Some(start) = started.pop_front() {
+                            ranges.push((start.time, event.time));
+                            let mut merged = start.attributes.clone();
+                            merged.extend(event.attributes.clone());
+                            attributes.push(merged);
+                        }
+                    }
+                }
+            }
+            SyntheticSpanSource::Attributes {
+                start_attribute,
+                end_attribute,
+            } => {
+                let time = |name: &String| match span.attributes.get(name) {
+                    Some(Some(Value::IntValue(nanos))) => {
+                        u64::try_from(*nanos).ok().map(time_point_from_unix_nano)
+                    }
+                    _ => None,
+                };
+                if let (Some(start), Some(end)) = (time(start_attribute), time(end_attribute)) {
+                    ranges.push((start, end));
+                    attributes.push(BTreeMap::new());
+                }
+            }
+        }
+
+        ranges
+            .into_iter()
+            .zip(attributes)
+            .filter(|((start, end), _)| start <= end)
+            .enumerate()
+            .map(|(i, ((start_time, end_time), attributes))| {
+                let mut id = span.span_id.bytes().to_vec();
+                id.extend(b"synthetic");
+                id.extend((rule_index as u64).to_le_bytes());
+                id.extend((i as u64).to_le_bytes());
+                Arc::new(SpanData {
+                    original_name: self.name.clone(),
+                    span_id: SpanId::intern(&id),
+                    trace_id: span.trace_id,
+                    parent_span_id: span.span_id,
+                    start_time,
+                    end_time,
+                    attributes,
+                    events: vec![],
+                    node: span.node.clone(),
+                    scope: span.scope.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Attribute name of a newly added attribute condition, until the user fills in a real one.
+pub const ATTRIBUTE_NAME_PLACEHOLDER: &str = "<attribute name>";
+
+/// Attribute conditions with an empty or placeholder name were probably added accidentally, it
+/// doesn't make much sense to enforce them.
+pub fn is_unfilled_attribute_name(attr_name: &str) -> bool {
+    attr_name.is_empty() || attr_name == ATTRIBUTE_NAME_PLACEHOLDER
+}
+
 /// A selector used to determine whether a span matches a rule.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct SpanSelector {
     /// Span's name must match this condition
     pub span_name_condition: MatchCondition,
@@ -57,7 +297,7 @@ pub struct SpanDecision {
     pub group: bool,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct MatchCondition {
     pub operator: MatchOperator,
     pub value: String,
@@ -75,10 +315,67 @@ pub enum MatchOperator {
     NotEqualTo,
     /// Matches if the value contains the given substring
     Contains,
+    /// Matches if the given regular expression matches some part of the value
+    Regex,
+    /// Matches if both the value and the given string are numbers and the value is greater
+    GreaterThan,
+    /// Matches if both the value and the given string are numbers and the value is smaller
+    LessThan,
+    /// Matches if the value is a number within the given inclusive range, written as `min..max`
+    InRange,
+    /// Matches if the attribute is present, with any value
+    Exists,
+    /// Matches if the attribute is not present
+    NotExists,
+}
+
+impl MatchOperator {
+    pub fn all() -> [MatchOperator; 11] {
+        [
+            MatchOperator::Any,
+            MatchOperator::None,
+            MatchOperator::EqualTo,
+            MatchOperator::NotEqualTo,
+            MatchOperator::Contains,
+            MatchOperator::Regex,
+            MatchOperator::GreaterThan,
+            MatchOperator::LessThan,
+            MatchOperator::InRange,
+            MatchOperator::Exists,
+            MatchOperator::NotExists,
+        ]
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            MatchOperator::Any => "Any",
+            MatchOperator::None => "None",
+            MatchOperator::EqualTo => "Equal To",
+            MatchOperator::NotEqualTo => "Not Equal To",
+            MatchOperator::Contains => "Contains",
+            MatchOperator::Regex => "Regex",
+            MatchOperator::GreaterThan => "Greater Than",
+            MatchOperator::LessThan => "Less Than",
+            MatchOperator::InRange => "In Range",
+            MatchOperator::Exists => "Exists",
+            MatchOperator::NotExists => "Doesn't Exist",
+        }
+    }
+
+    /// Whether the operator compares the value with the condition's value.
+    pub fn uses_value(&self) -> bool {
+        !matches!(
+            self,
+            MatchOperator::Any
+                | MatchOperator::None
+                | MatchOperator::Exists
+                | MatchOperator::NotExists
+        )
+    }
 }
 
 impl SpanSelector {
-    pub fn matches(&self, span: &Span) -> bool {
+    pub fn matches(&self, span: &SpanData) -> bool {
         if !self.span_name_condition.matches(span.original_name()) {
             return false;
         }
@@ -88,9 +385,7 @@ impl SpanSelector {
         }
 
         for (attr_name, attr_condition) in &self.attribute_conditions {
-            if attr_name.is_empty() || attr_name == "<attribute name>" {
-                // Ignore empty attribute conditions that have an empty or default name.
-                // They were probably added accidentally, it doesn't make much sense to enforce them.
+            if is_unfilled_attribute_name(attr_name) {
                 continue;
             }
 
@@ -98,7 +393,7 @@ impl SpanSelector {
                 if !attr_condition.matches(&value_to_text(attr_value)) {
                     return false;
                 }
-            } else {
+            } else if !attr_condition.matches_missing() {
                 return false;
             }
         }
@@ -151,11 +446,123 @@ impl MatchCondition {
             MatchOperator::EqualTo => value == self.value,
             MatchOperator::NotEqualTo => value != self.value,
             MatchOperator::Contains => value.contains(self.value.as_str()),
+            MatchOperator::Regex => with_cached_regex(&self.value, |regex| {
+                regex.is_some_and(|r| r.is_match(value))
+            }),
+            MatchOperator::GreaterThan => {
+                matches!((parse_number(value), parse_number(&self.value)), (Some(v), Some(limit)) if v > limit)
+            }
+            MatchOperator::LessThan => {
+                matches!((parse_number(value), parse_number(&self.value)), (Some(v), Some(limit)) if v < limit)
+            }
+            MatchOperator::InRange => match (parse_number(value), parse_range(&self.value)) {
+                (Some(v), Some((min, max))) => min <= v && v <= max,
+                _ => false,
+            },
+            MatchOperator::Exists => true,
+            MatchOperator::NotExists => false,
         }
     }
+
+    /// Whether the condition matches an attribute which isn't present.
+    pub fn matches_missing(&self) -> bool {
+        self.operator == MatchOperator::NotExists
+    }
+
+    /// Describes why the condition's value can't be used with the operator, e.g. an invalid regex.
+    pub fn validation_error(&self) -> Option<String> {
+        match self.operator {
+            MatchOperator::Regex => Regex::new(&self.value).err().map(|e| e.to_string()),
+            MatchOperator::GreaterThan | MatchOperator::LessThan => parse_number(&self.value)
+                .is_none()
+                .then(|| "The value must be a number".to_string()),
+            MatchOperator::InRange => parse_range(&self.value)
+                .is_none()
+                .then(|| "The value must be a range of numbers, e.g. 10..20".to_string()),
+            _ => None,
+        }
+    }
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    value.trim().parse::<f64>().ok()
+}
+
+/// Parses an inclusive range written as `min..max`.
+fn parse_range(value: &str) -> Option<(f64, f64)> {
+    let (min, max) = value.split_once("..")?;
+    Some((parse_number(min)?, parse_number(max)?))
+}
+
+/// Number of compiled regexes kept in [REGEX_CACHE]. Patterns typed into the UI change with every
+/// key press, only the recently used ones are worth keeping.
+const REGEX_CACHE_SIZE: usize = 32;
+
+thread_local! {
+    /// Compiled regexes of conditions, conditions are matched against many spans and compiling
+    /// the regex every time would be too slow. `None` for patterns which aren't valid. The most
+    /// recently used pattern is first, the least recently used one is evicted when it's full.
+    static REGEX_CACHE: RefCell<VecDeque<(String, Option<Regex>)>> =
+        RefCell::new(VecDeque::with_capacity(REGEX_CACHE_SIZE));
+}
+
+fn with_cached_regex<T>(pattern: &str, f: impl FnOnce(Option<&Regex>) -> T) -> T {
+    REGEX_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        match cache.iter().position(|(cached, _)| cached == pattern) {
+            Some(0) => {}
+            Some(index) => {
+                let entry = cache.remove(index).unwrap();
+                cache.push_front(entry);
+            }
+            None => {
+                cache.truncate(REGEX_CACHE_SIZE - 1);
+                cache.push_front((pattern.to_string(), Regex::new(pattern).ok()));
+            }
+        }
+        f(cache[0].1.as_ref())
+    })
 }
 
 impl StructuredMode {
+    /// Name of the lane (or sub-lane) in which the span is displayed in this mode.
+    pub fn lane_name(&self, span: &Span) -> String {
+        sub_lane_name(
+            self.lane_layout.lane_name(span),
+            &self.sub_lane_attribute,
+            span,
+        )
+    }
+
+    /// The first grouping rule which matches the span.
+    pub fn grouping_rule_for(&self, span: &SpanData) -> Option<&GroupingRule> {
+        self.grouping_rules
+            .iter()
+            .find(|rule| rule.selector.matches(span))
+    }
+
+    /// Spans created by the synthetic span rules which match `span`, they are not transformed yet.
+    pub fn synthetic_children(&self, span: &SpanData) -> Vec<Rc<Span>> {
+        let mut children = Vec::new();
+        for (i, rule) in self.synthetic_span_rules.iter().enumerate() {
+            if rule.selector.matches(span) {
+                children.extend(
+                    rule.synthesize(i, span)
+                        .into_iter()
+                        .map(|data| Rc::new(Span::new(data))),
+                );
+            }
+        }
+        children
+    }
+
+    /// The first rename rule which matches the span.
+    pub fn rename_rule_for(&self, span: &SpanData) -> Option<&RenameRule> {
+        self.rename_rules
+            .iter()
+            .find(|rule| rule.selector.matches(span))
+    }
+
     pub fn get_decision_for_span(&self, span: &Span) -> SpanDecision {
         for rule in &self.span_rules {
             if rule.selector.matches(span) {
@@ -174,6 +581,50 @@ impl StructuredMode {
     }
 }
 
+/// How many spans of the trace each rule of a mode would match, shown in the mode editor before the
+/// mode is saved.
+#[derive(Debug, Clone, Default)]
+pub struct ModePreview {
+    /// One entry for every rule of the mode.
+    pub rules: Vec<RulePreview>,
+    /// Spans which don't match any rule, they are hidden.
+    pub unmatched_count: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RulePreview {
+    /// Number of spans for which this is the first matching rule.
+    pub matched_count: usize,
+    /// The first few of the matched spans.
+    pub samples: Vec<Rc<Span>>,
+}
+
+/// Matches `spans` against the rules like [StructuredMode::get_decision_for_span] does, keeping at
+/// most `max_samples` spans for each rule.
+pub fn preview_span_rules(
+    rules: &[SpanRule],
+    spans: &[Rc<Span>],
+    max_samples: usize,
+) -> ModePreview {
+    let mut preview = ModePreview {
+        rules: vec![RulePreview::default(); rules.len()],
+        unmatched_count: 0,
+    };
+    for span in spans {
+        match rules.iter().position(|rule| rule.selector.matches(span)) {
+            Some(rule_idx) => {
+                let rule_preview = &mut preview.rules[rule_idx];
+                rule_preview.matched_count += 1;
+                if rule_preview.samples.len() < max_samples {
+                    rule_preview.samples.push(span.clone());
+                }
+            }
+            None => preview.unmatched_count += 1,
+        }
+    }
+    preview
+}
+
 /// Everything mode
 pub fn everything_structured_mode() -> StructuredMode {
     StructuredMode {
@@ -217,6 +668,12 @@ pub fn everything_structured_mode() -> StructuredMode {
                 },
             },
         ],
+        lane_layout: LaneLayout::PerNode,
+        show_event_markers: true,
+        sub_lane_attribute: String::new(),
+        grouping_rules: Vec::new(),
+        rename_rules: Vec::new(),
+        synthetic_span_rules: Vec::new(),
         is_builtin: true,
     }
 }
@@ -290,10 +747,24 @@ pub fn block_production_structured_mode() -> StructuredMode {
                 },
             },
         ],
+        lane_layout: LaneLayout::PerNode,
+        show_event_markers: false,
+        sub_lane_attribute: String::new(),
+        grouping_rules: Vec::new(),
+        rename_rules: Vec::new(),
+        synthetic_span_rules: Vec::new(),
         is_builtin: true,
     }
 }
 
+/// Everything, but with one lane per span name instead of one lane per node.
+fn everything_per_span_name_structured_mode() -> StructuredMode {
+    let mut mode = everything_structured_mode();
+    mode.name = "Everything (lane per span name)".to_string();
+    mode.lane_layout = LaneLayout::PerSpanName;
+    mode
+}
+
 /// tag_block_production reduced - without the spammy spans
 fn block_production_reduced_structured_mode() -> StructuredMode {
     let mut mode = block_production_structured_mode();
@@ -315,6 +786,12 @@ fn witness_distribution_structured_mode() -> StructuredMode {
     StructuredMode {
         name: "tag_witness_distribution".to_string(),
         span_rules: vec![show_spans_with_tag("tag_witness_distribution")],
+        lane_layout: LaneLayout::PerNode,
+        show_event_markers: false,
+        sub_lane_attribute: String::new(),
+        grouping_rules: Vec::new(),
+        rename_rules: Vec::new(),
+        synthetic_span_rules: Vec::new(),
         is_builtin: true,
     }
 }
@@ -353,6 +830,12 @@ fn witness_distribution_shard_1_structured_mode() -> StructuredMode {
                 group: false,
             },
         }],
+        lane_layout: LaneLayout::PerNode,
+        show_event_markers: false,
+        sub_lane_attribute: String::new(),
+        grouping_rules: Vec::new(),
+        rename_rules: Vec::new(),
+        synthetic_span_rules: Vec::new(),
         is_builtin: true,
     }
 }
@@ -361,6 +844,12 @@ fn chunk_distribution_structured_mode() -> StructuredMode {
     StructuredMode {
         name: "tag_chunk_distribution".to_string(),
         span_rules: vec![show_spans_with_tag("tag_chunk_distribution")],
+        lane_layout: LaneLayout::PerNode,
+        show_event_markers: false,
+        sub_lane_attribute: String::new(),
+        grouping_rules: Vec::new(),
+        rename_rules: Vec::new(),
+        synthetic_span_rules: Vec::new(),
         is_builtin: true,
     }
 }
@@ -399,6 +888,12 @@ fn chunk_distribution_shard_1_structured_mode() -> StructuredMode {
                 group: false,
             },
         }],
+        lane_layout: LaneLayout::PerNode,
+        show_event_markers: false,
+        sub_lane_attribute: String::new(),
+        grouping_rules: Vec::new(),
+        rename_rules: Vec::new(),
+        synthetic_span_rules: Vec::new(),
         is_builtin: true,
     }
 }
@@ -427,6 +922,12 @@ fn critical_path_structured_mode() -> StructuredMode {
             show_span("send partial_encoded_state_witnesses"),
             show_span_grouped("decode_witness_parts"),
         ],
+        lane_layout: LaneLayout::PerNode,
+        show_event_markers: false,
+        sub_lane_attribute: String::new(),
+        grouping_rules: Vec::new(),
+        rename_rules: Vec::new(),
+        synthetic_span_rules: Vec::new(),
         is_builtin: true,
     }
 }
@@ -495,6 +996,12 @@ fn min_critical_path_structured_mode() -> StructuredMode {
                 },
             },
         ],
+        lane_layout: LaneLayout::PerNode,
+        show_event_markers: false,
+        sub_lane_attribute: String::new(),
+        grouping_rules: Vec::new(),
+        rename_rules: Vec::new(),
+        synthetic_span_rules: Vec::new(),
         is_builtin: true,
     }
 }
@@ -594,6 +1101,7 @@ fn hide_span(name: &str) -> SpanRule {
 pub fn builtin_structured_modes() -> Vec<StructuredMode> {
     vec![
         everything_structured_mode(),
+        everything_per_span_name_structured_mode(),
         critical_path_structured_mode(),
         min_critical_path_structured_mode(),
         block_production_structured_mode(),
@@ -6,7 +6,7 @@ pub struct TaskTimer {
 impl TaskTimer {
     pub fn new(task_name: impl AsRef<str>) -> Self {
         let start_time = std::time::Instant::now();
-        println!("Task: {} started", task_name.as_ref());
+        log::debug!("Task: {} started", task_name.as_ref());
         Self {
             start_time,
             task_name: task_name.as_ref().to_string(),
@@ -14,7 +14,7 @@ impl TaskTimer {
     }
 
     pub fn stop(&self) {
-        println!(
+        log::info!(
             "Task: {} finished in {:.1}ms",
             self.task_name,
             self.start_time.elapsed().as_secs_f64() * 1000.0
@@ -0,0 +1,318 @@
+//! Throughput of a span name - spans are binned into fixed-width time buckets by their start time,
+//! and the count (or the total duration) per bucket is charted for every node. A dip in the rate of
+//! e.g. chunk applications during a stall is visible at a glance.
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use eframe::egui::{self, DragValue, Modal, RichText, Vec2};
+
+use crate::analyze_utils::{process_spans_for_analysis, span_search_ui, span_selection_list_ui};
+use crate::charts::{draw_chart, ChartAxis, ChartSeries};
+use crate::colors::{node_color, theme};
+use crate::types::{Span, TimeFormat, TimePoint, MILLISECONDS_PER_SECOND};
+
+pub const DEFAULT_BUCKET_WIDTH_MS: f64 = 1000.0;
+/// Buckets are widened when the spans would need more of them.
+pub const MAX_BUCKETS: usize = 10_000;
+/// Name of the series which sums all nodes.
+pub const ALL_NODES: &str = "all nodes";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ThroughputBucket {
+    pub count: usize,
+    /// Sum of the durations of spans which started in the bucket, in seconds.
+    pub total_duration: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Throughput {
+    /// Start of the first bucket.
+    pub start: TimePoint,
+    /// Seconds
+    pub bucket_width: f64,
+    /// Buckets of every node, all nodes have the same number of buckets.
+    pub per_node: BTreeMap<String, Vec<ThroughputBucket>>,
+}
+
+impl Throughput {
+    pub fn bucket_start(&self, index: usize) -> TimePoint {
+        self.start + index as f64 * self.bucket_width
+    }
+
+    /// Buckets summed over all nodes.
+    pub fn total(&self) -> Vec<ThroughputBucket> {
+        let buckets = self.per_node.values().next().map_or(0, |b| b.len());
+        let mut total = vec![ThroughputBucket::default(); buckets];
+        for node_buckets in self.per_node.values() {
+            for (sum, bucket) in total.iter_mut().zip(node_buckets) {
+                sum.count += bucket.count;
+                sum.total_duration += bucket.total_duration;
+            }
+        }
+        total
+    }
+}
+
+/// Bins spans named `span_name` by their start time into buckets `bucket_width` seconds wide,
+/// starting at the earliest span. The buckets are widened when there would be more than
+/// [`MAX_BUCKETS`] of them. `None` when there are no such spans.
+pub fn compute_throughput(
+    spans: &[Rc<Span>],
+    span_name: &str,
+    bucket_width: f64,
+) -> Option<Throughput> {
+    let matching: Vec<&Rc<Span>> = spans
+        .iter()
+        .filter(|s| s.original_name() == span_name)
+        .collect();
+    if matching.is_empty() || bucket_width <= 0.0 {
+        return None;
+    }
+    let start = matching
+        .iter()
+        .map(|s| s.start_time)
+        .fold(f64::INFINITY, f64::min);
+    let end = matching
+        .iter()
+        .map(|s| s.start_time)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    let bucket_width = bucket_width.max((end - start) / (MAX_BUCKETS - 1) as f64);
+
+    let bucket_index =
+        |time: TimePoint| (((time - start) / bucket_width) as usize).min(MAX_BUCKETS - 1);
+    let buckets = bucket_index(end) + 1;
+    let mut per_node: BTreeMap<String, Vec<ThroughputBucket>> = BTreeMap::new();
+    for span in matching {
+        let bucket = &mut per_node
+            .entry(span.node.name.clone())
+            .or_insert_with(|| vec![ThroughputBucket::default(); buckets])
+            [bucket_index(span.start_time)];
+        bucket.count += 1;
+        bucket.total_duration += span.end_time - span.start_time;
+    }
+    Some(Throughput {
+        start,
+        bucket_width,
+        per_node,
+    })
+}
+
+pub struct ThroughputModal {
+    pub show: bool,
+    all_spans: Vec<Rc<Span>>,
+    unique_span_names: Vec<String>,
+    search_text: String,
+    selected_span_name: Option<String>,
+    bucket_width_ms: f64,
+    /// Chart the total duration instead of the count.
+    show_total_duration: bool,
+    show_all_nodes_sum: bool,
+    throughput: Option<Throughput>,
+    /// If set, the trace view should show this time range after closing the modal.
+    pub focus_range: Option<(TimePoint, TimePoint)>,
+}
+
+impl Default for ThroughputModal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThroughputModal {
+    pub fn new() -> ThroughputModal {
+        ThroughputModal {
+            show: false,
+            all_spans: Vec::new(),
+            unique_span_names: Vec::new(),
+            search_text: String::new(),
+            selected_span_name: None,
+            bucket_width_ms: DEFAULT_BUCKET_WIDTH_MS,
+            show_total_duration: false,
+            show_all_nodes_sum: false,
+            throughput: None,
+            focus_range: None,
+        }
+    }
+
+    pub fn open(&mut self, spans_for_analysis: &[Rc<Span>]) {
+        let (all_spans, unique_span_names) = process_spans_for_analysis(spans_for_analysis);
+        self.all_spans = all_spans;
+        self.unique_span_names = unique_span_names;
+        self.show = true;
+        self.compute();
+    }
+
+    fn compute(&mut self) {
+        self.throughput = self.selected_span_name.as_ref().and_then(|name| {
+            compute_throughput(
+                &self.all_spans,
+                name,
+                self.bucket_width_ms / MILLISECONDS_PER_SECOND,
+            )
+        });
+    }
+
+    pub fn show_modal(
+        &mut self,
+        ctx: &egui::Context,
+        time_format: &TimeFormat,
+        max_width: f32,
+        max_height: f32,
+    ) {
+        if !self.show {
+            return;
+        }
+
+        let mut compute = false;
+        let mut focus_range = None;
+        Modal::new("throughput".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Throughput");
+            ui.label("Spans with the name per time bucket, by the time they started.");
+            ui.separator();
+
+            ui.horizontal_top(|ui| {
+                ui.vertical(|ui| {
+                    ui.set_width(250.0);
+                    span_search_ui(ui, &mut self.search_text, "Span name", "Search", 240.0);
+                    compute |= span_selection_list_ui(
+                        ui,
+                        &self.unique_span_names,
+                        &self.search_text,
+                        &mut self.selected_span_name,
+                        max_height - 200.0,
+                        "throughput span names",
+                    );
+                });
+                ui.separator();
+                ui.vertical(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Bucket width:");
+                        compute |= ui
+                            .add(
+                                DragValue::new(&mut self.bucket_width_ms)
+                                    .range(1.0..=3_600_000.0)
+                                    .suffix(" ms"),
+                            )
+                            .changed();
+                        ui.radio_value(&mut self.show_total_duration, false, "Count");
+                        ui.radio_value(&mut self.show_total_duration, true, "Total duration");
+                        ui.checkbox(&mut self.show_all_nodes_sum, "Sum of all nodes");
+                    });
+                    focus_range =
+                        self.draw_chart(ui, time_format, max_width - 300.0, max_height - 150.0);
+                });
+            });
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.show = false;
+            }
+        });
+
+        if compute {
+            self.compute();
+        }
+        if let Some(range) = focus_range {
+            self.focus_range = Some(range);
+            self.show = false;
+        }
+    }
+
+    /// Returns the time range of the clicked bucket.
+    fn draw_chart(
+        &self,
+        ui: &mut egui::Ui,
+        time_format: &TimeFormat,
+        width: f32,
+        height: f32,
+    ) -> Option<(f64, f64)> {
+        let Some(span_name) = &self.selected_span_name else {
+            ui.label("Choose a span name.");
+            return None;
+        };
+        let Some(throughput) = &self.throughput else {
+            ui.label(format!("No {span_name} spans."));
+            return None;
+        };
+        let bucket_width_ms = throughput.bucket_width * MILLISECONDS_PER_SECOND;
+        if bucket_width_ms > self.bucket_width_ms * (1.0 + 1e-9) {
+            ui.label(
+                RichText::new(format!(
+                    "The buckets were widened to {bucket_width_ms:.1} ms to have at most \
+                     {MAX_BUCKETS} of them."
+                ))
+                .weak(),
+            );
+        }
+
+        let to_points = |buckets: &[ThroughputBucket]| -> Vec<(f64, f64)> {
+            buckets
+                .iter()
+                .enumerate()
+                .map(|(i, bucket)| {
+                    let value = if self.show_total_duration {
+                        bucket.total_duration * MILLISECONDS_PER_SECOND
+                    } else {
+                        bucket.count as f64
+                    };
+                    (throughput.bucket_start(i), value)
+                })
+                .collect()
+        };
+        let mut points: Vec<(String, Vec<(f64, f64)>)> = if self.show_all_nodes_sum {
+            vec![(ALL_NODES.to_string(), to_points(&throughput.total()))]
+        } else {
+            throughput
+                .per_node
+                .iter()
+                .map(|(node, buckets)| (node.clone(), to_points(buckets)))
+                .collect()
+        };
+        points.retain(|(_, p)| !p.is_empty());
+        let series: Vec<ChartSeries> = points
+            .iter()
+            .map(|(node, points)| ChartSeries {
+                name: node,
+                color: if node == ALL_NODES {
+                    theme().chart_fill
+                } else {
+                    node_color(node)
+                },
+                points,
+                draw_lines: true,
+            })
+            .collect();
+
+        let clicked = draw_chart(
+            ui,
+            Vec2::new(width, height.clamp(150.0, 400.0)),
+            &series,
+            &ChartAxis {
+                label: "bucket start",
+                format: &|time| time_format.time_string(time),
+            },
+            &ChartAxis {
+                label: if self.show_total_duration {
+                    "total duration (ms)"
+                } else {
+                    "count"
+                },
+                format: &|value| format!("{value:.1}"),
+            },
+        );
+        ui.label(
+            RichText::new("Click on a point to show the time bucket in the timeline.")
+                .small()
+                .weak(),
+        );
+        clicked.map(|(_, point_idx)| {
+            let start = throughput.bucket_start(point_idx);
+            (start, start + throughput.bucket_width)
+        })
+    }
+}
@@ -0,0 +1,89 @@
+//! Time markers are vertical lines drawn on the timeline at the start of spans chosen by marker
+//! rules. By default there is one rule which marks every `produce_block`, but the rules can be
+//! edited to mark any span that is a good point of reference in the trace.
+
+use std::rc::Rc;
+
+use eframe::egui::Color32;
+
+use crate::colors;
+use crate::structured_modes::{MatchCondition, MatchOperator, SpanSelector};
+use crate::types::{Span, TimePoint};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TimeMarkerRule {
+    /// Shown in the legend next to the timeline.
+    pub label: String,
+    /// A marker is drawn at the start of every span that matches this selector.
+    pub selector: SpanSelector,
+    /// sRGB color of the markers.
+    pub color: [u8; 3],
+    /// Write the name of the span's node next to each marker.
+    pub show_node_name: bool,
+    pub enabled: bool,
+}
+
+impl TimeMarkerRule {
+    pub fn color32(&self) -> Color32 {
+        let [r, g, b] = self.color;
+        Color32::from_rgb(r, g, b)
+    }
+}
+
+/// Used when there are no rules in the persistent data, matches the markers which were shown
+/// before the rules were configurable.
+pub fn default_time_marker_rules() -> Vec<TimeMarkerRule> {
+    let red = colors::RED;
+    vec![TimeMarkerRule {
+        label: "produce_block".to_string(),
+        selector: SpanSelector {
+            span_name_condition: MatchCondition {
+                operator: MatchOperator::Contains,
+                value: "produce_block".to_string(),
+            },
+            node_name_condition: MatchCondition::any(),
+            attribute_conditions: vec![],
+        },
+        color: [red.r(), red.g(), red.b()],
+        show_node_name: true,
+        enabled: true,
+    }]
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeMarker {
+    pub time: TimePoint,
+    pub node_name: String,
+    /// Index of the rule which created this marker.
+    pub rule_index: usize,
+}
+
+/// Markers for all spans (including children) which match one of the enabled rules, sorted by time.
+/// A span which matches multiple rules gets a marker for each of them.
+pub fn collect_time_markers(rules: &[TimeMarkerRule], spans: &[Rc<Span>]) -> Vec<TimeMarker> {
+    let mut markers = Vec::new();
+    if rules.iter().any(|rule| rule.enabled) {
+        collect_time_markers_rec(rules, spans, &mut markers);
+    }
+    markers.sort_by(|a, b| a.time.total_cmp(&b.time));
+    markers
+}
+
+fn collect_time_markers_rec(
+    rules: &[TimeMarkerRule],
+    spans: &[Rc<Span>],
+    markers: &mut Vec<TimeMarker>,
+) {
+    for span in spans {
+        for (rule_index, rule) in rules.iter().enumerate() {
+            if rule.enabled && rule.selector.matches(span) {
+                markers.push(TimeMarker {
+                    time: span.start_time,
+                    node_name: span.node.name.clone(),
+                    rule_index,
+                });
+            }
+        }
+        collect_time_markers_rec(rules, span.children().as_slice(), markers);
+    }
+}
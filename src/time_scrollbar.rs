@@ -0,0 +1,54 @@
+//! Horizontal scrollbar under the span area. The track is the whole trace and the thumb is the
+//! selected time range, dragging the thumb or clicking on the track moves the selected range.
+
+use crate::types::TimePoint;
+
+/// Thumbs are never narrower than this, so they can be grabbed even when zoomed in a lot.
+pub const MIN_THUMB_WIDTH: f32 = 12.0;
+
+/// Position of the thumb as fractions of the track, `(start, end)` in `0..=1`.
+pub fn thumb_fractions(
+    absolute_start: TimePoint,
+    absolute_end: TimePoint,
+    selected_start: TimePoint,
+    selected_end: TimePoint,
+) -> (f32, f32) {
+    let absolute_len = absolute_end - absolute_start;
+    if absolute_len <= 0.0 {
+        return (0.0, 1.0);
+    }
+    let to_fraction = |time: TimePoint| ((time - absolute_start) / absolute_len).clamp(0.0, 1.0);
+    (
+        to_fraction(selected_start) as f32,
+        to_fraction(selected_end) as f32,
+    )
+}
+
+/// Start of a selected range of `selected_len` moved to `new_start`, kept inside of the trace. A
+/// range longer than the trace can only move while it still covers the whole trace.
+pub fn clamp_selected_start(
+    absolute_start: TimePoint,
+    absolute_end: TimePoint,
+    selected_len: TimePoint,
+    new_start: TimePoint,
+) -> TimePoint {
+    let max_start = absolute_end - selected_len;
+    new_start.clamp(absolute_start.min(max_start), absolute_start.max(max_start))
+}
+
+/// Start of the selected range after a click at `fraction` of the track, which centers the range
+/// on the clicked time.
+pub fn selected_start_for_click(
+    absolute_start: TimePoint,
+    absolute_end: TimePoint,
+    selected_len: TimePoint,
+    fraction: f32,
+) -> TimePoint {
+    let clicked_time = absolute_start + (absolute_end - absolute_start) * fraction as f64;
+    clamp_selected_start(
+        absolute_start,
+        absolute_end,
+        selected_len,
+        clicked_time - selected_len / 2.0,
+    )
+}
@@ -0,0 +1,60 @@
+//! Following one trace (e.g. one request) across the nodes. The spans with the focused trace id are
+//! tinted, or the spans of all other traces are filtered out.
+
+use anyhow::{bail, Context, Result};
+
+use crate::span_id::{SpanId, TraceId};
+use crate::types::SpanData;
+
+#[derive(Debug, Clone, Default)]
+pub struct TraceFocus {
+    /// Contents of the trace id text box.
+    pub input: String,
+    pub trace_id: Option<TraceId>,
+    /// Display only the spans of the focused trace, instead of tinting them.
+    pub filter: bool,
+}
+
+impl TraceFocus {
+    pub fn focus(&mut self, trace_id: TraceId) {
+        self.input = trace_id.to_hex();
+        self.trace_id = Some(trace_id);
+    }
+
+    /// Focuses the trace id typed into the text box.
+    pub fn focus_input(&mut self) -> Result<()> {
+        let trace_id = parse_trace_id(&self.input)?;
+        self.focus(trace_id);
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.input.clear();
+        self.trace_id = None;
+    }
+
+    /// Whether the span should be drawn with the trace tint.
+    pub fn is_tinted(&self, span: &SpanData) -> bool {
+        !self.filter && self.trace_id == Some(span.trace_id)
+    }
+
+    /// Whether a top level span is displayed, only the focused trace is when filtering.
+    pub fn shows(&self, span: &SpanData) -> bool {
+        match self.trace_id {
+            Some(trace_id) if self.filter => span.trace_id == trace_id,
+            _ => true,
+        }
+    }
+}
+
+/// Parses a hex trace id, e.g. copied from a log line. Surrounding whitespace and a "0x" prefix are
+/// ignored.
+pub fn parse_trace_id(text: &str) -> Result<TraceId> {
+    let text = text.trim();
+    let hex_digits = text.strip_prefix("0x").unwrap_or(text);
+    if hex_digits.is_empty() {
+        bail!("the trace id is empty");
+    }
+    let bytes = hex::decode(hex_digits).with_context(|| format!("invalid trace id '{text}'"))?;
+    Ok(SpanId::intern(&bytes))
+}
@@ -0,0 +1,315 @@
+//! Health checks of the trace structure, run after a file is loaded. Bad instrumentation (missing
+//! parents, negative durations, duplicate ids, ...) would otherwise just render weirdly.
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use eframe::egui::{self, Grid, Key, Modal, RichText, ScrollArea};
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+
+use crate::analyze_utils::ModalFocus;
+use crate::colors::theme;
+use crate::modes::{resource_node_name, OrphanSpans};
+use crate::span_id::SpanId;
+use crate::types::{Span, SpanData, MILLISECONDS_PER_SECOND};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LintKind {
+    /// The parent span id isn't empty, but there is no span with this id.
+    MissingParent,
+    /// The span ends before it starts.
+    NegativeDuration,
+    /// Multiple spans have the same id, only one of them is displayed.
+    DuplicateSpanId,
+    /// A child on a different node than its parent starts before the parent, the clocks of the
+    /// nodes are probably out of sync.
+    ClockSkew,
+    /// Resource spans without a resource, or instrumentation scopes without spans or without a
+    /// scope description.
+    OrphanedScope,
+}
+
+impl LintKind {
+    pub fn all() -> [LintKind; 5] {
+        [
+            LintKind::MissingParent,
+            LintKind::NegativeDuration,
+            LintKind::DuplicateSpanId,
+            LintKind::ClockSkew,
+            LintKind::OrphanedScope,
+        ]
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            LintKind::MissingParent => "Missing parent",
+            LintKind::NegativeDuration => "End before start",
+            LintKind::DuplicateSpanId => "Duplicate span id",
+            LintKind::ClockSkew => "Clock skew",
+            LintKind::OrphanedScope => "Orphaned scope",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LintIssue {
+    pub kind: LintKind,
+    pub message: String,
+    /// The span with the problem, if the problem is about a span.
+    pub span_id: Option<SpanId>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LintReport {
+    /// Sorted by kind.
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn count(&self, kind: LintKind) -> usize {
+        self.issues.iter().filter(|i| i.kind == kind).count()
+    }
+}
+
+fn describe_span(span: &SpanData) -> String {
+    format!(
+        "{} on {} ({})",
+        span.original_name(),
+        span.node.name,
+        span.span_id.to_hex()
+    )
+}
+
+/// Checks the structure of the trace, see [`LintKind`] for the problems it looks for. `spans` are
+/// all spans (including children) parsed from `requests`, only one of the spans with the same id
+/// is among them. The raw data is used for what the parsed spans don't keep: the duplicate ids and
+/// the resources and scopes.
+pub fn lint_trace(spans: &[Rc<Span>], requests: &[ExportTraceServiceRequest]) -> LintReport {
+    let mut issues = Vec::new();
+
+    let spans_by_id: HashMap<SpanId, &Rc<Span>> =
+        spans.iter().map(|span| (span.span_id, span)).collect();
+    for span in spans {
+        if span.end_time < span.start_time {
+            issues.push(LintIssue {
+                kind: LintKind::NegativeDuration,
+                message: format!(
+                    "{} ends {:.3} ms before it starts",
+                    describe_span(span),
+                    (span.start_time - span.end_time) * MILLISECONDS_PER_SECOND
+                ),
+                span_id: Some(span.span_id),
+            });
+        }
+    }
+
+    for span in spans {
+        if span.parent_span_id.is_empty() {
+            continue;
+        }
+        match spans_by_id.get(&span.parent_span_id) {
+            None => issues.push(LintIssue {
+                kind: LintKind::MissingParent,
+                message: format!(
+                    "{} has parent {} which isn't in the trace",
+                    describe_span(span),
+                    span.parent_span_id.to_hex()
+                ),
+                span_id: Some(span.span_id),
+            }),
+            Some(parent)
+                if parent.node.name != span.node.name && span.start_time < parent.start_time =>
+            {
+                issues.push(LintIssue {
+                    kind: LintKind::ClockSkew,
+                    message: format!(
+                        "{} starts {:.3} ms before its parent {}",
+                        describe_span(span),
+                        (parent.start_time - span.start_time) * MILLISECONDS_PER_SECOND,
+                        describe_span(parent)
+                    ),
+                    span_id: Some(span.span_id),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    let mut seen_ids = HashSet::new();
+    for request in requests {
+        for resource_spans in &request.resource_spans {
+            let node_name = resource_node_name(resource_spans.resource.as_ref());
+            if resource_spans.resource.is_none() {
+                issues.push(LintIssue {
+                    kind: LintKind::OrphanedScope,
+                    message: "Resource spans without a resource, the spans are shown on \
+                              the \"no resource\" node"
+                        .to_string(),
+                    span_id: None,
+                });
+            }
+            for scope_spans in &resource_spans.scope_spans {
+                for raw_span in &scope_spans.spans {
+                    let span_id = SpanId::intern(&raw_span.span_id);
+                    if !seen_ids.insert(span_id) {
+                        issues.push(LintIssue {
+                            kind: LintKind::DuplicateSpanId,
+                            message: format!(
+                                "{} on {node_name} ({}) has the same id as another span",
+                                raw_span.name,
+                                span_id.to_hex()
+                            ),
+                            span_id: Some(span_id),
+                        });
+                    }
+                }
+                let scope_name = scope_spans.scope.as_ref().map(|s| s.name.as_str());
+                if scope_spans.spans.is_empty() {
+                    issues.push(LintIssue {
+                        kind: LintKind::OrphanedScope,
+                        message: format!(
+                            "Scope {} on {node_name} has no spans",
+                            scope_name.unwrap_or("<none>")
+                        ),
+                        span_id: None,
+                    });
+                } else if scope_name.is_none() {
+                    issues.push(LintIssue {
+                        kind: LintKind::OrphanedScope,
+                        message: format!(
+                            "{} spans on {node_name} have no instrumentation scope",
+                            scope_spans.spans.len()
+                        ),
+                        span_id: None,
+                    });
+                }
+            }
+        }
+    }
+
+    issues.sort_by_key(|issue| issue.kind);
+    LintReport { issues }
+}
+
+#[derive(Default)]
+pub struct TraceLintModal {
+    pub show: bool,
+    report: LintReport,
+    /// Spans which can be jumped to, by id.
+    spans_by_id: HashMap<SpanId, Rc<Span>>,
+    /// Show only issues of this kind.
+    kind_filter: Option<LintKind>,
+    pub focus: ModalFocus,
+    /// How the spans with a missing parent are displayed, from the preferences.
+    pub orphan_spans: OrphanSpans,
+}
+
+impl TraceLintModal {
+    pub fn new() -> TraceLintModal {
+        TraceLintModal::default()
+    }
+
+    /// Remembers the report of a newly loaded file. `spans` should contain all spans (including
+    /// children) of the trace, they are used for the jump-to links.
+    pub fn set_report(&mut self, report: LintReport, spans: &[Rc<Span>]) {
+        self.report = report;
+        self.spans_by_id = spans.iter().map(|s| (s.span_id, s.clone())).collect();
+        self.kind_filter = None;
+        self.focus.clear();
+    }
+
+    pub fn report(&self) -> &LintReport {
+        &self.report
+    }
+
+    pub fn show_modal(&mut self, ctx: &egui::Context, max_width: f32, max_height: f32) {
+        if !self.show {
+            return;
+        }
+
+        let mut jump_to_span = None;
+        Modal::new("trace health".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Trace health");
+            if self.report.is_empty() {
+                ui.label("No structural problems found.");
+            }
+            let orphans = self.report.count(LintKind::MissingParent);
+            if orphans > 0 {
+                ui.label(format!(
+                    "{orphans} spans have a parent which isn't in the trace, they are {} (see \
+                     the preferences).",
+                    self.orphan_spans.description()
+                ));
+            }
+            ui.horizontal(|ui| {
+                ui.selectable_value(
+                    &mut self.kind_filter,
+                    None,
+                    format!("All ({})", self.report.issues.len()),
+                );
+                for kind in LintKind::all() {
+                    let count = self.report.count(kind);
+                    if count > 0 {
+                        ui.selectable_value(
+                            &mut self.kind_filter,
+                            Some(kind),
+                            format!("{} ({count})", kind.title()),
+                        );
+                    }
+                }
+            });
+            ui.separator();
+
+            let issues: Vec<&LintIssue> = self
+                .report
+                .issues
+                .iter()
+                .filter(|issue| self.kind_filter.is_none_or(|kind| kind == issue.kind))
+                .collect();
+            let row_height = ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+            ScrollArea::vertical()
+                .max_height(max_height - 150.0)
+                .auto_shrink([false, true])
+                .show_rows(ui, row_height, issues.len(), |ui, row_range| {
+                    Grid::new("trace health issues")
+                        .striped(true)
+                        .start_row(row_range.start)
+                        .show(ui, |ui| {
+                            for issue in &issues[row_range] {
+                                ui.label(RichText::new(issue.kind.title()).color(theme().error));
+                                ui.label(&issue.message);
+                                let span = issue.span_id.and_then(|id| self.spans_by_id.get(&id));
+                                match span {
+                                    Some(span) => {
+                                        if ui.link("Jump").clicked() {
+                                            jump_to_span = Some(span.clone());
+                                        }
+                                    }
+                                    None => {
+                                        ui.label("");
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(Key::Escape)) {
+                self.show = false;
+            }
+        });
+
+        if let Some(span) = jump_to_span {
+            self.focus.jump_to(span);
+            self.show = false;
+        }
+    }
+}
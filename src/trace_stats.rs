@@ -0,0 +1,221 @@
+//! Summary of what is in a trace file, shown after the file is loaded.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use eframe::egui::{self, Grid, Key, Modal, RichText, ScrollArea, Ui};
+
+use crate::colors::node_color;
+use crate::types::{Span, TimeFormat, TimePoint, MILLISECONDS_PER_SECOND};
+
+/// Number of span names shown in the "top" tables.
+pub const TOP_SPAN_NAMES: usize = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanNameStats {
+    pub name: String,
+    pub count: usize,
+    /// Seconds
+    pub total_duration: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceStats {
+    /// Number of export requests in the file.
+    pub requests: usize,
+    pub spans: usize,
+    pub events: usize,
+    pub start_time: TimePoint,
+    pub end_time: TimePoint,
+    /// The most frequent span names, most frequent first.
+    pub top_by_count: Vec<SpanNameStats>,
+    /// Span names with the largest total duration, largest first.
+    pub top_by_total_duration: Vec<SpanNameStats>,
+    /// Number of spans on each node, sorted by node name.
+    pub spans_per_node: Vec<(String, usize)>,
+}
+
+impl TraceStats {
+    pub fn nodes(&self) -> usize {
+        self.spans_per_node.len()
+    }
+}
+
+/// `spans` should contain all spans (including children) of the trace.
+pub fn compute_trace_stats(requests: usize, spans: &[Rc<Span>]) -> TraceStats {
+    let mut by_name: HashMap<&str, SpanNameStats> = HashMap::new();
+    let mut per_node: HashMap<&str, usize> = HashMap::new();
+    let mut events = 0;
+    let mut start_time = TimePoint::INFINITY;
+    let mut end_time = TimePoint::NEG_INFINITY;
+    for span in spans {
+        let name_stats = by_name
+            .entry(span.original_name())
+            .or_insert_with(|| SpanNameStats {
+                name: span.original_name().to_string(),
+                count: 0,
+                total_duration: 0.0,
+            });
+        name_stats.count += 1;
+        name_stats.total_duration += span.end_time - span.start_time;
+        *per_node.entry(span.node.name.as_str()).or_default() += 1;
+        events += span.events.len();
+        start_time = start_time.min(span.start_time);
+        end_time = end_time.max(span.end_time);
+    }
+    if spans.is_empty() {
+        (start_time, end_time) = (0.0, 0.0);
+    }
+
+    let mut top_by_count: Vec<SpanNameStats> = by_name.values().cloned().collect();
+    top_by_count.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    top_by_count.truncate(TOP_SPAN_NAMES);
+
+    let mut top_by_total_duration: Vec<SpanNameStats> = by_name.into_values().collect();
+    top_by_total_duration.sort_by(|a, b| {
+        b.total_duration
+            .total_cmp(&a.total_duration)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    top_by_total_duration.truncate(TOP_SPAN_NAMES);
+
+    let mut spans_per_node: Vec<(String, usize)> = per_node
+        .into_iter()
+        .map(|(node, count)| (node.to_string(), count))
+        .collect();
+    spans_per_node.sort();
+
+    TraceStats {
+        requests,
+        spans: spans.len(),
+        events,
+        start_time,
+        end_time,
+        top_by_count,
+        top_by_total_duration,
+        spans_per_node,
+    }
+}
+
+#[derive(Default)]
+pub struct TraceStatsModal {
+    pub show: bool,
+    /// Name of the file the stats are for.
+    file_name: String,
+    stats: Option<TraceStats>,
+}
+
+impl TraceStatsModal {
+    pub fn new() -> TraceStatsModal {
+        TraceStatsModal::default()
+    }
+
+    /// Remembers the stats of a newly loaded file, `show` decides whether they're shown right away.
+    pub fn set_stats(&mut self, file_name: String, stats: TraceStats, show: bool) {
+        self.file_name = file_name;
+        self.stats = Some(stats);
+        self.show = show;
+    }
+
+    pub fn has_stats(&self) -> bool {
+        self.stats.is_some()
+    }
+
+    pub fn show_modal(
+        &mut self,
+        ctx: &egui::Context,
+        time_format: &TimeFormat,
+        max_width: f32,
+        max_height: f32,
+    ) {
+        if !self.show {
+            return;
+        }
+        let Some(stats) = &self.stats else {
+            self.show = false;
+            return;
+        };
+
+        let mut close = false;
+        Modal::new("trace stats".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Trace info");
+            ui.label(&self.file_name);
+            ui.separator();
+
+            Grid::new("trace stats summary").show(ui, |ui| {
+                let mut row = |name: &str, value: String| {
+                    ui.label(name);
+                    ui.monospace(value);
+                    ui.end_row();
+                };
+                row("Requests", stats.requests.to_string());
+                row("Spans", stats.spans.to_string());
+                row("Events", stats.events.to_string());
+                row("Nodes", stats.nodes().to_string());
+                row("Start", time_format.time_string(stats.start_time));
+                row("End", time_format.time_string(stats.end_time));
+                row(
+                    "Duration",
+                    format!("{:.3} s", stats.end_time - stats.start_time),
+                );
+            });
+            ui.separator();
+
+            ScrollArea::vertical()
+                .max_height(max_height - 300.0)
+                .show(ui, |ui| {
+                    ui.horizontal_top(|ui| {
+                        ui.vertical(|ui| {
+                            ui.strong(format!("Top {TOP_SPAN_NAMES} span names by count"));
+                            draw_span_names(ui, "top by count", &stats.top_by_count);
+                        });
+                        ui.add_space(20.0);
+                        ui.vertical(|ui| {
+                            ui.strong(format!("Top {TOP_SPAN_NAMES} span names by total duration"));
+                            draw_span_names(ui, "top by duration", &stats.top_by_total_duration);
+                        });
+                        ui.add_space(20.0);
+                        ui.vertical(|ui| {
+                            ui.strong("Spans per node");
+                            Grid::new("spans per node").striped(true).show(ui, |ui| {
+                                for (node, count) in &stats.spans_per_node {
+                                    ui.label(RichText::new(node).color(node_color(node)));
+                                    ui.monospace(count.to_string());
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                    });
+                });
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(Key::Escape)) {
+                close = true;
+            }
+        });
+        if close {
+            self.show = false;
+        }
+    }
+}
+
+fn draw_span_names(ui: &mut Ui, id_salt: &str, names: &[SpanNameStats]) {
+    Grid::new(id_salt).striped(true).show(ui, |ui| {
+        ui.strong("Name");
+        ui.strong("Count");
+        ui.strong("Total");
+        ui.end_row();
+        for name_stats in names {
+            ui.label(&name_stats.name);
+            ui.monospace(name_stats.count.to_string());
+            ui.monospace(format!(
+                "{:.3} ms",
+                name_stats.total_duration * MILLISECONDS_PER_SECOND
+            ));
+            ui.end_row();
+        }
+    });
+}
@@ -1,21 +1,69 @@
 #![allow(unused)]
-use std::cell::{Cell, Ref, RefCell};
+use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::collections::BTreeMap;
 use std::fmt;
+use std::ops::Deref;
 use std::rc::Rc;
+use std::sync::Arc;
 
 use opentelemetry_proto::tonic::common::v1::any_value::Value;
 
 use crate::relation::{Relation, RelationInstance};
+use crate::span_id::{SpanId, TraceId};
 
 pub const MILLISECONDS_PER_SECOND: f64 = 1000.0;
 
 /// Seconds since epoch
 /// TODO: make nicer, f64 isn't great for this
+///
+/// Current times are around 1.7e9 seconds, at which f64 can only tell apart times that are about
+/// 240 ns apart. Differences of nearby times are exact up to that resolution, but anything shorter
+/// (nanosecond span durations, tiny zoom windows) is rounded.
 pub type TimePoint = f64;
 
+/// The timeline can't be zoomed in to a window shorter than this, at the current times f64 can't
+/// represent the steps inside of a smaller window.
+pub const MIN_TIME_WINDOW: f64 = 1e-6;
+
 pub fn time_point_from_unix_nano(unix_nano: u64) -> TimePoint {
-    unix_nano as f64 / 1_000_000_000.0
+    // Whole seconds and nanoseconds are converted separately, so the result is rounded only once.
+    let seconds = (unix_nano / 1_000_000_000) as f64;
+    let nanos = (unix_nano % 1_000_000_000) as f64;
+    seconds + nanos / 1_000_000_000.0
+}
+
+/// Duration with a unit which fits it, so that sub-millisecond durations aren't shown as
+/// "0.000 ms". Durations of at least a millisecond are in milliseconds, like everywhere else.
+pub fn format_duration(seconds: f64) -> String {
+    let abs_seconds = seconds.abs();
+    if abs_seconds >= 1e-3 || abs_seconds == 0.0 {
+        format_ms(seconds)
+    } else if abs_seconds >= 1e-6 {
+        format!("{:.3} µs", seconds * 1e6)
+    } else {
+        format!("{:.0} ns", seconds * 1e9)
+    }
+}
+
+/// Duration in milliseconds, e.g. "12.500 ms".
+pub fn format_ms(seconds: f64) -> String {
+    format!("{} ms", format_ms_value(seconds))
+}
+
+/// Duration in milliseconds without the unit, for table columns which have it in the header.
+pub fn format_ms_value(seconds: f64) -> String {
+    format!("{:.3}", seconds * MILLISECONDS_PER_SECOND)
+}
+
+/// Number of digits after the seconds needed to tell apart times which are `interval` seconds
+/// apart, at most nanoseconds.
+pub fn precision_for_interval(interval: f64) -> usize {
+    if !interval.is_finite() || interval <= 0.0 {
+        return MAX_TIME_PRECISION;
+    }
+    (-interval.log10())
+        .ceil()
+        .clamp(0.0, MAX_TIME_PRECISION as f64) as usize
 }
 
 pub fn time_point_to_utc_string(time: TimePoint) -> String {
@@ -23,23 +71,175 @@ pub fn time_point_to_utc_string(time: TimePoint) -> String {
     date_time.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
 }
 
+/// Time zone in which times are shown in the UI. Exported files always use UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum TimeZoneSetting {
+    #[default]
+    Utc,
+    /// Time zone of the computer running traviz.
+    Local,
+    /// Fixed offset from UTC, e.g. the time zone of the nodes' logs.
+    FixedOffset { minutes: i32 },
+    /// Seconds since the start of the opened trace.
+    RelativeToTraceStart,
+}
+
+impl TimeZoneSetting {
+    pub fn all_kinds() -> [TimeZoneSetting; 4] {
+        [
+            TimeZoneSetting::Utc,
+            TimeZoneSetting::Local,
+            TimeZoneSetting::FixedOffset { minutes: 0 },
+            TimeZoneSetting::RelativeToTraceStart,
+        ]
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            TimeZoneSetting::Utc => "UTC",
+            TimeZoneSetting::Local => "Local time",
+            TimeZoneSetting::FixedOffset { .. } => "Custom UTC offset",
+            TimeZoneSetting::RelativeToTraceStart => "Relative to trace start",
+        }
+    }
+}
+
+/// How times are shown in the timeline ticks, tooltips, span details and analysis tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TimeDisplaySettings {
+    pub time_zone: TimeZoneSetting,
+    /// Number of digits after the seconds in the timeline ticks, up to 9.
+    pub tick_precision: usize,
+}
+
+impl Default for TimeDisplaySettings {
+    fn default() -> Self {
+        TimeDisplaySettings {
+            time_zone: TimeZoneSetting::Utc,
+            tick_precision: 3,
+        }
+    }
+}
+
+/// Digits after the seconds in the times outside of the timeline ticks, milliseconds.
+pub const DEFAULT_TIME_PRECISION: usize = 3;
+pub const MAX_TIME_PRECISION: usize = 9;
+
+/// How times are shown to the user: the time display settings and the opened trace's start and
+/// t=0. Times of a trace are formatted with the time format of the tab the trace is opened in.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimeFormat {
+    pub settings: TimeDisplaySettings,
+    /// User-chosen t=0, displayed times are shown relative to it when it's set.
+    pub origin: Option<TimePoint>,
+    /// Start of the opened trace, used by `TimeZoneSetting::RelativeToTraceStart`.
+    pub trace_start: Option<TimePoint>,
+}
+
+impl TimeFormat {
+    /// Time in the time zone from the time display settings, with `precision` digits after the
+    /// seconds.
+    pub fn time_string_with_precision(&self, time: TimePoint, precision: usize) -> String {
+        let precision = precision.min(MAX_TIME_PRECISION);
+        let time_zone = self.settings.time_zone;
+        if time_zone == TimeZoneSetting::RelativeToTraceStart {
+            if let Some(trace_start) = self.trace_start {
+                return format!("{:+.precision$} s", time - trace_start);
+            }
+        }
+
+        let utc = chrono::DateTime::from_timestamp_nanos((time * 1e9) as i64);
+        let seconds = match time_zone {
+            TimeZoneSetting::Local => utc
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            TimeZoneSetting::FixedOffset { minutes } => {
+                match chrono::FixedOffset::east_opt(minutes * 60) {
+                    Some(offset) => utc
+                        .with_timezone(&offset)
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string(),
+                    None => utc.format("%Y-%m-%d %H:%M:%S").to_string(),
+                }
+            }
+            TimeZoneSetting::Utc | TimeZoneSetting::RelativeToTraceStart => {
+                utc.format("%Y-%m-%d %H:%M:%S").to_string()
+            }
+        };
+        if precision == 0 {
+            return seconds;
+        }
+        let fraction =
+            utc.timestamp_subsec_nanos() / 10u32.pow((MAX_TIME_PRECISION - precision) as u32);
+        format!("{seconds}.{fraction:0precision$}")
+    }
+
+    /// Time in the time zone from the time display settings, with millisecond precision.
+    pub fn time_string(&self, time: TimePoint) -> String {
+        self.time_string_with_precision(time, DEFAULT_TIME_PRECISION)
+    }
+
+    /// Offset of `time` relative to t=0, for example "t0+12.345 ms". None if t=0 isn't set.
+    pub fn relative_string(&self, time: TimePoint) -> Option<String> {
+        self.origin
+            .map(|origin| format!("t0{:+.3} ms", (time - origin) * MILLISECONDS_PER_SECOND))
+    }
+
+    /// Time as it should be displayed to the user - in the time zone from the time display
+    /// settings, followed by the offset to t=0 if it's set.
+    pub fn display_string(&self, time: TimePoint) -> String {
+        match self.relative_string(time) {
+            Some(relative) => format!("{} ({})", self.time_string(time), relative),
+            None => self.time_string(time),
+        }
+    }
+}
+
 pub type HeightLevel = u64;
 
+/// Data of a span read from the trace file. It doesn't change after the trace is loaded, so it can be
+/// shared between all display modes and sent to worker threads.
+/// Display modes which need to modify it (e.g. grouping) get their own copy with `Arc::make_mut`.
 #[derive(Debug, Clone)]
-pub struct Span {
-    pub name: String,
+pub struct SpanData {
     pub original_name: String,
-    pub span_id: Vec<u8>,
-    pub trace_id: Vec<u8>,
-    pub parent_span_id: Vec<u8>,
+    pub span_id: SpanId,
+    pub trace_id: TraceId,
+    pub parent_span_id: SpanId,
     pub start_time: TimePoint,
     pub end_time: TimePoint,
     pub attributes: BTreeMap<String, Option<Value>>,
     pub events: Vec<Event>,
-    pub node: Rc<Node>,
-    pub scope: Option<Rc<Scope>>,
+    pub node: Arc<Node>,
+    pub scope: Option<Arc<Scope>>,
+}
 
-    pub children: RefCell<Vec<Rc<Span>>>,
+impl SpanData {
+    pub fn original_name(&self) -> &str {
+        // TODO - should this be a field or an attribute?
+        &self.original_name
+    }
+}
+
+// Processing on worker threads relies on this.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SpanData>();
+};
+
+/// A span as displayed in the current display mode: trace data shared with other modes plus the
+/// per-mode tree and display state. Derefs to [SpanData], so `span.start_time` etc. work directly.
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub data: Arc<SpanData>,
+    pub name: String,
+
+    /// Empty until lazy children are transformed, use [Span::children] or [Span::children_mut].
+    children: RefCell<Vec<Rc<Span>>>,
+    /// Children which haven't been transformed by the display mode yet.
+    pub lazy_children: RefCell<Option<LazyChildren>>,
     pub display_children: RefCell<Vec<Rc<Span>>>,
     pub min_start_time: Cell<TimePoint>,
     pub max_end_time: Cell<TimePoint>,
@@ -63,24 +263,167 @@ pub struct Span {
     /// - `Some(vec![])`: Span marked for grouping
     /// - `Some(vec![...])`: Grouped span with actual active segments
     pub active_segments: Option<Vec<(TimePoint, TimePoint)>>,
+    /// The spans merged into a grouped span, sorted by start time. Empty for other spans.
+    pub grouped_spans: Vec<Rc<Span>>,
+    /// The parent of the span was recorded on another node.
+    pub remote_parent: bool,
+    /// The span has a parent span id, but the parent isn't in the trace.
+    pub orphan: bool,
+}
+
+impl Deref for Span {
+    type Target = SpanData;
+
+    fn deref(&self) -> &SpanData {
+        &self.data
+    }
 }
 
 impl Span {
-    pub fn is_ancestor_or_self(&self, target_span_id: &Vec<u8>) -> bool {
-        if self.span_id == *target_span_id {
+    /// Creates a span with default display state, named after the original span name.
+    pub fn new(data: Arc<SpanData>) -> Span {
+        let start_time = data.start_time;
+        let end_time = data.end_time;
+        Span {
+            name: data.original_name.clone(),
+            data,
+            children: RefCell::new(Vec::new()),
+            lazy_children: RefCell::new(None),
+            display_children: RefCell::new(Vec::new()),
+            min_start_time: Cell::new(start_time),
+            max_end_time: Cell::new(end_time),
+            display_options: SpanDisplayConfig {
+                display_length: DisplayLength::Time,
+            },
+            collapse_children: Cell::new(false),
+            dont_collapse_this_span: Cell::new(false),
+            parent_height_offset: Cell::new(0),
+            display_start: Cell::new(0.0),
+            display_length: Cell::new(0.0),
+            time_display_length: Cell::new(0.0),
+            incoming_relations: RefCell::new(Vec::new()),
+            outgoing_relations: RefCell::new(Vec::new()),
+            active_segments: None,
+            grouped_spans: Vec::new(),
+            remote_parent: false,
+            orphan: false,
+        }
+    }
+
+    /// Children of the span, transforms the lazy children first if there are any.
+    pub fn children(&self) -> Ref<'_, Vec<Rc<Span>>> {
+        let lazy_children = self.lazy_children.borrow_mut().take();
+        if let Some(lazy_children) = lazy_children {
+            *self.children.borrow_mut() = lazy_children
+                .original
+                .iter()
+                .map(|child| (lazy_children.transform)(child))
+                .collect();
+        }
+        self.children.borrow()
+    }
+
+    /// Mutable children of the span, transforms the lazy children first if there are any.
+    pub fn children_mut(&self) -> RefMut<'_, Vec<Rc<Span>>> {
+        drop(self.children());
+        self.children.borrow_mut()
+    }
+
+    /// Replaces the children of the span, including the lazy ones.
+    pub fn set_children(&self, children: Vec<Rc<Span>>) {
+        self.lazy_children.borrow_mut().take();
+        *self.children.borrow_mut() = children;
+    }
+
+    /// Children which were already transformed, lazy children are skipped. Only for readers which
+    /// must not transform the whole tree, everything else should use [Span::children].
+    pub fn transformed_children(&self) -> Ref<'_, Vec<Rc<Span>>> {
+        self.children.borrow()
+    }
+
+    /// True if the span has children, without transforming the lazy ones.
+    pub fn has_children(&self) -> bool {
+        !self.children.borrow().is_empty() || self.has_lazy_children()
+    }
+
+    /// True if the children haven't been transformed yet. Lazy children never contain spans which
+    /// are visible in the display mode, so they don't have to be transformed until the user expands
+    /// the span or something needs to go over the whole tree.
+    pub fn has_lazy_children(&self) -> bool {
+        self.lazy_children.borrow().is_some()
+    }
+
+    /// Screen x positions of the span's events, for the event markers drawn inside of the span.
+    /// Uses the display params set for the current frame, events outside of the span are skipped.
+    pub fn event_marker_positions(&self) -> Vec<(f32, &Event)> {
+        let duration = self.end_time - self.start_time;
+        if duration <= 0.0 {
+            return Vec::new();
+        }
+        let start_x = self.display_start.get();
+        let width = self.time_display_length.get();
+        self.events
+            .iter()
+            .filter(|e| e.time >= self.start_time && e.time <= self.end_time)
+            .map(|e| {
+                let x = start_x + ((e.time - self.start_time) / duration) as f32 * width;
+                (x, e)
+            })
+            .collect()
+    }
+
+    pub fn is_ancestor_or_self(&self, target_span_id: SpanId) -> bool {
+        if self.span_id == target_span_id {
             return true;
         }
-        for child in self.children.borrow().iter() {
+        for child in self.children().iter() {
             if child.is_ancestor_or_self(target_span_id) {
                 return true;
             }
         }
         false
     }
+}
 
-    pub fn original_name(&self) -> &str {
-        // TODO - should this be a field or an attribute?
-        &self.original_name
+/// Display mode transformation of a single span, including its subtree.
+pub type SpanTransform = Rc<dyn Fn(&Rc<Span>) -> Rc<Span>>;
+
+/// Untransformed children of a span and the display mode transformation to apply to each of them.
+#[derive(Clone)]
+pub struct LazyChildren {
+    pub original: Vec<Rc<Span>>,
+    pub transform: SpanTransform,
+}
+
+impl fmt::Debug for LazyChildren {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyChildren")
+            .field("original", &self.original.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Sets `min_start_time` and `max_end_time` to the time range of the span and all its descendants.
+/// Spans with lazy children keep their current range, it was computed on the original tree.
+pub fn set_min_max_time(spans: &[Rc<Span>]) {
+    for span in spans {
+        if span.has_lazy_children() {
+            continue;
+        }
+
+        let mut min_start_time = span.start_time;
+        let mut max_end_time = span.end_time;
+
+        let children = span.children.borrow();
+        set_min_max_time(children.as_slice());
+
+        for child in children.iter() {
+            min_start_time = min_start_time.min(child.min_start_time.get());
+            max_end_time = max_end_time.max(child.max_end_time.get());
+        }
+
+        span.min_start_time.set(min_start_time);
+        span.max_end_time.set(max_end_time);
     }
 }
 
@@ -199,7 +542,7 @@ pub fn stringify_span(span: &Rc<Span>, include_children: bool) -> String {
 
     if include_children {
         s.push_str(" children: [");
-        for child in span.children.borrow().iter() {
+        for child in span.children().iter() {
             s.push_str(&format!("\n  {}", stringify_span(child, true)));
         }
         s.push_str("],\n");
@@ -0,0 +1,110 @@
+//! Undo history of the edit dialogs. The dialogs store a snapshot of the edited list before every
+//! change (add, delete, modify, reorder), undo and redo swap the current list with the snapshots.
+
+use eframe::egui::{Button, Key, KeyboardShortcut, Modifiers, Ui};
+
+/// Maximum number of changes that can be undone, older snapshots are dropped.
+const MAX_UNDO_STEPS: usize = 100;
+
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Y);
+const REDO_SHORTCUT_SHIFT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+
+#[derive(Debug, Clone)]
+pub struct UndoStack<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+}
+
+impl<T> Default for UndoStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> UndoStack<T> {
+    pub fn new() -> UndoStack<T> {
+        UndoStack {
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Records the state from before a change. Clears the redo history, it doesn't apply to the
+    /// new state.
+    pub fn push(&mut self, state_before_change: T) {
+        if self.undo.len() == MAX_UNDO_STEPS {
+            self.undo.remove(0);
+        }
+        self.undo.push(state_before_change);
+        self.redo.clear();
+    }
+
+    /// Replaces `current` with the state from before the last change. Returns false when there is
+    /// nothing to undo.
+    pub fn undo(&mut self, current: &mut T) -> bool {
+        match self.undo.pop() {
+            Some(previous) => {
+                self.redo.push(std::mem::replace(current, previous));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reapplies the last undone change. Returns false when there is nothing to redo.
+    pub fn redo(&mut self, current: &mut T) -> bool {
+        match self.redo.pop() {
+            Some(next) => {
+                self.undo.push(std::mem::replace(current, next));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Draws "Undo" and "Redo" buttons and handles Ctrl+Z, Ctrl+Y and Ctrl+Shift+Z. The shortcuts
+    /// are ignored while a text field is focused, text fields have their own undo. Returns true
+    /// when `current` was changed.
+    pub fn draw_buttons(&mut self, ui: &mut Ui, current: &mut T) -> bool {
+        let mut undo_clicked = ui
+            .add_enabled(self.can_undo(), Button::new("Undo"))
+            .on_hover_text("Ctrl+Z")
+            .clicked();
+        let mut redo_clicked = ui
+            .add_enabled(self.can_redo(), Button::new("Redo"))
+            .on_hover_text("Ctrl+Y")
+            .clicked();
+        if !ui.ctx().wants_keyboard_input() {
+            ui.input_mut(|i| {
+                // Ctrl+Shift+Z has to be checked first, consume_shortcut ignores extra modifiers.
+                if i.consume_shortcut(&REDO_SHORTCUT_SHIFT) || i.consume_shortcut(&REDO_SHORTCUT) {
+                    redo_clicked = true;
+                } else if i.consume_shortcut(&UNDO_SHORTCUT) {
+                    undo_clicked = true;
+                }
+            });
+        }
+        if undo_clicked {
+            self.undo(current)
+        } else if redo_clicked {
+            self.redo(current)
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Report of spans which should take part in a relation, but weren't matched with any
+//! counterpart. A dropped message shows up as a send without a receive, which is easy to miss when
+//! looking only at the arrows that exist.
+
+use std::collections::{BTreeMap, HashSet};
+
+use eframe::egui::{self, Grid, Key, Modal, RichText, ScrollArea};
+
+use crate::analyze_utils::ModalFocus;
+use crate::colors::{node_color, theme};
+use crate::relation::{UnmatchedSide, UnmatchedSpan};
+use crate::span_id::SpanId;
+use crate::types::TimeFormat;
+
+/// Why the span is in the report, e.g. "no \"to\" span".
+pub fn unmatched_description(unmatched: &UnmatchedSpan) -> &'static str {
+    match unmatched.side {
+        UnmatchedSide::From => "no \"to\" span",
+        UnmatchedSide::To if unmatched.relation.is_fan_in() => "not enough \"from\" spans",
+        UnmatchedSide::To => "no \"from\" span",
+    }
+}
+
+/// Number of unmatched spans per (relation name, side), sorted by the relation name.
+pub fn count_unmatched(unmatched: &[UnmatchedSpan]) -> BTreeMap<(String, UnmatchedSide), usize> {
+    let mut counts = BTreeMap::new();
+    for u in unmatched {
+        *counts.entry((u.relation.name.clone(), u.side)).or_default() += 1;
+    }
+    counts
+}
+
+#[derive(Default)]
+pub struct UnmatchedRelationsModal {
+    pub show: bool,
+    unmatched: Vec<UnmatchedSpan>,
+    marked_span_ids: HashSet<SpanId>,
+    /// Mark the unmatched spans in the span view with a red badge.
+    pub mark_in_view: bool,
+    pub focus: ModalFocus,
+}
+
+impl UnmatchedRelationsModal {
+    pub fn new() -> UnmatchedRelationsModal {
+        UnmatchedRelationsModal::default()
+    }
+
+    /// Replaces the report with spans found by the last relation search.
+    pub fn set_unmatched(&mut self, unmatched: Vec<UnmatchedSpan>) {
+        self.marked_span_ids = unmatched.iter().map(|u| u.span.span_id).collect();
+        self.unmatched = unmatched;
+    }
+
+    pub fn clear(&mut self) {
+        self.unmatched.clear();
+        self.marked_span_ids.clear();
+        self.focus.clear();
+    }
+
+    pub fn unmatched(&self) -> &[UnmatchedSpan] {
+        &self.unmatched
+    }
+
+    /// Whether the span should get a badge in the span view.
+    pub fn is_marked(&self, span_id: SpanId) -> bool {
+        self.mark_in_view && self.marked_span_ids.contains(&span_id)
+    }
+
+    pub fn show_modal(
+        &mut self,
+        ctx: &egui::Context,
+        time_format: &TimeFormat,
+        max_width: f32,
+        max_height: f32,
+    ) {
+        if !self.show {
+            return;
+        }
+
+        let mut jump_to_span = None;
+        Modal::new("unmatched relations".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Unmatched relation spans");
+            ui.label(
+                "Spans of relations with \"Report unmatched spans\" enabled, which weren't matched \
+                 with any counterpart within the time limits of the relation.",
+            );
+            ui.checkbox(
+                &mut self.mark_in_view,
+                "Mark unmatched spans in the span view with a red badge",
+            );
+            ui.separator();
+
+            Grid::new("unmatched relations summary")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Relation");
+                    ui.strong("Missing");
+                    ui.strong("Spans");
+                    ui.end_row();
+                    for ((relation_name, side), count) in count_unmatched(&self.unmatched) {
+                        ui.label(relation_name);
+                        ui.label(match side {
+                            UnmatchedSide::From => "\"to\" span",
+                            UnmatchedSide::To => "\"from\" span",
+                        });
+                        ui.label(count.to_string());
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+
+            let row_height = ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+            ScrollArea::vertical()
+                .max_height(max_height - 250.0)
+                .auto_shrink([false, true])
+                .show_rows(ui, row_height, self.unmatched.len(), |ui, row_range| {
+                    Grid::new("unmatched relations list")
+                        .striped(true)
+                        .start_row(row_range.start)
+                        .show(ui, |ui| {
+                            for unmatched in &self.unmatched[row_range] {
+                                let span = &unmatched.span;
+                                ui.monospace(time_format.display_string(span.start_time));
+                                ui.label(
+                                    RichText::new(&span.node.name)
+                                        .color(node_color(&span.node.name)),
+                                );
+                                if ui
+                                    .link(&span.name)
+                                    .on_hover_text("Click to show the span")
+                                    .clicked()
+                                {
+                                    jump_to_span = Some(span.clone());
+                                }
+                                ui.label(&unmatched.relation.name);
+                                ui.label(
+                                    RichText::new(unmatched_description(unmatched))
+                                        .color(theme().error),
+                                );
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            ui.separator();
+            if ui.button("Close").clicked() || ui.input(|i| i.key_pressed(Key::Escape)) {
+                self.show = false;
+            }
+        });
+
+        if let Some(span) = jump_to_span {
+            self.focus.jump_to(span);
+            self.show = false;
+        }
+    }
+}
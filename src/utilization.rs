@@ -0,0 +1,343 @@
+//! Utilization lane - a mini bar chart above each lane which shows, for every time bucket, the
+//! fraction of time covered by spans matching a selector (e.g. all `apply_*` spans). It
+//! approximates how busy a node (or an actor on it) was using only the traces.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use eframe::egui::{self, DragValue, Modal, Pos2, Rect, Sense, Ui};
+
+use crate::colors::theme;
+use crate::edit_modes::EditDisplayModes;
+use crate::structured_modes::{MatchCondition, MatchOperator, SpanSelector};
+use crate::types::{Span, TimePoint};
+
+/// Buckets which are busier than this are drawn in a different color.
+pub const SATURATED_FRACTION: f64 = 0.95;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct UtilizationLaneSettings {
+    pub enabled: bool,
+    /// Time covered by spans matching this selector counts as busy.
+    pub selector: SpanSelector,
+    /// Width of one bar of the chart.
+    pub bucket_width_px: f32,
+}
+
+impl Default for UtilizationLaneSettings {
+    fn default() -> Self {
+        UtilizationLaneSettings {
+            enabled: false,
+            selector: SpanSelector {
+                span_name_condition: MatchCondition {
+                    operator: MatchOperator::Regex,
+                    value: "^apply_".to_string(),
+                },
+                node_name_condition: MatchCondition::any(),
+                attribute_conditions: vec![],
+            },
+            bucket_width_px: 6.0,
+        }
+    }
+}
+
+/// Fraction of each of the `buckets` equal parts of `start..end` which is covered by spans
+/// matching the selector. `spans` are searched recursively, overlapping spans are counted once.
+pub fn compute_utilization<'a>(
+    spans: impl Iterator<Item = &'a Rc<Span>>,
+    selector: &SpanSelector,
+    start: TimePoint,
+    end: TimePoint,
+    buckets: usize,
+) -> Vec<f64> {
+    let mut fractions = vec![0.0; buckets];
+    if buckets == 0 || end <= start {
+        return fractions;
+    }
+
+    let mut intervals = Vec::new();
+    for span in spans {
+        collect_busy_intervals(span, selector, start, end, &mut intervals);
+    }
+    intervals.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // Merge overlapping intervals, then add each of them to the buckets it covers
+    let bucket_width = (end - start) / buckets as f64;
+    let mut add_interval = |(from, to): (TimePoint, TimePoint)| {
+        let first_bucket = ((from - start) / bucket_width) as usize;
+        for (bucket, fraction) in fractions.iter_mut().enumerate().skip(first_bucket) {
+            let bucket_start = start + bucket as f64 * bucket_width;
+            if bucket_start >= to {
+                break;
+            }
+            let covered = to.min(bucket_start + bucket_width) - from.max(bucket_start);
+            *fraction += covered.max(0.0) / bucket_width;
+        }
+    };
+    let mut current: Option<(TimePoint, TimePoint)> = None;
+    for (from, to) in intervals {
+        match &mut current {
+            Some((_, current_to)) if from <= *current_to => *current_to = current_to.max(to),
+            _ => {
+                if let Some(interval) = current.replace((from, to)) {
+                    add_interval(interval);
+                }
+            }
+        }
+    }
+    if let Some(interval) = current {
+        add_interval(interval);
+    }
+    for fraction in &mut fractions {
+        *fraction = fraction.min(1.0);
+    }
+    fractions
+}
+
+fn collect_busy_intervals(
+    span: &Rc<Span>,
+    selector: &SpanSelector,
+    start: TimePoint,
+    end: TimePoint,
+    intervals: &mut Vec<(TimePoint, TimePoint)>,
+) {
+    if span.start_time < end && span.end_time > start && selector.matches(span) {
+        intervals.push((span.start_time.max(start), span.end_time.min(end)));
+    }
+    for child in span.children().iter() {
+        collect_busy_intervals(child, selector, start, end, intervals);
+    }
+}
+
+/// `width` rounded to a power of two, so that it stays the same while zooming a little.
+pub fn quantize_bucket_width(width: f64) -> f64 {
+    if width <= 0.0 || !width.is_finite() {
+        return width;
+    }
+    2f64.powf(width.log2().round())
+}
+
+/// Time range that the value of a lane was computed for, aligned to a grid of `bucket_width` wide
+/// buckets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LaneRange {
+    pub start: TimePoint,
+    pub end: TimePoint,
+    pub bucket_width: f64,
+}
+
+impl LaneRange {
+    /// The range around `start..end` with a margin of the window's length on both sides, so that
+    /// the value can be reused while the view is scrolled.
+    pub fn around(start: TimePoint, end: TimePoint, bucket_width: f64) -> LaneRange {
+        let margin = end - start;
+        LaneRange {
+            start: ((start - margin) / bucket_width).floor() * bucket_width,
+            end: ((end + margin) / bucket_width).ceil() * bucket_width,
+            bucket_width,
+        }
+    }
+
+    pub fn buckets(&self) -> usize {
+        ((self.end - self.start) / self.bucket_width).round() as usize
+    }
+}
+
+struct CachedLaneValue<T> {
+    /// [`crate::span_index::SpanIntervalIndex::id`] of the lane's spans.
+    contents: u64,
+    range: LaneRange,
+    value: T,
+}
+
+/// Values computed for every lane (e.g. the utilization) over a range around the time window.
+/// Recomputed when the spans of the lane or the quantized bucket width change, or when the window
+/// leaves the range.
+pub struct LaneCache<T> {
+    lanes: HashMap<String, CachedLaneValue<T>>,
+}
+
+impl<T> Default for LaneCache<T> {
+    fn default() -> Self {
+        LaneCache {
+            lanes: HashMap::new(),
+        }
+    }
+}
+
+impl<T> LaneCache<T> {
+    pub fn clear(&mut self) {
+        self.lanes.clear();
+    }
+
+    /// The value of the lane covering `start..end`, `compute` gets the range to compute it for.
+    pub fn get_or_compute(
+        &mut self,
+        lane_name: &str,
+        contents: u64,
+        start: TimePoint,
+        end: TimePoint,
+        bucket_width: f64,
+        compute: impl FnOnce(&LaneRange) -> T,
+    ) -> (&T, LaneRange) {
+        let is_valid = |cached: &CachedLaneValue<T>| {
+            cached.contents == contents
+                && cached.range.bucket_width == bucket_width
+                && cached.range.start <= start
+                && end <= cached.range.end
+        };
+        if !self.lanes.get(lane_name).is_some_and(is_valid) {
+            let range = LaneRange::around(start, end, bucket_width);
+            let value = compute(&range);
+            self.lanes.insert(
+                lane_name.to_string(),
+                CachedLaneValue {
+                    contents,
+                    range,
+                    value,
+                },
+            );
+        }
+        let cached = &self.lanes[lane_name];
+        (&cached.value, cached.range)
+    }
+}
+
+/// Draws the part of the fractions of the buckets of `range` which is in `start..end` as bars
+/// filling `rect`, hovering shows the value of a bar.
+pub fn draw_utilization_strip(
+    ui: &mut Ui,
+    rect: Rect,
+    fractions: &[f64],
+    range: &LaneRange,
+    (start, end): (TimePoint, TimePoint),
+    id_salt: &str,
+) {
+    ui.painter().rect_filled(rect, 0.0, theme().lane_background);
+    if fractions.is_empty() || end <= start {
+        return;
+    }
+    let to_x = |time: TimePoint| {
+        rect.min.x + ((time.clamp(start, end) - start) / (end - start)) as f32 * rect.width()
+    };
+    let bucket_at = |time: TimePoint| {
+        (((time - range.start) / range.bucket_width).max(0.0) as usize).min(fractions.len() - 1)
+    };
+    let first = bucket_at(start);
+    for (i, &fraction) in fractions
+        .iter()
+        .enumerate()
+        .take(bucket_at(end) + 1)
+        .skip(first)
+    {
+        if fraction <= 0.0 {
+            continue;
+        }
+        let bucket_start = range.start + i as f64 * range.bucket_width;
+        let bar = Rect::from_min_max(
+            Pos2::new(
+                to_x(bucket_start),
+                rect.max.y - fraction as f32 * rect.height(),
+            ),
+            Pos2::new(to_x(bucket_start + range.bucket_width), rect.max.y),
+        );
+        let color = if fraction >= SATURATED_FRACTION {
+            theme().chart_alert
+        } else {
+            theme().chart_fill
+        };
+        ui.painter().rect_filled(bar, 0.0, color);
+    }
+
+    let response = ui.interact(rect, ui.id().with(("utilization", id_salt)), Sense::hover());
+    if let Some(pointer) = response.hover_pos() {
+        let time = start + ((pointer.x - rect.min.x) / rect.width()) as f64 * (end - start);
+        let fraction = fractions[bucket_at(time)];
+        response.on_hover_text_at_pointer(format!("{:.0}% busy", fraction * 100.0));
+    }
+}
+
+/// Dialog for the settings of the utilization lane.
+pub struct EditUtilizationLane {
+    show: bool,
+    settings: UtilizationLaneSettings,
+}
+
+impl Default for EditUtilizationLane {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditUtilizationLane {
+    pub fn new() -> EditUtilizationLane {
+        EditUtilizationLane {
+            show: false,
+            settings: UtilizationLaneSettings::default(),
+        }
+    }
+
+    pub fn open(&mut self, settings: UtilizationLaneSettings) {
+        self.settings = settings;
+        self.show = true;
+    }
+
+    /// Returns the new settings when the user clicks "Save".
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        max_width: f32,
+        max_height: f32,
+    ) -> Option<UtilizationLaneSettings> {
+        if !self.show {
+            return None;
+        }
+
+        let mut result = None;
+        Modal::new("edit utilization lane".into()).show(ctx, |ui| {
+            ui.set_max_width(max_width);
+            ui.set_max_height(max_height);
+
+            ui.heading("Utilization lane");
+            ui.label(
+                "A bar chart above each lane shows which fraction of time is covered by the \
+                 spans matching the selector.",
+            );
+            ui.separator();
+
+            ui.checkbox(&mut self.settings.enabled, "Show the utilization lane");
+            ui.horizontal(|ui| {
+                ui.label("Bar width:");
+                ui.add(
+                    DragValue::new(&mut self.settings.bucket_width_px)
+                        .range(1.0..=100.0)
+                        .suffix(" px"),
+                );
+            });
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .max_height(max_height - 200.0)
+                .show(ui, |ui| {
+                    EditDisplayModes::draw_edit_span_selector(
+                        &mut self.settings.selector,
+                        ui,
+                        max_width,
+                        "utilization selector",
+                    );
+                });
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    result = Some(self.settings.clone());
+                    self.show = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.show = false;
+                }
+            });
+        });
+
+        result
+    }
+}
@@ -0,0 +1,168 @@
+//! Links which open a trace file at a specific view, so that a view can be shared with someone who
+//! has the same file. The link is a `traviz://view?...` URI, which can be passed as the command line
+//! argument instead of a file path.
+
+use anyhow::{bail, Context, Result};
+use sha2::Digest;
+
+use crate::span_id::SpanId;
+use crate::types::TimePoint;
+
+pub const VIEW_LINK_PREFIX: &str = "traviz://view?";
+
+/// Number of hex characters of the SHA-256 hash of the file which are kept in the link.
+const FILE_HASH_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewLink {
+    /// Absolute path of the trace file.
+    pub file: String,
+    /// Hash of the file contents, used to warn when the file isn't the one the link was made for.
+    pub file_hash: Option<String>,
+    pub start: TimePoint,
+    pub end: TimePoint,
+    pub display_mode: Option<String>,
+    pub node_filter: Option<String>,
+    pub relation_view: Option<String>,
+    pub highlighted_span_ids: Vec<SpanId>,
+}
+
+impl ViewLink {
+    pub fn to_uri(&self) -> String {
+        let mut params = vec![("file", percent_encode(&self.file))];
+        if let Some(hash) = &self.file_hash {
+            params.push(("hash", percent_encode(hash)));
+        }
+        // The default formatting of f64 is the shortest one which parses back to the same value.
+        params.push(("start", self.start.to_string()));
+        params.push(("end", self.end.to_string()));
+        for (key, value) in [
+            ("mode", &self.display_mode),
+            ("filter", &self.node_filter),
+            ("relations", &self.relation_view),
+        ] {
+            if let Some(value) = value {
+                params.push((key, percent_encode(value)));
+            }
+        }
+        if !self.highlighted_span_ids.is_empty() {
+            let ids: Vec<String> = self
+                .highlighted_span_ids
+                .iter()
+                .map(|id| id.to_hex())
+                .collect();
+            params.push(("highlight", ids.join(",")));
+        }
+
+        let query: Vec<String> = params
+            .into_iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        format!("{VIEW_LINK_PREFIX}{}", query.join("&"))
+    }
+
+    /// Parses a link created by [ViewLink::to_uri]. Unknown parameters are ignored.
+    pub fn parse(uri: &str) -> Result<ViewLink> {
+        let Some(query) = uri.trim().strip_prefix(VIEW_LINK_PREFIX) else {
+            bail!("a view link has to start with {VIEW_LINK_PREFIX}");
+        };
+
+        let mut file = None;
+        let mut start = None;
+        let mut end = None;
+        let mut link = ViewLink {
+            file: String::new(),
+            file_hash: None,
+            start: 0.0,
+            end: 0.0,
+            display_mode: None,
+            node_filter: None,
+            relation_view: None,
+            highlighted_span_ids: Vec::new(),
+        };
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = percent_decode(value).with_context(|| format!("invalid value of {key}"))?;
+            let parse_time = |value: &str| {
+                value
+                    .parse::<TimePoint>()
+                    .ok()
+                    .filter(|time| time.is_finite())
+                    .with_context(|| format!("invalid time '{value}'"))
+            };
+            match key {
+                "file" => file = Some(value),
+                "hash" => link.file_hash = Some(value),
+                "start" => start = Some(parse_time(&value)?),
+                "end" => end = Some(parse_time(&value)?),
+                "mode" => link.display_mode = Some(value),
+                "filter" => link.node_filter = Some(value),
+                "relations" => link.relation_view = Some(value),
+                "highlight" => {
+                    for id in value.split(',').filter(|id| !id.is_empty()) {
+                        let bytes =
+                            hex::decode(id).with_context(|| format!("invalid span id '{id}'"))?;
+                        link.highlighted_span_ids.push(SpanId::intern(&bytes));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        link.file = file
+            .filter(|file| !file.is_empty())
+            .context("the link doesn't have a file")?;
+        let (Some(start), Some(end)) = (start, end) else {
+            bail!("the link doesn't have a time range");
+        };
+        if start > end {
+            bail!("the start of the time range is after its end");
+        }
+        link.start = start;
+        link.end = end;
+        Ok(link)
+    }
+}
+
+/// Whether the command line argument is a view link rather than a file path.
+pub fn is_view_link(arg: &str) -> bool {
+    arg.starts_with(VIEW_LINK_PREFIX)
+}
+
+/// Short hash of the file contents, stored in view links.
+pub fn file_hash(bytes: &[u8]) -> String {
+    let digest = sha2::Sha256::digest(bytes);
+    hex::encode(digest)[..FILE_HASH_LENGTH].to_string()
+}
+
+/// Escapes everything except unreserved characters and '/', which keeps paths readable.
+pub(crate) fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+fn percent_decode(value: &str) -> Result<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex_digits = bytes
+                .get(i + 1..i + 3)
+                .context("incomplete escape sequence")?;
+            decoded.extend(hex::decode(hex_digits).context("invalid escape sequence")?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(String::from_utf8(decoded)?)
+}
@@ -1,8 +1,13 @@
 use approx::assert_abs_diff_eq;
 use traviz::analyze_dependency::{
-    AnalysisCardinality, AnalyzeDependencyModal, GroupAggregationStrategy, SourceScope,
-    SourceTimingStrategy,
+    dependency_to_relation, AnalysisCardinality, AnalyzeDependencyModal, ContextLinking,
+    DependencyPreset, GroupAggregationStrategy, LinkingPattern, SourceScope, SourceTimingStrategy,
 };
+use traviz::node_filter::{NodeFilter, NodeRule};
+use traviz::relation::{AttributeRelationOp, MatchType, RelationNodesConfig};
+use traviz::span_id::SpanId;
+use traviz::structured_modes::MatchCondition;
+use traviz::types::{Span, SpanData};
 
 mod test_helpers;
 use test_helpers::{string_attr, ScenarioBuilder, SpanConfig, TestScenario, TimeInterval};
@@ -2214,12 +2219,12 @@ fn test_span_reuse_prevention_same_node() {
             "Should use exactly 2 target spans (threshold)"
         );
 
-        let source_id = &link.source_spans[0].span_id;
+        let source_id = link.source_spans[0].span_id;
         assert!(
-            !all_used_source_ids.contains(source_id),
+            !all_used_source_ids.contains(&source_id),
             "Source span should not be reused across multiple links"
         );
-        all_used_source_ids.insert(source_id.clone());
+        all_used_source_ids.insert(source_id);
     }
 }
 
@@ -3352,3 +3357,448 @@ fn test_relative_offset_matching_invalid_syntax() {
         }
     }
 }
+
+/// Tests opening the modal from a clicked span, the span's name is used as the source.
+#[test]
+fn test_open_for_source_span() {
+    let scenario = TestScenario::one_to_n_same_node();
+
+    let mut modal = AnalyzeDependencyModal::new();
+    modal.open_for_source_span(&scenario.all_spans, "source", None);
+    assert!(modal.show);
+    assert_eq!(modal.get_source_span_name(), Some(&"source".to_string()));
+    assert_eq!(modal.get_source_search_text(), "source");
+    assert_eq!(modal.get_target_span_name(), None);
+}
+
+/// Nodes hidden by the node filter aren't analyzed when the modal is opened from a clicked span.
+#[test]
+fn test_open_for_source_span_with_node_filter() {
+    let scenario = TestScenario::one_to_n_cross_node();
+    let mut node_filter = NodeFilter::show_all();
+    node_filter.rules.insert(
+        0,
+        NodeRule {
+            name: "Hide node_3".to_string(),
+            condition: MatchCondition::equal_to("node_3"),
+            visible: false,
+        },
+    );
+
+    let mut modal = AnalyzeDependencyModal::new();
+    modal.open_for_source_span(&scenario.all_spans, "source", Some(&node_filter));
+    modal.set_target_span_name(Some("target".to_string()));
+    modal.set_threshold(2);
+    modal.set_source_scope(SourceScope::AllNodes);
+    modal.set_analysis_cardinality(AnalysisCardinality::OneToN);
+    modal.analyze_dependencies();
+
+    // Without node_3 only one target is left, which is below the threshold
+    let links = modal.get_links_for_node("node_1");
+    assert_eq!(links.map_or(0, |links| links.len()), 0);
+}
+
+/// Only the spans intersecting the selected time range are linked when the analysis is restricted.
+#[test]
+fn test_only_selected_time_range() {
+    let mut builder = ScenarioBuilder::new();
+    builder.add_node("node_a");
+    for start in [0.0, 10.0, 20.0] {
+        builder.add_span(SpanConfig::new(
+            "task",
+            "node_a",
+            TimeInterval::with_duration(start, 1.0),
+        ));
+        builder.add_span(SpanConfig::new(
+            "process",
+            "node_a",
+            TimeInterval::with_duration(start + 2.0, 1.0),
+        ));
+    }
+    let scenario = builder.build();
+
+    let mut modal = AnalyzeDependencyModal::new();
+    modal.update_span_list(&scenario.all_spans);
+    modal.set_source_span_name(Some("task".to_string()));
+    modal.set_target_span_name(Some("process".to_string()));
+    modal.set_only_selected_time_range(true);
+    modal.selected_time_range = (9.0, 15.0);
+    modal.analyze_dependencies();
+
+    let result = modal.analysis_result.as_ref().unwrap();
+    assert_eq!(result.time_range, Some((9.0, 15.0)));
+    assert_eq!(result.overall_stats.count, 1);
+    assert_eq!(result.delays_seconds(None), &[1.0]);
+    assert_eq!(result.delays_seconds(Some("node_a")), &[1.0]);
+    assert!(result.delays_seconds(Some("node_b")).is_empty());
+
+    modal.selected_time_range = (30.0, 40.0);
+    modal.analyze_dependencies();
+    assert!(modal.analysis_result.is_none());
+    assert_eq!(
+        modal.get_error_message(),
+        Some(&"No spans found with name 'task' in the selected time range".to_string())
+    );
+}
+
+/// A saved preset fills in the whole configuration.
+#[test]
+fn test_dependency_preset_round_trip() {
+    let mut modal = AnalyzeDependencyModal::new();
+    let mut presets = Vec::new();
+    assert!(modal.save_preset(&mut presets, "incomplete").is_err());
+
+    modal.set_source_span_name(Some("produce_block".to_string()));
+    modal.set_target_span_name(Some("apply_block".to_string()));
+    modal.set_threshold(3);
+    modal.set_linking_attribute("height".to_string());
+    modal.set_source_scope(SourceScope::AllNodes);
+    modal.set_analysis_cardinality(AnalysisCardinality::OneToN);
+    modal.set_source_timing_strategy(SourceTimingStrategy::LatestFirst);
+    modal.set_group_aggregation_strategy(GroupAggregationStrategy::WaitForLastGroup);
+    assert!(modal.save_preset(&mut presets, "  ").is_err());
+    modal.save_preset(&mut presets, "blocks").unwrap();
+    assert!(modal.presets_changed);
+    assert_eq!(presets.len(), 1);
+
+    // Saving under the same name replaces the preset.
+    modal.set_threshold(4);
+    modal.save_preset(&mut presets, "blocks").unwrap();
+    assert_eq!(presets.len(), 1);
+    assert_eq!(presets[0].threshold, 4);
+
+    let mut other = AnalyzeDependencyModal::new();
+    other.apply_preset(&presets[0]);
+    assert_eq!(other.current_preset("blocks").as_ref(), Some(&presets[0]));
+    assert_eq!(other.get_source_search_text(), "produce_block");
+    assert_eq!(other.get_threshold(), 4);
+}
+
+fn preset(
+    linking_attribute: &str,
+    threshold: usize,
+    cardinality: AnalysisCardinality,
+) -> DependencyPreset {
+    DependencyPreset {
+        name: "endorsements".to_string(),
+        source_span_name: "send_chunk_endorsement".to_string(),
+        target_span_name: "produce_block".to_string(),
+        threshold,
+        linking_attribute: linking_attribute.to_string(),
+        context_linking: ContextLinking::None,
+        group_by_attribute: String::new(),
+        source_scope: SourceScope::AllNodes,
+        source_timing_strategy: SourceTimingStrategy::EarliestFirst,
+        group_aggregation_strategy: GroupAggregationStrategy::default(),
+        analysis_cardinality: cardinality,
+    }
+}
+
+#[test]
+fn test_dependency_to_relation() {
+    let relation = dependency_to_relation(&preset(
+        "shard_id, height=+1",
+        3,
+        AnalysisCardinality::NToOne,
+    ))
+    .unwrap();
+    assert_eq!(relation.name, "endorsements");
+    assert!(!relation.is_builtin);
+    assert_eq!(relation.nodes_config, RelationNodesConfig::AllNodes);
+    assert_eq!(relation.match_type, MatchType::MatchAll);
+    assert_eq!(relation.required_sources, 3);
+    let attributes: Vec<_> = relation
+        .attribute_relations
+        .iter()
+        .map(|a| {
+            (
+                a.from_attribute.as_str(),
+                a.to_attribute.as_str(),
+                a.relation.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        attributes,
+        vec![
+            ("shard_id", "shard_id", AttributeRelationOp::Equal),
+            ("height", "height", AttributeRelationOp::OneGreater),
+        ]
+    );
+
+    let relation = dependency_to_relation(&preset("", 1, AnalysisCardinality::OneToN)).unwrap();
+    assert!(relation.attribute_relations.is_empty());
+    assert_eq!(relation.match_type, MatchType::MatchClosest);
+    assert_eq!(relation.required_sources, 1);
+
+    assert!(dependency_to_relation(&preset("height=+2", 1, AnalysisCardinality::NToOne)).is_err());
+    assert!(dependency_to_relation(&preset("height~1", 1, AnalysisCardinality::NToOne)).is_err());
+    let mut context_preset = preset("", 1, AnalysisCardinality::NToOne);
+    context_preset.context_linking = ContextLinking::SameTrace;
+    assert!(dependency_to_relation(&context_preset).is_err());
+    assert!(dependency_to_relation(&preset("", 2, AnalysisCardinality::OneToN)).is_err());
+}
+
+#[test]
+fn test_linking_pattern_parse() {
+    let pattern = |attribute: &str, offset, tolerance, case_insensitive| LinkingPattern {
+        attribute: attribute.to_string(),
+        offset,
+        tolerance,
+        case_insensitive,
+    };
+    assert_eq!(
+        LinkingPattern::parse("height"),
+        pattern("height", None, None, false)
+    );
+    assert_eq!(
+        LinkingPattern::parse("height=+1"),
+        pattern("height", Some(1.0), None, false)
+    );
+    assert_eq!(
+        LinkingPattern::parse("height+1"),
+        pattern("height", Some(1.0), None, false)
+    );
+    assert_eq!(
+        LinkingPattern::parse("height-2"),
+        pattern("height", Some(-2.0), None, false)
+    );
+    assert_eq!(
+        LinkingPattern::parse("height+1~0.5"),
+        pattern("height", Some(1.0), Some(0.5), false)
+    );
+    assert_eq!(
+        LinkingPattern::parse("chain_id/i"),
+        pattern("chain_id", None, None, true)
+    );
+    assert_eq!(
+        LinkingPattern::parse("shard-id"),
+        pattern("shard-id", None, None, false)
+    );
+    assert_eq!(
+        LinkingPattern::parse("height=+invalid"),
+        pattern("height", None, None, false)
+    );
+}
+
+/// Derived keys, tolerances and case-insensitive patterns link spans whose values aren't equal.
+#[test]
+fn test_linking_pattern_tolerance_and_case_insensitive() {
+    let mut builder = ScenarioBuilder::new();
+    builder.add_node("node_a");
+    builder.add_span(
+        SpanConfig::new("chunk", "node_a", TimeInterval::with_duration(0.0, 1.0))
+            .with_string_attr("height", "100")
+            .with_string_attr("kind", "Endorsement"),
+    );
+    builder.add_span(
+        SpanConfig::new("block", "node_a", TimeInterval::with_duration(2.0, 1.0))
+            .with_string_attr("height", "101.2")
+            .with_string_attr("kind", "endorsement"),
+    );
+    let scenario = builder.build();
+
+    let link_count = |linking_attribute: &str| {
+        let mut modal = AnalyzeDependencyModal::new();
+        modal.update_span_list(&scenario.all_spans);
+        modal.set_source_span_name(Some("chunk".to_string()));
+        modal.set_target_span_name(Some("block".to_string()));
+        modal.set_linking_attribute(linking_attribute.to_string());
+        modal.analyze_dependencies();
+        modal.analysis_result.unwrap().overall_stats.count
+    };
+
+    assert_eq!(link_count("height+1"), 0);
+    assert_eq!(link_count("height+1~0.5"), 1);
+    assert_eq!(link_count("height~0.5"), 0);
+    assert_eq!(link_count("kind"), 0);
+    assert_eq!(link_count("kind/i"), 1);
+    assert_eq!(link_count("height+1~0.5, kind/i"), 1);
+}
+
+fn context_span(
+    name: &str,
+    start_time: f64,
+    span_id: u8,
+    trace_id: u8,
+    parent_span_id: Option<u8>,
+) -> std::rc::Rc<Span> {
+    std::rc::Rc::new(Span::new(std::sync::Arc::new(SpanData {
+        original_name: name.to_string(),
+        span_id: SpanId::intern(&[span_id]),
+        trace_id: SpanId::intern(&[trace_id]),
+        parent_span_id: parent_span_id.map_or(SpanId::EMPTY, |id| SpanId::intern(&[id])),
+        start_time,
+        end_time: start_time + 1.0,
+        attributes: Default::default(),
+        events: vec![],
+        node: test_helpers::create_test_node("node_a"),
+        scope: None,
+    })))
+}
+
+/// Context linking links spans from the same trace or spans which are parent and child.
+#[test]
+fn test_context_linking() {
+    // Two requests handled concurrently, the targets of each are children of its source.
+    let spans = vec![
+        context_span("request", 0.0, 1, 10, None),
+        context_span("request", 0.5, 2, 20, None),
+        context_span("respond", 3.0, 3, 10, Some(1)),
+        context_span("respond", 5.0, 4, 20, Some(2)),
+    ];
+
+    let delays = |context_linking: ContextLinking| {
+        let mut modal = AnalyzeDependencyModal::new();
+        modal.update_span_list(&spans);
+        modal.set_source_span_name(Some("request".to_string()));
+        modal.set_target_span_name(Some("respond".to_string()));
+        modal.set_source_timing_strategy(SourceTimingStrategy::LatestFirst);
+        modal.set_context_linking(context_linking);
+        modal.analyze_dependencies();
+        let result = modal.analysis_result.unwrap();
+        let mut delays: Vec<f64> = result.per_node_results["node_a"]
+            .links
+            .iter()
+            .map(|link| link.delay_seconds)
+            .collect();
+        delays.sort_by(f64::total_cmp);
+        delays
+    };
+
+    // Without context linking the latest request is linked to the first response.
+    assert_eq!(delays(ContextLinking::None), vec![1.5, 4.0]);
+    assert_eq!(delays(ContextLinking::SameTrace), vec![2.0, 3.5]);
+    assert_eq!(delays(ContextLinking::ParentChild), vec![2.0, 3.5]);
+}
+
+/// Context linking is added to the description only when it's used and can be parsed back.
+#[test]
+fn test_context_linking_description_round_trip() {
+    let mut modal = AnalyzeDependencyModal::new();
+    modal
+        .parse_and_fill_from_description(
+            "Analysis of dependency: 'request' -> 'respond' (cardinality: N-to-1, threshold: 1, linking by: none, group by: none, scope: self, timing: Earliest First, group aggregation: First Completed Group, context linking: parent/child)",
+        )
+        .unwrap();
+    assert_eq!(modal.get_context_linking(), &ContextLinking::ParentChild);
+
+    modal
+        .parse_and_fill_from_description(
+            "Analysis of dependency: 'request' -> 'respond' (cardinality: N-to-1, threshold: 1, linking by: none, group by: none, scope: self, timing: Earliest First, group aggregation: First Completed Group)",
+        )
+        .unwrap();
+    assert_eq!(modal.get_context_linking(), &ContextLinking::None);
+}
+
+/// The delay trend buckets links by the start of the target span.
+#[test]
+fn test_delay_trend() {
+    let mut builder = ScenarioBuilder::new();
+    builder.add_node("node_a");
+    // The delay grows from 1 s to 3 s over the trace
+    for (start, delay) in [(0.0, 1.0), (10.0, 1.0), (20.0, 2.0), (30.0, 3.0)] {
+        builder.add_span(SpanConfig::new(
+            "task",
+            "node_a",
+            TimeInterval::with_duration(start, 1.0),
+        ));
+        builder.add_span(SpanConfig::new(
+            "process",
+            "node_a",
+            TimeInterval::with_duration(start + 1.0 + delay, 0.5),
+        ));
+    }
+    let scenario = builder.build();
+
+    let mut modal = AnalyzeDependencyModal::new();
+    modal.update_span_list(&scenario.all_spans);
+    modal.set_source_span_name(Some("task".to_string()));
+    modal.set_target_span_name(Some("process".to_string()));
+    modal.analyze_dependencies();
+    let result = modal.analysis_result.as_ref().unwrap();
+
+    let trend = result.delay_trend(None, 2);
+    assert_eq!(trend.len(), 2);
+    assert_eq!((trend[0].start, trend[0].end), (2.0, 18.0));
+    assert_eq!(trend[0].count, 2);
+    assert_abs_diff_eq!(trend[0].mean_seconds, 1.0, epsilon = 1e-9);
+    assert_eq!(trend[1].count, 2);
+    assert_abs_diff_eq!(trend[1].mean_seconds, 2.5, epsilon = 1e-9);
+    assert_abs_diff_eq!(trend[1].p99_seconds, 3.0, epsilon = 1e-9);
+
+    assert_eq!(result.delay_trend(Some("node_a"), 2), trend);
+    assert!(result.delay_trend(Some("node_b"), 2).is_empty());
+}
+
+#[test]
+fn test_span_name_patterns() {
+    let mut builder = ScenarioBuilder::new();
+    builder.add_node("node_a");
+    builder.add_span(SpanConfig::new(
+        "apply_chunk_v1",
+        "node_a",
+        TimeInterval::with_duration(0.0, 1.0),
+    ));
+    builder.add_span(SpanConfig::new(
+        "apply_chunk_v2",
+        "node_a",
+        TimeInterval::with_duration(3.0, 1.0),
+    ));
+    builder.add_span(SpanConfig::new(
+        "process",
+        "node_a",
+        TimeInterval::with_duration(2.0, 1.0),
+    ));
+    builder.add_span(SpanConfig::new(
+        "process",
+        "node_a",
+        TimeInterval::with_duration(5.0, 1.0),
+    ));
+    let scenario = builder.build();
+
+    let mut modal = AnalyzeDependencyModal::new();
+    modal.update_span_list(&scenario.all_spans);
+    modal.set_target_span_name(Some("process".to_string()));
+    modal.set_threshold(1);
+    modal.set_source_scope(SourceScope::SameNode);
+    modal.set_analysis_cardinality(AnalysisCardinality::NToOne);
+
+    let delays = |modal: &AnalyzeDependencyModal| -> Vec<f64> {
+        let result = modal.analysis_result.as_ref().unwrap();
+        result.per_node_results["node_a"]
+            .links
+            .iter()
+            .map(|link| link.delay_seconds)
+            .collect()
+    };
+    for pattern in ["apply_chunk*", "apply_chunk_v?", "/^apply_chunk_v[12]$/"] {
+        modal.set_source_span_name(Some(pattern.to_string()));
+        modal.analyze_dependencies();
+        assert_eq!(delays(&modal), vec![1.0, 1.0], "{pattern}");
+    }
+
+    modal.set_source_span_name(Some("apply_chunk_v2".to_string()));
+    modal.analyze_dependencies();
+    assert_eq!(delays(&modal), vec![1.0]);
+
+    modal.set_source_span_name(Some("/apply_(/".to_string()));
+    modal.analyze_dependencies();
+    assert!(modal.analysis_result.is_none());
+}
+
+#[test]
+fn test_dependency_to_relation_with_span_name_pattern() {
+    let mut pattern_preset = preset("", 1, AnalysisCardinality::NToOne);
+    pattern_preset.source_span_name = "send_chunk_endorsement*".to_string();
+    let relation = dependency_to_relation(&pattern_preset).unwrap();
+    let source_condition = &relation.from_span_selector.span_name_condition;
+    assert!(source_condition.matches("send_chunk_endorsement_v2"));
+    assert!(!source_condition.matches("resend_chunk_endorsement"));
+    let target_condition = &relation.to_span_selector.span_name_condition;
+    assert!(target_condition.matches("produce_block"));
+    assert!(!target_condition.matches("produce_block_v2"));
+
+    pattern_preset.source_span_name = "/send_(/".to_string();
+    assert!(dependency_to_relation(&pattern_preset).is_err());
+}
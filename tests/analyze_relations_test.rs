@@ -0,0 +1,89 @@
+mod test_helpers;
+
+use std::rc::Rc;
+
+use test_helpers::*;
+use traviz::analyze_relations::relation_limit_stats;
+use traviz::builtin_relations::preprocess_block_to_postprocess_ready_block_relation;
+use traviz::relation::{Relation, RelationInstance};
+use traviz::Span;
+
+fn instance(relation: &Rc<Relation>, from: &Rc<Span>, to: &Rc<Span>) -> RelationInstance {
+    RelationInstance {
+        from_span: Rc::downgrade(from),
+        to_span: Rc::downgrade(to),
+        relation: relation.clone(),
+        arrival: None,
+    }
+}
+
+#[test]
+fn test_time_limit_usage_and_near_miss() {
+    let mut relation = preprocess_block_to_postprocess_ready_block_relation();
+    relation.max_time_diff = Some(1.0);
+    let relation = Rc::new(relation);
+    let node = create_test_node("node0");
+    let from = create_test_span("from", node.clone(), 1.0, 1.2, b"from");
+    let close = create_test_span("to", node.clone(), 1.95, 2.0, b"close");
+    let early = create_test_span("to", node.clone(), 1.5, 1.6, b"early");
+
+    // Measured from the start of the "from" span, like max_time_diff.
+    let near = instance(&relation, &from, &close);
+    assert!((near.time_limit_usage().unwrap() - 0.95).abs() < 1e-9);
+    assert!(near.is_near_miss(10.0));
+    assert!(!near.is_near_miss(1.0));
+
+    let far = instance(&relation, &from, &early);
+    assert!(!far.is_near_miss(10.0));
+
+    // Without a limit nothing is a near miss.
+    let mut unlimited = (*relation).clone();
+    unlimited.max_time_diff = None;
+    let unlimited = instance(&Rc::new(unlimited), &from, &close);
+    assert_eq!(unlimited.time_limit_usage(), None);
+    assert!(!unlimited.is_near_miss(100.0));
+}
+
+#[test]
+fn test_relation_limit_stats() {
+    let mut relation = preprocess_block_to_postprocess_ready_block_relation();
+    relation.max_time_diff = Some(1.0);
+    let relation = Rc::new(relation);
+    let node = create_test_node("node0");
+    let spans: Vec<Rc<Span>> = (0..4)
+        .flat_map(|i| {
+            let start = i as f64 * 10.0;
+            // Delays (from start to start) of 0.5, 0.8, 0.92 and 0.98 s
+            let usage = [0.5, 0.8, 0.92, 0.98][i];
+            [
+                create_test_span("from", node.clone(), start, start + 0.1, &[i as u8, 0]),
+                create_test_span(
+                    "to",
+                    node.clone(),
+                    start + usage,
+                    start + usage + 0.1,
+                    &[i as u8, 1],
+                ),
+            ]
+        })
+        .collect();
+    let instances: Vec<RelationInstance> = spans
+        .chunks(2)
+        .map(|pair| instance(&relation, &pair[0], &pair[1]))
+        .collect();
+
+    let stats = relation_limit_stats(&instances, 10.0);
+    assert_eq!(stats.len(), 1);
+    let stats = &stats[0];
+    assert_eq!(stats.instances, 4);
+    assert!((stats.max_limit_usage.unwrap() - 0.98).abs() < 1e-9);
+    assert!((stats.max_delay - 0.88).abs() < 1e-9);
+    assert!((stats.mean_delay - 0.7).abs() < 1e-9);
+    // The closest to the limit first
+    let near_misses: Vec<_> = stats
+        .near_misses
+        .iter()
+        .map(|i| i.to_span.upgrade().unwrap().span_id)
+        .collect();
+    assert_eq!(near_misses, vec![spans[7].span_id, spans[5].span_id]);
+}
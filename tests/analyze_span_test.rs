@@ -1,6 +1,11 @@
 use std::collections::BTreeMap;
 
+use approx::assert_relative_eq;
 use traviz::analyze_span::AnalyzeSpanModal;
+use traviz::analyze_utils::highlighted_spans_range;
+use traviz::report::ReportBlock;
+use traviz::structured_modes::{MatchCondition, MatchOperator, ATTRIBUTE_NAME_PLACEHOLDER};
+use traviz::types::TimeFormat;
 
 mod test_helpers;
 use test_helpers::*;
@@ -149,3 +154,223 @@ fn test_single_span_group() {
         "Single span group should preserve name"
     );
 }
+
+#[test]
+fn test_open_for_span_runs_analysis() {
+    let node0 = create_test_node("node0");
+    let node1 = create_test_node("node1");
+    let spans = vec![
+        create_test_span("apply_chunk", node0.clone(), 0.0, 1.0, &[1]),
+        create_test_span("apply_chunk", node0.clone(), 2.0, 4.0, &[2]),
+        create_test_span("apply_chunk", node1.clone(), 0.0, 1.0, &[3]),
+        create_test_span("produce_block", node1.clone(), 0.0, 1.0, &[4]),
+    ];
+
+    let mut analyzer = AnalyzeSpanModal::default();
+    analyzer.open_for_span(&spans, "apply_chunk", "node0");
+
+    assert!(analyzer.show);
+    assert_eq!(
+        analyzer.get_selected_span_name(),
+        Some(&"apply_chunk".to_string())
+    );
+    assert_eq!(
+        analyzer.get_analyzed_span_counts(),
+        vec![("node0".to_string(), 2), ("node1".to_string(), 1)]
+    );
+}
+
+#[test]
+fn test_only_selected_time_range() {
+    let node0 = create_test_node("node0");
+    let spans = vec![
+        create_test_span("apply_chunk", node0.clone(), 0.0, 1.0, &[1]),
+        create_test_span("apply_chunk", node0.clone(), 2.0, 4.0, &[2]),
+        create_test_span("apply_chunk", node0.clone(), 5.0, 6.0, &[3]),
+    ];
+
+    let mut analyzer = AnalyzeSpanModal::default();
+    analyzer.set_only_selected_time_range(true);
+    analyzer.selected_time_range = (3.0, 5.5);
+    analyzer.open_for_span_name(&spans, "apply_chunk");
+    assert_eq!(
+        analyzer.get_analyzed_span_counts(),
+        vec![("node0".to_string(), 2)]
+    );
+
+    analyzer.selected_time_range = (10.0, 20.0);
+    analyzer.open_for_span_name(&spans, "apply_chunk");
+    assert!(analyzer.get_analyzed_span_counts().is_empty());
+}
+
+#[test]
+fn test_breakdown_attribute() {
+    let node0 = create_test_node("node0");
+    let node1 = create_test_node("node1");
+    let span = |node, start: f64, id: u8, shard: Option<i64>| {
+        let attrs = shard
+            .map(|shard| BTreeMap::from([("shard_id".to_string(), int_attr(shard))]))
+            .unwrap_or_default();
+        create_test_span_with_attributes("apply_chunk", node, start, start + 1.0, &[id], attrs)
+    };
+    let spans = vec![
+        span(node0.clone(), 0.0, 1, Some(0)),
+        span(node0.clone(), 2.0, 2, Some(1)),
+        span(node0.clone(), 4.0, 3, Some(1)),
+        span(node1.clone(), 0.0, 4, Some(0)),
+        span(node1.clone(), 2.0, 5, None),
+    ];
+
+    let mut analyzer = AnalyzeSpanModal::default();
+    analyzer.open_for_span_name(&spans, "apply_chunk");
+    assert!(analyzer.get_analyzed_group_counts().is_empty());
+
+    analyzer.set_breakdown_attribute("shard_id".to_string());
+    analyzer.open_for_span_name(&spans, "apply_chunk");
+    assert_eq!(
+        analyzer.get_analyzed_span_counts(),
+        vec![("node0".to_string(), 3), ("node1".to_string(), 2)]
+    );
+    assert_eq!(
+        analyzer.get_analyzed_group_counts(),
+        vec![
+            ("node0".to_string(), "0".to_string(), 1),
+            ("node0".to_string(), "1".to_string(), 2),
+            ("node1".to_string(), "(missing)".to_string(), 1),
+            ("node1".to_string(), "0".to_string(), 1),
+        ]
+    );
+}
+
+#[test]
+fn test_attribute_conditions() {
+    let node = create_test_node("node0");
+    let span = |start: f64, id: u8, shard: i64, height: i64| {
+        create_test_span_with_attributes(
+            "apply_chunk",
+            node.clone(),
+            start,
+            start + 1.0,
+            &[id],
+            BTreeMap::from([
+                ("shard_id".to_string(), int_attr(shard)),
+                ("height".to_string(), int_attr(height)),
+            ]),
+        )
+    };
+    let spans = vec![
+        span(0.0, 1, 2, 999),
+        span(1.0, 2, 2, 1001),
+        span(2.0, 3, 2, 1002),
+        span(3.0, 4, 3, 1003),
+    ];
+    let condition = |operator, value: &str| MatchCondition {
+        operator,
+        value: value.to_string(),
+    };
+
+    let mut analyzer = AnalyzeSpanModal::default();
+    analyzer.set_attribute_conditions(vec![
+        (
+            "shard_id".to_string(),
+            condition(MatchOperator::EqualTo, "2"),
+        ),
+        (
+            "height".to_string(),
+            condition(MatchOperator::GreaterThan, "1000"),
+        ),
+        // Conditions which weren't filled in are ignored
+        (
+            ATTRIBUTE_NAME_PLACEHOLDER.to_string(),
+            condition(MatchOperator::EqualTo, "val"),
+        ),
+    ]);
+    assert!(!analyzer.span_matches_attribute_conditions(&spans[0]));
+    assert!(analyzer.span_matches_attribute_conditions(&spans[1]));
+    assert!(!analyzer.span_matches_attribute_conditions(&spans[3]));
+
+    analyzer.open_for_span_name(&spans, "apply_chunk");
+    assert_eq!(
+        analyzer.get_analyzed_span_counts(),
+        vec![("node0".to_string(), 2)]
+    );
+
+    analyzer.set_attribute_conditions(vec![(
+        "epoch".to_string(),
+        condition(MatchOperator::NotExists, ""),
+    )]);
+    analyzer.open_for_span_name(&spans, "apply_chunk");
+    assert_eq!(
+        analyzer.get_analyzed_span_counts(),
+        vec![("node0".to_string(), 4)]
+    );
+}
+
+#[test]
+fn test_report_section() {
+    let node0 = create_test_node("node0");
+    let spans = vec![
+        create_test_span("apply_chunk", node0.clone(), 0.0, 1.0, &[1]),
+        create_test_span("apply_chunk", node0.clone(), 2.0, 4.0, &[2]),
+    ];
+
+    let mut analyzer = AnalyzeSpanModal::default();
+    assert!(analyzer
+        .get_report_section(&TimeFormat::default())
+        .is_none());
+    analyzer.open_for_span_name(&spans, "apply_chunk");
+    let section = analyzer.get_report_section(&TimeFormat::default()).unwrap();
+    assert_eq!(section.title, "Span analysis: apply_chunk");
+    let ReportBlock::Table { headers, rows } = &section.blocks[1] else {
+        panic!("expected a table, got {:?}", section.blocks[1]);
+    };
+    assert_eq!(headers.len(), 7);
+    assert_eq!(
+        rows,
+        &vec![
+            vec!["node0", "2", "1000.000", "2000.000", "1500.000", "1500.000", "500.000"],
+            vec![
+                "ALL NODES",
+                "2",
+                "1000.000",
+                "2000.000",
+                "1500.000",
+                "1500.000",
+                "500.000"
+            ],
+        ]
+    );
+}
+
+#[test]
+fn test_highlight_node_spans() {
+    let node0 = create_test_node("node0");
+    let node1 = create_test_node("node1");
+    let spans = vec![
+        create_test_span("apply_chunk", node0.clone(), 1.0, 2.0, &[1]),
+        create_test_span("apply_chunk", node0.clone(), 3.0, 4.0, &[2]),
+        create_test_span("apply_chunk", node1, 1.5, 2.5, &[3]),
+        create_test_span("produce_block", node0, 0.0, 5.0, &[4]),
+    ];
+
+    let mut analyzer = AnalyzeSpanModal::default();
+    analyzer.open_for_span_name(&spans, "apply_chunk");
+    let node0_spans = analyzer.get_node_spans("node0");
+    let ids: Vec<String> = node0_spans.iter().map(|s| s.span_id.to_hex()).collect();
+    assert_eq!(
+        ids,
+        vec![spans[0].span_id.to_hex(), spans[1].span_id.to_hex()]
+    );
+    assert!(analyzer.get_node_spans("node2").is_empty());
+
+    // The spans are shown with 20% padding on both sides
+    let (start, end) = highlighted_spans_range(&node0_spans).unwrap();
+    assert_relative_eq!(start, 0.4);
+    assert_relative_eq!(end, 4.6);
+    // At most 3 seconds after the first span are shown
+    let long = create_test_span("apply_chunk", create_test_node("node0"), 1.0, 10.0, &[5]);
+    let (start, end) = highlighted_spans_range(&[long]).unwrap();
+    assert_relative_eq!(start, 0.4);
+    assert_relative_eq!(end, 4.6);
+    assert!(highlighted_spans_range(&[]).is_none());
+}
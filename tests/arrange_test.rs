@@ -0,0 +1,282 @@
+mod test_helpers;
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use test_helpers::*;
+use traviz::arrange::{
+    arrange_spans_with_viewport, ArrangedLane, ArrangementCache, ArrangementKey, SpanPane,
+};
+use traviz::types::{Span, TimePoint};
+
+const VIEWPORT_START: TimePoint = 0.0;
+const VIEWPORT_END: TimePoint = 100.0;
+const VISUAL_START_X: f32 = 0.0;
+const VISUAL_END_X: f32 = 2000.0;
+
+/// Lanes of overlapping spans, each with a few displayed children.
+fn create_lanes(lane_count: usize, spans_per_lane: usize) -> Vec<(String, Vec<Rc<Span>>)> {
+    let mut lanes = Vec::new();
+    let mut next_id: u32 = 0;
+    let mut id = || {
+        next_id += 1;
+        next_id.to_le_bytes()
+    };
+    for lane in 0..lane_count {
+        let node = create_test_node(&format!("node{lane}"));
+        let mut spans = Vec::new();
+        for i in 0..spans_per_lane {
+            let start = (i as f64 / spans_per_lane as f64) * VIEWPORT_END;
+            let span = create_test_span("parent", node.clone(), start, start + 1.0, &id());
+            for c in 0..3 {
+                let child_start = start + c as f64 * 0.3;
+                let child =
+                    create_test_span("child", node.clone(), child_start, child_start + 0.5, &id());
+                span.children_mut().push(child.clone());
+                span.display_children.borrow_mut().push(child);
+            }
+            spans.push(span);
+        }
+        lanes.push((format!("node{lane}"), spans));
+    }
+    lanes
+}
+
+fn set_display_params(spans: &[Rc<Span>]) {
+    let scale = (VISUAL_END_X - VISUAL_START_X) as f64 / (VIEWPORT_END - VIEWPORT_START);
+    for span in spans {
+        span.display_start
+            .set(((span.start_time - VIEWPORT_START) * scale) as f32);
+        span.display_length
+            .set(((span.end_time - span.start_time) * scale) as f32);
+        set_display_params(&span.display_children.borrow());
+    }
+}
+
+fn arrange_lane(spans: &[Rc<Span>]) -> ArrangedLane {
+    set_display_params(spans);
+    let bbox = arrange_spans_with_viewport(spans, true, VIEWPORT_START, VIEWPORT_END);
+    ArrangedLane {
+        spans_in_range: spans.to_vec(),
+        bbox,
+    }
+}
+
+fn key() -> ArrangementKey {
+    ArrangementKey {
+        selected_start: VIEWPORT_START,
+        selected_end: VIEWPORT_END,
+        visual_start_x: VISUAL_START_X,
+        visual_end_x: VISUAL_END_X,
+        node_filter_index: 0,
+    }
+}
+
+/// Does the same work as one frame of `draw_spans` does for the arrangement.
+fn frame(cache: &mut ArrangementCache, lanes: &[(String, Vec<Rc<Span>>)]) -> Vec<u64> {
+    lanes
+        .iter()
+        .map(|(lane_name, spans)| {
+            cache
+                .get_or_arrange(SpanPane::Upper, lane_name, key(), || arrange_lane(spans))
+                .bbox
+                .height
+        })
+        .collect()
+}
+
+fn time_frames(frames: usize, mut run_frame: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..frames {
+        run_frame();
+    }
+    start.elapsed()
+}
+
+#[test]
+fn test_cached_arrangement_matches_fresh_arrangement() {
+    let lanes = create_lanes(3, 200);
+    let mut cache = ArrangementCache::new();
+    let heights = frame(&mut cache, &lanes);
+    assert_eq!(cache.len(), 3);
+
+    let offsets: Vec<u64> = lanes[0]
+        .1
+        .iter()
+        .map(|s| s.parent_height_offset.get())
+        .collect();
+    assert_eq!(frame(&mut cache, &lanes), heights);
+    assert_eq!(
+        arrange_lane(&lanes[0].1).bbox.height,
+        heights[0],
+        "fresh arrangement differs from the cached one"
+    );
+    let fresh_offsets: Vec<u64> = lanes[0]
+        .1
+        .iter()
+        .map(|s| s.parent_height_offset.get())
+        .collect();
+    assert_eq!(fresh_offsets, offsets);
+}
+
+#[test]
+fn test_arrangement_recomputed_on_changes() {
+    let lanes = create_lanes(1, 10);
+    let (lane_name, spans) = &lanes[0];
+    let mut cache = ArrangementCache::new();
+    let arrange_count = Cell::new(0);
+    let arrange = |cache: &mut ArrangementCache, key: ArrangementKey| {
+        cache.get_or_arrange(SpanPane::Upper, lane_name, key, || {
+            arrange_count.set(arrange_count.get() + 1);
+            arrange_lane(spans)
+        });
+    };
+
+    arrange(&mut cache, key());
+    arrange(&mut cache, key());
+    assert_eq!(arrange_count.get(), 1);
+
+    // Zooming changes the key.
+    arrange(
+        &mut cache,
+        ArrangementKey {
+            selected_end: VIEWPORT_END / 2.0,
+            ..key()
+        },
+    );
+    assert_eq!(arrange_count.get(), 2);
+
+    // Collapsing a span invalidates the cache.
+    cache.invalidate();
+    arrange(&mut cache, key());
+    assert_eq!(arrange_count.get(), 3);
+
+    // So does changing the highlights, but only when they actually change.
+    cache.update_highlighted_spans(&[]);
+    arrange(&mut cache, key());
+    assert_eq!(arrange_count.get(), 3);
+    cache.update_highlighted_spans(&spans[..1]);
+    cache.update_highlighted_spans(&spans[..1]);
+    arrange(&mut cache, key());
+    assert_eq!(arrange_count.get(), 4);
+}
+
+/// Frames where nothing changes (e.g. hovering over spans) used to rearrange all lanes on every
+/// frame, now only the first frame arranges them.
+#[test]
+fn test_cached_frames_reuse_arrangement() {
+    let lanes = create_lanes(10, 50);
+    let mut cache = ArrangementCache::new();
+    let arrange_count = Cell::new(0);
+    let arranged_frame = |cache: &mut ArrangementCache| -> Vec<Rc<ArrangedLane>> {
+        lanes
+            .iter()
+            .map(|(lane_name, spans)| {
+                cache.get_or_arrange(SpanPane::Upper, lane_name, key(), || {
+                    arrange_count.set(arrange_count.get() + 1);
+                    arrange_lane(spans)
+                })
+            })
+            .collect()
+    };
+
+    let first = arranged_frame(&mut cache);
+    for _ in 0..20 {
+        let next = arranged_frame(&mut cache);
+        assert!(first.iter().zip(&next).all(|(a, b)| Rc::ptr_eq(a, b)));
+    }
+    assert_eq!(arrange_count.get(), lanes.len());
+
+    cache.invalidate();
+    let after_invalidate = arranged_frame(&mut cache);
+    assert!(first
+        .iter()
+        .zip(&after_invalidate)
+        .all(|(a, b)| !Rc::ptr_eq(a, b)));
+    assert_eq!(arrange_count.get(), 2 * lanes.len());
+
+    cache.update_highlighted_spans(&lanes[0].1[..1]);
+    let after_highlight = arranged_frame(&mut cache);
+    assert!(after_invalidate
+        .iter()
+        .zip(&after_highlight)
+        .all(|(a, b)| !Rc::ptr_eq(a, b)));
+    assert_eq!(arrange_count.get(), 3 * lanes.len());
+}
+
+/// Benchmark of frames where nothing changes (e.g. hovering over spans), which used to rearrange
+/// all lanes on every frame. Timing depends on the machine, run it with
+/// `cargo test --release --test arrange_test -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn benchmark_cached_arrangement_frame_time() {
+    let lanes = create_lanes(10, 500);
+    let frames = 20;
+
+    let mut uncached = ArrangementCache::new();
+    let uncached_time = time_frames(frames, || {
+        uncached.invalidate();
+        frame(&mut uncached, &lanes);
+    });
+
+    let mut cached = ArrangementCache::new();
+    let cached_time = time_frames(frames, || {
+        frame(&mut cached, &lanes);
+    });
+
+    println!(
+        "Arrangement of {} lanes, {frames} frames: uncached {uncached_time:?}, cached {cached_time:?}",
+        lanes.len()
+    );
+    // Only the first cached frame does any arranging.
+    assert!(
+        cached_time * 5 < uncached_time,
+        "cached: {cached_time:?}, uncached: {uncached_time:?}"
+    );
+}
+
+fn display_starts(spans: &[Rc<Span>], out: &mut Vec<f32>) {
+    for span in spans {
+        out.push(span.display_start.get());
+        display_starts(&span.display_children.borrow(), out);
+    }
+}
+
+/// Both panes of the split view arrange the same spans differently, each keeps its arrangement.
+#[test]
+fn test_split_view_panes_keep_their_arrangements() {
+    let lanes = create_lanes(1, 50);
+    let (lane_name, spans) = &lanes[0];
+    let mut cache = ArrangementCache::new();
+    let arrange_count = Cell::new(0);
+    let mut arrange = |pane: SpanPane| {
+        cache.get_or_arrange(pane, lane_name, key(), || {
+            arrange_count.set(arrange_count.get() + 1);
+            let arranged = arrange_lane(spans);
+            if pane == SpanPane::Lower {
+                // The lower pane is zoomed in twice as much.
+                fn zoom(spans: &[Rc<Span>]) {
+                    for span in spans {
+                        span.display_start.set(span.display_start.get() * 2.0);
+                        zoom(&span.display_children.borrow());
+                    }
+                }
+                zoom(spans);
+            }
+            arranged
+        });
+        let mut starts = Vec::new();
+        display_starts(spans, &mut starts);
+        starts
+    };
+
+    let upper = arrange(SpanPane::Upper);
+    let lower = arrange(SpanPane::Lower);
+    assert_ne!(upper, lower);
+    for _ in 0..3 {
+        assert_eq!(arrange(SpanPane::Upper), upper);
+        assert_eq!(arrange(SpanPane::Lower), lower);
+    }
+    assert_eq!(arrange_count.get(), 2);
+}
@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use traviz::attribute_facets::{
+    attribute_keys, compute_facets, quantize_window, AttributeFacets, FacetEntry,
+};
+use traviz::types::Span;
+
+mod test_helpers;
+use test_helpers::{create_test_node, create_test_span_with_attributes, int_attr, string_attr};
+
+fn shard_span(name: &str, shard_id: i64, start: f64, end: f64, id: u8) -> Rc<Span> {
+    create_test_span_with_attributes(
+        name,
+        create_test_node("node0"),
+        start,
+        end,
+        &[id],
+        BTreeMap::from([
+            ("shard_id".to_string(), int_attr(shard_id)),
+            ("kind".to_string(), string_attr(name)),
+        ]),
+    )
+}
+
+fn entry(value: &str, count: usize) -> FacetEntry {
+    FacetEntry {
+        value: value.to_string(),
+        count,
+    }
+}
+
+#[test]
+fn test_compute_facets() {
+    let spans = vec![
+        shard_span("apply_chunk", 0, 1.0, 2.0, 1),
+        shard_span("apply_chunk", 1, 1.0, 2.0, 2),
+        shard_span("apply_chunk", 1, 3.0, 4.0, 3),
+        shard_span("validate_chunk", 2, 10.0, 11.0, 4),
+        create_test_span_with_attributes(
+            "no_shard",
+            create_test_node("node0"),
+            1.0,
+            2.0,
+            &[5],
+            BTreeMap::new(),
+        ),
+    ];
+
+    assert_eq!(attribute_keys(&spans), vec!["kind", "shard_id"]);
+
+    // The most common value first, values with the same count sorted by value.
+    assert_eq!(
+        compute_facets(&spans, "shard_id", 0.0, 20.0),
+        vec![entry("1", 2), entry("0", 1), entry("2", 1)]
+    );
+    // Only spans overlapping the window are counted.
+    assert_eq!(
+        compute_facets(&spans, "shard_id", 1.5, 3.5),
+        vec![entry("1", 2), entry("0", 1)]
+    );
+    assert_eq!(
+        compute_facets(&spans, "kind", 9.0, 20.0),
+        vec![entry("validate_chunk", 1)]
+    );
+    assert!(compute_facets(&spans, "missing", 0.0, 20.0).is_empty());
+}
+
+#[test]
+fn test_attribute_filter() {
+    let root = shard_span("produce_block", 0, 1.0, 5.0, 1);
+    root.children_mut()
+        .push(shard_span("apply_chunk", 3, 2.0, 3.0, 2));
+    let other = shard_span("produce_block", 1, 1.0, 5.0, 3);
+
+    let mut facets = AttributeFacets::new();
+    assert!(facets.shows(&root) && facets.shows(&other));
+
+    // A top level span is shown when a span in its subtree has the value.
+    facets.set_filter("shard_id", "3");
+    assert!(facets.shows(&root));
+    assert!(!facets.shows(&other));
+
+    facets.set_filter("shard_id", "1");
+    assert!(!facets.shows(&root));
+    assert!(facets.shows(&other));
+
+    facets.clear_filter();
+    assert!(facets.filter().is_none());
+    assert!(facets.shows(&root) && facets.shows(&other));
+}
+
+#[test]
+fn test_quantize_window() {
+    let (start, end) = quantize_window(1000.3, 1100.7);
+    assert!(start <= 1000.3 && end >= 1100.7);
+    assert!(end - start < 100.4 * 1.02);
+
+    // Moving or resizing the window by a little keeps the same quantized window.
+    assert_eq!(quantize_window(1000.35, 1100.65), (start, end));
+    assert_eq!(quantize_window(1000.3, 1100.6), (start, end));
+
+    assert_eq!(quantize_window(5.0, 5.0), (5.0, 5.0));
+}
@@ -0,0 +1,50 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+
+use test_helpers::{int_attr, string_attr};
+use traviz::attribute_table::{AttributeSortColumn, AttributeTable};
+
+#[test]
+fn test_attribute_table_rows() {
+    let attributes = BTreeMap::from([
+        ("height".to_string(), int_attr(10)),
+        ("shard_id".to_string(), int_attr(9)),
+        ("chunk_hash".to_string(), string_attr("abc")),
+        ("thread".to_string(), None),
+    ]);
+    let names = |table: &AttributeTable| -> Vec<String> {
+        table
+            .rows(&attributes)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    };
+
+    let mut table = AttributeTable::new();
+    assert_eq!(
+        names(&table),
+        vec!["chunk_hash", "height", "shard_id", "thread"]
+    );
+
+    // Numbers are sorted by value and go before text
+    table.sort_by(AttributeSortColumn::Value);
+    assert_eq!(
+        names(&table),
+        vec!["shard_id", "height", "chunk_hash", "thread"]
+    );
+    table.sort_by(AttributeSortColumn::Value);
+    assert_eq!(
+        names(&table),
+        vec!["thread", "chunk_hash", "height", "shard_id"]
+    );
+
+    // The filter matches names and values
+    table.set_filter("HASH");
+    assert_eq!(names(&table), vec!["chunk_hash"]);
+    table.set_filter("empty");
+    assert_eq!(
+        table.rows(&attributes),
+        vec![("thread".to_string(), "empty".to_string())]
+    );
+}
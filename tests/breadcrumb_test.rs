@@ -0,0 +1,41 @@
+use std::rc::Rc;
+
+use traviz::breadcrumb::{ancestor_chain, breadcrumb_text};
+use traviz::span_id::SpanId;
+use traviz::types::Span;
+
+mod test_helpers;
+use test_helpers::{create_test_node, create_test_span};
+
+fn names(chain: &[Rc<Span>]) -> Vec<&str> {
+    chain.iter().map(|span| span.name.as_str()).collect()
+}
+
+#[test]
+fn test_ancestor_chain() {
+    let node = create_test_node("node0");
+    let root = create_test_span("produce_block", node.clone(), 0.0, 10.0, &[1]);
+    let chunk0 = create_test_span("apply_chunk", node.clone(), 1.0, 2.0, &[2]);
+    let chunk1 = create_test_span("apply_chunk", node.clone(), 3.0, 4.0, &[3]);
+    chunk1
+        .children_mut()
+        .push(create_test_span("wasm_call", node.clone(), 3.1, 3.2, &[4]));
+    root.children_mut().extend([chunk0, chunk1.clone()]);
+
+    let chain = ancestor_chain(&root, SpanId::intern(&[4])).unwrap();
+    assert_eq!(
+        names(&chain),
+        vec!["produce_block", "apply_chunk", "wasm_call"]
+    );
+    assert!(Rc::ptr_eq(&chain[1], &chunk1));
+    assert_eq!(
+        breadcrumb_text(&chain),
+        "produce_block › apply_chunk › wasm_call"
+    );
+
+    let chain = ancestor_chain(&root, SpanId::intern(&[1])).unwrap();
+    assert_eq!(names(&chain), vec!["produce_block"]);
+
+    let other = create_test_span("other", node, 0.0, 1.0, &[5]);
+    assert!(ancestor_chain(&root, other.span_id).is_none());
+}
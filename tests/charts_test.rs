@@ -0,0 +1,27 @@
+use traviz::charts::{cumulative_distribution, histogram, HistogramBucket};
+
+#[test]
+fn test_histogram() {
+    assert!(histogram(&[], 10).is_empty());
+
+    let bucket = |start, end, count| HistogramBucket { start, end, count };
+    // Bimodal values, the max value goes to the last bucket
+    assert_eq!(
+        histogram(&[1.0, 1.5, 2.0, 8.0, 9.0, 9.0], 4),
+        vec![
+            bucket(1.0, 3.0, 3),
+            bucket(3.0, 5.0, 0),
+            bucket(5.0, 7.0, 0),
+            bucket(7.0, 9.0, 3),
+        ]
+    );
+    assert_eq!(histogram(&[2.0, 2.0], 4), vec![bucket(2.0, 2.0, 2)]);
+}
+
+#[test]
+fn test_cumulative_distribution() {
+    assert_eq!(
+        cumulative_distribution(&[3.0, 1.0, 2.0, 4.0]),
+        vec![(1.0, 0.25), (2.0, 0.5), (3.0, 0.75), (4.0, 1.0)]
+    );
+}
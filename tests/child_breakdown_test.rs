@@ -0,0 +1,70 @@
+mod test_helpers;
+
+use test_helpers::{assert_close, create_test_node, create_test_span, with_children};
+use traviz::child_breakdown::{compute_child_breakdown, SELF_TIME_NAME};
+
+#[test]
+fn test_child_breakdown() {
+    let node = create_test_node("node0");
+    let read1 = create_test_span("read", node.clone(), 0.2, 0.5, &[2]);
+    let write1 = create_test_span("write", node.clone(), 0.5, 0.6, &[3]);
+    let read2 = create_test_span("read", node.clone(), 0.7, 0.8, &[4]);
+    let read3 = create_test_span("read", node.clone(), 2.0, 2.2, &[6]);
+    let apply1 = with_children(
+        create_test_span("apply", node.clone(), 0.0, 1.0, &[1]),
+        vec![read1.clone(), write1.clone(), read2.clone()],
+    );
+    let apply2 = with_children(
+        create_test_span("apply", node.clone(), 2.0, 2.5, &[5]),
+        vec![read3.clone()],
+    );
+    let spans = vec![apply1, read1, write1, read2, apply2, read3];
+
+    let breakdown = compute_child_breakdown(&spans, "apply").unwrap();
+    assert_eq!(breakdown.instances, 2);
+    assert_close(breakdown.mean_duration, 0.75);
+
+    let names: Vec<&str> = breakdown
+        .children
+        .iter()
+        .map(|c| c.child_name.as_str())
+        .collect();
+    assert_eq!(names, vec!["read", "write"]);
+
+    let read = &breakdown.children[0];
+    assert_eq!(read.calls, 3);
+    assert_eq!(read.instances_with_child, 2);
+    assert_close(read.mean, 0.3);
+    assert_close(breakdown.share_percent(read), 40.0);
+
+    // Instances without the child count as 0
+    let write = &breakdown.children[1];
+    assert_eq!(write.calls, 1);
+    assert_eq!(write.instances_with_child, 1);
+    assert_close(write.mean, 0.05);
+
+    assert_eq!(breakdown.self_time.child_name, SELF_TIME_NAME);
+    assert_close(breakdown.self_time.mean, 0.4);
+
+    // Spans without children spend all the time in themselves
+    let leaf = compute_child_breakdown(&spans, "read").unwrap();
+    assert!(leaf.children.is_empty());
+    assert_close(leaf.self_time.mean, leaf.mean_duration);
+
+    assert!(compute_child_breakdown(&spans, "missing").is_none());
+}
+
+#[test]
+fn test_child_breakdown_parallel_children() {
+    let node = create_test_node("node0");
+    let parent = with_children(
+        create_test_span("parent", node.clone(), 0.0, 1.0, &[1]),
+        vec![
+            create_test_span("worker", node.clone(), 0.0, 0.8, &[2]),
+            create_test_span("worker", node.clone(), 0.1, 0.9, &[3]),
+        ],
+    );
+    let breakdown = compute_child_breakdown(&[parent], "parent").unwrap();
+    assert_close(breakdown.children[0].mean, 1.6);
+    assert_close(breakdown.self_time.mean, 0.0);
+}
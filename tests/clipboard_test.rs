@@ -0,0 +1,32 @@
+use traviz::clipboard::table_to_tsv;
+use traviz::report::{ReportBlock, ReportSection};
+
+#[test]
+fn test_table_to_tsv() {
+    let headers = vec!["name".to_string(), "value".to_string()];
+    let rows = vec![
+        vec!["a".to_string(), "1".to_string()],
+        vec!["with\ttab".to_string(), "two\nlines".to_string()],
+    ];
+    assert_eq!(
+        table_to_tsv(&headers, &rows),
+        "name\tvalue\na\t1\nwith tab\ttwo lines\n"
+    );
+}
+
+#[test]
+fn test_report_section_tables_to_tsv() {
+    let table = |value: &str| ReportBlock::Table {
+        headers: vec!["h".to_string()],
+        rows: vec![vec![value.to_string()]],
+    };
+    let section = ReportSection {
+        title: "section".to_string(),
+        blocks: vec![
+            ReportBlock::Text("not copied".to_string()),
+            table("1"),
+            table("2"),
+        ],
+    };
+    assert_eq!(section.tables_to_tsv(), "h\n1\n\nh\n2\n");
+}
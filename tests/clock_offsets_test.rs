@@ -0,0 +1,179 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use opentelemetry_proto::tonic::trace::v1::span::Event;
+use opentelemetry_proto::tonic::trace::v1::ResourceSpans;
+use test_helpers::{
+    create_test_node, create_test_span, create_test_span_with_attributes, int_attr,
+    otlp_resource_spans, otlp_scope_spans,
+};
+use traviz::builtin_relations::send_chunk_state_witness_to_validate_chunk_state_witness_relation;
+use traviz::clock_offsets::{
+    default_reference_node, estimate_clock_offsets, find_offset_links, shift_node_clocks,
+    trace_node_names, NodeOffsets,
+};
+use traviz::relation::{find_relations, RelationView};
+use traviz::types::Span;
+
+/// A message sent from `from` at `sent` (end of the "from" span) and received by `to` at
+/// `received` (start of the "to" span), times in seconds.
+fn link(from: &str, to: &str, sent: f64, received: f64, id: u8) -> (Rc<Span>, Rc<Span>) {
+    (
+        create_test_span("send", create_test_node(from), sent - 0.01, sent, &[id, 0]),
+        create_test_span(
+            "receive",
+            create_test_node(to),
+            received,
+            received + 0.01,
+            &[id, 1],
+        ),
+    )
+}
+
+#[test]
+fn test_estimate_clock_offsets_both_directions() {
+    // node1 is 2 ms ahead of node0, messages take 1-3 ms.
+    let links = vec![
+        link("node0", "node1", 1.0, 1.003, 1),
+        link("node0", "node1", 2.0, 2.005, 2),
+        link("node1", "node0", 3.0, 2.999, 3),
+        link("node1", "node0", 4.0, 4.0, 4),
+    ];
+
+    let offsets = estimate_clock_offsets(&links, "node0");
+    assert_eq!(offsets.len(), 2);
+    assert_eq!(offsets["node0"], 0.0);
+    assert!((offsets["node1"] - 2.0).abs() < 1e-6);
+
+    // The same offset, seen from the other side.
+    let offsets = estimate_clock_offsets(&links, "node1");
+    assert!((offsets["node0"] + 2.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_estimate_clock_offsets_one_direction() {
+    let links = vec![
+        // node1 receives 4 ms before the message was sent.
+        link("node0", "node1", 1.0, 0.996, 1),
+        link("node0", "node1", 2.0, 1.999, 2),
+        // node2 is only connected through node1, delays are positive.
+        link("node1", "node2", 3.0, 3.002, 3),
+        // node3 isn't connected to node0.
+        link("node4", "node3", 5.0, 4.99, 4),
+    ];
+
+    assert_eq!(default_reference_node(&links).as_deref(), Some("node1"));
+
+    let offsets = estimate_clock_offsets(&links, "node0");
+    assert_eq!(offsets.len(), 3);
+    assert!((offsets["node1"] + 4.0).abs() < 1e-6);
+    assert!((offsets["node2"] + 4.0).abs() < 1e-6);
+    assert!(!offsets.contains_key("node3"));
+
+    assert!(estimate_clock_offsets(&links, "missing").is_empty());
+}
+
+#[test]
+fn test_find_offset_links_includes_negative_delays() {
+    let relation = send_chunk_state_witness_to_validate_chunk_state_witness_relation();
+    let attributes = BTreeMap::from([
+        ("height".to_string(), int_attr(5)),
+        ("shard_id".to_string(), int_attr(0)),
+    ]);
+    let send = create_test_span_with_attributes(
+        "send_chunk_state_witness",
+        create_test_node("node0"),
+        1.0,
+        1.1,
+        &[1],
+        attributes.clone(),
+    );
+    // Received 5 ms before it was sent, because of the clock difference.
+    let validate = create_test_span_with_attributes(
+        "validate_chunk_state_witness",
+        create_test_node("node1"),
+        1.095,
+        1.2,
+        &[2],
+        attributes,
+    );
+    let spans = vec![send, validate];
+
+    let view = RelationView {
+        enabled_relations: vec![relation.id],
+        name: "test".to_string(),
+        is_builtin: false,
+    };
+    assert!(find_relations(std::slice::from_ref(&relation), &view, &spans).is_empty());
+
+    let links = find_offset_links(&relation, &spans);
+    assert_eq!(links.len(), 1);
+    let offsets = estimate_clock_offsets(&links, "node0");
+    assert!((offsets["node1"] + 5.0).abs() < 1e-6);
+}
+
+fn node_spans(node: &str, start_nanos: u64) -> ResourceSpans {
+    let span = opentelemetry_proto::tonic::trace::v1::Span {
+        name: "span".to_string(),
+        start_time_unix_nano: start_nanos,
+        end_time_unix_nano: start_nanos + 1000,
+        events: vec![Event {
+            time_unix_nano: start_nanos + 500,
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    otlp_resource_spans(Some(node), vec![otlp_scope_spans(None, vec![span])])
+}
+
+#[test]
+fn test_shift_node_clocks() {
+    let original = vec![ExportTraceServiceRequest {
+        resource_spans: vec![
+            node_spans("node0", 1_000_000_000),
+            node_spans("node1", 2_000_000_000),
+        ],
+    }];
+    let mut requests = original.clone();
+
+    let offsets = NodeOffsets::from([("node1".to_string(), 2.5)]);
+    shift_node_clocks(&mut requests, &NodeOffsets::new(), &offsets);
+    assert_eq!(requests[0].resource_spans[0], original[0].resource_spans[0]);
+    let span = &requests[0].resource_spans[1].scope_spans[0].spans[0];
+    assert_eq!(span.start_time_unix_nano, 1_997_500_000);
+    assert_eq!(span.end_time_unix_nano, 1_997_501_000);
+    assert_eq!(span.events[0].time_unix_nano, 1_997_500_500);
+
+    // Changing the offsets shifts by the difference, resetting them restores the original times.
+    let new_offsets = NodeOffsets::from([("node1".to_string(), -1.0)]);
+    shift_node_clocks(&mut requests, &offsets, &new_offsets);
+    let span = &requests[0].resource_spans[1].scope_spans[0].spans[0];
+    assert_eq!(span.start_time_unix_nano, 2_001_000_000);
+    shift_node_clocks(&mut requests, &new_offsets, &NodeOffsets::new());
+    assert_eq!(requests, original);
+}
+
+#[test]
+fn test_trace_node_names() {
+    let requests = vec![
+        ExportTraceServiceRequest {
+            resource_spans: vec![node_spans("node1", 0), node_spans("node0", 0)],
+        },
+        ExportTraceServiceRequest {
+            resource_spans: vec![
+                node_spans("node1", 0),
+                ResourceSpans {
+                    resource: None,
+                    ..Default::default()
+                },
+            ],
+        },
+    ];
+    assert_eq!(
+        trace_node_names(&requests),
+        vec!["no resource", "node0", "node1"]
+    );
+}
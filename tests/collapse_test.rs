@@ -0,0 +1,118 @@
+use std::rc::Rc;
+
+use traviz::collapse::{set_collapse_all, CollapseMemory};
+use traviz::types::{LazyChildren, Span};
+
+mod test_helpers;
+use test_helpers::{create_test_node, create_test_span};
+
+/// produce_block with two apply_chunk children, one of them with a child.
+fn tree() -> Vec<Rc<Span>> {
+    let node = create_test_node("node0");
+    let root = create_test_span("produce_block", node.clone(), 0.0, 10.0, &[1]);
+    let chunk0 = create_test_span("apply_chunk", node.clone(), 1.0, 2.0, &[2]);
+    let chunk1 = create_test_span("apply_chunk", node.clone(), 3.0, 4.0, &[3]);
+    chunk1
+        .children_mut()
+        .push(create_test_span("wasm_call", node, 3.1, 3.2, &[4]));
+    root.children_mut().extend([chunk0, chunk1]);
+    vec![root]
+}
+
+fn collapse_states(spans: &[Rc<Span>], out: &mut Vec<(String, bool)>) {
+    for span in spans {
+        out.push((span.original_name.clone(), span.collapse_children.get()));
+        collapse_states(&span.children(), out);
+    }
+}
+
+fn states(spans: &[Rc<Span>]) -> Vec<(String, bool)> {
+    let mut out = Vec::new();
+    collapse_states(spans, &mut out);
+    out
+}
+
+#[test]
+fn test_set_collapse_all() {
+    let spans = tree();
+    set_collapse_all(&spans, true);
+    assert!(states(&spans).iter().all(|(_, collapsed)| *collapsed));
+    set_collapse_all(&spans, false);
+    assert!(states(&spans).iter().all(|(_, collapsed)| !*collapsed));
+}
+
+#[test]
+fn test_collapse_memory() {
+    let mut memory = CollapseMemory::default();
+    assert_eq!(memory.choice("Everything", "apply_chunk"), None);
+
+    // A choice for a span applies to all spans with its name, only in its mode.
+    memory.remember_span("Everything", "apply_chunk", true);
+    let spans = tree();
+    memory.apply("Everything", &spans);
+    assert_eq!(
+        states(&spans),
+        vec![
+            ("produce_block".to_string(), false),
+            ("apply_chunk".to_string(), true),
+            ("apply_chunk".to_string(), true),
+            ("wasm_call".to_string(), false),
+        ]
+    );
+    let spans = tree();
+    memory.apply("Chain", &spans);
+    assert!(states(&spans).iter().all(|(_, collapsed)| !*collapsed));
+
+    // Collapse all, then a single span name is expanded again.
+    memory.remember_all("Everything", true);
+    assert_eq!(memory.choice("Everything", "wasm_call"), Some(true));
+    memory.remember_span("Everything", "apply_chunk", false);
+    let spans = tree();
+    memory.apply("Everything", &spans);
+    assert_eq!(
+        states(&spans),
+        vec![
+            ("produce_block".to_string(), true),
+            ("apply_chunk".to_string(), false),
+            ("apply_chunk".to_string(), false),
+            ("wasm_call".to_string(), true),
+        ]
+    );
+
+    // Expand all forgets the choices for single spans.
+    memory.remember_all("Everything", false);
+    assert_eq!(memory.choice("Everything", "apply_chunk"), Some(false));
+    assert_eq!(memory.choice("Everything", "produce_block"), Some(false));
+}
+
+#[test]
+fn test_collapse_memory_on_lazy_children() {
+    let node = create_test_node("node0");
+    let root = create_test_span("produce_block", node.clone(), 0.0, 10.0, &[1]);
+    let chunk = create_test_span("apply_chunk", node.clone(), 1.0, 2.0, &[2]);
+    chunk
+        .children_mut()
+        .push(create_test_span("wasm_call", node, 1.1, 1.2, &[3]));
+    *root.lazy_children.borrow_mut() = Some(LazyChildren {
+        original: vec![chunk],
+        transform: Rc::new(|child| child.clone()),
+    });
+    let spans = vec![root];
+
+    let mut memory = CollapseMemory::default();
+    memory.remember_span("Everything", "apply_chunk", true);
+    memory.apply("Everything", &spans);
+    // The choice waits until the lazy children are transformed.
+    assert!(spans[0].has_lazy_children());
+    assert_eq!(
+        states(&spans),
+        vec![
+            ("produce_block".to_string(), false),
+            ("apply_chunk".to_string(), true),
+            ("wasm_call".to_string(), false),
+        ]
+    );
+
+    set_collapse_all(&spans, false);
+    assert!(states(&spans).iter().all(|(_, collapsed)| !*collapsed));
+}
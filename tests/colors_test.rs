@@ -0,0 +1,84 @@
+use eframe::egui::Color32;
+use traviz::colors::{
+    node_color, set_theme, theme, ColorOverrides, Palette, SemanticColor, Theme, ThemeKind,
+    LIGHT_NODE_COLORS, NODE_COLORS,
+};
+use traviz::relation::ArrowStyle;
+
+#[test]
+fn test_set_theme() {
+    assert_eq!(theme(), Theme::dark());
+    let dark_color = node_color("node0");
+    assert!(NODE_COLORS.contains(&dark_color));
+
+    set_theme(ThemeKind::Light.theme());
+    assert!(!theme().dark_mode);
+    let light_color = node_color("node0");
+    assert!(LIGHT_NODE_COLORS.contains(&light_color));
+    // A node keeps its place in the palette, so it's easy to recognize after switching the theme
+    assert_eq!(
+        NODE_COLORS.iter().position(|c| *c == dark_color),
+        LIGHT_NODE_COLORS.iter().position(|c| *c == light_color)
+    );
+
+    set_theme(Theme::dark());
+    assert_eq!(node_color("node0"), dark_color);
+}
+
+#[test]
+fn test_palette_and_overrides() {
+    let dark = Theme::dark();
+    assert_eq!(dark.with_palette(Palette::Default), dark);
+
+    // The colorblind palettes don't use the default yellow, blue and red
+    for palette in [Palette::Deuteranopia, Palette::Protanopia] {
+        let theme = dark.with_palette(palette);
+        assert_ne!(theme.span_time, dark.span_time);
+        assert_ne!(theme.highlighted_span_time, dark.highlighted_span_time);
+        assert_ne!(theme.unmatched_span, dark.unmatched_span);
+    }
+
+    let overrides = ColorOverrides::from([
+        (SemanticColor::Highlight, [10, 20, 30]),
+        (SemanticColor::GroupedSpanActive, [40, 50, 60]),
+    ]);
+    let theme = dark
+        .with_palette(Palette::Deuteranopia)
+        .with_overrides(&overrides);
+    assert_eq!(
+        SemanticColor::Highlight.get(&theme),
+        Color32::from_rgb(10, 20, 30)
+    );
+    assert_eq!(theme.highlighted_span_border, Color32::from_rgb(10, 20, 30));
+    assert_eq!(
+        SemanticColor::GroupedSpanActive.get(&theme),
+        Color32::from_rgb(40, 50, 60)
+    );
+    // Colors without an override keep the palette's color
+    assert_eq!(
+        SemanticColor::RelationArrow.get(&theme),
+        SemanticColor::RelationArrow.get(&dark.with_palette(Palette::Deuteranopia))
+    );
+    assert_eq!(
+        theme.span_time,
+        dark.with_palette(Palette::Deuteranopia).span_time
+    );
+}
+
+#[test]
+fn test_default_arrow_color_follows_theme() {
+    let custom = ArrowStyle {
+        color: [1, 2, 3],
+        ..ArrowStyle::default()
+    };
+    set_theme(Theme::dark().with_overrides(&ColorOverrides::from([(
+        SemanticColor::RelationArrow,
+        [200, 0, 200],
+    )])));
+    assert_eq!(
+        ArrowStyle::default().color32(),
+        Color32::from_rgb(200, 0, 200)
+    );
+    assert_eq!(custom.color32(), Color32::from_rgb(1, 2, 3));
+    set_theme(Theme::dark());
+}
@@ -0,0 +1,38 @@
+mod test_helpers;
+
+use test_helpers::{create_test_node, create_test_span};
+use traviz::concurrency::{compute_concurrency, ConcurrencySteps};
+use traviz::structured_modes::SpanSelector;
+
+#[test]
+fn test_concurrency() {
+    let node = create_test_node("node0");
+    let parent = create_test_span("process", node.clone(), 0.0, 10.0, &[1]);
+    parent.children_mut().extend([
+        // Already open at the start of the window
+        create_test_span("validate", node.clone(), -1.0, 2.0, &[2]),
+        create_test_span("validate", node.clone(), 1.0, 3.0, &[3]),
+        // Starts when the previous one ends, not concurrent with it
+        create_test_span("validate", node.clone(), 3.0, 4.0, &[4]),
+        create_test_span("validate", node.clone(), 3.5, 20.0, &[5]),
+        create_test_span("other", node.clone(), 1.5, 2.5, &[6]),
+    ]);
+    let roots = [parent];
+
+    let selector = SpanSelector::new_equal_name("validate");
+    let concurrency = compute_concurrency(roots.iter(), &selector, 0.0, 5.0);
+    assert_eq!(
+        concurrency,
+        ConcurrencySteps {
+            steps: vec![(0.0, 1), (1.0, 2), (2.0, 1), (3.0, 1), (3.5, 2), (4.0, 1)],
+            max: 2,
+        }
+    );
+    assert_eq!(concurrency.count_at(0.5), 1);
+    assert_eq!(concurrency.count_at(1.5), 2);
+    assert_eq!(concurrency.count_at(4.5), 1);
+
+    let none = compute_concurrency(roots.iter(), &selector, 30.0, 40.0);
+    assert_eq!(none.max, 0);
+    assert_eq!(none.count_at(35.0), 0);
+}
@@ -0,0 +1,64 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use approx::assert_relative_eq;
+use test_helpers::{create_test_node, create_test_span_with_attributes, int_attr};
+use traviz::correlation::{
+    describe_correlation, pair_spans, pearson_correlation, CorrelationMetric,
+};
+use traviz::types::Span;
+
+fn span(name: &str, node: &str, height: i64, start: f64, end: f64, id: u8) -> Rc<Span> {
+    create_test_span_with_attributes(
+        name,
+        create_test_node(node),
+        start,
+        end,
+        &[id],
+        BTreeMap::from([("height".to_string(), int_attr(height))]),
+    )
+}
+
+#[test]
+fn test_pair_spans() {
+    let spans = vec![
+        span("validate", "node0", 1, 0.0, 0.1, 1),
+        span("produce", "node0", 1, 0.3, 0.5, 2),
+        span("validate", "node0", 2, 1.0, 1.3, 3),
+        span("produce", "node0", 2, 1.4, 1.9, 4),
+        // Different node, paired only when nodes don't have to match
+        span("produce", "node1", 3, 2.0, 2.1, 5),
+        span("validate", "node0", 3, 1.8, 1.9, 6),
+        // No counterpart
+        span("validate", "node0", 4, 3.0, 3.1, 7),
+    ];
+
+    let pairs = pair_spans(&spans, "validate", "produce", "height", true);
+    assert_eq!(pairs.len(), 2);
+    assert_eq!(pairs[0].link_value, "1");
+    assert_eq!(pairs[1].link_value, "2");
+
+    let (x, y) = pairs[1].values(CorrelationMetric::Durations);
+    assert_relative_eq!(x, 0.3, epsilon = 1e-9);
+    assert_relative_eq!(y, 0.5, epsilon = 1e-9);
+    let (_, delay) = pairs[0].values(CorrelationMetric::DurationAndDelay);
+    assert_relative_eq!(delay, 0.2, epsilon = 1e-9);
+
+    let pairs = pair_spans(&spans, "validate", "produce", "height", false);
+    assert_eq!(pairs.len(), 3);
+}
+
+#[test]
+fn test_pearson_correlation() {
+    let linear = [(1.0, 2.0), (2.0, 4.0), (3.0, 6.0)];
+    assert_relative_eq!(pearson_correlation(&linear).unwrap(), 1.0, epsilon = 1e-9);
+    let inverse = [(1.0, 3.0), (2.0, 2.0), (3.0, 1.0)];
+    assert_relative_eq!(pearson_correlation(&inverse).unwrap(), -1.0, epsilon = 1e-9);
+    assert_eq!(describe_correlation(-0.9), "strong");
+    assert_eq!(describe_correlation(0.1), "none");
+
+    assert!(pearson_correlation(&[(1.0, 1.0)]).is_none());
+    assert!(pearson_correlation(&[(1.0, 1.0), (2.0, 1.0)]).is_none());
+}
@@ -0,0 +1,41 @@
+mod test_helpers;
+
+use test_helpers::{ScenarioBuilder, SpanConfig, TimeInterval};
+use traviz::analyze_dependency::AnalyzeDependencyModal;
+use traviz::dependency_export::dependency_links_to_json;
+
+#[test]
+fn test_dependency_links_to_json() {
+    let mut builder = ScenarioBuilder::new();
+    builder.add_node("node_a");
+    builder.add_span(
+        SpanConfig::new("task", "node_a", TimeInterval::with_duration(0.0, 1.0))
+            .with_string_attr("height", "100"),
+    );
+    builder.add_span(
+        SpanConfig::new("process", "node_a", TimeInterval::with_duration(1.5, 1.0))
+            .with_string_attr("height", "100"),
+    );
+    let scenario = builder.build();
+
+    let mut modal = AnalyzeDependencyModal::new();
+    modal.update_span_list(&scenario.all_spans);
+    modal.set_source_span_name(Some("task".to_string()));
+    modal.set_target_span_name(Some("process".to_string()));
+    modal.set_linking_attribute("height".to_string());
+    modal.analyze_dependencies();
+
+    let json = dependency_links_to_json(modal.analysis_result.as_ref().unwrap());
+    let exported: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(exported["source_span_name"], "task");
+    assert_eq!(exported["target_span_name"], "process");
+    let links = exported["links"].as_array().unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0]["node"], "node_a");
+    assert_eq!(links[0]["delay_seconds"], 0.5);
+    let source = &links[0]["source_spans"][0];
+    assert_eq!(source["name"], "task");
+    assert_eq!(source["end_time"], 1.0);
+    assert_eq!(source["attributes"]["height"], "100");
+    assert_eq!(links[0]["target_spans"][0]["name"], "process");
+}
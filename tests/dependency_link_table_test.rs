@@ -0,0 +1,68 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+
+use test_helpers::{create_test_node, create_test_span_with_attributes, int_attr, string_attr};
+use traviz::dependency_link_table::{DependencyLinkTable, LinkSortColumn};
+use traviz::DependencyLink;
+
+#[test]
+fn test_dependency_link_table_rows() {
+    let node = create_test_node("node0");
+    let span = |name: &str, start: f64, id: u8, height: i64, shard: &str| {
+        create_test_span_with_attributes(
+            name,
+            node.clone(),
+            start,
+            start + 1.0,
+            &[id],
+            BTreeMap::from([
+                ("height".to_string(), int_attr(height)),
+                ("shard".to_string(), string_attr(shard)),
+            ]),
+        )
+    };
+    let links = vec![
+        DependencyLink {
+            source_spans: vec![
+                span("chunk", 0.0, 1, 10, "a"),
+                span("chunk", 0.5, 2, 10, "b"),
+            ],
+            target_spans: vec![span("block", 3.0, 3, 11, "a")],
+            delay_seconds: 1.5,
+        },
+        DependencyLink {
+            source_spans: vec![span("chunk", 5.0, 4, 11, "a")],
+            target_spans: vec![span("block", 6.5, 5, 12, "a")],
+            delay_seconds: 0.5,
+        },
+    ];
+
+    let mut table = DependencyLinkTable::new();
+    let rows = table.rows(&links, "height+1", "shard");
+    assert_eq!(
+        rows.iter().map(|row| row.index).collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+    assert_eq!(rows[0].time, 0.0);
+    assert_eq!(rows[0].linking_values, "height=11");
+    assert_eq!(rows[0].group_keys, "a, b");
+    assert_eq!(rows[1].group_keys, "a");
+
+    table.sort_by(LinkSortColumn::Delay);
+    let indexes = |table: &DependencyLinkTable| -> Vec<usize> {
+        table
+            .rows(&links, "", "")
+            .into_iter()
+            .map(|row| row.index)
+            .collect()
+    };
+    assert_eq!(indexes(&table), vec![1, 0]);
+    // Sorting by the same column again puts the slowest link first
+    table.sort_by(LinkSortColumn::Delay);
+    assert_eq!(indexes(&table), vec![0, 1]);
+
+    let rows = table.rows(&links, "", "");
+    assert_eq!(rows[0].linking_values, "");
+    assert_eq!(rows[0].group_keys, "");
+}
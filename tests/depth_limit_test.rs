@@ -0,0 +1,39 @@
+use traviz::depth_limit::DepthLimit;
+use traviz::span_id::SpanId;
+
+#[test]
+fn test_depth_limit() {
+    let span = SpanId::intern(&[1]);
+    let other = SpanId::intern(&[2]);
+
+    let mut limit = DepthLimit::default();
+    assert!(!limit.cuts_children(span, 10));
+
+    limit.enabled = true;
+    limit.max_depth = 3;
+    // Levels 0, 1 and 2 are displayed, the children of level 2 spans are cut.
+    assert!(!limit.cuts_children(span, 0));
+    assert!(!limit.cuts_children(span, 1));
+    assert!(limit.cuts_children(span, 2));
+    assert!(limit.cuts_children(span, 5));
+
+    // Only top level spans.
+    limit.max_depth = 1;
+    assert!(limit.cuts_children(span, 0));
+    // 0 behaves like 1.
+    limit.max_depth = 0;
+    assert!(limit.cuts_children(span, 0));
+
+    limit.max_depth = 3;
+    limit.toggle_expanded(span);
+    assert!(limit.is_expanded(span));
+    assert!(!limit.cuts_children(span, 2));
+    assert!(limit.cuts_children(other, 2));
+
+    limit.toggle_expanded(span);
+    assert!(limit.cuts_children(span, 2));
+
+    limit.toggle_expanded(other);
+    limit.clear_expanded();
+    assert!(!limit.is_expanded(other));
+}
@@ -0,0 +1,53 @@
+use std::collections::HashSet;
+
+use traviz::duration_filter::{highlighted_span_ancestors, DurationFilter, ShortSpans};
+
+mod test_helpers;
+use test_helpers::{create_test_node, create_test_span};
+
+#[test]
+fn test_duration_filter() {
+    let node = create_test_node("node0");
+    // 0.5 ms and 20 ms long.
+    let short = create_test_span("short", node.clone(), 1.0, 1.0005, &[1]);
+    let long = create_test_span("long", node, 1.0, 1.02, &[2]);
+
+    let mut filter = DurationFilter::default();
+    assert!(!filter.is_active());
+    assert!(!filter.hides(&short) && !filter.dims(&short));
+    assert_eq!(filter.hidden_below(), 0.0);
+
+    filter.min_duration_ms = 1.0;
+    assert!(filter.is_active());
+    assert!(filter.hides(&short));
+    assert!(!filter.hides(&long));
+    assert!(!filter.dims(&short));
+    assert_eq!(filter.hidden_below(), 0.001);
+
+    // Dimmed spans stay in the layout.
+    filter.short_spans = ShortSpans::Dim;
+    assert!(!filter.hides(&short));
+    assert!(filter.dims(&short));
+    assert!(!filter.dims(&long));
+    assert_eq!(filter.hidden_below(), 0.0);
+}
+
+#[test]
+fn test_highlighted_span_ancestors() {
+    let node = create_test_node("node0");
+    let root = create_test_span("root", node.clone(), 1.0, 2.0, &[1]);
+    // A short async span which starts a long one.
+    let short = create_test_span("short", node.clone(), 1.0, 1.0005, &[2]);
+    let long_child = create_test_span("long_child", node.clone(), 1.0, 1.5, &[3]);
+    let other_short = create_test_span("other_short", node, 1.2, 1.2005, &[4]);
+    short.children_mut().push(long_child.clone());
+    root.children_mut().extend([short.clone(), other_short]);
+    let spans = [root.clone()];
+
+    assert!(highlighted_span_ancestors(&spans, &HashSet::new()).is_empty());
+    // The short span isn't cut from the layout, the highlighted span under it is shown.
+    assert_eq!(
+        highlighted_span_ancestors(&spans, &HashSet::from([long_child.span_id])),
+        HashSet::from([root.span_id, short.span_id, long_child.span_id])
+    );
+}
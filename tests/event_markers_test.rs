@@ -0,0 +1,56 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use approx::assert_relative_eq;
+use test_helpers::{create_test_node, create_test_span};
+use traviz::structured_modes::{builtin_structured_modes, StructuredMode};
+use traviz::types::{Event, Span};
+
+fn event(name: &str, time: f64) -> Event {
+    Event {
+        name: name.to_string(),
+        time,
+        attributes: BTreeMap::new(),
+    }
+}
+
+#[test]
+fn test_event_marker_positions() {
+    let span = create_test_span("apply_chunk", create_test_node("node0"), 1.0, 3.0, &[1]);
+    let mut data = (*span.data).clone();
+    data.events = vec![
+        event("start", 1.0),
+        event("checkpoint", 1.5),
+        event("outside", 3.5),
+        event("end", 3.0),
+    ];
+    let span = Rc::new(Span::new(Arc::new(data)));
+    span.display_start.set(100.0);
+    span.time_display_length.set(200.0);
+
+    let positions: Vec<(f32, String)> = span
+        .event_marker_positions()
+        .into_iter()
+        .map(|(x, e)| (x, e.name.clone()))
+        .collect();
+    assert_eq!(positions.len(), 3);
+    assert_relative_eq!(positions[0].0, 100.0);
+    assert_relative_eq!(positions[1].0, 150.0);
+    assert_eq!(positions[1].1, "checkpoint");
+    assert_relative_eq!(positions[2].0, 300.0);
+}
+
+#[test]
+fn test_event_markers_setting_defaults_to_off() {
+    // Modes saved before the setting existed don't have the field
+    let mut mode_json = serde_json::to_value(&builtin_structured_modes()[0]).unwrap();
+    mode_json
+        .as_object_mut()
+        .unwrap()
+        .remove("show_event_markers");
+    let mode: StructuredMode = serde_json::from_value(mode_json).unwrap();
+    assert!(!mode.show_event_markers);
+}
@@ -0,0 +1,137 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use test_helpers::{create_test_node, create_test_span, string_attr};
+use traviz::event_search::{find_matching_events, EventSearchModal};
+use traviz::modes::OrphanSpans;
+use traviz::search::parse_search_term;
+use traviz::structured_modes::{everything_structured_mode, LaneLayout};
+use traviz::types::{Event, Span};
+
+fn span_with_events(name: &str, node: &str, events: Vec<Event>) -> Rc<Span> {
+    let span = create_test_span(name, create_test_node(node), 1.0, 5.0, &[1]);
+    let mut data = (*span.data).clone();
+    data.events = events;
+    Rc::new(Span::new(Arc::new(data)))
+}
+
+fn event(name: &str, time: f64, shard_id: &str) -> Event {
+    let mut attributes = BTreeMap::new();
+    attributes.insert("shard_id".to_string(), string_attr(shard_id));
+    Event {
+        name: name.to_string(),
+        time,
+        attributes,
+    }
+}
+
+fn test_spans() -> Vec<Rc<Span>> {
+    vec![
+        span_with_events(
+            "apply_chunk",
+            "node0",
+            vec![event("state_changed", 3.0, "1"), event("done", 4.0, "1")],
+        ),
+        span_with_events(
+            "produce_block",
+            "node1",
+            vec![event("state_changed", 2.0, "0")],
+        ),
+    ]
+}
+
+#[test]
+fn test_find_matching_events() {
+    let spans = test_spans();
+
+    let all = find_matching_events(&spans, &[]);
+    let times: Vec<f64> = all.iter().map(|m| m.time()).collect();
+    assert_eq!(times, vec![2.0, 3.0, 4.0]);
+
+    let by_name = find_matching_events(&spans, &parse_search_term("STATE"));
+    assert_eq!(by_name.len(), 2);
+    assert_eq!(by_name[0].span.name, "produce_block");
+
+    let by_attribute = find_matching_events(&spans, &parse_search_term("state shard_id=1"));
+    assert_eq!(by_attribute.len(), 1);
+    assert_eq!(by_attribute[0].event().name, "state_changed");
+    assert_eq!(by_attribute[0].span.node.name, "node0");
+}
+
+#[test]
+fn test_event_lane_results() {
+    let mut mode = everything_structured_mode();
+    let mut modal = EventSearchModal::new();
+    modal.open(test_spans());
+    modal.set_query("state_changed");
+    assert_eq!(modal.results().len(), 2);
+
+    // The lane is off by default
+    assert!(modal
+        .lane_results(&mode, OrphanSpans::Root, "node0")
+        .is_empty());
+
+    modal.show_lane = true;
+    let node0 = modal.lane_results(&mode, OrphanSpans::Root, "node0");
+    assert_eq!(node0.len(), 1);
+    assert_eq!(node0[0].time(), 3.0);
+    assert_eq!(
+        modal.lane_results(&mode, OrphanSpans::Root, "node1").len(),
+        1
+    );
+
+    mode.lane_layout = LaneLayout::PerSpanName;
+    assert_eq!(
+        modal
+            .lane_results(&mode, OrphanSpans::Root, "produce_block")
+            .len(),
+        1
+    );
+
+    // Sub-lanes are chosen by attributes of the span, not of the event
+    mode.sub_lane_attribute = "shard_id".to_string();
+    assert_eq!(
+        modal
+            .lane_results(&mode, OrphanSpans::Root, "produce_block")
+            .len(),
+        1
+    );
+    assert!(modal
+        .lane_results(&mode, OrphanSpans::Root, "produce_block shard_id=0")
+        .is_empty());
+
+    modal.clear();
+    assert!(modal
+        .lane_results(&mode, OrphanSpans::Root, "apply_chunk shard_id=1")
+        .is_empty());
+}
+
+#[test]
+fn test_event_lane_results_of_orphans() {
+    let mut orphan =
+        (*span_with_events("apply_chunk", "node0", vec![event("done", 4.0, "1")])).clone();
+    orphan.orphan = true;
+    let mode = everything_structured_mode();
+    let mut modal = EventSearchModal::new();
+    modal.open(vec![Rc::new(orphan)]);
+    modal.set_query("done");
+    modal.show_lane = true;
+
+    // Orphans in their own lane have their events there too
+    assert_eq!(
+        modal.lane_results(&mode, OrphanSpans::Root, "node0").len(),
+        1
+    );
+    assert!(modal
+        .lane_results(&mode, OrphanSpans::Lane, "node0")
+        .is_empty());
+    assert_eq!(
+        modal
+            .lane_results(&mode, OrphanSpans::Lane, "node0 orphans")
+            .len(),
+        1
+    );
+}
@@ -0,0 +1,86 @@
+use traviz::external_events::{
+    parse_csv_events, parse_event_time, parse_json_events, ExternalEvent,
+};
+
+#[test]
+fn test_parse_event_time() {
+    assert_eq!(parse_event_time("1735689600").unwrap(), 1735689600.0);
+    assert_eq!(parse_event_time("1735689600.5").unwrap(), 1735689600.5);
+    // Milliseconds, microseconds and nanoseconds
+    assert_eq!(parse_event_time("1735689600500").unwrap(), 1735689600.5);
+    assert_eq!(parse_event_time("1735689600500000").unwrap(), 1735689600.5);
+    let nanoseconds = parse_event_time("1735689600500000000").unwrap();
+    assert!((nanoseconds - 1735689600.5).abs() < 1e-6, "{nanoseconds}");
+    assert_eq!(
+        parse_event_time("2025-01-01T00:00:00.25Z").unwrap(),
+        1735689600.25
+    );
+    assert_eq!(
+        parse_event_time("2025-01-01T02:00:00+02:00").unwrap(),
+        1735689600.0
+    );
+    assert!(parse_event_time("yesterday").is_err());
+}
+
+#[test]
+fn test_parse_csv_events() {
+    let csv = "Timestamp,Category,Label\r\n\
+               1735689660,chaos,\"kill node, then restart\"\r\n\
+               \r\n\
+               1735689600,deploy,\"patch \"\"v2\"\"\"\r\n\
+               1735689700,,alert fired\r\n";
+    assert_eq!(
+        parse_csv_events(csv).unwrap(),
+        vec![
+            ExternalEvent {
+                time: 1735689600.0,
+                label: "patch \"v2\"".to_string(),
+                category: Some("deploy".to_string()),
+            },
+            ExternalEvent {
+                time: 1735689660.0,
+                label: "kill node, then restart".to_string(),
+                category: Some("chaos".to_string()),
+            },
+            ExternalEvent {
+                time: 1735689700.0,
+                label: "alert fired".to_string(),
+                category: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_csv_events_errors() {
+    assert!(parse_csv_events("").is_err());
+    assert!(parse_csv_events("label\nsomething\n").is_err());
+    let err = parse_csv_events("time,label\n1,a\nnever,b\n").unwrap_err();
+    assert!(format!("{err:#}").contains("row 3"), "{err:#}");
+}
+
+#[test]
+fn test_parse_json_events() {
+    let expected = vec![
+        ExternalEvent {
+            time: 1735689600.0,
+            label: "deploy".to_string(),
+            category: None,
+        },
+        ExternalEvent {
+            time: 1735689601.5,
+            label: "alert".to_string(),
+            category: Some("pager".to_string()),
+        },
+    ];
+    let array = r#"[
+        {"timestamp": 1735689601500, "name": "alert", "type": "pager"},
+        {"time": "2025-01-01T00:00:00Z", "label": "deploy"}
+    ]"#;
+    assert_eq!(parse_json_events(array).unwrap(), expected);
+    let object = format!(r#"{{"events": {array}}}"#);
+    assert_eq!(parse_json_events(&object).unwrap(), expected);
+
+    assert!(parse_json_events(r#"{"other": []}"#).is_err());
+    assert!(parse_json_events(r#"[{"label": "no time"}]"#).is_err());
+}
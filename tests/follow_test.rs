@@ -0,0 +1,32 @@
+use traviz::follow::{follow_range, FileWatcher};
+
+mod test_helpers;
+use test_helpers::TestDir;
+
+#[test]
+fn test_follow_range() {
+    assert_eq!(follow_range(10.0, 12.5, 20.0), (17.5, 20.0));
+    // The range can also move back, e.g. after the file was truncated.
+    assert_eq!(follow_range(10.0, 11.0, 5.0), (4.0, 5.0));
+}
+
+#[test]
+fn test_file_watcher() {
+    let dir = TestDir::new("follow");
+    let path = dir.0.join("trace.json");
+    std::fs::write(&path, "[]").unwrap();
+
+    let mut watcher = FileWatcher::new(&path);
+    assert!(!watcher.check());
+    // Checked right after the previous check, the change isn't noticed yet.
+    std::fs::write(&path, "[{\"resourceSpans\": []}]").unwrap();
+    assert!(!watcher.poll());
+    assert!(watcher.check());
+    assert!(!watcher.check());
+
+    // While the file is missing nothing is reported, it's noticed when it comes back.
+    std::fs::remove_file(&path).unwrap();
+    assert!(!watcher.check());
+    std::fs::write(&path, "[]").unwrap();
+    assert!(watcher.check());
+}
@@ -0,0 +1,56 @@
+mod test_helpers;
+
+use std::collections::HashSet;
+
+use test_helpers::{create_test_node, create_test_span};
+use traviz::html_export::{export_html, ExportLane};
+use traviz::span_id::SpanId;
+
+#[test]
+fn test_export_html() {
+    let node = create_test_node("node0");
+    let parent = create_test_span("parent", node.clone(), 1.0, 3.0, &[1]);
+    let overlapping = create_test_span("</script>", node.clone(), 2.0, 4.0, &[2]);
+    let outside = create_test_span("outside_span", node.clone(), 10.0, 11.0, &[3]);
+    let child = create_test_span("child_span", node.clone(), 1.5, 2.5, &[4]);
+    parent.display_children.borrow_mut().push(child);
+
+    let lanes = vec![ExportLane {
+        name: "node0".to_string(),
+        spans: vec![parent, overlapping, outside],
+    }];
+    let html = export_html(
+        "test <export>",
+        &lanes,
+        0.0,
+        5.0,
+        &HashSet::from([SpanId::intern(&[4])]),
+    );
+
+    assert!(html.contains("<title>test &lt;export&gt;</title>"));
+    // Span names can't end the script tag
+    assert!(!html.contains("\"</script>\""));
+    assert!(html.contains("<\\/script>"));
+    assert!(!html.contains("outside_span"));
+
+    // Overlapping top level spans get separate rows, the child is placed below them
+    let data_start = html.find("const data = ").unwrap() + "const data = ".len();
+    let data_end = data_start + html[data_start..].find(";\n").unwrap();
+    let data: serde_json::Value = serde_json::from_str(&html[data_start..data_end]).unwrap();
+    let lane = &data["lanes"][0];
+    assert_eq!(lane["rows"], 3);
+    let spans = lane["spans"].as_array().unwrap();
+    let row_of = |name: &str| {
+        spans
+            .iter()
+            .find(|s| s["name"] == name)
+            .map(|s| s["row"].as_u64().unwrap())
+            .unwrap()
+    };
+    assert_eq!(row_of("parent"), 0);
+    assert_eq!(row_of("child_span"), 2);
+    assert_eq!(
+        spans.iter().find(|s| s["name"] == "child_span").unwrap()["highlighted"],
+        true
+    );
+}
@@ -0,0 +1,78 @@
+mod test_helpers;
+
+use std::collections::HashSet;
+
+use eframe::egui::{Color32, ColorImage, Pos2, Rect};
+use test_helpers::{create_test_node, create_test_span};
+use traviz::html_export::ExportLane;
+use traviz::image_export::{crop_screenshot, encode_png, export_svg};
+use traviz::span_id::SpanId;
+
+#[test]
+fn test_export_svg() {
+    let node = create_test_node("node0");
+    let inside = create_test_span("inside_span", node.clone(), 1.0, 3.0, &[1]);
+    let outside = create_test_span("outside_span", node.clone(), 10.0, 11.0, &[2]);
+    let lanes = vec![ExportLane {
+        name: "node<0>".to_string(),
+        spans: vec![inside, outside],
+    }];
+
+    let svg = export_svg(
+        "test",
+        &lanes,
+        0.0,
+        5.0,
+        1234,
+        &HashSet::from([SpanId::intern(&[1])]),
+    );
+
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.contains("width=\"1234\""));
+    assert!(svg.contains("node&lt;0&gt;"));
+    assert!(svg.contains("inside_span"));
+    assert!(!svg.contains("outside_span"));
+    // The span is 2 of the 5 seconds, starting at 1 second
+    assert!(svg.contains("x=\"246.8\""));
+    assert!(svg.contains("width=\"493.6\""));
+}
+
+#[test]
+fn test_encode_png() {
+    let image = ColorImage::new([3, 2], Color32::RED);
+    let png = encode_png(&image);
+
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(&png[16..20], &3u32.to_be_bytes());
+    assert_eq!(&png[20..24], &2u32.to_be_bytes());
+    // The IEND chunk is always the same, including its checksum
+    assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xae\x42\x60\x82");
+}
+
+#[test]
+fn test_crop_screenshot() {
+    let image = ColorImage::new([200, 100], Color32::RED);
+
+    let cropped = crop_screenshot(
+        &image,
+        Rect::from_min_max(Pos2::new(10.0, 10.0), Pos2::new(50.0, 30.0)),
+        2.0,
+    );
+    assert_eq!(cropped.size, [80, 40]);
+
+    // The part outside of the screenshot is left out
+    let clamped = crop_screenshot(
+        &image,
+        Rect::from_min_max(Pos2::new(80.0, 40.0), Pos2::new(500.0, 500.0)),
+        2.0,
+    );
+    assert_eq!(clamped.size, [40, 20]);
+
+    let outside = crop_screenshot(
+        &image,
+        Rect::from_min_max(Pos2::new(300.0, 300.0), Pos2::new(400.0, 400.0)),
+        2.0,
+    );
+    assert_eq!(outside.size, [0, 0]);
+}
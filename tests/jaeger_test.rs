@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use traviz::jaeger::{is_jaeger_json, parse_jaeger_json};
+use traviz::modes::structured_mode_transformation;
+use traviz::structured_modes::everything_structured_mode;
+
+const JAEGER_TRACE: &str = r#"{
+  "data": [
+    {
+      "traceID": "0af7651916cd43dd8448eb211c80319c",
+      "spans": [
+        {
+          "traceID": "0af7651916cd43dd8448eb211c80319c",
+          "spanID": "b7ad6b7169203331",
+          "operationName": "produce_block",
+          "references": [],
+          "startTime": 1700000000000000,
+          "duration": 500000,
+          "tags": [
+            {"key": "height", "type": "int64", "value": 100},
+            {"key": "is_final", "type": "bool", "value": true}
+          ],
+          "logs": [
+            {"timestamp": 1700000000100000, "fields": [{"key": "event", "type": "string", "value": "block produced"}]}
+          ],
+          "processID": "p1"
+        },
+        {
+          "traceID": "0af7651916cd43dd8448eb211c80319c",
+          "spanID": "0000000000000002",
+          "operationName": "apply_chunk",
+          "references": [
+            {"refType": "CHILD_OF", "traceID": "0af7651916cd43dd8448eb211c80319c", "spanID": "b7ad6b7169203331"}
+          ],
+          "startTime": 1700000000100000,
+          "duration": 200000,
+          "tags": [],
+          "logs": [],
+          "processID": "p1"
+        },
+        {
+          "traceID": "0af7651916cd43dd8448eb211c80319c",
+          "spanID": "3",
+          "operationName": "receive_block",
+          "references": [],
+          "startTime": 1700000000600000,
+          "duration": 100000,
+          "tags": [],
+          "logs": [],
+          "processID": "p2"
+        }
+      ],
+      "processes": {
+        "p1": {"serviceName": "node0", "tags": []},
+        "p2": {"serviceName": "node1", "tags": [{"key": "hostname", "type": "string", "value": "host1"}]}
+      }
+    }
+  ]
+}"#;
+
+#[test]
+fn test_jaeger_import() {
+    assert!(is_jaeger_json(JAEGER_TRACE));
+    assert!(!is_jaeger_json("  [{\"resourceSpans\": []}]"));
+
+    let requests = parse_jaeger_json(JAEGER_TRACE).unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].resource_spans.len(), 2);
+
+    let spans = structured_mode_transformation(&requests, &everything_structured_mode()).unwrap();
+
+    let mut roots_per_node: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for span in &spans {
+        roots_per_node
+            .entry(span.node.name.clone())
+            .or_default()
+            .push(span.original_name.clone());
+    }
+    assert_eq!(roots_per_node["node0"], vec!["produce_block".to_string()]);
+    assert_eq!(roots_per_node["node1"], vec!["receive_block".to_string()]);
+
+    let produce_block = spans
+        .iter()
+        .find(|s| s.original_name == "produce_block")
+        .unwrap();
+    approx::assert_relative_eq!(produce_block.start_time, 1700000000.0);
+    approx::assert_relative_eq!(produce_block.end_time, 1700000000.5);
+    assert_eq!(produce_block.events.len(), 1);
+    assert_eq!(produce_block.events[0].name, "block produced");
+    assert!(produce_block.attributes.contains_key("height"));
+
+    let children = produce_block.children();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].original_name, "apply_chunk");
+    assert!(Arc::ptr_eq(&children[0].node, &produce_block.node));
+}
+
+#[test]
+fn test_jaeger_time_overflow() {
+    let overflowing_duration = JAEGER_TRACE.replace(
+        r#""duration": 500000"#,
+        r#""duration": 18446744073709551615"#,
+    );
+    let error = parse_jaeger_json(&overflowing_duration).unwrap_err();
+    assert!(error.to_string().contains("produce_block"), "{error}");
+
+    let overflowing_start = JAEGER_TRACE.replace(
+        r#""startTime": 1700000000600000"#,
+        r#""startTime": 18446744073709552"#,
+    );
+    let error = parse_jaeger_json(&overflowing_start).unwrap_err();
+    assert!(error.to_string().contains("receive_block"), "{error}");
+
+    let overflowing_log = JAEGER_TRACE.replace(
+        r#""timestamp": 1700000000100000"#,
+        r#""timestamp": 18446744073709552"#,
+    );
+    assert!(parse_jaeger_json(&overflowing_log).is_err());
+}
@@ -0,0 +1,73 @@
+use chrono::Local;
+use log::Level;
+use traviz::log_viewer::LogViewer;
+use traviz::logging::{self, LogRecord};
+
+fn record(level: Level, target: &str, message: &str) -> LogRecord {
+    LogRecord {
+        time: Local::now(),
+        level,
+        target: target.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[test]
+fn test_logger_collects_records() {
+    logging::init();
+    log::info!(target: "traviz::relation", "Found 5 relations");
+    log::debug!(target: "traviz::modes", "Transformed trace");
+    // Only warnings and errors from other crates are recorded
+    log::info!(target: "winit::window", "Resized");
+    log::warn!(target: "winit::window", "Lost focus");
+
+    let mut viewer = LogViewer::new();
+    viewer.poll();
+    let messages: Vec<&str> = viewer
+        .records()
+        .iter()
+        .map(|r| r.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        vec!["Found 5 relations", "Transformed trace", "Lost focus"]
+    );
+
+    // Records are taken only once
+    viewer.poll();
+    assert_eq!(viewer.records().len(), 3);
+}
+
+#[test]
+fn test_log_viewer_filters() {
+    let mut viewer = LogViewer::new();
+    viewer.add_records(vec![
+        record(Level::Error, "traviz::persistent", "Failed to save data"),
+        record(Level::Warn, "traviz::modes", "Grouping span without height"),
+        record(Level::Info, "traviz::relation", "Found 3 relations"),
+        record(Level::Debug, "traviz::task_timer", "Task: load started"),
+    ]);
+    let messages = |viewer: &LogViewer| -> Vec<String> {
+        viewer
+            .filtered_records()
+            .iter()
+            .map(|r| r.message.clone())
+            .collect()
+    };
+
+    // Debug records are hidden by default
+    assert_eq!(messages(&viewer).len(), 3);
+
+    viewer.set_level(Level::Warn);
+    assert_eq!(
+        messages(&viewer),
+        vec!["Failed to save data", "Grouping span without height"]
+    );
+
+    // Search is case insensitive and also matches the target
+    viewer.set_level(Level::Trace);
+    viewer.set_search("RELATION");
+    assert_eq!(messages(&viewer), vec!["Found 3 relations"]);
+    viewer.set_search("task_timer");
+    assert_eq!(messages(&viewer), vec!["Task: load started"]);
+}
@@ -0,0 +1,62 @@
+use eframe::egui::Key;
+use traviz::macros::{bindable_keys, KeyMacro, MacroAction, RunningMacro};
+
+#[test]
+fn test_macro_key_round_trip() {
+    for key in bindable_keys() {
+        let key_macro = KeyMacro {
+            name: "triage".to_string(),
+            key: Some(key.name().to_string()),
+            actions: vec![],
+        };
+        assert_eq!(key_macro.key(), Some(key));
+    }
+
+    let unbound = KeyMacro {
+        name: "unbound".to_string(),
+        key: None,
+        actions: vec![],
+    };
+    assert_eq!(unbound.key(), None);
+}
+
+#[test]
+fn test_macro_serialization() {
+    let key_macro = KeyMacro {
+        name: "nightly triage".to_string(),
+        key: Some(Key::F5.name().to_string()),
+        actions: vec![
+            MacroAction::SwitchDisplayMode("Everything".to_string()),
+            MacroAction::SetNodeFilter("Show all".to_string()),
+            MacroAction::SetRelationView("No relations".to_string()),
+            MacroAction::RunDependencyAnalysis(
+                "Analysis of dependency: 'a' -> 'b' (threshold: 1)".to_string(),
+            ),
+            MacroAction::ExportHtml("/tmp/triage.html".to_string()),
+        ],
+    };
+    let json = serde_json::to_string(&key_macro).unwrap();
+    let parsed: KeyMacro = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, key_macro);
+}
+
+#[test]
+fn test_running_macro_waits_for_analysis() {
+    let analysis =
+        MacroAction::RunDependencyAnalysis("Analysis of dependency: 'a' -> 'b'".to_string());
+    let export = MacroAction::ExportHtml("/tmp/triage.html".to_string());
+    let key_macro = KeyMacro {
+        name: "triage".to_string(),
+        key: None,
+        actions: vec![analysis.clone(), export.clone()],
+    };
+
+    let mut running = RunningMacro::new(&key_macro);
+    assert_eq!(running.next_action(false), Some(analysis));
+    // The export waits until the analysis started by the previous action finishes
+    assert_eq!(running.next_action(true), None);
+    assert!(!running.is_finished());
+    assert_eq!(running.next_action(false), Some(export));
+    assert!(running.is_finished());
+    assert_eq!(running.next_action(false), None);
+}
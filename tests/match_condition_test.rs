@@ -0,0 +1,95 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+
+use test_helpers::{create_test_node, create_test_span_with_attributes, int_attr};
+use traviz::structured_modes::{MatchCondition, MatchOperator, SpanSelector};
+
+fn condition(operator: MatchOperator, value: &str) -> MatchCondition {
+    MatchCondition {
+        operator,
+        value: value.to_string(),
+    }
+}
+
+#[test]
+fn test_regex_operator() {
+    let regex = condition(MatchOperator::Regex, "^apply_(new|old)_chunk$");
+    assert!(regex.matches("apply_new_chunk"));
+    assert!(regex.matches("apply_old_chunk"));
+    assert!(!regex.matches("apply_chunk"));
+    assert!(regex.validation_error().is_none());
+
+    let invalid = condition(MatchOperator::Regex, "apply_(");
+    assert!(!invalid.matches("apply_("));
+    assert!(invalid.validation_error().is_some());
+}
+
+#[test]
+fn test_regex_operator_with_many_patterns() {
+    // More patterns than the compiled regexes which are kept, the evicted ones are compiled again
+    let patterns: Vec<MatchCondition> = (0..100)
+        .map(|i| condition(MatchOperator::Regex, &format!("^chunk_{i}$")))
+        .collect();
+    for _ in 0..2 {
+        for (i, pattern) in patterns.iter().enumerate() {
+            assert!(pattern.matches(&format!("chunk_{i}")));
+            assert!(!pattern.matches(&format!("chunk_{}", i + 1)));
+        }
+    }
+}
+
+#[test]
+fn test_numeric_operators() {
+    let greater = condition(MatchOperator::GreaterThan, "10");
+    assert!(greater.matches("11"));
+    assert!(greater.matches("10.5"));
+    assert!(!greater.matches("10"));
+    assert!(!greater.matches("abc"));
+
+    let less = condition(MatchOperator::LessThan, "10");
+    assert!(less.matches("9"));
+    assert!(less.matches("-3"));
+    assert!(!less.matches("100"));
+
+    let range = condition(MatchOperator::InRange, "3..5");
+    assert!(range.matches("3"));
+    assert!(range.matches("4.2"));
+    assert!(range.matches("5"));
+    assert!(!range.matches("6"));
+    assert!(range.validation_error().is_none());
+
+    assert!(condition(MatchOperator::InRange, "3-5")
+        .validation_error()
+        .is_some());
+    assert!(condition(MatchOperator::GreaterThan, "x")
+        .validation_error()
+        .is_some());
+}
+
+#[test]
+fn test_attribute_exists_operators() {
+    let mut attributes = BTreeMap::new();
+    attributes.insert("shard_id".to_string(), int_attr(2));
+    let span = create_test_span_with_attributes(
+        "apply_chunk",
+        create_test_node("node0"),
+        1.0,
+        2.0,
+        &[1],
+        attributes,
+    );
+
+    let selector_with = |attr: &str, operator: MatchOperator, value: &str| SpanSelector {
+        span_name_condition: MatchCondition::any(),
+        node_name_condition: MatchCondition::any(),
+        attribute_conditions: vec![(attr.to_string(), condition(operator, value))],
+    };
+
+    assert!(selector_with("shard_id", MatchOperator::Exists, "").matches(&span));
+    assert!(!selector_with("height", MatchOperator::Exists, "").matches(&span));
+    assert!(!selector_with("shard_id", MatchOperator::NotExists, "").matches(&span));
+    assert!(selector_with("height", MatchOperator::NotExists, "").matches(&span));
+    assert!(selector_with("shard_id", MatchOperator::InRange, "0..3").matches(&span));
+    assert!(!selector_with("shard_id", MatchOperator::GreaterThan, "2").matches(&span));
+}
@@ -0,0 +1,75 @@
+use traviz::metrics::{
+    format_metric_value, parse_metrics_csv, parse_prometheus_json, MetricSeries,
+};
+
+#[test]
+fn test_parse_prometheus_range_query() {
+    let json = r#"{
+        "status": "success",
+        "data": {
+            "resultType": "matrix",
+            "result": [
+                {
+                    "metric": {"__name__": "cpu_usage", "job": "neard", "instance": "node0"},
+                    "values": [[1735689601, "0.5"], [1735689600.5, "0.25"], [1735689602, "NaN"]]
+                },
+                {
+                    "metric": {"__name__": "mempool_size"},
+                    "values": [[1735689600, "120"]]
+                }
+            ]
+        }
+    }"#;
+    assert_eq!(
+        parse_prometheus_json(json).unwrap(),
+        vec![
+            MetricSeries {
+                name: "cpu_usage{instance=\"node0\", job=\"neard\"}".to_string(),
+                points: vec![(1735689600.5, 0.25), (1735689601.0, 0.5)],
+            },
+            MetricSeries {
+                name: "mempool_size".to_string(),
+                points: vec![(1735689600.0, 120.0)],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_prometheus_errors() {
+    assert!(parse_prometheus_json(r#"{"status": "error", "error": "bad query"}"#).is_err());
+    assert!(parse_prometheus_json(r#"{"status": "success", "data": {}}"#).is_err());
+    let invalid_value = r#"{"data": {"result": [{"metric": {}, "values": [[1, "x"]]}]}}"#;
+    assert!(parse_prometheus_json(invalid_value).is_err());
+}
+
+#[test]
+fn test_parse_metrics_csv() {
+    let csv = "time,cpu,mempool\n\
+               2025-01-01T00:00:01Z,0.5,\n\
+               1735689600,0.25,100\n";
+    assert_eq!(
+        parse_metrics_csv(csv).unwrap(),
+        vec![
+            MetricSeries {
+                name: "cpu".to_string(),
+                points: vec![(1735689600.0, 0.25), (1735689601.0, 0.5)],
+            },
+            MetricSeries {
+                name: "mempool".to_string(),
+                points: vec![(1735689600.0, 100.0)],
+            },
+        ]
+    );
+    assert!(parse_metrics_csv("time\n1\n").is_err());
+    assert!(parse_metrics_csv("time,cpu\n1,high\n").is_err());
+}
+
+#[test]
+fn test_format_metric_value() {
+    assert_eq!(format_metric_value(0.0), "0");
+    assert_eq!(format_metric_value(42.0), "42");
+    assert_eq!(format_metric_value(0.125), "0.125");
+    assert_eq!(format_metric_value(1234.4), "1234");
+    assert_eq!(format_metric_value(2_500_000.0), "2.500e6");
+}
@@ -0,0 +1,760 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use opentelemetry_proto::tonic::common::v1::{AnyValue, KeyValue};
+use test_helpers::{
+    create_test_node, create_test_span, create_test_span_with_attributes, int_attr,
+    otlp_resource_spans, otlp_scope_spans, otlp_span,
+};
+use traviz::breadcrumb::ancestor_chain;
+use traviz::collapse::set_collapse_all;
+use traviz::modes::{
+    extract_span_data, orphans_lane_name, structured_mode_transformation,
+    structured_mode_transformation_lazy, structured_mode_transformation_muted, trace_span_ids,
+    OrphanSpans, RemoteParentLinking,
+};
+use traviz::relation::link_remote_parents;
+use traviz::scope_filter::ScopeFilter;
+use traviz::span_id::SpanId;
+use traviz::structured_modes::{
+    builtin_structured_modes, everything_structured_mode, preview_span_rules, GroupingRule,
+    LaneLayout, RenameRule, SpanSelector, StructuredMode, SyntheticSpanRule, SyntheticSpanSource,
+};
+use traviz::types::{set_min_max_time, value_to_text, Span};
+
+fn load_example(name: &str) -> Vec<ExportTraceServiceRequest> {
+    let path = format!("{}/examples/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let file_str = std::fs::read_to_string(path).unwrap();
+    serde_json::from_str(&file_str).unwrap()
+}
+
+#[test]
+fn test_extract_span_data_on_worker_thread() {
+    let trace_data = load_example("mini.json");
+    let expected_count = extract_span_data(&trace_data).unwrap().len();
+    assert!(expected_count > 0);
+
+    let span_data = std::thread::spawn(move || extract_span_data(&trace_data).unwrap())
+        .join()
+        .unwrap();
+    assert_eq!(span_data.len(), expected_count);
+}
+
+#[test]
+fn test_span_data_is_shared_between_spans() {
+    let trace_data = load_example("mini.json");
+    let spans = structured_mode_transformation(&trace_data, &everything_structured_mode()).unwrap();
+    assert!(!spans.is_empty());
+
+    // Cloning a span doesn't copy the trace data
+    let span = (*spans[0]).clone();
+    assert!(Arc::ptr_eq(&span.data, &spans[0].data));
+
+    // Modifying it does
+    let mut modified = span.clone();
+    Arc::make_mut(&mut modified.data).start_time -= 1.0;
+    assert!(!Arc::ptr_eq(&modified.data, &span.data));
+    assert_eq!(span.start_time, spans[0].start_time);
+}
+
+/// Name, id, time range and collapse state of every span in the tree, transforming lazy children.
+fn describe_tree(spans: &[Rc<Span>], out: &mut Vec<String>, depth: usize) {
+    for span in spans {
+        out.push(format!(
+            "{depth} {} {:?} {} {} {} {}",
+            span.name,
+            span.span_id,
+            span.min_start_time.get(),
+            span.max_end_time.get(),
+            span.collapse_children.get(),
+            span.dont_collapse_this_span.get()
+        ));
+        describe_tree(&span.children(), out, depth + 1);
+    }
+}
+
+/// Counts spans with lazy children, without transforming them.
+fn count_lazy(spans: &[Rc<Span>]) -> usize {
+    spans
+        .iter()
+        .map(|s| s.has_lazy_children() as usize + count_lazy(&s.transformed_children()))
+        .sum()
+}
+
+#[test]
+fn test_lazy_transformation_matches_eager() {
+    let trace_data = load_example("small.json");
+    let mut any_lazy = false;
+    for mode in builtin_structured_modes() {
+        let eager = structured_mode_transformation(&trace_data, &mode).unwrap();
+        set_min_max_time(&eager);
+        let lazy = structured_mode_transformation_lazy(&trace_data, &mode).unwrap();
+        set_min_max_time(&lazy);
+        any_lazy |= count_lazy(&lazy) > 0;
+
+        let mut eager_tree = Vec::new();
+        describe_tree(&eager, &mut eager_tree, 0);
+        let mut lazy_tree = Vec::new();
+        describe_tree(&lazy, &mut lazy_tree, 0);
+        assert_eq!(lazy_tree, eager_tree, "mode {}", mode.name);
+        assert_eq!(count_lazy(&lazy), 0);
+    }
+    assert!(any_lazy, "no mode has lazy children");
+}
+
+/// Original names of all spans in the tree, transforming lazy children.
+fn tree_span_names(spans: &[Rc<Span>], out: &mut Vec<String>) {
+    for span in spans {
+        out.push(span.original_name.clone());
+        tree_span_names(&span.children(), out);
+    }
+}
+
+#[test]
+fn test_muted_spans() {
+    let trace_data = load_example("small.json");
+    let muted = [SpanSelector::new_equal_name("process_transaction")];
+
+    let mut all_names = Vec::new();
+    tree_span_names(
+        &structured_mode_transformation(&trace_data, &everything_structured_mode()).unwrap(),
+        &mut all_names,
+    );
+    let mut names = Vec::new();
+    tree_span_names(
+        &structured_mode_transformation_muted(
+            &trace_data,
+            &everything_structured_mode(),
+            &muted,
+            &ScopeFilter::default(),
+            RemoteParentLinking::Stitch,
+            OrphanSpans::Root,
+            false,
+        )
+        .unwrap(),
+        &mut names,
+    );
+    assert!(all_names.iter().any(|name| name == "process_transaction"));
+    assert!(!names.iter().any(|name| name == "process_transaction"));
+    // The children of the muted spans are gone too.
+    let count = |names: &[String], name: &str| names.iter().filter(|n| *n == name).count();
+    assert!(
+        count(&names, "verify_and_charge_transaction")
+            < count(&all_names, "verify_and_charge_transaction")
+    );
+
+    let transform = |mode: &StructuredMode, lazy| {
+        structured_mode_transformation_muted(
+            &trace_data,
+            mode,
+            &muted,
+            &ScopeFilter::default(),
+            RemoteParentLinking::Stitch,
+            OrphanSpans::Root,
+            lazy,
+        )
+        .unwrap()
+    };
+    for mode in builtin_structured_modes() {
+        let eager = transform(&mode, false);
+        set_min_max_time(&eager);
+        let lazy = transform(&mode, true);
+        set_min_max_time(&lazy);
+
+        let mut eager_tree = Vec::new();
+        describe_tree(&eager, &mut eager_tree, 0);
+        let mut lazy_tree = Vec::new();
+        describe_tree(&lazy, &mut lazy_tree, 0);
+        assert_eq!(lazy_tree, eager_tree, "mode {}", mode.name);
+    }
+}
+
+/// Spans in the tree with the given original name, as (number of grouped spans, active segments).
+fn grouped_spans(spans: &[Rc<Span>], name: &str, out: &mut Vec<(usize, usize)>) {
+    for span in spans {
+        if span.original_name == name {
+            let count = span
+                .attributes
+                .get("grouped_spans_info")
+                .map_or(1, |info| value_to_text(info).lines().count());
+            out.push((count, span.active_segments.as_ref().map_or(0, Vec::len)));
+        }
+        grouped_spans(&span.children(), name, out);
+    }
+}
+
+#[test]
+fn test_grouping_rules() {
+    let trace_data = load_example("small.json");
+    let name = "verify_and_charge_transaction";
+    let grouped_with_gap = |max_gap| {
+        let mut mode = everything_structured_mode();
+        mode.grouping_rules = vec![GroupingRule {
+            name: "Group transactions".to_string(),
+            selector: SpanSelector::new_equal_name(name),
+            max_gap,
+            group_by_attribute: String::new(),
+        }];
+        let spans = structured_mode_transformation(&trace_data, &mode).unwrap();
+        let mut grouped = Vec::new();
+        grouped_spans(&spans, name, &mut grouped);
+        grouped
+    };
+
+    let mut ungrouped = Vec::new();
+    grouped_spans(
+        &structured_mode_transformation(&trace_data, &everything_structured_mode()).unwrap(),
+        name,
+        &mut ungrouped,
+    );
+    let total: usize = ungrouped.iter().map(|(count, _)| count).sum();
+
+    // With an unlimited gap there is one group per node, a small gap splits them.
+    let one_per_node = grouped_with_gap(f64::INFINITY);
+    let split = grouped_with_gap(0.0001);
+    assert!(
+        one_per_node.len() < split.len(),
+        "{one_per_node:?} {split:?}"
+    );
+    assert!(split.len() < ungrouped.len(), "{split:?}");
+    for grouped in [&one_per_node, &split] {
+        assert_eq!(grouped.iter().map(|(count, _)| count).sum::<usize>(), total);
+        assert!(grouped
+            .iter()
+            .all(|(count, segments)| *count == 1 || *segments > 0));
+    }
+
+    let mut mode = everything_structured_mode();
+    mode.grouping_rules = vec![GroupingRule {
+        name: "Group by height".to_string(),
+        selector: SpanSelector::new_equal_name("process_transaction"),
+        max_gap: 0.001,
+        group_by_attribute: "height".to_string(),
+    }];
+    let eager = structured_mode_transformation(&trace_data, &mode).unwrap();
+    set_min_max_time(&eager);
+    let lazy = structured_mode_transformation_lazy(&trace_data, &mode).unwrap();
+    set_min_max_time(&lazy);
+    let mut eager_tree = Vec::new();
+    describe_tree(&eager, &mut eager_tree, 0);
+    let mut lazy_tree = Vec::new();
+    describe_tree(&lazy, &mut lazy_tree, 0);
+    assert_eq!(lazy_tree, eager_tree);
+}
+
+#[test]
+fn test_grouped_span_members() {
+    let trace_data = load_example("small.json");
+    let name = "verify_and_charge_transaction";
+    let mut mode = everything_structured_mode();
+    mode.grouping_rules = vec![GroupingRule {
+        name: "Group transactions".to_string(),
+        selector: SpanSelector::new_equal_name(name),
+        max_gap: f64::INFINITY,
+        group_by_attribute: String::new(),
+    }];
+    let spans = structured_mode_transformation(&trace_data, &mode).unwrap();
+    let groups: Vec<&Rc<Span>> = spans
+        .iter()
+        .filter(|s| s.original_name == name && s.active_segments.is_some())
+        .collect();
+    assert!(!groups.is_empty());
+    for group in groups {
+        let info = value_to_text(&group.attributes["grouped_spans_info"]);
+        assert_eq!(group.grouped_spans.len(), info.lines().count());
+        assert!(group
+            .grouped_spans
+            .windows(2)
+            .all(|w| w[0].start_time <= w[1].start_time));
+        for member in &group.grouped_spans {
+            // The members are displayed as regular spans when the group is expanded.
+            assert!(member.active_segments.is_none());
+            assert!(member.grouped_spans.is_empty());
+            assert!(member.start_time >= group.start_time && member.end_time <= group.end_time);
+        }
+    }
+}
+
+#[test]
+fn test_remote_parents() {
+    let trace_data = vec![ExportTraceServiceRequest {
+        resource_spans: vec![
+            otlp_resource_spans(
+                Some("node0"),
+                vec![otlp_scope_spans(
+                    None,
+                    vec![
+                        otlp_span("send_request", 1, None, 0, 100),
+                        otlp_span("serialize", 2, Some(1), 10, 20),
+                    ],
+                )],
+            ),
+            otlp_resource_spans(
+                Some("node1"),
+                vec![otlp_scope_spans(
+                    None,
+                    vec![otlp_span("handle_request", 3, Some(1), 30, 80)],
+                )],
+            ),
+        ],
+    }];
+    let transform = |remote_parents| {
+        structured_mode_transformation_muted(
+            &trace_data,
+            &everything_structured_mode(),
+            &[],
+            &ScopeFilter::default(),
+            remote_parents,
+            OrphanSpans::Root,
+            false,
+        )
+        .unwrap()
+    };
+
+    let stitched = transform(RemoteParentLinking::Stitch);
+    assert_eq!(stitched.len(), 1);
+    let children = stitched[0].children();
+    let remote: Vec<(&str, bool)> = children
+        .iter()
+        .map(|child| (child.name.as_str(), child.remote_parent))
+        .collect();
+    assert_eq!(remote, vec![("serialize", false), ("handle_request", true)]);
+
+    for linking in [RemoteParentLinking::Root, RemoteParentLinking::Relation] {
+        let spans = transform(linking);
+        let mut names: Vec<(&str, &str)> = spans
+            .iter()
+            .map(|span| (span.name.as_str(), span.node.name.as_str()))
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![("handle_request", "node1"), ("send_request", "node0")]
+        );
+        assert_eq!(spans.iter().filter(|span| span.remote_parent).count(), 1);
+
+        let relations = link_remote_parents(&spans);
+        assert_eq!(relations.len(), 1);
+        let from = relations[0].from_span.upgrade().unwrap();
+        let to = relations[0].to_span.upgrade().unwrap();
+        assert_eq!(
+            (from.name.as_str(), to.name.as_str()),
+            ("send_request", "handle_request")
+        );
+        assert_eq!(to.incoming_relations.borrow().len(), 1);
+        assert_eq!(from.outgoing_relations.borrow().len(), 1);
+    }
+}
+
+#[test]
+fn test_orphan_spans() {
+    let trace_data = vec![ExportTraceServiceRequest {
+        resource_spans: vec![otlp_resource_spans(
+            Some("node0"),
+            vec![otlp_scope_spans(
+                None,
+                vec![
+                    otlp_span("produce_block", 1, None, 0, 100),
+                    // The parent started before the capture
+                    otlp_span("apply_chunk", 2, Some(9), 10, 50),
+                    otlp_span("apply_transactions", 3, Some(2), 20, 40),
+                ],
+            )],
+        )],
+    }];
+    let top_level = |orphans| {
+        let spans = structured_mode_transformation_muted(
+            &trace_data,
+            &everything_structured_mode(),
+            &[],
+            &ScopeFilter::default(),
+            RemoteParentLinking::Stitch,
+            orphans,
+            false,
+        )
+        .unwrap();
+        spans
+            .iter()
+            .map(|span| (span.name.clone(), span.orphan, span.children().len()))
+            .collect::<Vec<_>>()
+    };
+
+    let expected = vec![
+        ("produce_block".to_string(), false, 0),
+        ("apply_chunk".to_string(), true, 1),
+    ];
+    assert_eq!(top_level(OrphanSpans::Root), expected);
+    assert_eq!(top_level(OrphanSpans::Lane), expected);
+    // The children of a hidden orphan are hidden with it.
+    assert_eq!(top_level(OrphanSpans::Hide), expected[..1]);
+    assert_eq!(orphans_lane_name("node0"), "node0 orphans");
+}
+
+/// Display names of the spans in the tree with the given original name.
+fn display_names(spans: &[Rc<Span>], original_name: &str, out: &mut Vec<String>) {
+    for span in spans {
+        if span.original_name == original_name {
+            out.push(span.name.clone());
+        }
+        display_names(&span.children(), original_name, out);
+    }
+}
+
+#[test]
+fn test_rename_rules() {
+    let node = create_test_node("node0");
+    let mut attributes = BTreeMap::new();
+    attributes.insert("shard_id".to_string(), int_attr(3));
+    let span = create_test_span_with_attributes(
+        "process_partial_encoded_chunk_forward",
+        node,
+        1.0,
+        2.0,
+        &[1],
+        attributes,
+    );
+    let rule = |label: &str| RenameRule {
+        selector: SpanSelector::new_equal_name("process_partial_encoded_chunk_forward"),
+        label: label.to_string(),
+    };
+    assert_eq!(rule("PECF").apply(&span), "PECF");
+    assert_eq!(rule("PECF {shard_id}").apply(&span), "PECF 3");
+    assert_eq!(
+        rule("{name} s={shard_id}").apply(&span),
+        "process_partial_encoded_chunk_forward s=3"
+    );
+    // Missing attributes are left out, unclosed braces are kept.
+    assert_eq!(rule("PECF {height}").apply(&span), "PECF");
+    assert_eq!(rule("PECF {shard_id").apply(&span), "PECF {shard_id");
+
+    let trace_data = load_example("small.json");
+    let mut mode = everything_structured_mode();
+    mode.rename_rules = vec![RenameRule {
+        selector: SpanSelector::new_equal_name("process_transaction"),
+        label: "PT".to_string(),
+    }];
+    let spans = structured_mode_transformation(&trace_data, &mode).unwrap();
+    let mut renamed = Vec::new();
+    display_names(&spans, "process_transaction", &mut renamed);
+    assert!(!renamed.is_empty());
+    assert!(
+        renamed.iter().all(|name| name.starts_with("PT")),
+        "{renamed:?}"
+    );
+    // Other spans keep their names.
+    let mut others = Vec::new();
+    display_names(&spans, "verify_and_charge_transaction", &mut others);
+    assert!(!others.is_empty());
+    assert!(others
+        .iter()
+        .all(|name| name.starts_with("verify_and_charge_transaction")));
+}
+
+fn otel_event(name: &str, time_ms: u64) -> opentelemetry_proto::tonic::trace::v1::span::Event {
+    opentelemetry_proto::tonic::trace::v1::span::Event {
+        name: name.to_string(),
+        time_unix_nano: time_ms * 1_000_000,
+        ..Default::default()
+    }
+}
+
+fn int_key_value(key: &str, value: i64) -> KeyValue {
+    KeyValue {
+        key: key.to_string(),
+        value: Some(AnyValue {
+            value: int_attr(value),
+        }),
+    }
+}
+
+#[test]
+fn test_synthetic_spans() {
+    let mut apply = otlp_span("apply_chunk", 1, None, 0, 100);
+    apply.events = vec![
+        otel_event("start_x", 10),
+        otel_event("start_x", 30),
+        otel_event("end_x", 20),
+        otel_event("end_x", 50),
+        // Never ended.
+        otel_event("start_x", 90),
+    ];
+    let mut validate = otlp_span("validate_chunk", 2, None, 200, 300);
+    validate.attributes = vec![
+        int_key_value("io_start", 210_000_000),
+        int_key_value("io_end", 250_000_000),
+    ];
+    let trace_data = vec![ExportTraceServiceRequest {
+        resource_spans: vec![otlp_resource_spans(
+            Some("node0"),
+            vec![otlp_scope_spans(None, vec![apply, validate])],
+        )],
+    }];
+
+    let mut mode = everything_structured_mode();
+    mode.synthetic_span_rules = vec![
+        SyntheticSpanRule {
+            name: "x".to_string(),
+            selector: SpanSelector::new_equal_name("apply_chunk"),
+            source: SyntheticSpanSource::Events {
+                start_event: "start_x".to_string(),
+                end_event: "end_x".to_string(),
+            },
+        },
+        SyntheticSpanRule {
+            name: "io".to_string(),
+            selector: SpanSelector::new_equal_name("validate_chunk"),
+            source: SyntheticSpanSource::Attributes {
+                start_attribute: "io_start".to_string(),
+                end_attribute: "io_end".to_string(),
+            },
+        },
+    ];
+
+    let child_times = |spans: &[Rc<Span>], parent: &str| -> Vec<(String, f64, f64)> {
+        let parent = spans.iter().find(|s| s.original_name == parent).unwrap();
+        parent
+            .children()
+            .iter()
+            .map(|child| {
+                assert_eq!(child.parent_span_id, parent.span_id);
+                (
+                    child.original_name.clone(),
+                    (child.start_time * 1000.0).round(),
+                    (child.end_time * 1000.0).round(),
+                )
+            })
+            .collect()
+    };
+    for lazy in [false, true] {
+        let spans = if lazy {
+            structured_mode_transformation_lazy(&trace_data, &mode).unwrap()
+        } else {
+            structured_mode_transformation(&trace_data, &mode).unwrap()
+        };
+        assert_eq!(
+            child_times(&spans, "apply_chunk"),
+            vec![("x".to_string(), 10.0, 20.0), ("x".to_string(), 30.0, 50.0)]
+        );
+        assert_eq!(
+            child_times(&spans, "validate_chunk"),
+            vec![("io".to_string(), 210.0, 250.0)]
+        );
+    }
+
+    // Without the rules there are no children.
+    let spans = structured_mode_transformation(&trace_data, &everything_structured_mode()).unwrap();
+    assert!(child_times(&spans, "apply_chunk").is_empty());
+}
+
+#[test]
+fn test_lane_layout_lane_name() {
+    let apply_chunk = create_test_span("apply_chunk", create_test_node("node0"), 1.0, 2.0, &[1]);
+    let mut renamed =
+        (*create_test_span("produce_block", create_test_node("node1"), 1.0, 2.0, &[2])).clone();
+    renamed.name = "produce".to_string();
+
+    assert_eq!(LaneLayout::default(), LaneLayout::PerNode);
+    assert_eq!(LaneLayout::PerNode.lane_name(&apply_chunk), "node0");
+    assert_eq!(LaneLayout::PerNode.lane_name(&renamed), "node1");
+    assert_eq!(
+        LaneLayout::PerSpanName.lane_name(&apply_chunk),
+        "apply_chunk"
+    );
+    // Renamed spans stay in the lane of their original name
+    assert_eq!(LaneLayout::PerSpanName.lane_name(&renamed), "produce_block");
+
+    // Modes saved before the lane layout existed have a lane per node
+    let mut mode_json = serde_json::to_value(everything_structured_mode()).unwrap();
+    mode_json.as_object_mut().unwrap().remove("lane_layout");
+    let mode: StructuredMode = serde_json::from_value(mode_json).unwrap();
+    assert_eq!(mode.lane_layout, LaneLayout::PerNode);
+}
+
+#[test]
+fn test_sub_lanes_by_attribute() {
+    let node = create_test_node("node0");
+    let mut attributes = BTreeMap::new();
+    attributes.insert("shard_id".to_string(), int_attr(3));
+    let with_shard =
+        create_test_span_with_attributes("apply_chunk", node.clone(), 1.0, 2.0, &[1], attributes);
+    let without_shard = create_test_span("produce_block", node, 1.0, 2.0, &[2]);
+
+    let mut mode = everything_structured_mode();
+    assert_eq!(mode.lane_name(&with_shard), "node0");
+
+    mode.sub_lane_attribute = "shard_id".to_string();
+    assert_eq!(mode.lane_name(&with_shard), "node0 shard_id=3");
+    assert_eq!(mode.lane_name(&without_shard), "node0");
+
+    mode.lane_layout = LaneLayout::PerSpanName;
+    assert_eq!(mode.lane_name(&with_shard), "apply_chunk shard_id=3");
+}
+
+#[test]
+fn test_preview_span_rules() {
+    let node = create_test_node("node0");
+    let spans: Vec<Rc<Span>> = [
+        "verify_chunk_endorsement",
+        "apply_chunk",
+        "apply_chunk",
+        "other",
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, name)| create_test_span(name, node.clone(), 1.0, 2.0, &[i as u8]))
+    .collect();
+
+    let mut rules = everything_structured_mode().span_rules;
+    // Spans are counted only for the first matching rule
+    let preview = preview_span_rules(&rules, &spans, 2);
+    assert_eq!(preview.rules.len(), 2);
+    assert_eq!(preview.rules[0].matched_count, 1);
+    assert_eq!(preview.rules[1].matched_count, 3);
+    assert_eq!(preview.rules[1].samples.len(), 2);
+    assert_eq!(preview.rules[1].samples[0].original_name, "apply_chunk");
+    assert_eq!(preview.unmatched_count, 0);
+
+    rules[1].selector = SpanSelector::new_equal_name("apply_chunk");
+    let preview = preview_span_rules(&rules, &spans, 2);
+    assert_eq!(preview.rules[1].matched_count, 2);
+    assert_eq!(preview.unmatched_count, 1);
+}
+
+/// Ids of the spans on the first path from `span` down to a leaf, transforming lazy children.
+fn first_leaf_path(span: &Rc<Span>, out: &mut Vec<SpanId>) {
+    out.push(span.span_id);
+    if let Some(child) = span.children().first() {
+        first_leaf_path(child, out);
+    }
+}
+
+/// True if all spans in the tree have collapsed children, without transforming lazy children.
+fn all_collapsed(spans: &[Rc<Span>]) -> bool {
+    spans
+        .iter()
+        .all(|s| s.collapse_children.get() && all_collapsed(&s.transformed_children()))
+}
+
+#[test]
+fn test_collapse_all_and_breadcrumb_on_lazy_trace() {
+    let trace_data = load_example("small.json");
+    let mut any_lazy = false;
+    for mode in builtin_structured_modes() {
+        let lazy = structured_mode_transformation_lazy(&trace_data, &mode).unwrap();
+        let Some(root) = lazy
+            .iter()
+            .find(|span| count_lazy(std::slice::from_ref(*span)) > 0)
+        else {
+            continue;
+        };
+        any_lazy = true;
+
+        // Collapse all reaches the spans under lazy subtrees once they're transformed, without
+        // transforming them.
+        let lazy_count = count_lazy(&lazy);
+        set_collapse_all(&lazy, true);
+        assert_eq!(count_lazy(&lazy), lazy_count, "mode {}", mode.name);
+        assert!(all_collapsed(&lazy), "mode {}", mode.name);
+        let mut tree = Vec::new();
+        describe_tree(&lazy, &mut tree, 0);
+        assert_eq!(count_lazy(&lazy), 0, "mode {}", mode.name);
+        assert!(all_collapsed(&lazy), "mode {}", mode.name);
+
+        // The breadcrumb finds the spans under lazy subtrees.
+        let lazy = structured_mode_transformation_lazy(&trace_data, &mode).unwrap();
+        let root = lazy
+            .iter()
+            .find(|span| span.span_id == root.span_id)
+            .unwrap();
+        let mut expected = Vec::new();
+        first_leaf_path(
+            &structured_mode_transformation(&trace_data, &mode)
+                .unwrap()
+                .into_iter()
+                .find(|span| span.span_id == root.span_id)
+                .unwrap(),
+            &mut expected,
+        );
+        assert!(count_lazy(std::slice::from_ref(root)) > 0);
+        let chain = ancestor_chain(root, *expected.last().unwrap()).unwrap();
+        let chain: Vec<SpanId> = chain.iter().map(|span| span.span_id).collect();
+        assert_eq!(chain, expected, "mode {}", mode.name);
+    }
+    assert!(any_lazy, "no mode has lazy children");
+}
+
+#[test]
+fn test_trace_span_ids() {
+    let mut root = otlp_span("root", 0xe1, None, 0, 100);
+    root.trace_id = vec![0xe0; 16];
+    let mut child = otlp_span("child", 0xe2, Some(0xe1), 10, 20);
+    child.trace_id = vec![0xe0; 16];
+    let trace_data = vec![ExportTraceServiceRequest {
+        resource_spans: vec![otlp_resource_spans(
+            Some("node0"),
+            vec![otlp_scope_spans(None, vec![root, child])],
+        )],
+    }];
+    let not_loaded = vec![ExportTraceServiceRequest {
+        resource_spans: vec![otlp_resource_spans(
+            Some("node0"),
+            vec![otlp_scope_spans(
+                None,
+                vec![otlp_span("other", 0xe3, Some(0xe1), 0, 10)],
+            )],
+        )],
+    }];
+    extract_span_data(&trace_data).unwrap();
+
+    let expected = [vec![0xe0; 16], vec![0xe1; 8], vec![0xe2; 8]]
+        .iter()
+        .map(|id| SpanId::find(id).unwrap())
+        .collect();
+    assert_eq!(trace_span_ids(&trace_data), expected);
+    // The id of the span which was never extracted isn't interned, only the shared parent is.
+    assert_eq!(
+        trace_span_ids(&not_loaded),
+        [SpanId::find(&[0xe1; 8]).unwrap()].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_scope_filter_hides_child_spans() {
+    let trace_data = vec![ExportTraceServiceRequest {
+        resource_spans: vec![otlp_resource_spans(
+            Some("node0"),
+            vec![
+                otlp_scope_spans(
+                    Some("neard"),
+                    vec![otlp_span("produce_block", 0xd1, None, 0, 100)],
+                ),
+                otlp_scope_spans(
+                    Some("runtime"),
+                    vec![otlp_span("wasm_call", 0xd2, Some(0xd1), 10, 20)],
+                ),
+            ],
+        )],
+    }];
+
+    let mut scope_filter = ScopeFilter::default();
+    scope_filter.hidden_scopes.insert("runtime".to_string());
+    for lazy in [false, true] {
+        let spans = structured_mode_transformation_muted(
+            &trace_data,
+            &everything_structured_mode(),
+            &[],
+            &scope_filter,
+            RemoteParentLinking::Stitch,
+            OrphanSpans::Root,
+            lazy,
+        )
+        .unwrap();
+        let mut names = Vec::new();
+        tree_span_names(&spans, &mut names);
+        assert_eq!(names, vec!["produce_block"], "lazy: {lazy}");
+    }
+}
@@ -0,0 +1,101 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+
+use test_helpers::{create_test_node, create_test_span, string_attr};
+use traviz::node_filter::{NodeFilter, NodeSection, SpanContentCache, OTHER_SECTION_NAME};
+use traviz::span_index::SpanIntervalIndex;
+use traviz::structured_modes::{MatchCondition, MatchOperator, SpanSelector};
+use traviz::types::{set_min_max_time, Node};
+
+fn node(name: &str, role: Option<&str>) -> Node {
+    let mut attributes = BTreeMap::new();
+    if let Some(role) = role {
+        attributes.insert("role".to_string(), string_attr(role));
+    }
+    Node {
+        name: name.to_string(),
+        attributes,
+    }
+}
+
+fn filter_with_sections() -> NodeFilter {
+    let mut filter = NodeFilter::show_all();
+    filter.sections = vec![
+        NodeSection {
+            name: "Validators".to_string(),
+            node_name_condition: MatchCondition::any(),
+            attribute_conditions: vec![("role".to_string(), MatchCondition::equal_to("validator"))],
+        },
+        NodeSection {
+            name: "RPC".to_string(),
+            node_name_condition: MatchCondition {
+                operator: MatchOperator::Contains,
+                value: "rpc".to_string(),
+            },
+            attribute_conditions: vec![],
+        },
+    ];
+    filter
+}
+
+#[test]
+fn test_node_sections() {
+    let filter = filter_with_sections();
+
+    assert_eq!(filter.section_index(&node("node0", Some("validator"))), 0);
+    // The first matching section wins
+    assert_eq!(filter.section_index(&node("rpc0", Some("validator"))), 0);
+    assert_eq!(filter.section_index(&node("rpc1", Some("archival"))), 1);
+    assert_eq!(filter.section_index(&node("node1", None)), 2);
+
+    assert_eq!(filter.section_name(0), "Validators");
+    assert_eq!(filter.section_name(1), "RPC");
+    assert_eq!(filter.section_name(2), OTHER_SECTION_NAME);
+}
+
+#[test]
+fn test_filters_without_sections_can_be_loaded() {
+    // Filters saved before sections existed don't have the field
+    let mut filter_json = serde_json::to_value(filter_with_sections()).unwrap();
+    filter_json.as_object_mut().unwrap().remove("sections");
+    let filter: NodeFilter = serde_json::from_value(filter_json).unwrap();
+    assert!(filter.sections.is_empty());
+    assert_eq!(filter.section_index(&node("node0", Some("validator"))), 0);
+    assert_eq!(filter.section_name(0), OTHER_SECTION_NAME);
+}
+
+#[test]
+fn test_filter_by_span_content() {
+    let node = create_test_node("node0");
+    let root = create_test_span("process_block", node.clone(), 1.0, 10.0, &[1]);
+    let child = create_test_span("apply_chunk", node.clone(), 6.0, 7.0, &[2]);
+    root.children_mut().push(child);
+    let spans = vec![
+        root,
+        create_test_span("apply_chunk", node, 12.0, 13.0, &[3]),
+    ];
+    set_min_max_time(&spans);
+
+    let mut filter = NodeFilter::show_all();
+    assert!(filter.has_matching_spans(&spans, 0.0, 1.0));
+
+    filter.span_content_selector = Some(SpanSelector::new_equal_name("apply_chunk"));
+    // The child matches
+    assert!(filter.has_matching_spans(&spans, 5.0, 6.5));
+    // Only the parent is in the range
+    assert!(!filter.has_matching_spans(&spans, 2.0, 5.0));
+    assert!(filter.has_matching_spans(&spans, 11.0, 12.0));
+    assert!(!filter.has_matching_spans(&spans, 14.0, 15.0));
+    assert!(!filter.has_matching_spans(&[], 0.0, 20.0));
+
+    let lane = SpanIntervalIndex::new(spans);
+    let mut cache = SpanContentCache::default();
+    assert!(cache.has_matching_spans(&filter, &lane, 5.0, 6.5));
+    assert!(!cache.has_matching_spans(&filter, &lane, 2.0, 5.0));
+    // A change of the selector is noticed without moving the range
+    filter.span_content_selector = Some(SpanSelector::new_equal_name("process_block"));
+    assert!(cache.has_matching_spans(&filter, &lane, 2.0, 5.0));
+    filter.span_content_selector = None;
+    assert!(cache.has_matching_spans(&filter, &lane, 14.0, 15.0));
+}
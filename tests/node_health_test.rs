@@ -0,0 +1,65 @@
+mod test_helpers;
+
+use approx::assert_relative_eq;
+use test_helpers::*;
+use traviz::analyze_utils::Statistics;
+use traviz::node_health::{compute_node_health, parse_span_names};
+
+#[test]
+fn test_slow_node_is_ranked_first_and_flagged() {
+    let mut spans = Vec::new();
+    for (node_name, duration) in [("node0", 0.010), ("node1", 0.011), ("slow", 0.030)] {
+        let node = create_test_node(node_name);
+        for i in 0..10 {
+            let start = i as f64;
+            spans.push(create_test_span(
+                "apply_new_chunk",
+                node.clone(),
+                start,
+                start + duration,
+                format!("{node_name}-{i}").as_bytes(),
+            ));
+        }
+        // Not a key span, ignored
+        spans.push(create_test_span(
+            "other",
+            node.clone(),
+            0.0,
+            100.0,
+            format!("{node_name}-other").as_bytes(),
+        ));
+    }
+
+    let report = compute_node_health(&spans, &parse_span_names("apply_new_chunk"), 20.0);
+
+    assert_eq!(report.nodes.len(), 3);
+    assert_eq!(report.nodes[0].node_name, "slow");
+    assert_eq!(report.nodes[0].flagged_count(20.0), 1);
+    let slow = &report.nodes[0].spans[0];
+    assert_eq!(slow.count, 10);
+    assert_relative_eq!(
+        slow.mean_deviation_percent,
+        (0.030 - 0.011) / 0.011 * 100.0,
+        epsilon = 1e-6
+    );
+    assert_eq!(slow.p99_span.node.name, "slow");
+
+    let (median_mean, _) = report.medians["apply_new_chunk"];
+    assert_relative_eq!(median_mean, 0.011, epsilon = 1e-9);
+    // node1 is the median, node0 is within 20% of it
+    assert_eq!(report.nodes[1].flagged_count(20.0), 0);
+    assert_eq!(report.nodes[2].flagged_count(20.0), 0);
+}
+
+#[test]
+fn test_percentile() {
+    let mut stats = Statistics::new();
+    for value in 1..=100 {
+        stats.add_value(value as f64);
+    }
+    assert_eq!(stats.percentile(99.0), 99.0);
+    assert_eq!(stats.percentile(50.0), 50.0);
+    assert_eq!(stats.percentile(100.0), 100.0);
+    assert_eq!(stats.percentile(0.0), 1.0);
+    assert_eq!(Statistics::new().percentile(99.0), 0.0);
+}
@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use regex::Regex;
+use traviz::node_logs::{guess_node_name, parse_node_log, NodeLogs, DEFAULT_TIMESTAMP_REGEX};
+
+#[test]
+fn test_parse_neard_log() {
+    let log =
+        "\x1b[2m2025-01-01T00:00:01.500000Z\x1b[0m \x1b[32m INFO\x1b[0m stats: #100 Validator\n\
+               2025-01-01T00:00:00Z  WARN chain: block is late\n\
+               thread 'main' panicked at src/main.rs:1\n\
+               \n\
+               2025-01-01 00:00:02,250 INFO client: done\n";
+    let regex = Regex::new(DEFAULT_TIMESTAMP_REGEX).unwrap();
+    let lines = parse_node_log(log, "node0", &regex).unwrap();
+    let times: Vec<f64> = lines.iter().map(|line| line.time).collect();
+    assert_eq!(times, vec![1735689600.0, 1735689601.5, 1735689602.25]);
+    assert_eq!(
+        lines[0].text,
+        "2025-01-01T00:00:00Z  WARN chain: block is late\nthread 'main' panicked at src/main.rs:1"
+    );
+    assert_eq!(
+        lines[1].text,
+        "2025-01-01T00:00:01.500000Z  INFO stats: #100 Validator"
+    );
+    assert!(lines.iter().all(|line| line.node == "node0"));
+}
+
+#[test]
+fn test_parse_node_log_custom_regex() {
+    let log = "level=info ts=1735689600.5 msg=started\nlevel=info ts=1735689601000 msg=ms\n";
+    let regex = Regex::new(r"ts=(?<time>\S+)").unwrap();
+    let lines = parse_node_log(log, "node1", &regex).unwrap();
+    let times: Vec<f64> = lines.iter().map(|line| line.time).collect();
+    assert_eq!(times, vec![1735689600.5, 1735689601.0]);
+
+    assert!(parse_node_log("no timestamps here\n", "node1", &regex).is_err());
+}
+
+#[test]
+fn test_guess_node_name() {
+    let nodes = vec!["neard:node0".to_string(), "neard:node1".to_string()];
+    assert_eq!(
+        guess_node_name(Path::new("/logs/node1.log"), &nodes),
+        "neard:node1"
+    );
+    assert_eq!(
+        guess_node_name(Path::new("/logs/neard:node0.txt"), &nodes),
+        "neard:node0"
+    );
+    assert_eq!(guess_node_name(Path::new("/logs/rpc.log"), &nodes), "rpc");
+}
+
+#[test]
+fn test_node_logs_queries() {
+    let regex = Regex::new(DEFAULT_TIMESTAMP_REGEX).unwrap();
+    let mut logs = NodeLogs::new();
+    logs.add(
+        parse_node_log(
+            "2025-01-01T00:00:00Z a\n2025-01-01T00:00:02Z b ERROR\n2025-01-01T00:00:04Z c\n",
+            "node0",
+            &regex,
+        )
+        .unwrap(),
+    );
+    logs.add(parse_node_log("2025-01-01T00:00:01Z d error\n", "node1", &regex).unwrap());
+    assert_eq!(logs.len(), 4);
+
+    let start = 1735689600.0;
+    let in_range = logs.lines_in_range("node0", start + 1.0, start + 4.0);
+    assert_eq!(in_range.len(), 2);
+    assert!(logs.lines_in_range("node2", start, start + 4.0).is_empty());
+
+    let found: Vec<&str> = logs
+        .search(None, "error")
+        .iter()
+        .map(|line| line.text.as_str())
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            "2025-01-01T00:00:01Z d error",
+            "2025-01-01T00:00:02Z b ERROR"
+        ]
+    );
+    assert_eq!(logs.search(Some("node1"), "").len(), 1);
+
+    // The positions are sorted by time across the nodes.
+    assert_eq!(logs.search_indices(None, "error"), vec![(1, 0), (0, 1)]);
+    assert_eq!(logs.line((1, 0)).node, "node1");
+
+    let generation = logs.generation();
+    logs.clear();
+    assert_ne!(logs.generation(), generation);
+    assert!(logs.search_indices(None, "").is_empty());
+}
@@ -0,0 +1,48 @@
+use traviz::notifications::{NotificationLevel, Notifications};
+
+#[test]
+fn test_unread_warnings_and_errors() {
+    let mut notifications = Notifications::new();
+    notifications.info("Loaded trace.json");
+    assert_eq!(notifications.unread_count(), 0);
+
+    notifications.warn("No relation view found at index 3");
+    notifications.error("Failed to save persistent data: permission denied");
+    assert_eq!(notifications.unread_count(), 2);
+    assert_eq!(notifications.notifications().len(), 3);
+    assert_eq!(
+        notifications.notifications()[2].level,
+        NotificationLevel::Error
+    );
+
+    notifications.open_log();
+    assert_eq!(notifications.unread_count(), 0);
+    assert_eq!(notifications.notifications().len(), 3);
+
+    notifications.clear();
+    assert!(notifications.notifications().is_empty());
+}
+
+#[test]
+fn test_diagnostics_text() {
+    let mut notifications = Notifications::new();
+    notifications.info("first");
+    notifications.error("second");
+    let text = notifications.diagnostics_text();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with("[INFO] first"), "{}", lines[0]);
+    assert!(lines[1].ends_with("[ERROR] second"), "{}", lines[1]);
+    // Starts with a timestamp
+    assert!(lines[0].chars().next().unwrap().is_ascii_digit());
+}
+
+#[test]
+fn test_old_notifications_are_dropped() {
+    let mut notifications = Notifications::new();
+    for i in 0..1500 {
+        notifications.info(format!("message {i}"));
+    }
+    assert_eq!(notifications.notifications().len(), 1000);
+    assert_eq!(notifications.notifications()[0].message, "message 500");
+}
@@ -0,0 +1,91 @@
+mod test_helpers;
+
+use std::rc::Rc;
+
+use test_helpers::{create_test_node, create_test_span};
+use traviz::outliers::{find_outliers, OutlierThreshold, MIN_INSTANCES};
+use traviz::types::Span;
+
+/// `count` spans with the name and durations cycling through `durations`.
+fn spans_with_durations(
+    name: &str,
+    durations: &[f64],
+    count: usize,
+    first_id: u8,
+) -> Vec<Rc<Span>> {
+    let node = create_test_node("node0");
+    (0..count)
+        .map(|i| {
+            let start = i as f64;
+            let duration = durations[i % durations.len()];
+            create_test_span(
+                name,
+                node.clone(),
+                start,
+                start + duration,
+                &[first_id, i as u8],
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_outliers_mad() {
+    let mut spans = spans_with_durations("apply_chunk", &[0.10, 0.11, 0.12], 30, 1);
+    let node = create_test_node("node1");
+    let slow = create_test_span("apply_chunk", node.clone(), 100.0, 102.0, &[9, 1]);
+    let slower = create_test_span("apply_chunk", node, 200.0, 205.0, &[9, 2]);
+    spans.push(slow.clone());
+    spans.push(slower.clone());
+
+    let outliers = find_outliers(&spans, None, OutlierThreshold::Mad { k: 10.0 });
+    assert_eq!(outliers.len(), 2);
+    // The worst one first
+    assert!(Rc::ptr_eq(&outliers[0].span, &slower));
+    assert!(Rc::ptr_eq(&outliers[1].span, &slow));
+    assert!((outliers[0].median - 0.11).abs() < 1e-9);
+    assert!((outliers[0].times_median() - 5.0 / 0.11).abs() < 1e-6);
+
+    // Filtering by name
+    assert!(find_outliers(&spans, Some("other"), OutlierThreshold::Mad { k: 10.0 }).is_empty());
+    assert_eq!(
+        find_outliers(
+            &spans,
+            Some("apply_chunk"),
+            OutlierThreshold::Mad { k: 10.0 }
+        )
+        .len(),
+        2
+    );
+}
+
+#[test]
+fn test_outliers_percentile() {
+    let durations: Vec<f64> = (1..=100).map(|i| i as f64 / 100.0).collect();
+    let spans = spans_with_durations("produce_block", &durations, 100, 1);
+    let outliers = find_outliers(&spans, None, OutlierThreshold::Percentile(95.0));
+    assert_eq!(outliers.len(), 5);
+    assert!(outliers.iter().all(|o| o.duration > 0.95 - 1e-9));
+}
+
+#[test]
+fn test_outliers_few_instances() {
+    let spans = spans_with_durations("rare", &[0.1, 0.1, 10.0], MIN_INSTANCES - 1, 1);
+    assert!(find_outliers(&spans, None, OutlierThreshold::Mad { k: 1.0 }).is_empty());
+}
+
+/// When most spans have the same duration the MAD is 0, a slightly longer span isn't an outlier.
+#[test]
+fn test_outliers_zero_mad() {
+    let mut spans = spans_with_durations("apply_chunk", &[0.1], 30, 1);
+    let node = create_test_node("node1");
+    let jitter = create_test_span("apply_chunk", node.clone(), 100.0, 100.1001, &[9, 1]);
+    let slow = create_test_span("apply_chunk", node, 200.0, 202.0, &[9, 2]);
+    spans.push(jitter);
+    spans.push(slow.clone());
+
+    let outliers = find_outliers(&spans, None, OutlierThreshold::Mad { k: 10.0 });
+    assert_eq!(outliers.len(), 1);
+    assert!(Rc::ptr_eq(&outliers[0].span, &slow));
+    assert!((outliers[0].threshold - 0.11).abs() < 1e-9);
+}
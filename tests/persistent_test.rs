@@ -0,0 +1,397 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+use traviz::analyze_dependency::{
+    AnalysisCardinality, ContextLinking, DependencyPreset, GroupAggregationStrategy, SourceScope,
+    SourceTimingStrategy,
+};
+use traviz::builtin_relations::builtin_relations;
+use traviz::colors::{ColorOverrides, Palette, SemanticColor, ThemeKind};
+use traviz::concurrency::{ConcurrencyOverlaySettings, OverlayPosition};
+use traviz::macros::{KeyMacro, MacroAction};
+use traviz::modes::{OrphanSpans, RemoteParentLinking};
+use traviz::node_filter::builtin_filters;
+use traviz::persistent::{PersistentData, PersistentDataLatest, CURRENT_VERSION};
+use traviz::preferences::{InitialZoom, Preferences};
+use traviz::profiles::{Profile, DEFAULT_PROFILE_NAME};
+use traviz::recent_files::RecentFile;
+use traviz::relation::builtin_relation_views;
+use traviz::relation_arrows::ArrowDeclutterSettings;
+use traviz::remote_source::{RemoteBackend, RemoteSourceSettings};
+use traviz::sticky_header::StickyLaneHeader;
+use traviz::structured_modes::{builtin_structured_modes, SpanSelector};
+use traviz::time_markers::{default_time_marker_rules, TimeMarkerRule};
+use traviz::types::{TimeDisplaySettings, TimeZoneSetting};
+use traviz::utilization::UtilizationLaneSettings;
+
+/// Saved user data, the fields of every version are taken from here.
+struct TestData {
+    display_modes: Value,
+    node_filters: Value,
+    relations_v0: Value,
+    relations: Value,
+    relation_views: Value,
+    preferences: Value,
+    macros: Value,
+    recent_files: Value,
+    time_marker_rules: Value,
+    dependency_presets: Value,
+    clock_offsets: Value,
+}
+
+fn custom<T: serde::Serialize>(items: Vec<T>, name: &str) -> Value {
+    let mut value = serde_json::to_value(items).unwrap();
+    for item in value.as_array_mut().unwrap() {
+        item["name"] = json!(name);
+        item["is_builtin"] = json!(false);
+    }
+    value
+}
+
+fn test_data() -> TestData {
+    let relations = custom(
+        builtin_relations().into_iter().take(1).collect(),
+        "My relation",
+    );
+    let relation = &relations[0];
+    let relations_v0 = json!([{
+        "id": relation["id"],
+        "name": "My relation",
+        "from_span_name": "produce_block",
+        "to_span_name": "apply_block",
+        "attribute_relations": [],
+        "max_time_diff": 1.5,
+        "nodes_config": relation["nodes_config"],
+        "match_type": relation["match_type"],
+        "is_builtin": false,
+    }]);
+    TestData {
+        display_modes: custom(
+            builtin_structured_modes().into_iter().take(1).collect(),
+            "My mode",
+        ),
+        node_filters: custom(builtin_filters().into_iter().take(1).collect(), "My filter"),
+        relations_v0,
+        relations,
+        relation_views: custom(
+            builtin_relation_views().into_iter().take(1).collect(),
+            "My view",
+        ),
+        preferences: serde_json::to_value(Preferences {
+            initial_zoom: InitialZoom::FullTrace,
+            lazy_mode_transformation: !Preferences::default().lazy_mode_transformation,
+            show_trace_info_on_load: false,
+            utilization_lane: UtilizationLaneSettings {
+                enabled: true,
+                ..Default::default()
+            },
+            concurrency_overlay: ConcurrencyOverlaySettings {
+                position: OverlayPosition::AboveLane,
+                ..Default::default()
+            },
+            relation_arrows: ArrowDeclutterSettings {
+                bundle: true,
+                ..Default::default()
+            },
+            theme: ThemeKind::Light,
+            palette: Palette::Deuteranopia,
+            color_overrides: ColorOverrides::from([(SemanticColor::Highlight, [1, 2, 3])]),
+            time_display: TimeDisplaySettings {
+                time_zone: TimeZoneSetting::FixedOffset { minutes: -300 },
+                tick_precision: 6,
+            },
+            editor_command: "vim +{line} {file}".to_string(),
+            node_log_timestamp_regex: r"^\[(?<time>[^\]]+)\]".to_string(),
+            remote_source: RemoteSourceSettings {
+                backend: RemoteBackend::Jaeger,
+                url: "http://jaeger:16686".to_string(),
+                service: "neard".to_string(),
+                limit: 5,
+            },
+            cache_downloaded_files: false,
+            ui_scale: 1.25,
+            muted_spans: vec![SpanSelector::new_equal_name("handle_network_message")],
+            remote_parents: RemoteParentLinking::Relation,
+            orphan_spans: OrphanSpans::Lane,
+            sticky_lane_header: StickyLaneHeader::NodeNameAndTopLevelSpans,
+        })
+        .unwrap(),
+        macros: serde_json::to_value(vec![KeyMacro {
+            name: "My macro".to_string(),
+            key: Some("F1".to_string()),
+            actions: vec![MacroAction::SwitchDisplayMode("My mode".to_string())],
+        }])
+        .unwrap(),
+        recent_files: serde_json::to_value(vec![RecentFile {
+            path: PathBuf::from("/tmp/trace.json"),
+            file_size: 1234,
+            trace_start: 1.0,
+            trace_end: 2.0,
+        }])
+        .unwrap(),
+        time_marker_rules: serde_json::to_value(vec![TimeMarkerRule {
+            label: "My marker".to_string(),
+            selector: SpanSelector::new_equal_name("apply_block"),
+            color: [1, 2, 3],
+            show_node_name: false,
+            enabled: true,
+        }])
+        .unwrap(),
+        dependency_presets: serde_json::to_value(vec![DependencyPreset {
+            name: "My preset".to_string(),
+            source_span_name: "produce_block".to_string(),
+            target_span_name: "apply_block".to_string(),
+            threshold: 2,
+            linking_attribute: "height".to_string(),
+            context_linking: ContextLinking::SameTrace,
+            group_by_attribute: String::new(),
+            source_scope: SourceScope::AllNodes,
+            source_timing_strategy: SourceTimingStrategy::LatestFirst,
+            group_aggregation_strategy: GroupAggregationStrategy::WaitForLastGroup,
+            analysis_cardinality: AnalysisCardinality::OneToN,
+        }])
+        .unwrap(),
+        clock_offsets: serde_json::to_value(BTreeMap::from([(
+            PathBuf::from("/tmp/trace.json"),
+            BTreeMap::from([("node1".to_string(), 40.0)]),
+        )]))
+        .unwrap(),
+    }
+}
+
+/// JSON of the data saved in `version`, with the fields that existed in that version.
+fn saved_json(version: u32, data: &TestData) -> Value {
+    if version >= 8 {
+        return json!({ format!("V{version}"): {
+            "profiles": [{
+                "name": "Default",
+                "display_modes": data.display_modes,
+                "node_filters": data.node_filters,
+                "relations": data.relations,
+                "relation_views": data.relation_views,
+                "time_marker_rules": data.time_marker_rules,
+            }],
+            "current_profile": "Default",
+            "preferences": data.preferences,
+            "macros": data.macros,
+            "recent_files": data.recent_files,
+            "dependency_presets": data.dependency_presets,
+            "clock_offsets": data.clock_offsets,
+        }});
+    }
+    let mut fields = json!({
+        "display_modes": data.display_modes,
+        "node_filters": data.node_filters,
+    });
+    if version >= 2 {
+        fields["relations"] = if version == 2 {
+            data.relations_v0.clone()
+        } else {
+            data.relations.clone()
+        };
+        fields["relation_views"] = data.relation_views.clone();
+    }
+    if version >= 4 {
+        fields["preferences"] = data.preferences.clone();
+    }
+    if version >= 5 {
+        fields["macros"] = data.macros.clone();
+    }
+    if version >= 6 {
+        fields["recent_files"] = data.recent_files.clone();
+    }
+    if version >= 7 {
+        fields["time_marker_rules"] = data.time_marker_rules.clone();
+    }
+    json!({ format!("V{version}"): fields })
+}
+
+fn names(items: &Value) -> Vec<String> {
+    items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+fn check_migrated(version: u32, data: &TestData, migrated: &PersistentDataLatest) {
+    let migrated = serde_json::to_value(migrated).unwrap();
+    // Data saved before profiles existed goes to the default profile.
+    assert_eq!(names(&migrated["profiles"]), vec![DEFAULT_PROFILE_NAME]);
+    assert_eq!(migrated["current_profile"], json!(DEFAULT_PROFILE_NAME));
+    let profile = &migrated["profiles"][0];
+    assert_eq!(profile["display_modes"], data.display_modes);
+    assert_eq!(profile["node_filters"], data.node_filters);
+    match version {
+        1 => {
+            assert_eq!(profile["relations"], json!([]));
+            assert_eq!(profile["relation_views"], json!([]));
+        }
+        2 => {
+            let relation = &profile["relations"][0];
+            assert_eq!(names(&profile["relations"]), vec!["My relation"]);
+            assert_eq!(relation["id"], data.relations_v0[0]["id"]);
+            assert_eq!(relation["max_time_diff"], json!(1.5));
+            assert_eq!(
+                relation["from_span_selector"],
+                serde_json::to_value(SpanSelector::new_equal_name("produce_block")).unwrap()
+            );
+            assert_eq!(profile["relation_views"], data.relation_views);
+        }
+        _ => {
+            assert_eq!(profile["relations"], data.relations);
+            assert_eq!(profile["relation_views"], data.relation_views);
+        }
+    }
+    let expected_preferences = if version >= 4 {
+        data.preferences.clone()
+    } else {
+        serde_json::to_value(Preferences::default()).unwrap()
+    };
+    assert_eq!(migrated["preferences"], expected_preferences);
+    let expected_macros = if version >= 5 {
+        data.macros.clone()
+    } else {
+        json!([])
+    };
+    assert_eq!(migrated["macros"], expected_macros);
+    let expected_recent_files = if version >= 6 {
+        data.recent_files.clone()
+    } else {
+        json!([])
+    };
+    assert_eq!(migrated["recent_files"], expected_recent_files);
+    let expected_dependency_presets = if version >= 8 {
+        data.dependency_presets.clone()
+    } else {
+        json!([])
+    };
+    assert_eq!(migrated["dependency_presets"], expected_dependency_presets);
+    let expected_clock_offsets = if version >= 8 {
+        data.clock_offsets.clone()
+    } else {
+        json!({})
+    };
+    assert_eq!(migrated["clock_offsets"], expected_clock_offsets);
+    let expected_time_marker_rules = if version >= 7 {
+        data.time_marker_rules.clone()
+    } else {
+        serde_json::to_value(default_time_marker_rules()).unwrap()
+    };
+    assert_eq!(profile["time_marker_rules"], expected_time_marker_rules);
+}
+
+#[test]
+fn test_load_and_migrate_every_version() {
+    let data = test_data();
+    for version in 1..=CURRENT_VERSION {
+        let json = saved_json(version, &data).to_string();
+        let parsed = PersistentData::from_json(&json).unwrap();
+        assert_eq!(parsed.version(), version);
+        check_migrated(version, &data, &parsed.migrate());
+    }
+}
+
+#[test]
+fn test_round_trip_every_version() {
+    let data = test_data();
+    for version in 1..=CURRENT_VERSION {
+        let saved = saved_json(version, &data);
+        let parsed = PersistentData::from_json(&saved.to_string()).unwrap();
+        let written: Value = serde_json::from_str(&parsed.to_json().unwrap()).unwrap();
+        assert_eq!(written, saved, "version {version}");
+    }
+}
+
+#[test]
+fn test_migrated_data_round_trips_in_current_version() {
+    let data = test_data();
+    for version in 1..=CURRENT_VERSION {
+        let migrated = PersistentData::from_json(&saved_json(version, &data).to_string())
+            .unwrap()
+            .migrate();
+        let json = PersistentData::V8(migrated).to_json().unwrap();
+        let reparsed = PersistentData::from_json(&json).unwrap();
+        assert_eq!(reparsed.version(), CURRENT_VERSION);
+        check_migrated(version, &data, &reparsed.migrate());
+    }
+}
+
+#[test]
+fn test_newer_version_is_rejected() {
+    let json = json!({ format!("V{}", CURRENT_VERSION + 1): {} }).to_string();
+    let err = PersistentData::from_json(&json).unwrap_err();
+    assert!(err.to_string().contains("newer version of traviz"), "{err}");
+}
+
+#[test]
+fn test_data_without_version_is_rejected() {
+    let err = PersistentData::from_json(r#"{"display_modes": []}"#).unwrap_err();
+    assert!(err.to_string().contains("no version"), "{err}");
+}
+
+#[test]
+fn test_missing_fields_of_the_current_version_fail() {
+    // A field missing from the latest version must be an error, not silently dropped data.
+    let json = json!({ format!("V{CURRENT_VERSION}"): { "display_modes": [] } }).to_string();
+    assert!(PersistentData::from_json(&json).is_err());
+}
+
+#[test]
+fn test_data_saved_before_dependency_presets_loads() {
+    let data = test_data();
+    let mut saved = saved_json(CURRENT_VERSION, &data);
+    saved[format!("V{CURRENT_VERSION}")]
+        .as_object_mut()
+        .unwrap()
+        .remove("dependency_presets");
+    let parsed = PersistentData::from_json(&saved.to_string())
+        .unwrap()
+        .migrate();
+    assert!(parsed.dependency_presets.is_empty());
+}
+
+#[test]
+fn test_data_saved_before_clock_offsets_loads() {
+    let data = test_data();
+    let mut saved = saved_json(CURRENT_VERSION, &data);
+    saved[format!("V{CURRENT_VERSION}")]
+        .as_object_mut()
+        .unwrap()
+        .remove("clock_offsets");
+    let parsed = PersistentData::from_json(&saved.to_string())
+        .unwrap()
+        .migrate();
+    assert!(parsed.clock_offsets.is_empty());
+}
+
+#[test]
+fn test_profiles() {
+    let mut data = PersistentData::default().migrate();
+    assert_eq!(data.profile_names(), vec![DEFAULT_PROFILE_NAME]);
+
+    let mut forknet = Profile::new("forknet");
+    forknet.time_marker_rules.clear();
+    data.set_profile(forknet.clone());
+    assert_eq!(data.profile_names(), vec![DEFAULT_PROFILE_NAME, "forknet"]);
+    assert_eq!(data.current_profile, "forknet");
+
+    // Saving an existing profile replaces it.
+    forknet.display_modes = builtin_structured_modes();
+    data.set_profile(forknet);
+    assert_eq!(data.profile_names(), vec![DEFAULT_PROFILE_NAME, "forknet"]);
+    assert!(!data.get_profile("forknet").display_modes.is_empty());
+
+    assert!(!data
+        .get_profile(DEFAULT_PROFILE_NAME)
+        .time_marker_rules
+        .is_empty());
+    // Unknown (or empty on startup) names fall back to the current profile.
+    assert_eq!(data.get_profile("").name, "forknet");
+    assert_eq!(data.get_profile("deleted").name, "forknet");
+
+    data.profiles.clear();
+    assert_eq!(data.get_profile("").name, DEFAULT_PROFILE_NAME);
+}
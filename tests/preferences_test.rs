@@ -0,0 +1,74 @@
+use traviz::colors::ThemeKind;
+use traviz::preferences::{InitialWindow, InitialZoom, Preferences};
+
+#[test]
+fn test_initial_window_first_seconds() {
+    let window = InitialZoom::default().initial_window(10.0, 100.0, Some(50.0));
+    assert_eq!(
+        window,
+        InitialWindow {
+            visible_start: 10.0,
+            visible_end: 15.0,
+            selected_start: 10.0,
+            selected_end: 11.0,
+        }
+    );
+
+    // Short traces are not extended past their end
+    let window = InitialZoom::default().initial_window(10.0, 10.5, None);
+    assert_eq!(window.visible_end, 10.5);
+    assert_eq!(window.selected_end, 10.5);
+}
+
+#[test]
+fn test_initial_window_full_trace() {
+    let window = InitialZoom::FullTrace.initial_window(10.0, 100.0, None);
+    assert_eq!(window.selected_start, 10.0);
+    assert_eq!(window.selected_end, 100.0);
+    assert_eq!(window.visible_start, 10.0);
+    assert_eq!(window.visible_end, 100.0);
+}
+
+#[test]
+fn test_initial_window_around_produce_block() {
+    let zoom = InitialZoom::AroundFirstProduceBlock {
+        selected_seconds: 2.0,
+    };
+
+    let window = zoom.initial_window(10.0, 100.0, Some(50.0));
+    approx::assert_relative_eq!(window.selected_start, 49.8);
+    approx::assert_relative_eq!(window.selected_end, 51.8);
+    assert!(window.visible_start <= window.selected_start);
+    assert!(window.visible_end >= window.selected_end);
+
+    // Without produce_block spans the start of the trace is shown
+    let window = zoom.initial_window(10.0, 100.0, None);
+    assert_eq!(window.selected_start, 10.0);
+    assert_eq!(window.selected_end, 12.0);
+}
+
+#[test]
+fn test_preferences_default_when_missing() {
+    let preferences: Preferences = serde_json::from_str("{}").unwrap();
+    assert_eq!(preferences, Preferences::default());
+}
+
+#[test]
+fn test_preferences_appearance() {
+    // Preferences saved before the theme could be chosen get the dark theme at the native size
+    let preferences: Preferences =
+        serde_json::from_str(r#"{"lazy_mode_transformation": false}"#).unwrap();
+    assert_eq!(preferences.theme, ThemeKind::Dark);
+    assert_eq!(preferences.ui_scale, 1.0);
+
+    let preferences = Preferences {
+        theme: ThemeKind::Light,
+        ui_scale: 1.5,
+        ..Preferences::default()
+    };
+    let json = serde_json::to_string(&preferences).unwrap();
+    assert_eq!(
+        serde_json::from_str::<Preferences>(&json).unwrap(),
+        preferences
+    );
+}
@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use traviz::recent_files::{add_recent_file, format_file_size, RecentFile, MAX_RECENT_FILES};
+
+fn recent_file(path: &str) -> RecentFile {
+    RecentFile {
+        path: PathBuf::from(path),
+        file_size: 1234,
+        trace_start: 1_700_000_000.0,
+        trace_end: 1_700_000_002.5,
+    }
+}
+
+#[test]
+fn test_add_recent_file() {
+    let mut recent_files = Vec::new();
+    add_recent_file(&mut recent_files, recent_file("/traces/a.json"));
+    add_recent_file(&mut recent_files, recent_file("/traces/b.json"));
+
+    // Reopening a file moves it to the front, with updated information
+    let mut reopened = recent_file("/traces/a.json");
+    reopened.file_size = 5678;
+    add_recent_file(&mut recent_files, reopened.clone());
+    assert_eq!(recent_files, vec![reopened, recent_file("/traces/b.json")]);
+
+    for i in 0..20 {
+        add_recent_file(&mut recent_files, recent_file(&format!("/traces/{i}.json")));
+    }
+    assert_eq!(recent_files.len(), MAX_RECENT_FILES);
+    assert_eq!(recent_files[0].path, PathBuf::from("/traces/19.json"));
+}
+
+#[test]
+fn test_recent_file_description() {
+    let file = recent_file("/traces/snapshot/trace.json.gz");
+    assert_eq!(file.file_name(), "trace.json.gz");
+    assert_eq!(
+        file.description(),
+        "1.2 KB, 2023-11-14 22:13:20.000 UTC, 2.500 s"
+    );
+
+    assert_eq!(format_file_size(999), "999 B");
+    assert_eq!(format_file_size(12_300_000), "12.3 MB");
+    assert_eq!(format_file_size(4_500_000_000_000_000), "4500.0 TB");
+}
+
+#[test]
+fn test_recent_file_with_clock_offsets() {
+    // Clock offsets used to be saved with the recent files, such entries still load.
+    let json = r#"{"path": "/traces/a.json", "file_size": 1, "trace_start": 1.0, "trace_end": 2.0,
+        "clock_offsets": {"node0": 40.0}}"#;
+    let file: RecentFile = serde_json::from_str(json).unwrap();
+    assert_eq!(file.path, PathBuf::from("/traces/a.json"));
+}
@@ -0,0 +1,48 @@
+use std::path::{Path, PathBuf};
+
+use traviz::regions::{load_regions, regions_sidecar_path, save_regions, Region};
+
+mod test_helpers;
+use test_helpers::TestDir;
+
+#[test]
+fn test_regions_sidecar_path() {
+    assert_eq!(
+        regions_sidecar_path(Path::new("/traces/trace.json.gz")),
+        PathBuf::from("/traces/trace.json.gz.traviz-regions.json")
+    );
+}
+
+#[test]
+fn test_region_new_orders_times() {
+    let region = Region::new("restart".to_string(), 5.0, 2.0);
+    assert_eq!((region.start, region.end), (2.0, 5.0));
+}
+
+#[test]
+fn test_save_and_load_regions() {
+    let dir = TestDir::new("regions");
+    let trace_path = dir.0.join("trace.json");
+    assert_eq!(load_regions(&trace_path).unwrap(), vec![]);
+
+    let mut region = Region::new("patch deployed".to_string(), 1.5, 3.0);
+    region.note = "Only on the validators".to_string();
+    region.color = [1, 2, 3];
+    let regions = vec![region, Region::new("restart".to_string(), 10.0, 11.0)];
+    save_regions(&trace_path, &regions).unwrap();
+    assert!(regions_sidecar_path(&trace_path).exists());
+    assert_eq!(load_regions(&trace_path).unwrap(), regions);
+
+    // Removing all regions removes the sidecar file.
+    save_regions(&trace_path, &[]).unwrap();
+    assert!(!regions_sidecar_path(&trace_path).exists());
+    assert_eq!(load_regions(&trace_path).unwrap(), vec![]);
+}
+
+#[test]
+fn test_load_invalid_regions() {
+    let dir = TestDir::new("invalid_regions");
+    let trace_path = dir.0.join("trace.json");
+    std::fs::write(regions_sidecar_path(&trace_path), "not json").unwrap();
+    assert!(load_regions(&trace_path).is_err());
+}
@@ -0,0 +1,84 @@
+use eframe::egui::Pos2;
+use traviz::relation_arrows::{plan_arrows, ArrowCandidate, ArrowDeclutterSettings};
+
+fn candidate(key: &str, from: (f32, f32), to: (f32, f32), delay_ms: f64) -> ArrowCandidate<String> {
+    ArrowCandidate {
+        key: key.to_string(),
+        from: Pos2::new(from.0, from.1),
+        to: Pos2::new(to.0, to.1),
+        delay_ms,
+    }
+}
+
+#[test]
+fn test_plan_arrows_default_draws_everything() {
+    let candidates = vec![
+        candidate("a", (0.0, 0.0), (10.0, 10.0), 0.1),
+        candidate("a", (1.0, 1.0), (11.0, 11.0), 5.0),
+    ];
+    let plan = plan_arrows(&candidates, &ArrowDeclutterSettings::default());
+    assert_eq!(plan.bundles.len(), 2);
+    assert_eq!(plan.hidden_short, 0);
+    assert_eq!(plan.truncated, 0);
+}
+
+#[test]
+fn test_plan_arrows_hides_short_delays() {
+    let candidates = vec![
+        candidate("a", (0.0, 0.0), (10.0, 10.0), 0.1),
+        candidate("a", (100.0, 0.0), (110.0, 10.0), 5.0),
+    ];
+    let settings = ArrowDeclutterSettings {
+        hide_short_delays: true,
+        min_delay_ms: 1.0,
+        ..Default::default()
+    };
+    let plan = plan_arrows(&candidates, &settings);
+    assert_eq!(plan.hidden_short, 1);
+    assert_eq!(plan.bundles.len(), 1);
+    assert_eq!(plan.bundles[0].members, vec![1]);
+}
+
+#[test]
+fn test_plan_arrows_bundles_close_arrows_with_same_key() {
+    let candidates = vec![
+        candidate("a", (0.0, 0.0), (100.0, 50.0), 2.0),
+        candidate("a", (4.0, 2.0), (104.0, 52.0), 4.0),
+        // Same positions, different pair of nodes
+        candidate("b", (2.0, 1.0), (102.0, 51.0), 3.0),
+        // Same nodes, far away
+        candidate("a", (300.0, 0.0), (400.0, 50.0), 1.0),
+    ];
+    let settings = ArrowDeclutterSettings {
+        bundle: true,
+        bundle_bucket_px: 20.0,
+        ..Default::default()
+    };
+    let plan = plan_arrows(&candidates, &settings);
+    assert_eq!(plan.bundles.len(), 3);
+    let bundle = &plan.bundles[0];
+    assert_eq!(bundle.members, vec![0, 1]);
+    assert_eq!(bundle.from, Pos2::new(2.0, 1.0));
+    assert_eq!(bundle.to, Pos2::new(102.0, 51.0));
+    assert_eq!(bundle.mean_delay_ms, 3.0);
+    assert_eq!(plan.bundles[1].members, vec![2]);
+    assert_eq!(plan.bundles[2].members, vec![3]);
+}
+
+#[test]
+fn test_plan_arrows_keeps_longest_when_truncated() {
+    let candidates: Vec<_> = (0..5)
+        .map(|i| {
+            let x = i as f32 * 100.0;
+            candidate("a", (x, 0.0), (x + 50.0, 10.0), i as f64)
+        })
+        .collect();
+    let settings = ArrowDeclutterSettings {
+        max_arrows: 2,
+        ..Default::default()
+    };
+    let plan = plan_arrows(&candidates, &settings);
+    assert_eq!(plan.truncated, 3);
+    let kept: Vec<_> = plan.bundles.iter().map(|b| b.members[0]).collect();
+    assert_eq!(kept, vec![4, 3]);
+}
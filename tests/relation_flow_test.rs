@@ -0,0 +1,95 @@
+mod test_helpers;
+
+use std::rc::Rc;
+
+use test_helpers::*;
+use traviz::builtin_relations::preprocess_block_to_postprocess_ready_block_relation;
+use traviz::relation::RelationInstance;
+use traviz::relation_flow::{aggregate_flows, sankey_layout, Flow, FlowEndpoint, FlowWeight};
+use traviz::Span;
+
+fn instance(from: &Rc<Span>, to: &Rc<Span>) -> RelationInstance {
+    RelationInstance {
+        from_span: Rc::downgrade(from),
+        to_span: Rc::downgrade(to),
+        relation: Rc::new(preprocess_block_to_postprocess_ready_block_relation()),
+        arrival: None,
+    }
+}
+
+fn endpoint(span_name: &str, node_name: &str) -> FlowEndpoint {
+    FlowEndpoint {
+        span_name: span_name.to_string(),
+        node_name: node_name.to_string(),
+    }
+}
+
+#[test]
+fn test_aggregate_flows() {
+    let node0 = create_test_node("node0");
+    let node1 = create_test_node("node1");
+    let send1 = create_test_span("send", node0.clone(), 1.0, 1.1, b"send1");
+    let send2 = create_test_span("send", node0.clone(), 2.0, 2.1, b"send2");
+    let receive1 = create_test_span("receive", node1.clone(), 1.2, 1.3, b"receive1");
+    let receive2 = create_test_span("receive", node1.clone(), 2.4, 2.5, b"receive2");
+    let local = create_test_span("receive", node0.clone(), 2.2, 2.3, b"local");
+
+    let flows = aggregate_flows(&[
+        instance(&send1, &receive1),
+        instance(&send2, &receive2),
+        instance(&send2, &local),
+    ]);
+
+    assert_eq!(flows.len(), 2);
+    assert_eq!(flows[0].from, endpoint("send", "node0"));
+    assert_eq!(flows[0].to, endpoint("receive", "node0"));
+    assert_eq!(flows[0].count, 1);
+    assert!((flows[0].mean_delay_ms - 100.0).abs() < 1e-6);
+    assert_eq!(flows[1].to, endpoint("receive", "node1"));
+    assert_eq!(flows[1].count, 2);
+    assert!((flows[1].mean_delay_ms - 200.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_sankey_layout() {
+    let flow = |from: &str, to: &str, count: usize, mean_delay_ms: f64| Flow {
+        from: endpoint(from, "node0"),
+        to: endpoint(to, "node1"),
+        count,
+        mean_delay_ms,
+    };
+    let flows = vec![
+        flow("a", "x", 3, 10.0),
+        flow("a", "y", 1, 30.0),
+        flow("b", "x", 4, 0.0),
+    ];
+
+    // 8 instances on 80 px, no gaps: 10 px per instance.
+    let layout = sankey_layout(&flows, FlowWeight::Count, 80.0, 0.0);
+    let ranges: Vec<(f32, f32)> = layout
+        .sources
+        .iter()
+        .map(|n| (n.y_start, n.y_end))
+        .collect();
+    assert_eq!(ranges, vec![(0.0, 40.0), (40.0, 80.0)]);
+    let ranges: Vec<(f32, f32)> = layout
+        .targets
+        .iter()
+        .map(|n| (n.y_start, n.y_end))
+        .collect();
+    assert_eq!(ranges, vec![(0.0, 70.0), (70.0, 80.0)]);
+
+    assert_eq!(layout.bands.len(), 3);
+    assert_eq!(layout.bands[0].from_y, (0.0, 30.0));
+    assert_eq!(layout.bands[0].to_y, (0.0, 30.0));
+    assert_eq!(layout.bands[1].from_y, (30.0, 40.0));
+    assert_eq!(layout.bands[1].to_y, (70.0, 80.0));
+    assert_eq!(layout.bands[2].from_y, (40.0, 80.0));
+    assert_eq!(layout.bands[2].to_y, (30.0, 70.0));
+
+    // Weighted by the mean delay, "b" -> "x" has no weight.
+    let layout = sankey_layout(&flows, FlowWeight::MeanDelay, 40.0, 0.0);
+    assert_eq!(layout.bands[0].from_y, (0.0, 10.0));
+    assert_eq!(layout.bands[1].from_y, (10.0, 40.0));
+    assert_eq!(layout.bands[2].from_y, (40.0, 40.0));
+}
@@ -0,0 +1,292 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use test_helpers::*;
+use traviz::builtin_relations::{
+    preprocess_block_to_postprocess_ready_block_relation,
+    validate_chunk_endorsement_to_produce_block_on_head_relation,
+};
+use traviz::relation::{
+    find_relations, related_spans, ArrowStyle, Relation, RelationNodesConfig, RelationSearch,
+    RelationView, UnmatchedSide,
+};
+use traviz::Span;
+
+/// Pairs of preprocess_block -> postprocess_ready_block on two nodes, more than one batch per node.
+fn block_processing_spans(blocks_per_node: usize) -> Vec<Rc<Span>> {
+    let mut spans = Vec::new();
+    for node_name in ["node0", "node1"] {
+        let node = create_test_node(node_name);
+        for height in 0..blocks_per_node {
+            let start = height as f64;
+            let attributes = BTreeMap::from([("height".to_string(), int_attr(height as i64))]);
+            spans.push(create_test_span_with_attributes(
+                "preprocess_block",
+                node.clone(),
+                start,
+                start + 0.1,
+                format!("{node_name}-pre-{height}").as_bytes(),
+                attributes.clone(),
+            ));
+            spans.push(create_test_span_with_attributes(
+                "postprocess_ready_block",
+                node.clone(),
+                start + 0.5,
+                start + 0.6,
+                format!("{node_name}-post-{height}").as_bytes(),
+                attributes,
+            ));
+        }
+    }
+    spans
+}
+
+fn pre_post_view() -> (Vec<traviz::relation::Relation>, RelationView) {
+    let relation = preprocess_block_to_postprocess_ready_block_relation();
+    let view = RelationView {
+        enabled_relations: vec![relation.id],
+        name: "test".to_string(),
+        is_builtin: false,
+    };
+    (vec![relation], view)
+}
+
+#[test]
+fn test_find_relations_matches_each_block_once() {
+    let spans = block_processing_spans(5000);
+    let (relations, view) = pre_post_view();
+
+    let instances = find_relations(&relations, &view, &spans);
+
+    assert_eq!(instances.len(), 10000);
+    for span in &spans {
+        let (outgoing, incoming) = match span.original_name() {
+            "preprocess_block" => (1, 0),
+            _ => (0, 1),
+        };
+        assert_eq!(span.outgoing_relations.borrow().len(), outgoing);
+        assert_eq!(span.incoming_relations.borrow().len(), incoming);
+    }
+    for instance in &instances {
+        let from = instance.from_span.upgrade().unwrap();
+        let to = instance.to_span.upgrade().unwrap();
+        assert_eq!(from.node.name, to.node.name);
+        assert_eq!(from.attributes["height"], to.attributes["height"]);
+    }
+
+    // Searching again replaces the previously found relations.
+    let instances = find_relations(&relations, &view, &spans);
+    assert_eq!(instances.len(), 10000);
+    assert_eq!(spans[0].outgoing_relations.borrow().len(), 1);
+}
+
+#[test]
+fn test_relation_search_polling() {
+    let spans = block_processing_spans(1000);
+    let (relations, view) = pre_post_view();
+
+    let mut search = RelationSearch::start(&relations, &view, &spans);
+    let mut instances = Vec::new();
+    while !search.is_finished() {
+        instances.extend(search.poll());
+        std::thread::yield_now();
+    }
+
+    assert_eq!(instances.len(), 2000);
+    assert_eq!(search.progress(), 1.0);
+}
+
+#[test]
+fn test_fan_in_relation() {
+    let mut relation = validate_chunk_endorsement_to_produce_block_on_head_relation();
+    relation.required_sources = 2;
+    // Endorsements come from other nodes.
+    relation.nodes_config = RelationNodesConfig::AllNodes;
+    let view = RelationView {
+        enabled_relations: vec![relation.id],
+        name: "test".to_string(),
+        is_builtin: false,
+    };
+
+    let span = |name: &str, node: &str, height: i64, start: f64, end: f64| {
+        create_test_span_with_attributes(
+            name,
+            create_test_node(node),
+            start,
+            end,
+            format!("{name}-{node}-{height}").as_bytes(),
+            BTreeMap::from([("height".to_string(), int_attr(height))]),
+        )
+    };
+    let spans = vec![
+        // Three endorsements for height 10, the two earliest are needed.
+        span("validate_chunk_endorsement", "node1", 10, 1.0, 1.3),
+        span("validate_chunk_endorsement", "node2", 10, 1.0, 1.1),
+        span("validate_chunk_endorsement", "node3", 10, 1.0, 1.2),
+        span("produce_block_on_head", "node0", 10, 1.5, 1.6),
+        // Only one endorsement for height 11, not enough.
+        span("validate_chunk_endorsement", "node1", 11, 2.0, 2.1),
+        span("produce_block_on_head", "node0", 11, 2.5, 2.6),
+    ];
+
+    let mut instances = find_relations(std::slice::from_ref(&relation), &view, &spans);
+    assert_eq!(instances.len(), 2);
+    instances.sort_by_key(|instance| instance.arrival);
+    let sources: Vec<String> = instances
+        .iter()
+        .map(|instance| instance.from_span.upgrade().unwrap().node.name.clone())
+        .collect();
+    assert_eq!(sources, vec!["node2", "node3"]);
+    assert_eq!(instances[0].arrival, Some(1));
+    assert!(!instances[0].is_last_arrival());
+    assert!(instances[1].is_last_arrival());
+    assert_eq!(spans[3].incoming_relations.borrow().len(), 2);
+    assert!(spans[5].incoming_relations.borrow().is_empty());
+    assert!(spans[0].outgoing_relations.borrow().is_empty());
+}
+
+#[test]
+fn test_related_spans() {
+    let spans = block_processing_spans(2);
+    let (relations, view) = pre_post_view();
+    find_relations(&relations, &view, &spans);
+
+    // node0: pre-0, post-0, pre-1, post-1
+    let outgoing = related_spans(&spans[0]);
+    assert_eq!(outgoing.len(), 1);
+    assert!(!outgoing[0].incoming);
+    assert_eq!(outgoing[0].span.span_id, spans[1].span_id);
+    assert_eq!(outgoing[0].relation.id, relations[0].id);
+    assert!((outgoing[0].delay - 0.4).abs() < 1e-9);
+
+    let incoming = related_spans(&spans[1]);
+    assert_eq!(incoming.len(), 1);
+    assert!(incoming[0].incoming);
+    assert_eq!(incoming[0].span.span_id, spans[0].span_id);
+}
+
+#[test]
+fn test_unmatched_relation_spans() {
+    let mut relation = preprocess_block_to_postprocess_ready_block_relation();
+    relation.report_unmatched = true;
+    let view = RelationView {
+        enabled_relations: vec![relation.id],
+        name: "test".to_string(),
+        is_builtin: false,
+    };
+
+    let node = create_test_node("node0");
+    let span = |name: &str, height: i64, start: f64| {
+        create_test_span_with_attributes(
+            name,
+            node.clone(),
+            start,
+            start + 0.1,
+            format!("{name}-{height}").as_bytes(),
+            BTreeMap::from([("height".to_string(), int_attr(height))]),
+        )
+    };
+    let spans = vec![
+        span("preprocess_block", 1, 1.0),
+        span("postprocess_ready_block", 1, 1.5),
+        // Never postprocessed
+        span("preprocess_block", 2, 2.0),
+        // Postprocessed without preprocessing
+        span("postprocess_ready_block", 3, 3.5),
+    ];
+
+    let mut search = RelationSearch::start(std::slice::from_ref(&relation), &view, &spans);
+    let mut instances = Vec::new();
+    while !search.is_finished() {
+        instances.extend(search.poll());
+        std::thread::yield_now();
+    }
+    assert_eq!(instances.len(), 1);
+
+    let unmatched: Vec<(String, UnmatchedSide)> = search
+        .unmatched()
+        .iter()
+        .map(|u| (u.span.span_id.to_hex(), u.side))
+        .collect();
+    assert_eq!(
+        unmatched,
+        vec![
+            (spans[2].span_id.to_hex(), UnmatchedSide::From),
+            (spans[3].span_id.to_hex(), UnmatchedSide::To),
+        ]
+    );
+
+    // Relations without the option don't report anything.
+    relation.report_unmatched = false;
+    let mut search = RelationSearch::start(std::slice::from_ref(&relation), &view, &spans);
+    while !search.is_finished() {
+        search.poll();
+        std::thread::yield_now();
+    }
+    assert!(search.unmatched().is_empty());
+}
+
+#[test]
+fn test_unmatched_fan_in_sources() {
+    let mut relation = validate_chunk_endorsement_to_produce_block_on_head_relation();
+    relation.required_sources = 2;
+    relation.nodes_config = RelationNodesConfig::AllNodes;
+    relation.report_unmatched = true;
+    let view = RelationView {
+        enabled_relations: vec![relation.id],
+        name: "test".to_string(),
+        is_builtin: false,
+    };
+
+    let span = |name: &str, node: &str, height: i64, start: f64, end: f64| {
+        create_test_span_with_attributes(
+            name,
+            create_test_node(node),
+            start,
+            end,
+            format!("{name}-{node}-{height}").as_bytes(),
+            BTreeMap::from([("height".to_string(), int_attr(height))]),
+        )
+    };
+    let spans = vec![
+        span("validate_chunk_endorsement", "node1", 10, 1.0, 1.3),
+        span("validate_chunk_endorsement", "node2", 10, 1.0, 1.1),
+        span("validate_chunk_endorsement", "node3", 10, 1.0, 1.2),
+        span("produce_block_on_head", "node0", 10, 1.5, 1.6),
+    ];
+
+    let mut search = RelationSearch::start(std::slice::from_ref(&relation), &view, &spans);
+    while !search.is_finished() {
+        search.poll();
+        std::thread::yield_now();
+    }
+    // The latest endorsement isn't needed, the fan-in drops its match
+    let unmatched: Vec<(String, UnmatchedSide)> = search
+        .unmatched()
+        .iter()
+        .map(|u| (u.span.span_id.to_hex(), u.side))
+        .collect();
+    assert_eq!(
+        unmatched,
+        vec![(spans[0].span_id.to_hex(), UnmatchedSide::From)]
+    );
+}
+
+#[test]
+fn test_relation_saved_without_new_options() {
+    let relation = preprocess_block_to_postprocess_ready_block_relation();
+    let mut json = serde_json::to_value(&relation).unwrap();
+    let object = json.as_object_mut().unwrap();
+    for key in ["required_sources", "report_unmatched", "arrow_style"] {
+        assert!(object.remove(key).is_some());
+    }
+
+    let loaded: Relation = serde_json::from_value(json).unwrap();
+    assert_eq!(loaded.required_sources, 1);
+    assert!(!loaded.is_fan_in());
+    assert!(!loaded.report_unmatched);
+    assert_eq!(loaded.arrow_style, ArrowStyle::default());
+    assert_eq!(loaded.arrow_style.color32(), traviz::colors::INTENSE_BLUE);
+}
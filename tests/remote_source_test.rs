@@ -0,0 +1,147 @@
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use opentelemetry_proto::tonic::common::v1::{AnyValue, KeyValue};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use opentelemetry_proto::tonic::trace::v1::{ResourceSpans, ScopeSpans, Span};
+use prost::Message;
+use traviz::remote_source::{
+    decode_tempo_trace, parse_jaeger_response, parse_tempo_search, RemoteBackend, RemoteQuery,
+    RemoteSourceSettings,
+};
+
+mod test_helpers;
+use test_helpers::string_attr;
+
+fn query(backend: RemoteBackend, service: &str) -> RemoteQuery {
+    RemoteQuery {
+        settings: RemoteSourceSettings {
+            backend,
+            url: "http://backend:1234/".to_string(),
+            service: service.to_string(),
+            limit: 10,
+        },
+        start: 1700000000.5,
+        end: 1700000060.25,
+    }
+}
+
+#[test]
+fn test_tempo_urls() {
+    let query = query(RemoteBackend::Tempo, "neard");
+    assert_eq!(
+        query.search_url(),
+        "http://backend:1234/api/search?tags=service.name%3D%22neard%22\
+         &start=1700000000&end=1700000061&limit=10"
+    );
+    assert_eq!(
+        query.trace_url("2f3e0cee77ae5dc9"),
+        "http://backend:1234/api/traces/2f3e0cee77ae5dc9"
+    );
+}
+
+#[test]
+fn test_jaeger_url() {
+    let query = query(RemoteBackend::Jaeger, "my service");
+    assert_eq!(
+        query.search_url(),
+        "http://backend:1234/api/traces?service=my%20service\
+         &start=1700000000500000&end=1700000060250000&limit=10"
+    );
+}
+
+#[test]
+fn test_file_name() {
+    assert_eq!(
+        query(RemoteBackend::Tempo, "node/1 a").file_name(),
+        "tempo-node_1_a-20231114-221320.json"
+    );
+    assert_eq!(
+        query(RemoteBackend::Jaeger, "neard").file_name(),
+        "jaeger-neard-20231114-221320.json"
+    );
+}
+
+#[test]
+fn test_parse_tempo_search() {
+    let json = r#"{
+        "traces": [
+            {"traceID": "2f3e0cee77ae5dc9c17ade3689eb2e54", "rootServiceName": "neard"},
+            {"traceID": "1234", "rootServiceName": "neard"}
+        ],
+        "metrics": {"inspectedTraces": 2}
+    }"#;
+    assert_eq!(
+        parse_tempo_search(json).unwrap(),
+        vec!["2f3e0cee77ae5dc9c17ade3689eb2e54", "1234"]
+    );
+    assert!(parse_tempo_search(r#"{"metrics": {}}"#).unwrap().is_empty());
+    assert!(parse_tempo_search(r#"{"traces": [{"rootServiceName": "neard"}]}"#).is_err());
+    assert!(parse_tempo_search("not json").is_err());
+}
+
+#[test]
+fn test_decode_tempo_trace() {
+    let request = ExportTraceServiceRequest {
+        resource_spans: vec![ResourceSpans {
+            resource: Some(Resource {
+                attributes: vec![KeyValue {
+                    key: "service.name".to_string(),
+                    value: Some(AnyValue {
+                        value: string_attr("neard"),
+                    }),
+                }],
+                dropped_attributes_count: 0,
+            }),
+            scope_spans: vec![ScopeSpans {
+                spans: vec![Span {
+                    trace_id: vec![1; 16],
+                    span_id: vec![2; 8],
+                    name: "produce_block".to_string(),
+                    start_time_unix_nano: 1_700_000_000_000_000_000,
+                    end_time_unix_nano: 1_700_000_001_000_000_000,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            schema_url: String::new(),
+        }],
+    };
+    let bytes = request.encode_to_vec();
+    assert_eq!(decode_tempo_trace(&bytes).unwrap(), request);
+    assert!(decode_tempo_trace(&[0xff, 0xff, 0xff]).is_err());
+}
+
+#[test]
+fn test_parse_jaeger_response() {
+    let json = r#"{
+        "data": [{
+            "traceID": "0af7651916cd43dd8448eb211c80319c",
+            "spans": [{
+                "traceID": "0af7651916cd43dd8448eb211c80319c",
+                "spanID": "b7ad6b7169203331",
+                "operationName": "produce_block",
+                "startTime": 1700000000000000,
+                "duration": 500000,
+                "processID": "p1"
+            }],
+            "processes": {"p1": {"serviceName": "neard", "tags": []}}
+        }],
+        "total": 0, "limit": 0, "offset": 0, "errors": null
+    }"#;
+    let traces = parse_jaeger_response(json).unwrap();
+    assert_eq!(traces.len(), 1);
+    assert_eq!(
+        traces[0].resource_spans[0].scope_spans[0].spans[0].name,
+        "produce_block"
+    );
+
+    let no_traces = r#"{"data": null, "total": 0, "limit": 0, "offset": 0, "errors": null}"#;
+    assert!(parse_jaeger_response(no_traces).unwrap().is_empty());
+
+    let error =
+        r#"{"data": null, "errors": [{"code": 400, "msg": "parameter 'service' is required"}]}"#;
+    let message = parse_jaeger_response(error).unwrap_err().to_string();
+    assert!(
+        message.contains("parameter 'service' is required"),
+        "{message}"
+    );
+}
@@ -0,0 +1,43 @@
+use traviz::reorder::{duplicate_name, move_entry};
+
+#[test]
+fn test_move_entry_down() {
+    let mut items = vec!["a", "b", "c", "d"];
+    let mut selected = 0;
+    move_entry(&mut items, 0, 2, &mut selected);
+    assert_eq!(items, vec!["b", "c", "a", "d"]);
+    assert_eq!(selected, 2);
+}
+
+#[test]
+fn test_move_entry_up() {
+    let mut items = vec!["a", "b", "c", "d"];
+    let mut selected = 3;
+    move_entry(&mut items, 3, 1, &mut selected);
+    assert_eq!(items, vec!["a", "d", "b", "c"]);
+    assert_eq!(selected, 1);
+}
+
+#[test]
+fn test_move_entry_out_of_bounds() {
+    let mut items = vec!["a", "b"];
+    let mut selected = 1;
+    move_entry(&mut items, 0, 5, &mut selected);
+    assert_eq!(items, vec!["a", "b"]);
+    assert_eq!(selected, 1);
+}
+
+#[test]
+fn test_duplicate_name_is_unique() {
+    let existing = ["Mode", "Other"];
+    assert_eq!(
+        duplicate_name("Mode", existing.iter().copied()),
+        "Mode Copy"
+    );
+
+    let existing = ["Mode", "Mode Copy", "Mode Copy 2"];
+    assert_eq!(
+        duplicate_name("Mode", existing.iter().copied()),
+        "Mode Copy 3"
+    );
+}
@@ -0,0 +1,86 @@
+use traviz::charts::HistogramBucket;
+use traviz::report::{chart_svg, Report, ReportBlock, ReportChart, ReportSection, ReportSeries};
+
+fn test_report() -> Report {
+    Report {
+        title: "Incident <42>".to_string(),
+        sections: vec![ReportSection {
+            title: "Span analysis: apply_chunk".to_string(),
+            blocks: vec![
+                ReportBlock::Text("Shard 2 is slow".to_string()),
+                ReportBlock::Table {
+                    headers: vec!["Node".to_string(), "Count".to_string()],
+                    rows: vec![
+                        vec!["node|0".to_string(), "3".to_string()],
+                        vec!["node1".to_string(), "5".to_string()],
+                    ],
+                },
+                ReportBlock::Chart {
+                    title: "Delays".to_string(),
+                    chart: ReportChart::Lines {
+                        x_label: "Time (s)".to_string(),
+                        y_label: "Delay (ms)".to_string(),
+                        series: vec![ReportSeries {
+                            name: "mean".to_string(),
+                            points: vec![(0.0, 1.0), (1.0, 3.0)],
+                        }],
+                    },
+                },
+            ],
+        }],
+    }
+}
+
+#[test]
+fn test_report_to_markdown() {
+    let markdown = test_report().to_markdown();
+    assert!(markdown.starts_with("# Incident <42>\n"));
+    assert!(markdown.contains("\n## Span analysis: apply_chunk\n"));
+    assert!(markdown.contains("\nShard 2 is slow\n"));
+    assert!(markdown.contains("| Node | Count |\n| --- | --- |\n| node\\|0 | 3 |\n| node1 | 5 |\n"));
+    // The chart is embedded as an image, without characters which would end the link
+    let chart_line = markdown
+        .lines()
+        .find(|line| line.starts_with("![Delays](data:image/svg+xml;utf8,"))
+        .unwrap();
+    assert!(chart_line.ends_with(')'));
+    assert!(!chart_line[..chart_line.len() - 1]["![Delays](".len()..].contains([' ', '(', ')']));
+}
+
+#[test]
+fn test_report_to_html() {
+    let html = test_report().to_html();
+    assert!(html.contains("<title>Incident &lt;42&gt;</title>"));
+    assert!(html.contains("<tr><th>Node</th><th>Count</th></tr>"));
+    assert!(html.contains("<tr><td>node|0</td><td>3</td></tr>"));
+    assert!(html.contains("<svg "));
+    assert!(html.contains("<figcaption>Delays</figcaption>"));
+}
+
+#[test]
+fn test_chart_svg() {
+    let svg = chart_svg(&ReportChart::Histogram {
+        x_label: "Delay (ms)".to_string(),
+        buckets: vec![
+            HistogramBucket {
+                start: 0.0,
+                end: 1.0,
+                count: 2,
+            },
+            HistogramBucket {
+                start: 1.0,
+                end: 2.0,
+                count: 0,
+            },
+            HistogramBucket {
+                start: 2.0,
+                end: 3.0,
+                count: 4,
+            },
+        ],
+    });
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<rect ").count(), 4); // background and one bar per bucket
+    assert!(svg.contains(">Delay (ms)</text>"));
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use traviz::scope_filter::{count_scopes, ScopeFilter, NO_SCOPE_NAME};
+use traviz::types::{Node, Scope, Span, SpanData};
+
+mod test_helpers;
+use test_helpers::{create_test_span, string_attr, with_span_data};
+
+fn node(name: &str, region: &str) -> Arc<Node> {
+    Arc::new(Node {
+        name: name.to_string(),
+        attributes: BTreeMap::from([("region".to_string(), string_attr(region))]),
+    })
+}
+
+fn span_in_scope(name: &str, node: Arc<Node>, scope: Option<&str>, id: u8) -> Rc<Span> {
+    let span = create_test_span(name, node, 1.0, 2.0, &[id]);
+    with_span_data(span, |data| {
+        data.scope = scope.map(|scope| {
+            Arc::new(Scope {
+                name: scope.to_string(),
+                version: String::new(),
+                attributes: BTreeMap::new(),
+            })
+        })
+    })
+}
+
+#[test]
+fn test_count_scopes() {
+    let eu = node("node0", "eu");
+    let root = span_in_scope("produce_block", eu.clone(), Some("neard"), 1);
+    root.children_mut()
+        .push(span_in_scope("wasm_call", eu, Some("runtime"), 2));
+    let other = span_in_scope("apply_chunk", node("node1", "us"), None, 3);
+
+    let counts = count_scopes(&[root, other]);
+    assert_eq!(
+        counts.scopes,
+        BTreeMap::from([
+            (NO_SCOPE_NAME.to_string(), 1),
+            ("neard".to_string(), 1),
+            ("runtime".to_string(), 1),
+        ])
+    );
+    assert_eq!(
+        counts.resource_attributes,
+        BTreeMap::from([
+            (("region".to_string(), "eu".to_string()), 2),
+            (("region".to_string(), "us".to_string()), 1),
+        ])
+    );
+}
+
+#[test]
+fn test_scope_filter() {
+    let neard: SpanData = (*span_in_scope("a", node("node0", "eu"), Some("neard"), 1).data).clone();
+    let unscoped: SpanData = (*span_in_scope("b", node("node1", "us"), None, 2).data).clone();
+
+    let mut filter = ScopeFilter::default();
+    assert!(!filter.is_active());
+    assert!(filter.shows(&neard) && filter.shows(&unscoped));
+
+    filter.hidden_scopes.insert(NO_SCOPE_NAME.to_string());
+    assert!(filter.is_active());
+    assert!(filter.shows(&neard));
+    assert!(!filter.shows(&unscoped));
+
+    filter.hidden_scopes.clear();
+    filter
+        .hidden_resource_attributes
+        .insert(("region".to_string(), "eu".to_string()));
+    assert!(!filter.shows(&neard));
+    assert!(filter.shows(&unscoped));
+}
@@ -0,0 +1,71 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+
+use test_helpers::{create_test_node, create_test_span_with_attributes, int_attr, string_attr};
+use traviz::search::{add_attribute_criterion, parse_search_term, Search, SearchCriterion};
+
+#[test]
+fn test_parse_search_term() {
+    assert_eq!(
+        parse_search_term("Apply  shard_id=1 status=\"in progress\""),
+        vec![
+            SearchCriterion::NameContains("Apply".to_string()),
+            SearchCriterion::AttributeEquals {
+                key: "shard_id".to_string(),
+                value: "1".to_string()
+            },
+            SearchCriterion::AttributeEquals {
+                key: "status".to_string(),
+                value: "in progress".to_string()
+            },
+        ]
+    );
+    assert!(parse_search_term("   ").is_empty());
+
+    let mut term = "apply".to_string();
+    add_attribute_criterion(&mut term, "status", "in progress");
+    add_attribute_criterion(&mut term, "shard_id", "1");
+    add_attribute_criterion(&mut term, "shard_id", "1");
+    assert_eq!(term, "apply status=\"in progress\" shard_id=1");
+}
+
+#[test]
+fn test_search_results() {
+    let node = create_test_node("node0");
+    let span = |name: &str, start: f64, shard_id: i64, id: u8| {
+        create_test_span_with_attributes(
+            name,
+            node.clone(),
+            start,
+            start + 1.0,
+            &[id],
+            BTreeMap::from([
+                ("shard_id".to_string(), int_attr(shard_id)),
+                ("kind".to_string(), string_attr("chunk")),
+            ]),
+        )
+    };
+    let spans = vec![
+        span("apply_chunk", 3.0, 1, 1),
+        span("apply_chunk", 1.0, 1, 2),
+        span("apply_chunk", 2.0, 0, 3),
+        span("produce_chunk", 0.0, 1, 4),
+    ];
+
+    let mut search = Search::default();
+    search.search_term = "APPLY shard_id=1".to_string();
+    search.run(&spans);
+    assert_eq!(search.position_text(), "2 results");
+
+    // Results are visited in time order and wrap around
+    let starts: Vec<f64> = (0..3)
+        .map(|_| search.next_result().unwrap().start_time)
+        .collect();
+    assert_eq!(starts, vec![1.0, 3.0, 1.0]);
+    assert_eq!(search.position_text(), "1/2");
+
+    search.search_term = String::new();
+    search.run(&spans);
+    assert!(search.next_result().is_none());
+}
@@ -0,0 +1,62 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use test_helpers::{create_test_node, create_test_span_with_attributes, int_attr};
+use traviz::skew::{compute_node_lags, compute_skew};
+use traviz::types::Span;
+
+fn witness(node: &str, height: i64, start: f64, end: f64, id: u8) -> Rc<Span> {
+    create_test_span_with_attributes(
+        "validate_witness",
+        create_test_node(node),
+        start,
+        end,
+        &[id],
+        BTreeMap::from([("height".to_string(), int_attr(height))]),
+    )
+}
+
+#[test]
+fn test_skew() {
+    let spans = vec![
+        witness("node0", 10, 1.0, 1.5, 1),
+        witness("node1", 10, 1.1, 1.7, 2),
+        witness("node2", 10, 1.2, 1.6, 3),
+        // node2 is late again
+        witness("node0", 11, 2.0, 2.2, 4),
+        witness("node2", 11, 2.5, 2.9, 5),
+        // A second span for the same height on the same node, the earliest one is used
+        witness("node2", 11, 3.0, 3.1, 6),
+        // Only one node, skipped
+        witness("node0", 12, 3.0, 3.5, 7),
+    ];
+
+    let occurrences = compute_skew(&spans, "validate_witness", "height");
+    assert_eq!(occurrences.len(), 2);
+
+    let first = &occurrences[0];
+    assert_eq!(first.link_value, "10");
+    assert_eq!(first.node_spans.len(), 3);
+    assert!((first.start_skew() - 0.2).abs() < 1e-9);
+    assert!((first.end_skew() - 0.2).abs() < 1e-9);
+    assert_eq!(first.last_to_start().node.name, "node2");
+    assert_eq!(first.last_to_finish().node.name, "node1");
+
+    let second = &occurrences[1];
+    assert_eq!(second.link_value, "11");
+    assert!((second.start_skew() - 0.5).abs() < 1e-9);
+    assert!((second.end_skew() - 0.7).abs() < 1e-9);
+
+    let lags = compute_node_lags(&occurrences);
+    let names: Vec<&str> = lags.iter().map(|l| l.node_name.as_str()).collect();
+    assert_eq!(names, vec!["node2", "node1", "node0"]);
+    assert_eq!(lags[0].occurrences, 2);
+    assert_eq!(lags[0].last_to_start, 2);
+    assert_eq!(lags[0].last_to_finish, 1);
+    assert!((lags[0].mean_end_lag - 0.4).abs() < 1e-9);
+    assert_eq!(lags[2].last_to_finish, 0);
+
+    assert!(compute_skew(&spans, "validate_witness", "shard_id").is_empty());
+}
@@ -0,0 +1,83 @@
+mod test_helpers;
+
+use std::collections::BTreeMap;
+
+use test_helpers::{int_attr, string_attr};
+use traviz::source_location::{editor_command, SourceLocation, DEFAULT_EDITOR_COMMAND};
+
+#[test]
+fn test_source_location_from_attributes() {
+    let attributes = BTreeMap::from([
+        ("code.filepath".to_string(), string_attr("src/main.rs")),
+        ("code.lineno".to_string(), int_attr(42)),
+    ]);
+    let location = SourceLocation::from_attributes(&attributes).unwrap();
+    assert_eq!(
+        location,
+        SourceLocation {
+            file: "src/main.rs".to_string(),
+            line: Some(42),
+        }
+    );
+    assert_eq!(location.display_string(), "src/main.rs:42");
+}
+
+#[test]
+fn test_source_location_newer_attribute_names() {
+    let attributes = BTreeMap::from([
+        ("code.file.path".to_string(), string_attr("lib.rs")),
+        ("code.line.number".to_string(), int_attr(7)),
+    ]);
+    let location = SourceLocation::from_attributes(&attributes).unwrap();
+    assert_eq!(location.display_string(), "lib.rs:7");
+}
+
+#[test]
+fn test_source_location_without_file() {
+    let attributes = BTreeMap::from([("code.lineno".to_string(), int_attr(3))]);
+    assert_eq!(SourceLocation::from_attributes(&attributes), None);
+
+    let attributes = BTreeMap::from([("code.filepath".to_string(), string_attr(""))]);
+    assert_eq!(SourceLocation::from_attributes(&attributes), None);
+}
+
+#[test]
+fn test_source_location_without_line() {
+    let attributes = BTreeMap::from([("code.filepath".to_string(), string_attr("a.rs"))]);
+    let location = SourceLocation::from_attributes(&attributes).unwrap();
+    assert_eq!(location.line, None);
+    assert_eq!(location.display_string(), "a.rs");
+    assert_eq!(
+        editor_command(DEFAULT_EDITOR_COMMAND, &location).unwrap(),
+        vec!["code", "--goto", "a.rs:1"]
+    );
+}
+
+#[test]
+fn test_editor_command() {
+    let location = SourceLocation {
+        file: "/home/me/my project/main.rs".to_string(),
+        line: Some(10),
+    };
+    assert_eq!(
+        editor_command("vim +{line} {file}", &location).unwrap(),
+        vec!["vim", "+10", "/home/me/my project/main.rs"]
+    );
+    assert!(editor_command("  ", &location).is_err());
+}
+
+#[test]
+fn test_editor_command_with_option_like_path() {
+    let location = SourceLocation {
+        file: "--install-extension=evil".to_string(),
+        line: Some(3),
+    };
+    assert_eq!(
+        editor_command(DEFAULT_EDITOR_COMMAND, &location).unwrap(),
+        vec!["code", "--goto", "./--install-extension=evil:3"]
+    );
+    assert_eq!(
+        editor_command("vim +{line} {file}", &location).unwrap(),
+        vec!["vim", "+3", "./--install-extension=evil"]
+    );
+}
@@ -0,0 +1,64 @@
+mod test_helpers;
+
+use test_helpers::{create_test_node, create_test_span};
+use traviz::span_catalog::{compute_span_catalog, sort_span_catalog, CatalogColumn};
+
+fn names(entries: &[traviz::span_catalog::SpanCatalogEntry]) -> Vec<&str> {
+    entries.iter().map(|e| e.name.as_str()).collect()
+}
+
+#[test]
+fn test_span_catalog() {
+    let node = create_test_node("node0");
+    let spans = vec![
+        create_test_span("apply_chunk", node.clone(), 1.0, 1.1, &[1]),
+        create_test_span("apply_chunk", node.clone(), 2.0, 2.3, &[2]),
+        create_test_span("produce_block", node.clone(), 1.0, 1.5, &[3]),
+        create_test_span("validate", node.clone(), 2.0, 2.01, &[4]),
+        // Outside of the time window
+        create_test_span("apply_chunk", node.clone(), 10.0, 20.0, &[5]),
+    ];
+
+    let mut entries = compute_span_catalog(&spans, 0.0, 5.0);
+    assert_eq!(
+        names(&entries),
+        vec!["apply_chunk", "produce_block", "validate"]
+    );
+    let apply_chunk = &entries[0];
+    assert_eq!(apply_chunk.count, 2);
+    assert!((apply_chunk.total_duration - 0.4).abs() < 1e-9);
+    assert!((apply_chunk.mean_duration - 0.2).abs() < 1e-9);
+    assert!((apply_chunk.p99_duration - 0.3).abs() < 1e-9);
+
+    sort_span_catalog(&mut entries, CatalogColumn::Total, true);
+    assert_eq!(
+        names(&entries),
+        vec!["produce_block", "apply_chunk", "validate"]
+    );
+    sort_span_catalog(&mut entries, CatalogColumn::Count, true);
+    assert_eq!(
+        names(&entries),
+        vec!["apply_chunk", "produce_block", "validate"]
+    );
+    sort_span_catalog(&mut entries, CatalogColumn::Mean, false);
+    assert_eq!(
+        names(&entries),
+        vec!["validate", "apply_chunk", "produce_block"]
+    );
+    sort_span_catalog(&mut entries, CatalogColumn::Name, true);
+    assert_eq!(
+        names(&entries),
+        vec!["validate", "produce_block", "apply_chunk"]
+    );
+}
+
+#[test]
+fn test_span_catalog_includes_spans_overlapping_the_window() {
+    let node = create_test_node("node0");
+    let spans = vec![
+        create_test_span("long", node.clone(), 0.0, 10.0, &[1]),
+        create_test_span("before", node.clone(), 0.0, 1.0, &[2]),
+    ];
+    let entries = compute_span_catalog(&spans, 4.0, 5.0);
+    assert_eq!(names(&entries), vec!["long"]);
+}
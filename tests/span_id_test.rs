@@ -0,0 +1,58 @@
+use std::thread;
+
+use traviz::span_id::SpanId;
+
+#[test]
+fn test_interning() {
+    let a = SpanId::intern(&[0xab, 0xcd]);
+    assert_eq!(a, SpanId::intern(&[0xab, 0xcd]));
+    assert_ne!(a, SpanId::intern(&[0xab, 0xce]));
+    assert_eq!(&*a.bytes(), &[0xab, 0xcd]);
+    assert_eq!(a.to_hex(), "abcd");
+    assert!(!a.is_empty());
+
+    assert_eq!(SpanId::intern(&[]), SpanId::EMPTY);
+    assert!(SpanId::EMPTY.is_empty());
+    assert_eq!(SpanId::EMPTY.to_hex(), "");
+}
+
+#[test]
+fn test_interning_from_many_threads() {
+    let handles: Vec<Vec<SpanId>> = (0..4)
+        .map(|_| {
+            thread::spawn(|| {
+                (0..1000u32)
+                    .map(|i| SpanId::intern(&(i + 1_000_000).to_le_bytes()))
+                    .collect()
+            })
+        })
+        .map(|t| t.join().unwrap())
+        .collect();
+    for ids in &handles[1..] {
+        assert_eq!(ids, &handles[0]);
+    }
+}
+
+#[test]
+fn test_forget() {
+    let kept = SpanId::intern(b"forget test kept");
+    let forgotten = SpanId::intern(b"forget test forgotten");
+    SpanId::forget([forgotten, SpanId::EMPTY]);
+
+    assert_eq!(SpanId::find(b"forget test forgotten"), None);
+    assert!(forgotten.bytes().is_empty());
+    assert_eq!(SpanId::find(b"forget test kept"), Some(kept));
+    assert_eq!(&*kept.bytes(), b"forget test kept");
+    assert!(SpanId::EMPTY.is_empty());
+
+    // Interning the id again gives a new handle, the stale one never equals it.
+    let interned_again = SpanId::intern(b"forget test forgotten");
+    assert_ne!(interned_again, forgotten);
+    assert_eq!(&*interned_again.bytes(), b"forget test forgotten");
+    assert!(forgotten.bytes().is_empty());
+
+    // Forgetting a stale handle does nothing.
+    SpanId::forget([forgotten]);
+    assert_eq!(SpanId::find(b"forget test forgotten"), Some(interned_again));
+    assert_eq!(&*interned_again.bytes(), b"forget test forgotten");
+}
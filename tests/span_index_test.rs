@@ -0,0 +1,62 @@
+mod test_helpers;
+
+use test_helpers::*;
+use traviz::span_id::SpanId;
+use traviz::span_index::SpanIntervalIndex;
+
+#[test]
+fn test_query_matches_linear_scan() {
+    let node = create_test_node("node0");
+    let mut spans = Vec::new();
+    // Mostly short spans with a few very long ones, in a shuffled order.
+    let mut seed: u64 = 12345;
+    for i in 0..2000u32 {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let start = (seed >> 33) as f64 / (1u64 << 31) as f64 * 100.0;
+        let length = if i % 97 == 0 { 30.0 } else { 0.05 };
+        spans.push(create_test_span(
+            "span",
+            node.clone(),
+            start,
+            start + length,
+            &i.to_le_bytes(),
+        ));
+    }
+    let index = SpanIntervalIndex::new(spans.clone());
+    assert_eq!(index.len(), spans.len());
+
+    for (start, end) in [
+        (0.0, 100.0),
+        (10.0, 10.5),
+        (50.0, 50.0),
+        (99.9, 150.0),
+        (-10.0, -1.0),
+        (200.0, 300.0),
+    ] {
+        let mut expected: Vec<SpanId> = spans
+            .iter()
+            .filter(|s| s.max_end_time.get() >= start && s.min_start_time.get() <= end)
+            .map(|s| s.span_id)
+            .collect();
+        let mut found: Vec<SpanId> = index.query(start, end).map(|s| s.span_id).collect();
+        expected.sort_by_key(|id| id.bytes());
+        found.sort_by_key(|id| id.bytes());
+        assert_eq!(found, expected, "query {start}..{end}");
+    }
+}
+
+#[test]
+fn test_query_returns_spans_sorted_by_start() {
+    let node = create_test_node("node0");
+    let spans = vec![
+        create_test_span("c", node.clone(), 3.0, 4.0, b"c"),
+        create_test_span("a", node.clone(), 1.0, 2.0, b"a"),
+        create_test_span("b", node.clone(), 2.0, 3.0, b"b"),
+    ];
+    let index = SpanIntervalIndex::new(spans);
+    let names: Vec<&str> = index.query(1.5, 3.5).map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["a", "b", "c"]);
+    assert!(index.query(4.5, 5.0).next().is_none());
+}
@@ -0,0 +1,50 @@
+mod test_helpers;
+
+use test_helpers::{assert_close, create_test_node, create_test_span, with_children};
+use traviz::span_overlay::{compute_span_overlay, OverlayBar};
+
+#[test]
+fn test_span_overlay() {
+    let node = create_test_node("node0");
+    let read1 = create_test_span("read", node.clone(), 1.25, 1.5, &[2]);
+    let read2 = create_test_span("read", node.clone(), 5.25, 5.75, &[4]);
+    let write2 = create_test_span("write", node.clone(), 5.75, 6.5, &[5]);
+    // Listed out of order, instances are aligned at their own start.
+    let apply2 = with_children(
+        create_test_span("apply", node.clone(), 5.0, 7.0, &[3]),
+        vec![read2.clone(), write2.clone()],
+    );
+    let apply1 = with_children(
+        create_test_span("apply", node.clone(), 1.0, 2.0, &[1]),
+        vec![read1.clone()],
+    );
+    let spans = vec![apply2, read2, write2, apply1, read1];
+
+    assert!(compute_span_overlay(&spans, "missing").is_none());
+
+    let overlay = compute_span_overlay(&spans, "apply").unwrap();
+    assert_eq!(overlay.instances, 2);
+    assert_eq!(overlay.total_instances, 2);
+    assert_eq!(overlay.durations, vec![1.0, 2.0]);
+    assert_close(overlay.max_end, 2.0);
+    assert_eq!(overlay.max_depth, 1);
+    assert_eq!(
+        overlay.bars[1],
+        OverlayBar {
+            name: "read".to_string(),
+            depth: 1,
+            start: 0.25,
+            end: 0.5,
+        }
+    );
+    assert_eq!(overlay.bars.len(), 5);
+
+    // Both reads overlap at t=0.3, only the second instance writes at t=1.
+    assert_eq!(overlay.names_at(1, 0.3), vec![("read".to_string(), 2)]);
+    assert_eq!(overlay.names_at(1, 1.0), vec![("write".to_string(), 1)]);
+    assert_eq!(overlay.names_at(0, 1.5), vec![("apply".to_string(), 1)]);
+    assert!(overlay.names_at(2, 0.3).is_empty());
+
+    let counts: Vec<(&str, usize)> = overlay.name_counts().into_iter().collect();
+    assert_eq!(counts, vec![("apply", 2), ("read", 2), ("write", 1)]);
+}
@@ -0,0 +1,103 @@
+mod test_helpers;
+
+use std::rc::Rc;
+
+use approx::assert_relative_eq;
+use eframe::egui::{Pos2, Rect};
+use test_helpers::{create_test_node, create_test_span};
+use traviz::span_selection::{find_spans_by_id, selection_to_csv, spans_in_rect, SpanSelection};
+
+#[test]
+fn test_selection_stats() {
+    let node = create_test_node("node0");
+    let a = create_test_span("a", node.clone(), 1.0, 1.5, &[1]);
+    let b = create_test_span("b", node.clone(), 2.0, 3.0, &[2]);
+    let c = create_test_span("c", node.clone(), 0.5, 0.75, &[3]);
+
+    let mut selection = SpanSelection::new();
+    assert!(selection.stats().is_none());
+
+    selection.toggle(&a);
+    selection.extend([b.clone(), a.clone(), c.clone()]);
+    assert_eq!(selection.len(), 3);
+
+    let stats = selection.stats().unwrap();
+    assert_eq!(stats.count, 3);
+    assert_relative_eq!(stats.total_duration, 1750.0, epsilon = 1e-9);
+    assert_relative_eq!(stats.mean_duration, 1750.0 / 3.0, epsilon = 1e-9);
+    assert_relative_eq!(stats.start_time, 0.5);
+    assert_relative_eq!(stats.end_time, 3.0);
+    assert_relative_eq!(stats.extent(), 2500.0, epsilon = 1e-9);
+
+    // Ctrl+clicking a selected span deselects it
+    selection.toggle(&b);
+    assert!(!selection.contains(b.span_id));
+    assert_eq!(selection.stats().unwrap().count, 2);
+}
+
+#[test]
+fn test_rubber_band_selection() {
+    let node = create_test_node("node0");
+    let a = create_test_span("a", node.clone(), 1.0, 2.0, &[1]);
+    let b = create_test_span("b", node.clone(), 2.0, 3.0, &[2]);
+    let drawn = vec![
+        (
+            Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(100.0, 20.0)),
+            a.clone(),
+        ),
+        (
+            Rect::from_min_max(Pos2::new(100.0, 30.0), Pos2::new(200.0, 50.0)),
+            b.clone(),
+        ),
+    ];
+    let selected = spans_in_rect(
+        &drawn,
+        Rect::from_two_pos(Pos2::new(150.0, 10.0), Pos2::new(50.0, 25.0)),
+    );
+    assert_eq!(selected.len(), 1);
+    assert_eq!(selected[0].span_id, a.span_id);
+
+    let selected = spans_in_rect(
+        &drawn,
+        Rect::from_min_max(Pos2::new(90.0, 10.0), Pos2::new(110.0, 40.0)),
+    );
+    assert_eq!(selected.len(), 2);
+}
+
+#[test]
+fn test_selection_to_csv() {
+    let node = create_test_node("node0");
+    let span = create_test_span("apply, \"chunk\"", node, 1.0, 1.25, &[0xab]);
+    let csv = selection_to_csv(&[span]);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "name,node,start_time,end_time,duration_ms,span_id,trace_id"
+    );
+    assert!(
+        lines[1].starts_with("\"apply, \"\"chunk\"\"\",node0,1.000000000,1.250000000,250.000,ab,")
+    );
+}
+
+#[test]
+fn test_select_spans_of_rebuilt_trace() {
+    let node = create_test_node("node0");
+    let a = create_test_span("a", node.clone(), 1.0, 2.0, &[1]);
+    let b = create_test_span("b", node.clone(), 2.0, 3.0, &[2]);
+    let mut selection = SpanSelection::new();
+    selection.extend([b.clone(), a.clone()]);
+    let span_ids = selection.span_ids();
+    assert_eq!(span_ids, vec![b.span_id, a.span_id]);
+
+    // The trace is built again, `b` is gone and `a` is now a child of another span.
+    let new_root = create_test_span("root", node.clone(), 0.0, 5.0, &[3]);
+    let new_a = create_test_span("a", node, 1.0, 2.0, &[1]);
+    new_root.children_mut().push(new_a.clone());
+    let spans_by_id = find_spans_by_id(&[new_root], &span_ids.iter().copied().collect());
+    assert_eq!(spans_by_id.len(), 1);
+
+    selection.select_ids(&span_ids, &spans_by_id);
+    assert_eq!(selection.len(), 1);
+    assert!(Rc::ptr_eq(&selection.spans()[0], &new_a));
+    assert!(!selection.contains(b.span_id));
+}
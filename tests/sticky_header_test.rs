@@ -0,0 +1,25 @@
+use traviz::sticky_header::{sticky_header_top, StickyLaneHeader};
+
+#[test]
+fn test_sticky_header_top() {
+    // The top of the lane is visible, nothing is pinned.
+    assert_eq!(sticky_header_top(100.0, 500.0, 50.0, 20.0), None);
+    assert_eq!(sticky_header_top(50.0, 500.0, 50.0, 20.0), None);
+    // The lane is above the viewport.
+    assert_eq!(sticky_header_top(-500.0, 50.0, 50.0, 20.0), None);
+
+    // Scrolled inside the lane, the header is at the top of the viewport.
+    assert_eq!(sticky_header_top(0.0, 500.0, 50.0, 20.0), Some(50.0));
+    // Near the end of the lane the header is pushed up by the lane's bottom edge.
+    assert_eq!(sticky_header_top(0.0, 60.0, 50.0, 20.0), Some(40.0));
+}
+
+#[test]
+fn test_sticky_lane_header_names() {
+    assert_eq!(StickyLaneHeader::default(), StickyLaneHeader::NodeName);
+    assert!(StickyLaneHeader::NodeNameAndTopLevelSpans.pins_top_level_spans());
+    assert!(!StickyLaneHeader::NodeName.pins_top_level_spans());
+    for header in StickyLaneHeader::all() {
+        assert!(!header.name().is_empty());
+    }
+}
@@ -1,16 +1,21 @@
 #![allow(dead_code)]
 
-use std::cell::{Cell, RefCell};
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::Arc;
 
 use opentelemetry_proto::tonic::common::v1::any_value::Value;
+use opentelemetry_proto::tonic::common::v1::{AnyValue, InstrumentationScope, KeyValue};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use opentelemetry_proto::tonic::trace::v1::{ResourceSpans, ScopeSpans};
 
-use traviz::types::{DisplayLength, Node, Span, SpanDisplayConfig, TimePoint};
+use traviz::span_id::SpanId;
+use traviz::types::{Node, Span, SpanData, TimePoint};
 
 /// Helper to create a simple fake node
-pub fn create_test_node(name: &str) -> Rc<Node> {
-    Rc::new(Node {
+pub fn create_test_node(name: &str) -> Arc<Node> {
+    Arc::new(Node {
         name: name.to_string(),
         attributes: BTreeMap::new(),
     })
@@ -19,80 +24,83 @@ pub fn create_test_node(name: &str) -> Rc<Node> {
 /// Helper to create a fake span with minimal required fields
 pub fn create_test_span(
     name: &str,
-    node: Rc<Node>,
+    node: Arc<Node>,
     start_time: TimePoint,
     end_time: TimePoint,
     span_id: &[u8],
 ) -> Rc<Span> {
-    Rc::new(Span {
-        name: name.to_string(),
+    Rc::new(Span::new(Arc::new(SpanData {
         original_name: name.to_string(),
-        span_id: span_id.to_vec(),
-        trace_id: vec![1, 2, 3, 4],
-        parent_span_id: vec![],
+        span_id: SpanId::intern(span_id),
+        trace_id: SpanId::intern(&[1, 2, 3, 4]),
+        parent_span_id: SpanId::EMPTY,
         start_time,
         end_time,
         attributes: BTreeMap::new(),
         events: vec![],
         node,
         scope: None,
-        children: RefCell::new(vec![]),
-        display_children: RefCell::new(vec![]),
-        min_start_time: Cell::new(start_time),
-        max_end_time: Cell::new(end_time),
-        display_options: SpanDisplayConfig {
-            display_length: DisplayLength::Time,
-        },
-        collapse_children: Cell::new(false),
-        dont_collapse_this_span: Cell::new(false),
-        parent_height_offset: Cell::new(0),
-        display_start: Cell::new(0.0),
-        display_length: Cell::new(0.0),
-        time_display_length: Cell::new(0.0),
-        incoming_relations: RefCell::new(vec![]),
-        outgoing_relations: RefCell::new(vec![]),
-        active_segments: None,
-    })
+    })))
 }
 
 /// Helper to create a span with attributes
 pub fn create_test_span_with_attributes(
     name: &str,
-    node: Rc<Node>,
+    node: Arc<Node>,
     start_time: TimePoint,
     end_time: TimePoint,
     span_id: &[u8],
     attributes: BTreeMap<String, Option<Value>>,
 ) -> Rc<Span> {
-    Rc::new(Span {
-        name: name.to_string(),
+    Rc::new(Span::new(Arc::new(SpanData {
         original_name: name.to_string(),
-        span_id: span_id.to_vec(),
-        trace_id: vec![1, 2, 3, 4],
-        parent_span_id: vec![],
+        span_id: SpanId::intern(span_id),
+        trace_id: SpanId::intern(&[1, 2, 3, 4]),
+        parent_span_id: SpanId::EMPTY,
         start_time,
         end_time,
         attributes,
         events: vec![],
         node,
         scope: None,
-        children: RefCell::new(vec![]),
-        display_children: RefCell::new(vec![]),
-        min_start_time: Cell::new(start_time),
-        max_end_time: Cell::new(end_time),
-        display_options: SpanDisplayConfig {
-            display_length: DisplayLength::Time,
-        },
-        collapse_children: Cell::new(false),
-        dont_collapse_this_span: Cell::new(false),
-        parent_height_offset: Cell::new(0),
-        display_start: Cell::new(0.0),
-        display_length: Cell::new(0.0),
-        time_display_length: Cell::new(0.0),
-        incoming_relations: RefCell::new(vec![]),
-        outgoing_relations: RefCell::new(vec![]),
-        active_segments: None,
-    })
+    })))
+}
+
+/// Adds `children` to the children of `parent`
+pub fn with_children(parent: Rc<Span>, children: Vec<Rc<Span>>) -> Rc<Span> {
+    parent.children_mut().extend(children);
+    parent
+}
+
+/// Helper to create a copy of `span` with its data changed by `change`, without the children
+pub fn with_span_data(span: Rc<Span>, change: impl FnOnce(&mut SpanData)) -> Rc<Span> {
+    let mut data = Arc::unwrap_or_clone(span.data.clone());
+    change(&mut data);
+    Rc::new(Span::new(Arc::new(data)))
+}
+
+/// Empty temporary directory for the test files, removed when dropped
+pub struct TestDir(pub PathBuf);
+
+impl TestDir {
+    /// `name` has to be unique among the tests, the tests run in parallel
+    pub fn new(name: &str) -> TestDir {
+        let path = std::env::temp_dir().join(format!("traviz-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TestDir(path)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Asserts that two times or durations are equal up to rounding errors
+pub fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-9, "{a} != {b}");
 }
 
 /// Helper to create a string attribute value
@@ -115,6 +123,56 @@ pub fn double_attr(value: f64) -> Option<Value> {
     Some(Value::DoubleValue(value))
 }
 
+/// Helper to create the OTLP spans of a node, without a resource when `node` is `None`
+pub fn otlp_resource_spans(node: Option<&str>, scope_spans: Vec<ScopeSpans>) -> ResourceSpans {
+    ResourceSpans {
+        resource: node.map(|node| Resource {
+            attributes: vec![KeyValue {
+                key: "service.name".to_string(),
+                value: Some(AnyValue {
+                    value: string_attr(node),
+                }),
+            }],
+            ..Default::default()
+        }),
+        scope_spans,
+        ..Default::default()
+    }
+}
+
+/// Helper to put OTLP spans in the instrumentation scope `scope`, or in no scope when it's `None`
+pub fn otlp_scope_spans(
+    scope: Option<&str>,
+    spans: Vec<opentelemetry_proto::tonic::trace::v1::Span>,
+) -> ScopeSpans {
+    ScopeSpans {
+        scope: scope.map(|name| InstrumentationScope {
+            name: name.to_string(),
+            ..Default::default()
+        }),
+        spans,
+        ..Default::default()
+    }
+}
+
+/// Helper to create an OTLP span with `id` repeated as its 8 byte span id, times in milliseconds
+pub fn otlp_span(
+    name: &str,
+    id: u8,
+    parent: Option<u8>,
+    start_ms: u64,
+    end_ms: u64,
+) -> opentelemetry_proto::tonic::trace::v1::Span {
+    opentelemetry_proto::tonic::trace::v1::Span {
+        name: name.to_string(),
+        span_id: vec![id; 8],
+        parent_span_id: parent.map(|p| vec![p; 8]).unwrap_or_default(),
+        start_time_unix_nano: start_ms * 1_000_000,
+        end_time_unix_nano: end_ms * 1_000_000,
+        ..Default::default()
+    }
+}
+
 /// Represents a time interval for spans
 #[derive(Debug, Clone)]
 pub struct TimeInterval {
@@ -184,7 +242,7 @@ impl SpanConfig {
 
 /// Builder for creating comprehensive test scenarios
 pub struct ScenarioBuilder {
-    nodes: Vec<Rc<Node>>,
+    nodes: Vec<Arc<Node>>,
     spans: Vec<SpanConfig>,
     next_node_counter: usize,
 }
@@ -235,7 +293,7 @@ impl ScenarioBuilder {
 
     /// Build the scenario into a TestScenario
     pub fn build(self) -> TestScenario {
-        let mut node_map: BTreeMap<String, Rc<Node>> = BTreeMap::new();
+        let mut node_map: BTreeMap<String, Arc<Node>> = BTreeMap::new();
 
         // Ensure all referenced nodes exist
         for span_config in &self.spans {
@@ -0,0 +1,60 @@
+mod test_helpers;
+
+use test_helpers::{create_test_node, create_test_span};
+use traviz::throughput::{compute_throughput, ThroughputBucket, MAX_BUCKETS};
+
+#[test]
+fn test_throughput() {
+    let node0 = create_test_node("node0");
+    let node1 = create_test_node("node1");
+    let spans = vec![
+        create_test_span("apply_chunk", node0.clone(), 10.0, 10.2, &[1]),
+        create_test_span("apply_chunk", node0.clone(), 10.5, 10.6, &[2]),
+        create_test_span("apply_chunk", node1.clone(), 10.9, 11.5, &[3]),
+        // Nothing in the second bucket, a stall
+        create_test_span("apply_chunk", node0.clone(), 12.1, 12.2, &[4]),
+        create_test_span("other", node0.clone(), 11.0, 11.1, &[5]),
+    ];
+
+    let throughput = compute_throughput(&spans, "apply_chunk", 1.0).unwrap();
+    assert_eq!(throughput.start, 10.0);
+    assert_eq!(throughput.bucket_start(2), 12.0);
+
+    let node0_counts: Vec<usize> = throughput.per_node["node0"]
+        .iter()
+        .map(|b| b.count)
+        .collect();
+    assert_eq!(node0_counts, vec![2, 0, 1]);
+    let node1_counts: Vec<usize> = throughput.per_node["node1"]
+        .iter()
+        .map(|b| b.count)
+        .collect();
+    assert_eq!(node1_counts, vec![1, 0, 0]);
+
+    let total = throughput.total();
+    assert_eq!(total.len(), 3);
+    assert_eq!(total[0].count, 3);
+    assert!((total[0].total_duration - 0.9).abs() < 1e-9);
+    assert_eq!(total[1], ThroughputBucket::default());
+
+    assert!(compute_throughput(&spans, "missing", 1.0).is_none());
+    assert!(compute_throughput(&spans, "apply_chunk", 0.0).is_none());
+}
+
+#[test]
+fn test_throughput_bucket_count_is_capped() {
+    let node = create_test_node("node0");
+    let spans = vec![
+        create_test_span("apply_chunk", node.clone(), 0.0, 0.1, &[1]),
+        create_test_span("apply_chunk", node.clone(), 500.0, 500.1, &[2]),
+        create_test_span("apply_chunk", node.clone(), 1000.0, 1000.1, &[3]),
+    ];
+
+    // A millisecond wide bucket would need a million buckets.
+    let throughput = compute_throughput(&spans, "apply_chunk", 0.001).unwrap();
+    assert!(throughput.bucket_width > 0.001);
+    let buckets = &throughput.per_node["node0"];
+    assert!(buckets.len() <= MAX_BUCKETS);
+    assert_eq!(buckets.iter().map(|b| b.count).sum::<usize>(), 3);
+    assert_eq!(buckets.last().unwrap().count, 1);
+}
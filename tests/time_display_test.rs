@@ -0,0 +1,112 @@
+use traviz::types::{
+    format_duration, precision_for_interval, time_point_from_unix_nano, TimeDisplaySettings,
+    TimeFormat, TimeZoneSetting, MAX_TIME_PRECISION,
+};
+
+// 2025-01-02 03:04:05.1234567 UTC
+const TIME: f64 = 1735787045.1234567;
+
+fn time_format(time_zone: TimeZoneSetting) -> TimeFormat {
+    TimeFormat {
+        settings: TimeDisplaySettings {
+            time_zone,
+            ..TimeDisplaySettings::default()
+        },
+        ..TimeFormat::default()
+    }
+}
+
+#[test]
+fn test_utc_precision() {
+    let format = TimeFormat::default();
+    assert_eq!(format.time_string(TIME), "2025-01-02 03:04:05.123");
+    assert_eq!(
+        format.time_string_with_precision(TIME, 0),
+        "2025-01-02 03:04:05"
+    );
+    assert_eq!(
+        format.time_string_with_precision(TIME, 6),
+        "2025-01-02 03:04:05.123456"
+    );
+    // Precision is capped at nanoseconds
+    assert_eq!(
+        format.time_string_with_precision(TIME, 20).len(),
+        "2025-01-02 03:04:05.123456789".len()
+    );
+}
+
+#[test]
+fn test_fixed_offset() {
+    let format = time_format(TimeZoneSetting::FixedOffset { minutes: 90 });
+    assert_eq!(format.time_string(TIME), "2025-01-02 04:34:05.123");
+    let format = time_format(TimeZoneSetting::FixedOffset { minutes: -240 });
+    assert_eq!(format.time_string(TIME), "2025-01-01 23:04:05.123");
+}
+
+#[test]
+fn test_relative_to_trace_start() {
+    let mut format = time_format(TimeZoneSetting::RelativeToTraceStart);
+    // Without an opened trace the time is shown in UTC
+    assert_eq!(format.time_string(TIME), "2025-01-02 03:04:05.123");
+
+    format.trace_start = Some(TIME - 2.5);
+    assert_eq!(format.time_string(TIME), "+2.500 s");
+    assert_eq!(format.display_string(TIME - 3.0), "-0.500 s");
+}
+
+#[test]
+fn test_time_origin() {
+    let mut format = TimeFormat::default();
+    assert_eq!(format.relative_string(TIME), None);
+
+    format.origin = Some(TIME - 0.0125);
+    assert_eq!(format.relative_string(TIME).unwrap(), "t0+12.500 ms");
+    assert_eq!(
+        format.display_string(TIME),
+        "2025-01-02 03:04:05.123 (t0+12.500 ms)"
+    );
+}
+
+#[test]
+fn test_settings_default_when_missing() {
+    let settings: TimeDisplaySettings = serde_json::from_str("{}").unwrap();
+    assert_eq!(settings, TimeDisplaySettings::default());
+    let settings: TimeDisplaySettings =
+        serde_json::from_str(r#"{"time_zone": {"FixedOffset": {"minutes": 60}}}"#).unwrap();
+    assert_eq!(
+        settings.time_zone,
+        TimeZoneSetting::FixedOffset { minutes: 60 }
+    );
+    assert_eq!(settings.tick_precision, 3);
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(1.5), "1500.000 ms");
+    assert_eq!(format_duration(0.0012345), "1.234 ms");
+    assert_eq!(format_duration(0.0), "0.000 ms");
+    assert_eq!(format_duration(12.5e-6), "12.500 µs");
+    assert_eq!(format_duration(250e-9), "250 ns");
+    assert_eq!(format_duration(-3e-6), "-3.000 µs");
+}
+
+#[test]
+fn test_precision_for_interval() {
+    assert_eq!(precision_for_interval(2.0), 0);
+    assert_eq!(precision_for_interval(0.05), 2);
+    assert_eq!(precision_for_interval(0.002), 3);
+    assert_eq!(precision_for_interval(3e-7), 7);
+    assert_eq!(precision_for_interval(1e-12), MAX_TIME_PRECISION);
+    assert_eq!(precision_for_interval(0.0), MAX_TIME_PRECISION);
+}
+
+#[test]
+fn test_time_point_from_unix_nano() {
+    let unix_nano = 1_735_787_045_123_456_789;
+    let time = time_point_from_unix_nano(unix_nano);
+    // Exact up to the resolution of f64 at this magnitude
+    assert!((time - 1_735_787_045.123_456_8).abs() < 3e-7);
+    // Durations of nearby times keep sub-microsecond precision
+    let later = time_point_from_unix_nano(unix_nano + 1_000);
+    assert!((later - time - 1e-6).abs() < 3e-7);
+}
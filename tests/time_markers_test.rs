@@ -0,0 +1,60 @@
+mod test_helpers;
+
+use test_helpers::{create_test_node, create_test_span};
+use traviz::structured_modes::SpanSelector;
+use traviz::time_markers::{collect_time_markers, default_time_marker_rules, TimeMarkerRule};
+
+fn apply_block_rule() -> TimeMarkerRule {
+    TimeMarkerRule {
+        label: "apply_block".to_string(),
+        selector: SpanSelector::new_equal_name("apply_block"),
+        color: [0, 0, 255],
+        show_node_name: false,
+        enabled: true,
+    }
+}
+
+#[test]
+fn test_default_rule_marks_produce_block() {
+    let node0 = create_test_node("node0");
+    let node1 = create_test_node("node1");
+    let root = create_test_span("process_block", node0.clone(), 1.0, 5.0, &[1]);
+    let child = create_test_span("produce_block_on_head", node0.clone(), 3.0, 4.0, &[2]);
+    root.children_mut().push(child);
+    let spans = vec![
+        root,
+        create_test_span("produce_block", node1, 2.0, 3.0, &[3]),
+        create_test_span("apply_block", node0, 1.5, 2.0, &[4]),
+    ];
+
+    let markers = collect_time_markers(&default_time_marker_rules(), &spans);
+    let found: Vec<(f64, &str)> = markers
+        .iter()
+        .map(|m| (m.time, m.node_name.as_str()))
+        .collect();
+    assert_eq!(found, vec![(2.0, "node1"), (3.0, "node0")]);
+    assert!(markers.iter().all(|m| m.rule_index == 0));
+}
+
+#[test]
+fn test_multiple_and_disabled_rules() {
+    let node = create_test_node("node0");
+    let spans = vec![
+        create_test_span("produce_block", node.clone(), 2.0, 3.0, &[1]),
+        create_test_span("apply_block", node, 1.0, 2.0, &[2]),
+    ];
+    let mut rules = default_time_marker_rules();
+    rules.push(apply_block_rule());
+
+    let markers = collect_time_markers(&rules, &spans);
+    let found: Vec<(f64, usize)> = markers.iter().map(|m| (m.time, m.rule_index)).collect();
+    assert_eq!(found, vec![(1.0, 1), (2.0, 0)]);
+
+    rules[0].enabled = false;
+    let markers = collect_time_markers(&rules, &spans);
+    assert_eq!(markers.len(), 1);
+    assert_eq!(markers[0].rule_index, 1);
+
+    rules[1].enabled = false;
+    assert!(collect_time_markers(&rules, &spans).is_empty());
+}
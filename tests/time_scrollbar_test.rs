@@ -0,0 +1,30 @@
+use traviz::time_scrollbar::{clamp_selected_start, selected_start_for_click, thumb_fractions};
+
+#[test]
+fn test_thumb_fractions() {
+    assert_eq!(thumb_fractions(10.0, 20.0, 12.0, 15.0), (0.2, 0.5));
+    // Parts of the selected range outside of the trace are cut off.
+    assert_eq!(thumb_fractions(10.0, 20.0, 5.0, 25.0), (0.0, 1.0));
+    // An empty trace has the whole track as the thumb.
+    assert_eq!(thumb_fractions(10.0, 10.0, 10.0, 11.0), (0.0, 1.0));
+}
+
+#[test]
+fn test_clamp_selected_start() {
+    assert_eq!(clamp_selected_start(10.0, 20.0, 2.0, 15.0), 15.0);
+    assert_eq!(clamp_selected_start(10.0, 20.0, 2.0, 5.0), 10.0);
+    assert_eq!(clamp_selected_start(10.0, 20.0, 2.0, 19.0), 18.0);
+    // A range longer than the trace keeps covering the whole trace.
+    assert_eq!(clamp_selected_start(10.0, 20.0, 20.0, 8.0), 8.0);
+    assert_eq!(clamp_selected_start(10.0, 20.0, 20.0, 12.0), 10.0);
+    assert_eq!(clamp_selected_start(10.0, 20.0, 20.0, -5.0), 0.0);
+}
+
+#[test]
+fn test_selected_start_for_click() {
+    // The range is centered on the clicked time.
+    assert_eq!(selected_start_for_click(10.0, 20.0, 2.0, 0.5), 14.0);
+    // Near the ends it stays inside of the trace.
+    assert_eq!(selected_start_for_click(10.0, 20.0, 2.0, 0.0), 10.0);
+    assert_eq!(selected_start_for_click(10.0, 20.0, 2.0, 1.0), 18.0);
+}
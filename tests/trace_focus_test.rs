@@ -0,0 +1,59 @@
+use std::rc::Rc;
+
+use traviz::span_id::SpanId;
+use traviz::trace_focus::{parse_trace_id, TraceFocus};
+use traviz::types::Span;
+
+mod test_helpers;
+use test_helpers::{create_test_node, create_test_span, with_span_data};
+
+fn span_with_trace(trace_id: &[u8]) -> Rc<Span> {
+    let span = create_test_span("produce_block", create_test_node("node0"), 1.0, 2.0, &[7]);
+    with_span_data(span, |data| data.trace_id = SpanId::intern(trace_id))
+}
+
+#[test]
+fn test_parse_trace_id() {
+    let expected = SpanId::intern(&[0x0a, 0xf7, 0x65, 0x19]);
+    assert_eq!(parse_trace_id("0af76519").unwrap(), expected);
+    assert_eq!(parse_trace_id("  0x0af76519\n").unwrap(), expected);
+    assert!(parse_trace_id("").is_err());
+    assert!(parse_trace_id("0x").is_err());
+    assert!(parse_trace_id("0af7651").is_err());
+    assert!(parse_trace_id("not hex").is_err());
+}
+
+#[test]
+fn test_trace_focus() {
+    let focused = span_with_trace(&[1, 1]);
+    let other = span_with_trace(&[2, 2]);
+
+    let mut focus = TraceFocus::default();
+    assert!(!focus.is_tinted(&focused));
+    assert!(focus.shows(&focused) && focus.shows(&other));
+
+    focus.input = "0101".to_string();
+    focus.focus_input().unwrap();
+    assert_eq!(focus.trace_id, Some(focused.trace_id));
+    assert!(focus.is_tinted(&focused));
+    assert!(!focus.is_tinted(&other));
+    assert!(focus.shows(&other));
+
+    // Filtered traces aren't tinted, all displayed spans are from the trace.
+    focus.filter = true;
+    assert!(!focus.is_tinted(&focused));
+    assert!(focus.shows(&focused));
+    assert!(!focus.shows(&other));
+
+    focus.focus(other.trace_id);
+    assert_eq!(focus.input, "0202");
+    assert!(focus.shows(&other));
+
+    focus.clear();
+    assert!(focus.input.is_empty());
+    assert!(focus.shows(&focused) && focus.shows(&other));
+
+    focus.input = "xyz".to_string();
+    assert!(focus.focus_input().is_err());
+    assert_eq!(focus.trace_id, None);
+}
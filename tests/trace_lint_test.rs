@@ -0,0 +1,104 @@
+mod test_helpers;
+
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use opentelemetry_proto::tonic::trace::v1::{ScopeSpans, Span};
+use test_helpers::{otlp_resource_spans, otlp_scope_spans, otlp_span};
+use traviz::analyze_utils::process_spans_for_analysis;
+use traviz::modes::structured_mode_transformation;
+use traviz::structured_modes::everything_structured_mode;
+use traviz::trace_lint::{lint_trace, LintKind, LintReport};
+
+fn span(id: u8, parent: Option<u8>, start_ms: u64, end_ms: u64) -> Span {
+    otlp_span(&format!("span{id}"), id, parent, start_ms, end_ms)
+}
+
+fn scope_spans(spans: Vec<Span>) -> ScopeSpans {
+    otlp_scope_spans(Some("scope"), spans)
+}
+
+/// Lints the request with the spans parsed from it, like the app does after loading a file.
+fn lint(request: ExportTraceServiceRequest) -> LintReport {
+    let requests = [request];
+    let top_level_spans =
+        structured_mode_transformation(&requests, &everything_structured_mode()).unwrap();
+    let (all_spans, _) = process_spans_for_analysis(&top_level_spans);
+    lint_trace(&all_spans, &requests)
+}
+
+#[test]
+fn test_lint_trace() {
+    let request = ExportTraceServiceRequest {
+        resource_spans: vec![
+            otlp_resource_spans(
+                Some("node0"),
+                vec![
+                    scope_spans(vec![
+                        span(1, None, 100, 200),
+                        // Ends before it starts
+                        span(2, Some(1), 150, 120),
+                        // Parent isn't in the trace
+                        span(3, Some(99), 100, 110),
+                    ]),
+                    // No spans
+                    scope_spans(vec![]),
+                ],
+            ),
+            otlp_resource_spans(
+                Some("node1"),
+                vec![scope_spans(vec![
+                    // Child on another node which starts before its parent
+                    span(4, Some(1), 90, 150),
+                    // Same id as another span
+                    span(5, None, 300, 400),
+                    span(5, None, 500, 600),
+                ])],
+            ),
+        ],
+    };
+
+    let report = lint(request);
+    assert_eq!(report.count(LintKind::MissingParent), 1);
+    assert_eq!(report.count(LintKind::NegativeDuration), 1);
+    assert_eq!(report.count(LintKind::DuplicateSpanId), 1);
+    assert_eq!(report.count(LintKind::ClockSkew), 1);
+    assert_eq!(report.count(LintKind::OrphanedScope), 1);
+
+    let kinds: Vec<LintKind> = report.issues.iter().map(|i| i.kind).collect();
+    let mut sorted = kinds.clone();
+    sorted.sort();
+    assert_eq!(kinds, sorted);
+
+    let skew = report
+        .issues
+        .iter()
+        .find(|i| i.kind == LintKind::ClockSkew)
+        .unwrap();
+    assert!(
+        skew.message.contains("10.000 ms before its parent"),
+        "{}",
+        skew.message
+    );
+}
+
+#[test]
+fn test_lint_clean_trace() {
+    let request = ExportTraceServiceRequest {
+        resource_spans: vec![otlp_resource_spans(
+            Some("node0"),
+            vec![scope_spans(vec![
+                span(1, None, 100, 200),
+                span(2, Some(1), 120, 150),
+            ])],
+        )],
+    };
+    assert!(lint(request).is_empty());
+
+    let no_resource = ExportTraceServiceRequest {
+        resource_spans: vec![otlp_resource_spans(
+            None,
+            vec![scope_spans(vec![span(1, None, 0, 1)])],
+        )],
+    };
+    let report = lint(no_resource);
+    assert_eq!(report.count(LintKind::OrphanedScope), 1);
+}
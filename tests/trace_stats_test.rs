@@ -0,0 +1,64 @@
+mod test_helpers;
+
+use test_helpers::{create_test_node, create_test_span};
+use traviz::trace_stats::{compute_trace_stats, TOP_SPAN_NAMES};
+
+#[test]
+fn test_trace_stats() {
+    let node0 = create_test_node("node0");
+    let node1 = create_test_node("node1");
+    let spans = vec![
+        create_test_span("apply_chunk", node0.clone(), 1.0, 1.5, &[1]),
+        create_test_span("apply_chunk", node0.clone(), 2.0, 2.5, &[2]),
+        create_test_span("apply_chunk", node1.clone(), 2.0, 2.5, &[3]),
+        create_test_span("produce_block", node1.clone(), 0.5, 3.0, &[4]),
+    ];
+
+    let stats = compute_trace_stats(2, &spans);
+    assert_eq!(stats.requests, 2);
+    assert_eq!(stats.spans, 4);
+    assert_eq!(stats.events, 0);
+    assert_eq!(stats.nodes(), 2);
+    assert_eq!((stats.start_time, stats.end_time), (0.5, 3.0));
+
+    let by_count: Vec<(&str, usize)> = stats
+        .top_by_count
+        .iter()
+        .map(|s| (s.name.as_str(), s.count))
+        .collect();
+    assert_eq!(by_count, vec![("apply_chunk", 3), ("produce_block", 1)]);
+
+    let by_duration: Vec<(&str, f64)> = stats
+        .top_by_total_duration
+        .iter()
+        .map(|s| (s.name.as_str(), s.total_duration))
+        .collect();
+    assert_eq!(
+        by_duration,
+        vec![("produce_block", 2.5), ("apply_chunk", 1.5)]
+    );
+
+    assert_eq!(
+        stats.spans_per_node,
+        vec![("node0".to_string(), 2), ("node1".to_string(), 2)]
+    );
+}
+
+#[test]
+fn test_trace_stats_top_is_limited() {
+    let node = create_test_node("node0");
+    let spans: Vec<_> = (0..TOP_SPAN_NAMES + 5)
+        .map(|i| create_test_span(&format!("span{i}"), node.clone(), 0.0, 1.0, &[i as u8]))
+        .collect();
+    let stats = compute_trace_stats(1, &spans);
+    assert_eq!(stats.top_by_count.len(), TOP_SPAN_NAMES);
+    assert_eq!(stats.top_by_total_duration.len(), TOP_SPAN_NAMES);
+}
+
+#[test]
+fn test_trace_stats_empty() {
+    let stats = compute_trace_stats(0, &[]);
+    assert_eq!(stats.spans, 0);
+    assert_eq!(stats.nodes(), 0);
+    assert_eq!((stats.start_time, stats.end_time), (0.0, 0.0));
+}
@@ -0,0 +1,60 @@
+use traviz::undo::UndoStack;
+
+#[test]
+fn test_undo_and_redo() {
+    let mut undo = UndoStack::new();
+    let mut items = vec!["a"];
+
+    undo.push(items.clone());
+    items.push("b");
+    undo.push(items.clone());
+    items.remove(0);
+    assert_eq!(items, vec!["b"]);
+
+    assert!(undo.undo(&mut items));
+    assert_eq!(items, vec!["a", "b"]);
+    assert!(undo.undo(&mut items));
+    assert_eq!(items, vec!["a"]);
+    assert!(!undo.undo(&mut items));
+    assert_eq!(items, vec!["a"]);
+
+    assert!(undo.redo(&mut items));
+    assert_eq!(items, vec!["a", "b"]);
+    assert!(undo.redo(&mut items));
+    assert_eq!(items, vec!["b"]);
+    assert!(!undo.redo(&mut items));
+}
+
+#[test]
+fn test_change_after_undo_clears_redo() {
+    let mut undo = UndoStack::new();
+    let mut value = 1;
+
+    undo.push(value);
+    value = 2;
+    assert!(undo.undo(&mut value));
+    assert_eq!(value, 1);
+    assert!(undo.can_redo());
+
+    undo.push(value);
+    value = 3;
+    assert!(!undo.can_redo());
+    assert!(undo.undo(&mut value));
+    assert_eq!(value, 1);
+}
+
+#[test]
+fn test_undo_history_is_limited() {
+    let mut undo = UndoStack::new();
+    let mut value = 0;
+    for i in 1..=1000 {
+        undo.push(value);
+        value = i;
+    }
+    let mut undone = 0;
+    while undo.undo(&mut value) {
+        undone += 1;
+    }
+    assert!(undone < 1000);
+    assert_eq!(value, 1000 - undone);
+}
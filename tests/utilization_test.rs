@@ -0,0 +1,76 @@
+mod test_helpers;
+
+use approx::assert_relative_eq;
+use test_helpers::{create_test_node, create_test_span};
+use traviz::structured_modes::SpanSelector;
+use traviz::utilization::{
+    compute_utilization, quantize_bucket_width, LaneCache, LaneRange, UtilizationLaneSettings,
+};
+
+#[test]
+fn test_utilization() {
+    let node = create_test_node("node0");
+    let parent = create_test_span("process", node.clone(), 0.0, 4.0, &[1]);
+    parent.children_mut().extend([
+        create_test_span("apply_chunk", node.clone(), 0.0, 1.0, &[2]),
+        // Overlaps the first one, the time is counted once
+        create_test_span("apply_chunk", node.clone(), 0.5, 1.5, &[3]),
+        create_test_span("apply_chunk", node.clone(), 3.5, 5.0, &[4]),
+        create_test_span("other", node.clone(), 2.0, 3.0, &[5]),
+    ]);
+    let roots = [parent];
+
+    let selector = UtilizationLaneSettings::default().selector;
+    let fractions = compute_utilization(roots.iter(), &selector, 0.0, 4.0, 4);
+    assert_eq!(fractions.len(), 4);
+    assert_relative_eq!(fractions[0], 1.0, epsilon = 1e-9);
+    assert_relative_eq!(fractions[1], 0.5, epsilon = 1e-9);
+    assert_relative_eq!(fractions[2], 0.0, epsilon = 1e-9);
+    // Clipped to the window
+    assert_relative_eq!(fractions[3], 0.5, epsilon = 1e-9);
+
+    let all = compute_utilization(
+        roots.iter(),
+        &SpanSelector::new_name_contains(""),
+        0.0,
+        4.0,
+        2,
+    );
+    assert_relative_eq!(all[0], 1.0, epsilon = 1e-9);
+    assert_relative_eq!(all[1], 1.0, epsilon = 1e-9);
+
+    assert!(compute_utilization(roots.iter(), &selector, 0.0, 4.0, 0).is_empty());
+}
+
+#[test]
+fn test_quantize_bucket_width() {
+    assert_eq!(quantize_bucket_width(1.0), 1.0);
+    assert_eq!(quantize_bucket_width(0.26), 0.25);
+    assert_eq!(quantize_bucket_width(0.24), 0.25);
+    assert_eq!(quantize_bucket_width(3.5), 4.0);
+    assert_eq!(quantize_bucket_width(0.0), 0.0);
+}
+
+#[test]
+fn test_lane_cache() {
+    let mut cache: LaneCache<LaneRange> = LaneCache::default();
+    let mut computed = 0;
+    let mut get = |cache: &mut LaneCache<LaneRange>, contents, start, end, width| {
+        *cache
+            .get_or_compute("node0", contents, start, end, width, |range| {
+                computed += 1;
+                *range
+            })
+            .0
+    };
+
+    let range = get(&mut cache, 1, 10.0, 20.0, 0.5);
+    assert_eq!((range.start, range.end, range.buckets()), (0.0, 30.0, 60));
+    // Scrolling within the range reuses the value.
+    assert_eq!(get(&mut cache, 1, 15.0, 25.0, 0.5), range);
+    // Scrolling out of it, a different bucket width or different spans in the lane don't.
+    assert_eq!(get(&mut cache, 1, 25.0, 35.0, 0.5).start, 15.0);
+    get(&mut cache, 1, 25.0, 35.0, 1.0);
+    get(&mut cache, 2, 25.0, 35.0, 1.0);
+    assert_eq!(computed, 4);
+}
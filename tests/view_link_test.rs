@@ -0,0 +1,58 @@
+use traviz::span_id::SpanId;
+use traviz::view_link::{file_hash, is_view_link, ViewLink};
+
+fn test_link() -> ViewLink {
+    ViewLink {
+        file: "/home/me/my traces/trace&1.json".to_string(),
+        file_hash: Some(file_hash(b"trace contents")),
+        start: 1735689600.123456,
+        end: 1735689601.5,
+        display_mode: Some("Chunks & witnesses".to_string()),
+        node_filter: Some("Validators=1".to_string()),
+        relation_view: None,
+        highlighted_span_ids: vec![SpanId::intern(&[1, 2, 3]), SpanId::intern(&[0xab])],
+    }
+}
+
+#[test]
+fn test_view_link_round_trip() {
+    let link = test_link();
+    let uri = link.to_uri();
+    assert!(is_view_link(&uri));
+    assert!(!uri.contains(' '));
+    assert!(uri.contains("file=/home/me/my%20traces/trace%261.json"));
+    assert!(uri.contains("highlight=010203,ab"));
+    assert_eq!(ViewLink::parse(&uri).unwrap(), link);
+}
+
+#[test]
+fn test_view_link_minimal() {
+    let link = ViewLink::parse("traviz://view?file=trace.json&start=1&end=2&unknown=x").unwrap();
+    assert_eq!(link.file, "trace.json");
+    assert_eq!((link.start, link.end), (1.0, 2.0));
+    assert_eq!(link.file_hash, None);
+    assert_eq!(link.display_mode, None);
+    assert!(link.highlighted_span_ids.is_empty());
+}
+
+#[test]
+fn test_view_link_invalid() {
+    for uri in [
+        "trace.json",
+        "traviz://view?start=1&end=2",
+        "traviz://view?file=trace.json&start=1",
+        "traviz://view?file=trace.json&start=2&end=1",
+        "traviz://view?file=trace.json&start=abc&end=2",
+        "traviz://view?file=trace.json&start=1&end=2&highlight=xyz",
+        "traviz://view?file=trace%2.json&start=1&end=2",
+    ] {
+        assert!(ViewLink::parse(uri).is_err(), "{uri} should be invalid");
+    }
+}
+
+#[test]
+fn test_file_hash() {
+    assert_eq!(file_hash(b"abc"), file_hash(b"abc"));
+    assert_ne!(file_hash(b"abc"), file_hash(b"abd"));
+    assert_eq!(file_hash(b"abc").len(), 16);
+}